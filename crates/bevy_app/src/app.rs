@@ -3,15 +3,48 @@ use bevy_ecs::{
     component::{Component, ComponentDescriptor},
     prelude::{FromWorld, IntoExclusiveSystem, IntoSystem},
     schedule::{
-        RunOnce, Schedule, Stage, StageLabel, State, SystemDescriptor, SystemSet, SystemStage,
+        DynHash, RunOnce, Schedule, Stage, StageLabel, State, SystemDescriptor, SystemSet,
+        SystemStage,
     },
     world::World,
 };
-use bevy_utils::tracing::debug;
 #[cfg(feature = "trace")]
 use bevy_utils::tracing::info_span;
+use bevy_utils::{tracing::debug, HashMap};
 use std::fmt::Debug;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
+
+/// A label identifying a sub-app added with [`App::add_sub_app`].
+///
+/// ```
+/// # use bevy_app::AppLabel;
+/// #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, AppLabel)]
+/// pub struct RenderApp;
+/// ```
+pub trait AppLabel: DynHash + Debug + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn dyn_clone(&self) -> Box<dyn AppLabel>;
+}
+
+impl PartialEq for dyn AppLabel {
+    fn eq(&self, other: &Self) -> bool {
+        self.dyn_eq(other.as_dyn_eq())
+    }
+}
+
+impl Eq for dyn AppLabel {}
+
+impl Hash for dyn AppLabel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dyn_hash(state);
+    }
+}
+
+impl Clone for Box<dyn AppLabel> {
+    fn clone(&self) -> Self {
+        self.dyn_clone()
+    }
+}
 
 #[allow(clippy::needless_doctest_main)]
 /// Containers of app logic and data
@@ -40,7 +73,7 @@ pub struct App {
     pub world: World,
     pub runner: Box<dyn Fn(App)>,
     pub schedule: Schedule,
-    sub_apps: Vec<SubApp>,
+    sub_apps: HashMap<Box<dyn AppLabel>, SubApp>,
 }
 
 struct SubApp {
@@ -77,7 +110,7 @@ impl App {
             world: Default::default(),
             schedule: Default::default(),
             runner: Box::new(run_once),
-            sub_apps: Vec::new(),
+            sub_apps: HashMap::default(),
         }
     }
 
@@ -87,7 +120,7 @@ impl App {
         #[cfg(feature = "trace")]
         let _bevy_frame_update_guard = bevy_frame_update_span.enter();
         self.schedule.run(&mut self.world);
-        for sub_app in self.sub_apps.iter_mut() {
+        for sub_app in self.sub_apps.values_mut() {
             (sub_app.runner)(&mut self.world, &mut sub_app.app);
         }
     }
@@ -568,21 +601,60 @@ impl App {
         self
     }
 
+    /// Adds a sub-app identified by `label`. After each update of this app, `f` is called with
+    /// this app's world and the sub-app, and is responsible for running the sub-app.
     pub fn add_sub_app(
         &mut self,
+        label: impl AppLabel,
         app: App,
         f: impl Fn(&mut World, &mut App) + 'static,
     ) -> &mut Self {
-        self.sub_apps.push(SubApp {
-            app,
-            runner: Box::new(f),
-        });
+        self.sub_apps.insert(
+            Box::new(label),
+            SubApp {
+                app,
+                runner: Box::new(f),
+            },
+        );
         self
     }
 
-    // TODO: use labels instead of indices
-    pub fn sub_app_mut(&mut self, index: usize) -> &mut App {
-        &mut self.sub_apps[index].app
+    /// Returns the sub-app identified by `label`.
+    ///
+    /// # Panics
+    /// Panics if no sub-app was added with `label`.
+    pub fn sub_app(&self, label: impl AppLabel) -> &App {
+        match self.get_sub_app(label) {
+            Ok(app) => app,
+            Err(label) => panic!("Sub-App with label '{:?}' does not exist", label),
+        }
+    }
+
+    /// Returns the sub-app identified by `label`.
+    ///
+    /// # Panics
+    /// Panics if no sub-app was added with `label`.
+    pub fn sub_app_mut(&mut self, label: impl AppLabel) -> &mut App {
+        match self.get_sub_app_mut(label) {
+            Ok(app) => app,
+            Err(label) => panic!("Sub-App with label '{:?}' does not exist", label),
+        }
+    }
+
+    /// Returns the sub-app identified by `label`, or gives `label` back if there is none.
+    pub fn get_sub_app(&self, label: impl AppLabel) -> Result<&App, impl AppLabel> {
+        self.sub_apps
+            .get((&label) as &dyn AppLabel)
+            .map(|sub_app| &sub_app.app)
+            .ok_or(label)
+    }
+
+    /// Returns the sub-app identified by `label`, or gives `label` back if there is none.
+    pub fn get_sub_app_mut(&mut self, label: impl AppLabel) -> Result<&mut App, impl AppLabel> {
+        self.sub_apps
+            .get_mut((&label) as &dyn AppLabel)
+            .map(|sub_app| &mut sub_app.app)
+            .ok_or(label)
     }
 }
 
@@ -7,7 +7,7 @@ mod schedule_runner;
 mod ci_testing;
 
 pub use app::*;
-pub use bevy_derive::DynamicPlugin;
+pub use bevy_derive::{AppLabel, DynamicPlugin};
 pub use bevy_ecs::event::*;
 pub use plugin::*;
 pub use plugin_group::*;
@@ -115,6 +115,12 @@ impl AssetServer {
         loaders.push(Arc::new(Box::new(loader)));
     }
 
+    /// Returns the [`AssetIo`] assets are read from, e.g. to read parts of an asset file again
+    /// after it was loaded.
+    pub fn asset_io(&self) -> &dyn AssetIo {
+        &*self.server.asset_io
+    }
+
     pub fn watch_for_changes(&self) -> Result<(), AssetServerError> {
         self.server.asset_io.watch_for_changes()?;
         Ok(())
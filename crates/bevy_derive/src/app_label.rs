@@ -0,0 +1,30 @@
+use bevy_macro_utils::BevyManifest;
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput};
+
+pub fn derive_app_label(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let struct_name = &ast.ident;
+    let bevy_app_path = BevyManifest::default().get_path(crate::modules::BEVY_APP);
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| syn::WhereClause {
+        where_token: Default::default(),
+        predicates: Default::default(),
+    });
+    where_clause.predicates.push(
+        syn::parse2(quote! {
+            Self: Eq + ::std::fmt::Debug + ::std::hash::Hash + Clone + Send + Sync + 'static
+        })
+        .unwrap(),
+    );
+
+    TokenStream::from(quote! {
+        impl #impl_generics #bevy_app_path::AppLabel for #struct_name #ty_generics #where_clause {
+            fn dyn_clone(&self) -> Box<dyn #bevy_app_path::AppLabel> {
+                Box::new(Clone::clone(self))
+            }
+        }
+    })
+}
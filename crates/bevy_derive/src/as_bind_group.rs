@@ -0,0 +1,285 @@
+use bevy_macro_utils::BevyManifest;
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+use syn::{
+    parse_macro_input, Data, DataStruct, DeriveInput, Field, Fields, Lit, Meta, NestedMeta, Path,
+    Type,
+};
+
+static UNIFORM_ATTRIBUTE_NAME: &str = "uniform";
+static TEXTURE_ATTRIBUTE_NAME: &str = "texture";
+static SAMPLER_ATTRIBUTE_NAME: &str = "sampler";
+
+enum Binding<'a> {
+    /// The uniform fields packed in one buffer, in declaration order.
+    Uniform(Vec<&'a Field>),
+    Texture {
+        field: &'a Field,
+        dimension: &'static str,
+    },
+    Sampler {
+        field: &'a Field,
+    },
+}
+
+/// The arguments of a `#[uniform(..)]`, `#[texture(..)]` or `#[sampler(..)]` attribute.
+struct BindingAttribute {
+    index: u32,
+    dimension: Option<String>,
+    placeholder: Option<String>,
+}
+
+fn parse_binding_attribute(meta: Meta) -> BindingAttribute {
+    let name = meta.path().get_ident().unwrap().to_string();
+    let list = match meta {
+        Meta::List(list) => list,
+        _ => panic!("Expected the binding index in `#[{}(..)]`.", name),
+    };
+    let mut index = None;
+    let mut dimension = None;
+    let mut placeholder = None;
+    for nested in list.nested.iter() {
+        match nested {
+            NestedMeta::Lit(Lit::Int(lit)) => index = Some(lit.base10_parse::<u32>().unwrap()),
+            NestedMeta::Meta(Meta::NameValue(name_value)) => {
+                let value = match &name_value.lit {
+                    Lit::Str(value) => value.value(),
+                    _ => panic!("Expected a string value in `#[{}(..)]`.", name),
+                };
+                if name_value.path.is_ident("dimension") && name == TEXTURE_ATTRIBUTE_NAME {
+                    dimension = Some(value);
+                } else if name_value.path.is_ident("placeholder") && name != UNIFORM_ATTRIBUTE_NAME
+                {
+                    placeholder = Some(value);
+                } else {
+                    panic!("Unexpected argument in `#[{}(..)]`.", name);
+                }
+            }
+            _ => panic!("Unexpected argument in `#[{}(..)]`.", name),
+        }
+    }
+    BindingAttribute {
+        index: index.unwrap_or_else(|| panic!("Expected the binding index in `#[{}(..)]`.", name)),
+        dimension,
+        placeholder,
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+pub fn derive_as_bind_group(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let bevy_render_path: Path = BevyManifest::default().get_path(crate::modules::BEVY_RENDER2);
+
+    let fields = match &ast.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("Expected a struct with named fields."),
+    };
+
+    let mut bindings = BTreeMap::<u32, Binding>::new();
+    // the placeholder of each optional texture field, used by its texture and sampler bindings
+    let mut texture_fields = Vec::<(&Field, Option<String>)>::new();
+    for field in fields.iter() {
+        for attr in field.attrs.iter() {
+            let name = match attr.path.get_ident() {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if name != UNIFORM_ATTRIBUTE_NAME
+                && name != TEXTURE_ATTRIBUTE_NAME
+                && name != SAMPLER_ATTRIBUTE_NAME
+            {
+                continue;
+            }
+            let attribute = parse_binding_attribute(attr.parse_meta().unwrap());
+            if name == UNIFORM_ATTRIBUTE_NAME {
+                match bindings
+                    .entry(attribute.index)
+                    .or_insert_with(|| Binding::Uniform(Vec::new()))
+                {
+                    Binding::Uniform(fields) => fields.push(field),
+                    _ => panic!("Binding {} is used more than once.", attribute.index),
+                }
+                continue;
+            }
+
+            let binding = if name == TEXTURE_ATTRIBUTE_NAME {
+                let dimension = match attribute.dimension.as_deref().unwrap_or("2d") {
+                    "2d" => "D2",
+                    "2d_array" => "D2Array",
+                    "cube" => "Cube",
+                    "3d" => "D3",
+                    dimension => panic!("Unknown texture dimension \"{}\".", dimension),
+                };
+                Binding::Texture { field, dimension }
+            } else {
+                Binding::Sampler { field }
+            };
+            if bindings.insert(attribute.index, binding).is_some() {
+                panic!("Binding {} is used more than once.", attribute.index);
+            }
+            match texture_fields
+                .iter_mut()
+                .find(|(texture_field, _)| std::ptr::eq(*texture_field, field))
+            {
+                Some((_, placeholder)) => {
+                    if attribute.placeholder.is_some() {
+                        *placeholder = attribute.placeholder;
+                    }
+                }
+                None => texture_fields.push((field, attribute.placeholder)),
+            }
+        }
+    }
+
+    let gpu_data_ident =
+        |field: &Field| format_ident!("{}_gpu_data", field.ident.as_ref().unwrap());
+    let retry = quote!(#bevy_render_path::render_resource::AsBindGroupError::RetryNextFrame);
+
+    // textures are looked up first, so no buffer is created if one isn't ready
+    let mut texture_lookups = Vec::new();
+    for (field, placeholder) in texture_fields.iter() {
+        let field_ident = field.ident.as_ref().unwrap();
+        let gpu_data = gpu_data_ident(field);
+        if is_option(&field.ty) {
+            let placeholder = match placeholder.as_deref().unwrap_or("white") {
+                "white" => "WHITE_TEXTURE_HANDLE",
+                "black" => "BLACK_TEXTURE_HANDLE",
+                "normal" => "NORMAL_TEXTURE_HANDLE",
+                "transparent" => "TRANSPARENT_TEXTURE_HANDLE",
+                placeholder => panic!("Unknown placeholder texture \"{}\".", placeholder),
+            };
+            let placeholder = format_ident!("{}", placeholder);
+            texture_lookups.push(quote! {
+                let #gpu_data = #bevy_render_path::texture::texture_gpu_data_or_placeholder(
+                    textures,
+                    self.#field_ident.as_ref(),
+                    &#bevy_render_path::texture::#placeholder,
+                )
+                .ok_or(#retry)?;
+            });
+        } else {
+            if placeholder.is_some() {
+                panic!(
+                    "Only `Option` fields have a placeholder texture, `{}` is always set.",
+                    field_ident
+                );
+            }
+            texture_lookups.push(quote! {
+                let #gpu_data = textures
+                    .get(&self.#field_ident)
+                    .and_then(|texture| texture.gpu_data.as_ref())
+                    .ok_or(#retry)?;
+            });
+        }
+    }
+
+    let struct_name = &ast.ident;
+    let mut binding_descriptors = Vec::new();
+    let mut bind_group_entries = Vec::new();
+    for (index, binding) in bindings.iter() {
+        match binding {
+            Binding::Uniform(fields) => {
+                let name = format!("{}_{}", struct_name, index);
+                let types = fields.iter().map(|field| &field.ty);
+                let field_idents = fields.iter().map(|field| field.ident.as_ref().unwrap());
+                binding_descriptors.push(quote! {{
+                    let mut sizer = #bevy_render_path::crevice::std140::Sizer::new();
+                    #(sizer.add::<#types>();)*
+                    #bevy_render_path::render_resource::uniform_binding_descriptor(
+                        #name,
+                        #index,
+                        sizer.len(),
+                    )
+                }});
+                bind_group_entries.push(quote! {{
+                    let mut data = Vec::new();
+                    {
+                        let mut writer = #bevy_render_path::crevice::std140::Writer::new(&mut data);
+                        #(writer.write(&self.#field_idents).unwrap();)*
+                    }
+                    let (buffer, size) = #bevy_render_path::render_resource::create_uniform_buffer(
+                        render_resources,
+                        data,
+                    );
+                    buffers.push(buffer);
+                    bind_group = bind_group.add_buffer(#index, buffer, 0..size);
+                }});
+            }
+            Binding::Texture { field, dimension } => {
+                let name = field.ident.as_ref().unwrap().to_string();
+                let dimension = syn::Ident::new(dimension, Span::call_site());
+                binding_descriptors.push(quote! {
+                    #bevy_render_path::render_resource::texture_binding_descriptor(
+                        #name,
+                        #index,
+                        #bevy_render_path::texture::TextureViewDimension::#dimension,
+                    )
+                });
+                let gpu_data = gpu_data_ident(field);
+                bind_group_entries.push(quote! {
+                    bind_group = bind_group.add_texture_view(#index, #gpu_data.texture_view);
+                });
+            }
+            Binding::Sampler { field } => {
+                let name = format!("{}_sampler", field.ident.as_ref().unwrap());
+                binding_descriptors.push(quote! {
+                    #bevy_render_path::render_resource::sampler_binding_descriptor(#name, #index)
+                });
+                let gpu_data = gpu_data_ident(field);
+                bind_group_entries.push(quote! {
+                    bind_group = bind_group.add_sampler(#index, #gpu_data.sampler);
+                });
+            }
+        }
+    }
+
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+    let as_bind_group: TokenStream2 = quote! {
+        impl #impl_generics #bevy_render_path::render_resource::AsBindGroup for #struct_name #type_generics #where_clause {
+            fn bind_group_descriptor(index: u32) -> #bevy_render_path::pipeline::BindGroupDescriptor {
+                #bevy_render_path::pipeline::BindGroupDescriptor::new(
+                    index,
+                    vec![#(#binding_descriptors,)*],
+                )
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn as_bind_group(
+                &self,
+                descriptor: &#bevy_render_path::pipeline::BindGroupDescriptor,
+                render_resources: &#bevy_render_path::renderer::RenderResources,
+                textures: &#bevy_render_path::render_resource::AsBindGroupTextures,
+            ) -> Result<
+                #bevy_render_path::render_resource::PreparedBindGroup,
+                #bevy_render_path::render_resource::AsBindGroupError,
+            > {
+                #(#texture_lookups)*
+                let mut buffers = Vec::new();
+                let mut bind_group = #bevy_render_path::render_resource::BindGroupBuilder::default();
+                #(#bind_group_entries)*
+                let bind_group = bind_group.finish();
+                render_resources.create_bind_group(descriptor.id, &bind_group);
+                Ok(#bevy_render_path::render_resource::PreparedBindGroup {
+                    bind_group: bind_group.id,
+                    buffers,
+                })
+            }
+        }
+    };
+    TokenStream::from(as_bind_group)
+}
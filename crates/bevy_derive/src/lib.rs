@@ -1,6 +1,8 @@
 extern crate proc_macro;
 
+mod app_label;
 mod app_plugin;
+mod as_bind_group;
 mod bevy_main;
 mod bytes;
 mod enum_variant_meta;
@@ -45,12 +47,25 @@ pub fn derive_shader_defs(input: TokenStream) -> TokenStream {
     shader_defs::derive_shader_defs(input)
 }
 
+/// Derives the `AsBindGroup` trait of `bevy_render2`. Fields are bound with `#[uniform(index)]`,
+/// `#[texture(index)]` and `#[sampler(index)]`, see the trait for the arguments of each.
+#[proc_macro_derive(AsBindGroup, attributes(uniform, texture, sampler))]
+pub fn derive_as_bind_group(input: TokenStream) -> TokenStream {
+    as_bind_group::derive_as_bind_group(input)
+}
+
 /// Generates a dynamic plugin entry point function for the given `Plugin` type.  
 #[proc_macro_derive(DynamicPlugin)]
 pub fn derive_dynamic_plugin(input: TokenStream) -> TokenStream {
     app_plugin::derive_dynamic_plugin(input)
 }
 
+/// Derives the AppLabel trait, used to identify sub-apps.
+#[proc_macro_derive(AppLabel)]
+pub fn derive_app_label(input: TokenStream) -> TokenStream {
+    app_label::derive_app_label(input)
+}
+
 #[proc_macro_attribute]
 pub fn bevy_main(attr: TokenStream, item: TokenStream) -> TokenStream {
     bevy_main::bevy_main(attr, item)
@@ -2,4 +2,5 @@ pub const BEVY_APP: &str = "bevy_app";
 pub const BEVY_ASSET: &str = "bevy_asset";
 pub const BEVY_CORE: &str = "bevy_core";
 pub const BEVY_RENDER: &str = "bevy_render";
+pub const BEVY_RENDER2: &str = "bevy_render2";
 pub const BEVY_UTILS: &str = "bevy_utils";
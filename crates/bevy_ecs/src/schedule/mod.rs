@@ -215,6 +215,26 @@ impl Schedule {
             .iter()
             .map(move |label| (&**label, &*self.stages[label]))
     }
+
+    /// Iterates over all of schedule's stages and their labels, in execution order, allowing
+    /// them to be run individually.
+    pub fn iter_stages_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&(dyn StageLabel + 'static), &mut dyn Stage)> {
+        let Schedule {
+            stages,
+            stage_order,
+            ..
+        } = self;
+        let mut stages = stages
+            .iter_mut()
+            .map(|(label, stage)| (&**label, stage))
+            .collect::<HashMap<&(dyn StageLabel + 'static), &mut Box<dyn Stage>>>();
+        stage_order.iter().map(move |label| {
+            let stage: &mut dyn Stage = &mut **stages.remove(&**label).unwrap();
+            (&**label, stage)
+        })
+    }
 }
 
 impl Stage for Schedule {
@@ -147,6 +147,13 @@ async fn load_gltf<'a, 'b>(
                 mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
             }
 
+            if let Some(vertex_attribute) = reader
+                .read_tex_coords(1)
+                .map(|v| VertexAttributeValues::Float32x2(v.into_f32().collect()))
+            {
+                mesh.set_attribute(Mesh::ATTRIBUTE_UV_1, vertex_attribute);
+            }
+
             if let Some(vertex_attribute) = reader
                 .read_colors(0)
                 .map(|v| VertexAttributeValues::Float32x4(v.into_rgba_f32().collect()))
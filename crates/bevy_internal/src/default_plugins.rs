@@ -130,8 +130,15 @@ impl PluginGroup for PipelinedDefaultPlugins {
         #[cfg(feature = "bevy_wgpu2")]
         group.add(bevy_wgpu2::WgpuPlugin::default());
 
+        // needs the render backend to create its pipeline
+        #[cfg(feature = "bevy_render2")]
+        group.add(bevy_render2::gizmos::GizmoPlugin::default());
+
         #[cfg(feature = "bevy_sprite2")]
-        group.add(bevy_sprite2::SpritePlugin::default());
+        {
+            group.add(bevy_sprite2::SpritePlugin::default());
+            group.add(bevy_sprite2::TileMapPlugin::default());
+        }
 
         #[cfg(feature = "bevy_pbr2")]
         group.add(bevy_pbr2::PbrPlugin::default());
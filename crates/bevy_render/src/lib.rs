@@ -54,7 +54,9 @@ use render_graph::{
     base::{self, BaseRenderGraphConfig, MainPass},
     RenderGraph,
 };
-use renderer::{AssetRenderResourceBindings, RenderResourceBindings, RenderResourceContext};
+use renderer::{
+    AssetRenderResourceBindings, RenderDeviceInfo, RenderResourceBindings, RenderResourceContext,
+};
 use shader::ShaderLoader;
 #[cfg(feature = "hdr")]
 use texture::HdrTextureLoader;
@@ -171,6 +173,7 @@ impl Plugin for RenderPlugin {
         .init_resource::<RenderGraph>()
         .init_resource::<PipelineCompiler>()
         .init_resource::<Msaa>()
+        .init_resource::<RenderDeviceInfo>()
         .init_resource::<RenderResourceBindings>()
         .init_resource::<AssetRenderResourceBindings>()
         .init_resource::<ActiveCameras>()
@@ -227,6 +230,7 @@ impl Plugin for RenderPlugin {
             shader::clear_shader_defs_system.system(),
         );
 
+        base::validate_msaa(&mut app.world);
         if let Some(ref config) = self.base_render_graph_config {
             crate::base::add_base_graph(config, &mut app.world);
             let mut active_cameras = app.world.get_resource_mut::<ActiveCameras>().unwrap();
@@ -261,6 +261,9 @@ impl Mesh {
     pub const ATTRIBUTE_POSITION: &'static str = "Vertex_Position";
     /// Texture coordinates for the vertex. Use in conjunction with [`Mesh::set_attribute`]
     pub const ATTRIBUTE_UV_0: &'static str = "Vertex_Uv";
+    /// A second set of texture coordinates, typically used for lightmaps. Use in conjunction
+    /// with [`Mesh::set_attribute`]
+    pub const ATTRIBUTE_UV_1: &'static str = "Vertex_Uv_1";
 
     /// Per vertex joint transform matrix weight. Use in conjunction with [`Mesh::set_attribute`]
     pub const ATTRIBUTE_JOINT_WEIGHT: &'static str = "Vertex_JointWeight";
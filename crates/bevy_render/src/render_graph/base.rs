@@ -7,11 +7,13 @@ use crate::{
         LoadOp, Operations, PassDescriptor, RenderPassColorAttachment,
         RenderPassDepthStencilAttachment, TextureAttachment,
     },
+    renderer::RenderDeviceInfo,
     texture::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage},
     Color,
 };
 use bevy_ecs::{reflect::ReflectComponent, world::World};
 use bevy_reflect::Reflect;
+use bevy_utils::tracing::warn;
 use bevy_window::WindowId;
 
 /// A component that indicates that an entity should be drawn in the "main pass"
@@ -19,6 +21,10 @@ use bevy_window::WindowId;
 #[reflect(Component)]
 pub struct MainPass;
 
+/// The number of samples of the multisampled attachments of the render graph. Counts the device
+/// doesn't support for the formats of the base render graph are lowered to a supported count when
+/// the [`RenderPlugin`](crate::RenderPlugin) is built and when the render backend creates the
+/// device, so insert it before adding the plugins.
 #[derive(Debug)]
 pub struct Msaa {
     pub samples: u32,
@@ -51,6 +57,28 @@ impl Msaa {
             }
         }
     }
+
+    /// The formats of the attachments the base render graph multisamples.
+    pub fn formats() -> [TextureFormat; 2] {
+        [TextureFormat::default(), TextureFormat::Depth32Float]
+    }
+}
+
+/// Lowers the sample count of the [`Msaa`] resource to the highest count the
+/// [`RenderDeviceInfo`] supports for the [`Msaa::formats`], with a warning.
+pub fn validate_msaa(world: &mut World) {
+    let samples = world.get_resource_or_insert_with(Msaa::default).samples;
+    let supported_samples = world
+        .get_resource_or_insert_with(RenderDeviceInfo::default)
+        .supported_sample_count(&Msaa::formats(), samples);
+    let mut msaa = world.get_resource_mut::<Msaa>().unwrap();
+    if supported_samples != msaa.samples {
+        warn!(
+            "Msaa with {} samples is not supported by the render device, using {} samples instead.",
+            msaa.samples, supported_samples
+        );
+        msaa.samples = supported_samples;
+    }
 }
 
 #[derive(Debug)]
@@ -1,9 +1,11 @@
 mod headless_render_resource_context;
 mod render_context;
+mod render_device_info;
 mod render_resource;
 mod render_resource_context;
 
 pub use headless_render_resource_context::*;
 pub use render_context::*;
+pub use render_device_info::*;
 pub use render_resource::*;
 pub use render_resource_context::*;
@@ -0,0 +1,92 @@
+use crate::texture::TextureFormat;
+use bevy_utils::HashMap;
+
+/// The formats that can be multisampled on every device that supports them as render attachments.
+const MULTISAMPLE_FORMATS: [TextureFormat; 13] = [
+    TextureFormat::R8Unorm,
+    TextureFormat::R16Float,
+    TextureFormat::Rg8Unorm,
+    TextureFormat::Rg16Float,
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Bgra8Unorm,
+    TextureFormat::Bgra8UnormSrgb,
+    TextureFormat::Rgb10a2Unorm,
+    TextureFormat::Rgba16Float,
+    TextureFormat::Depth32Float,
+    TextureFormat::Depth24Plus,
+    TextureFormat::Depth24PlusStencil8,
+];
+
+/// The sample counts every multisampled format supports.
+const MULTISAMPLE_COUNTS: [u32; 2] = [1, 4];
+
+/// The capabilities of the render device. Render backends insert it when they create the device,
+/// until then it holds the capabilities every device is guaranteed to have.
+#[derive(Debug, Clone, Default)]
+pub struct RenderDeviceInfo {
+    sample_counts: HashMap<TextureFormat, Vec<u32>>,
+}
+
+impl RenderDeviceInfo {
+    /// Creates the info of a device that can render to the formats accepted by
+    /// `is_render_attachment`. The graphics APIs don't report other sample counts than the ones
+    /// every device supports yet, so multisampled render attachments support 1 and 4 samples, and
+    /// all other formats 1 sample.
+    pub fn new(mut is_render_attachment: impl FnMut(TextureFormat) -> bool) -> Self {
+        let mut info = RenderDeviceInfo::default();
+        for format in MULTISAMPLE_FORMATS.iter() {
+            let sample_counts = if is_render_attachment(*format) {
+                MULTISAMPLE_COUNTS.to_vec()
+            } else {
+                vec![1]
+            };
+            info.sample_counts.insert(*format, sample_counts);
+        }
+        info
+    }
+
+    /// The sample counts textures of `format` can be created with, in ascending order.
+    pub fn sample_counts(&self, format: TextureFormat) -> &[u32] {
+        match self.sample_counts.get(&format) {
+            Some(sample_counts) => sample_counts,
+            None if MULTISAMPLE_FORMATS.contains(&format) => &MULTISAMPLE_COUNTS,
+            None => &[1],
+        }
+    }
+
+    /// Returns the highest sample count not above `samples` that all of `formats` support.
+    pub fn supported_sample_count(&self, formats: &[TextureFormat], samples: u32) -> u32 {
+        formats.iter().fold(samples, |samples, format| {
+            self.sample_counts(*format)
+                .iter()
+                .copied()
+                .filter(|count| *count <= samples)
+                .max()
+                .unwrap_or(1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderDeviceInfo;
+    use crate::texture::TextureFormat;
+
+    #[test]
+    fn sample_count_is_clamped_to_every_format() {
+        let info = RenderDeviceInfo::new(|format| format != TextureFormat::Rgba16Float);
+        let formats = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Depth32Float];
+        assert_eq!(info.supported_sample_count(&formats, 8), 4);
+        assert_eq!(info.supported_sample_count(&formats, 4), 4);
+        assert_eq!(info.supported_sample_count(&formats, 2), 1);
+        assert_eq!(
+            info.supported_sample_count(&[TextureFormat::Rgba16Float], 4),
+            1
+        );
+        assert_eq!(
+            info.supported_sample_count(&[TextureFormat::Rgba8Uint], 4),
+            1
+        );
+    }
+}
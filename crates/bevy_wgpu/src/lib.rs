@@ -15,6 +15,7 @@ use bevy_ecs::{
     world::World,
 };
 use bevy_render::{
+    render_graph::base::validate_msaa,
     renderer::{shared_buffers_update_system, RenderResourceContext, SharedBuffers},
     RenderStage,
 };
@@ -124,6 +125,8 @@ pub fn get_wgpu_render_system(world: &mut World) -> impl FnMut(&mut World) {
     let resource_context = WgpuRenderResourceContext::new(wgpu_renderer.device.clone());
     world.insert_resource::<Box<dyn RenderResourceContext>>(Box::new(resource_context));
     world.insert_resource(SharedBuffers::new(4096));
+    world.insert_resource(wgpu_renderer.device_info.clone());
+    validate_msaa(world);
     move |world| {
         wgpu_renderer.update(world);
     }
@@ -7,7 +7,7 @@ use bevy_app::{Events, ManualEventReader};
 use bevy_ecs::world::{Mut, World};
 use bevy_render::{
     render_graph::{DependentNodeStager, RenderGraph, RenderGraphStager},
-    renderer::RenderResourceContext,
+    renderer::{RenderDeviceInfo, RenderResourceContext},
 };
 use bevy_window::{WindowCreated, WindowResized, Windows};
 use std::{ops::Deref, sync::Arc};
@@ -16,6 +16,7 @@ pub struct WgpuRenderer {
     pub instance: wgpu::Instance,
     pub device: Arc<wgpu::Device>,
     pub queue: wgpu::Queue,
+    pub device_info: RenderDeviceInfo,
     pub window_resized_event_reader: ManualEventReader<WindowResized>,
     pub window_created_event_reader: ManualEventReader<WindowCreated>,
     pub initialized: bool,
@@ -46,6 +47,13 @@ impl WgpuRenderer {
             .await
             .expect("Unable to find a GPU! Make sure you have installed required drivers!");
 
+        let device_info = RenderDeviceInfo::new(|format| {
+            adapter
+                .get_texture_format_features(format.wgpu_into())
+                .allowed_usages
+                .contains(wgpu::TextureUsage::RENDER_ATTACHMENT)
+        });
+
         #[cfg(feature = "trace")]
         let trace_path = {
             let path = std::path::Path::new("wgpu_trace");
@@ -72,6 +80,7 @@ impl WgpuRenderer {
             instance,
             device,
             queue,
+            device_info,
             window_resized_event_reader: Default::default(),
             window_created_event_reader: Default::default(),
             initialized: false,
@@ -96,3 +96,19 @@ pub struct WindowMoved {
     pub id: WindowId,
     pub position: IVec2,
 }
+
+/// An event that is sent when the native surface of a window is destroyed while the window still
+/// exists, e.g. when an Android app is suspended. Renderers must drop the swap chain and surface
+/// of the window, which can't be rendered to until a [`WindowSurfaceCreated`] event.
+#[derive(Debug, Clone)]
+pub struct WindowSurfaceDestroyed {
+    pub id: WindowId,
+}
+
+/// An event that is sent when a window gets a new native surface after a
+/// [`WindowSurfaceDestroyed`] event, e.g. when an Android app is resumed. The new
+/// [`Window::raw_window_handle`](crate::Window::raw_window_handle) is set before it is sent.
+#[derive(Debug, Clone)]
+pub struct WindowSurfaceCreated {
+    pub id: WindowId,
+}
@@ -51,6 +51,8 @@ impl Plugin for WindowPlugin {
             .add_event::<WindowBackendScaleFactorChanged>()
             .add_event::<FileDragAndDrop>()
             .add_event::<WindowMoved>()
+            .add_event::<WindowSurfaceDestroyed>()
+            .add_event::<WindowSurfaceCreated>()
             .init_resource::<Windows>();
 
         if self.add_primary_window {
@@ -123,10 +123,13 @@ pub struct Window {
     vsync: bool,
     resizable: bool,
     decorations: bool,
+    transparent: bool,
+    resolution_scale: f32,
     cursor_visible: bool,
     cursor_locked: bool,
     cursor_position: Option<Vec2>,
     raw_window_handle: RawWindowHandleWrapper,
+    has_surface: bool,
     focused: bool,
     mode: WindowMode,
     #[cfg(target_arch = "wasm32")]
@@ -194,6 +197,22 @@ pub enum WindowMode {
     Fullscreen { use_size: bool },
 }
 
+/// Whether the surfaces of windows are stretched to the window when their swap chain is smaller.
+const RESOLUTION_SCALE_SUPPORTED: bool = cfg!(any(
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "android"
+));
+
+/// Scales a size in physical pixels, keeping sizes above zero at least one pixel.
+fn scale_resolution(size: u32, scale: f32) -> u32 {
+    if size == 0 {
+        0
+    } else {
+        ((size as f32 * scale).round() as u32).max(1)
+    }
+}
+
 impl Window {
     pub fn new(
         id: WindowId,
@@ -218,10 +237,17 @@ impl Window {
             vsync: window_descriptor.vsync,
             resizable: window_descriptor.resizable,
             decorations: window_descriptor.decorations,
+            transparent: window_descriptor.transparent,
+            resolution_scale: if RESOLUTION_SCALE_SUPPORTED {
+                window_descriptor.resolution_scale.clamp(0.1, 1.0)
+            } else {
+                1.0
+            },
             cursor_visible: window_descriptor.cursor_visible,
             cursor_locked: window_descriptor.cursor_locked,
             cursor_position: None,
             raw_window_handle: RawWindowHandleWrapper::new(raw_window_handle),
+            has_surface: true,
             focused: true,
             mode: window_descriptor.mode,
             #[cfg(target_arch = "wasm32")]
@@ -279,6 +305,27 @@ impl Window {
         self.physical_height
     }
 
+    /// The width in pixels the window is rendered at, its physical width scaled by the
+    /// [`Window::resolution_scale`].
+    #[inline]
+    pub fn render_width(&self) -> u32 {
+        scale_resolution(self.physical_width, self.resolution_scale)
+    }
+
+    /// The height in pixels the window is rendered at, its physical height scaled by the
+    /// [`Window::resolution_scale`].
+    #[inline]
+    pub fn render_height(&self) -> u32 {
+        scale_resolution(self.physical_height, self.resolution_scale)
+    }
+
+    /// The scale of the resolution the window is rendered at, relative to its physical size. See
+    /// [`WindowDescriptor::resolution_scale`].
+    #[inline]
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
     /// The window's client resize constraint in logical pixels.
     #[inline]
     pub fn resize_constraints(&self) -> WindowResizeConstraints {
@@ -449,6 +496,13 @@ impl Window {
             .push(WindowCommand::SetDecorations { decorations });
     }
 
+    /// Whether the window was created with a transparent background. See
+    /// [`WindowDescriptor::transparent`].
+    #[inline]
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
     #[inline]
     pub fn cursor_locked(&self) -> bool {
         self.cursor_locked
@@ -521,6 +575,22 @@ impl Window {
     pub fn raw_window_handle(&self) -> RawWindowHandleWrapper {
         self.raw_window_handle.clone()
     }
+
+    /// Whether the window has a native surface to render to. Surfaces can be destroyed while the
+    /// window exists, see [`WindowSurfaceDestroyed`](crate::WindowSurfaceDestroyed).
+    #[inline]
+    pub fn has_surface(&self) -> bool {
+        self.has_surface
+    }
+
+    /// Sets the handle of the new native surface of the window, or `None` when its surface was
+    /// destroyed.
+    pub fn update_surface_from_backend(&mut self, raw_window_handle: Option<RawWindowHandle>) {
+        self.has_surface = raw_window_handle.is_some();
+        if let Some(raw_window_handle) = raw_window_handle {
+            self.raw_window_handle = RawWindowHandleWrapper::new(raw_window_handle);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -533,6 +603,22 @@ pub struct WindowDescriptor {
     pub vsync: bool,
     pub resizable: bool,
     pub decorations: bool,
+    /// Lets the desktop show through where the window is rendered with transparent pixels, for
+    /// overlays and widgets. The renderer then clears the window to transparent black rather than
+    /// an opaque color, and colors should be premultiplied by their alpha. This needs support from
+    /// the platform's compositor and the graphics backend, and can't be changed after the window
+    /// is created.
+    pub transparent: bool,
+    /// The scale of the resolution the window is rendered at, relative to its physical size,
+    /// between 0.1 and 1. The swap chain is created at the scaled size, which the platform's
+    /// compositor stretches to the window. Rendering below the native resolution of high DPI
+    /// screens saves power and GPU time at the cost of sharpness: on a phone with a scale factor
+    /// of 3, a scale of `1.0 / 3.0` renders one pixel per logical pixel.
+    ///
+    /// Only platforms whose surfaces scale to the window support this, which are iOS and macOS
+    /// through their Metal layer, and Android. Windows elsewhere are always rendered at their
+    /// physical size. The scale can't be changed after the window is created.
+    pub resolution_scale: f32,
     pub cursor_visible: bool,
     pub cursor_locked: bool,
     pub mode: WindowMode,
@@ -551,6 +637,8 @@ impl Default for WindowDescriptor {
             vsync: true,
             resizable: true,
             decorations: true,
+            transparent: false,
+            resolution_scale: 1.0,
             cursor_locked: false,
             cursor_visible: true,
             mode: WindowMode::Windowed,
@@ -17,7 +17,8 @@ use bevy_utils::tracing::{error, trace, warn};
 use bevy_window::{
     CreateWindow, CursorEntered, CursorLeft, CursorMoved, FileDragAndDrop, ReceivedCharacter,
     WindowBackendScaleFactorChanged, WindowCloseRequested, WindowCreated, WindowFocused,
-    WindowMoved, WindowResized, WindowScaleFactorChanged, Windows,
+    WindowMoved, WindowResized, WindowScaleFactorChanged, WindowSurfaceCreated,
+    WindowSurfaceDestroyed, Windows,
 };
 use winit::{
     dpi::PhysicalPosition,
@@ -25,6 +26,7 @@ use winit::{
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
 };
 
+use raw_window_handle::HasRawWindowHandle;
 use winit::dpi::LogicalSize;
 #[cfg(any(
     target_os = "linux",
@@ -224,6 +226,8 @@ pub fn winit_runner_any_thread(app: App) {
 pub fn winit_runner_with(mut app: App, mut event_loop: EventLoop<()>) {
     let mut create_window_event_reader = ManualEventReader::<CreateWindow>::default();
     let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
+    // whether the surfaces of the windows were destroyed by a `Suspended` event
+    let mut surfaces_destroyed = false;
     app.world.insert_non_send(event_loop.create_proxy());
 
     trace!("Entering winit event loop");
@@ -475,6 +479,35 @@ pub fn winit_runner_with(mut app: App, mut event_loop: EventLoop<()>) {
                     delta: Vec2::new(delta.0 as f32, delta.1 as f32),
                 });
             }
+            event::Event::Suspended => {
+                // the native windows are destroyed on Android, so their surfaces can't be used
+                // until the app is resumed
+                let world = app.world.cell();
+                let mut windows = world.get_resource_mut::<Windows>().unwrap();
+                let mut surface_destroyed_events = world
+                    .get_resource_mut::<Events<WindowSurfaceDestroyed>>()
+                    .unwrap();
+                for window in windows.iter_mut() {
+                    window.update_surface_from_backend(None);
+                    surface_destroyed_events.send(WindowSurfaceDestroyed { id: window.id() });
+                }
+                surfaces_destroyed = true;
+            }
+            event::Event::Resumed if surfaces_destroyed => {
+                let world = app.world.cell();
+                let winit_windows = world.get_resource::<WinitWindows>().unwrap();
+                let mut windows = world.get_resource_mut::<Windows>().unwrap();
+                let mut surface_created_events = world
+                    .get_resource_mut::<Events<WindowSurfaceCreated>>()
+                    .unwrap();
+                for window in windows.iter_mut() {
+                    if let Some(winit_window) = winit_windows.get_window(window.id()) {
+                        window.update_surface_from_backend(Some(winit_window.raw_window_handle()));
+                        surface_created_events.send(WindowSurfaceCreated { id: window.id() });
+                    }
+                }
+                surfaces_destroyed = false;
+            }
             event::Event::MainEventsCleared => {
                 handle_create_window_events(
                     &mut app.world,
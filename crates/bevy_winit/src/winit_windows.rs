@@ -81,7 +81,9 @@ impl WinitWindows {
             };
 
         #[allow(unused_mut)]
-        let mut winit_window_builder = winit_window_builder.with_title(&window_descriptor.title);
+        let mut winit_window_builder = winit_window_builder
+            .with_title(&window_descriptor.title)
+            .with_transparent(window_descriptor.transparent);
 
         #[cfg(target_arch = "wasm32")]
         {
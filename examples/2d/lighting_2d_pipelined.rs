@@ -0,0 +1,113 @@
+use bevy::{
+    core::Time,
+    ecs::prelude::*,
+    math::{Vec2, Vec3},
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::OrthographicCameraBundle,
+        color::Color,
+        texture::{Extent3d, Texture, TextureDimension, TextureFormat},
+    },
+    sprite2::{
+        AmbientLight2d, Lighting2dPlugin, PipelinedSpriteBundle, PointLight2d, PointLight2dBundle,
+        Sprite, SpriteNormalMap,
+    },
+    PipelinedDefaultPlugins,
+};
+
+const TEXTURE_SIZE: u32 = 64;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(Lighting2dPlugin)
+        .insert_resource(AmbientLight2d {
+            color: Color::rgb(0.6, 0.7, 1.0),
+            brightness: 0.15,
+        })
+        .add_startup_system(setup.system())
+        .add_system(move_lights.system())
+        .run();
+}
+
+/// set up a grid of bumpy sprites with a normal map, lit by two moving lights
+fn setup(mut commands: Commands, mut textures: ResMut<Assets<Texture>>) {
+    let size = Extent3d {
+        width: TEXTURE_SIZE,
+        height: TEXTURE_SIZE,
+        depth_or_array_layers: 1,
+    };
+    // a white tile and the normals of the dome bulging out of it
+    let texture = textures.add(Texture::new_fill(
+        size,
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    let mut normals = Vec::new();
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            // texture rows go down, while the y axis of normals goes up
+            let position = Vec2::new(x as f32 + 0.5, (TEXTURE_SIZE - y) as f32 - 0.5)
+                / (TEXTURE_SIZE as f32 * 0.5)
+                - Vec2::ONE;
+            let normal = if position.length_squared() < 1.0 {
+                position.extend((1.0 - position.length_squared()).sqrt())
+            } else {
+                Vec3::Z
+            };
+            let encoded = normal * 0.5 + Vec3::splat(0.5);
+            normals.extend_from_slice(&[
+                (encoded.x * 255.0) as u8,
+                (encoded.y * 255.0) as u8,
+                (encoded.z * 255.0) as u8,
+                255,
+            ]);
+        }
+    }
+    let normal_map = textures.add(Texture::new(
+        size,
+        TextureDimension::D2,
+        normals,
+        TextureFormat::Rgba8Unorm,
+    ));
+
+    for x in -4..=4 {
+        for y in -3..=3 {
+            commands
+                .spawn_bundle(PipelinedSpriteBundle {
+                    sprite: Sprite::new(Vec2::splat(TEXTURE_SIZE as f32)),
+                    texture: texture.clone(),
+                    transform: Transform::from_xyz(x as f32 * 80.0, y as f32 * 80.0, 0.0),
+                    ..Default::default()
+                })
+                .insert(SpriteNormalMap(normal_map.clone()));
+        }
+    }
+    commands.spawn_bundle(PointLight2dBundle {
+        point_light: PointLight2d {
+            color: Color::rgb(1.0, 0.6, 0.3),
+            radius: 300.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    commands.spawn_bundle(PointLight2dBundle {
+        point_light: PointLight2d {
+            color: Color::rgb(0.3, 0.6, 1.0),
+            radius: 250.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+}
+
+/// moves the lights around the sprites on opposite circles
+fn move_lights(time: Res<Time>, mut query: Query<&mut Transform, With<PointLight2d>>) {
+    let angle = time.seconds_since_startup() as f32;
+    for (i, mut transform) in query.iter_mut().enumerate() {
+        let angle = angle * (1.0 + i as f32 * 0.3) + i as f32 * std::f32::consts::PI;
+        transform.translation = Vec3::new(angle.cos() * 250.0, angle.sin() * 180.0, 10.0);
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::{
+    core::{Time, Timer},
+    ecs::prelude::*,
+    math::{UVec2, Vec2},
+    prelude::{App, Assets, Handle, Transform},
+    render2::{
+        camera::OrthographicCameraBundle,
+        texture::{Extent3d, Texture, TextureDimension, TextureFormat},
+    },
+    sprite2::{TileMap, TileMapBundle},
+    PipelinedDefaultPlugins,
+};
+use rand::Rng;
+
+const MAP_SIZE: u32 = 256;
+const TILE_SIZE: f32 = 16.0;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_startup_system(setup.system())
+        .add_system(paint_tiles.system())
+        .run();
+}
+
+struct PaintTimer(Timer);
+
+/// set up a large two layer tile map, with an atlas of 4 colored tiles
+fn setup(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut tile_maps: ResMut<Assets<TileMap>>,
+) {
+    // a 2x2 atlas of 8x8 tiles: grass, water, sand and a stone with a transparent border
+    let colors = [
+        [70, 160, 60, 255],
+        [50, 90, 200, 255],
+        [220, 200, 120, 255],
+        [120, 120, 120, 255],
+    ];
+    let mut data = Vec::new();
+    for y in 0..16 {
+        for x in 0..16 {
+            let tile = (y / 8) * 2 + x / 8;
+            let border = x % 8 == 0 || x % 8 == 7 || y % 8 == 0 || y % 8 == 7;
+            if tile == 3 && border {
+                data.extend_from_slice(&[0, 0, 0, 0]);
+            } else {
+                data.extend_from_slice(&colors[tile]);
+            }
+        }
+    }
+    let atlas = textures.add(Texture::new(
+        Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    let mut tile_map = TileMap::new(
+        UVec2::splat(MAP_SIZE),
+        2,
+        Vec2::splat(TILE_SIZE),
+        atlas,
+        UVec2::new(2, 2),
+    );
+    let mut rng = rand::thread_rng();
+    for y in 0..MAP_SIZE {
+        for x in 0..MAP_SIZE {
+            let tile = if rng.gen_bool(0.1) { 1 } else { 0 };
+            tile_map.set(0, UVec2::new(x, y), Some(tile));
+        }
+    }
+
+    commands.spawn_bundle(TileMapBundle {
+        tile_map: tile_maps.add(tile_map),
+        // center the map on the camera
+        transform: Transform::from_xyz(
+            -(MAP_SIZE as f32) * TILE_SIZE * 0.5,
+            -(MAP_SIZE as f32) * TILE_SIZE * 0.5,
+            0.0,
+        ),
+        ..Default::default()
+    });
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.insert_resource(PaintTimer(Timer::from_seconds(0.05, true)));
+}
+
+/// paints random tiles, which only uploads the chunks they are in again
+fn paint_tiles(
+    time: Res<Time>,
+    mut timer: ResMut<PaintTimer>,
+    mut tile_maps: ResMut<Assets<TileMap>>,
+    query: Query<&Handle<TileMap>>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for handle in query.iter() {
+        if let Some(tile_map) = tile_maps.get_mut(handle) {
+            let position = UVec2::new(rng.gen_range(0..MAP_SIZE), rng.gen_range(0..MAP_SIZE));
+            tile_map.set(0, position, Some(rng.gen_range(0..3)));
+            let stone = if rng.gen_bool(0.5) { Some(3) } else { None };
+            tile_map.set(1, position, stone);
+        }
+    }
+}
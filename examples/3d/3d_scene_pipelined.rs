@@ -9,6 +9,7 @@ use bevy::{
     render2::{
         camera::PerspectiveCameraBundle,
         color::Color,
+        diagnostic::RenderDiagnosticsPlugin,
         mesh::{shape, Mesh},
     },
     wgpu2::diagnostic::WgpuResourceDiagnosticsPlugin,
@@ -21,6 +22,7 @@ fn main() {
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(LogDiagnosticsPlugin::default())
         .add_plugin(WgpuResourceDiagnosticsPlugin::default())
+        .add_plugin(RenderDiagnosticsPlugin::default())
         .add_startup_system(setup.system())
         .add_system(movement.system())
         .run();
@@ -0,0 +1,77 @@
+use bevy::{
+    core::Time,
+    ecs::prelude::*,
+    math::Vec3,
+    pbr2::{
+        AutoExposure, AutoExposurePlugin, PbrBundle, PointLight, PointLightBundle, StandardMaterial,
+    },
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::PerspectiveCameraBundle,
+        color::Color,
+        core_pipeline::CorePipelineSettings,
+        mesh::{shape, Mesh},
+    },
+    PipelinedDefaultPlugins,
+};
+
+fn main() {
+    App::new()
+        // auto exposure measures the views before they are tonemapped
+        .insert_resource(CorePipelineSettings {
+            hdr: true,
+            ..Default::default()
+        })
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(AutoExposurePlugin)
+        .add_startup_system(setup.system())
+        .add_system(flicker.system())
+        .run();
+}
+
+/// set up a scene lit by a light that slowly gets much brighter and darker, which the camera
+/// adapts to
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // plane
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 5.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    // cube
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+        transform: Transform::from_xyz(0.0, 0.5, 0.0),
+        ..Default::default()
+    });
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+    // camera
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        // This adapts the exposure of the camera to the brightness of the scene
+        .insert(AutoExposure {
+            speed_to_light: 2.0,
+            speed_to_dark: 0.5,
+            ..Default::default()
+        });
+}
+
+/// changes the intensity of the light by 8 stops every few seconds
+fn flicker(time: Res<Time>, mut query: Query<&mut PointLight>) {
+    let stops = (time.seconds_since_startup() as f32 * 0.5).sin() * 4.0;
+    for mut light in query.iter_mut() {
+        light.intensity = 200.0 * stops.exp2();
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::{
+    ecs::prelude::*,
+    math::Vec3,
+    pbr2::{
+        OitPlugin, OrderIndependentTransparency, PbrBundle, PointLightBundle, StandardMaterial,
+    },
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::PerspectiveCameraBundle,
+        color::Color,
+        mesh::{shape, Mesh},
+    },
+    PipelinedDefaultPlugins,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(OitPlugin)
+        .add_startup_system(setup.system())
+        .run();
+}
+
+/// set up overlapping transparent cubes, which look the same from any direction
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // plane
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 5.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    // transparent cubes, the pbr shader takes their alpha from their vertex colors
+    for (i, color) in [
+        [1.0, 0.2, 0.2, 0.5],
+        [0.2, 1.0, 0.2, 0.5],
+        [0.2, 0.2, 1.0, 0.5],
+    ]
+    .iter()
+    .enumerate()
+    {
+        let mut mesh = Mesh::from(shape::Cube { size: 1.0 });
+        mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, vec![*color; mesh.count_vertices()]);
+        commands.spawn_bundle(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(Color::rgba(1.0, 1.0, 1.0, color[3]).into()),
+            transform: Transform::from_xyz(i as f32 * 0.4 - 0.4, 0.5, i as f32 * -0.4),
+            ..Default::default()
+        });
+    }
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+    // camera
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        // This draws transparent meshes with order-independent transparency
+        .insert(OrderIndependentTransparency);
+}
@@ -0,0 +1,74 @@
+use bevy::{
+    core::Time,
+    ecs::prelude::*,
+    math::{Quat, Vec3},
+    pbr2::{
+        PbrBundle, PlanarReflection, PlanarReflectionBundle, PlanarReflectionMaterial,
+        PlanarReflectionPlugin, PointLightBundle, StandardMaterial,
+    },
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::PerspectiveCameraBundle,
+        color::Color,
+        mesh::{shape, Mesh},
+    },
+    PipelinedDefaultPlugins,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(PlanarReflectionPlugin)
+        .add_startup_system(setup.system())
+        .add_system(rotate.system())
+        .run();
+}
+
+struct Rotates;
+
+/// set up a cube floating above a pool of water
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut reflection_materials: ResMut<Assets<PlanarReflectionMaterial>>,
+) {
+    // water, rendered at half resolution
+    commands.spawn_bundle(PlanarReflectionBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 5.0 })),
+        material: reflection_materials.add(PlanarReflectionMaterial {
+            color: Color::rgb(0.1, 0.2, 0.3),
+            reflectivity: 0.7,
+        }),
+        reflection: PlanarReflection {
+            resolution_scale: 0.5,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    // cube
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+            material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+            ..Default::default()
+        })
+        .insert(Rotates);
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    });
+}
+
+fn rotate(time: Res<Time>, mut query: Query<&mut Transform, With<Rotates>>) {
+    for mut transform in query.iter_mut() {
+        transform.rotation *= Quat::from_rotation_y(time.delta_seconds());
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{
+    ecs::prelude::*,
+    math::Vec3,
+    pbr2::{
+        PbrBundle, PointCloudBundle, PointCloudMaterial, PointCloudPlugin, PointLightBundle,
+        StandardMaterial,
+    },
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::PerspectiveCameraBundle,
+        color::Color,
+        mesh::{Indices, Mesh},
+        pipeline::PrimitiveTopology,
+    },
+    PipelinedDefaultPlugins,
+};
+
+const POINTS: usize = 4096;
+const HELIX_SEGMENTS: u16 = 512;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(PointCloudPlugin)
+        .add_startup_system(setup.system())
+        .run();
+}
+
+/// set up a point cloud sphere with colored points, wrapped in a line strip helix drawn with the
+/// standard material
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut point_cloud_materials: ResMut<Assets<PointCloudMaterial>>,
+) {
+    // points spread evenly over a sphere along a fibonacci spiral, colored by their height
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let positions = (0..POINTS)
+        .map(|i| {
+            let y = 1.0 - (i as f32 + 0.5) / POINTS as f32 * 2.0;
+            let radius = (1.0 - y * y).sqrt();
+            let angle = golden_angle * i as f32;
+            [angle.cos() * radius, y, angle.sin() * radius]
+        })
+        .collect::<Vec<_>>();
+    let colors = positions
+        .iter()
+        .map(|[_, y, _]| {
+            let t = y * 0.5 + 0.5;
+            [1.0 - t, 0.4, t, 1.0]
+        })
+        .collect::<Vec<[f32; 4]>>();
+    let mut point_cloud = Mesh::new(PrimitiveTopology::PointList);
+    point_cloud.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    point_cloud.set_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    commands.spawn_bundle(PointCloudBundle {
+        mesh: meshes.add(point_cloud),
+        material: point_cloud_materials.add(PointCloudMaterial {
+            point_size: 4.0,
+            ..Default::default()
+        }),
+        transform: Transform::from_xyz(0.0, 1.0, 0.0),
+        ..Default::default()
+    });
+
+    // a helix around the sphere, as an indexed line strip with 16 bit indices
+    let positions = (0..=HELIX_SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / HELIX_SEGMENTS as f32;
+            let angle = t * std::f32::consts::PI * 16.0;
+            [angle.cos() * 1.3, t * 2.0, angle.sin() * 1.3]
+        })
+        .collect::<Vec<_>>();
+    let normals = positions
+        .iter()
+        .map(|[x, _, z]| Vec3::new(*x, 0.0, *z).normalize().into())
+        .collect::<Vec<[f32; 3]>>();
+    let vertex_count = positions.len();
+    let mut helix = Mesh::new(PrimitiveTopology::LineStrip);
+    helix.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    helix.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    helix.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; vertex_count]);
+    helix.set_indices(Some(Indices::U16((0..=HELIX_SEGMENTS).collect())));
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(helix),
+        material: materials.add(Color::rgb(0.3, 0.6, 1.0).into()),
+        ..Default::default()
+    });
+
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-3.0, 3.0, 5.0)
+            .looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        ..Default::default()
+    });
+}
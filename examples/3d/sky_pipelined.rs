@@ -0,0 +1,60 @@
+use bevy::{
+    core::Time,
+    ecs::prelude::*,
+    math::Vec3,
+    pbr2::{PbrBundle, PointLightBundle, Sky, SkyPlugin, StandardMaterial},
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::PerspectiveCameraBundle,
+        color::Color,
+        mesh::{shape, Mesh},
+    },
+    PipelinedDefaultPlugins,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(SkyPlugin)
+        .add_startup_system(setup.system())
+        .add_system(move_sun.system())
+        .run();
+}
+
+/// set up a simple scene under a procedural sky
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // plane
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 5.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    // cube
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+        transform: Transform::from_xyz(0.0, 0.5, 0.0),
+        ..Default::default()
+    });
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-2.0, 1.0, 5.0)
+            .looking_at(Vec3::new(0.0, 1.5, 0.0), Vec3::Y),
+        ..Default::default()
+    });
+}
+
+/// moves the sun across the sky, from sunrise to sunset
+fn move_sun(time: Res<Time>, mut sky: ResMut<Sky>) {
+    let angle = (time.seconds_since_startup() as f32 * 0.1) % std::f32::consts::PI;
+    sky.sun_direction = Vec3::new(angle.cos(), angle.sin(), -0.3).normalize();
+}
@@ -0,0 +1,110 @@
+use bevy::{
+    core::Time,
+    ecs::prelude::*,
+    math::{UVec2, Vec2, Vec3},
+    pbr2::{
+        PointLightBundle, SplatMaterial, StandardMaterial, Terrain, TerrainBundle, TerrainPlugin,
+    },
+    prelude::{App, Assets, Transform},
+    render2::{
+        camera::{Camera, PerspectiveCameraBundle},
+        color::Color,
+        texture::{Extent3d, Texture, TextureDimension, TextureFormat},
+    },
+    PipelinedDefaultPlugins,
+};
+
+const MAP_SIZE: u32 = 256;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(TerrainPlugin)
+        .add_startup_system(setup.system())
+        .add_system(fly_camera.system())
+        .run();
+}
+
+/// set up a terrain from a generated heightmap, painted with a splat map
+fn setup(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut splat_materials: ResMut<Assets<SplatMaterial>>,
+) {
+    let size = Extent3d {
+        width: MAP_SIZE,
+        height: MAP_SIZE,
+        depth_or_array_layers: 1,
+    };
+    let mut heights = Vec::new();
+    let mut splat = Vec::new();
+    for y in 0..MAP_SIZE {
+        for x in 0..MAP_SIZE {
+            let uv = Vec2::new(x as f32, y as f32) / MAP_SIZE as f32;
+            let height = 0.5
+                + 0.3 * (uv.x * 9.0).sin() * (uv.y * 7.0).cos()
+                + 0.2 * (uv.x * 23.0 + uv.y * 17.0).sin();
+            let height = height.clamp(0.0, 1.0);
+            heights.push((height * 255.0) as u8);
+            // sand in the valleys, then grass, rock and snow on the peaks
+            let weight =
+                |center: f32| ((1.0 - (height - center).abs() * 4.0).max(0.0) * 255.0) as u8;
+            splat.extend_from_slice(&[weight(0.1), weight(0.4), weight(0.65), weight(0.9)]);
+        }
+    }
+    let heightmap = textures.add(Texture::new(
+        size,
+        TextureDimension::D2,
+        heights,
+        TextureFormat::R8Unorm,
+    ));
+    let splat_map = textures.add(Texture::new(
+        size,
+        TextureDimension::D2,
+        splat,
+        TextureFormat::Rgba8Unorm,
+    ));
+
+    // terrain
+    commands
+        .spawn_bundle(TerrainBundle {
+            terrain: Terrain {
+                heightmap,
+                size: Vec2::new(400.0, 400.0),
+                height: 40.0,
+                chunks: UVec2::new(16, 16),
+                ..Default::default()
+            },
+            material: materials.add(Color::WHITE.into()),
+            ..Default::default()
+        })
+        .insert(splat_materials.add(SplatMaterial {
+            splat_map,
+            layers: [
+                Color::rgb(0.8, 0.7, 0.5),
+                Color::rgb(0.3, 0.5, 0.2),
+                Color::rgb(0.4, 0.4, 0.4),
+                Color::rgb(0.95, 0.95, 0.95),
+            ],
+        }));
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(0.0, 150.0, 0.0),
+        ..Default::default()
+    });
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-150.0, 60.0, 150.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    });
+}
+
+/// circles the camera around the terrain, so chunks change their level of detail
+fn fly_camera(time: Res<Time>, mut query: Query<&mut Transform, With<Camera>>) {
+    let angle = time.seconds_since_startup() as f32 * 0.2;
+    for mut transform in query.iter_mut() {
+        *transform = Transform::from_xyz(angle.cos() * 150.0, 60.0, angle.sin() * 150.0)
+            .looking_at(Vec3::ZERO, Vec3::Y);
+    }
+}
@@ -8,6 +8,9 @@ fn main() {
             vsync: true,
             resizable: false,
             mode: WindowMode::BorderlessFullscreen,
+            // render at half the native resolution of the retina screen, which saves battery and
+            // GPU time; 1.0 renders at full sharpness
+            resolution_scale: 0.5,
             ..Default::default()
         })
         .insert_resource(Msaa { samples: 4 })
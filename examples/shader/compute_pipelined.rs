@@ -0,0 +1,49 @@
+use bevy::{
+    ecs::prelude::*,
+    prelude::App,
+    render2::compute::{ComputeJob, ComputeJobId, ComputeJobs, ComputePlugin},
+    PipelinedDefaultPlugins,
+};
+
+/// This example squares a list of numbers on the GPU with a compute shader, and prints the
+/// results once they were read back.
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(ComputePlugin)
+        .add_startup_system(setup.system())
+        .add_system(print_results.system())
+        .run();
+}
+
+const SQUARE_SHADER: &str = r"
+#version 450
+layout(local_size_x = 64) in;
+
+layout(set = 0, binding = 0) buffer Values {
+    float values[];
+};
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    values[index] = values[index] * values[index];
+}
+";
+
+struct SquareJob(ComputeJobId);
+
+fn setup(mut commands: Commands, compute_jobs: Res<ComputeJobs>) {
+    let values = (0..256).map(|i| i as f32).collect::<Vec<_>>();
+    // one workgroup squares 64 values
+    let job = ComputeJob::from_glsl(SQUARE_SHADER, [values.len() as u32 / 64, 1, 1])
+        .with_read_back_buffer(0, &values);
+    commands.insert_resource(SquareJob(compute_jobs.submit(job)));
+}
+
+fn print_results(square_job: Res<SquareJob>, compute_jobs: Res<ComputeJobs>) {
+    match compute_jobs.take_results(square_job.0) {
+        Some(Ok(results)) => println!("{:?}", results.read::<f32>(0).unwrap()),
+        Some(Err(err)) => println!("the compute job failed: {}", err),
+        None => {}
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::{
+    ecs::prelude::*,
+    input::Input,
+    math::Vec2,
+    prelude::{App, Assets, Handle, KeyCode},
+    render2::{
+        camera::OrthographicCameraBundle,
+        compute::{ComputeJob, ComputeJobs, ComputePlugin},
+        texture::{
+            Extent3d, StorageTextureAccess, Texture, TextureDimension, TextureFormat, TextureUsage,
+        },
+    },
+    sprite2::{PipelinedSpriteBundle, Sprite},
+    PipelinedDefaultPlugins,
+};
+
+/// This example writes procedural noise into a texture with a compute shader, and draws it with a
+/// sprite. Press space to generate new noise.
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(ComputePlugin)
+        .add_startup_system(setup.system())
+        .add_system(generate_noise.system())
+        .run();
+}
+
+const TEXTURE_SIZE: u32 = 256;
+
+const NOISE_SHADER: &str = r"
+#version 450
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform Noise {
+    float seed;
+    float scale;
+};
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D Output;
+
+float hash(vec2 p) {
+    return fract(sin(dot(p + seed, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+float value_noise(vec2 p) {
+    vec2 cell = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(cell), hash(cell + vec2(1.0, 0.0)), u.x),
+        mix(hash(cell + vec2(0.0, 1.0)), hash(cell + vec2(1.0, 1.0)), u.x),
+        u.y
+    );
+}
+
+void main() {
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    float value = value_noise(vec2(texel) / scale);
+    imageStore(Output, texel, vec4(vec3(value), 1.0));
+}
+";
+
+struct NoiseTexture {
+    handle: Handle<Texture>,
+    seed: f32,
+    generated: bool,
+}
+
+/// Creates a texture that compute shaders can write to, and sprites can sample.
+fn storage_texture(size: u32) -> Texture {
+    let mut texture = Texture::new_fill(
+        Extent3d::new(size, size, 1),
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        // sRGB formats can't be storage textures
+        TextureFormat::Rgba8Unorm,
+    );
+    texture.usage = TextureUsage::SAMPLED | TextureUsage::STORAGE;
+    texture
+}
+
+fn setup(mut commands: Commands, mut textures: ResMut<Assets<Texture>>) {
+    let handle = textures.add(storage_texture(TEXTURE_SIZE));
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(PipelinedSpriteBundle {
+        texture: handle.clone(),
+        sprite: Sprite {
+            size: Vec2::splat(2.0 * TEXTURE_SIZE as f32),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    commands.insert_resource(NoiseTexture {
+        handle,
+        seed: 0.0,
+        generated: false,
+    });
+}
+
+fn generate_noise(
+    input: Res<Input<KeyCode>>,
+    textures: Res<Assets<Texture>>,
+    compute_jobs: Res<ComputeJobs>,
+    mut noise: ResMut<NoiseTexture>,
+) {
+    if input.just_pressed(KeyCode::Space) {
+        noise.seed += 1.0;
+        noise.generated = false;
+    }
+    if noise.generated {
+        return;
+    }
+    // the texture can only be bound once it was uploaded
+    let texture_view = match textures
+        .get(&noise.handle)
+        .and_then(|texture| texture.gpu_data.as_ref())
+    {
+        Some(gpu_data) => gpu_data.texture_view,
+        None => return,
+    };
+    // one workgroup writes 8x8 texels
+    let job = ComputeJob::from_glsl(NOISE_SHADER, [TEXTURE_SIZE / 8, TEXTURE_SIZE / 8, 1])
+        .with_buffer(0, &[noise.seed, 32.0])
+        .with_storage_texture(1, texture_view, StorageTextureAccess::WriteOnly);
+    compute_jobs.submit(job);
+    noise.generated = true;
+}
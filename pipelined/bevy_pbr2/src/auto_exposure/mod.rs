@@ -0,0 +1,677 @@
+use crate::Exposure;
+use bevy_app::prelude::*;
+use bevy_core::Time;
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    camera::ActiveCameras,
+    core_pipeline::{self, CorePipelineSettings, ViewHdrTexture, ViewMainTexture},
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, BufferId, BufferInfo, BufferMapMode, BufferUsage,
+        DynamicUniformIndex, DynamicUniformVec, SamplerId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResourceContext, RenderResources},
+    shader::{ComputeShaderStages, Shader, ShaderReflectOptions, ShaderStage},
+    texture::*,
+    view::ExtractedView,
+    RenderApp, RenderStage,
+};
+use bevy_utils::{tracing::warn, HashMap};
+use crevice::std140::AsStd140;
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const AUTO_EXPOSURE: &'static str = "auto_exposure";
+    }
+}
+
+// NOTE: this must be kept in sync with HISTOGRAM_BINS in luminance_histogram.comp
+const HISTOGRAM_BINS: usize = 256;
+const HISTOGRAM_SIZE: u64 = (HISTOGRAM_BINS * std::mem::size_of::<u32>()) as u64;
+/// The size of the workgroups of the histogram shader in each dimension.
+const WORKGROUP_SIZE: u32 = 16;
+/// The fractions of the darkest and brightest pixels that are left out of the average
+/// luminance, so that small highlights and shadows don't change the exposure.
+const LOW_PERCENTILE: f32 = 0.1;
+const HIGH_PERCENTILE: f32 = 0.9;
+/// The most histograms of a view that are being read back at once. Reading a histogram back
+/// takes a frame or more, this many frames can be in flight before one is skipped.
+const MAX_READ_BACKS: usize = 3;
+
+/// Adapts the [`Exposure`] of 3d cameras with an [`AutoExposure`] component to the brightness of
+/// what they see, the way eyes adapt to the dark. Must be added after [`crate::PbrPlugin`], and
+/// after [`crate::OitPlugin`] and [`crate::WireframePlugin`] if they are used.
+///
+/// The views are measured before they are tonemapped, so this needs
+/// [`CorePipelineSettings::hdr`]: a compute pass builds a histogram of the luminance of the
+/// [`ViewHdrTexture`] (or [`ViewMainTexture`]) of each view after its main pass. The histogram is
+/// read back without waiting for the GPU, usually a frame or two later, and the exposure computed
+/// from it is applied from the frame after that.
+#[derive(Debug, Default)]
+pub struct AutoExposurePlugin;
+
+impl Plugin for AutoExposurePlugin {
+    fn build(&self, app: &mut App) {
+        let hdr = app
+            .world
+            .get_resource::<CorePipelineSettings>()
+            .map_or(false, |settings| settings.hdr);
+        if !hdr {
+            warn!(
+                "AutoExposurePlugin needs CorePipelineSettings::hdr to measure the luminance of \
+                views, exposures won't adapt."
+            );
+        }
+        let histograms = LuminanceHistograms::default();
+        app.register_type::<AutoExposure>()
+            .insert_resource(histograms.clone())
+            .add_system_to_stage(CoreStage::PostUpdate, auto_exposure_system.system());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(histograms)
+            .add_system_to_stage(RenderStage::Extract, extract_auto_exposure_cameras.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_auto_exposure.system())
+            .add_system_to_stage(RenderStage::Queue, queue_auto_exposure.system())
+            .add_system_to_stage(RenderStage::Cleanup, read_luminance_histograms.system())
+            .init_render_resource::<AutoExposureShaders>()
+            .init_resource::<AutoExposureMeta>();
+
+        let auto_exposure_node = AutoExposureNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("auto_exposure", AutoExposureUniformsNode);
+        graph
+            .add_node_edge("auto_exposure", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::AUTO_EXPOSURE, auto_exposure_node);
+        for node in [
+            core_pipeline::draw_3d_graph::node::MAIN_PASS,
+            crate::oit::draw_3d_graph::node::OIT_PASS,
+            crate::wireframe::draw_3d_graph::node::WIREFRAME_PASS,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(*node, draw_3d_graph::node::AUTO_EXPOSURE)
+                    .unwrap();
+            }
+        }
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::AUTO_EXPOSURE,
+                AutoExposureNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+/// Adapts the [`Exposure`] of a 3d camera to the average luminance of its view, see
+/// [`AutoExposurePlugin`]. The exposure is inserted if the camera doesn't have one.
+///
+/// Exposures are in stops, like [`Exposure::ev`]: an exposure of `0.0` shows a luminance of
+/// `0.18` as middle grey, and each stop more doubles that luminance.
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct AutoExposure {
+    /// The lowest exposure, used in dark scenes.
+    pub min_ev: f32,
+    /// The highest exposure, used in bright scenes.
+    pub max_ev: f32,
+    /// Added to the exposure that brings the average luminance to middle grey. Negative values
+    /// brighten the image.
+    pub compensation: f32,
+    /// How fast the exposure adapts to a brighter view, in stops per second for each stop it
+    /// is off by.
+    pub speed_to_light: f32,
+    /// How fast the exposure adapts to a darker view, in stops per second for each stop it is
+    /// off by. Eyes adapt to the dark slower than to light.
+    pub speed_to_dark: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        AutoExposure {
+            min_ev: -8.0,
+            max_ev: 8.0,
+            compensation: 0.0,
+            speed_to_light: 3.0,
+            speed_to_dark: 1.0,
+        }
+    }
+}
+
+impl AutoExposure {
+    /// Returns the exposure that brings the average luminance of a histogram built with these
+    /// settings to middle grey, or `None` if all its pixels are black.
+    pub fn target_ev(&self, histogram: &[u32]) -> Option<f32> {
+        let count: u32 = histogram.iter().skip(1).sum();
+        if count == 0 {
+            return None;
+        }
+        let (low, high) = (
+            count as f32 * LOW_PERCENTILE,
+            count as f32 * HIGH_PERCENTILE,
+        );
+        let bin_ev = (self.max_ev - self.min_ev) / (histogram.len() - 2) as f32;
+        let (mut seen, mut sum, mut weight) = (0.0f32, 0.0f32, 0.0f32);
+        for (bin, &pixels) in histogram.iter().enumerate().skip(1) {
+            // the pixels of this bin between the low and high percentiles
+            let kept = (seen + pixels as f32).min(high) - seen.max(low);
+            seen += pixels as f32;
+            if kept > 0.0 {
+                sum += (self.min_ev + (bin as f32 - 0.5) * bin_ev) * kept;
+                weight += kept;
+            }
+        }
+        let average = if weight > 0.0 { sum / weight } else { 0.0 };
+        Some(average.clamp(self.min_ev, self.max_ev) + self.compensation)
+    }
+}
+
+/// The luminance histograms read back from the views with an [`AutoExposure`], by camera. This
+/// resource exists in both the app world and the render world, and both share the same state.
+/// Histograms are read back a frame or more after the frame they were built in.
+#[derive(Clone, Default)]
+pub struct LuminanceHistograms {
+    histograms: Arc<Mutex<HashMap<Entity, Vec<u32>>>>,
+}
+
+impl LuminanceHistograms {
+    /// Returns the latest histogram read back from `camera`, if it hasn't been taken yet.
+    /// Bin 0 counts the black pixels, and the other bins split the range from
+    /// [`AutoExposure::min_ev`] to [`AutoExposure::max_ev`] evenly.
+    pub fn take(&self, camera: Entity) -> Option<Vec<u32>> {
+        self.histograms.lock().unwrap().remove(&camera)
+    }
+}
+
+pub fn auto_exposure_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    histograms: Res<LuminanceHistograms>,
+    mut cameras: Query<(Entity, &AutoExposure, Option<&mut Exposure>)>,
+) {
+    for (entity, auto_exposure, exposure) in cameras.iter_mut() {
+        let mut exposure = match exposure {
+            Some(exposure) => exposure,
+            None => {
+                commands.entity(entity).insert(Exposure::default());
+                continue;
+            }
+        };
+        let target = match histograms
+            .take(entity)
+            .and_then(|histogram| auto_exposure.target_ev(&histogram))
+        {
+            Some(target) => target,
+            None => continue,
+        };
+        let speed = if target > exposure.ev {
+            auto_exposure.speed_to_light
+        } else {
+            auto_exposure.speed_to_dark
+        };
+        // exponential smoothing, independent of the frame rate
+        let t = 1.0 - (-speed * time.delta_seconds()).exp();
+        exposure.ev += (target - exposure.ev) * t;
+    }
+}
+
+pub fn extract_auto_exposure_cameras(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    query: Query<&AutoExposure>,
+) {
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(auto_exposure) = query.get(entity) {
+                commands.get_or_spawn(entity).insert(auto_exposure.clone());
+            }
+        }
+    }
+}
+
+pub struct AutoExposureShaders {
+    histogram_pipeline: PipelineId,
+    histogram_layout: PipelineLayout,
+    sampler: SamplerId,
+}
+
+impl FromWorld for AutoExposureShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+
+        let histogram_shader = Shader::from_glsl(
+            ShaderStage::Compute,
+            include_str!("luminance_histogram.comp"),
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+        let histogram_shader_layout = histogram_shader
+            .reflect_layout(&ShaderReflectOptions {
+                bevy_conventions: false,
+                ..Default::default()
+            })
+            .unwrap();
+        let mut histogram_layout =
+            PipelineLayout::from_shader_layouts(&mut [histogram_shader_layout]);
+        histogram_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        histogram_layout.update_bind_group_ids();
+        let compute = render_resources.create_shader_module(&histogram_shader);
+        let histogram_pipeline =
+            render_resources.create_compute_pipeline(&ComputePipelineDescriptor::new(
+                ComputeShaderStages { compute },
+                histogram_layout.clone(),
+            ));
+
+        AutoExposureShaders {
+            histogram_pipeline,
+            histogram_layout,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuAutoExposure {
+    min_ev: f32,
+    ev_range: f32,
+    exposure: f32,
+}
+
+/// The state of a buffer a histogram is copied to for reading it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadBackState {
+    /// The buffer can be copied to.
+    Free,
+    /// A histogram is copied to the buffer this frame, the buffer is mapped once the frame was
+    /// submitted.
+    Copied,
+    /// The buffer is being mapped, it is read once the mapping completes.
+    Mapping,
+}
+
+struct ReadBack {
+    buffer: BufferId,
+    state: ReadBackState,
+    /// The frame the histogram was copied in, so the latest of the histograms read back at once
+    /// is kept.
+    frame: u64,
+}
+
+/// The histogram of a camera, and the buffers it is copied to for reading it back. These are
+/// kept for as long as the camera has an [`AutoExposure`].
+struct HistogramBuffers {
+    histogram: BufferId,
+    read_backs: Vec<ReadBack>,
+}
+
+impl HistogramBuffers {
+    /// Returns a buffer the histogram can be copied to this frame, creating one if all are in
+    /// use, or `None` if [`MAX_READ_BACKS`] are being read back already.
+    fn read_back(&mut self, render_resources: &RenderResources, frame: u64) -> Option<BufferId> {
+        let index = match self
+            .read_backs
+            .iter()
+            .position(|read_back| read_back.state == ReadBackState::Free)
+        {
+            Some(index) => index,
+            None if self.read_backs.len() < MAX_READ_BACKS => {
+                self.read_backs.push(ReadBack {
+                    buffer: render_resources.create_buffer(BufferInfo {
+                        size: HISTOGRAM_SIZE as usize,
+                        buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+                        ..Default::default()
+                    }),
+                    state: ReadBackState::Free,
+                    frame,
+                });
+                self.read_backs.len() - 1
+            }
+            None => return None,
+        };
+        let read_back = &mut self.read_backs[index];
+        read_back.state = ReadBackState::Copied;
+        read_back.frame = frame;
+        Some(read_back.buffer)
+    }
+
+    fn remove(&self, render_resources: &RenderResources) {
+        render_resources.remove_buffer(self.histogram);
+        for read_back in self.read_backs.iter() {
+            render_resources.remove_buffer(read_back.buffer);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AutoExposureMeta {
+    pub uniforms: DynamicUniformVec<GpuAutoExposure>,
+    histograms: HashMap<Entity, HistogramBuffers>,
+    /// Counts the frames, to order the histograms read back.
+    frame: u64,
+}
+
+/// The histogram and settings of a view with [`AutoExposure`].
+pub struct ViewAutoExposure {
+    pub histogram: BufferId,
+    /// The buffer the histogram is copied to for reading it back, `None` if too many histograms
+    /// of the view are being read back already.
+    pub read_back: Option<BufferId>,
+    pub uniform: DynamicUniformIndex,
+}
+
+/// Prepares the histograms of the views with an [`AutoExposure`] that are drawn into a
+/// [`ViewHdrTexture`].
+pub fn prepare_auto_exposure(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    mut auto_exposure_meta: ResMut<AutoExposureMeta>,
+    views: Query<(Entity, &AutoExposure, Option<&Exposure>), With<ViewHdrTexture>>,
+) {
+    let auto_exposure_meta = &mut *auto_exposure_meta;
+    auto_exposure_meta.frame += 1;
+    auto_exposure_meta
+        .histograms
+        .retain(|entity, buffers| match views.get(*entity) {
+            Ok(_) => true,
+            Err(_) => {
+                buffers.remove(&render_resources);
+                false
+            }
+        });
+    auto_exposure_meta
+        .uniforms
+        .reserve_and_clear(views.iter().count(), &render_resources);
+    for (entity, auto_exposure, exposure) in views.iter() {
+        let buffers = auto_exposure_meta
+            .histograms
+            .entry(entity)
+            .or_insert_with(|| HistogramBuffers {
+                histogram: render_resources.create_buffer(BufferInfo {
+                    size: HISTOGRAM_SIZE as usize,
+                    buffer_usage: BufferUsage::STORAGE
+                        | BufferUsage::COPY_SRC
+                        | BufferUsage::COPY_DST,
+                    ..Default::default()
+                }),
+                read_backs: Vec::new(),
+            });
+        render_resources.write_buffer(buffers.histogram, 0, &[0; HISTOGRAM_SIZE as usize]);
+
+        let uniform = auto_exposure_meta.uniforms.push(GpuAutoExposure {
+            min_ev: auto_exposure.min_ev,
+            ev_range: (auto_exposure.max_ev - auto_exposure.min_ev).max(f32::EPSILON),
+            exposure: exposure.map_or(1.0, Exposure::multiplier),
+        });
+
+        commands.entity(entity).insert(ViewAutoExposure {
+            histogram: buffers.histogram,
+            read_back: buffers.read_back(&render_resources, auto_exposure_meta.frame),
+            uniform,
+        });
+    }
+
+    auto_exposure_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+struct AutoExposureViewBindGroups {
+    histogram_bind_group: BindGroupId,
+    uniform_bind_group: BindGroupId,
+}
+
+pub fn queue_auto_exposure(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    auto_exposure_shaders: Res<AutoExposureShaders>,
+    auto_exposure_meta: Res<AutoExposureMeta>,
+    views: Query<(
+        Entity,
+        &ViewAutoExposure,
+        &ViewHdrTexture,
+        Option<&ViewMainTexture>,
+    )>,
+) {
+    let shaders = &*auto_exposure_shaders;
+    for (view_entity, view_auto_exposure, hdr_texture, main_texture) in views.iter() {
+        // the texture the main pass draws into, see `draw_3d_graph::input::RENDER_TARGET`
+        let render_target = main_texture.map_or(hdr_texture.view, |main_texture| main_texture.view);
+        let histogram_bind_group = BindGroupBuilder::default()
+            .add_texture_view(0, render_target)
+            .add_sampler(1, shaders.sampler)
+            .add_buffer(2, view_auto_exposure.histogram, 0..HISTOGRAM_SIZE)
+            .finish();
+        render_resources.create_bind_group(
+            shaders.histogram_layout.bind_group(0).id,
+            &histogram_bind_group,
+        );
+
+        let uniform_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                auto_exposure_meta
+                    .uniforms
+                    .binding(view_auto_exposure.uniform.chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(
+            shaders.histogram_layout.bind_group(1).id,
+            &uniform_bind_group,
+        );
+
+        commands
+            .entity(view_entity)
+            .insert(AutoExposureViewBindGroups {
+                histogram_bind_group: histogram_bind_group.id,
+                uniform_bind_group: uniform_bind_group.id,
+            });
+    }
+}
+
+/// Writes the [`AutoExposureMeta`] uniforms before the views are drawn.
+pub struct AutoExposureUniformsNode;
+
+impl Node for AutoExposureUniformsNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let auto_exposure_meta = world.get_resource::<AutoExposureMeta>().unwrap();
+        auto_exposure_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Builds the luminance histogram of the render target of a view and copies it to be read back.
+pub struct AutoExposureNode {
+    query: QueryState<(
+        &'static ExtractedView,
+        &'static ViewAutoExposure,
+        &'static AutoExposureViewBindGroups,
+    )>,
+}
+
+impl AutoExposureNode {
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for AutoExposureNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(AutoExposureNode::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view, view_auto_exposure, bind_groups) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(view) => view,
+                Err(_) => return Ok(()),
+            };
+        let shaders = world.get_resource::<AutoExposureShaders>().unwrap();
+
+        render_context.begin_compute_pass(&mut |compute_pass| {
+            compute_pass.set_pipeline(shaders.histogram_pipeline);
+            compute_pass.set_bind_group(
+                0,
+                shaders.histogram_layout.bind_group(0).id,
+                bind_groups.histogram_bind_group,
+                None,
+            );
+            compute_pass.set_bind_group(
+                1,
+                shaders.histogram_layout.bind_group(1).id,
+                bind_groups.uniform_bind_group,
+                Some(&[view_auto_exposure.uniform.offset]),
+            );
+            compute_pass.dispatch(
+                (view.width + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                (view.height + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                1,
+            );
+        });
+        if let Some(read_back) = view_auto_exposure.read_back {
+            render_context.copy_buffer_to_buffer(
+                view_auto_exposure.histogram,
+                0,
+                read_back,
+                0,
+                HISTOGRAM_SIZE,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Maps the buffers the histograms were copied to this frame, and reads back those whose mapping
+/// completed since the last frame, without waiting for the GPU. Runs in
+/// [`RenderStage::Cleanup`], after the frame was submitted.
+pub fn read_luminance_histograms(
+    histograms: Res<LuminanceHistograms>,
+    render_resources: Res<RenderResources>,
+    mut auto_exposure_meta: ResMut<AutoExposureMeta>,
+) {
+    let render_resources: &dyn RenderResourceContext = &**render_resources;
+    for (entity, buffers) in auto_exposure_meta.histograms.iter_mut() {
+        let mut latest: Option<(u64, Vec<u32>)> = None;
+        for read_back in buffers.read_backs.iter_mut() {
+            match read_back.state {
+                ReadBackState::Free => {}
+                ReadBackState::Copied => {
+                    render_resources.map_buffer_async(read_back.buffer, BufferMapMode::Read);
+                    read_back.state = ReadBackState::Mapping;
+                }
+                ReadBackState::Mapping => {
+                    if !render_resources.is_buffer_mapped(read_back.buffer) {
+                        continue;
+                    }
+                    let histogram = RefCell::new(Vec::with_capacity(HISTOGRAM_BINS));
+                    render_resources.read_mapped_buffer(
+                        read_back.buffer,
+                        0..HISTOGRAM_SIZE,
+                        &|bytes, _| {
+                            histogram.borrow_mut().extend(
+                                bytes.chunks_exact(4).map(|bin| {
+                                    u32::from_ne_bytes([bin[0], bin[1], bin[2], bin[3]])
+                                }),
+                            )
+                        },
+                    );
+                    render_resources.unmap_buffer(read_back.buffer);
+                    read_back.state = ReadBackState::Free;
+                    if latest
+                        .as_ref()
+                        .map_or(true, |(frame, _)| read_back.frame > *frame)
+                    {
+                        latest = Some((read_back.frame, histogram.into_inner()));
+                    }
+                }
+            }
+        }
+        if let Some((_, histogram)) = latest {
+            histograms
+                .histograms
+                .lock()
+                .unwrap()
+                .insert(*entity, histogram);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoExposure, HISTOGRAM_BINS};
+
+    #[test]
+    fn target_ev_averages_the_middle_of_the_histogram() {
+        let auto_exposure = AutoExposure {
+            min_ev: -8.0,
+            max_ev: 8.0,
+            ..Default::default()
+        };
+        // the bins after the black one split the 16 stops evenly
+        let bin_ev = 16.0 / (HISTOGRAM_BINS - 2) as f32;
+        let bin_center = |bin: usize| -8.0 + (bin as f32 - 0.5) * bin_ev;
+
+        let mut histogram = vec![0; HISTOGRAM_BINS];
+        assert_eq!(auto_exposure.target_ev(&histogram), None);
+        histogram[0] = 100;
+        assert_eq!(auto_exposure.target_ev(&histogram), None);
+
+        histogram[128] = 100;
+        let target = auto_exposure.target_ev(&histogram).unwrap();
+        assert!((target - bin_center(128)).abs() < 1e-4);
+
+        // the darkest and brightest tenth are left out
+        histogram[1] = 10;
+        histogram[HISTOGRAM_BINS - 1] = 10;
+        let target = auto_exposure.target_ev(&histogram).unwrap();
+        assert!((target - bin_center(128)).abs() < 1e-4);
+
+        let compensated = AutoExposure {
+            compensation: -1.0,
+            ..auto_exposure.clone()
+        };
+        let target = compensated.target_ev(&histogram).unwrap();
+        assert!((target - (bin_center(128) - 1.0)).abs() < 1e-4);
+    }
+}
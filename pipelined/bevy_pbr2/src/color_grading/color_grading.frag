@@ -0,0 +1,37 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Color;
+layout(set = 0, binding = 1) uniform texture2D t_Lut;
+layout(set = 0, binding = 2) uniform sampler s_Linear;
+
+layout(set = 1, binding = 0) uniform ColorGrading {
+    float Blend;
+};
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+// the lut is a strip of one size x size slice for each blue value, red increases to the right
+// and green downwards within a slice. it is indexed with sRGB encoded colors, like the strips
+// made in image editors, and returns linear colors as its texture is sRGB
+vec3 grade(vec3 color) {
+    float size = float(textureSize(sampler2D(t_Lut, s_Linear), 0).y);
+    vec3 coords = clamp(linear_to_srgb(color), 0.0, 1.0) * (size - 1.0);
+    float slice = floor(coords.b);
+    float next_slice = min(slice + 1.0, size - 1.0);
+    // sampling the centers of the texels keeps neighbouring slices from bleeding in
+    vec2 uv = (coords.rg + 0.5) / vec2(size * size, size);
+    vec3 low = texture(sampler2D(t_Lut, s_Linear), uv + vec2(slice / size, 0.0)).rgb;
+    vec3 high = texture(sampler2D(t_Lut, s_Linear), uv + vec2(next_slice / size, 0.0)).rgb;
+    return mix(low, high, coords.b - slice);
+}
+
+void main() {
+    vec4 color = texelFetch(sampler2D(t_Color, s_Linear), ivec2(gl_FragCoord.xy), 0);
+    o_Target = vec4(mix(color.rgb, grade(color.rgb), Blend), color.a);
+}
@@ -0,0 +1,453 @@
+use crate::render::FULLSCREEN_VERTEX_SHADER;
+use bevy_app::prelude::*;
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    camera::{ActiveCameras, ExtractedCamera},
+    core_pipeline::{self, CameraGraph, Transparent3dPhase, ViewOutputTexture},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+        TextureViewId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{ExtractedView, ExtractedWindows},
+    RenderApp, RenderStage,
+};
+use bevy_utils::HashMap;
+use crevice::std140::AsStd140;
+
+/// Grades the final image of 3d cameras with a [`ColorGrading`] component through a color lookup
+/// table, after tonemapping and every other effect of the view, in the
+/// [`tonemapping_graph`](core_pipeline::tonemapping_graph) so that views drawn after it are drawn
+/// over the graded image. Overlays are drawn after it, so they aren't graded. Must be added after
+/// the
+/// [`CorePipelinePlugin`](bevy_render2::core_pipeline::CorePipelinePlugin).
+#[derive(Debug, Default)]
+pub struct ColorGradingPlugin;
+
+impl ColorGradingPlugin {
+    pub const COLOR_GRADING_NODE: &'static str = "color_grading";
+}
+
+impl Plugin for ColorGradingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ColorGrading>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_color_grading_cameras.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_color_grading.system())
+            .add_system_to_stage(RenderStage::Queue, queue_color_grading.system())
+            .init_render_resource::<ColorGradingShaders>()
+            .init_resource::<ColorGradingMeta>();
+
+        let color_grading_node = ColorGradingNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("color_grading_uniforms", ColorGradingUniformsNode);
+        graph
+            .add_node_edge(
+                "color_grading_uniforms",
+                core_pipeline::node::MAIN_PASS_DEPENDENCIES,
+            )
+            .unwrap();
+        let tonemapping_graph = graph
+            .get_sub_graph_mut(core_pipeline::tonemapping_graph::NAME)
+            .unwrap();
+        tonemapping_graph.add_node(Self::COLOR_GRADING_NODE, color_grading_node);
+        tonemapping_graph
+            .add_node_edge(
+                core_pipeline::tonemapping_graph::node::TONEMAPPING,
+                Self::COLOR_GRADING_NODE,
+            )
+            .unwrap();
+        let input_node_id = tonemapping_graph.input_node().unwrap().id;
+        tonemapping_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::tonemapping_graph::input::VIEW_ENTITY,
+                Self::COLOR_GRADING_NODE,
+                ColorGradingNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+/// Enables color grading for a 3d camera, see [`ColorGradingPlugin`].
+///
+/// The lookup table is a strip of 32 slices of 32 x 32 texels, one slice for each blue value from
+/// left to right, with red increasing to the right and green downwards within a slice. Tables of
+/// other sizes work the same way, as long as the strip is as wide as its height squared. Such
+/// strips are loaded from PNG files, which must keep their default sRGB format, or from `.cube`
+/// files with the [`CubeLutLoader`]. The camera is drawn ungraded until the table is loaded.
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct ColorGrading {
+    pub lut: Handle<Texture>,
+    /// How much of the graded color replaces the original one, from 0.0 to 1.0.
+    pub blend: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        ColorGrading {
+            lut: Default::default(),
+            blend: 1.0,
+        }
+    }
+}
+
+/// The [`ColorGrading`] of a view whose lookup table is loaded.
+pub struct ExtractedColorGrading {
+    pub lut: TextureViewId,
+    pub blend: f32,
+}
+
+pub fn extract_color_grading_cameras(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    textures: Res<Assets<Texture>>,
+    query: Query<&ColorGrading>,
+) {
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(color_grading) = query.get(entity) {
+                let gpu_data = textures
+                    .get(&color_grading.lut)
+                    .and_then(|lut| lut.gpu_data.as_ref());
+                if let Some(gpu_data) = gpu_data {
+                    commands.get_or_spawn(entity).insert(ExtractedColorGrading {
+                        lut: gpu_data.texture_view,
+                        blend: color_grading.blend.max(0.0).min(1.0),
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub struct ColorGradingShaders {
+    pipeline_descriptor: RenderPipelineDescriptor,
+    /// The grading pipeline for each format of the targets of the views.
+    pipelines: HashMap<TextureFormat, PipelineId>,
+    sampler: SamplerId,
+}
+
+impl FromWorld for ColorGradingShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("color_grading.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let mut pipelines = HashMap::default();
+        pipelines.insert(
+            TextureFormat::default(),
+            render_resources.create_render_pipeline(&pipeline_descriptor),
+        );
+
+        ColorGradingShaders {
+            pipeline_descriptor,
+            pipelines,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl ColorGradingShaders {
+    /// Returns the pipeline that grades to targets of `format`, creating it if needed.
+    fn pipeline(
+        &mut self,
+        render_resources: &RenderResources,
+        format: TextureFormat,
+    ) -> PipelineId {
+        let pipeline_descriptor = &self.pipeline_descriptor;
+        *self.pipelines.entry(format).or_insert_with(|| {
+            let mut descriptor = pipeline_descriptor.clone();
+            descriptor.color_target_states[0].format = format;
+            render_resources.create_render_pipeline(&descriptor)
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuColorGrading {
+    blend: f32,
+}
+
+#[derive(Default)]
+pub struct ColorGradingMeta {
+    pub uniforms: DynamicUniformVec<GpuColorGrading>,
+}
+
+/// The ungraded image and settings of a view with [`ColorGrading`].
+pub struct ViewColorGrading {
+    /// The [`ViewOutputTexture`] of the view, which is graded to the target of its camera.
+    pub source: CachedTexture,
+    pub uniform: DynamicUniformIndex,
+}
+
+pub fn prepare_color_grading(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    mut color_grading_meta: ResMut<ColorGradingMeta>,
+    views: Query<
+        (Entity, &ExtractedView, &ExtractedColorGrading),
+        With<RenderPhase<Transparent3dPhase>>,
+    >,
+) {
+    color_grading_meta
+        .uniforms
+        .reserve_and_clear(views.iter().len(), &render_resources);
+    for (entity, view, color_grading) in views.iter() {
+        let source = texture_cache.get(
+            &render_resources,
+            TextureDescriptor {
+                size: Extent3d {
+                    depth_or_array_layers: 1,
+                    width: view.width,
+                    height: view.height,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::default(),
+                usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+            },
+        );
+        let uniform = color_grading_meta.uniforms.push(GpuColorGrading {
+            blend: color_grading.blend,
+        });
+        commands.entity(entity).insert_bundle((
+            ViewOutputTexture {
+                texture: source.texture,
+                view: source.default_view,
+            },
+            ViewColorGrading { source, uniform },
+        ));
+    }
+
+    color_grading_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+struct ColorGradingViewBindGroups {
+    pipeline: PipelineId,
+    textures_bind_group: BindGroupId,
+    uniform_bind_group: BindGroupId,
+}
+
+pub fn queue_color_grading(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    mut color_grading_shaders: ResMut<ColorGradingShaders>,
+    color_grading_meta: Res<ColorGradingMeta>,
+    windows: Res<ExtractedWindows>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedColorGrading,
+        &ViewColorGrading,
+    )>,
+) {
+    for (view_entity, camera, color_grading, view_color_grading) in views.iter() {
+        let format = match windows.get(&camera.window_id) {
+            Some(window) => window.swap_chain_config.format,
+            None => continue,
+        };
+        let layout = &color_grading_shaders.pipeline_descriptor.layout;
+        let textures_bind_group = BindGroupBuilder::default()
+            .add_binding(0, view_color_grading.source.default_view)
+            .add_binding(1, color_grading.lut)
+            .add_binding(2, color_grading_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &textures_bind_group);
+
+        let uniform_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                color_grading_meta
+                    .uniforms
+                    .binding(view_color_grading.uniform.chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(1).id, &uniform_bind_group);
+
+        commands
+            .entity(view_entity)
+            .insert(ColorGradingViewBindGroups {
+                pipeline: color_grading_shaders.pipeline(&render_resources, format),
+                textures_bind_group: textures_bind_group.id,
+                uniform_bind_group: uniform_bind_group.id,
+            });
+    }
+}
+
+/// Writes the [`ColorGradingMeta`] uniforms before the views are drawn.
+pub struct ColorGradingUniformsNode;
+
+impl Node for ColorGradingUniformsNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let color_grading_meta = world.get_resource::<ColorGradingMeta>().unwrap();
+        color_grading_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Grades the [`ViewOutputTexture`] of a view with [`ColorGrading`] to the target of its camera,
+/// within the viewport of the view.
+pub struct ColorGradingNode {
+    query: QueryState<(
+        &'static ExtractedView,
+        &'static ExtractedCamera,
+        &'static ViewColorGrading,
+        &'static ColorGradingViewBindGroups,
+    )>,
+}
+
+impl ColorGradingNode {
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for ColorGradingNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(ColorGradingNode::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view, camera, view_color_grading, bind_groups) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(query) => query,
+                Err(_) => return Ok(()),
+            };
+        let windows = world.get_resource::<ExtractedWindows>().unwrap();
+        let color_grading_shaders = world.get_resource::<ColorGradingShaders>().unwrap();
+        let layout = &color_grading_shaders.pipeline_descriptor.layout;
+        let target = match windows
+            .get(&camera.window_id)
+            .and_then(|window| window.swap_chain_texture)
+        {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(target),
+                resolve_target: None,
+                ops: Operations {
+                    load: core_pipeline::view_window_load_op(
+                        world,
+                        view_entity,
+                        CameraGraph::Draw3d,
+                    ),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                tracked_pass.set_pipeline(bind_groups.pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_group(0).id,
+                    bind_groups.textures_bind_group,
+                    None,
+                );
+                tracked_pass.set_bind_group(
+                    1,
+                    layout.bind_group(1).id,
+                    bind_groups.uniform_bind_group,
+                    Some(&[view_color_grading.uniform.offset]),
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
@@ -1,21 +1,50 @@
+mod auto_exposure;
 mod bundle;
+mod color_grading;
 mod light;
+mod lightmap_bake;
 mod material;
+mod motion_blur;
+mod motion_vectors;
+mod oit;
+mod outline;
+mod planar_reflection;
+mod point_cloud;
 mod render;
+mod scene;
+mod sky;
+mod taa;
+mod terrain;
+mod wireframe;
 
+pub use auto_exposure::*;
 pub use bundle::*;
+pub use color_grading::*;
 pub use light::*;
+pub use lightmap_bake::*;
 pub use material::*;
+pub use motion_blur::*;
+pub use motion_vectors::*;
+pub use oit::*;
+pub use outline::*;
+pub use planar_reflection::*;
+pub use point_cloud::*;
 pub use render::*;
+pub use scene::*;
+pub use sky::*;
+pub use taa::*;
+pub use terrain::*;
+pub use wireframe::*;
 
 use bevy_app::prelude::*;
-use bevy_asset::AddAsset;
+use bevy_asset::{AddAsset, Handle};
 use bevy_ecs::prelude::*;
 use bevy_render2::{
     core_pipeline,
     render_graph::RenderGraph,
-    render_phase::{sort_phase_system, DrawFunctions},
-    RenderStage,
+    render_phase::{AddRenderPhase, DrawFunctions},
+    renderer::RenderInitAppExt,
+    RenderApp, RenderStage,
 };
 
 pub mod draw_3d_graph {
@@ -24,18 +53,63 @@ pub mod draw_3d_graph {
     }
 }
 
+/// The names the pbr resources are published under in the
+/// [`RenderResourceRegistry`](bevy_render2::render_resource::RenderResourceRegistry).
+pub mod registry {
+    /// The depth texture array of the point light shadow maps of a view, one layer per light.
+    pub const SHADOW_MAPS: &'static str = "shadow_maps";
+    /// The comparison sampler the shadow maps are sampled with.
+    pub const SHADOW_SAMPLER: &'static str = "shadow_sampler";
+}
+
 #[derive(Default)]
 pub struct PbrPlugin;
 
 impl Plugin for PbrPlugin {
     fn build(&self, app: &mut App) {
-        app.add_asset::<StandardMaterial>();
+        app.add_asset::<StandardMaterial>()
+            .add_asset::<SplatMaterial>()
+            .register_type::<PointLight>()
+            .register_type::<Exposure>()
+            .register_type::<Lightmap>()
+            .register_type::<Handle<StandardMaterial>>()
+            .register_type::<MaterialPath<StandardMaterial>>()
+            .register_type::<MaterialPath<SplatMaterial>>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                load_material_paths::<StandardMaterial>.system(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                load_material_paths::<SplatMaterial>.system(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                sync_material_paths::<StandardMaterial>.system(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                sync_material_paths::<SplatMaterial>.system(),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, report_material_texture_users.system())
+            .add_system_to_stage(CoreStage::PostUpdate, request_render_system.system());
+        #[cfg(feature = "ron")]
+        app.init_asset_loader::<StandardMaterialLoader>()
+            .init_asset_loader::<SplatMaterialLoader>();
+        let shadow_depth_bias = app
+            .world
+            .get_resource::<ShadowDepthBias>()
+            .cloned()
+            .unwrap_or_default();
 
-        let render_app = app.sub_app_mut(0);
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
+            .insert_resource(shadow_depth_bias)
             .add_system_to_stage(RenderStage::Extract, render::extract_meshes.system())
             .add_system_to_stage(RenderStage::Extract, render::extract_lights.system())
+            .add_system_to_stage(RenderStage::Extract, render::extract_exposures.system())
             .add_system_to_stage(RenderStage::Prepare, render::prepare_meshes.system())
+            .add_system_to_stage(RenderStage::Prepare, render::prepare_mesh_lods.system())
             .add_system_to_stage(
                 RenderStage::Prepare,
                 // this is added as an exclusive system because it contributes new views. it must run (and have Commands applied)
@@ -43,13 +117,10 @@ impl Plugin for PbrPlugin {
                 render::prepare_lights.exclusive_system(),
             )
             .add_system_to_stage(RenderStage::Queue, render::queue_meshes.system())
-            .add_system_to_stage(
-                RenderStage::PhaseSort,
-                sort_phase_system::<ShadowPhase>.system(),
-            )
+            .add_render_phase::<ShadowPhase>()
             .add_system_to_stage(RenderStage::Cleanup, render::cleanup_view_lights.system())
-            .init_resource::<PbrShaders>()
-            .init_resource::<ShadowShaders>()
+            .init_render_resource::<PbrShaders>()
+            .init_render_resource::<ShadowShaders>()
             .init_resource::<MeshMeta>()
             .init_resource::<LightMeta>();
 
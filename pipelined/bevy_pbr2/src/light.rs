@@ -22,3 +22,19 @@ impl Default for PointLight {
         }
     }
 }
+
+/// The exposure of a camera, in stops. The lit color of meshes is scaled by `2^-ev` before it
+/// is tonemapped, so higher values darken the image. Cameras with an
+/// [`AutoExposure`](crate::AutoExposure) adapt it to what they see.
+#[derive(Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
+pub struct Exposure {
+    pub ev: f32,
+}
+
+impl Exposure {
+    /// The factor colors are scaled by.
+    pub fn multiplier(&self) -> f32 {
+        (-self.ev).exp2()
+    }
+}
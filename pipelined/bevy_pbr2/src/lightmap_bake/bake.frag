@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) out uint o_ObjectIndex;
+layout(location = 1) out uint o_MaterialIndex;
+
+layout(set = 0, binding = 0) uniform BakeObject {
+    vec4 LightmapUvRect;
+    uint ObjectIndex;
+    uint MaterialIndex;
+};
+
+void main() {
+    o_ObjectIndex = ObjectIndex;
+    o_MaterialIndex = MaterialIndex;
+}
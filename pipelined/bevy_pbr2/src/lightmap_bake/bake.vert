@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 7) in vec2 Vertex_Uv_1;
+
+layout(set = 0, binding = 0) uniform BakeObject {
+    vec4 LightmapUvRect;
+    uint ObjectIndex;
+    uint MaterialIndex;
+};
+
+void main() {
+    vec2 lightmap_uv = Vertex_Uv_1 * LightmapUvRect.zw + LightmapUvRect.xy;
+    // the lightmap is the target: uvs start at its top left, clip space at its bottom left
+    gl_Position = vec4(lightmap_uv.x * 2.0 - 1.0, 1.0 - lightmap_uv.y * 2.0, 0.0, 1.0);
+}
@@ -0,0 +1,602 @@
+use crate::{
+    render::{ExtractedMeshLevel, MeshPipelines},
+    Lightmap, StandardMaterial,
+};
+use bevy_app::prelude::*;
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_math::Vec4;
+use bevy_render2::{
+    color::Color,
+    core_pipeline,
+    mesh::Mesh,
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_phase::TrackedRenderPass,
+    render_resource::{
+        BindGroupBuilder, BindGroupId, BufferId, BufferInfo, BufferMapMode, BufferUsage,
+        DynamicUniformIndex, DynamicUniformVec,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResourceContext, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::{
+        CachedTexture, Extent3d, Texture, TextureCache, TextureDescriptor, TextureDimension,
+        TextureFormat, TextureUsage,
+    },
+    RenderApp, RenderStage,
+};
+use bevy_utils::HashMap;
+use crevice::std140::AsStd140;
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
+
+/// The format of the index textures, see [`LightmapBakeData`].
+const INDEX_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+/// Renders the objects of a lightmap into its texels on request, so external lightmappers can map
+/// the light they bake back to entities and materials, see [`LightmapBakes`]. This is opt-in: it
+/// is not part of the default plugins, and must be added after [`crate::PbrPlugin`].
+///
+/// The objects of a lightmap are the meshes with a [`Lightmap`] of its image, drawn at their
+/// [`Mesh::ATTRIBUTE_UV_1`] transformed by the uv offset and scale of their [`Lightmap`]. Meshes
+/// without lightmap uvs, or that aren't triangle lists, are left out.
+#[derive(Debug, Default)]
+pub struct LightmapBakePlugin;
+
+impl LightmapBakePlugin {
+    pub const BAKE_NODE: &'static str = "lightmap_bake";
+}
+
+impl Plugin for LightmapBakePlugin {
+    fn build(&self, app: &mut App) {
+        let bakes = LightmapBakes::default();
+        app.insert_resource(bakes.clone());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(bakes)
+            .add_system_to_stage(RenderStage::Extract, extract_lightmap_bakes.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_lightmap_bakes.system())
+            .add_system_to_stage(RenderStage::Queue, queue_lightmap_bakes.system())
+            .add_system_to_stage(RenderStage::Cleanup, read_lightmap_bakes.system())
+            .init_render_resource::<LightmapBakeShaders>()
+            .init_resource::<LightmapBakeMeta>();
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(Self::BAKE_NODE, LightmapBakeNode);
+        graph
+            .add_node_edge(Self::BAKE_NODE, core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+    }
+}
+
+/// The data of a lightmap rendered by the [`LightmapBakePlugin`].
+///
+/// The index textures have the size of the request and the [`TextureFormat::R32Uint`] format,
+/// with their rows starting at the top of the lightmap, like its uvs. A texel holds the index of
+/// the object or material covering it plus one, or `0` if no object covers it. Where the uvs of
+/// objects overlap, the texel holds the object drawn last.
+#[derive(Debug, Clone)]
+pub struct LightmapBakeData {
+    pub object_indices: Texture,
+    pub material_indices: Texture,
+    /// The entity of each object index. Indices are stable: an entity keeps its index in every
+    /// bake, and the indices of despawned entities aren't reused.
+    pub objects: Vec<Entity>,
+    /// The weak handle of the material of each material index, which are as stable as the object
+    /// indices.
+    pub materials: Vec<Handle<StandardMaterial>>,
+}
+
+impl LightmapBakeData {
+    /// Returns the entity covering the texel at `x`, `y`, if any.
+    pub fn object(&self, x: u32, y: u32) -> Option<Entity> {
+        let index = texel_index(&self.object_indices, x, y)?;
+        self.objects.get(index).copied()
+    }
+
+    /// Returns the material of the entity covering the texel at `x`, `y`, if any.
+    pub fn material(&self, x: u32, y: u32) -> Option<&Handle<StandardMaterial>> {
+        let index = texel_index(&self.material_indices, x, y)?;
+        self.materials.get(index)
+    }
+
+    /// Returns whether an object covers the texel at `x`, `y`, i.e. whether the lightmapper needs
+    /// to bake it.
+    pub fn is_covered(&self, x: u32, y: u32) -> bool {
+        texel_index(&self.object_indices, x, y).is_some()
+    }
+}
+
+/// Returns the index stored in the texel at `x`, `y`, or `None` if it is outside of the texture
+/// or not covered.
+fn texel_index(texture: &Texture, x: u32, y: u32) -> Option<usize> {
+    if x >= texture.size.width || y >= texture.size.height {
+        return None;
+    }
+    let offset = (y as usize * texture.size.width as usize + x as usize) * 4;
+    let texel = &texture.data[offset..offset + 4];
+    let value = u32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]);
+    (value as usize).checked_sub(1)
+}
+
+struct LightmapBakeRequest {
+    lightmap: Handle<Texture>,
+    size: Extent3d,
+}
+
+struct PendingBake {
+    lightmap: Handle<Texture>,
+    size: Extent3d,
+    bytes_per_row: usize,
+    object_indices: BufferId,
+    material_indices: BufferId,
+}
+
+/// The indices assigned to the entities and materials of the baked objects.
+#[derive(Default)]
+struct BakeIndices {
+    objects: Vec<Entity>,
+    object_indices: HashMap<Entity, u32>,
+    materials: Vec<Handle<StandardMaterial>>,
+    material_indices: HashMap<Handle<StandardMaterial>, u32>,
+}
+
+impl BakeIndices {
+    fn object(&mut self, entity: Entity) -> u32 {
+        let objects = &mut self.objects;
+        *self.object_indices.entry(entity).or_insert_with(|| {
+            objects.push(entity);
+            objects.len() as u32 - 1
+        })
+    }
+
+    fn material(&mut self, material: &Handle<StandardMaterial>) -> u32 {
+        let materials = &mut self.materials;
+        *self
+            .material_indices
+            .entry(material.clone_weak())
+            .or_insert_with(|| {
+                materials.push(material.clone_weak());
+                materials.len() as u32 - 1
+            })
+    }
+}
+
+#[derive(Default)]
+struct BakeState {
+    requested: Vec<LightmapBakeRequest>,
+    pending: Vec<PendingBake>,
+    baked: HashMap<Handle<Texture>, LightmapBakeData>,
+    indices: BakeIndices,
+}
+
+/// Requests and receives the data of lightmaps for external lightmappers, see
+/// [`LightmapBakePlugin`]. This resource exists in both the app world and the render world, and
+/// both share the same state.
+#[derive(Clone, Default)]
+pub struct LightmapBakes {
+    state: Arc<Mutex<BakeState>>,
+}
+
+impl LightmapBakes {
+    /// Renders the objects of the lightmap `lightmap` into textures of `width` by `height` texels
+    /// in the next rendered frame in which the meshes of all its objects are ready. The image of
+    /// the lightmap doesn't have to exist, the handle only identifies its objects.
+    pub fn request(&self, lightmap: Handle<Texture>, width: u32, height: u32) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .requested
+            .retain(|request| request.lightmap != lightmap);
+        state.requested.push(LightmapBakeRequest {
+            lightmap,
+            size: Extent3d::new(width.max(1), height.max(1), 1),
+        });
+    }
+
+    /// Returns the last data rendered for `lightmap`, if it hasn't been taken yet.
+    pub fn take(&self, lightmap: &Handle<Texture>) -> Option<LightmapBakeData> {
+        self.state.lock().unwrap().baked.remove(lightmap)
+    }
+}
+
+pub struct LightmapBakeShaders {
+    /// The pipelines for each [`MeshVertexBuffer`](crate::MeshVertexBuffer) with lightmap uvs.
+    pipelines: MeshPipelines,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for LightmapBakeShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("bake.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("bake.frag"))
+            .get_spirv_shader(None)
+            .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let index_target = ColorTargetState {
+            format: INDEX_FORMAT,
+            blend: None,
+            write_mask: ColorWrite::ALL,
+        };
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                // the winding of the uvs doesn't follow the winding of the mesh
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![index_target.clone(), index_target],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+
+        LightmapBakeShaders {
+            pipelines: MeshPipelines::new(pipeline_descriptor.clone()),
+            pipeline_descriptor,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuBakeObject {
+    /// The offset of the lightmap uvs in `xy` and their scale in `zw`.
+    uv_rect: Vec4,
+    /// The values written to the index textures, the indices plus one.
+    object_index: u32,
+    material_index: u32,
+}
+
+struct ExtractedBakeObject {
+    level: ExtractedMeshLevel,
+    uniform: GpuBakeObject,
+    binding: DynamicUniformIndex,
+}
+
+struct BakeTargets {
+    object_indices: CachedTexture,
+    material_indices: CachedTexture,
+}
+
+struct ExtractedLightmapBake {
+    lightmap: Handle<Texture>,
+    size: Extent3d,
+    objects: Vec<ExtractedBakeObject>,
+    targets: Option<BakeTargets>,
+}
+
+/// The lightmaps baked this frame.
+#[derive(Default)]
+pub struct ExtractedLightmapBakes {
+    bakes: Vec<ExtractedLightmapBake>,
+}
+
+pub fn extract_lightmap_bakes(
+    mut commands: Commands,
+    lightmap_bakes: Res<LightmapBakes>,
+    meshes: Res<Assets<Mesh>>,
+    query: Query<(Entity, &Handle<Mesh>, &Handle<StandardMaterial>, &Lightmap)>,
+) {
+    let mut state = lightmap_bakes.state.lock().unwrap();
+    let state = &mut *state;
+    let mut bakes = Vec::new();
+    for request in std::mem::take(&mut state.requested) {
+        let mut objects = Vec::new();
+        let mut ready = true;
+        for (entity, mesh_handle, material, lightmap) in query.iter() {
+            if lightmap.image != request.lightmap {
+                continue;
+            }
+            let mesh = match meshes.get(mesh_handle) {
+                Some(mesh) => mesh,
+                None => {
+                    ready = false;
+                    break;
+                }
+            };
+            if mesh.attribute(Mesh::ATTRIBUTE_UV_1).is_none() {
+                continue;
+            }
+            let level = match ExtractedMeshLevel::base(mesh) {
+                Ok(Some(level)) => level,
+                // the mesh can't be drawn with the pbr shaders, which extract_meshes logs
+                Err(_) => continue,
+                Ok(None) => {
+                    ready = false;
+                    break;
+                }
+            };
+            if !level.key.is_triangle_list() {
+                continue;
+            }
+            objects.push(ExtractedBakeObject {
+                level,
+                uniform: GpuBakeObject {
+                    uv_rect: Vec4::new(
+                        lightmap.uv_offset.x,
+                        lightmap.uv_offset.y,
+                        lightmap.uv_scale.x,
+                        lightmap.uv_scale.y,
+                    ),
+                    object_index: state.indices.object(entity) + 1,
+                    material_index: state.indices.material(material) + 1,
+                },
+                binding: DynamicUniformIndex::default(),
+            });
+        }
+        // the bake waits for the meshes of its objects to be uploaded
+        if ready {
+            bakes.push(ExtractedLightmapBake {
+                lightmap: request.lightmap,
+                size: request.size,
+                objects,
+                targets: None,
+            });
+        } else {
+            state.requested.push(request);
+        }
+    }
+    commands.insert_resource(ExtractedLightmapBakes { bakes });
+}
+
+#[derive(Default)]
+pub struct LightmapBakeMeta {
+    uniforms: DynamicUniformVec<GpuBakeObject>,
+    /// One bind group per chunk of the uniforms.
+    bind_groups: Vec<BindGroupId>,
+}
+
+pub fn prepare_lightmap_bakes(
+    render_resources: Res<RenderResources>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut meta: ResMut<LightmapBakeMeta>,
+    mut extracted_bakes: ResMut<ExtractedLightmapBakes>,
+) {
+    let object_count = extracted_bakes
+        .bakes
+        .iter()
+        .map(|bake| bake.objects.len())
+        .sum();
+    meta.uniforms
+        .reserve_and_clear(object_count, &render_resources);
+    for bake in extracted_bakes.bakes.iter_mut() {
+        let descriptor = TextureDescriptor {
+            size: bake.size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: INDEX_FORMAT,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::COPY_SRC,
+        };
+        bake.targets = Some(BakeTargets {
+            object_indices: texture_cache.get(&render_resources, descriptor.clone()),
+            material_indices: texture_cache.get(&render_resources, descriptor),
+        });
+        for object in bake.objects.iter_mut() {
+            object.binding = meta.uniforms.push(object.uniform);
+        }
+    }
+    meta.uniforms.write_to_staging_buffer(&render_resources);
+}
+
+pub fn queue_lightmap_bakes(
+    render_resources: Res<RenderResources>,
+    mut shaders: ResMut<LightmapBakeShaders>,
+    mut meta: ResMut<LightmapBakeMeta>,
+    extracted_bakes: Res<ExtractedLightmapBakes>,
+) {
+    for bake in extracted_bakes.bakes.iter() {
+        for object in bake.objects.iter() {
+            shaders
+                .pipelines
+                .specialize(&render_resources, object.level.key.vertex_buffer);
+        }
+    }
+    let layout = &shaders.pipeline_descriptor.layout;
+    meta.bind_groups = (0..meta.uniforms.chunk_count())
+        .map(|chunk| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, meta.uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(layout.bind_group(0).id, &bind_group);
+            bind_group.id
+        })
+        .collect();
+}
+
+/// Renders the lightmaps baked this frame and copies their index textures to buffers.
+pub struct LightmapBakeNode;
+
+impl Node for LightmapBakeNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let extracted_bakes = world.get_resource::<ExtractedLightmapBakes>().unwrap();
+        if extracted_bakes.bakes.is_empty() {
+            return Ok(());
+        }
+        let shaders = world.get_resource::<LightmapBakeShaders>().unwrap();
+        let meta = world.get_resource::<LightmapBakeMeta>().unwrap();
+        let lightmap_bakes = world.get_resource::<LightmapBakes>().unwrap();
+        meta.uniforms.write_to_uniform_buffer(render_context);
+
+        let layout = &shaders.pipeline_descriptor.layout;
+        let mut state = lightmap_bakes.state.lock().unwrap();
+        for bake in extracted_bakes.bakes.iter() {
+            let targets = match &bake.targets {
+                Some(targets) => targets,
+                None => continue,
+            };
+            let index_attachment = |texture: &CachedTexture| RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(texture.default_view),
+                resolve_target: None,
+                // texels no object covers are left at 0
+                ops: Operations {
+                    load: LoadOp::Clear(Color::NONE),
+                    store: true,
+                },
+            };
+            let pass_descriptor = PassDescriptor {
+                color_attachments: vec![
+                    index_attachment(&targets.object_indices),
+                    index_attachment(&targets.material_indices),
+                ],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
+            render_context.begin_render_pass(
+                &pass_descriptor,
+                &mut |render_pass: &mut dyn RenderPass| {
+                    let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                    for object in bake.objects.iter() {
+                        tracked_pass
+                            .set_pipeline(shaders.pipelines.get(object.level.key.vertex_buffer));
+                        tracked_pass.set_bind_group(
+                            0,
+                            layout.bind_group(0).id,
+                            meta.bind_groups[object.binding.chunk],
+                            Some(&[object.binding.offset]),
+                        );
+                        object.level.draw(&mut tracked_pass);
+                    }
+                },
+            );
+
+            let bytes_per_row = render_context
+                .resources()
+                .get_aligned_texture_size(bake.size.width as usize * INDEX_FORMAT.pixel_size());
+            let mut copy_to_buffer = |texture: &CachedTexture| {
+                let buffer = render_context.resources().create_buffer(BufferInfo {
+                    size: bytes_per_row * bake.size.height as usize,
+                    buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+                    ..Default::default()
+                });
+                render_context.copy_texture_to_buffer(
+                    texture.texture,
+                    [0, 0, 0],
+                    0,
+                    buffer,
+                    0,
+                    bytes_per_row as u32,
+                    bake.size,
+                );
+                buffer
+            };
+            let object_indices = copy_to_buffer(&targets.object_indices);
+            let material_indices = copy_to_buffer(&targets.material_indices);
+            state.pending.push(PendingBake {
+                lightmap: bake.lightmap.clone(),
+                size: bake.size,
+                bytes_per_row,
+                object_indices,
+                material_indices,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads back the lightmaps baked this frame. Runs in [`RenderStage::Cleanup`], after the frame
+/// was submitted.
+pub fn read_lightmap_bakes(
+    lightmap_bakes: Res<LightmapBakes>,
+    render_resources: Res<RenderResources>,
+    extracted_bakes: Res<ExtractedLightmapBakes>,
+) {
+    let render_resources: &dyn RenderResourceContext = &**render_resources;
+    let mut state = lightmap_bakes.state.lock().unwrap();
+    let state = &mut *state;
+    // the graph doesn't run in paused frames, their bakes are requested again
+    for bake in extracted_bakes.bakes.iter() {
+        let pending = state
+            .pending
+            .iter()
+            .any(|pending| pending.lightmap == bake.lightmap);
+        let requested = state
+            .requested
+            .iter()
+            .any(|request| request.lightmap == bake.lightmap);
+        if !pending && !requested {
+            state.requested.push(LightmapBakeRequest {
+                lightmap: bake.lightmap.clone(),
+                size: bake.size,
+            });
+        }
+    }
+
+    for pending in std::mem::take(&mut state.pending) {
+        let data = LightmapBakeData {
+            object_indices: read_index_texture(
+                render_resources,
+                pending.object_indices,
+                pending.bytes_per_row,
+                pending.size,
+            ),
+            material_indices: read_index_texture(
+                render_resources,
+                pending.material_indices,
+                pending.bytes_per_row,
+                pending.size,
+            ),
+            objects: state.indices.objects.clone(),
+            materials: state.indices.materials.clone(),
+        };
+        state.baked.insert(pending.lightmap, data);
+    }
+}
+
+/// Reads an index texture copied to `buffer`, and removes the buffer.
+fn read_index_texture(
+    render_resources: &dyn RenderResourceContext,
+    buffer: BufferId,
+    bytes_per_row: usize,
+    size: Extent3d,
+) -> Texture {
+    let row_size = size.width as usize * INDEX_FORMAT.pixel_size();
+    let data = RefCell::new(Vec::with_capacity(row_size * size.height as usize));
+    render_resources.map_buffer(buffer, BufferMapMode::Read);
+    render_resources.read_mapped_buffer(
+        buffer,
+        0..(bytes_per_row * size.height as usize) as u64,
+        &|bytes, _| {
+            let mut data = data.borrow_mut();
+            // drop the padding of each row
+            for row in bytes.chunks(bytes_per_row) {
+                data.extend_from_slice(&row[..row_size]);
+            }
+        },
+    );
+    render_resources.unmap_buffer(buffer);
+    render_resources.remove_buffer(buffer);
+    Texture::new(size, TextureDimension::D2, data.into_inner(), INDEX_FORMAT)
+}
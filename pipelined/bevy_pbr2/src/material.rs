@@ -1,10 +1,69 @@
-use bevy_reflect::{Reflect, TypeUuid};
-use bevy_render2::color::Color;
+use crate::{Exposure, PointLight};
+use bevy_asset::{AssetEvent, Assets, Handle};
+use bevy_ecs::{prelude::*, reflect::ReflectComponent};
+use bevy_math::Vec2;
+use bevy_reflect::{Reflect, ReflectDeserialize, TypeUuid};
+use bevy_render2::{
+    color::Color,
+    render_mode::RequestRender,
+    texture::{Texture, TextureUsers},
+};
+use bevy_transform::components::GlobalTransform;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Clone, TypeUuid, Reflect)]
+#[derive(Debug, Clone, TypeUuid, Reflect, Serialize, Deserialize)]
 #[uuid = "7494888b-c082-457b-aacf-517228cc0c22"]
 pub struct StandardMaterial {
     pub color: Color,
+    /// The phase of the main pass meshes with the material are drawn in. If it is `None`, they
+    /// are drawn in [`MeshPhase::Transparent`] if the alpha of `color` is below 1.0, and in
+    /// [`MeshPhase::Opaque`] otherwise.
+    pub phase: Option<MeshPhase>,
+    /// In [`MeshPhase::AlphaMask`], the fragments whose alpha, the alpha of `color` multiplied by
+    /// the vertex color, is below the cutoff are discarded, and the others are drawn opaque.
+    /// Meshes with a [`SplatMaterial`] aren't masked.
+    pub alpha_cutoff: f32,
+}
+
+impl Default for StandardMaterial {
+    fn default() -> Self {
+        StandardMaterial {
+            color: Color::default(),
+            phase: None,
+            alpha_cutoff: 0.5,
+        }
+    }
+}
+
+impl StandardMaterial {
+    /// Returns the phase meshes with the material are drawn in, see [`StandardMaterial::phase`].
+    pub fn phase(&self) -> MeshPhase {
+        self.phase.unwrap_or(if self.color.a() < 1.0 {
+            MeshPhase::Transparent
+        } else {
+            MeshPhase::Opaque
+        })
+    }
+}
+
+/// The phase of the main pass a mesh is drawn in, chosen by its [`StandardMaterial`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+#[reflect_value(Serialize, Deserialize, PartialEq, Hash)]
+pub enum MeshPhase {
+    /// The [`Opaque3dPhase`](bevy_render2::core_pipeline::Opaque3dPhase).
+    Opaque,
+    /// The [`AlphaMask3dPhase`](bevy_render2::core_pipeline::AlphaMask3dPhase), drawn with
+    /// [`crate::PbrVariant::AlphaMask`] to discard the fragments below
+    /// [`StandardMaterial::alpha_cutoff`].
+    AlphaMask,
+    /// The [`Transparent3dPhase`](bevy_render2::core_pipeline::Transparent3dPhase). With
+    /// [`crate::OrderIndependentTransparency`], meshes of triangles are drawn by its pass instead.
+    Transparent,
+    /// The [`Overlay3dPhase`](bevy_render2::core_pipeline::Overlay3dPhase).
+    Overlay,
+    /// None of the main pass phases. The mesh is still extracted and casts shadows, and a plugin
+    /// can queue it in a phase of its own, e.g. with [`crate::DrawPbr`].
+    Custom,
 }
 
 impl From<Color> for StandardMaterial {
@@ -15,3 +74,110 @@ impl From<Color> for StandardMaterial {
         }
     }
 }
+
+/// A material that blends up to 4 layer colors, weighted by the red, green, blue and alpha
+/// channels of a splat map texture sampled with the mesh uvs. This is typically used for
+/// [`crate::Terrain`], to paint e.g. grass, rock, sand and snow. Splat maps without an alpha
+/// channel are loaded with an opaque alpha, which gives the fourth layer full weight everywhere.
+///
+/// An entity with a `Handle<SplatMaterial>` is drawn with it instead of its
+/// [`StandardMaterial`]. Until the material and its splat map are loaded, the entity is drawn
+/// with the magenta checkerboard of [`crate::PbrVariant::Error`]. It is stored in files as a
+/// [`crate::SplatMaterialDescriptor`].
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "d6b8f2b4-5d07-4a3c-9a8e-3c1f1e2a7b51"]
+pub struct SplatMaterial {
+    pub splat_map: Handle<Texture>,
+    pub layers: [Color; 4],
+}
+
+/// Baked indirect lighting for a mesh entity, sampled with the [`Mesh::ATTRIBUTE_UV_1`] of its
+/// mesh and used instead of the constant ambient light. Lightmaps of several meshes are usually
+/// packed in one texture, so the uvs are scaled by `uv_scale` and offset by `uv_offset` to the
+/// area of the mesh.
+///
+/// The lightmap is ignored by meshes without [`Mesh::ATTRIBUTE_UV_1`], like levels of a
+/// [`Lod`] that don't have them, by meshes with a [`SplatMaterial`] and by meshes in
+/// [`MeshPhase::AlphaMask`].
+///
+/// [`Mesh::ATTRIBUTE_UV_1`]: bevy_render2::mesh::Mesh::ATTRIBUTE_UV_1
+/// [`Lod`]: bevy_render2::mesh::Lod
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Lightmap {
+    pub image: Handle<Texture>,
+    pub uv_offset: Vec2,
+    pub uv_scale: Vec2,
+    /// The factor the baked light is scaled by.
+    pub intensity: f32,
+}
+
+impl Default for Lightmap {
+    fn default() -> Self {
+        Lightmap {
+            image: Default::default(),
+            uv_offset: Vec2::ZERO,
+            uv_scale: Vec2::ONE,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Reports the splat maps of [`SplatMaterial`]s and the images of [`Lightmap`]s as used by their
+/// entities, so a [`TextureStreamingPlugin`](bevy_render2::texture::TextureStreamingPlugin)
+/// streams them in like textures of `Handle<Texture>` components.
+pub fn report_material_texture_users(
+    texture_users: Option<ResMut<TextureUsers>>,
+    splat_materials: Res<Assets<SplatMaterial>>,
+    splats: Query<(&Handle<SplatMaterial>, &GlobalTransform)>,
+    lightmaps: Query<(&Lightmap, &GlobalTransform)>,
+) {
+    let mut texture_users = match texture_users {
+        Some(texture_users) => texture_users,
+        None => return,
+    };
+    for (material, transform) in splats.iter() {
+        if let Some(material) = splat_materials.get(material) {
+            texture_users.add(&material.splat_map, transform.translation);
+        }
+    }
+    for (lightmap, transform) in lightmaps.iter() {
+        texture_users.add(&lightmap.image, transform.translation);
+    }
+}
+
+/// Sends a [`RequestRender`] when a material, a light or an exposure is added, changed or
+/// removed, which the [`RenderMode::OnDemand`](bevy_render2::render_mode::RenderMode::OnDemand)
+/// can't detect by itself.
+#[allow(clippy::type_complexity)]
+pub fn request_render_system(
+    mut request_render: EventWriter<RequestRender>,
+    mut material_events: EventReader<AssetEvent<StandardMaterial>>,
+    mut splat_material_events: EventReader<AssetEvent<SplatMaterial>>,
+    changed: Query<
+        (),
+        Or<(
+            Changed<Handle<StandardMaterial>>,
+            Changed<Handle<SplatMaterial>>,
+            Changed<Lightmap>,
+            Changed<PointLight>,
+            Changed<Exposure>,
+        )>,
+    >,
+    removed_materials: RemovedComponents<Handle<StandardMaterial>>,
+    removed_splat_materials: RemovedComponents<Handle<SplatMaterial>>,
+    removed_lightmaps: RemovedComponents<Lightmap>,
+    removed_lights: RemovedComponents<PointLight>,
+) {
+    // every reader is drained, so events of earlier frames don't request this one
+    let events = material_events.iter().count() + splat_material_events.iter().count();
+    if events > 0
+        || changed.iter().next().is_some()
+        || removed_materials.iter().next().is_some()
+        || removed_splat_materials.iter().next().is_some()
+        || removed_lightmaps.iter().next().is_some()
+        || removed_lights.iter().next().is_some()
+    {
+        request_render.send(RequestRender);
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{
+    motion_vectors::{self, add_motion_vector_pass, MotionVectors, ViewMotionVectors},
+    render::FULLSCREEN_VERTEX_SHADER,
+    taa, TemporalAntiAliasing,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    camera::ActiveCameras,
+    core_pipeline::{self, CorePipelineSettings, ViewMainTexture},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::TrackedRenderPass,
+    render_resource::{
+        BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::ExtractedView,
+    RenderApp, RenderStage,
+};
+use crevice::std140::AsStd140;
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const MOTION_BLUR: &'static str = "motion_blur";
+    }
+}
+
+/// Blurs the image of cameras with a [`MotionBlur`] component along the motion of each pixel
+/// since the previous frame. Must be added after [`crate::PbrPlugin`], and after
+/// [`crate::OitPlugin`], [`crate::WireframePlugin`] and [`crate::TaaPlugin`] if they are used.
+#[derive(Debug, Default)]
+pub struct MotionBlurPlugin;
+
+impl Plugin for MotionBlurPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MotionBlur>();
+        add_motion_vector_pass(app);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_motion_blur_cameras.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_motion_blur.system())
+            .add_system_to_stage(RenderStage::Queue, queue_motion_blur.system())
+            .init_render_resource::<MotionBlurShaders>()
+            .init_resource::<MotionBlurMeta>();
+
+        let motion_blur_node = MotionBlurNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("motion_blur", MotionBlurUniformsNode);
+        graph
+            .add_node_edge("motion_blur", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::MOTION_BLUR, motion_blur_node);
+        draw_3d_graph
+            .add_node_edge(
+                motion_vectors::draw_3d_graph::node::MOTION_VECTOR_PASS,
+                draw_3d_graph::node::MOTION_BLUR,
+            )
+            .unwrap();
+        for node in [
+            crate::oit::draw_3d_graph::node::OIT_PASS,
+            crate::wireframe::draw_3d_graph::node::WIREFRAME_PASS,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(*node, draw_3d_graph::node::MOTION_BLUR)
+                    .unwrap();
+            }
+        }
+        // the temporal anti-aliasing resolve reads the blurred main texture
+        if draw_3d_graph
+            .get_node_state(taa::draw_3d_graph::node::TAA_RESOLVE)
+            .is_ok()
+        {
+            draw_3d_graph
+                .add_node_edge(
+                    draw_3d_graph::node::MOTION_BLUR,
+                    taa::draw_3d_graph::node::TAA_RESOLVE,
+                )
+                .unwrap();
+        }
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::MOTION_BLUR,
+                MotionBlurNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::OUTPUT_TARGET,
+                draw_3d_graph::node::MOTION_BLUR,
+                MotionBlurNode::IN_OUTPUT_TARGET,
+            )
+            .unwrap();
+    }
+}
+
+/// Enables motion blur for a 3d camera, see [`MotionBlurPlugin`].
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MotionBlur {
+    /// The number of samples taken along the motion of each pixel. Higher values give smoother
+    /// blur, but are slower.
+    pub samples: u32,
+    /// The length of the blur, as a fraction of the motion since the previous frame.
+    pub intensity: f32,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        MotionBlur {
+            samples: 8,
+            intensity: 0.5,
+        }
+    }
+}
+
+pub fn extract_motion_blur_cameras(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    query: Query<&MotionBlur>,
+) {
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(motion_blur) = query.get(entity) {
+                commands
+                    .get_or_spawn(entity)
+                    .insert_bundle((motion_blur.clone(), MotionVectors));
+            }
+        }
+    }
+}
+
+pub struct MotionBlurShaders {
+    pipeline: PipelineId,
+    pipeline_descriptor: RenderPipelineDescriptor,
+    sampler: SamplerId,
+}
+
+impl FromWorld for MotionBlurShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("motion_blur.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: settings.main_texture_format(),
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+
+        MotionBlurShaders {
+            pipeline,
+            pipeline_descriptor,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuMotionBlur {
+    samples: u32,
+    intensity: f32,
+}
+
+#[derive(Default)]
+pub struct MotionBlurMeta {
+    pub uniforms: DynamicUniformVec<GpuMotionBlur>,
+}
+
+/// The textures and settings of a view with [`MotionBlur`].
+pub struct ViewMotionBlur {
+    /// A copy of the [`ViewMainTexture`] of the view, which the blur reads from.
+    pub source: CachedTexture,
+    pub uniform: DynamicUniformIndex,
+}
+
+pub fn prepare_motion_blur(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    settings: Res<CorePipelineSettings>,
+    mut motion_blur_meta: ResMut<MotionBlurMeta>,
+    views: Query<(Entity, &ExtractedView, &MotionBlur)>,
+) {
+    motion_blur_meta
+        .uniforms
+        .reserve_and_clear(views.iter().len(), &render_resources);
+    for (entity, view, motion_blur) in views.iter() {
+        let source = texture_cache.get(
+            &render_resources,
+            TextureDescriptor {
+                size: Extent3d {
+                    depth_or_array_layers: 1,
+                    width: view.width,
+                    height: view.height,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: settings.main_texture_format(),
+                usage: TextureUsage::COPY_DST | TextureUsage::SAMPLED,
+            },
+        );
+        let uniform = motion_blur_meta.uniforms.push(GpuMotionBlur {
+            samples: motion_blur.samples.max(1),
+            intensity: motion_blur.intensity,
+        });
+        commands
+            .entity(entity)
+            .insert(ViewMotionBlur { source, uniform });
+    }
+
+    motion_blur_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+struct MotionBlurViewBindGroups {
+    textures_bind_group: BindGroupId,
+    uniform_bind_group: BindGroupId,
+}
+
+pub fn queue_motion_blur(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    motion_blur_shaders: Res<MotionBlurShaders>,
+    motion_blur_meta: Res<MotionBlurMeta>,
+    views: Query<(Entity, &ViewMotionBlur, &ViewMotionVectors)>,
+) {
+    let layout = &motion_blur_shaders.pipeline_descriptor.layout;
+    for (view_entity, view_motion_blur, motion_vectors) in views.iter() {
+        let textures_bind_group = BindGroupBuilder::default()
+            .add_binding(0, view_motion_blur.source.default_view)
+            .add_binding(1, motion_vectors.texture)
+            .add_binding(2, motion_blur_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &textures_bind_group);
+
+        let uniform_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                motion_blur_meta
+                    .uniforms
+                    .binding(view_motion_blur.uniform.chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(1).id, &uniform_bind_group);
+
+        commands
+            .entity(view_entity)
+            .insert(MotionBlurViewBindGroups {
+                textures_bind_group: textures_bind_group.id,
+                uniform_bind_group: uniform_bind_group.id,
+            });
+    }
+}
+
+/// Writes the [`MotionBlurMeta`] uniforms before the views are drawn.
+pub struct MotionBlurUniformsNode;
+
+impl Node for MotionBlurUniformsNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let motion_blur_meta = world.get_resource::<MotionBlurMeta>().unwrap();
+        motion_blur_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Copies the main texture of a view and blurs it along its motion vectors. The result is
+/// written back to the main texture when the view has [`TemporalAntiAliasing`], which resolves
+/// it to the output target, and to the output target otherwise.
+pub struct MotionBlurNode {
+    query: QueryState<(
+        &'static ExtractedView,
+        &'static ViewMainTexture,
+        &'static ViewMotionBlur,
+        &'static MotionBlurViewBindGroups,
+        Option<&'static TemporalAntiAliasing>,
+    )>,
+}
+
+impl MotionBlurNode {
+    pub const IN_OUTPUT_TARGET: &'static str = "output_target";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for MotionBlurNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(MotionBlurNode::IN_OUTPUT_TARGET, SlotType::TextureView),
+            SlotInfo::new(MotionBlurNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view, main_texture, view_motion_blur, bind_groups, taa) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(view) => view,
+                Err(_) => return Ok(()),
+            };
+
+        render_context.copy_texture_to_texture(
+            main_texture.texture,
+            [0, 0, 0],
+            0,
+            view_motion_blur.source.texture,
+            [0, 0, 0],
+            0,
+            Extent3d {
+                depth_or_array_layers: 1,
+                width: view.width,
+                height: view.height,
+            },
+        );
+
+        let target = if taa.is_some() {
+            main_texture.view
+        } else {
+            graph.get_input_texture(Self::IN_OUTPUT_TARGET)?
+        };
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(target),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+
+        let motion_blur_shaders = world.get_resource::<MotionBlurShaders>().unwrap();
+        let layout = &motion_blur_shaders.pipeline_descriptor.layout;
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                tracked_pass.set_pipeline(motion_blur_shaders.pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_group(0).id,
+                    bind_groups.textures_bind_group,
+                    None,
+                );
+                tracked_pass.set_bind_group(
+                    1,
+                    layout.bind_group(1).id,
+                    bind_groups.uniform_bind_group,
+                    Some(&[view_motion_blur.uniform.offset]),
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
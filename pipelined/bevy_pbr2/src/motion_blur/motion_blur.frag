@@ -0,0 +1,27 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Color;
+layout(set = 0, binding = 1) uniform texture2D t_MotionVectors;
+layout(set = 0, binding = 2) uniform sampler s_Linear;
+
+layout(set = 1, binding = 0) uniform MotionBlur {
+    uint Samples;
+    float Intensity;
+};
+
+void main() {
+    ivec2 size = textureSize(sampler2D(t_Color, s_Linear), 0);
+    vec2 uv = gl_FragCoord.xy / vec2(size);
+    vec2 motion_vector = texelFetch(sampler2D(t_MotionVectors, s_Linear), ivec2(gl_FragCoord.xy), 0).xy;
+    vec2 blur = motion_vector * Intensity;
+
+    // the samples are spread along the motion of the last frame, centered on the current position
+    vec4 color = vec4(0.0);
+    for (uint i = 0u; i < Samples; ++i) {
+        float t = (float(i) + 0.5) / float(Samples) - 0.5;
+        color += texture(sampler2D(t_Color, s_Linear), uv - blur * t);
+    }
+    o_Target = color / float(Samples);
+}
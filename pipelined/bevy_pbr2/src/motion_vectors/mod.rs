@@ -0,0 +1,463 @@
+use crate::{
+    render::MeshPipelines, ExtractedMeshes, MeshMeta, MeshPhase, MeshVertexBuffer,
+    MeshVertexLayout, OrderIndependentTransparency, ViewMeshLods,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{self, CorePipelineSettings, DepthMode, ViewMainTexture},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{BindGroupBuilder, BindGroupId, TextureViewId},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{ExtractedView, ViewMeta, ViewUniform},
+    RenderApp, RenderStage,
+};
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const MOTION_VECTOR_PASS: &'static str = "motion_vector_pass";
+    }
+}
+
+pub const MOTION_VECTOR_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+#[derive(Debug, Clone)]
+pub struct MotionVectorConfig {
+    /// Tracks the transform of each mesh in the previous frame, so motion vectors include the
+    /// motion of meshes. Otherwise they only include the motion of the camera.
+    pub object_motion: bool,
+}
+
+impl Default for MotionVectorConfig {
+    fn default() -> Self {
+        MotionVectorConfig {
+            object_motion: true,
+        }
+    }
+}
+
+/// Marks a view that needs motion vectors. Its main pass is drawn into a [`ViewMainTexture`],
+/// and the motion of its meshes since the previous frame into [`ViewMotionVectors`].
+///
+/// This is inserted in the render world by the effects that use motion vectors, like
+/// [`crate::TemporalAntiAliasing`] and [`crate::MotionBlur`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MotionVectors;
+
+/// Adds the motion vector pass, unless it has already been added by another plugin. The pass
+/// must be added after [`crate::PbrPlugin`].
+pub(crate) fn add_motion_vector_pass(app: &mut App) {
+    app.init_resource::<MotionVectorConfig>();
+
+    let render_app = app.sub_app_mut(RenderApp);
+    if render_app.world.contains_resource::<MotionVectorShaders>() {
+        return;
+    }
+    render_app
+        .add_system_to_stage(
+            RenderStage::Prepare,
+            prepare_motion_vector_textures.system(),
+        )
+        .add_system_to_stage(RenderStage::Queue, queue_motion_vectors.system())
+        .init_render_resource::<MotionVectorShaders>();
+
+    let draw_motion_vectors = DrawMotionVectors::new(&mut render_app.world);
+    let motion_vector_pass_node = MotionVectorPassNode::new(&mut render_app.world);
+    let render_world = render_app.world.cell();
+    let draw_functions = render_world.get_resource::<DrawFunctions>().unwrap();
+    draw_functions.write().add(draw_motion_vectors);
+    let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
+    let draw_3d_graph = graph
+        .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+        .unwrap();
+    draw_3d_graph.add_node(
+        draw_3d_graph::node::MOTION_VECTOR_PASS,
+        motion_vector_pass_node,
+    );
+    draw_3d_graph
+        .add_node_edge(
+            core_pipeline::draw_3d_graph::node::MAIN_PASS,
+            draw_3d_graph::node::MOTION_VECTOR_PASS,
+        )
+        .unwrap();
+    let input_node_id = draw_3d_graph.input_node().unwrap().id;
+    draw_3d_graph
+        .add_slot_edge(
+            input_node_id,
+            core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+            draw_3d_graph::node::MOTION_VECTOR_PASS,
+            MotionVectorPassNode::IN_VIEW,
+        )
+        .unwrap();
+    draw_3d_graph
+        .add_slot_edge(
+            input_node_id,
+            core_pipeline::draw_3d_graph::input::DEPTH,
+            draw_3d_graph::node::MOTION_VECTOR_PASS,
+            MotionVectorPassNode::IN_DEPTH,
+        )
+        .unwrap();
+}
+
+pub struct MotionVectorPhase;
+
+pub struct MotionVectorShaders {
+    /// The pipelines for each [`MeshVertexBuffer`].
+    pipelines: MeshPipelines,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for MotionVectorShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        let vertex_shader =
+            Shader::from_glsl(ShaderStage::Vertex, include_str!("motion_vectors.vert"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("motion_vectors.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        pipeline_layout.vertex_buffer_descriptors =
+            vec![MeshVertexBuffer::standard(MeshVertexLayout::default()).layout()];
+
+        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        pipeline_layout.bind_group_mut(1).bindings[1].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let mut pipeline_descriptor = RenderPipelineDescriptor {
+            color_target_states: vec![ColorTargetState {
+                format: MOTION_VECTOR_FORMAT,
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::default_config(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        // the main pass has already written the depth of these meshes
+        if let Some(depth_stencil) = pipeline_descriptor.depth_stencil.as_mut() {
+            depth_stencil.format = settings.depth_format;
+            depth_stencil.depth_write_enabled = false;
+            depth_stencil.depth_compare = depth_mode.compare(CompareFunction::LessEqual);
+        }
+
+        let pipelines = MeshPipelines::new(pipeline_descriptor.clone());
+
+        MotionVectorShaders {
+            pipelines,
+            pipeline_descriptor,
+        }
+    }
+}
+
+/// The motion vectors of a view with [`MotionVectors`], in uv units per frame.
+pub struct ViewMotionVectors {
+    pub texture: TextureViewId,
+}
+
+pub fn prepare_motion_vector_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    settings: Res<CorePipelineSettings>,
+    views: Query<(Entity, &ExtractedView), With<MotionVectors>>,
+) {
+    for (entity, view) in views.iter() {
+        let descriptor = |format, usage| TextureDescriptor {
+            size: Extent3d {
+                depth_or_array_layers: 1,
+                width: view.width,
+                height: view.height,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | usage,
+        };
+        // effects that draw into the main texture copy it first
+        let main_texture = texture_cache.get(
+            &render_resources,
+            descriptor(settings.main_texture_format(), TextureUsage::COPY_SRC),
+        );
+        let motion_vectors = texture_cache.get(
+            &render_resources,
+            descriptor(MOTION_VECTOR_FORMAT, TextureUsage::empty()),
+        );
+        commands.entity(entity).insert_bundle((
+            ViewMainTexture {
+                texture: main_texture.texture,
+                view: main_texture.default_view,
+            },
+            ViewMotionVectors {
+                texture: motion_vectors.default_view,
+            },
+        ));
+    }
+}
+
+// TODO: This is temporary. Once we expose BindGroupLayouts directly, we can create view bind groups without specific shader context
+struct MotionVectorViewBindGroups {
+    view_bind_group: BindGroupId,
+    /// One bind group per chunk of [`MeshMeta::transform_uniforms`].
+    mesh_transform_bind_groups: Vec<BindGroupId>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_motion_vectors(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut motion_vector_shaders: ResMut<MotionVectorShaders>,
+    mesh_meta: Res<MeshMeta>,
+    view_meta: Res<ViewMeta>,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<
+        (Entity, &ViewUniform, Option<&OrderIndependentTransparency>),
+        With<MotionVectors>,
+    >,
+) {
+    motion_vector_shaders
+        .pipelines
+        .specialize_meshes(&render_resources, extracted_meshes.meshes.iter());
+    let layout = &motion_vector_shaders.pipeline_descriptor.layout;
+    // the previous transforms are pushed in the same order as the transforms, so a mesh has the
+    // same chunk and offset in both
+    let mesh_transform_bind_groups = (0..mesh_meta.transform_uniforms.chunk_count())
+        .map(|chunk| {
+            let mesh_transform_bind_group = BindGroupBuilder::default()
+                .add_binding(0, mesh_meta.transform_uniforms.binding(chunk))
+                .add_binding(1, mesh_meta.previous_transform_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(layout.bind_group(1).id, &mesh_transform_bind_group);
+            mesh_transform_bind_group.id
+        })
+        .collect::<Vec<_>>();
+
+    let draw_motion_vectors = draw_functions.read().get_id::<DrawMotionVectors>().unwrap();
+    for (view_entity, view_uniform, oit) in views.iter() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &view_bind_group);
+
+        let mut motion_vector_phase = RenderPhase::<MotionVectorPhase>::default();
+        for (i, extracted_mesh) in extracted_meshes.meshes.iter().enumerate() {
+            // these don't write depth, so they would replace the motion of the meshes behind them
+            if oit.is_some()
+                && extracted_mesh.phase == MeshPhase::Transparent
+                && extracted_mesh.is_triangle_list()
+            {
+                continue;
+            }
+            motion_vector_phase.add(Drawable {
+                draw_function: draw_motion_vectors,
+                draw_key: i,
+                sort_key: 0,
+                entity: extracted_mesh.entity,
+                clip: None,
+            });
+        }
+
+        commands.entity(view_entity).insert_bundle((
+            motion_vector_phase,
+            MotionVectorViewBindGroups {
+                view_bind_group: view_bind_group.id,
+                mesh_transform_bind_groups: mesh_transform_bind_groups.clone(),
+            },
+        ));
+    }
+}
+
+/// Draws the motion vectors of the meshes of a view into its [`ViewMotionVectors`].
+pub struct MotionVectorPassNode {
+    query: QueryState<(
+        &'static RenderPhase<MotionVectorPhase>,
+        &'static ViewMotionVectors,
+    )>,
+}
+
+impl MotionVectorPassNode {
+    pub const IN_DEPTH: &'static str = "depth";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for MotionVectorPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(MotionVectorPassNode::IN_DEPTH, SlotType::TextureView),
+            SlotInfo::new(MotionVectorPassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        // only views with effects that use motion vectors have them
+        let (motion_vector_phase, motion_vectors) = match self.query.get_manual(world, view_entity)
+        {
+            Ok(view) => view,
+            Err(_) => return Ok(()),
+        };
+
+        let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(motion_vectors.texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::NONE),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                attachment: TextureAttachment::Id(depth_texture),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: settings.stencil_ops(),
+            }),
+            sample_count: 1,
+        };
+
+        let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut draw_functions = draw_functions.write();
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                for drawable in motion_vector_phase.drawn_things.iter() {
+                    let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
+                    draw_function.draw(
+                        world,
+                        &mut tracked_pass,
+                        view_entity,
+                        drawable.draw_key,
+                        drawable.sort_key,
+                    );
+                }
+            },
+        );
+        Ok(())
+    }
+}
+
+type DrawMotionVectorsParams<'a> = (
+    Res<'a, MotionVectorShaders>,
+    Res<'a, ExtractedMeshes>,
+    Query<
+        'a,
+        (
+            &'a ViewUniform,
+            &'a MotionVectorViewBindGroups,
+            Option<&'a ViewMeshLods>,
+        ),
+    >,
+);
+pub struct DrawMotionVectors {
+    params: SystemState<DrawMotionVectorsParams<'static>>,
+}
+
+impl DrawMotionVectors {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawMotionVectors {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (motion_vector_shaders, extracted_meshes, views) = self.params.get(world);
+        let (view_uniforms, motion_vector_view_bind_groups, view_mesh_lods) =
+            views.get(view).unwrap();
+        let layout = &motion_vector_shaders.pipeline_descriptor.layout;
+        let (extracted_mesh, mesh_level) = extracted_meshes.get(draw_key, view_mesh_lods);
+        // the pipelines are only created for triangle lists
+        if !mesh_level.key.is_triangle_list() {
+            return;
+        }
+        pass.set_pipeline(
+            motion_vector_shaders
+                .pipelines
+                .get(mesh_level.key.vertex_buffer),
+        );
+        pass.set_bind_group(
+            0,
+            layout.bind_group(0).id,
+            motion_vector_view_bind_groups.view_bind_group,
+            Some(&[view_uniforms.view_uniform_offset]),
+        );
+        let mesh_transform_bind_group = motion_vector_view_bind_groups.mesh_transform_bind_groups
+            [extracted_mesh.transform_binding.chunk];
+        let offset = extracted_mesh.transform_binding.offset;
+        pass.set_bind_group(
+            1,
+            layout.bind_group(1).id,
+            mesh_transform_bind_group,
+            Some(&[offset, offset]),
+        );
+        mesh_level.draw(pass);
+    }
+}
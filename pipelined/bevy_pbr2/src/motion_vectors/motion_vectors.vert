@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+
+layout(location = 0) out vec4 v_ClipPosition;
+layout(location = 1) out vec4 v_PreviousClipPosition;
+
+layout(set = 0, binding = 0) uniform View {
+    mat4 ViewProj;
+    vec3 ViewWorldPosition;
+    mat4 UnjitteredViewProj;
+    mat4 PreviousViewProj;
+};
+
+layout(set = 1, binding = 0) uniform MeshTransform {
+    mat4 Model;
+};
+
+layout(set = 1, binding = 1) uniform PreviousMeshTransform {
+    mat4 PreviousModel;
+};
+
+void main() {
+    vec4 world_position = Model * vec4(Vertex_Position, 1.0);
+    v_ClipPosition = UnjitteredViewProj * world_position;
+    v_PreviousClipPosition = PreviousViewProj * PreviousModel * vec4(Vertex_Position, 1.0);
+    gl_Position = ViewProj * world_position;
+}
@@ -0,0 +1,545 @@
+use crate::{
+    render::{pbr_pipeline_descriptor, MeshPipelines, FULLSCREEN_VERTEX_SHADER},
+    DrawPbr, ExtractedMeshes, MeshPhase, MeshVertexLayout,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    camera::ActiveCameras,
+    color::Color,
+    core_pipeline::{self, CorePipelineSettings, DepthMode},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{BindGroupBuilder, BindGroupId, SamplerId, TextureViewId},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::ExtractedView,
+    RenderApp, RenderStage,
+};
+use bevy_utils::HashMap;
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const OIT_PASS: &'static str = "oit_pass";
+    }
+}
+
+pub const OIT_ACCUM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+pub const OIT_REVEALAGE_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+/// Draws transparent meshes of cameras with an [`OrderIndependentTransparency`] component using
+/// weighted blended order-independent transparency, so the result doesn't depend on the draw
+/// order. Must be added after [`crate::PbrPlugin`].
+#[derive(Debug, Default)]
+pub struct OitPlugin;
+
+impl Plugin for OitPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OrderIndependentTransparency>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_oit_cameras.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_oit_textures.system())
+            .add_system_to_stage(RenderStage::Queue, queue_oit_meshes.system())
+            .init_render_resource::<OitShaders>();
+
+        let draw_pbr_oit = DrawPbrOit::new(&mut render_app.world);
+        let oit_pass_node = OitPassNode::new(&mut render_app.world);
+        let render_world = render_app.world.cell();
+        let draw_functions = render_world.get_resource::<DrawFunctions>().unwrap();
+        draw_functions.write().add(draw_pbr_oit);
+        let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::OIT_PASS, oit_pass_node);
+        draw_3d_graph
+            .add_node_edge(
+                core_pipeline::draw_3d_graph::node::MAIN_PASS,
+                draw_3d_graph::node::OIT_PASS,
+            )
+            .unwrap();
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::OIT_PASS,
+                OitPassNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::RENDER_TARGET,
+                draw_3d_graph::node::OIT_PASS,
+                OitPassNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::DEPTH,
+                draw_3d_graph::node::OIT_PASS,
+                OitPassNode::IN_DEPTH,
+            )
+            .unwrap();
+    }
+}
+
+/// Enables order-independent transparency for a 3d camera, see [`OitPlugin`]. Meshes whose
+/// [`StandardMaterial`](crate::StandardMaterial) color has an alpha below 1.0 are drawn after
+/// the main pass, without writing depth. Their alpha currently comes from their vertex colors.
+#[derive(Debug, Clone, Reflect, Default)]
+#[reflect(Component)]
+pub struct OrderIndependentTransparency;
+
+pub struct OitPhase;
+
+pub struct OitShaders {
+    /// The accumulation pipelines for each [`MeshVertexLayout`], specialized for the vertex
+    /// buffers of the meshes.
+    pipelines: HashMap<MeshVertexLayout, MeshPipelines>,
+    composite_pipeline: PipelineId,
+    composite_pipeline_descriptor: RenderPipelineDescriptor,
+    sampler: SamplerId,
+}
+
+impl FromWorld for OitShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        // these have the bind group layouts of `PbrShaders::pipeline`, so `DrawPbr` can draw
+        // with them
+        let accumulate_pipeline_descriptor = |vertex_layout| {
+            let mut descriptor = pbr_pipeline_descriptor(
+                render_resources,
+                depth_mode,
+                settings,
+                vertex_layout,
+                &["ORDER_INDEPENDENT_TRANSPARENCY"],
+                &Default::default(),
+            )
+            .unwrap();
+            // transparent meshes are tested against the depth of the main pass, but don't
+            // occlude each other
+            if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+                depth_stencil.depth_write_enabled = false;
+            }
+            descriptor.color_target_states = vec![
+                ColorTargetState {
+                    format: OIT_ACCUM_FORMAT,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrite::ALL,
+                },
+                // the revealage is the product of (1 - alpha) of all fragments
+                ColorTargetState {
+                    format: OIT_REVEALAGE_FORMAT,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::OneMinusSrc,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::OneMinusSrc,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrite::ALL,
+                },
+            ];
+            descriptor
+        };
+        let pipelines = MeshVertexLayout::ALL
+            .iter()
+            .map(|&vertex_layout| {
+                let descriptor = accumulate_pipeline_descriptor(vertex_layout);
+                (vertex_layout, MeshPipelines::new(descriptor))
+            })
+            .collect();
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("oit_composite.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let composite_pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: settings.main_texture_format(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let composite_pipeline =
+            render_resources.create_render_pipeline(&composite_pipeline_descriptor);
+
+        OitShaders {
+            pipelines,
+            composite_pipeline,
+            composite_pipeline_descriptor,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+pub fn extract_oit_cameras(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    query: Query<&OrderIndependentTransparency>,
+) {
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(oit) = query.get(entity) {
+                commands.get_or_spawn(entity).insert(oit.clone());
+            }
+        }
+    }
+}
+
+/// The accumulation and revealage targets of a view with [`OrderIndependentTransparency`].
+pub struct ViewOitTextures {
+    pub accum: TextureViewId,
+    pub revealage: TextureViewId,
+}
+
+pub fn prepare_oit_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    views: Query<(Entity, &ExtractedView), With<OrderIndependentTransparency>>,
+) {
+    for (entity, view) in views.iter() {
+        let mut get_texture = |format| {
+            texture_cache
+                .get(
+                    &render_resources,
+                    TextureDescriptor {
+                        size: Extent3d {
+                            depth_or_array_layers: 1,
+                            width: view.width,
+                            height: view.height,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format,
+                        usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+                    },
+                )
+                .default_view
+        };
+        let accum = get_texture(OIT_ACCUM_FORMAT);
+        let revealage = get_texture(OIT_REVEALAGE_FORMAT);
+        commands
+            .entity(entity)
+            .insert(ViewOitTextures { accum, revealage });
+    }
+}
+
+struct ViewOitBindGroup {
+    composite_bind_group: BindGroupId,
+}
+
+pub fn queue_oit_meshes(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut oit_shaders: ResMut<OitShaders>,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<(Entity, &ExtractedView, &ViewOitTextures)>,
+) {
+    for extracted_mesh in extracted_meshes.meshes.iter() {
+        if extracted_mesh.phase != MeshPhase::Transparent {
+            continue;
+        }
+        for vertex_buffer in extracted_mesh.triangle_list_vertex_buffers() {
+            oit_shaders
+                .pipelines
+                .get_mut(&vertex_buffer.vertex_layout)
+                .unwrap()
+                .specialize(&render_resources, vertex_buffer);
+        }
+    }
+    let layout = &oit_shaders.composite_pipeline_descriptor.layout;
+    let draw_pbr_oit = draw_functions.read().get_id::<DrawPbrOit>().unwrap();
+    for (view_entity, view, oit_textures) in views.iter() {
+        let frustum = view.frustum();
+        let mut oit_phase = RenderPhase::<OitPhase>::default();
+        for (i, extracted_mesh) in extracted_meshes.meshes.iter().enumerate() {
+            // other topologies are drawn in the main pass, as the accumulate pipelines are only
+            // created for triangle lists
+            if extracted_mesh.phase == MeshPhase::Transparent
+                && extracted_mesh.is_triangle_list()
+                && extracted_mesh.is_visible(&frustum)
+            {
+                oit_phase.add(Drawable {
+                    draw_function: draw_pbr_oit,
+                    draw_key: i,
+                    sort_key: 0,
+                    entity: extracted_mesh.entity,
+                    clip: None,
+                });
+            }
+        }
+        if oit_phase.drawn_things.is_empty() {
+            continue;
+        }
+
+        let composite_bind_group = BindGroupBuilder::default()
+            .add_binding(0, oit_textures.accum)
+            .add_binding(1, oit_textures.revealage)
+            .add_binding(2, oit_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &composite_bind_group);
+
+        commands.entity(view_entity).insert_bundle((
+            oit_phase,
+            ViewOitBindGroup {
+                composite_bind_group: composite_bind_group.id,
+            },
+        ));
+    }
+}
+
+/// Accumulates the transparent meshes of a view into its [`ViewOitTextures`], then composites
+/// them over the render target.
+pub struct OitPassNode {
+    query: QueryState<(
+        &'static RenderPhase<OitPhase>,
+        &'static ViewOitTextures,
+        &'static ViewOitBindGroup,
+    )>,
+}
+
+impl OitPassNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_DEPTH: &'static str = "depth";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for OitPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(OitPassNode::IN_COLOR_ATTACHMENT, SlotType::TextureView),
+            SlotInfo::new(OitPassNode::IN_DEPTH, SlotType::TextureView),
+            SlotInfo::new(OitPassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        // views only have an oit phase when they have transparent meshes to draw
+        let (oit_phase, oit_textures, oit_bind_group) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(view) => view,
+                Err(_) => return Ok(()),
+            };
+
+        let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let accumulate_pass_descriptor = PassDescriptor {
+            color_attachments: vec![
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(oit_textures.accum),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::NONE),
+                        store: true,
+                    },
+                },
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(oit_textures.revealage),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::WHITE),
+                        store: true,
+                    },
+                },
+            ],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                attachment: TextureAttachment::Id(depth_texture),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: settings.stencil_ops(),
+            }),
+            sample_count: 1,
+        };
+
+        let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
+
+        render_context.begin_render_pass(
+            &accumulate_pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut draw_functions = draw_functions.write();
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                for drawable in oit_phase.drawn_things.iter() {
+                    let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
+                    draw_function.draw(
+                        world,
+                        &mut tracked_pass,
+                        view_entity,
+                        drawable.draw_key,
+                        drawable.sort_key,
+                    );
+                }
+            },
+        );
+
+        let composite_pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(color_attachment_texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+
+        let oit_shaders = world.get_resource::<OitShaders>().unwrap();
+        let layout = &oit_shaders.composite_pipeline_descriptor.layout;
+
+        render_context.begin_render_pass(
+            &composite_pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                tracked_pass.set_pipeline(oit_shaders.composite_pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_group(0).id,
+                    oit_bind_group.composite_bind_group,
+                    None,
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Draws pbr meshes into the [`ViewOitTextures`] of a view.
+pub struct DrawPbrOit {
+    draw_pbr: DrawPbr,
+}
+
+impl DrawPbrOit {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            draw_pbr: DrawPbr::new(world),
+        }
+    }
+}
+
+impl Draw for DrawPbrOit {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let oit_shaders = world.get_resource::<OitShaders>().unwrap();
+        self.draw_pbr
+            .draw_with_pipeline(world, pass, view, draw_key, |key| {
+                oit_shaders.pipelines[&key.vertex_buffer.vertex_layout].get(key.vertex_buffer)
+            });
+    }
+}
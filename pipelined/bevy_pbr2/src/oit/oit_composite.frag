@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Accum;
+layout(set = 0, binding = 1) uniform texture2D t_Revealage;
+layout(set = 0, binding = 2) uniform sampler s_Oit;
+
+void main() {
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    float revealage = texelFetch(sampler2D(t_Revealage, s_Oit), coords, 0).r;
+    // no transparent fragment covers this pixel
+    if (revealage == 1.0) {
+        discard;
+    }
+    vec4 accum = texelFetch(sampler2D(t_Accum, s_Oit), coords, 0);
+    vec3 average_color = accum.rgb / clamp(accum.a, 1e-4, 5e4);
+    o_Target = vec4(average_color, 1.0 - revealage);
+}
@@ -0,0 +1,37 @@
+#version 450
+
+layout(location = 0) out vec4 o_Seed;
+
+// the offset to the nearest seed in xy, its outline width in z, and whether there is one in w
+layout(set = 0, binding = 0) uniform texture2D t_Seeds;
+layout(set = 0, binding = 1) uniform sampler s_Nearest;
+
+layout(set = 1, binding = 0) uniform JumpFloodStep {
+    int Step;
+};
+
+void main() {
+    ivec2 size = textureSize(sampler2D(t_Seeds, s_Nearest), 0);
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec4 nearest = vec4(0.0);
+    float nearest_distance = 0.0;
+    for (int y = -1; y <= 1; ++y) {
+        for (int x = -1; x <= 1; ++x) {
+            ivec2 sample_coords = coords + ivec2(x, y) * Step;
+            if (any(lessThan(sample_coords, ivec2(0))) || any(greaterThanEqual(sample_coords, size))) {
+                continue;
+            }
+            vec4 seed = texelFetch(sampler2D(t_Seeds, s_Nearest), sample_coords, 0);
+            if (seed.w == 0.0) {
+                continue;
+            }
+            vec2 offset = seed.xy + vec2(sample_coords - coords);
+            float distance = dot(offset, offset);
+            if (nearest.w == 0.0 || distance < nearest_distance) {
+                nearest = vec4(offset, seed.z, 1.0);
+                nearest_distance = distance;
+            }
+        }
+    }
+    o_Seed = nearest;
+}
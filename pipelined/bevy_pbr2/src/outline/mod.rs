@@ -0,0 +1,815 @@
+use crate::{
+    auto_exposure, motion_blur, oit,
+    render::{MeshPipelines, FULLSCREEN_VERTEX_SHADER},
+    sky, taa, wireframe, ExtractedMeshes, MeshMeta, MeshVertexBuffer, MeshVertexLayout,
+    ViewMeshLods,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_math::Vec4;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{self, CorePipelineSettings, Transparent3dPhase},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{ExtractedView, ViewMeta, ViewUniform},
+    RenderApp, RenderStage,
+};
+use bevy_utils::HashMap;
+use crevice::std140::AsStd140;
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const OUTLINE_PASS: &'static str = "outline_pass";
+    }
+}
+
+/// The widest outline, in physical pixels.
+pub const MAX_OUTLINE_WIDTH: f32 = 64.0;
+
+/// The format of the mask the outlined meshes are drawn into, and of the seeds the jump flood
+/// spreads from them. Half floats hold the offsets to the seeds exactly.
+const OUTLINE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Draws an outline around meshes with an [`Outlined`] component, e.g. to highlight the objects
+/// hovered or selected in an editor. The outlined meshes are drawn into a mask, from which a jump
+/// flood finds the nearest outlined mesh of every pixel around them.
+///
+/// Outlines are drawn on top of the 3d scene, including the meshes in front of the outlined ones,
+/// before the effects reading the image of the view, like [`crate::MotionBlurPlugin`]. Must be
+/// added after [`crate::PbrPlugin`], and after the other plugins adding passes to the 3d graph.
+#[derive(Debug, Default)]
+pub struct OutlinePlugin;
+
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Outlined>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_outlines.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_outlines.system())
+            .add_system_to_stage(RenderStage::Queue, queue_outlines.system())
+            .init_render_resource::<OutlineShaders>()
+            .init_resource::<OutlineMeta>();
+
+        let draw_outline_mask = DrawOutlineMask::new(&mut render_app.world);
+        let outline_pass_node = OutlinePassNode::new(&mut render_app.world);
+        let render_world = render_app.world.cell();
+        let draw_functions = render_world.get_resource::<DrawFunctions>().unwrap();
+        draw_functions.write().add(draw_outline_mask);
+        let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("outline", OutlineUniformsNode);
+        graph
+            .add_node_edge("outline", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::OUTLINE_PASS, outline_pass_node);
+        draw_3d_graph
+            .add_node_edge(
+                core_pipeline::draw_3d_graph::node::MAIN_PASS,
+                draw_3d_graph::node::OUTLINE_PASS,
+            )
+            .unwrap();
+        // the outlines are drawn over the scene, and then seen by the passes reading its image
+        for node in [
+            sky::draw_3d_graph::node::SKY_PASS,
+            oit::draw_3d_graph::node::OIT_PASS,
+            wireframe::draw_3d_graph::node::WIREFRAME_PASS,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(*node, draw_3d_graph::node::OUTLINE_PASS)
+                    .unwrap();
+            }
+        }
+        for node in [
+            auto_exposure::draw_3d_graph::node::AUTO_EXPOSURE,
+            motion_blur::draw_3d_graph::node::MOTION_BLUR,
+            taa::draw_3d_graph::node::TAA_RESOLVE,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(draw_3d_graph::node::OUTLINE_PASS, *node)
+                    .unwrap();
+            }
+        }
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::OUTLINE_PASS,
+                OutlinePassNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::RENDER_TARGET,
+                draw_3d_graph::node::OUTLINE_PASS,
+                OutlinePassNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+    }
+}
+
+/// Draws an outline around a mesh entity, see [`OutlinePlugin`].
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Outlined {
+    pub color: Color,
+    /// The width of the outline in physical pixels, up to [`MAX_OUTLINE_WIDTH`].
+    pub width: f32,
+}
+
+impl Default for Outlined {
+    fn default() -> Self {
+        Outlined {
+            color: Color::ORANGE,
+            width: 3.0,
+        }
+    }
+}
+
+/// The [`Outlined`] entities of the frame.
+#[derive(Default)]
+pub struct ExtractedOutlines {
+    pub outlines: HashMap<Entity, Outlined>,
+}
+
+pub fn extract_outlines(mut commands: Commands, query: Query<(Entity, &Outlined)>) {
+    commands.insert_resource(ExtractedOutlines {
+        outlines: query
+            .iter()
+            .map(|(entity, outlined)| (entity, outlined.clone()))
+            .collect(),
+    });
+}
+
+/// Returns the steps of the jump flood that spreads outlines up to `max_width` pixels wide, from
+/// the longest to 1. Together they reach twice as far as the longest step.
+pub fn jump_flood_steps(max_width: f32) -> Vec<i32> {
+    let longest = (max_width.max(1.0).ceil() as u32).next_power_of_two();
+    std::iter::successors(Some(longest), |step| Some(step / 2))
+        .take_while(|step| *step > 0)
+        .map(|step| step as i32)
+        .collect()
+}
+
+pub struct OutlineShaders {
+    /// The pipelines drawing the mask for each [`MeshVertexBuffer`].
+    mask_pipelines: MeshPipelines,
+    mask_pipeline_descriptor: RenderPipelineDescriptor,
+    jump_flood_pipeline: PipelineId,
+    jump_flood_layout: PipelineLayout,
+    outline_pipeline: PipelineId,
+    outline_layout: PipelineLayout,
+    sampler: SamplerId,
+}
+
+impl FromWorld for OutlineShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let primitive = PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        };
+        let outline_target = ColorTargetState {
+            format: OUTLINE_FORMAT,
+            blend: None,
+            write_mask: ColorWrite::ALL,
+        };
+
+        let vertex_shader =
+            Shader::from_glsl(ShaderStage::Vertex, include_str!("outline_mask.vert"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("outline_mask.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let mut mask_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        mask_layout.vertex_buffer_descriptors =
+            vec![MeshVertexBuffer::standard(MeshVertexLayout::default()).layout()];
+        mask_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        mask_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        mask_layout.bind_group_mut(2).bindings[0].set_dynamic(true);
+        mask_layout.update_bind_group_ids();
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+        let mask_pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: primitive.clone(),
+            color_target_states: vec![outline_target.clone(), outline_target.clone()],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                mask_layout,
+            )
+        };
+        let mask_pipelines = MeshPipelines::new(mask_pipeline_descriptor.clone());
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("jump_flood.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let mut jump_flood_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        jump_flood_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        jump_flood_layout.update_bind_group_ids();
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+        let jump_flood_pipeline =
+            render_resources.create_render_pipeline(&RenderPipelineDescriptor {
+                primitive: primitive.clone(),
+                color_target_states: vec![outline_target],
+                ..RenderPipelineDescriptor::new(
+                    ShaderStages {
+                        vertex,
+                        fragment: Some(fragment),
+                    },
+                    jump_flood_layout.clone(),
+                )
+            });
+
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("outline.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let outline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+        let outline_pipeline = render_resources.create_render_pipeline(&RenderPipelineDescriptor {
+            primitive,
+            color_target_states: vec![ColorTargetState {
+                format: settings.main_texture_format(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                outline_layout.clone(),
+            )
+        });
+
+        OutlineShaders {
+            mask_pipelines,
+            mask_pipeline_descriptor,
+            jump_flood_pipeline,
+            jump_flood_layout,
+            outline_pipeline,
+            outline_layout,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuOutline {
+    color: Vec4,
+    width: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuJumpFloodStep {
+    step: i32,
+}
+
+#[derive(Default)]
+pub struct OutlineMeta {
+    pub outline_uniforms: DynamicUniformVec<GpuOutline>,
+    pub step_uniforms: DynamicUniformVec<GpuJumpFloodStep>,
+    /// The index in [`ExtractedMeshes`] and the uniform of each outlined mesh.
+    outlined_meshes: Vec<(usize, DynamicUniformIndex)>,
+    steps: Vec<DynamicUniformIndex>,
+    /// One bind group per chunk of [`MeshMeta::transform_uniforms`].
+    mesh_transform_bind_groups: Vec<BindGroupId>,
+    /// One bind group per chunk of [`OutlineMeta::outline_uniforms`].
+    outline_bind_groups: Vec<BindGroupId>,
+    /// One bind group per chunk of [`OutlineMeta::step_uniforms`].
+    step_bind_groups: Vec<BindGroupId>,
+}
+
+/// The textures the outlines of a view are found in.
+pub struct ViewOutlineTextures {
+    /// The colors of the outlined meshes.
+    pub mask: CachedTexture,
+    /// The seeds of the jump flood, which reads from one texture and writes to the other.
+    pub seeds: [CachedTexture; 2],
+}
+
+pub fn prepare_outlines(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    mut outline_meta: ResMut<OutlineMeta>,
+    extracted_outlines: Res<ExtractedOutlines>,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<(Entity, &ExtractedView), With<RenderPhase<Transparent3dPhase>>>,
+) {
+    let outline_meta = &mut *outline_meta;
+    let outlines = extracted_meshes
+        .meshes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, extracted_mesh)| {
+            let outline = extracted_outlines.outlines.get(&extracted_mesh.entity)?;
+            Some((i, outline))
+        })
+        .collect::<Vec<_>>();
+    outline_meta
+        .outline_uniforms
+        .reserve_and_clear(outlines.len(), &render_resources);
+    outline_meta.outlined_meshes.clear();
+    let mut max_width = 0.0f32;
+    for (i, outline) in outlines {
+        let width = outline.width.max(0.0).min(MAX_OUTLINE_WIDTH);
+        let uniform = outline_meta.outline_uniforms.push(GpuOutline {
+            color: Vec4::from(outline.color.as_linear_rgba_f32()),
+            width,
+        });
+        outline_meta.outlined_meshes.push((i, uniform));
+        max_width = max_width.max(width);
+    }
+
+    let steps = if outline_meta.outlined_meshes.is_empty() {
+        Vec::new()
+    } else {
+        jump_flood_steps(max_width)
+    };
+    outline_meta
+        .step_uniforms
+        .reserve_and_clear(steps.len(), &render_resources);
+    outline_meta.steps.clear();
+    for step in steps {
+        let uniform = outline_meta.step_uniforms.push(GpuJumpFloodStep { step });
+        outline_meta.steps.push(uniform);
+    }
+
+    if !outline_meta.outlined_meshes.is_empty() {
+        for (entity, view) in views.iter() {
+            let mut get_texture = || {
+                texture_cache.get(
+                    &render_resources,
+                    TextureDescriptor {
+                        size: Extent3d {
+                            depth_or_array_layers: 1,
+                            width: view.width,
+                            height: view.height,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: OUTLINE_FORMAT,
+                        usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+                    },
+                )
+            };
+            commands.entity(entity).insert(ViewOutlineTextures {
+                mask: get_texture(),
+                seeds: [get_texture(), get_texture()],
+            });
+        }
+    }
+
+    outline_meta
+        .outline_uniforms
+        .write_to_staging_buffer(&render_resources);
+    outline_meta
+        .step_uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+pub struct OutlinePhase;
+
+// TODO: This is temporary. Once we expose BindGroupLayouts directly, we can create view bind groups without specific shader context
+struct OutlineViewBindGroups {
+    view_bind_group: BindGroupId,
+    /// The bind groups of the jump flood passes reading each of the seed textures.
+    jump_flood_bind_groups: [BindGroupId; 2],
+    outline_bind_group: BindGroupId,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_outlines(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut outline_shaders: ResMut<OutlineShaders>,
+    mut outline_meta: ResMut<OutlineMeta>,
+    mesh_meta: Res<MeshMeta>,
+    view_meta: Res<ViewMeta>,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<(Entity, &ViewUniform, &ViewOutlineTextures)>,
+) {
+    if outline_meta.outlined_meshes.is_empty() {
+        return;
+    }
+
+    let outline_meta = &mut *outline_meta;
+    outline_shaders.mask_pipelines.specialize_meshes(
+        &render_resources,
+        outline_meta
+            .outlined_meshes
+            .iter()
+            .map(|(mesh_index, _)| &extracted_meshes.meshes[*mesh_index]),
+    );
+    let mask_layout = &outline_shaders.mask_pipeline_descriptor.layout;
+    outline_meta.mesh_transform_bind_groups = (0..mesh_meta.transform_uniforms.chunk_count())
+        .map(|chunk| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, mesh_meta.transform_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(mask_layout.bind_group(1).id, &bind_group);
+            bind_group.id
+        })
+        .collect();
+    outline_meta.outline_bind_groups = (0..outline_meta.outline_uniforms.chunk_count())
+        .map(|chunk| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, outline_meta.outline_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(mask_layout.bind_group(2).id, &bind_group);
+            bind_group.id
+        })
+        .collect();
+    let jump_flood_layout = &outline_shaders.jump_flood_layout;
+    outline_meta.step_bind_groups = (0..outline_meta.step_uniforms.chunk_count())
+        .map(|chunk| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, outline_meta.step_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(jump_flood_layout.bind_group(1).id, &bind_group);
+            bind_group.id
+        })
+        .collect();
+
+    // the result of the jump flood ends up in the texture the last step writes to
+    let result = outline_meta.steps.len() % 2;
+    let draw_outline_mask = draw_functions.read().get_id::<DrawOutlineMask>().unwrap();
+    for (view_entity, view_uniform, textures) in views.iter() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(mask_layout.bind_group(0).id, &view_bind_group);
+
+        let jump_flood_bind_group = |source: &CachedTexture| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, source.default_view)
+                .add_binding(1, outline_shaders.sampler)
+                .finish();
+            render_resources.create_bind_group(jump_flood_layout.bind_group(0).id, &bind_group);
+            bind_group.id
+        };
+        let jump_flood_bind_groups = [
+            jump_flood_bind_group(&textures.seeds[0]),
+            jump_flood_bind_group(&textures.seeds[1]),
+        ];
+
+        let outline_bind_group = BindGroupBuilder::default()
+            .add_binding(0, textures.mask.default_view)
+            .add_binding(1, textures.seeds[result].default_view)
+            .add_binding(2, outline_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(
+            outline_shaders.outline_layout.bind_group(0).id,
+            &outline_bind_group,
+        );
+
+        let mut outline_phase = RenderPhase::<OutlinePhase>::default();
+        for (i, (mesh_index, _)) in outline_meta.outlined_meshes.iter().enumerate() {
+            outline_phase.add(Drawable {
+                draw_function: draw_outline_mask,
+                draw_key: i,
+                sort_key: 0,
+                entity: extracted_meshes.meshes[*mesh_index].entity,
+                clip: None,
+            });
+        }
+
+        commands.entity(view_entity).insert_bundle((
+            outline_phase,
+            OutlineViewBindGroups {
+                view_bind_group: view_bind_group.id,
+                jump_flood_bind_groups,
+                outline_bind_group: outline_bind_group.id,
+            },
+        ));
+    }
+}
+
+/// Writes the [`OutlineMeta`] uniforms before the views are drawn.
+pub struct OutlineUniformsNode;
+
+impl Node for OutlineUniformsNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let outline_meta = world.get_resource::<OutlineMeta>().unwrap();
+        outline_meta
+            .outline_uniforms
+            .write_to_uniform_buffer(render_context);
+        outline_meta
+            .step_uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Draws the outlined meshes into the mask of a view, spreads them with the jump flood, and draws
+/// the outlines on top of the view.
+pub struct OutlinePassNode {
+    query: QueryState<(
+        &'static ExtractedView,
+        &'static RenderPhase<OutlinePhase>,
+        &'static ViewOutlineTextures,
+        &'static OutlineViewBindGroups,
+    )>,
+}
+
+impl OutlinePassNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for OutlinePassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(OutlinePassNode::IN_COLOR_ATTACHMENT, SlotType::TextureView),
+            SlotInfo::new(OutlinePassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        // views only have an outline phase when there are outlines to draw
+        let (view, outline_phase, textures, bind_groups) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(view) => view,
+                Err(_) => return Ok(()),
+            };
+        let outline_shaders = world.get_resource::<OutlineShaders>().unwrap();
+        let outline_meta = world.get_resource::<OutlineMeta>().unwrap();
+        let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
+        // the passes write every pixel of their targets
+        let clear = Operations {
+            load: LoadOp::Clear(Color::NONE),
+            store: true,
+        };
+
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(textures.mask.default_view),
+                    resolve_target: None,
+                    ops: clear.clone(),
+                },
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(textures.seeds[0].default_view),
+                    resolve_target: None,
+                    ops: clear.clone(),
+                },
+            ],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut draw_functions = draw_functions.write();
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                for drawable in outline_phase.drawn_things.iter() {
+                    let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
+                    draw_function.draw(
+                        world,
+                        &mut tracked_pass,
+                        view_entity,
+                        drawable.draw_key,
+                        drawable.sort_key,
+                    );
+                }
+            },
+        );
+
+        let layout = &outline_shaders.jump_flood_layout;
+        for (i, step) in outline_meta.steps.iter().enumerate() {
+            let pass_descriptor = PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(textures.seeds[(i + 1) % 2].default_view),
+                    resolve_target: None,
+                    ops: clear.clone(),
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
+            render_context.begin_render_pass(
+                &pass_descriptor,
+                &mut |render_pass: &mut dyn RenderPass| {
+                    let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                    tracked_pass.set_pipeline(outline_shaders.jump_flood_pipeline);
+                    tracked_pass.set_bind_group(
+                        0,
+                        layout.bind_group(0).id,
+                        bind_groups.jump_flood_bind_groups[i % 2],
+                        None,
+                    );
+                    tracked_pass.set_bind_group(
+                        1,
+                        layout.bind_group(1).id,
+                        outline_meta.step_bind_groups[step.chunk],
+                        Some(&[step.offset]),
+                    );
+                    tracked_pass.draw(0..3, 0..1);
+                },
+            );
+        }
+
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(
+                    graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?,
+                ),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                tracked_pass.set_pipeline(outline_shaders.outline_pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    outline_shaders.outline_layout.bind_group(0).id,
+                    bind_groups.outline_bind_group,
+                    None,
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
+
+type DrawOutlineMaskParams<'a> = (
+    Res<'a, OutlineShaders>,
+    Res<'a, OutlineMeta>,
+    Res<'a, ExtractedMeshes>,
+    Query<
+        'a,
+        (
+            &'a ViewUniform,
+            &'a OutlineViewBindGroups,
+            Option<&'a ViewMeshLods>,
+        ),
+    >,
+);
+
+/// Draws an outlined mesh into the mask of a view. The draw key is the index of the mesh in the
+/// outlined meshes of the [`OutlineMeta`].
+pub struct DrawOutlineMask {
+    params: SystemState<DrawOutlineMaskParams<'static>>,
+}
+
+impl DrawOutlineMask {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawOutlineMask {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (outline_shaders, outline_meta, extracted_meshes, views) = self.params.get(world);
+        let (view_uniforms, outline_view_bind_groups, view_mesh_lods) = views.get(view).unwrap();
+        let layout = &outline_shaders.mask_pipeline_descriptor.layout;
+        let (mesh_index, outline_uniform) = outline_meta.outlined_meshes[draw_key];
+        let (extracted_mesh, mesh_level) = extracted_meshes.get(mesh_index, view_mesh_lods);
+        // the pipelines are only created for triangle lists
+        if !mesh_level.key.is_triangle_list() {
+            return;
+        }
+        pass.set_pipeline(
+            outline_shaders
+                .mask_pipelines
+                .get(mesh_level.key.vertex_buffer),
+        );
+        pass.set_bind_group(
+            0,
+            layout.bind_group(0).id,
+            outline_view_bind_groups.view_bind_group,
+            Some(&[view_uniforms.view_uniform_offset]),
+        );
+        pass.set_bind_group(
+            1,
+            layout.bind_group(1).id,
+            outline_meta.mesh_transform_bind_groups[extracted_mesh.transform_binding.chunk],
+            Some(&[extracted_mesh.transform_binding.offset]),
+        );
+        pass.set_bind_group(
+            2,
+            layout.bind_group(2).id,
+            outline_meta.outline_bind_groups[outline_uniform.chunk],
+            Some(&[outline_uniform.offset]),
+        );
+        mesh_level.draw(pass);
+    }
+}
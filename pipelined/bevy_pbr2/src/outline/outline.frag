@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Mask;
+layout(set = 0, binding = 1) uniform texture2D t_Seeds;
+layout(set = 0, binding = 2) uniform sampler s_Nearest;
+
+void main() {
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec4 seed = texelFetch(sampler2D(t_Seeds, s_Nearest), coords, 0);
+    // the pixels covered by outlined meshes are their own seeds, and are left as they are
+    if (seed.w == 0.0 || seed.xy == vec2(0.0)) {
+        discard;
+    }
+    vec4 color = texelFetch(sampler2D(t_Mask, s_Nearest), coords + ivec2(seed.xy), 0);
+    // antialias the outer edge over a pixel
+    float coverage = clamp(seed.z + 0.5 - length(seed.xy), 0.0, 1.0);
+    if (coverage == 0.0) {
+        discard;
+    }
+    o_Target = vec4(color.rgb, color.a * coverage);
+}
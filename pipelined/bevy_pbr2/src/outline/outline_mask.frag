@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) out vec4 o_Mask;
+layout(location = 1) out vec4 o_Seed;
+
+layout(set = 2, binding = 0) uniform Outline {
+    vec4 Color;
+    float Width;
+};
+
+void main() {
+    o_Mask = Color;
+    // covered pixels are their own nearest seed: no offset, and the width the seed spreads to
+    o_Seed = vec4(0.0, 0.0, Width, 1.0);
+}
@@ -0,0 +1,698 @@
+use crate::render::{ExtractedMeshLevel, MeshPipelines};
+use bevy_app::prelude::*;
+use bevy_asset::{AddAsset, Assets, Handle};
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_math::{Mat4, Vec3, Vec4};
+use bevy_reflect::{Reflect, TypeUuid};
+use bevy_render2::{
+    camera::{camera_target_size, ActiveCameras, Camera, CameraPlugin},
+    color::Color,
+    core_pipeline::{self, Cameras3d, CorePipelineSettings, DepthMode, Transparent3dPhase},
+    mesh::Mesh,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{
+        Aabb, ExternalWindows, ExtractedView, ExtractedWindows, FloatingOrigin, OffscreenTarget,
+        OffscreenTargets, ViewMeta, ViewUniform,
+    },
+    RenderApp, RenderStage,
+};
+use bevy_transform::{
+    components::{GlobalTransform, Transform},
+    TransformSystem,
+};
+use bevy_utils::HashMap;
+use bevy_window::{WindowId, Windows};
+use crevice::std140::AsStd140;
+
+/// Renders the scene mirrored about the plane of each [`PlanarReflection`] into an
+/// [`OffscreenTarget`], and draws the meshes of entities with a [`PlanarReflectionMaterial`]
+/// with the reflection of their plane, see [`PlanarReflectionBundle`].
+///
+/// Each plane adds a camera rendering its reflection, drawn with the [`draw_3d_graph`] before the
+/// other [`Cameras3d`]. Must be added after [`crate::PbrPlugin`].
+///
+/// [`draw_3d_graph`]: core_pipeline::draw_3d_graph
+#[derive(Debug, Default)]
+pub struct PlanarReflectionPlugin;
+
+impl Plugin for PlanarReflectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PlanarReflectionMaterial>()
+            .register_type::<PlanarReflection>()
+            .init_resource::<PlanarReflectionViews>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_planar_reflections
+                    .system()
+                    .after(TransformSystem::TransformPropagate),
+            );
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_planar_reflections.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_planar_reflections.system())
+            .add_system_to_stage(RenderStage::Queue, queue_planar_reflections.system())
+            .init_render_resource::<PlanarReflectionShaders>()
+            .init_resource::<PlanarReflectionMeta>();
+        let draw_planar_reflection = DrawPlanarReflection::new(&mut render_app.world);
+        render_app
+            .world
+            .get_resource::<DrawFunctions>()
+            .unwrap()
+            .write()
+            .add(draw_planar_reflection);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("planar_reflection", PlanarReflectionNode);
+        graph
+            .add_node_edge(
+                "planar_reflection",
+                core_pipeline::node::MAIN_PASS_DEPENDENCIES,
+            )
+            .unwrap();
+    }
+}
+
+/// Reflects the scene seen by a camera in the plane of the entity, its local xz plane with the
+/// normal pointing along its local y axis like [`shape::Plane`](bevy_render2::mesh::shape::Plane).
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PlanarReflection {
+    /// The name of the active camera that sees the reflection.
+    pub camera: String,
+    /// The size of the reflection relative to the target of the camera, e.g. 0.5 to render it at
+    /// half the resolution.
+    pub resolution_scale: f32,
+    /// Moves the plane clipping the reflection towards the camera, to hide the parts of objects
+    /// that are below the surface, such as the bottom of a wave.
+    pub clip_offset: f32,
+}
+
+impl Default for PlanarReflection {
+    fn default() -> Self {
+        PlanarReflection {
+            camera: CameraPlugin::CAMERA_3D.to_string(),
+            resolution_scale: 1.0,
+            clip_offset: 0.0,
+        }
+    }
+}
+
+/// Draws a mesh with the reflection of its [`PlanarReflection`], blended over `color` by
+/// `reflectivity`: 1.0 is a perfect mirror and lower values tint it like water.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "776a997f-cc25-409a-b104-e6cbfe1b8131"]
+pub struct PlanarReflectionMaterial {
+    pub color: Color,
+    pub reflectivity: f32,
+}
+
+impl Default for PlanarReflectionMaterial {
+    fn default() -> Self {
+        PlanarReflectionMaterial {
+            color: Color::BLACK,
+            reflectivity: 1.0,
+        }
+    }
+}
+
+impl From<Color> for PlanarReflectionMaterial {
+    fn from(color: Color) -> Self {
+        PlanarReflectionMaterial {
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct PlanarReflectionBundle {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<PlanarReflectionMaterial>,
+    pub reflection: PlanarReflection,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// Marks the cameras rendering the reflection of a [`PlanarReflection`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlanarReflectionCamera;
+
+/// The camera and target rendering the reflection of a [`PlanarReflection`].
+#[derive(Debug, Clone)]
+pub struct PlanarReflectionView {
+    pub camera: Entity,
+    /// The [`OffscreenTarget`] the reflection is rendered to.
+    pub target: WindowId,
+    /// The name of the camera in the [`ActiveCameras`] and [`Cameras3d`].
+    pub name: String,
+    /// The view projection of the camera, which maps world space points on the plane to their
+    /// position in the reflection.
+    pub view_projection: Mat4,
+}
+
+/// The [`PlanarReflectionView`] of each [`PlanarReflection`], by entity.
+#[derive(Debug, Default)]
+pub struct PlanarReflectionViews {
+    views: HashMap<Entity, PlanarReflectionView>,
+}
+
+impl PlanarReflectionViews {
+    pub fn get(&self, entity: Entity) -> Option<&PlanarReflectionView> {
+        self.views.get(&entity)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Entity, &PlanarReflectionView)> {
+        self.views.iter()
+    }
+}
+
+/// Returns the transform and projection of the camera that sees the reflection of a camera with
+/// `camera_transform` and `projection`, in the plane through `point` with `normal`. The near
+/// plane of the projection is replaced by the plane, moved `clip_offset` towards the camera, so
+/// nothing behind it is reflected.
+///
+/// Mirroring the camera would flip the winding of every triangle, so the reflection is seen by
+/// a camera looking in the mirrored direction instead, which sees the same image flipped
+/// horizontally. Sampling it with the view projection of that camera undoes the flip.
+pub fn reflect_camera(
+    camera_transform: &GlobalTransform,
+    projection: Mat4,
+    point: Vec3,
+    normal: Vec3,
+    clip_offset: f32,
+    depth_mode: DepthMode,
+) -> (GlobalTransform, Mat4) {
+    let normal = normal.normalize();
+    let reflect = |vector: Vec3| vector - 2.0 * vector.dot(normal) * normal;
+    let position = point + reflect(camera_transform.translation - point);
+    let forward = reflect(camera_transform.rotation * -Vec3::Z);
+    let up = reflect(camera_transform.rotation * Vec3::Y);
+    let mut transform =
+        GlobalTransform::from_translation(position).looking_at(position + forward, up);
+    transform.scale = camera_transform.scale;
+
+    // the side of the plane the camera is on is kept, whichever way the normal points
+    let side = if normal.dot(camera_transform.translation - point) < 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    let plane = side.extend(-side.dot(point) - clip_offset);
+    // planes are transformed by the inverse transpose of the view matrix, which is the transpose
+    // of the camera's matrix
+    let view_plane = transform.compute_matrix().transpose() * plane;
+    (
+        transform,
+        oblique_projection(projection, view_plane, depth_mode),
+    )
+}
+
+/// Replaces the near plane of `projection` with `clip_plane`, in view space, so only points on
+/// its positive side are drawn. The far plane is tilted to keep the depth range, as described in
+/// Eric Lengyel's "Oblique View Frustum Depth Projection and Clipping".
+///
+/// The camera must be on the negative side of the plane.
+pub fn oblique_projection(projection: Mat4, clip_plane: Vec4, depth_mode: DepthMode) -> Mat4 {
+    // the corner of the far plane opposite to the clip plane, which stays at the far depth
+    let corner = projection.inverse()
+        * Vec4::new(
+            clip_plane.x.signum(),
+            clip_plane.y.signum(),
+            depth_mode.clear_value(),
+            1.0,
+        );
+    let distance = clip_plane.dot(corner);
+    if distance.abs() <= f32::EPSILON {
+        return projection;
+    }
+    let plane = clip_plane / distance;
+    // the columns of the transpose are the rows of the matrix
+    let mut rows = projection.transpose();
+    rows.z_axis = match depth_mode {
+        DepthMode::Standard => plane,
+        // the near plane is where the depth reaches w
+        DepthMode::ReverseZ => rows.w_axis - plane,
+    };
+    rows.transpose()
+}
+
+/// Creates the cameras and targets of new [`PlanarReflection`]s, removes those of despawned
+/// ones, and moves the cameras to mirror the cameras they reflect.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_planar_reflections(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    external_windows: Res<ExternalWindows>,
+    depth_mode: Option<Res<DepthMode>>,
+    mut offscreen_targets: ResMut<OffscreenTargets>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    mut cameras_3d: ResMut<Cameras3d>,
+    mut reflection_views: ResMut<PlanarReflectionViews>,
+    planes: Query<(Entity, &PlanarReflection, &GlobalTransform), Without<PlanarReflectionCamera>>,
+    cameras: Query<(&Camera, &GlobalTransform), Without<PlanarReflectionCamera>>,
+    mut reflection_cameras: Query<
+        (&mut Camera, &mut Transform, &mut GlobalTransform),
+        With<PlanarReflectionCamera>,
+    >,
+) {
+    reflection_views.views.retain(|entity, view| {
+        if planes.get(*entity).is_ok() {
+            return true;
+        }
+        commands.entity(view.camera).despawn();
+        offscreen_targets.remove(view.target);
+        active_cameras.remove(&view.name);
+        cameras_3d.names.retain(|name| *name != view.name);
+        false
+    });
+
+    let depth_mode = depth_mode.map(|mode| *mode).unwrap_or_default();
+    for (entity, reflection, plane_transform) in planes.iter() {
+        let (camera, camera_transform) = match active_cameras
+            .get(&reflection.camera)
+            .and_then(|active_camera| active_camera.entity)
+            .and_then(|camera| cameras.get(camera).ok())
+        {
+            Some(camera) => camera,
+            None => continue,
+        };
+        let (width, height) =
+            match camera_target_size(camera, &windows, &offscreen_targets, &external_windows) {
+                Some(size) => size,
+                None => continue,
+            };
+        let scale = |size: u32| ((size as f32 * reflection.resolution_scale) as u32).max(1);
+        let target = OffscreenTarget::new(scale(width), scale(height));
+        let (transform, projection) = reflect_camera(
+            camera_transform,
+            camera.projection_matrix,
+            plane_transform.translation,
+            plane_transform.rotation * Vec3::Y,
+            reflection.clip_offset,
+            depth_mode,
+        );
+        let view_projection = projection * transform.compute_matrix().inverse();
+
+        if let Some(view) = reflection_views.views.get_mut(&entity) {
+            // only set the target when it changes, as that recreates its texture
+            if offscreen_targets.get(view.target) != Some(&target) {
+                offscreen_targets.set(view.target, target);
+            }
+            if let Ok((mut camera, mut camera_transform, mut camera_global_transform)) =
+                reflection_cameras.get_mut(view.camera)
+            {
+                camera.projection_matrix = projection;
+                *camera_transform = transform.into();
+                *camera_global_transform = transform;
+            }
+            view.view_projection = view_projection;
+        } else {
+            let target = offscreen_targets.add(target);
+            let name = format!("planar_reflection_{}", entity.id());
+            let camera = commands
+                .spawn_bundle((
+                    Camera {
+                        projection_matrix: projection,
+                        name: Some(name.clone()),
+                        window: target,
+                        ..Default::default()
+                    },
+                    Transform::from(transform),
+                    transform,
+                    PlanarReflectionCamera,
+                ))
+                .id();
+            active_cameras.add(&name);
+            // reflections are rendered before the views that show them
+            cameras_3d.names.insert(0, name.clone());
+            reflection_views.views.insert(
+                entity,
+                PlanarReflectionView {
+                    camera,
+                    target,
+                    name,
+                    view_projection,
+                },
+            );
+        }
+    }
+}
+
+pub struct PlanarReflectionShaders {
+    /// The pipelines for each [`MeshVertexBuffer`](crate::MeshVertexBuffer).
+    pipelines: MeshPipelines,
+    pipeline_descriptor: RenderPipelineDescriptor,
+    sampler: SamplerId,
+}
+
+impl FromWorld for PlanarReflectionShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let vertex_shader =
+            Shader::from_glsl(ShaderStage::Vertex, include_str!("planar_reflection.vert"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let fragment_shader = Shader::from_glsl(
+            ShaderStage::Fragment,
+            include_str!("planar_reflection.frag"),
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let mut pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                // the surface can be seen from below, e.g. from under water
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            ..RenderPipelineDescriptor::default_config(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        pipeline_descriptor.color_target_states[0].format = settings.main_texture_format();
+        if let Some(depth_stencil) = pipeline_descriptor.depth_stencil.as_mut() {
+            depth_stencil.format = settings.depth_format;
+            depth_stencil.depth_compare = depth_mode.compare(CompareFunction::Less);
+        }
+
+        let pipelines = MeshPipelines::new(pipeline_descriptor.clone());
+
+        PlanarReflectionShaders {
+            pipelines,
+            pipeline_descriptor,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+struct GpuPlanarReflection {
+    model: Mat4,
+    reflection_view_proj: Mat4,
+    color: Vec4,
+    reflectivity: f32,
+}
+
+struct ExtractedPlanarReflection {
+    entity: Entity,
+    level: ExtractedMeshLevel,
+    uniform: GpuPlanarReflection,
+    /// The camera rendering the reflection, which doesn't draw the surface it is sampled by.
+    camera: Entity,
+    target: WindowId,
+    /// The local bounds of the mesh, if it can be culled.
+    aabb: Option<Aabb>,
+    /// The index of the surface in [`PlanarReflectionMeta::uniforms`].
+    binding: DynamicUniformIndex,
+    bind_group: Option<BindGroupId>,
+}
+
+pub struct ExtractedPlanarReflections {
+    surfaces: Vec<ExtractedPlanarReflection>,
+}
+
+pub fn extract_planar_reflections(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<PlanarReflectionMaterial>>,
+    reflection_views: Res<PlanarReflectionViews>,
+    floating_origin: Res<FloatingOrigin>,
+    query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Handle<Mesh>,
+        &Handle<PlanarReflectionMaterial>,
+        Option<&Aabb>,
+    )>,
+) {
+    let mut surfaces = Vec::new();
+    for (entity, transform, mesh_handle, material_handle, aabb) in query.iter() {
+        let view = match reflection_views.get(entity) {
+            Some(view) => view,
+            None => continue,
+        };
+        let (level, material) = match (
+            meshes
+                .get(mesh_handle)
+                .and_then(|mesh| ExtractedMeshLevel::base(mesh).ok().flatten()),
+            materials.get(material_handle),
+        ) {
+            (Some(level), Some(material)) => (level, material),
+            _ => continue,
+        };
+        // the pipelines are only created for triangle lists
+        if !level.key.is_triangle_list() {
+            continue;
+        }
+        // the surface is drawn relative to the floating origin, so the view projection of the
+        // reflection, which is in world space, is moved back from it
+        let reflection_view_proj =
+            view.view_projection * Mat4::from_translation(floating_origin.origin);
+        surfaces.push(ExtractedPlanarReflection {
+            entity,
+            level,
+            uniform: GpuPlanarReflection {
+                model: floating_origin.to_render_matrix(transform),
+                reflection_view_proj,
+                color: material.color.as_linear_rgba_f32().into(),
+                reflectivity: material.reflectivity,
+            },
+            camera: view.camera,
+            target: view.target,
+            aabb: aabb.copied(),
+            binding: DynamicUniformIndex::default(),
+            bind_group: None,
+        });
+    }
+    commands.insert_resource(ExtractedPlanarReflections { surfaces });
+}
+
+#[derive(Default)]
+pub struct PlanarReflectionMeta {
+    uniforms: DynamicUniformVec<GpuPlanarReflection>,
+}
+
+/// The planar reflection bind group of a view.
+pub struct ViewPlanarReflections {
+    bind_group: BindGroupId,
+}
+
+pub fn prepare_planar_reflections(
+    render_resources: Res<RenderResources>,
+    mut planar_reflection_meta: ResMut<PlanarReflectionMeta>,
+    mut extracted_planar_reflections: ResMut<ExtractedPlanarReflections>,
+) {
+    planar_reflection_meta.uniforms.reserve_and_clear(
+        extracted_planar_reflections.surfaces.len(),
+        &render_resources,
+    );
+    for surface in extracted_planar_reflections.surfaces.iter_mut() {
+        surface.binding = planar_reflection_meta.uniforms.push(surface.uniform);
+    }
+    planar_reflection_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_planar_reflections(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut planar_reflection_shaders: ResMut<PlanarReflectionShaders>,
+    planar_reflection_meta: Res<PlanarReflectionMeta>,
+    view_meta: Res<ViewMeta>,
+    extracted_windows: Res<ExtractedWindows>,
+    mut extracted_planar_reflections: ResMut<ExtractedPlanarReflections>,
+    mut views: Query<(
+        Entity,
+        &ExtractedView,
+        &ViewUniform,
+        &mut RenderPhase<Transparent3dPhase>,
+    )>,
+) {
+    if extracted_planar_reflections.surfaces.is_empty() {
+        return;
+    }
+
+    for surface in extracted_planar_reflections.surfaces.iter() {
+        planar_reflection_shaders
+            .pipelines
+            .specialize(&render_resources, surface.level.key.vertex_buffer);
+    }
+    let layout = &planar_reflection_shaders.pipeline_descriptor.layout;
+    for surface in extracted_planar_reflections.surfaces.iter_mut() {
+        // the reflection is rendered to the texture of its target, created in prepare
+        let reflection = match extracted_windows
+            .get(&surface.target)
+            .and_then(|window| window.swap_chain_texture)
+        {
+            Some(reflection) => reflection,
+            None => continue,
+        };
+        let bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                planar_reflection_meta
+                    .uniforms
+                    .binding(surface.binding.chunk),
+            )
+            .add_binding(1, reflection)
+            .add_binding(2, planar_reflection_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(1).id, &bind_group);
+        surface.bind_group = Some(bind_group.id);
+    }
+
+    let draw_planar_reflection = draw_functions
+        .read()
+        .get_id::<DrawPlanarReflection>()
+        .unwrap();
+    for (view_entity, view, view_uniform, mut transparent_phase) in views.iter_mut() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &view_bind_group);
+        commands.entity(view_entity).insert(ViewPlanarReflections {
+            bind_group: view_bind_group.id,
+        });
+
+        let frustum = view.frustum();
+        for (i, surface) in extracted_planar_reflections.surfaces.iter().enumerate() {
+            let visible = surface.aabb.map_or(true, |aabb| {
+                frustum.intersects_aabb(&aabb, &surface.uniform.model)
+            });
+            // a surface can't sample its reflection while it is rendered
+            if surface.bind_group.is_none() || surface.camera == view_entity || !visible {
+                continue;
+            }
+            transparent_phase.add(Drawable {
+                draw_function: draw_planar_reflection,
+                draw_key: i,
+                sort_key: 0,
+                entity: surface.entity,
+                clip: None,
+            });
+        }
+    }
+}
+
+// TODO: this logic can be moved to prepare_planar_reflections once wgpu::Queue is exposed directly
+pub struct PlanarReflectionNode;
+
+impl Node for PlanarReflectionNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let planar_reflection_meta = world.get_resource::<PlanarReflectionMeta>().unwrap();
+        planar_reflection_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+type DrawPlanarReflectionParams<'a> = (
+    Res<'a, PlanarReflectionShaders>,
+    Res<'a, ExtractedPlanarReflections>,
+    Query<'a, (&'a ViewUniform, &'a ViewPlanarReflections)>,
+);
+pub struct DrawPlanarReflection {
+    params: SystemState<DrawPlanarReflectionParams<'static>>,
+}
+
+impl DrawPlanarReflection {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawPlanarReflection {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (planar_reflection_shaders, extracted_planar_reflections, views) =
+            self.params.get(world);
+        let (view_uniform, view_planar_reflections) = views.get(view).unwrap();
+        let layout = &planar_reflection_shaders.pipeline_descriptor.layout;
+        let surface = &extracted_planar_reflections.surfaces[draw_key];
+        pass.set_pipeline(
+            planar_reflection_shaders
+                .pipelines
+                .get(surface.level.key.vertex_buffer),
+        );
+        pass.set_bind_group(
+            0,
+            layout.bind_group(0).id,
+            view_planar_reflections.bind_group,
+            Some(&[view_uniform.view_uniform_offset]),
+        );
+        pass.set_bind_group(
+            1,
+            layout.bind_group(1).id,
+            surface.bind_group.unwrap(),
+            Some(&[surface.binding.offset]),
+        );
+        surface.level.draw(pass);
+    }
+}
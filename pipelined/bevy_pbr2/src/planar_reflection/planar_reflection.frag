@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) in vec4 v_ReflectionPosition;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform PlanarReflection {
+    mat4 Model;
+    mat4 ReflectionViewProj;
+    vec4 Color;
+    float Reflectivity;
+};
+layout(set = 1, binding = 1) uniform texture2D t_Reflection;
+layout(set = 1, binding = 2) uniform sampler s_Reflection;
+
+void main() {
+    // points on the plane are where the reflection camera sees them, so projecting them with its
+    // view projection gives their position in the reflection
+    vec2 ndc = v_ReflectionPosition.xy / v_ReflectionPosition.w;
+    vec2 uv = ndc * vec2(0.5, -0.5) + 0.5;
+    vec3 reflection = texture(sampler2D(t_Reflection, s_Reflection), uv).rgb;
+    o_Target = vec4(mix(Color.rgb, reflection, Reflectivity), Color.a);
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+
+layout(location = 0) out vec4 v_ReflectionPosition;
+
+layout(set = 0, binding = 0) uniform View {
+    mat4 ViewProj;
+    vec3 ViewWorldPosition;
+};
+
+layout(set = 1, binding = 0) uniform PlanarReflection {
+    mat4 Model;
+    mat4 ReflectionViewProj;
+    vec4 Color;
+    float Reflectivity;
+};
+
+void main() {
+    vec4 world_position = Model * vec4(Vertex_Position, 1.0);
+    // interpolated in clip space and divided per fragment, so the lookup stays perspective correct
+    v_ReflectionPosition = ReflectionViewProj * world_position;
+    gl_Position = ViewProj * world_position;
+}
@@ -0,0 +1,537 @@
+use bevy_app::prelude::*;
+use bevy_asset::{AddAsset, Assets, Handle};
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_math::{Mat4, Vec2, Vec4};
+use bevy_reflect::TypeUuid;
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{self, CorePipelineSettings, DepthMode, Transparent3dPhase},
+    mesh::Mesh,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, BufferId, DynamicUniformIndex, DynamicUniformVec,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    view::{Aabb, ExtractedView, FloatingOrigin, ViewMeta, ViewUniform},
+    RenderApp, RenderStage,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use bevy_utils::HashMap;
+use crevice::std140::AsStd140;
+
+/// Draws the meshes of entities with a [`PointCloudMaterial`] as points, see
+/// [`PointCloudBundle`].
+#[derive(Debug, Default)]
+pub struct PointCloudPlugin;
+
+impl Plugin for PointCloudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PointCloudMaterial>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_point_clouds.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_point_clouds.system())
+            .add_system_to_stage(RenderStage::Queue, queue_point_clouds.system())
+            .init_render_resource::<PointCloudShaders>()
+            .init_resource::<PointCloudMeta>();
+        let draw_point_cloud = DrawPointCloud::new(&mut render_app.world);
+        render_app
+            .world
+            .get_resource::<DrawFunctions>()
+            .unwrap()
+            .write()
+            .add(draw_point_cloud);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("point_cloud", PointCloudNode);
+        graph
+            .add_node_edge("point_cloud", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+    }
+}
+
+/// Draws every vertex of a mesh as a square point of `point_size` pixels, multiplied with the
+/// [`Mesh::ATTRIBUTE_COLOR`] of the vertex when the mesh has one. The topology and indices of
+/// the mesh are ignored.
+///
+/// wgpu only rasterizes points of one pixel, so larger points are drawn as a quad per point.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "2f3c8e1a-6b4d-4c9e-a7f2-5d1e9b3c8a60"]
+pub struct PointCloudMaterial {
+    pub color: Color,
+    /// The width and height of each point, in pixels.
+    pub point_size: f32,
+}
+
+impl Default for PointCloudMaterial {
+    fn default() -> Self {
+        PointCloudMaterial {
+            color: Color::WHITE,
+            point_size: 1.0,
+        }
+    }
+}
+
+impl From<Color> for PointCloudMaterial {
+    fn from(color: Color) -> Self {
+        PointCloudMaterial {
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct PointCloudBundle {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<PointCloudMaterial>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// The variant of the point cloud pipelines a mesh is drawn with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PointCloudPipelineKey {
+    vertex_colors: bool,
+    /// Whether points are drawn as instanced quads rather than as a point list.
+    quads: bool,
+    /// The stride and attributes of the vertex buffer of the mesh.
+    stride: u64,
+    attributes: Vec<VertexAttribute>,
+}
+
+pub struct PointCloudShaders {
+    /// The descriptor of the pipeline for meshes with only positions, which has the same bind
+    /// group layouts as every other variant.
+    pipeline_descriptor: RenderPipelineDescriptor,
+    depth_mode: DepthMode,
+    settings: CorePipelineSettings,
+    /// `None` for meshes that don't provide the vertex inputs of the shaders.
+    pipelines: HashMap<PointCloudPipelineKey, Option<PipelineId>>,
+}
+
+impl PointCloudShaders {
+    /// Returns the pipeline for `key`, creating it if it doesn't exist yet.
+    fn specialize(
+        &mut self,
+        render_resources: &RenderResources,
+        key: &PointCloudPipelineKey,
+    ) -> Option<PipelineId> {
+        if let Some(pipeline) = self.pipelines.get(key) {
+            return *pipeline;
+        }
+        let vertex_buffer = VertexBufferLayout {
+            name: "Vertex".into(),
+            stride: key.stride,
+            step_mode: if key.quads {
+                InputStepMode::Instance
+            } else {
+                InputStepMode::Vertex
+            },
+            attributes: key.attributes.clone(),
+        };
+        let pipeline = point_cloud_pipeline_descriptor(
+            render_resources,
+            self.depth_mode,
+            self.settings,
+            key.vertex_colors,
+            key.quads,
+            vertex_buffer,
+        )
+        .ok()
+        .map(|descriptor| render_resources.create_render_pipeline(&descriptor));
+        self.pipelines.insert(key.clone(), pipeline);
+        pipeline
+    }
+}
+
+impl FromWorld for PointCloudShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let vertex_buffer = VertexBufferLayout::build("Vertex")
+            .add_attribute(0, Mesh::ATTRIBUTE_POSITION, VertexFormat::Float32x3)
+            .finish();
+        PointCloudShaders {
+            pipeline_descriptor: point_cloud_pipeline_descriptor(
+                render_resources,
+                depth_mode,
+                settings,
+                false,
+                false,
+                vertex_buffer,
+            )
+            .unwrap(),
+            depth_mode,
+            settings,
+            pipelines: HashMap::default(),
+        }
+    }
+}
+
+/// Creates the descriptor of a point cloud pipeline reading `vertex_buffer`, or returns why the
+/// buffer doesn't provide the inputs of the vertex shader.
+fn point_cloud_pipeline_descriptor(
+    render_resources: &RenderResources,
+    depth_mode: DepthMode,
+    settings: CorePipelineSettings,
+    vertex_colors: bool,
+    quads: bool,
+    vertex_buffer: VertexBufferLayout,
+) -> Result<RenderPipelineDescriptor, VertexLayoutError> {
+    let mut shader_defs = Vec::new();
+    if vertex_colors {
+        shader_defs.push(String::from("VERTEX_COLORS"));
+    }
+    if quads {
+        shader_defs.push(String::from("QUADS"));
+    }
+    let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("point_cloud.vert"))
+        .get_spirv_shader(Some(&shader_defs))
+        .unwrap();
+    let fragment_shader =
+        Shader::from_glsl(ShaderStage::Fragment, include_str!("point_cloud.frag"))
+            .get_spirv_shader(None)
+            .unwrap();
+
+    let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+    let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+    vertex_layout.validate_vertex_buffers(std::slice::from_ref(&vertex_buffer))?;
+
+    let mut pipeline_layout =
+        PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+    let vertex = render_resources.create_shader_module(&vertex_shader);
+    let fragment = render_resources.create_shader_module(&fragment_shader);
+
+    pipeline_layout.vertex_buffer_descriptors = vec![vertex_buffer];
+
+    pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+    pipeline_layout.bind_group_mut(0).bindings[1].set_dynamic(true);
+    pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+    pipeline_layout.update_bind_group_ids();
+
+    let mut pipeline_descriptor = RenderPipelineDescriptor {
+        primitive: PrimitiveState {
+            topology: if quads {
+                PrimitiveTopology::TriangleStrip
+            } else {
+                PrimitiveTopology::PointList
+            },
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        },
+        ..RenderPipelineDescriptor::default_config(
+            ShaderStages {
+                vertex,
+                fragment: Some(fragment),
+            },
+            pipeline_layout,
+        )
+    };
+    pipeline_descriptor.color_target_states[0].format = settings.main_texture_format();
+    if let Some(depth_stencil) = pipeline_descriptor.depth_stencil.as_mut() {
+        depth_stencil.format = settings.depth_format;
+        depth_stencil.depth_compare = depth_mode.compare(CompareFunction::Less);
+    }
+    Ok(pipeline_descriptor)
+}
+
+struct ExtractedPointCloud {
+    entity: Entity,
+    transform: Mat4,
+    color: Vec4,
+    point_size: f32,
+    vertex_buffer: BufferId,
+    vertex_count: u32,
+    key: PointCloudPipelineKey,
+    /// The local bounds of the mesh, if it can be culled.
+    aabb: Option<Aabb>,
+    /// The index of the point cloud in [`PointCloudMeta::uniforms`].
+    binding: DynamicUniformIndex,
+    pipeline: Option<PipelineId>,
+}
+
+pub struct ExtractedPointClouds {
+    point_clouds: Vec<ExtractedPointCloud>,
+}
+
+pub fn extract_point_clouds(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<PointCloudMaterial>>,
+    floating_origin: Res<FloatingOrigin>,
+    query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Handle<Mesh>,
+        &Handle<PointCloudMaterial>,
+        Option<&Aabb>,
+    )>,
+) {
+    let mut point_clouds = Vec::new();
+    for (entity, transform, mesh_handle, material_handle, aabb) in query.iter() {
+        let (mesh, material) = match (meshes.get(mesh_handle), materials.get(material_handle)) {
+            (Some(mesh), Some(material)) => (mesh, material),
+            _ => continue,
+        };
+        let vertex_buffer = match mesh.gpu_data() {
+            Some(gpu_data) => gpu_data.vertex_buffer,
+            None => continue,
+        };
+        let layout = mesh.get_vertex_buffer_layout();
+        // points of one pixel are the only size rasterized natively
+        let quads = material.point_size != 1.0;
+        point_clouds.push(ExtractedPointCloud {
+            entity,
+            transform: floating_origin.to_render_matrix(transform),
+            color: material.color.as_linear_rgba_f32().into(),
+            point_size: material.point_size,
+            vertex_buffer,
+            vertex_count: mesh.count_vertices() as u32,
+            key: PointCloudPipelineKey {
+                vertex_colors: mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_some(),
+                quads,
+                stride: layout.stride,
+                attributes: layout.attributes,
+            },
+            aabb: aabb.copied(),
+            binding: DynamicUniformIndex::default(),
+            pipeline: None,
+        });
+    }
+    commands.insert_resource(ExtractedPointClouds { point_clouds });
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+struct GpuPointCloud {
+    model: Mat4,
+    color: Vec4,
+    point_size: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+struct GpuPointCloudView {
+    viewport_size: Vec2,
+}
+
+#[derive(Default)]
+pub struct PointCloudMeta {
+    uniforms: DynamicUniformVec<GpuPointCloud>,
+    /// The size of each view, to size the quads of points in pixels.
+    view_uniforms: DynamicUniformVec<GpuPointCloudView>,
+    /// One bind group per chunk of `uniforms`.
+    bind_groups: Vec<BindGroupId>,
+}
+
+/// The point cloud bind group of a view.
+pub struct ViewPointClouds {
+    /// The index of the view in [`PointCloudMeta::view_uniforms`].
+    binding: DynamicUniformIndex,
+    bind_group: Option<BindGroupId>,
+}
+
+pub fn prepare_point_clouds(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    mut point_cloud_meta: ResMut<PointCloudMeta>,
+    mut extracted_point_clouds: ResMut<ExtractedPointClouds>,
+    views: Query<(Entity, &ExtractedView), With<RenderPhase<Transparent3dPhase>>>,
+) {
+    point_cloud_meta
+        .uniforms
+        .reserve_and_clear(extracted_point_clouds.point_clouds.len(), &render_resources);
+    for point_cloud in extracted_point_clouds.point_clouds.iter_mut() {
+        point_cloud.binding = point_cloud_meta.uniforms.push(GpuPointCloud {
+            model: point_cloud.transform,
+            color: point_cloud.color,
+            point_size: point_cloud.point_size,
+        });
+    }
+
+    point_cloud_meta
+        .view_uniforms
+        .reserve_and_clear(views.iter().count(), &render_resources);
+    for (entity, view) in views.iter() {
+        let binding = point_cloud_meta.view_uniforms.push(GpuPointCloudView {
+            viewport_size: Vec2::new(view.width as f32, view.height as f32),
+        });
+        commands.entity(entity).insert(ViewPointClouds {
+            binding,
+            bind_group: None,
+        });
+    }
+
+    point_cloud_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+    point_cloud_meta
+        .view_uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_point_clouds(
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut point_cloud_shaders: ResMut<PointCloudShaders>,
+    mut point_cloud_meta: ResMut<PointCloudMeta>,
+    view_meta: Res<ViewMeta>,
+    mut extracted_point_clouds: ResMut<ExtractedPointClouds>,
+    mut views: Query<(
+        &ExtractedView,
+        &ViewUniform,
+        &mut ViewPointClouds,
+        &mut RenderPhase<Transparent3dPhase>,
+    )>,
+) {
+    if extracted_point_clouds.point_clouds.is_empty() {
+        return;
+    }
+    for point_cloud in extracted_point_clouds.point_clouds.iter_mut() {
+        // point clouds whose mesh has no positions are not drawn
+        point_cloud.pipeline = point_cloud_shaders.specialize(&render_resources, &point_cloud.key);
+    }
+
+    let layout = &point_cloud_shaders.pipeline_descriptor.layout;
+    let point_cloud_meta = &mut *point_cloud_meta;
+    point_cloud_meta.bind_groups = (0..point_cloud_meta.uniforms.chunk_count())
+        .map(|chunk| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, point_cloud_meta.uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(layout.bind_group(1).id, &bind_group);
+            bind_group.id
+        })
+        .collect();
+
+    let draw_point_cloud = draw_functions.read().get_id::<DrawPointCloud>().unwrap();
+    for (view, view_uniform, mut view_point_clouds, mut transparent_phase) in views.iter_mut() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .add_binding(
+                1,
+                point_cloud_meta
+                    .view_uniforms
+                    .binding(view_point_clouds.binding.chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &view_bind_group);
+        view_point_clouds.bind_group = Some(view_bind_group.id);
+
+        let frustum = view.frustum();
+        for (i, point_cloud) in extracted_point_clouds.point_clouds.iter().enumerate() {
+            let visible = point_cloud.aabb.map_or(true, |aabb| {
+                frustum.intersects_aabb(&aabb, &point_cloud.transform)
+            });
+            if point_cloud.pipeline.is_none() || !visible {
+                continue;
+            }
+            transparent_phase.add(Drawable {
+                draw_function: draw_point_cloud,
+                draw_key: i,
+                sort_key: 0,
+                entity: point_cloud.entity,
+                clip: None,
+            });
+        }
+    }
+}
+
+// TODO: this logic can be moved to prepare_point_clouds once wgpu::Queue is exposed directly
+pub struct PointCloudNode;
+
+impl Node for PointCloudNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let point_cloud_meta = world.get_resource::<PointCloudMeta>().unwrap();
+        point_cloud_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        point_cloud_meta
+            .view_uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+type DrawPointCloudParams<'a> = (
+    Res<'a, PointCloudShaders>,
+    Res<'a, PointCloudMeta>,
+    Res<'a, ExtractedPointClouds>,
+    Query<'a, (&'a ViewUniform, &'a ViewPointClouds)>,
+);
+pub struct DrawPointCloud {
+    params: SystemState<DrawPointCloudParams<'static>>,
+}
+
+impl DrawPointCloud {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawPointCloud {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (point_cloud_shaders, point_cloud_meta, extracted_point_clouds, views) =
+            self.params.get(world);
+        let (view_uniform, view_point_clouds) = views.get(view).unwrap();
+        let layout = &point_cloud_shaders.pipeline_descriptor.layout;
+        let point_cloud = &extracted_point_clouds.point_clouds[draw_key];
+        pass.set_pipeline(point_cloud.pipeline.unwrap());
+        pass.set_bind_group(
+            0,
+            layout.bind_group(0).id,
+            view_point_clouds.bind_group.unwrap(),
+            Some(&[
+                view_uniform.view_uniform_offset,
+                view_point_clouds.binding.offset,
+            ]),
+        );
+        pass.set_bind_group(
+            1,
+            layout.bind_group(1).id,
+            point_cloud_meta.bind_groups[point_cloud.binding.chunk],
+            Some(&[point_cloud.binding.offset]),
+        );
+        pass.set_vertex_buffer(0, point_cloud.vertex_buffer, 0);
+        if point_cloud.key.quads {
+            // the vertex buffer steps per instance, with a quad strip of 4 vertices per point
+            pass.draw(0..4, 0..point_cloud.vertex_count);
+        } else {
+            pass.draw(0..point_cloud.vertex_count, 0..1);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+#ifdef VERTEX_COLORS
+layout(location = 3) in vec4 Vertex_Color;
+#endif
+
+layout(location = 0) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform View {
+    mat4 ViewProj;
+    vec3 ViewWorldPosition;
+};
+
+layout(set = 0, binding = 1) uniform PointCloudView {
+    vec2 ViewportSize;
+};
+
+layout(set = 1, binding = 0) uniform PointCloud {
+    mat4 Model;
+    vec4 Color;
+    float PointSize;
+};
+
+void main() {
+    v_Color = Color;
+#ifdef VERTEX_COLORS
+    v_Color *= Vertex_Color;
+#endif
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+#ifdef QUADS
+    // each point is an instance of a quad strip, with its corner picked from the vertex index
+    vec2 corner = vec2(gl_VertexIndex & 1, gl_VertexIndex >> 1) * 2.0 - 1.0;
+    gl_Position.xy += corner * PointSize / ViewportSize * gl_Position.w;
+#endif
+}
@@ -1,18 +1,25 @@
-use crate::{render::MeshViewBindGroups, ExtractedMeshes, PointLight};
+use crate::{
+    render::{MeshPipelines, MeshViewBindGroups},
+    Exposure, ExtractedMeshes, MeshVertexBuffer, MeshVertexLayout, PointLight, ViewMeshLods,
+};
 use bevy_ecs::{prelude::*, system::SystemState};
 use bevy_math::{Mat4, Vec3, Vec4};
 use bevy_render2::{
+    camera::ActiveCameras,
     color::Color,
     core_pipeline::Transparent3dPhase,
     pass::*,
     pipeline::*,
+    quality::RenderQualitySettings,
     render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
     render_phase::{Draw, DrawFunctions, RenderPhase, TrackedRenderPass},
-    render_resource::{DynamicUniformVec, SamplerId, TextureId, TextureViewId},
-    renderer::{RenderContext, RenderResources},
+    render_resource::{
+        DynamicUniformVec, RenderResourceRegistry, SamplerId, TextureId, TextureViewId,
+    },
+    renderer::{RenderContext, RenderFeatures, RenderResources},
     shader::{Shader, ShaderStage, ShaderStages},
     texture::*,
-    view::{ExtractedView, ViewUniform},
+    view::{ExtractedView, FloatingOrigin, ViewUniform},
 };
 use bevy_transform::components::GlobalTransform;
 use crevice::std140::AsStd140;
@@ -41,27 +48,43 @@ pub struct GpuLight {
 pub struct GpuLights {
     len: u32,
     lights: [GpuLight; MAX_POINT_LIGHTS],
+    exposure: f32,
 }
 
 // NOTE: this must be kept in sync MAX_POINT_LIGHTS in pbr.frag
 pub const MAX_POINT_LIGHTS: usize = 10;
-pub const SHADOW_SIZE: Extent3d = Extent3d {
-    width: 1024,
-    height: 1024,
-    depth_or_array_layers: MAX_POINT_LIGHTS as u32,
-};
 pub const SHADOW_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
 pub struct ShadowShaders {
-    pub pipeline: PipelineId,
+    /// The pipelines for each [`MeshVertexBuffer`].
+    pub(crate) pipelines: MeshPipelines,
     pub pipeline_descriptor: RenderPipelineDescriptor,
     pub light_sampler: SamplerId,
 }
 
+/// The depth bias of the shadow map passes, which prevents shadow acne. Insert it before adding
+/// the [`PbrPlugin`](crate::PbrPlugin), the shadow pipelines are only created once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowDepthBias(pub DepthBiasState);
+
+impl Default for ShadowDepthBias {
+    fn default() -> Self {
+        ShadowDepthBias(DepthBiasState {
+            constant: 2,
+            slope_scale: 2.0,
+            clamp: 0.0,
+        })
+    }
+}
+
 // TODO: this pattern for initializing the shaders / pipeline isn't ideal. this should be handled by the asset system
 impl FromWorld for ShadowShaders {
     fn from_world(world: &mut World) -> Self {
         let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let shadow_depth_bias = world
+            .get_resource::<ShadowDepthBias>()
+            .cloned()
+            .unwrap_or_default();
         let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("pbr.vert"))
             .get_spirv_shader(None)
             .unwrap();
@@ -71,32 +94,8 @@ impl FromWorld for ShadowShaders {
 
         let vertex = render_resources.create_shader_module(&vertex_shader);
 
-        pipeline_layout.vertex_buffer_descriptors = vec![VertexBufferLayout {
-            stride: 32,
-            name: "Vertex".into(),
-            step_mode: InputStepMode::Vertex,
-            attributes: vec![
-                // GOTCHA! Vertex_Position isn't first in the buffer due to how Mesh sorts attributes (alphabetically)
-                VertexAttribute {
-                    name: "Vertex_Position".into(),
-                    format: VertexFormat::Float32x3,
-                    offset: 12,
-                    shader_location: 0,
-                },
-                VertexAttribute {
-                    name: "Vertex_Normals".into(),
-                    format: VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 1,
-                },
-                VertexAttribute {
-                    name: "Vertex_Uv".into(),
-                    format: VertexFormat::Float32x2,
-                    offset: 24,
-                    shader_location: 2,
-                },
-            ],
-        }];
+        pipeline_layout.vertex_buffer_descriptors =
+            vec![MeshVertexBuffer::standard(MeshVertexLayout::default()).layout()];
 
         pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
         pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
@@ -113,17 +112,15 @@ impl FromWorld for ShadowShaders {
                     read_mask: 0,
                     write_mask: 0,
                 },
-                bias: DepthBiasState {
-                    constant: 2,
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
+                bias: shadow_depth_bias.0,
             }),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleList,
                 cull_mode: Some(Face::Back),
-                // TODO: detect if this feature is enabled
-                clamp_depth: false,
+                // casters in front of the near plane still cast shadows when depth is clamped
+                clamp_depth: render_resources
+                    .features()
+                    .contains(RenderFeatures::DEPTH_CLAMPING),
                 ..Default::default()
             },
             color_target_states: vec![],
@@ -136,21 +133,26 @@ impl FromWorld for ShadowShaders {
             )
         };
 
-        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+        let pipelines = MeshPipelines::new(pipeline_descriptor.clone());
+        let light_sampler = render_resources.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            compare_function: Some(CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        world
+            .get_resource_mut::<RenderResourceRegistry>()
+            .unwrap()
+            .insert(crate::registry::SHADOW_SAMPLER, light_sampler);
 
         ShadowShaders {
-            pipeline,
+            pipelines,
             pipeline_descriptor,
-            light_sampler: render_resources.create_sampler(&SamplerDescriptor {
-                address_mode_u: AddressMode::ClampToEdge,
-                address_mode_v: AddressMode::ClampToEdge,
-                address_mode_w: AddressMode::ClampToEdge,
-                mag_filter: FilterMode::Linear,
-                min_filter: FilterMode::Linear,
-                mipmap_filter: FilterMode::Nearest,
-                compare_function: Some(CompareFunction::LessEqual),
-                ..Default::default()
-            }),
+            light_sampler,
         }
     }
 }
@@ -158,6 +160,7 @@ impl FromWorld for ShadowShaders {
 // TODO: ultimately these could be filtered down to lights relevant to actual views
 pub fn extract_lights(
     mut commands: Commands,
+    floating_origin: Res<FloatingOrigin>,
     lights: Query<(Entity, &PointLight, &GlobalTransform)>,
 ) {
     for (entity, light, transform) in lights.iter() {
@@ -166,11 +169,25 @@ pub fn extract_lights(
             intensity: light.intensity,
             range: light.range,
             radius: light.radius,
-            transform: transform.clone(),
+            transform: floating_origin.to_render(transform),
         });
     }
 }
 
+pub fn extract_exposures(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    query: Query<&Exposure>,
+) {
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(exposure) = query.get(entity) {
+                commands.get_or_spawn(entity).insert(*exposure);
+            }
+        }
+    }
+}
+
 pub struct ViewLight {
     pub depth_texture: TextureViewId,
 }
@@ -180,6 +197,8 @@ pub struct ViewLights {
     pub light_depth_texture_view: TextureViewId,
     pub lights: Vec<Entity>,
     pub gpu_light_binding_index: u32,
+    /// The chunk of [`LightMeta::view_gpu_lights`] that holds this view's lights.
+    pub gpu_light_binding_chunk: usize,
 }
 
 #[derive(Default)]
@@ -187,12 +206,15 @@ pub struct LightMeta {
     pub view_gpu_lights: DynamicUniformVec<GpuLights>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_lights(
     mut commands: Commands,
     mut texture_cache: ResMut<TextureCache>,
     render_resources: Res<RenderResources>,
+    quality: Res<RenderQualitySettings>,
     mut light_meta: ResMut<LightMeta>,
-    views: Query<Entity, With<RenderPhase<Transparent3dPhase>>>,
+    mut registry: ResMut<RenderResourceRegistry>,
+    views: Query<(Entity, Option<&Exposure>), With<RenderPhase<Transparent3dPhase>>>,
     lights: Query<&ExtractedPointLight>,
 ) {
     // PERF: view.iter().count() could be views.iter().len() if we implemented ExactSizeIterator for archetype-only filters
@@ -200,12 +222,19 @@ pub fn prepare_lights(
         .view_gpu_lights
         .reserve_and_clear(views.iter().count(), &render_resources);
 
+    // one layer per light, at the resolution of the render quality
+    let shadow_size = Extent3d::new(
+        quality.shadow_map_size,
+        quality.shadow_map_size,
+        MAX_POINT_LIGHTS as u32,
+    );
+
     // set up light data for each view
-    for entity in views.iter() {
+    for (entity, exposure) in views.iter() {
         let light_depth_texture = texture_cache.get(
             &render_resources,
             TextureDescriptor {
-                size: SHADOW_SIZE,
+                size: shadow_size,
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
@@ -219,6 +248,7 @@ pub fn prepare_lights(
         let mut gpu_lights = GpuLights {
             len: lights.iter().len() as u32,
             lights: [GpuLight::default(); MAX_POINT_LIGHTS],
+            exposure: exposure.map_or(1.0, Exposure::multiplier),
         };
 
         // TODO: this should select lights based on relevance to the view instead of the first ones that show up in a query
@@ -259,10 +289,11 @@ pub fn prepare_lights(
                         depth_texture: depth_texture_view,
                     },
                     ExtractedView {
-                        width: SHADOW_SIZE.width,
-                        height: SHADOW_SIZE.height,
+                        width: shadow_size.width,
+                        height: shadow_size.height,
                         transform: view_transform.clone(),
                         projection,
+                        viewport: None,
                     },
                     RenderPhase::<ShadowPhase>::default(),
                 ))
@@ -270,11 +301,18 @@ pub fn prepare_lights(
             view_lights.push(view_light_entity);
         }
 
+        registry.insert_view(
+            entity,
+            crate::registry::SHADOW_MAPS,
+            light_depth_texture.default_view,
+        );
+        let gpu_light_binding = light_meta.view_gpu_lights.push(gpu_lights);
         commands.entity(entity).insert(ViewLights {
             light_depth_texture: light_depth_texture.texture,
             light_depth_texture_view: light_depth_texture.default_view,
             lights: view_lights,
-            gpu_light_binding_index: light_meta.view_gpu_lights.push(gpu_lights),
+            gpu_light_binding_index: gpu_light_binding.offset,
+            gpu_light_binding_chunk: gpu_light_binding.chunk,
         });
     }
 
@@ -372,7 +410,14 @@ impl Node for ShadowPassNode {
 type DrawShadowMeshParams<'a> = (
     Res<'a, ShadowShaders>,
     Res<'a, ExtractedMeshes>,
-    Query<'a, (&'a ViewUniform, &'a MeshViewBindGroups)>,
+    Query<
+        'a,
+        (
+            &'a ViewUniform,
+            &'a MeshViewBindGroups,
+            Option<&'a ViewMeshLods>,
+        ),
+    >,
 );
 pub struct DrawShadowMesh {
     params: SystemState<DrawShadowMeshParams<'static>>,
@@ -396,10 +441,14 @@ impl Draw for DrawShadowMesh {
         _sort_key: usize,
     ) {
         let (shadow_shaders, extracted_meshes, views) = self.params.get(world);
-        let (view_uniforms, mesh_view_bind_groups) = views.get(view).unwrap();
+        let (view_uniforms, mesh_view_bind_groups, view_mesh_lods) = views.get(view).unwrap();
         let layout = &shadow_shaders.pipeline_descriptor.layout;
-        let extracted_mesh = &extracted_meshes.meshes[draw_key];
-        pass.set_pipeline(shadow_shaders.pipeline);
+        let (extracted_mesh, mesh_level) = extracted_meshes.get(draw_key, view_mesh_lods);
+        // the pipelines are only created for triangle lists
+        if !mesh_level.key.is_triangle_list() {
+            return;
+        }
+        pass.set_pipeline(shadow_shaders.pipelines.get(mesh_level.key.vertex_buffer));
         pass.set_bind_group(
             0,
             layout.bind_group(0).id,
@@ -407,18 +456,14 @@ impl Draw for DrawShadowMesh {
             Some(&[view_uniforms.view_uniform_offset]),
         );
 
+        let mesh_transform_bind_group = mesh_view_bind_groups.mesh_transform_bind_groups
+            [extracted_mesh.transform_binding.chunk];
         pass.set_bind_group(
             1,
             layout.bind_group(1).id,
-            mesh_view_bind_groups.mesh_transform_bind_group,
-            Some(&[extracted_mesh.transform_binding_offset]),
+            mesh_transform_bind_group,
+            Some(&[extracted_mesh.transform_binding.offset]),
         );
-        pass.set_vertex_buffer(0, extracted_mesh.vertex_buffer, 0);
-        if let Some(index_info) = &extracted_mesh.index_info {
-            pass.set_index_buffer(index_info.buffer, 0, IndexFormat::Uint32);
-            pass.draw_indexed(0..index_info.count, 0, 0..1);
-        } else {
-            panic!("non-indexed drawing not supported yet")
-        }
+        mesh_level.draw(pass);
     }
 }
@@ -1,182 +1,1061 @@
 mod light;
 pub use light::*;
 
-use crate::StandardMaterial;
-use bevy_asset::{Assets, Handle};
+use crate::{
+    Lightmap, MeshPhase, MotionVectorConfig, OrderIndependentTransparency, SplatMaterial,
+    StandardMaterial, Wireframe, WireframeConfig,
+};
+use bevy_asset::{Assets, Handle, HandleId};
 use bevy_ecs::{prelude::*, system::SystemState};
-use bevy_math::Mat4;
+use bevy_math::{Mat4, Vec3, Vec4};
 use bevy_render2::{
-    core_pipeline::Transparent3dPhase,
-    mesh::Mesh,
+    core_pipeline::{
+        AlphaMask3dPhase, CorePipelineSettings, DepthMode, Opaque3dPhase, Overlay3dPhase,
+        Transparent3dPhase,
+    },
+    mesh::{select_lod, Lod, LodThreshold, LodView, Mesh, MeshVertexAttribute},
     pipeline::*,
     render_graph::{Node, NodeRunError, RenderGraphContext},
     render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
-    render_resource::{BindGroupBuilder, BindGroupId, BufferId, DynamicUniformVec},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, BufferId, DynamicUniformIndex, DynamicUniformVec,
+        RenderResourceBinding, SamplerId, TextureViewId,
+    },
     renderer::{RenderContext, RenderResources},
-    shader::{Shader, ShaderStage, ShaderStages},
-    texture::{TextureFormat, TextureSampleType},
-    view::{ViewMeta, ViewUniform},
+    shader::{Shader, ShaderError, ShaderLayout, ShaderReflectOptions, ShaderStage, ShaderStages},
+    texture::{BindlessBatches, BindlessTextures, Texture, TextureSampleType},
+    view::{Aabb, ExtractedView, FloatingOrigin, Frustum, ViewMeta, ViewUniform},
 };
 use bevy_transform::components::GlobalTransform;
+use bevy_utils::{
+    tracing::{error, warn},
+    HashMap, HashSet,
+};
+use crevice::std140::AsStd140;
+use std::collections::hash_map::Entry;
+
+/// A vertex shader that draws a triangle covering the whole target with 3 vertices and no
+/// vertex buffers, for passes that process every pixel of a view.
+pub(crate) const FULLSCREEN_VERTEX_SHADER: &str = include_str!("fullscreen.vert");
+
+/// The attributes the pbr shaders read from a mesh besides its positions, normals and uvs. The
+/// shaders are compiled for each layout.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshVertexLayout {
+    /// Whether the mesh has [`Mesh::ATTRIBUTE_COLOR`].
+    pub vertex_colors: bool,
+    /// Whether the mesh has [`Mesh::ATTRIBUTE_UV_1`], which its [`Lightmap`] is sampled with.
+    pub lightmap_uvs: bool,
+}
+
+impl MeshVertexLayout {
+    pub const ALL: [MeshVertexLayout; 4] = [
+        MeshVertexLayout {
+            vertex_colors: false,
+            lightmap_uvs: false,
+        },
+        MeshVertexLayout {
+            vertex_colors: true,
+            lightmap_uvs: false,
+        },
+        MeshVertexLayout {
+            vertex_colors: false,
+            lightmap_uvs: true,
+        },
+        MeshVertexLayout {
+            vertex_colors: true,
+            lightmap_uvs: true,
+        },
+    ];
+
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        MeshVertexLayout {
+            vertex_colors: mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_some(),
+            lightmap_uvs: mesh.attribute(Mesh::ATTRIBUTE_UV_1).is_some(),
+        }
+    }
+
+    /// Returns the vertex buffer the pbr vertex shader reads for this layout: positions, normals
+    /// and uvs, followed by the attributes of the layout, at the shader locations and in the
+    /// formats of [`Mesh::VERTEX_ATTRIBUTES`].
+    fn shader_inputs(&self) -> VertexBufferLayout {
+        let mut names = vec![
+            Mesh::ATTRIBUTE_POSITION,
+            Mesh::ATTRIBUTE_NORMAL,
+            Mesh::ATTRIBUTE_UV_0,
+        ];
+        if self.vertex_colors {
+            names.push(Mesh::ATTRIBUTE_COLOR);
+        }
+        if self.lightmap_uvs {
+            names.push(Mesh::ATTRIBUTE_UV_1);
+        }
+        let mut layout = VertexBufferLayout::build("Vertex");
+        for attribute in Mesh::VERTEX_ATTRIBUTES
+            .iter()
+            .filter(|attribute| names.contains(&attribute.name))
+        {
+            layout =
+                layout.add_attribute(attribute.shader_location, attribute.name, attribute.format);
+        }
+        layout.finish()
+    }
+}
+
+/// The layout of the vertex buffer of a mesh, limited to the attributes the pbr shaders read:
+/// their formats and offsets, and the stride of the buffer. Pipelines drawing meshes are created
+/// for each layout, since it differs for meshes with more attributes, or with
+/// [quantized](Mesh::quantize_standard_attributes) ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshVertexBuffer {
+    pub vertex_layout: MeshVertexLayout,
+    stride: u64,
+    /// The attributes in the buffer, with their offset, in order of shader location.
+    attributes: [Option<(MeshVertexAttribute, u64)>; 5],
+}
+
+impl MeshVertexBuffer {
+    /// Returns the layout of the vertex buffer of `mesh`, built with
+    /// [`Mesh::vertex_buffer_layout_for`], or the attributes the pbr shaders read that the mesh
+    /// doesn't have in a format they can read.
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, VertexLayoutError> {
+        let vertex_layout = MeshVertexLayout::from_mesh(mesh);
+        let shader_layout = ShaderLayout {
+            bind_groups: Vec::new(),
+            vertex_buffer_layout: vec![vertex_layout.shader_inputs()],
+            entry_point: "main".to_string(),
+        };
+        let layout = mesh.vertex_buffer_layout_for(&shader_layout)?;
+        Ok(Self::from_layout(vertex_layout, &layout))
+    }
+
+    /// The layout of meshes that only have the attributes of `vertex_layout`, in the formats of
+    /// [`Mesh::VERTEX_ATTRIBUTES`], like the [shapes](bevy_render2::mesh::shape).
+    pub fn standard(vertex_layout: MeshVertexLayout) -> Self {
+        Self::from_layout(vertex_layout, &vertex_layout.shader_inputs())
+    }
+
+    fn from_layout(vertex_layout: MeshVertexLayout, layout: &VertexBufferLayout) -> Self {
+        // the shader inputs are built-in attributes, so they can be found by shader location
+        let located_attributes = layout.attributes.iter().filter_map(|attribute| {
+            let built_in = Mesh::VERTEX_ATTRIBUTES
+                .iter()
+                .find(|built_in| built_in.shader_location == attribute.shader_location)?;
+            let mesh_attribute = MeshVertexAttribute {
+                format: attribute.format,
+                ..*built_in
+            };
+            Some((mesh_attribute, attribute.offset))
+        });
+        let mut attributes = [None; 5];
+        for (slot, attribute) in attributes.iter_mut().zip(located_attributes) {
+            *slot = Some(attribute);
+        }
+        MeshVertexBuffer {
+            vertex_layout,
+            stride: layout.stride,
+            attributes,
+        }
+    }
+
+    /// Returns the layout pipelines drawing meshes with this vertex buffer must use.
+    pub fn layout(&self) -> VertexBufferLayout {
+        VertexBufferLayout {
+            name: "Vertex".into(),
+            stride: self.stride,
+            step_mode: InputStepMode::Vertex,
+            attributes: self
+                .attributes
+                .iter()
+                .flatten()
+                .map(|(attribute, offset)| VertexAttribute {
+                    name: attribute.name.into(),
+                    format: attribute.format,
+                    offset: *offset,
+                    shader_location: attribute.shader_location,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The variant of the pbr pipelines a mesh level is drawn with, see [`PbrShaders::specialize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshPipelineKey {
+    pub vertex_buffer: MeshVertexBuffer,
+    pub primitive_topology: PrimitiveTopology,
+    /// The index format of indexed line and triangle strips, which restart the strip at the
+    /// maximum value of the format. `None` for lists and non-indexed strips.
+    pub strip_index_format: Option<IndexFormat>,
+}
+
+impl MeshPipelineKey {
+    /// Returns the key of `mesh`, or the attributes the pbr shaders need that it doesn't have, see
+    /// [`MeshVertexBuffer::from_mesh`].
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, VertexLayoutError> {
+        let primitive_topology = mesh.primitive_topology();
+        let strip_index_format = match primitive_topology {
+            PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip => {
+                mesh.indices().map(IndexFormat::from)
+            }
+            _ => None,
+        };
+        Ok(MeshPipelineKey {
+            vertex_buffer: MeshVertexBuffer::from_mesh(mesh)?,
+            primitive_topology,
+            strip_index_format,
+        })
+    }
+
+    /// Returns whether the mesh is a list of triangles, which is the only topology drawn by
+    /// the passes that aren't specialized for the topology of meshes, like shadows.
+    pub fn is_triangle_list(&self) -> bool {
+        self.primitive_topology == PrimitiveTopology::TriangleList
+    }
+}
+
+/// What is bound to set 2 of the pbr pipeline a mesh is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PbrVariant {
+    /// Nothing, the mesh is drawn with its [`StandardMaterial`].
+    Standard,
+    /// The [`SplatMaterial`] of the mesh.
+    Splat,
+    /// The [`Lightmap`] of the mesh, which requires [`MeshVertexLayout::lightmap_uvs`].
+    Lightmap,
+    /// The alpha and [`StandardMaterial::alpha_cutoff`] of the material of a mesh in
+    /// [`MeshPhase::AlphaMask`], whose fragments below the cutoff are discarded.
+    AlphaMask,
+    /// Nothing, the mesh is drawn with an unlit magenta checkerboard because its
+    /// [`StandardMaterial`], splat map or lightmap isn't loaded, or because the shaders of its
+    /// variant failed to compile.
+    Error,
+}
 
 pub struct PbrShaders {
-    pipeline: PipelineId,
-    pipeline_descriptor: RenderPipelineDescriptor,
+    depth_mode: DepthMode,
+    settings: CorePipelineSettings,
+    /// The descriptors of each variant, for each vertex layout they have been used with.
+    descriptors: HashMap<(PbrVariant, MeshVertexLayout), RenderPipelineDescriptor>,
+    pipelines: HashMap<(PbrVariant, MeshPipelineKey), PipelineId>,
+    /// The variants whose shaders failed to compile, their pipelines are those of
+    /// [`PbrVariant::Error`].
+    failed_variants: HashSet<PbrVariant>,
+    /// Set if the splat maps and lightmaps are drawn with [`BindlessTextures`], which binds them
+    /// in binding arrays indexed with the `texture_index` of their uniforms.
+    bindless: Option<BindlessTextures>,
+}
+
+impl PbrShaders {
+    /// Returns the pipeline specialized for `key`.
+    ///
+    /// # Panics
+    /// Panics if the pipeline hasn't been created with [`PbrShaders::specialize`].
+    pub fn pipeline(&self, key: MeshPipelineKey) -> PipelineId {
+        self.variant_pipeline(PbrVariant::Standard, key)
+    }
+
+    /// Returns the pipeline of `variant` specialized for `key`, which has the bind group layouts
+    /// of [`PbrShaders::pipeline`] followed by the bind group of the variant, if any.
+    ///
+    /// # Panics
+    /// Panics if the pipeline hasn't been created with [`PbrShaders::specialize`].
+    pub fn variant_pipeline(&self, variant: PbrVariant, key: MeshPipelineKey) -> PipelineId {
+        self.pipelines[&(variant, key)]
+    }
+
+    /// Returns whether the shaders of `variant` failed to compile. Meshes of failed variants
+    /// should be drawn with [`PbrVariant::Error`] instead.
+    pub fn has_failed(&self, variant: PbrVariant) -> bool {
+        self.failed_variants.contains(&variant)
+    }
+
+    /// Returns the pipeline layout of `variant`. Every pipeline of a variant has the same bind
+    /// group layouts, so the bind groups created with it can be used with all of them. Sets 0
+    /// and 1 are the same for every variant, so the layout of [`PbrVariant::Error`], which never
+    /// fails, can be used for them.
+    ///
+    /// # Panics
+    /// Panics if the variant [failed](PbrShaders::has_failed).
+    pub fn layout(&self, variant: PbrVariant) -> &PipelineLayout {
+        let vertex_layout = MeshVertexLayout {
+            vertex_colors: false,
+            lightmap_uvs: variant == PbrVariant::Lightmap,
+        };
+        &self.descriptors[&(variant, vertex_layout)].layout
+    }
+
+    /// Creates the pipeline of `variant` for `key` if it doesn't exist yet. If the shaders of
+    /// the variant fail to compile, the error is logged once and the pipeline of
+    /// [`PbrVariant::Error`] is used instead.
+    ///
+    /// # Panics
+    /// Panics if the shaders of [`PbrVariant::Error`] fail to compile, since nothing could be
+    /// drawn in place of the meshes.
+    pub fn specialize(
+        &mut self,
+        render_resources: &RenderResources,
+        key: MeshPipelineKey,
+        variant: PbrVariant,
+    ) {
+        if self.pipelines.contains_key(&(variant, key)) {
+            return;
+        }
+        let vertex_layout = key.vertex_buffer.vertex_layout;
+        let descriptor = match self.variant_descriptor(render_resources, vertex_layout, variant) {
+            Some(descriptor) => descriptor,
+            None => {
+                self.specialize(render_resources, key, PbrVariant::Error);
+                let pipeline = self.pipelines[&(PbrVariant::Error, key)];
+                self.pipelines.insert((variant, key), pipeline);
+                return;
+            }
+        };
+        // the shaders don't depend on the topology and the formats of the attributes, so only
+        // the primitive state and the vertex buffer change
+        let mut descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: key.primitive_topology,
+                strip_index_format: key.strip_index_format,
+                ..descriptor.primitive.clone()
+            },
+            ..descriptor.clone()
+        };
+        descriptor.layout.vertex_buffer_descriptors = vec![key.vertex_buffer.layout()];
+        let pipeline = render_resources.create_render_pipeline(&descriptor);
+        self.pipelines.insert((variant, key), pipeline);
+    }
+
+    /// Returns the descriptor of `variant` for `vertex_layout`, compiling its shaders if needed.
+    /// Returns `None` if they failed to compile.
+    fn variant_descriptor(
+        &mut self,
+        render_resources: &RenderResources,
+        vertex_layout: MeshVertexLayout,
+        variant: PbrVariant,
+    ) -> Option<&RenderPipelineDescriptor> {
+        if self.failed_variants.contains(&variant) {
+            return None;
+        }
+        let (depth_mode, settings, bindless) = (self.depth_mode, self.settings, self.bindless);
+        match self.descriptors.entry((variant, vertex_layout)) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                match pbr_variant_descriptor(
+                    render_resources,
+                    depth_mode,
+                    settings,
+                    vertex_layout,
+                    variant,
+                    bindless,
+                ) {
+                    Ok(descriptor) => Some(entry.insert(descriptor)),
+                    Err(err) => {
+                        if variant == PbrVariant::Error {
+                            panic!("failed to compile the {:?} pbr shaders: {}", variant, err);
+                        }
+                        error!(
+                            "failed to compile the {:?} pbr shaders, meshes using them are drawn \
+                            with the error material: {}",
+                            variant, err
+                        );
+                        self.failed_variants.insert(variant);
+                        None
+                    }
+                }
+            }
+        }
+    }
 }
 
 // TODO: this pattern for initializing the shaders / pipeline isn't ideal. this should be handled by the asset system
 impl FromWorld for PbrShaders {
     fn from_world(world: &mut World) -> Self {
+        // the shadow maps are sampled besides the binding arrays
+        let bindless = BindlessTextures::get(world, 1);
         let render_resources = world.get_resource::<RenderResources>().unwrap();
-        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("pbr.vert"))
-            .get_spirv_shader(None)
-            .unwrap();
-        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("pbr.frag"))
-            .get_spirv_shader(None)
-            .unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
 
-        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
-        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let mut pbr_shaders = PbrShaders {
+            depth_mode,
+            settings,
+            descriptors: HashMap::default(),
+            pipelines: HashMap::default(),
+            failed_variants: HashSet::default(),
+            bindless,
+        };
+        // most meshes are triangle lists, so their pipelines are created up front. this also
+        // creates the descriptors `PbrShaders::layout` returns
+        for &vertex_layout in MeshVertexLayout::ALL.iter() {
+            let key = MeshPipelineKey {
+                vertex_buffer: MeshVertexBuffer::standard(vertex_layout),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+            };
+            pbr_shaders.specialize(render_resources, key, PbrVariant::Standard);
+            pbr_shaders.specialize(render_resources, key, PbrVariant::Error);
+            pbr_shaders.specialize(render_resources, key, PbrVariant::Splat);
+            pbr_shaders.specialize(render_resources, key, PbrVariant::AlphaMask);
+            if vertex_layout.lightmap_uvs {
+                pbr_shaders.specialize(render_resources, key, PbrVariant::Lightmap);
+            }
+        }
+        pbr_shaders
+    }
+}
 
-        let mut pipeline_layout =
-            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+/// Creates the pipeline descriptor of `variant` for meshes with `vertex_layout`. The variants
+/// with a material texture bind it to set 2, with a dynamic uniform at binding 2. With
+/// `bindless`, their texture is a binding array. [`PbrVariant::AlphaMask`] only binds a dynamic
+/// uniform, at binding 0.
+fn pbr_variant_descriptor(
+    render_resources: &RenderResources,
+    depth_mode: DepthMode,
+    settings: CorePipelineSettings,
+    vertex_layout: MeshVertexLayout,
+    variant: PbrVariant,
+    bindless: Option<BindlessTextures>,
+) -> Result<RenderPipelineDescriptor, ShaderError> {
+    let (shader_def, texture_array) = match variant {
+        PbrVariant::Standard => {
+            return pbr_pipeline_descriptor(
+                render_resources,
+                depth_mode,
+                settings,
+                vertex_layout,
+                &[],
+                &Default::default(),
+            )
+        }
+        PbrVariant::Error => {
+            return pbr_pipeline_descriptor(
+                render_resources,
+                depth_mode,
+                settings,
+                vertex_layout,
+                &["ERROR_MATERIAL"],
+                &Default::default(),
+            )
+        }
+        PbrVariant::AlphaMask => {
+            // the uniform is the only binding of set 2, as there is no texture
+            let mut descriptor = pbr_pipeline_descriptor(
+                render_resources,
+                depth_mode,
+                settings,
+                vertex_layout,
+                &["ALPHA_MASK"],
+                &Default::default(),
+            )?;
+            descriptor.layout.bind_group_mut(2).bindings[0].set_dynamic(true);
+            descriptor.layout.update_bind_group_ids();
+            return Ok(descriptor);
+        }
+        PbrVariant::Splat => ("SPLAT_MAP", "t_SplatMaps"),
+        PbrVariant::Lightmap => ("LIGHTMAP", "t_Lightmaps"),
+    };
+    let mut shader_defs = vec![shader_def];
+    let reflect_options = match bindless {
+        Some(bindless) => {
+            shader_defs.push("BINDLESS");
+            bindless.reflect_options(texture_array)
+        }
+        None => ShaderReflectOptions::default(),
+    };
+    let mut descriptor = pbr_pipeline_descriptor(
+        render_resources,
+        depth_mode,
+        settings,
+        vertex_layout,
+        &shader_defs,
+        &reflect_options,
+    )?;
+    descriptor.layout.bind_group_mut(2).bindings[2].set_dynamic(true);
+    descriptor.layout.update_bind_group_ids();
+    Ok(descriptor)
+}
 
-        let vertex = render_resources.create_shader_module(&vertex_shader);
-        let fragment = render_resources.create_shader_module(&fragment_shader);
+/// Creates the pipeline descriptor for the pbr shaders. Variants created with other
+/// `shader_defs` have the same bind group layouts as long as they use the same bindings. With
+/// [`CorePipelineSettings::hdr`], the shaders output the exposed color without tonemapping it.
+pub(crate) fn pbr_pipeline_descriptor(
+    render_resources: &RenderResources,
+    depth_mode: DepthMode,
+    settings: CorePipelineSettings,
+    vertex_layout: MeshVertexLayout,
+    shader_defs: &[&str],
+    reflect_options: &ShaderReflectOptions,
+) -> Result<RenderPipelineDescriptor, ShaderError> {
+    let mut shader_defs = shader_defs
+        .iter()
+        .map(|shader_def| shader_def.to_string())
+        .collect::<Vec<_>>();
+    if vertex_layout.vertex_colors {
+        shader_defs.push(String::from("VERTEX_COLORS"));
+    }
+    if settings.hdr {
+        shader_defs.push(String::from("HDR"));
+    }
+    let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("pbr.vert"))
+        .get_spirv_shader(Some(&shader_defs))?;
+    let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("pbr.frag"))
+        .get_spirv_shader(Some(&shader_defs))?;
 
-        pipeline_layout.vertex_buffer_descriptors = vec![VertexBufferLayout {
-            stride: 32,
-            name: "Vertex".into(),
-            step_mode: InputStepMode::Vertex,
-            attributes: vec![
-                // GOTCHA! Vertex_Position isn't first in the buffer due to how Mesh sorts attributes (alphabetically)
-                VertexAttribute {
-                    name: "Vertex_Position".into(),
-                    format: VertexFormat::Float32x3,
-                    offset: 12,
-                    shader_location: 0,
-                },
-                VertexAttribute {
-                    name: "Vertex_Normals".into(),
-                    format: VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 1,
-                },
-                VertexAttribute {
-                    name: "Vertex_Uv".into(),
-                    format: VertexFormat::Float32x2,
-                    offset: 24,
-                    shader_location: 2,
-                },
-            ],
-        }];
+    let vertex_shader_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+    let fragment_shader_layout = fragment_shader.reflect_layout(reflect_options).unwrap();
 
-        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
-        pipeline_layout.bind_group_mut(0).bindings[1].set_dynamic(true);
-        if let BindType::Texture { sample_type, .. } =
-            &mut pipeline_layout.bind_group_mut(0).bindings[2].bind_type
-        {
-            *sample_type = TextureSampleType::Depth;
-        }
-        if let BindType::Sampler { comparison, .. } =
-            &mut pipeline_layout.bind_group_mut(0).bindings[3].bind_type
-        {
-            *comparison = true;
-        }
-        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
-
-        pipeline_layout.update_bind_group_ids();
-
-        let pipeline_descriptor = RenderPipelineDescriptor {
-            depth_stencil: Some(DepthStencilState {
-                format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: CompareFunction::Less,
-                stencil: StencilState {
-                    front: StencilFaceState::IGNORE,
-                    back: StencilFaceState::IGNORE,
-                    read_mask: 0,
-                    write_mask: 0,
+    let mut pipeline_layout =
+        PipelineLayout::from_shader_layouts(&mut [vertex_shader_layout, fragment_shader_layout]);
+
+    let vertex = render_resources.create_shader_module(&vertex_shader);
+    let fragment = render_resources.create_shader_module(&fragment_shader);
+
+    // pipelines are specialized for the vertex buffer of each mesh
+    pipeline_layout.vertex_buffer_descriptors =
+        vec![MeshVertexBuffer::standard(vertex_layout).layout()];
+
+    pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+    pipeline_layout.bind_group_mut(0).bindings[1].set_dynamic(true);
+    if let BindType::Texture { sample_type, .. } =
+        &mut pipeline_layout.bind_group_mut(0).bindings[2].bind_type
+    {
+        *sample_type = TextureSampleType::Depth;
+    }
+    if let BindType::Sampler { comparison, .. } =
+        &mut pipeline_layout.bind_group_mut(0).bindings[3].bind_type
+    {
+        *comparison = true;
+    }
+    pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+
+    pipeline_layout.update_bind_group_ids();
+
+    Ok(RenderPipelineDescriptor {
+        depth_stencil: Some(DepthStencilState {
+            format: settings.depth_format,
+            depth_write_enabled: true,
+            depth_compare: depth_mode.compare(CompareFunction::Less),
+            stencil: StencilState {
+                front: StencilFaceState::IGNORE,
+                back: StencilFaceState::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+            bias: DepthBiasState {
+                constant: 0,
+                slope_scale: 0.0,
+                clamp: 0.0,
+            },
+        }),
+        color_target_states: vec![ColorTargetState {
+            format: settings.main_texture_format(),
+            blend: Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
                 },
-                bias: DepthBiasState {
-                    constant: 0,
-                    slope_scale: 0.0,
-                    clamp: 0.0,
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
                 },
             }),
-            color_target_states: vec![ColorTargetState {
-                format: TextureFormat::default(),
-                blend: Some(BlendState {
-                    color: BlendComponent {
-                        src_factor: BlendFactor::SrcAlpha,
-                        dst_factor: BlendFactor::OneMinusSrcAlpha,
-                        operation: BlendOperation::Add,
-                    },
-                    alpha: BlendComponent {
-                        src_factor: BlendFactor::One,
-                        dst_factor: BlendFactor::One,
-                        operation: BlendOperation::Add,
-                    },
-                }),
-                write_mask: ColorWrite::ALL,
-            }],
-            ..RenderPipelineDescriptor::new(
-                ShaderStages {
-                    vertex,
-                    fragment: Some(fragment),
-                },
-                pipeline_layout,
-            )
-        };
+            write_mask: ColorWrite::ALL,
+        }],
+        ..RenderPipelineDescriptor::new(
+            ShaderStages {
+                vertex,
+                fragment: Some(fragment),
+            },
+            pipeline_layout,
+        )
+    })
+}
 
-        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+/// The pipelines of a pass drawing meshes with the same shaders, created from a descriptor for the
+/// [`MeshVertexBuffer`] of each mesh drawn with [`MeshPipelines::specialize`].
+pub(crate) struct MeshPipelines {
+    descriptor: RenderPipelineDescriptor,
+    pipelines: HashMap<MeshVertexBuffer, PipelineId>,
+}
 
-        PbrShaders {
-            pipeline,
-            pipeline_descriptor,
+impl MeshPipelines {
+    pub(crate) fn new(descriptor: RenderPipelineDescriptor) -> Self {
+        MeshPipelines {
+            descriptor,
+            pipelines: HashMap::default(),
         }
     }
+
+    /// Creates the pipeline for `vertex_buffer` if it doesn't exist yet.
+    pub(crate) fn specialize(
+        &mut self,
+        render_resources: &RenderResources,
+        vertex_buffer: MeshVertexBuffer,
+    ) {
+        let descriptor = &self.descriptor;
+        self.pipelines.entry(vertex_buffer).or_insert_with(|| {
+            let mut descriptor = descriptor.clone();
+            descriptor.layout.vertex_buffer_descriptors = vec![vertex_buffer.layout()];
+            render_resources.create_render_pipeline(&descriptor)
+        });
+    }
+
+    /// Creates the pipelines for the triangle list levels of `meshes`, the only levels drawn by
+    /// passes that aren't specialized for the topology of meshes.
+    pub(crate) fn specialize_meshes<'a>(
+        &mut self,
+        render_resources: &RenderResources,
+        meshes: impl IntoIterator<Item = &'a ExtractedMesh>,
+    ) {
+        for extracted_mesh in meshes {
+            for vertex_buffer in extracted_mesh.triangle_list_vertex_buffers() {
+                self.specialize(render_resources, vertex_buffer);
+            }
+        }
+    }
+
+    /// Returns the pipeline for `vertex_buffer`.
+    ///
+    /// # Panics
+    /// Panics if the pipeline hasn't been created with [`MeshPipelines::specialize`].
+    pub(crate) fn get(&self, vertex_buffer: MeshVertexBuffer) -> PipelineId {
+        self.pipelines[&vertex_buffer]
+    }
 }
 
-struct ExtractedMesh {
+pub(crate) struct ExtractedMesh {
+    pub(crate) entity: Entity,
     transform: Mat4,
-    vertex_buffer: BufferId,
-    index_info: Option<IndexInfo>,
-    transform_binding_offset: u32,
+    /// The transform of the mesh in the previous frame, or its current transform if it wasn't
+    /// drawn or [`MotionVectorConfig::object_motion`] is disabled.
+    previous_transform: Mat4,
+    /// The mesh itself, followed by its [`Lod`] levels from the most to the least detailed.
+    levels: Vec<ExtractedMeshLevel>,
+    pub(crate) transform_binding: DynamicUniformIndex,
+    pub(crate) wireframe: bool,
+    /// The phase of the main pass the mesh is drawn in, chosen by its material.
+    pub(crate) phase: MeshPhase,
+    /// The local bounds of the mesh, if it can be culled.
+    aabb: Option<Aabb>,
+    splat: Option<ExtractedSplat>,
+    lightmap: Option<ExtractedLightmap>,
+    alpha_mask: Option<ExtractedAlphaMask>,
+    /// Whether the mesh is drawn with [`PbrVariant::Error`].
+    error_material: bool,
 }
 
-struct IndexInfo {
-    buffer: BufferId,
-    count: u32,
+impl ExtractedMesh {
+    /// Returns whether the mesh may be visible in `frustum`. Meshes without an [`Aabb`] are
+    /// never culled.
+    pub(crate) fn is_visible(&self, frustum: &Frustum) -> bool {
+        self.aabb
+            .map_or(true, |aabb| frustum.intersects_aabb(&aabb, &self.transform))
+    }
+
+    /// Returns whether every level of the mesh is a list of triangles, see
+    /// [`MeshPipelineKey::is_triangle_list`].
+    pub(crate) fn is_triangle_list(&self) -> bool {
+        self.levels.iter().all(|level| level.key.is_triangle_list())
+    }
+
+    /// Returns the vertex buffers of the levels of the mesh that are lists of triangles.
+    pub(crate) fn triangle_list_vertex_buffers(
+        &self,
+    ) -> impl Iterator<Item = MeshVertexBuffer> + '_ {
+        self.levels
+            .iter()
+            .filter(|level| level.key.is_triangle_list())
+            .map(|level| level.key.vertex_buffer)
+    }
+
+    /// Returns the center of the bounds of the mesh, or its origin if it has no [`Aabb`].
+    fn center(&self) -> Vec3 {
+        self.transform
+            .transform_point3(self.aabb.map_or(Vec3::ZERO, |aabb| aabb.center))
+    }
+
+    /// Returns the variant of the pbr pipelines the level of the mesh with `key` is drawn with.
+    fn variant(&self, key: MeshPipelineKey) -> PbrVariant {
+        if self.error_material {
+            PbrVariant::Error
+        } else if self.splat.is_some() {
+            PbrVariant::Splat
+        } else if self.alpha_mask.is_some() {
+            PbrVariant::AlphaMask
+        } else if self.lightmap.is_some() && key.vertex_buffer.vertex_layout.lightmap_uvs {
+            PbrVariant::Lightmap
+        } else {
+            PbrVariant::Standard
+        }
+    }
+}
+
+/// The [`SplatMaterial`] of an extracted mesh.
+struct ExtractedSplat {
+    splat_map: TextureViewId,
+    sampler: SamplerId,
+    layers: [Vec4; 4],
+    /// The index of the layers in [`MeshMeta::splat_uniforms`].
+    binding: DynamicUniformIndex,
+    bind_group: Option<BindGroupId>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuSplatLayers {
+    layers: [Vec4; 4],
+    /// The index of the splat map in its binding array, with [`BindlessTextures`].
+    texture_index: u32,
+}
+
+/// The [`Lightmap`] of an extracted mesh.
+struct ExtractedLightmap {
+    image: TextureViewId,
+    sampler: SamplerId,
+    uniform: GpuLightmap,
+    /// The index of the uniform in [`MeshMeta::lightmap_uniforms`].
+    binding: DynamicUniformIndex,
+    bind_group: Option<BindGroupId>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuLightmap {
+    /// The offset of the uvs in `xy` and their scale in `zw`.
+    uv_rect: Vec4,
+    intensity: f32,
+    /// The index of the lightmap in its binding array, with [`BindlessTextures`].
+    texture_index: u32,
+}
+
+/// The [`StandardMaterial::alpha_cutoff`] of an extracted mesh in [`MeshPhase::AlphaMask`].
+struct ExtractedAlphaMask {
+    uniform: GpuAlphaMask,
+    /// The index of the uniform in [`MeshMeta::alpha_mask_uniforms`].
+    binding: DynamicUniformIndex,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuAlphaMask {
+    /// The alpha of the material color.
+    alpha: f32,
+    alpha_cutoff: f32,
+}
+
+pub(crate) struct ExtractedMeshLevel {
+    threshold: LodThreshold,
+    pub(crate) vertex_buffer: BufferId,
+    pub(crate) vertex_count: u32,
+    pub(crate) index_info: Option<IndexInfo>,
+    pub(crate) key: MeshPipelineKey,
+}
+
+impl ExtractedMeshLevel {
+    /// The level of the entity's own mesh, which is used whenever no [`Lod`] level is.
+    pub(crate) fn base(mesh: &Mesh) -> Result<Option<Self>, VertexLayoutError> {
+        Self::new(mesh, LodThreshold::Distance(0.0))
+    }
+
+    /// Returns the level drawing `mesh`, or `None` if the mesh hasn't been uploaded yet. Meshes
+    /// the pbr shaders can't read return an error.
+    pub(crate) fn new(
+        mesh: &Mesh,
+        threshold: LodThreshold,
+    ) -> Result<Option<Self>, VertexLayoutError> {
+        let key = MeshPipelineKey::from_mesh(mesh)?;
+        Ok(mesh.gpu_data().map(|gpu_data| ExtractedMeshLevel {
+            threshold,
+            vertex_buffer: gpu_data.vertex_buffer,
+            vertex_count: mesh.count_vertices() as u32,
+            index_info: gpu_data.index_buffer.map(|i| {
+                let indices = mesh.indices().unwrap();
+                IndexInfo {
+                    buffer: i,
+                    count: indices.len() as u32,
+                    format: indices.into(),
+                }
+            }),
+            key,
+        }))
+    }
+
+    /// Binds the buffers of the level and draws it, with its indices if it has any.
+    pub(crate) fn draw(&self, pass: &mut TrackedRenderPass) {
+        pass.set_vertex_buffer(0, self.vertex_buffer, 0);
+        if let Some(index_info) = &self.index_info {
+            pass.set_index_buffer(index_info.buffer, 0, index_info.format);
+            pass.draw_indexed(0..index_info.count, 0, 0..1);
+        } else {
+            pass.draw(0..self.vertex_count, 0..1);
+        }
+    }
+}
+
+pub(crate) struct IndexInfo {
+    pub(crate) buffer: BufferId,
+    pub(crate) count: u32,
+    pub(crate) format: IndexFormat,
 }
 
 pub struct ExtractedMeshes {
-    meshes: Vec<ExtractedMesh>,
+    pub(crate) meshes: Vec<ExtractedMesh>,
+}
+
+impl ExtractedMeshes {
+    /// Returns the mesh for `draw_key`, along with the level of detail selected for the view.
+    pub(crate) fn get(
+        &self,
+        draw_key: usize,
+        view_mesh_lods: Option<&ViewMeshLods>,
+    ) -> (&ExtractedMesh, &ExtractedMeshLevel) {
+        let extracted_mesh = &self.meshes[draw_key];
+        let level = view_mesh_lods.map_or(0, |lods| lods.levels[draw_key]);
+        (extracted_mesh, &extracted_mesh.levels[level])
+    }
+
+    /// Returns the draw keys and entities of the meshes drawn in `phase`, e.g. to queue the
+    /// meshes in [`MeshPhase::Custom`] with [`DrawPbr`] in a phase of their own.
+    pub fn iter_phase(&self, phase: MeshPhase) -> impl Iterator<Item = (usize, Entity)> + '_ {
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter(move |(_, extracted_mesh)| extracted_mesh.phase == phase)
+            .map(|(draw_key, extracted_mesh)| (draw_key, extracted_mesh.entity))
+    }
 }
 
+/// Logs once per mesh that it isn't drawn, because the pbr shaders can't read its attributes.
+fn log_unreadable_mesh(
+    logged_assets: &mut HashSet<HandleId>,
+    mesh: HandleId,
+    err: &VertexLayoutError,
+) {
+    if logged_assets.insert(mesh) {
+        error!(
+            "mesh {:?} can't be drawn with the pbr shaders: {}",
+            mesh, err
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn extract_meshes(
     mut commands: Commands,
     meshes: Res<Assets<Mesh>>,
-    _materials: Res<Assets<StandardMaterial>>,
-    query: Query<(&GlobalTransform, &Handle<Mesh>, &Handle<StandardMaterial>)>,
+    materials: Res<Assets<StandardMaterial>>,
+    splat_materials: Res<Assets<SplatMaterial>>,
+    textures: Res<Assets<Texture>>,
+    wireframe_config: Option<Res<WireframeConfig>>,
+    motion_vector_config: Option<Res<MotionVectorConfig>>,
+    floating_origin: Res<FloatingOrigin>,
+    mut previous_transforms: Local<HashMap<Entity, Mat4>>,
+    mut logged_assets: Local<HashSet<HandleId>>,
+    query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Handle<Mesh>,
+        &Handle<StandardMaterial>,
+        Option<&Wireframe>,
+        Option<&Lod>,
+        Option<&Aabb>,
+        Option<&Handle<SplatMaterial>>,
+        Option<&Lightmap>,
+    )>,
 ) {
+    let global_wireframe = wireframe_config.map_or(false, |config| config.global);
+    let object_motion = motion_vector_config.map_or(false, |config| config.object_motion);
+    let mut transforms = HashMap::default();
     let mut extracted_meshes = Vec::new();
-    for (transform, mesh_handle, _material_handle) in query.iter() {
-        if let Some(mesh) = meshes.get(mesh_handle) {
-            if let Some(gpu_data) = &mesh.gpu_data() {
-                extracted_meshes.push(ExtractedMesh {
-                    transform: transform.compute_matrix(),
-                    vertex_buffer: gpu_data.vertex_buffer,
-                    index_info: gpu_data.index_buffer.map(|i| IndexInfo {
-                        buffer: i,
-                        count: mesh.indices().unwrap().len() as u32,
-                    }),
-                    transform_binding_offset: 0,
-                })
+    for (
+        entity,
+        transform,
+        mesh_handle,
+        material_handle,
+        wireframe,
+        lod,
+        aabb,
+        splat_material,
+        lightmap,
+    ) in query.iter()
+    {
+        let base_level = match meshes.get(mesh_handle).map(ExtractedMeshLevel::base) {
+            Some(Ok(Some(base_level))) => base_level,
+            Some(Err(err)) => {
+                log_unreadable_mesh(&mut logged_assets, mesh_handle.id, &err);
+                continue;
             }
+            _ => continue,
+        };
+        let mut levels = vec![base_level];
+        if let Some(lod) = lod {
+            // levels that aren't ready yet are skipped, falling back to the previous level
+            levels.extend(lod.levels().iter().filter_map(|level| {
+                let mesh = meshes.get(&level.mesh)?;
+                ExtractedMeshLevel::new(mesh, level.threshold).unwrap_or_else(|err| {
+                    log_unreadable_mesh(&mut logged_assets, level.mesh.id, &err);
+                    None
+                })
+            }));
         }
+
+        let render_transform = floating_origin.to_render_matrix(transform);
+        let previous_transform = if object_motion {
+            // the previous transforms are kept in world space, so they stay valid when the
+            // floating origin moves
+            let world_transform = transform.compute_matrix();
+            transforms.insert(entity, world_transform);
+            previous_transforms
+                .get(&entity)
+                .map(|previous_transform| {
+                    Mat4::from_translation(-floating_origin.origin) * *previous_transform
+                })
+                .unwrap_or(render_transform)
+        } else {
+            render_transform
+        };
+        let transform = render_transform;
+
+        // meshes whose material, splat map or lightmap isn't ready are drawn with the error
+        // material, which is logged once per asset
+        let mut error_material = false;
+        let mut alpha_mask = None;
+        let phase = match materials.get(material_handle) {
+            Some(material) => {
+                let phase = material.phase();
+                if phase == MeshPhase::AlphaMask {
+                    alpha_mask = Some(ExtractedAlphaMask {
+                        uniform: GpuAlphaMask {
+                            alpha: material.color.a(),
+                            alpha_cutoff: material.alpha_cutoff,
+                        },
+                        binding: DynamicUniformIndex::default(),
+                    });
+                }
+                phase
+            }
+            None => {
+                if logged_assets.insert(material_handle.id) {
+                    warn!(
+                        "StandardMaterial {:?} isn't loaded, drawing with the error material",
+                        material_handle.id
+                    );
+                }
+                error_material = true;
+                MeshPhase::Opaque
+            }
+        };
+        let splat = splat_material.and_then(|handle| {
+            let material = match splat_materials.get(handle) {
+                Some(material) => material,
+                None => {
+                    if logged_assets.insert(handle.id) {
+                        warn!(
+                            "SplatMaterial {:?} isn't loaded, drawing with the error material",
+                            handle.id
+                        );
+                    }
+                    error_material = true;
+                    return None;
+                }
+            };
+            let gpu_data = match textures
+                .get(&material.splat_map)
+                .and_then(|texture| texture.gpu_data.as_ref())
+            {
+                Some(gpu_data) => gpu_data,
+                None => {
+                    if logged_assets.insert(material.splat_map.id) {
+                        warn!(
+                            "splat map {:?} isn't loaded, drawing with the error material",
+                            material.splat_map.id
+                        );
+                    }
+                    error_material = true;
+                    return None;
+                }
+            };
+            Some(ExtractedSplat {
+                splat_map: gpu_data.texture_view,
+                sampler: gpu_data.sampler,
+                layers: [
+                    material.layers[0].as_linear_rgba_f32().into(),
+                    material.layers[1].as_linear_rgba_f32().into(),
+                    material.layers[2].as_linear_rgba_f32().into(),
+                    material.layers[3].as_linear_rgba_f32().into(),
+                ],
+                binding: DynamicUniformIndex::default(),
+                bind_group: None,
+            })
+        });
+        let lightmap = lightmap.and_then(|lightmap| {
+            let gpu_data = match textures
+                .get(&lightmap.image)
+                .and_then(|texture| texture.gpu_data.as_ref())
+            {
+                Some(gpu_data) => gpu_data,
+                None => {
+                    if logged_assets.insert(lightmap.image.id) {
+                        warn!(
+                            "lightmap {:?} isn't loaded, drawing with the error material",
+                            lightmap.image.id
+                        );
+                    }
+                    error_material = true;
+                    return None;
+                }
+            };
+            Some(ExtractedLightmap {
+                image: gpu_data.texture_view,
+                sampler: gpu_data.sampler,
+                uniform: GpuLightmap {
+                    uv_rect: Vec4::new(
+                        lightmap.uv_offset.x,
+                        lightmap.uv_offset.y,
+                        lightmap.uv_scale.x,
+                        lightmap.uv_scale.y,
+                    ),
+                    intensity: lightmap.intensity,
+                    texture_index: 0,
+                },
+                binding: DynamicUniformIndex::default(),
+                bind_group: None,
+            })
+        });
+
+        // the error material binds nothing to set 2, a splat material replaces the standard
+        // material and its alpha mask, and the alpha mask is bound instead of the lightmap
+        let splat = splat.filter(|_| !error_material);
+        let alpha_mask = alpha_mask.filter(|_| !error_material && splat.is_none());
+        let lightmap = lightmap.filter(|_| !error_material && alpha_mask.is_none());
+        extracted_meshes.push(ExtractedMesh {
+            entity,
+            transform,
+            previous_transform,
+            levels,
+            transform_binding: DynamicUniformIndex::default(),
+            wireframe: global_wireframe || wireframe.is_some(),
+            phase,
+            aabb: aabb.copied(),
+            splat,
+            lightmap,
+            alpha_mask,
+            error_material,
+        });
     }
 
+    // meshes that weren't extracted this frame are forgotten
+    *previous_transforms = transforms;
+
     commands.insert_resource(ExtractedMeshes {
         meshes: extracted_meshes,
     });
@@ -184,54 +1063,380 @@ pub fn extract_meshes(
 
 #[derive(Default)]
 pub struct MeshMeta {
-    transform_uniforms: DynamicUniformVec<Mat4>,
+    pub(crate) transform_uniforms: DynamicUniformVec<Mat4>,
+    /// The [`ExtractedMesh::previous_transform`] of each mesh, at the same index as its
+    /// transform in `transform_uniforms`.
+    pub(crate) previous_transform_uniforms: DynamicUniformVec<Mat4>,
+    /// The layers of the meshes with an [`ExtractedSplat`].
+    splat_uniforms: DynamicUniformVec<GpuSplatLayers>,
+    /// The uniforms of the meshes with an [`ExtractedLightmap`].
+    lightmap_uniforms: DynamicUniformVec<GpuLightmap>,
+    /// The uniforms of the meshes with an [`ExtractedAlphaMask`].
+    alpha_mask_uniforms: DynamicUniformVec<GpuAlphaMask>,
+    splat_bind_groups: MaterialBindGroups,
+    lightmap_bind_groups: MaterialBindGroups,
+    /// One bind group per chunk of `alpha_mask_uniforms`.
+    alpha_mask_bind_groups: Vec<BindGroupId>,
+}
+
+/// The bind groups of the meshes drawn with the textures of a material variant.
+#[derive(Default)]
+struct MaterialBindGroups {
+    /// The binding arrays of the textures, with [`BindlessTextures`].
+    batches: Option<BindlessBatches>,
+    /// The bind group of each batch, for each chunk of the uniforms of the variant.
+    bindless_bind_groups: HashMap<(usize, usize), BindGroupId>,
+}
+
+impl MaterialBindGroups {
+    fn clear(&mut self, bindless: Option<BindlessTextures>) {
+        if let Some(bindless) = bindless {
+            self.batches
+                .get_or_insert_with(|| BindlessBatches::new(bindless))
+                .clear();
+        }
+        self.bindless_bind_groups.clear();
+    }
+
+    /// Adds `texture` to a binding array, and returns its index in the array. Without
+    /// [`BindlessTextures`], the texture is bound on its own and the index is 0.
+    fn texture_index(&mut self, texture: TextureViewId, sampler: SamplerId) -> u32 {
+        self.batches
+            .as_mut()
+            .map_or(0, |batches| batches.add(texture, sampler).index)
+    }
+
+    /// Returns the bind group of `texture` and `sampler`, at bindings 0 and 1 of `layout`, and of
+    /// the `uniform` of `chunk` at binding 2. With [`BindlessTextures`], it's the bind group of
+    /// the binding array the texture was added to by [`MaterialBindGroups::texture_index`],
+    /// shared by the meshes of the same batch and chunk.
+    fn bind_group(
+        &mut self,
+        render_resources: &RenderResources,
+        layout: &BindGroupDescriptor,
+        texture: TextureViewId,
+        sampler: SamplerId,
+        chunk: usize,
+        uniform: RenderResourceBinding,
+    ) -> BindGroupId {
+        let batches = match self.batches.as_mut() {
+            Some(batches) => batches,
+            None => {
+                let bind_group = BindGroupBuilder::default()
+                    .add_binding(0, texture)
+                    .add_binding(1, sampler)
+                    .add_binding(2, uniform)
+                    .finish();
+                render_resources.create_bind_group(layout.id, &bind_group);
+                return bind_group.id;
+            }
+        };
+        let batch = batches.add(texture, sampler).batch;
+        *self
+            .bindless_bind_groups
+            .entry((batch, chunk))
+            .or_insert_with(|| {
+                let bind_group = batches
+                    .bind_group(batch, layout, 0, 1)
+                    .buffer(2, uniform)
+                    .build()
+                    .unwrap();
+                render_resources.create_bind_group(layout.id, &bind_group);
+                bind_group.id
+            })
+    }
 }
 
 pub fn prepare_meshes(
     render_resources: Res<RenderResources>,
+    pbr_shaders: Res<PbrShaders>,
     mut mesh_meta: ResMut<MeshMeta>,
     mut extracted_meshes: ResMut<ExtractedMeshes>,
 ) {
+    // the bind groups of failed variants can't be created, their meshes use the error material
+    for extracted_mesh in extracted_meshes.meshes.iter_mut() {
+        if (extracted_mesh.splat.is_some() && pbr_shaders.has_failed(PbrVariant::Splat))
+            || (extracted_mesh.lightmap.is_some() && pbr_shaders.has_failed(PbrVariant::Lightmap))
+            || (extracted_mesh.alpha_mask.is_some()
+                && pbr_shaders.has_failed(PbrVariant::AlphaMask))
+        {
+            extracted_mesh.splat = None;
+            extracted_mesh.lightmap = None;
+            extracted_mesh.alpha_mask = None;
+            extracted_mesh.error_material = true;
+        }
+    }
+
     mesh_meta
         .transform_uniforms
         .reserve_and_clear(extracted_meshes.meshes.len(), &render_resources);
+    mesh_meta
+        .previous_transform_uniforms
+        .reserve_and_clear(extracted_meshes.meshes.len(), &render_resources);
     for extracted_mesh in extracted_meshes.meshes.iter_mut() {
-        extracted_mesh.transform_binding_offset =
+        extracted_mesh.transform_binding =
             mesh_meta.transform_uniforms.push(extracted_mesh.transform);
+        mesh_meta
+            .previous_transform_uniforms
+            .push(extracted_mesh.previous_transform);
     }
 
+    let splat_count = extracted_meshes
+        .meshes
+        .iter()
+        .filter(|extracted_mesh| extracted_mesh.splat.is_some())
+        .count();
+    mesh_meta
+        .splat_uniforms
+        .reserve_and_clear(splat_count, &render_resources);
+    let mesh_meta = &mut *mesh_meta;
+    mesh_meta.splat_bind_groups.clear(pbr_shaders.bindless);
+    for splat in extracted_meshes
+        .meshes
+        .iter_mut()
+        .filter_map(|extracted_mesh| extracted_mesh.splat.as_mut())
+    {
+        splat.binding = mesh_meta.splat_uniforms.push(GpuSplatLayers {
+            layers: splat.layers,
+            texture_index: mesh_meta
+                .splat_bind_groups
+                .texture_index(splat.splat_map, splat.sampler),
+        });
+    }
+    // binding arrays are only bound once every texture has been added to them. the layouts of
+    // failed variants don't exist, but none of their meshes are left
+    for splat in extracted_meshes
+        .meshes
+        .iter_mut()
+        .filter_map(|extracted_mesh| extracted_mesh.splat.as_mut())
+    {
+        let uniform = mesh_meta.splat_uniforms.binding(splat.binding.chunk);
+        splat.bind_group = Some(mesh_meta.splat_bind_groups.bind_group(
+            &render_resources,
+            pbr_shaders.layout(PbrVariant::Splat).bind_group(2),
+            splat.splat_map,
+            splat.sampler,
+            splat.binding.chunk,
+            uniform,
+        ));
+    }
+
+    let lightmap_count = extracted_meshes
+        .meshes
+        .iter()
+        .filter(|extracted_mesh| extracted_mesh.lightmap.is_some())
+        .count();
+    mesh_meta
+        .lightmap_uniforms
+        .reserve_and_clear(lightmap_count, &render_resources);
+    mesh_meta.lightmap_bind_groups.clear(pbr_shaders.bindless);
+    for lightmap in extracted_meshes
+        .meshes
+        .iter_mut()
+        .filter_map(|extracted_mesh| extracted_mesh.lightmap.as_mut())
+    {
+        lightmap.uniform.texture_index = mesh_meta
+            .lightmap_bind_groups
+            .texture_index(lightmap.image, lightmap.sampler);
+        lightmap.binding = mesh_meta.lightmap_uniforms.push(lightmap.uniform);
+    }
+    for lightmap in extracted_meshes
+        .meshes
+        .iter_mut()
+        .filter_map(|extracted_mesh| extracted_mesh.lightmap.as_mut())
+    {
+        let uniform = mesh_meta.lightmap_uniforms.binding(lightmap.binding.chunk);
+        lightmap.bind_group = Some(mesh_meta.lightmap_bind_groups.bind_group(
+            &render_resources,
+            pbr_shaders.layout(PbrVariant::Lightmap).bind_group(2),
+            lightmap.image,
+            lightmap.sampler,
+            lightmap.binding.chunk,
+            uniform,
+        ));
+    }
+
+    let alpha_mask_count = extracted_meshes
+        .meshes
+        .iter()
+        .filter(|extracted_mesh| extracted_mesh.alpha_mask.is_some())
+        .count();
+    mesh_meta
+        .alpha_mask_uniforms
+        .reserve_and_clear(alpha_mask_count, &render_resources);
+    for alpha_mask in extracted_meshes
+        .meshes
+        .iter_mut()
+        .filter_map(|extracted_mesh| extracted_mesh.alpha_mask.as_mut())
+    {
+        alpha_mask.binding = mesh_meta.alpha_mask_uniforms.push(alpha_mask.uniform);
+    }
+    // the layout doesn't exist if the variant failed, but then none of its meshes are left
+    mesh_meta.alpha_mask_bind_groups = if alpha_mask_count == 0 {
+        Vec::new()
+    } else {
+        let alpha_mask_layout = pbr_shaders.layout(PbrVariant::AlphaMask).bind_group(2);
+        (0..mesh_meta.alpha_mask_uniforms.chunk_count())
+            .map(|chunk| {
+                let bind_group = BindGroupBuilder::default()
+                    .add_binding(0, mesh_meta.alpha_mask_uniforms.binding(chunk))
+                    .finish();
+                render_resources.create_bind_group(alpha_mask_layout.id, &bind_group);
+                bind_group.id
+            })
+            .collect()
+    };
+
     mesh_meta
         .transform_uniforms
         .write_to_staging_buffer(&render_resources);
+    mesh_meta
+        .previous_transform_uniforms
+        .write_to_staging_buffer(&render_resources);
+    mesh_meta
+        .splat_uniforms
+        .write_to_staging_buffer(&render_resources);
+    mesh_meta
+        .lightmap_uniforms
+        .write_to_staging_buffer(&render_resources);
+    mesh_meta
+        .alpha_mask_uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+/// The level of detail selected for each extracted mesh, indexed by draw key.
+pub struct ViewMeshLods {
+    levels: Vec<usize>,
+}
+
+pub fn prepare_mesh_lods(
+    mut commands: Commands,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<(Entity, &ExtractedView)>,
+) {
+    if extracted_meshes
+        .meshes
+        .iter()
+        .all(|extracted_mesh| extracted_mesh.levels.len() == 1)
+    {
+        return;
+    }
+
+    for (entity, view) in views.iter() {
+        let lod_view = LodView::new(view.transform.translation, &view.projection, view.height);
+        let levels = extracted_meshes
+            .meshes
+            .iter()
+            .map(|extracted_mesh| {
+                select_lod(
+                    extracted_mesh.levels.iter().map(|level| &level.threshold),
+                    &lod_view,
+                    extracted_mesh.transform.w_axis.truncate(),
+                )
+                .unwrap_or(0)
+            })
+            .collect();
+        commands.entity(entity).insert(ViewMeshLods { levels });
+    }
+}
+
+/// Returns the [`Drawable::sort_key`] of a mesh at `depth` along the view direction, which orders
+/// meshes from the nearest to the farthest, or from the farthest to the nearest if
+/// `back_to_front`.
+fn depth_sort_key(depth: f32, back_to_front: bool) -> usize {
+    let bits = depth.to_bits();
+    // negative floats sort backwards when their bits are compared
+    let key = if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    };
+    if back_to_front {
+        !key as usize
+    } else {
+        key as usize
+    }
 }
 
 // TODO: This is temporary. Once we expose BindGroupLayouts directly, we can create view bind groups without specific shader context
 struct MeshViewBindGroups {
     view_bind_group: BindGroupId,
-    mesh_transform_bind_group: BindGroupId,
+    /// One bind group per chunk of [`MeshMeta::transform_uniforms`].
+    mesh_transform_bind_groups: Vec<BindGroupId>,
 }
 
 pub fn queue_meshes(
     mut commands: Commands,
     draw_functions: Res<DrawFunctions>,
     render_resources: Res<RenderResources>,
-    pbr_shaders: Res<PbrShaders>,
-    shadow_shaders: Res<ShadowShaders>,
+    mut pbr_shaders: ResMut<PbrShaders>,
+    mut shadow_shaders: ResMut<ShadowShaders>,
     mesh_meta: Res<MeshMeta>,
     light_meta: Res<LightMeta>,
     view_meta: Res<ViewMeta>,
     extracted_meshes: Res<ExtractedMeshes>,
-    mut views: Query<(Entity, &ViewLights, &mut RenderPhase<Transparent3dPhase>)>,
-    mut view_light_shadow_phases: Query<&mut RenderPhase<ShadowPhase>>,
+    mut views: Query<(
+        Entity,
+        &ExtractedView,
+        &ViewUniform,
+        &ViewLights,
+        &mut RenderPhase<Opaque3dPhase>,
+        &mut RenderPhase<AlphaMask3dPhase>,
+        &mut RenderPhase<Transparent3dPhase>,
+        &mut RenderPhase<Overlay3dPhase>,
+        Option<&OrderIndependentTransparency>,
+    )>,
+    mut view_light_shadow_phases: Query<(&ViewUniform, &mut RenderPhase<ShadowPhase>)>,
 ) {
     if extracted_meshes.meshes.is_empty() {
         return;
     }
-    for (entity, view_lights, mut transparent_phase) in views.iter_mut() {
-        let layout = &pbr_shaders.pipeline_descriptor.layout;
+    for extracted_mesh in extracted_meshes.meshes.iter() {
+        for level in extracted_mesh.levels.iter() {
+            let variant = extracted_mesh.variant(level.key);
+            pbr_shaders.specialize(&render_resources, level.key, variant);
+            // the shadow pipelines are only created for triangle lists
+            if level.key.is_triangle_list() {
+                shadow_shaders
+                    .pipelines
+                    .specialize(&render_resources, level.key.vertex_buffer);
+            }
+        }
+    }
+    let layout = pbr_shaders.layout(PbrVariant::Error);
+    let mesh_transform_bind_groups = (0..mesh_meta.transform_uniforms.chunk_count())
+        .map(|chunk| {
+            let mesh_transform_bind_group = BindGroupBuilder::default()
+                .add_binding(0, mesh_meta.transform_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(layout.bind_group(1).id, &mesh_transform_bind_group);
+            mesh_transform_bind_group.id
+        })
+        .collect::<Vec<_>>();
+
+    for (
+        entity,
+        view,
+        view_uniform,
+        view_lights,
+        mut opaque_phase,
+        mut alpha_mask_phase,
+        mut transparent_phase,
+        mut overlay_phase,
+        oit,
+    ) in views.iter_mut()
+    {
         let view_bind_group = BindGroupBuilder::default()
-            .add_binding(0, view_meta.uniforms.binding())
-            .add_binding(1, light_meta.view_gpu_lights.binding())
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .add_binding(
+                1,
+                light_meta
+                    .view_gpu_lights
+                    .binding(view_lights.gpu_light_binding_chunk),
+            )
             .add_binding(2, view_lights.light_depth_texture_view)
             .add_binding(3, shadow_shaders.light_sampler)
             .finish();
@@ -239,42 +1444,68 @@ pub fn queue_meshes(
         // TODO: this will only create the bind group if it isn't already created. this is a bit nasty
         render_resources.create_bind_group(layout.bind_group(0).id, &view_bind_group);
 
-        let mesh_transform_bind_group = BindGroupBuilder::default()
-            .add_binding(0, mesh_meta.transform_uniforms.binding())
-            .finish();
-        render_resources.create_bind_group(layout.bind_group(1).id, &mesh_transform_bind_group);
-
         commands.entity(entity).insert(MeshViewBindGroups {
             view_bind_group: view_bind_group.id,
-            mesh_transform_bind_group: mesh_transform_bind_group.id,
+            mesh_transform_bind_groups: mesh_transform_bind_groups.clone(),
         });
 
+        let frustum = view.frustum();
+        let view_forward = view.transform.rotation * -Vec3::Z;
         let draw_pbr = draw_functions.read().get_id::<DrawPbr>().unwrap();
-        for i in 0..extracted_meshes.meshes.len() {
-            // TODO: currently there is only "transparent phase". this should pick transparent vs opaque according to the mesh material
-            transparent_phase.add(Drawable {
+        for (i, extracted_mesh) in extracted_meshes.meshes.iter().enumerate() {
+            if !extracted_mesh.is_visible(&frustum) {
+                continue;
+            }
+            let depth = (extracted_mesh.center() - view.transform.translation).dot(view_forward);
+            // opaque meshes are drawn front to back, so fewer fragments are shaded behind the
+            // nearest ones, and blended meshes back to front, so they blend over what's behind
+            let back_to_front = matches!(
+                extracted_mesh.phase,
+                MeshPhase::Transparent | MeshPhase::Overlay
+            );
+            let drawable = Drawable {
                 draw_function: draw_pbr,
                 draw_key: i,
-                sort_key: 0, // TODO: sort back-to-front
-            });
+                sort_key: depth_sort_key(depth, back_to_front),
+                entity: extracted_mesh.entity,
+                clip: None,
+            };
+            match extracted_mesh.phase {
+                MeshPhase::Opaque => opaque_phase.add(drawable),
+                MeshPhase::AlphaMask => alpha_mask_phase.add(drawable),
+                // transparent triangle meshes are drawn by the `OitPassNode` instead
+                MeshPhase::Transparent if oit.is_some() && extracted_mesh.is_triangle_list() => {}
+                MeshPhase::Transparent => transparent_phase.add(drawable),
+                MeshPhase::Overlay => overlay_phase.add(drawable),
+                MeshPhase::Custom => {}
+            }
         }
 
         // ultimately lights should check meshes for relevancy (ex: light views can "see" different meshes than the main view can)
         let draw_shadow_mesh = draw_functions.read().get_id::<DrawShadowMesh>().unwrap();
         for view_light_entity in view_lights.lights.iter().copied() {
-            let mut shadow_phase = view_light_shadow_phases.get_mut(view_light_entity).unwrap();
+            let (view_light_uniform, mut shadow_phase) =
+                view_light_shadow_phases.get_mut(view_light_entity).unwrap();
             let layout = &shadow_shaders.pipeline_descriptor.layout;
             let shadow_view_bind_group = BindGroupBuilder::default()
-                .add_binding(0, view_meta.uniforms.binding())
+                .add_binding(
+                    0,
+                    view_meta
+                        .uniforms
+                        .binding(view_light_uniform.view_uniform_chunk),
+                )
                 .finish();
 
             render_resources.create_bind_group(layout.bind_group(0).id, &shadow_view_bind_group);
             // TODO: this should only queue up meshes that are actually visible by each "light view"
-            for i in 0..extracted_meshes.meshes.len() {
+            for (i, extracted_mesh) in extracted_meshes.meshes.iter().enumerate() {
+                // shadows only write depth, so the order they are drawn in doesn't matter
                 shadow_phase.add(Drawable {
                     draw_function: draw_shadow_mesh,
                     draw_key: i,
-                    sort_key: 0, // TODO: sort back-to-front
+                    sort_key: 0,
+                    entity: extracted_mesh.entity,
+                    clip: None,
                 })
             }
 
@@ -282,7 +1513,7 @@ pub fn queue_meshes(
                 .entity(view_light_entity)
                 .insert(MeshViewBindGroups {
                     view_bind_group: shadow_view_bind_group.id,
-                    mesh_transform_bind_group: mesh_transform_bind_group.id,
+                    mesh_transform_bind_groups: mesh_transform_bind_groups.clone(),
                 });
         }
     }
@@ -303,6 +1534,18 @@ impl Node for PbrNode {
         mesh_meta
             .transform_uniforms
             .write_to_uniform_buffer(render_context);
+        mesh_meta
+            .previous_transform_uniforms
+            .write_to_uniform_buffer(render_context);
+        mesh_meta
+            .splat_uniforms
+            .write_to_uniform_buffer(render_context);
+        mesh_meta
+            .lightmap_uniforms
+            .write_to_uniform_buffer(render_context);
+        mesh_meta
+            .alpha_mask_uniforms
+            .write_to_uniform_buffer(render_context);
         light_meta
             .view_gpu_lights
             .write_to_uniform_buffer(render_context);
@@ -313,7 +1556,15 @@ impl Node for PbrNode {
 type DrawPbrParams<'a> = (
     Res<'a, PbrShaders>,
     Res<'a, ExtractedMeshes>,
-    Query<'a, (&'a ViewUniform, &'a MeshViewBindGroups, &'a ViewLights)>,
+    Query<
+        'a,
+        (
+            &'a ViewUniform,
+            &'a MeshViewBindGroups,
+            &'a ViewLights,
+            Option<&'a ViewMeshLods>,
+        ),
+    >,
 );
 pub struct DrawPbr {
     params: SystemState<DrawPbrParams<'static>>,
@@ -325,22 +1576,24 @@ impl DrawPbr {
             params: SystemState::new(world),
         }
     }
-}
 
-impl Draw for DrawPbr {
-    fn draw(
+    /// Draws the mesh for `draw_key` with the pipeline returned by `pipeline`, which is given
+    /// the [`MeshPipelineKey`] of the level drawn. The pipeline must have the bind group layouts
+    /// of [`PbrShaders::pipeline`].
+    pub(crate) fn draw_with_pipeline(
         &mut self,
         world: &World,
         pass: &mut TrackedRenderPass,
         view: Entity,
         draw_key: usize,
-        _sort_key: usize,
+        pipeline: impl FnOnce(MeshPipelineKey) -> PipelineId,
     ) {
         let (pbr_shaders, extracted_meshes, views) = self.params.get(world);
-        let (view_uniforms, mesh_view_bind_groups, view_lights) = views.get(view).unwrap();
-        let layout = &pbr_shaders.pipeline_descriptor.layout;
-        let extracted_mesh = &extracted_meshes.meshes[draw_key];
-        pass.set_pipeline(pbr_shaders.pipeline);
+        let (view_uniforms, mesh_view_bind_groups, view_lights, view_mesh_lods) =
+            views.get(view).unwrap();
+        let layout = pbr_shaders.layout(PbrVariant::Error);
+        let (extracted_mesh, mesh_level) = extracted_meshes.get(draw_key, view_mesh_lods);
+        pass.set_pipeline(pipeline(mesh_level.key));
         pass.set_bind_group(
             0,
             layout.bind_group(0).id,
@@ -350,18 +1603,289 @@ impl Draw for DrawPbr {
                 view_lights.gpu_light_binding_index,
             ]),
         );
+        let mesh_transform_bind_group = mesh_view_bind_groups.mesh_transform_bind_groups
+            [extracted_mesh.transform_binding.chunk];
         pass.set_bind_group(
             1,
             layout.bind_group(1).id,
-            mesh_view_bind_groups.mesh_transform_bind_group,
-            Some(&[extracted_mesh.transform_binding_offset]),
+            mesh_transform_bind_group,
+            Some(&[extracted_mesh.transform_binding.offset]),
         );
-        pass.set_vertex_buffer(0, extracted_mesh.vertex_buffer, 0);
-        if let Some(index_info) = &extracted_mesh.index_info {
-            pass.set_index_buffer(index_info.buffer, 0, IndexFormat::Uint32);
-            pass.draw_indexed(0..index_info.count, 0, 0..1);
+        mesh_level.draw(pass);
+    }
+}
+
+impl Draw for DrawPbr {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let pbr_shaders = world.get_resource::<PbrShaders>().unwrap();
+        let mesh_meta = world.get_resource::<MeshMeta>().unwrap();
+        let extracted_meshes = world.get_resource::<ExtractedMeshes>().unwrap();
+        let extracted_mesh = &extracted_meshes.meshes[draw_key];
+        // bind groups stay bound when the pipeline is set, so the material can be bound first.
+        // the lightmap is only read by the levels with lightmap uvs, but binding it for the
+        // others does no harm
+        let material = if let Some(splat) = &extracted_mesh.splat {
+            Some((PbrVariant::Splat, splat.bind_group, splat.binding))
+        } else if let Some(alpha_mask) = &extracted_mesh.alpha_mask {
+            Some((
+                PbrVariant::AlphaMask,
+                Some(mesh_meta.alpha_mask_bind_groups[alpha_mask.binding.chunk]),
+                alpha_mask.binding,
+            ))
         } else {
-            panic!("non-indexed drawing not supported yet")
+            extracted_mesh
+                .lightmap
+                .as_ref()
+                .map(|lightmap| (PbrVariant::Lightmap, lightmap.bind_group, lightmap.binding))
+        };
+        if let Some((variant, bind_group, binding)) = material {
+            pass.set_bind_group(
+                2,
+                pbr_shaders.layout(variant).bind_group(2).id,
+                bind_group.unwrap(),
+                Some(&[binding.offset]),
+            );
         }
+        self.draw_with_pipeline(world, pass, view, draw_key, |key| {
+            pbr_shaders.variant_pipeline(extracted_mesh.variant(key), key)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_render2::{mesh::shape, renderer::HeadlessRenderResourceContext};
+    use std::num::NonZeroU32;
+
+    fn attribute_format(vertex_buffer: &MeshVertexBuffer, name: &str) -> Option<VertexFormat> {
+        vertex_buffer
+            .layout()
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.format)
+    }
+
+    #[test]
+    fn quantized_meshes_get_their_own_vertex_buffer() {
+        let mut mesh = Mesh::from(shape::Cube { size: 1.0 });
+        let standard = MeshVertexBuffer::from_mesh(&mesh).unwrap();
+        assert_eq!(
+            standard,
+            MeshVertexBuffer::standard(MeshVertexLayout::default())
+        );
+
+        mesh.quantize_standard_attributes();
+        let quantized = MeshVertexBuffer::from_mesh(&mesh).unwrap();
+        assert_ne!(quantized, standard);
+        assert_eq!(
+            attribute_format(&quantized, Mesh::ATTRIBUTE_POSITION),
+            Some(VertexFormat::Float32x3)
+        );
+        assert_eq!(
+            attribute_format(&quantized, Mesh::ATTRIBUTE_NORMAL),
+            Some(VertexFormat::Snorm16x4)
+        );
+        assert_eq!(
+            attribute_format(&quantized, Mesh::ATTRIBUTE_UV_0),
+            Some(VertexFormat::Unorm16x2)
+        );
+        assert_eq!(quantized.layout().stride, 12 + 8 + 4);
+    }
+
+    #[test]
+    fn meshes_without_normals_are_refused() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0]; 3]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; 3]);
+        assert!(MeshVertexBuffer::from_mesh(&mesh).is_err());
+        assert!(MeshPipelineKey::from_mesh(&mesh).is_err());
+    }
+
+    #[test]
+    fn depth_sort_keys_order_meshes_by_depth() {
+        let depths = [2.0, -1.0, 0.5, 10.0, 0.0];
+        let mut front_to_back = depths.to_vec();
+        front_to_back.sort_by_key(|depth| depth_sort_key(*depth, false));
+        assert_eq!(front_to_back, vec![-1.0, 0.0, 0.5, 2.0, 10.0]);
+        let mut back_to_front = depths.to_vec();
+        back_to_front.sort_by_key(|depth| depth_sort_key(*depth, true));
+        assert_eq!(back_to_front, vec![10.0, 2.0, 0.5, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn bindless_shaders_compile() {
+        let bindless = BindlessTextures { max_textures: 4 };
+        for (shader_def, texture_array) in
+            [("SPLAT_MAP", "t_SplatMaps"), ("LIGHTMAP", "t_Lightmaps")].iter()
+        {
+            let shader_defs = [shader_def.to_string(), String::from("BINDLESS")];
+            Shader::from_glsl(ShaderStage::Vertex, include_str!("pbr.vert"))
+                .get_spirv_shader(Some(&shader_defs))
+                .unwrap();
+            let fragment_shader =
+                Shader::from_glsl(ShaderStage::Fragment, include_str!("pbr.frag"))
+                    .get_spirv_shader(Some(&shader_defs))
+                    .unwrap();
+            let layout = fragment_shader
+                .reflect_layout(&bindless.reflect_options(texture_array))
+                .unwrap();
+            let material = layout
+                .bind_groups
+                .iter()
+                .find(|bind_group| bind_group.index == 2)
+                .unwrap();
+            assert_eq!(material.bindings[0].count, NonZeroU32::new(4));
+        }
+    }
+
+    fn extracted_mesh(
+        splat: Option<ExtractedSplat>,
+        lightmap: Option<ExtractedLightmap>,
+    ) -> ExtractedMesh {
+        ExtractedMesh {
+            entity: Entity::new(0),
+            transform: Mat4::IDENTITY,
+            previous_transform: Mat4::IDENTITY,
+            levels: Vec::new(),
+            transform_binding: DynamicUniformIndex::default(),
+            wireframe: false,
+            phase: MeshPhase::Opaque,
+            aabb: None,
+            splat,
+            lightmap,
+            alpha_mask: None,
+            error_material: false,
+        }
+    }
+
+    fn splat(splat_map: TextureViewId, sampler: SamplerId) -> ExtractedSplat {
+        ExtractedSplat {
+            splat_map,
+            sampler,
+            layers: [Vec4::ONE; 4],
+            binding: DynamicUniformIndex::default(),
+            bind_group: None,
+        }
+    }
+
+    /// Returns the `u32` at `offset` bytes in the uniform of `binding`.
+    fn uniform_u32<T: AsStd140>(
+        context: &HeadlessRenderResourceContext,
+        uniforms: &DynamicUniformVec<T>,
+        binding: DynamicUniformIndex,
+        offset: usize,
+    ) -> u32 {
+        let data = context
+            .get_buffer_data(uniforms.uniform_buffer(binding.chunk).unwrap())
+            .unwrap();
+        let start = binding.offset as usize + offset;
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&data[start..start + 4]);
+        u32::from_ne_bytes(bytes)
+    }
+
+    #[test]
+    fn bindless_meshes_are_batched_by_texture_array() {
+        let context = HeadlessRenderResourceContext::default();
+        let mut world = World::new();
+        world.insert_resource(RenderResources::new(Box::new(context.clone())));
+        world.insert_resource(BindlessTextures { max_textures: 2 });
+        let pbr_shaders = PbrShaders::from_world(&mut world);
+        assert!(pbr_shaders.bindless.is_some());
+        world.insert_resource(pbr_shaders);
+        world.init_resource::<MeshMeta>();
+
+        let sampler = SamplerId::new();
+        let textures = (0..3).map(|_| TextureViewId::new()).collect::<Vec<_>>();
+        let lightmap = ExtractedLightmap {
+            image: textures[1],
+            sampler,
+            uniform: GpuLightmap {
+                uv_rect: Vec4::new(0.0, 0.0, 1.0, 1.0),
+                intensity: 1.0,
+                texture_index: 0,
+            },
+            binding: DynamicUniformIndex::default(),
+            bind_group: None,
+        };
+        // the third texture doesn't fit in the array of the first two
+        let meshes = vec![
+            extracted_mesh(Some(splat(textures[0], sampler)), None),
+            extracted_mesh(Some(splat(textures[1], sampler)), None),
+            extracted_mesh(Some(splat(textures[0], sampler)), None),
+            extracted_mesh(Some(splat(textures[2], sampler)), None),
+            extracted_mesh(None, Some(lightmap)),
+        ];
+        world.insert_resource(ExtractedMeshes { meshes });
+
+        let mut system = prepare_meshes.system();
+        system.initialize(&mut world);
+        system.run((), &mut world);
+
+        let mesh_meta = world.get_resource::<MeshMeta>().unwrap();
+        let extracted_meshes = world.get_resource::<ExtractedMeshes>().unwrap();
+        let splats = extracted_meshes.meshes[..4]
+            .iter()
+            .map(|extracted_mesh| extracted_mesh.splat.as_ref().unwrap())
+            .collect::<Vec<_>>();
+        // `texture_index` follows the 4 layer colors
+        let texture_indices = splats
+            .iter()
+            .map(|splat| uniform_u32(&context, &mesh_meta.splat_uniforms, splat.binding, 64))
+            .collect::<Vec<_>>();
+        assert_eq!(texture_indices, vec![0, 1, 0, 0]);
+        assert_eq!(splats[0].bind_group, splats[1].bind_group);
+        assert_eq!(splats[0].bind_group, splats[2].bind_group);
+        assert_ne!(splats[0].bind_group, splats[3].bind_group);
+        let (_, bind_group) = context
+            .get_bind_group(splats[0].bind_group.unwrap())
+            .unwrap();
+        assert_eq!(
+            bind_group.indexed_bindings[0].entry,
+            RenderResourceBinding::TextureArrayView(vec![textures[0], textures[1]])
+        );
+
+        // lightmaps are batched on their own, so the texture starts a new array
+        let lightmap = extracted_meshes.meshes[4].lightmap.as_ref().unwrap();
+        assert_eq!(lightmap.uniform.texture_index, 0);
+        // `texture_index` follows the uv rect and the intensity
+        assert_eq!(
+            uniform_u32(&context, &mesh_meta.lightmap_uniforms, lightmap.binding, 20),
+            0
+        );
+        let (_, bind_group) = context
+            .get_bind_group(lightmap.bind_group.unwrap())
+            .unwrap();
+        assert_eq!(
+            bind_group.indexed_bindings[0].entry,
+            RenderResourceBinding::TextureArrayView(vec![textures[1], textures[1]])
+        );
+    }
+
+    #[test]
+    fn alpha_mask_shader_binds_its_uniform_to_set_2() {
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("pbr.frag"))
+            .get_spirv_shader(Some(&[String::from("ALPHA_MASK")]))
+            .unwrap();
+        let layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let alpha_mask = layout
+            .bind_groups
+            .iter()
+            .find(|bind_group| bind_group.index == 2)
+            .unwrap();
+        assert_eq!(alpha_mask.bindings.len(), 1);
+        assert!(matches!(
+            alpha_mask.bindings[0].bind_type,
+            BindType::Uniform { .. }
+        ));
     }
 }
@@ -1,10 +1,24 @@
 #version 450
+#ifdef BINDLESS
+#extension GL_EXT_nonuniform_qualifier : require
+#endif
 
 layout(location = 0) in vec4 v_WorldPosition;
 layout(location = 1) in vec3 v_WorldNormal;
 layout(location = 2) in vec2 v_Uv;
-
+#ifdef VERTEX_COLORS
+layout(location = 3) in vec4 v_Color;
+#endif
+#ifdef LIGHTMAP
+layout(location = 4) in vec2 v_LightmapUv;
+#endif
+
+#ifdef ORDER_INDEPENDENT_TRANSPARENCY
+layout(location = 0) out vec4 o_Accum;
+layout(location = 1) out float o_Revealage;
+#else
 layout(location = 0) out vec4 o_Target;
+#endif
 
 struct PointLight {
     vec4 color;
@@ -25,10 +39,48 @@ layout(set = 0, binding = 0) uniform View {
 layout(std140, set = 0, binding = 1) uniform Lights {
     uint NumLights;
     PointLight PointLights[MAX_POINT_LIGHTS];
+    float Exposure;
 };
 layout(set = 0, binding = 2) uniform texture2DArray t_Shadow;
 layout(set = 0, binding = 3) uniform samplerShadow s_Shadow;
 
+#ifdef SPLAT_MAP
+#ifdef BINDLESS
+// sized to `BindlessTextures::max_textures` when the layout is reflected
+layout(set = 2, binding = 0) uniform texture2D t_SplatMaps[];
+#else
+layout(set = 2, binding = 0) uniform texture2D t_SplatMap;
+#endif
+layout(set = 2, binding = 1) uniform sampler s_SplatMap;
+layout(set = 2, binding = 2) uniform SplatLayers {
+    vec4 LayerColors[4];
+    uint SplatMapIndex;
+};
+#endif
+
+#ifdef LIGHTMAP
+#ifdef BINDLESS
+layout(set = 2, binding = 0) uniform texture2D t_Lightmaps[];
+#else
+layout(set = 2, binding = 0) uniform texture2D t_Lightmap;
+#endif
+layout(set = 2, binding = 1) uniform sampler s_Lightmap;
+layout(set = 2, binding = 2) uniform Lightmap {
+    // offset in xy, scale in zw
+    vec4 LightmapUvRect;
+    float LightmapIntensity;
+    uint LightmapIndex;
+};
+#endif
+
+#ifdef ALPHA_MASK
+layout(set = 2, binding = 0) uniform AlphaMask {
+    // the alpha of the material color
+    float Alpha;
+    float AlphaCutoff;
+};
+#endif
+
 #    define saturate(x) clamp(x, 0.0, 1.0)
 const float PI = 3.141592653589793;
 
@@ -253,11 +305,41 @@ float fetch_shadow(int light_id, vec4 homogeneous_coords) {
 
 void main() {
     vec4 color = vec4(0.6, 0.6, 0.6, 1.0); 
+#ifdef SPLAT_MAP
+    // the weights of the layers are normalized, so they don't need to add up to 1
+#ifdef BINDLESS
+    vec4 weights = texture(sampler2D(t_SplatMaps[SplatMapIndex], s_SplatMap), v_Uv);
+#else
+    vec4 weights = texture(sampler2D(t_SplatMap, s_SplatMap), v_Uv);
+#endif
+    color = (LayerColors[0] * weights.r
+        + LayerColors[1] * weights.g
+        + LayerColors[2] * weights.b
+        + LayerColors[3] * weights.a) / max(dot(weights, vec4(1.0)), 1e-4);
+#endif
+#ifdef VERTEX_COLORS
+    color *= v_Color;
+#endif
+#ifdef ALPHA_MASK
+    color.a *= Alpha;
+    if (color.a < AlphaCutoff) {
+        discard;
+    }
+#endif
     float metallic = 0.01;
     float reflectance = 0.5;
     float perceptual_roughness = 0.089;
     vec3 emissive = vec3(0.0, 0.0, 0.0);
     vec3 ambient_color = vec3(0.1, 0.1, 0.1);
+#ifdef LIGHTMAP
+    // the baked indirect light replaces the constant ambient light
+    vec2 lightmap_uv = v_LightmapUv * LightmapUvRect.zw + LightmapUvRect.xy;
+#ifdef BINDLESS
+    ambient_color = texture(sampler2D(t_Lightmaps[LightmapIndex], s_Lightmap), lightmap_uv).rgb * LightmapIntensity;
+#else
+    ambient_color = texture(sampler2D(t_Lightmap, s_Lightmap), lightmap_uv).rgb * LightmapIntensity;
+#endif
+#endif
     float occlusion = 1.0;
 
     float roughness = perceptualRoughnessToRoughness(perceptual_roughness);    
@@ -288,11 +370,34 @@ void main() {
     output_color += (diffuse_ambient + specular_ambient) * ambient_color * occlusion;
     output_color += emissive * color.a;
 
+#ifdef HDR
+    // tonemapped once the view is drawn
+    output_color *= Exposure;
+#else
     // tone_mapping
-    output_color = reinhard_luminance(output_color);
+    output_color = reinhard_luminance(output_color * Exposure);
+#endif
     // Gamma correction.
     // Not needed with sRGB buffer
     // output_color = pow(output_color, vec3(1.0 / 2.2));
-
+#ifdef ERROR_MATERIAL
+    // an unlit magenta checkerboard, which stands out from any lighting
+    vec2 cell = floor(v_Uv * 8.0);
+    output_color = mod(cell.x + cell.y, 2.0) < 1.0 ? vec3(1.0, 0.0, 1.0) : vec3(0.05, 0.0, 0.05);
+    color.a = 1.0;
+#endif
+
+#ifdef ORDER_INDEPENDENT_TRANSPARENCY
+    // Weighted blended order-independent transparency, McGuire and Bavoil 2013, equation 10
+    float view_distance = length(ViewWorldPosition.xyz - v_WorldPosition.xyz);
+    float weight = color.a * clamp(
+        10.0 / (1e-5 + pow(view_distance / 5.0, 2.0) + pow(view_distance / 200.0, 6.0)),
+        1e-2,
+        3e3
+    );
+    o_Accum = vec4(output_color * color.a, color.a) * weight;
+    o_Revealage = color.a;
+#else
     o_Target = vec4(output_color, 1.0);
+#endif
 }
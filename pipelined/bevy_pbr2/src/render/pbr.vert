@@ -3,10 +3,22 @@
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in vec3 Vertex_Normal;
 layout(location = 2) in vec2 Vertex_Uv;
+#ifdef VERTEX_COLORS
+layout(location = 3) in vec4 Vertex_Color;
+#endif
+#ifdef LIGHTMAP
+layout(location = 7) in vec2 Vertex_Uv_1;
+#endif
 
 layout(location = 0) out vec4 v_WorldPosition;
 layout(location = 1) out vec3 v_WorldNormal;
 layout(location = 2) out vec2 v_Uv;
+#ifdef VERTEX_COLORS
+layout(location = 3) out vec4 v_Color;
+#endif
+#ifdef LIGHTMAP
+layout(location = 4) out vec2 v_LightmapUv;
+#endif
 
 layout(set = 0, binding = 0) uniform View {
     mat4 ViewProj;
@@ -19,6 +31,12 @@ layout(set = 1, binding = 0) uniform MeshTransform {
 
 void main() {
     v_Uv = Vertex_Uv;
+#ifdef VERTEX_COLORS
+    v_Color = Vertex_Color;
+#endif
+#ifdef LIGHTMAP
+    v_LightmapUv = Vertex_Uv_1;
+#endif
     v_WorldPosition = Model * vec4(Vertex_Position, 1.0);
     v_WorldNormal = mat3(Model) * Vertex_Normal;
     gl_Position = ViewProj * v_WorldPosition;
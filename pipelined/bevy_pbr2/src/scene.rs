@@ -0,0 +1,243 @@
+use crate::{SplatMaterial, StandardMaterial};
+use bevy_asset::{Asset, AssetPath, AssetServer, Handle, HandleId};
+use bevy_ecs::{prelude::*, reflect::ReflectComponent};
+use bevy_reflect::Reflect;
+use bevy_render2::color::Color;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// The asset path of an entity's `Handle<T>` material, so scenes can save and restore materials
+/// that were loaded from files.
+///
+/// Handles are stored in scenes by id, which doesn't load the asset when the scene is spawned.
+/// This component is kept up to date for handles that were loaded from a path, and the handle is
+/// loaded from the path when an entity is spawned with only this component, e.g. from a scene.
+/// Materials created in code have no path and can't be restored this way.
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MaterialPath<T: Asset> {
+    pub path: String,
+    #[reflect(ignore)]
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Asset> MaterialPath<T> {
+    pub fn new(path: impl Into<String>) -> Self {
+        MaterialPath {
+            path: path.into(),
+            marker: PhantomData,
+        }
+    }
+
+    fn handle_id(&self) -> HandleId {
+        AssetPath::from(self.path.as_str()).into()
+    }
+}
+
+impl<T: Asset> Default for MaterialPath<T> {
+    fn default() -> Self {
+        MaterialPath::new(String::new())
+    }
+}
+
+fn asset_path_string(asset_path: &AssetPath) -> String {
+    let path = asset_path.path().to_string_lossy();
+    match asset_path.label() {
+        Some(label) => format!("{}#{}", path, label),
+        None => path.into_owned(),
+    }
+}
+
+/// Loads the material of entities whose [`MaterialPath`] doesn't match their handle.
+pub fn load_material_paths<T: Asset>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<(Entity, &MaterialPath<T>, Option<&Handle<T>>), Changed<MaterialPath<T>>>,
+) {
+    for (entity, material_path, handle) in query.iter() {
+        // handles spawned from scenes have the id of the path, but are weak and don't load it
+        if material_path.path.is_empty()
+            || handle.map_or(false, |handle| {
+                handle.is_strong() && handle.id == material_path.handle_id()
+            })
+        {
+            continue;
+        }
+        let handle: Handle<T> = asset_server.load(material_path.path.as_str());
+        commands.entity(entity).insert(handle);
+    }
+}
+
+/// Updates the [`MaterialPath`] of entities whose material handle changed.
+pub fn sync_material_paths<T: Asset>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<(Entity, &Handle<T>, Option<&MaterialPath<T>>), Changed<Handle<T>>>,
+) {
+    for (entity, handle, material_path) in query.iter() {
+        // weak handles are restored from scenes, and loaded by `load_material_paths`
+        if handle.is_weak() {
+            continue;
+        }
+        match asset_server.get_handle_path(handle) {
+            Some(asset_path) => {
+                if material_path
+                    .map_or(true, |material_path| material_path.handle_id() != handle.id)
+                {
+                    commands
+                        .entity(entity)
+                        .insert(MaterialPath::<T>::new(asset_path_string(&asset_path)));
+                }
+            }
+            // the path would load a different material than the one in the handle
+            None if material_path.is_some() => {
+                commands.entity(entity).remove::<MaterialPath<T>>();
+            }
+            None => {}
+        }
+    }
+}
+
+/// The file format of [`SplatMaterial`]s, which refers to the splat map by path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplatMaterialDescriptor {
+    pub splat_map: String,
+    pub layers: [Color; 4],
+}
+
+impl SplatMaterialDescriptor {
+    /// Returns `None` if the splat map wasn't loaded from a path.
+    pub fn new(material: &SplatMaterial, asset_server: &AssetServer) -> Option<Self> {
+        let splat_map = asset_server.get_handle_path(&material.splat_map)?;
+        Some(SplatMaterialDescriptor {
+            splat_map: asset_path_string(&splat_map),
+            layers: material.layers,
+        })
+    }
+}
+
+/// Loads [`StandardMaterial`]s from `.material.ron` files, as written by
+/// `ron::ser::to_string_pretty`.
+#[cfg(feature = "ron")]
+#[derive(Default)]
+pub struct StandardMaterialLoader;
+
+#[cfg(feature = "ron")]
+impl bevy_asset::AssetLoader for StandardMaterialLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy_asset::LoadContext,
+    ) -> bevy_utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let material = ron::de::from_bytes::<StandardMaterial>(bytes)?;
+            load_context.set_default_asset(bevy_asset::LoadedAsset::new(material));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["material.ron"]
+    }
+}
+
+/// Loads [`SplatMaterial`]s from `.splat.ron` files containing a [`SplatMaterialDescriptor`],
+/// along with their splat maps.
+#[cfg(feature = "ron")]
+#[derive(Default)]
+pub struct SplatMaterialLoader;
+
+#[cfg(feature = "ron")]
+impl bevy_asset::AssetLoader for SplatMaterialLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy_asset::LoadContext,
+    ) -> bevy_utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let descriptor = ron::de::from_bytes::<SplatMaterialDescriptor>(bytes)?;
+            let splat_map = AssetPath::from(descriptor.splat_map.as_str());
+            let material = SplatMaterial {
+                splat_map: load_context.get_handle(splat_map.clone()),
+                layers: descriptor.layers,
+            };
+            load_context.set_default_asset(
+                bevy_asset::LoadedAsset::new(material).with_dependency(splat_map),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["splat.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaterialPath, SplatMaterialDescriptor};
+    use crate::{SplatMaterial, StandardMaterial};
+    use bevy_ecs::{entity::EntityMap, world::World};
+    use bevy_reflect::TypeRegistryArc;
+    use bevy_render2::color::Color;
+    use bevy_scene::{serde::SceneDeserializer, DynamicScene};
+    use serde::de::DeserializeSeed;
+
+    #[test]
+    fn material_paths_survive_scene_round_trip() {
+        let registry = TypeRegistryArc::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<String>();
+            registry.register::<MaterialPath<StandardMaterial>>();
+            registry.register::<MaterialPath<SplatMaterial>>();
+        }
+        let mut world = World::default();
+        world
+            .spawn()
+            .insert(MaterialPath::<StandardMaterial>::new("rock.material.ron"));
+        world
+            .spawn()
+            .insert(MaterialPath::<SplatMaterial>::new("terrain.gltf#Splat0"));
+
+        let scene = DynamicScene::from_world(&world, &registry)
+            .serialize_ron(&registry)
+            .unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&scene).unwrap();
+        let scene = SceneDeserializer {
+            type_registry: &registry.read(),
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        let mut loaded = World::default();
+        loaded.insert_resource(registry.clone());
+        scene
+            .write_to_world(&mut loaded, &mut EntityMap::default())
+            .unwrap();
+
+        let standard_paths = loaded
+            .query::<&MaterialPath<StandardMaterial>>()
+            .iter(&loaded)
+            .map(|material_path| material_path.path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(standard_paths, vec!["rock.material.ron"]);
+        let splat_paths = loaded
+            .query::<&MaterialPath<SplatMaterial>>()
+            .iter(&loaded)
+            .map(|material_path| material_path.path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(splat_paths, vec!["terrain.gltf#Splat0"]);
+    }
+
+    #[test]
+    fn splat_material_descriptor_round_trip() {
+        let descriptor = SplatMaterialDescriptor {
+            splat_map: "textures/splat.png".to_string(),
+            layers: [Color::GREEN, Color::GRAY, Color::YELLOW, Color::WHITE],
+        };
+        let ron = ron::ser::to_string_pretty(&descriptor, Default::default()).unwrap();
+        let loaded = ron::de::from_str::<SplatMaterialDescriptor>(&ron).unwrap();
+        assert_eq!(loaded.splat_map, descriptor.splat_map);
+        assert_eq!(loaded.layers, descriptor.layers);
+    }
+}
@@ -0,0 +1,637 @@
+use crate::{motion_blur, oit, taa, wireframe, Exposure};
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_math::{Mat4, Vec3};
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{self, CorePipelineSettings, DepthMode, Transparent3dPhase},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{RenderPhase, TrackedRenderPass},
+    render_resource::{BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::ExtractedView,
+    RenderApp, RenderStage,
+};
+use crevice::std140::AsStd140;
+use std::num::NonZeroU32;
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const SKY_PASS: &'static str = "sky_pass";
+    }
+}
+
+pub const SKY_ENVIRONMENT_MAP_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Renders a procedural [`Sky`] behind the 3d scene and/or into a [`SkyEnvironmentMap`]. Must be
+/// added after [`crate::PbrPlugin`], and after the other plugins adding passes to 3d cameras so
+/// the sky is drawn before them.
+#[derive(Debug, Default)]
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sky>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_sky.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_sky.system())
+            .add_system_to_stage(RenderStage::Queue, queue_sky.system())
+            .init_render_resource::<SkyShaders>()
+            .init_resource::<SkyMeta>();
+
+        let sky_pass_node = SkyPassNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("sky", SkyNode);
+        graph
+            .add_node_edge("sky", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::SKY_PASS, sky_pass_node);
+        draw_3d_graph
+            .add_node_edge(
+                core_pipeline::draw_3d_graph::node::MAIN_PASS,
+                draw_3d_graph::node::SKY_PASS,
+            )
+            .unwrap();
+        // the passes drawing on top of the scene or reading its image come after the sky
+        for node in [
+            oit::draw_3d_graph::node::OIT_PASS,
+            wireframe::draw_3d_graph::node::WIREFRAME_PASS,
+            motion_blur::draw_3d_graph::node::MOTION_BLUR,
+            taa::draw_3d_graph::node::TAA_RESOLVE,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(draw_3d_graph::node::SKY_PASS, *node)
+                    .unwrap();
+            }
+        }
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::SKY_PASS,
+                SkyPassNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::RENDER_TARGET,
+                draw_3d_graph::node::SKY_PASS,
+                SkyPassNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::DEPTH,
+                draw_3d_graph::node::SKY_PASS,
+                SkyPassNode::IN_DEPTH,
+            )
+            .unwrap();
+    }
+}
+
+/// The settings of the sky, see [`SkyPlugin`]. The sky is lit by a sun, scattered by air
+/// molecules (Rayleigh scattering) and by larger particles like dust or water droplets (Mie
+/// scattering).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    /// The direction towards the sun. The sun sets when it goes below the horizon.
+    pub sun_direction: Vec3,
+    /// The haziness of the atmosphere, from 1 for a perfectly clear sky to around 10 for a
+    /// hazy one.
+    pub turbidity: f32,
+    /// Scales the Rayleigh scattering, which makes the sky blue and sunsets red.
+    pub rayleigh: f32,
+    /// Scales the Mie scattering, which makes the sky around the sun brighter.
+    pub mie_coefficient: f32,
+    /// How much Mie scattering favors light continuing in the direction it came from, from -1
+    /// to 1. Higher values give a smaller halo around the sun.
+    pub mie_directional_g: f32,
+    /// Draws the sky behind everything 3d cameras see.
+    pub background: bool,
+    /// Renders the sky into a [`SkyEnvironmentMap`] with faces of this size, which is updated
+    /// whenever the sky changes.
+    pub environment_map_size: Option<u32>,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            sun_direction: Vec3::new(0.3, 0.5, -0.8).normalize(),
+            turbidity: 2.0,
+            rayleigh: 1.0,
+            mie_coefficient: 0.005,
+            mie_directional_g: 0.8,
+            background: true,
+            environment_map_size: None,
+        }
+    }
+}
+
+pub fn extract_sky(mut commands: Commands, sky: Res<Sky>) {
+    commands.insert_resource(sky.clone());
+}
+
+pub struct SkyShaders {
+    pipeline: PipelineId,
+    environment_map_pipeline: PipelineId,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for SkyShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        let shader_defs = match depth_mode {
+            DepthMode::Standard => vec![],
+            DepthMode::ReverseZ => vec![String::from("REVERSE_Z")],
+        };
+        // views are tonemapped like the pbr shaders, the environment map keeps the luminance
+        let compile_fragment_shader = |tonemap: bool| {
+            let shader_defs = if tonemap {
+                vec![String::from("TONEMAP")]
+            } else {
+                vec![]
+            };
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("sky.frag"))
+                .get_spirv_shader(Some(&shader_defs))
+                .unwrap()
+        };
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("sky.vert"))
+            .get_spirv_shader(Some(&shader_defs))
+            .unwrap();
+        let fragment_shader = compile_fragment_shader(!settings.hdr);
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        // the sky is only drawn where the depth buffer is still clear
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            depth_stencil: Some(DepthStencilState {
+                format: settings.depth_format,
+                depth_write_enabled: false,
+                depth_compare: depth_mode.compare(CompareFunction::LessEqual),
+                stencil: StencilState {
+                    front: StencilFaceState::IGNORE,
+                    back: StencilFaceState::IGNORE,
+                    read_mask: 0,
+                    write_mask: 0,
+                },
+                bias: DepthBiasState {
+                    constant: 0,
+                    slope_scale: 0.0,
+                    clamp: 0.0,
+                },
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: settings.main_texture_format(),
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+
+        let mut environment_map_pipeline_descriptor = pipeline_descriptor.clone();
+        environment_map_pipeline_descriptor.depth_stencil = None;
+        environment_map_pipeline_descriptor.color_target_states[0].format =
+            SKY_ENVIRONMENT_MAP_FORMAT;
+        if !settings.hdr {
+            let fragment = render_resources.create_shader_module(&compile_fragment_shader(false));
+            environment_map_pipeline_descriptor.shader_stages.fragment = Some(fragment);
+        }
+        let environment_map_pipeline =
+            render_resources.create_render_pipeline(&environment_map_pipeline_descriptor);
+
+        SkyShaders {
+            pipeline,
+            environment_map_pipeline,
+            pipeline_descriptor,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuSky {
+    view_rotation: Mat4,
+    inverse_projection: Mat4,
+    sun_direction: Vec3,
+    turbidity: f32,
+    rayleigh: f32,
+    mie_coefficient: f32,
+    mie_directional_g: f32,
+    exposure: f32,
+}
+
+impl GpuSky {
+    /// The uniform of the sky seen with `view_rotation` and `projection`, scaled by the
+    /// [`Exposure::multiplier`] of the view.
+    fn new(sky: &Sky, view_rotation: Mat4, projection: Mat4, exposure: f32) -> Self {
+        GpuSky {
+            view_rotation,
+            inverse_projection: projection.inverse(),
+            sun_direction: sky.sun_direction,
+            turbidity: sky.turbidity,
+            rayleigh: sky.rayleigh,
+            mie_coefficient: sky.mie_coefficient,
+            mie_directional_g: sky.mie_directional_g,
+            exposure,
+        }
+    }
+}
+
+/// A cube map the [`Sky`] is rendered into when [`Sky::environment_map_size`] is set, e.g. for
+/// image based lighting. It has a single mip level, and uses [`SKY_ENVIRONMENT_MAP_FORMAT`].
+pub struct SkyEnvironmentMap {
+    pub texture: TextureId,
+    /// A cube view of the texture.
+    pub view: TextureViewId,
+    pub size: u32,
+    face_views: Vec<TextureViewId>,
+    /// The sky the faces were last rendered with.
+    rendered: Option<Sky>,
+}
+
+impl SkyEnvironmentMap {
+    fn new(render_resources: &RenderResources, size: u32) -> Self {
+        let texture = render_resources.create_texture(TextureDescriptor {
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: SKY_ENVIRONMENT_MAP_FORMAT,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+        });
+        let view = render_resources.create_texture_view(
+            texture,
+            TextureViewDescriptor {
+                dimension: Some(TextureViewDimension::Cube),
+                ..Default::default()
+            },
+        );
+        let face_views = (0..6)
+            .map(|face| {
+                render_resources.create_texture_view(
+                    texture,
+                    TextureViewDescriptor {
+                        dimension: Some(TextureViewDimension::D2),
+                        base_array_layer: face,
+                        array_layer_count: NonZeroU32::new(1),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        SkyEnvironmentMap {
+            texture,
+            view,
+            size,
+            face_views,
+            rendered: None,
+        }
+    }
+
+    fn remove(&self, render_resources: &RenderResources) {
+        for face_view in self.face_views.iter() {
+            render_resources.remove_texture_view(*face_view);
+        }
+        render_resources.remove_texture_view(self.view);
+        render_resources.remove_texture(self.texture);
+    }
+}
+
+/// The rotations of the cube map faces, in the order of their array layers. The columns are
+/// the directions of the right and top of the face, and the opposite of the direction it faces.
+fn cube_face_rotations() -> [Mat4; 6] {
+    let face = |right: Vec3, up: Vec3, forward: Vec3| {
+        Mat4::from_cols(
+            right.extend(0.0),
+            up.extend(0.0),
+            (-forward).extend(0.0),
+            Vec3::ZERO.extend(1.0),
+        )
+    };
+    [
+        face(-Vec3::Z, Vec3::Y, Vec3::X),
+        face(Vec3::Z, Vec3::Y, -Vec3::X),
+        face(Vec3::X, -Vec3::Z, Vec3::Y),
+        face(Vec3::X, Vec3::Z, -Vec3::Y),
+        face(Vec3::X, Vec3::Y, Vec3::Z),
+        face(-Vec3::X, Vec3::Y, -Vec3::Z),
+    ]
+}
+
+#[derive(Default)]
+pub struct SkyMeta {
+    pub uniforms: DynamicUniformVec<GpuSky>,
+    pub environment_map: Option<SkyEnvironmentMap>,
+    /// The uniforms of the environment map faces, when they need to be rendered this frame.
+    environment_map_faces: Vec<(DynamicUniformIndex, Option<BindGroupId>)>,
+}
+
+/// The sky of a 3d view, when [`Sky::background`] is set.
+pub struct ViewSky {
+    pub uniform: DynamicUniformIndex,
+}
+
+pub fn prepare_sky(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    sky: Res<Sky>,
+    mut sky_meta: ResMut<SkyMeta>,
+    views: Query<
+        (Entity, &ExtractedView, Option<&Exposure>),
+        With<RenderPhase<Transparent3dPhase>>,
+    >,
+) {
+    let sky_meta = &mut *sky_meta;
+    let environment_map_size = sky.environment_map_size.map(|size| size.max(1));
+    if sky_meta
+        .environment_map
+        .as_ref()
+        .map(|environment_map| environment_map.size)
+        != environment_map_size
+    {
+        if let Some(environment_map) = sky_meta.environment_map.take() {
+            environment_map.remove(&render_resources);
+        }
+        sky_meta.environment_map =
+            environment_map_size.map(|size| SkyEnvironmentMap::new(&render_resources, size));
+    }
+
+    let render_environment_map = sky_meta
+        .environment_map
+        .as_ref()
+        .map_or(false, |environment_map| {
+            environment_map.rendered.as_ref() != Some(&*sky)
+        });
+    let view_count = if sky.background {
+        views.iter().count()
+    } else {
+        0
+    };
+    let face_count = if render_environment_map { 6 } else { 0 };
+    sky_meta
+        .uniforms
+        .reserve_and_clear(view_count + face_count, &render_resources);
+
+    if sky.background {
+        for (entity, view, exposure) in views.iter() {
+            let view_rotation = Mat4::from_quat(view.transform.rotation);
+            let uniform = sky_meta.uniforms.push(GpuSky::new(
+                &sky,
+                view_rotation,
+                view.projection,
+                exposure.map_or(1.0, Exposure::multiplier),
+            ));
+            commands.entity(entity).insert(ViewSky { uniform });
+        }
+    }
+
+    sky_meta.environment_map_faces.clear();
+    if render_environment_map {
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 10.0);
+        for rotation in cube_face_rotations().iter() {
+            // exposed by the shaders sampling it
+            let uniform = sky_meta
+                .uniforms
+                .push(GpuSky::new(&sky, *rotation, projection, 1.0));
+            sky_meta.environment_map_faces.push((uniform, None));
+        }
+        if let Some(environment_map) = sky_meta.environment_map.as_mut() {
+            environment_map.rendered = Some(sky.clone());
+        }
+    }
+
+    sky_meta.uniforms.write_to_staging_buffer(&render_resources);
+}
+
+struct SkyViewBindGroup {
+    bind_group: BindGroupId,
+}
+
+pub fn queue_sky(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    sky_shaders: Res<SkyShaders>,
+    mut sky_meta: ResMut<SkyMeta>,
+    views: Query<(Entity, &ViewSky)>,
+) {
+    let sky_meta = &mut *sky_meta;
+    let uniforms = &sky_meta.uniforms;
+    let layout = &sky_shaders.pipeline_descriptor.layout;
+    let bind_group = |uniform: DynamicUniformIndex| {
+        let bind_group = BindGroupBuilder::default()
+            .add_binding(0, uniforms.binding(uniform.chunk))
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &bind_group);
+        bind_group.id
+    };
+    for (entity, view_sky) in views.iter() {
+        commands.entity(entity).insert(SkyViewBindGroup {
+            bind_group: bind_group(view_sky.uniform),
+        });
+    }
+    for (uniform, face_bind_group) in sky_meta.environment_map_faces.iter_mut() {
+        *face_bind_group = Some(bind_group(*uniform));
+    }
+}
+
+/// Writes the [`SkyMeta`] uniforms before the views are drawn, and renders the
+/// [`SkyEnvironmentMap`] when the sky changed.
+pub struct SkyNode;
+
+impl Node for SkyNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let sky_meta = world.get_resource::<SkyMeta>().unwrap();
+        sky_meta.uniforms.write_to_uniform_buffer(render_context);
+
+        let environment_map = match sky_meta.environment_map.as_ref() {
+            Some(environment_map) => environment_map,
+            None => return Ok(()),
+        };
+        let sky_shaders = world.get_resource::<SkyShaders>().unwrap();
+        let layout = &sky_shaders.pipeline_descriptor.layout;
+        for (face_view, (uniform, bind_group)) in environment_map
+            .face_views
+            .iter()
+            .zip(sky_meta.environment_map_faces.iter())
+        {
+            let bind_group = match bind_group {
+                Some(bind_group) => *bind_group,
+                None => continue,
+            };
+            let pass_descriptor = PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(*face_view),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
+            render_context.begin_render_pass(
+                &pass_descriptor,
+                &mut |render_pass: &mut dyn RenderPass| {
+                    let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                    tracked_pass.set_pipeline(sky_shaders.environment_map_pipeline);
+                    tracked_pass.set_bind_group(
+                        0,
+                        layout.bind_group(0).id,
+                        bind_group,
+                        Some(&[uniform.offset]),
+                    );
+                    tracked_pass.draw(0..3, 0..1);
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Draws the sky of a 3d view wherever the main pass didn't draw anything.
+pub struct SkyPassNode {
+    query: QueryState<(&'static ViewSky, &'static SkyViewBindGroup)>,
+}
+
+impl SkyPassNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_DEPTH: &'static str = "depth";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for SkyPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(SkyPassNode::IN_COLOR_ATTACHMENT, SlotType::TextureView),
+            SlotInfo::new(SkyPassNode::IN_DEPTH, SlotType::TextureView),
+            SlotInfo::new(SkyPassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view_sky, bind_group) = match self.query.get_manual(world, view_entity) {
+            Ok(view) => view,
+            Err(_) => return Ok(()),
+        };
+        let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(color_attachment_texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                attachment: TextureAttachment::Id(depth_texture),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: settings.stencil_ops(),
+            }),
+            sample_count: 1,
+        };
+
+        let sky_shaders = world.get_resource::<SkyShaders>().unwrap();
+        let layout = &sky_shaders.pipeline_descriptor.layout;
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                tracked_pass.set_pipeline(sky_shaders.pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_group(0).id,
+                    bind_group.bind_group,
+                    Some(&[view_sky.uniform.offset]),
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+#version 450
+
+// An analytic Rayleigh and Mie scattering sky, after "A Practical Analytic Model for
+// Daylight" by Preetham, Shirley and Smits.
+
+layout(location = 0) in vec3 v_Direction;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform Sky {
+    mat4 ViewRotation;
+    mat4 InverseProjection;
+    vec3 SunDirection;
+    float Turbidity;
+    float Rayleigh;
+    float MieCoefficient;
+    float MieDirectionalG;
+    // the multiplier of the exposure of the view
+    float Exposure;
+};
+
+const float PI = 3.141592653589793;
+const vec3 UP = vec3(0.0, 1.0, 0.0);
+
+// the Rayleigh scattering coefficient of air at sea level for the primary wavelengths
+const vec3 TOTAL_RAYLEIGH = vec3(5.804542996261093e-6, 1.3562911419845635e-5, 3.0265902468824876e-5);
+// 0.434 * (2 * PI / wavelength)^2 * K for the primary wavelengths
+const vec3 MIE_CONST = vec3(1.8399918514433978e14, 2.7798023919660528e14, 4.0790479543861094e14);
+// the optical length of the atmosphere at the zenith, in meters
+const float RAYLEIGH_ZENITH_LENGTH = 8.4e3;
+const float MIE_ZENITH_LENGTH = 1.25e3;
+// the sun fades out below the horizon, up to this zenith angle
+const float SUN_CUTOFF_ANGLE = 1.6110731556870734;
+const float SUN_STEEPNESS = 1.5;
+const float SUN_ILLUMINANCE = 1000.0;
+const float SUN_ANGULAR_DIAMETER_COS = 0.9999566769464484;
+
+#ifdef TONEMAP
+// the tonemapping of the pbr shaders
+float luminance(vec3 v) {
+    return dot(v, vec3(0.2126, 0.7152, 0.0722));
+}
+
+vec3 reinhard_luminance(vec3 color) {
+    float l_old = luminance(color);
+    float l_new = l_old / (1.0 + l_old);
+    return color * (l_new / max(l_old, 1e-8));
+}
+#endif
+
+float sun_intensity(float zenith_angle_cos) {
+    float zenith_angle = acos(clamp(zenith_angle_cos, -1.0, 1.0));
+    return SUN_ILLUMINANCE * max(0.0, 1.0 - exp(-((SUN_CUTOFF_ANGLE - zenith_angle) / SUN_STEEPNESS)));
+}
+
+float rayleigh_phase(float cos_theta) {
+    return 3.0 / (16.0 * PI) * (1.0 + pow(cos_theta * 0.5 + 0.5, 2.0));
+}
+
+// the Henyey-Greenstein phase function
+float mie_phase(float cos_theta, float g) {
+    float g2 = g * g;
+    return 1.0 / (4.0 * PI) * (1.0 - g2) / pow(1.0 - 2.0 * g * cos_theta + g2, 1.5);
+}
+
+void main() {
+    vec3 direction = normalize(v_Direction);
+    vec3 sun_direction = normalize(SunDirection);
+
+    float sun_e = sun_intensity(dot(sun_direction, UP));
+    float sun_fade = 1.0 - clamp(1.0 - exp(sun_direction.y), 0.0, 1.0);
+    vec3 beta_r = TOTAL_RAYLEIGH * (Rayleigh - (1.0 - sun_fade));
+    vec3 beta_m = 0.434 * (0.2 * Turbidity * 1e-17) * MIE_CONST * MieCoefficient;
+
+    // the optical length along the view ray, through an atmosphere of constant density
+    float zenith_angle = acos(max(0.0, dot(UP, direction)));
+    float inverse = 1.0 / (cos(zenith_angle) + 0.15 * pow(93.885 - zenith_angle * 180.0 / PI, -1.253));
+    float s_r = RAYLEIGH_ZENITH_LENGTH * inverse;
+    float s_m = MIE_ZENITH_LENGTH * inverse;
+    vec3 extinction = exp(-(beta_r * s_r + beta_m * s_m));
+
+    // the light scattered towards the camera
+    float cos_theta = dot(direction, sun_direction);
+    vec3 beta_theta = beta_r * rayleigh_phase(cos_theta) + beta_m * mie_phase(cos_theta, MieDirectionalG);
+    vec3 scattering = sun_e * beta_theta / (beta_r + beta_m);
+    vec3 in_scattering = pow(scattering * (1.0 - extinction), vec3(1.5));
+    in_scattering *= mix(
+        vec3(1.0),
+        pow(scattering * extinction, vec3(0.5)),
+        clamp(pow(1.0 - dot(UP, sun_direction), 5.0), 0.0, 1.0)
+    );
+
+    // the night sky and the sun disk
+    vec3 l0 = vec3(0.1) * extinction;
+    float sun_disk = smoothstep(SUN_ANGULAR_DIAMETER_COS, SUN_ANGULAR_DIAMETER_COS + 0.00002, cos_theta);
+    l0 += sun_e * 19000.0 * extinction * sun_disk;
+
+    vec3 color = (in_scattering + l0) * 0.04 + vec3(0.0, 0.0003, 0.00075);
+    color = pow(color, vec3(1.0 / (1.2 + 1.2 * sun_fade)));
+    // the model gives display values, the target expects linear ones
+    color = pow(color, vec3(2.2)) * Exposure;
+#ifdef TONEMAP
+    color = reinhard_luminance(color);
+#endif
+    o_Target = vec4(color, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) out vec3 v_Direction;
+
+layout(set = 0, binding = 0) uniform Sky {
+    mat4 ViewRotation;
+    mat4 InverseProjection;
+    vec3 SunDirection;
+    float Turbidity;
+    float Rayleigh;
+    float MieCoefficient;
+    float MieDirectionalG;
+};
+
+void main() {
+    // a single triangle that covers the whole target
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    vec2 ndc = uv * 2.0 - 1.0;
+    // every point of the view ray goes through the camera, so any depth gives its direction
+    vec4 view_position = InverseProjection * vec4(ndc, 0.5, 1.0);
+    v_Direction = mat3(ViewRotation) * (view_position.xyz / view_position.w);
+    // the sky is drawn at the far plane, behind everything else
+#ifdef REVERSE_Z
+    gl_Position = vec4(ndc, 0.0, 1.0);
+#else
+    gl_Position = vec4(ndc, 1.0, 1.0);
+#endif
+}
@@ -0,0 +1,375 @@
+use crate::{
+    motion_vectors::{self, add_motion_vector_pass, MotionVectors, ViewMotionVectors},
+    render::FULLSCREEN_VERTEX_SHADER,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_math::Vec2;
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    camera::ActiveCameras,
+    core_pipeline::{self, CorePipelineSettings, ViewMainTexture},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::TrackedRenderPass,
+    render_resource::{BindGroupBuilder, BindGroupId, SamplerId},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{ExtractedView, TemporalJitter},
+    RenderApp, RenderStage,
+};
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const TAA_RESOLVE: &'static str = "taa_resolve";
+    }
+}
+
+pub const TAA_HISTORY_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The number of different sub-pixel offsets the projection of a view cycles through.
+const JITTER_SEQUENCE_LENGTH: u32 = 8;
+
+/// Smooths the edges of cameras with a [`TemporalAntiAliasing`] component by jittering their
+/// projection by a fraction of a pixel every frame and blending each frame with the previous
+/// ones, reprojected with motion vectors. Must be added after [`crate::PbrPlugin`], and after
+/// [`crate::OitPlugin`] and [`crate::WireframePlugin`] if they are used, so their output is
+/// anti-aliased too.
+#[derive(Debug, Default)]
+pub struct TaaPlugin;
+
+impl Plugin for TaaPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TemporalAntiAliasing>();
+        add_motion_vector_pass(app);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_taa_cameras.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_taa_textures.system())
+            .add_system_to_stage(RenderStage::Queue, queue_taa.system())
+            .init_render_resource::<TaaShaders>();
+
+        let taa_resolve_node = TaaResolveNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::TAA_RESOLVE, taa_resolve_node);
+        draw_3d_graph
+            .add_node_edge(
+                motion_vectors::draw_3d_graph::node::MOTION_VECTOR_PASS,
+                draw_3d_graph::node::TAA_RESOLVE,
+            )
+            .unwrap();
+        for node in [
+            crate::oit::draw_3d_graph::node::OIT_PASS,
+            crate::wireframe::draw_3d_graph::node::WIREFRAME_PASS,
+        ]
+        .iter()
+        {
+            if draw_3d_graph.get_node_state(*node).is_ok() {
+                draw_3d_graph
+                    .add_node_edge(*node, draw_3d_graph::node::TAA_RESOLVE)
+                    .unwrap();
+            }
+        }
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::TAA_RESOLVE,
+                TaaResolveNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::OUTPUT_TARGET,
+                draw_3d_graph::node::TAA_RESOLVE,
+                TaaResolveNode::IN_OUTPUT_TARGET,
+            )
+            .unwrap();
+    }
+}
+
+/// Enables temporal anti-aliasing for a 3d camera, see [`TaaPlugin`].
+#[derive(Debug, Clone, Reflect, Default)]
+#[reflect(Component)]
+pub struct TemporalAntiAliasing;
+
+pub struct TaaShaders {
+    resolve_pipeline: PipelineId,
+    resolve_pipeline_descriptor: RenderPipelineDescriptor,
+    sampler: SamplerId,
+}
+
+impl FromWorld for TaaShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("taa_resolve.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let resolve_pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![
+                ColorTargetState {
+                    format: settings.main_texture_format(),
+                    blend: None,
+                    write_mask: ColorWrite::ALL,
+                },
+                ColorTargetState {
+                    format: TAA_HISTORY_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrite::ALL,
+                },
+            ],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let resolve_pipeline =
+            render_resources.create_render_pipeline(&resolve_pipeline_descriptor);
+
+        TaaShaders {
+            resolve_pipeline,
+            resolve_pipeline_descriptor,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// Returns the element at `index` of the Halton sequence with the given `base`, a low
+/// discrepancy sequence in `[0, 1)`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+pub fn extract_taa_cameras(
+    mut commands: Commands,
+    mut frame: Local<u32>,
+    active_cameras: Res<ActiveCameras>,
+    query: Query<&TemporalAntiAliasing>,
+) {
+    // the sequence starts at 1, the first element of the Halton sequence is always 0
+    let index = *frame % JITTER_SEQUENCE_LENGTH + 1;
+    *frame = frame.wrapping_add(1);
+    let jitter = TemporalJitter {
+        offset: Vec2::new(halton(index, 2), halton(index, 3)) - Vec2::splat(0.5),
+    };
+    for camera in active_cameras.iter() {
+        if let Some(entity) = camera.entity {
+            if let Ok(taa) = query.get(entity) {
+                commands
+                    .get_or_spawn(entity)
+                    .insert_bundle((taa.clone(), jitter, MotionVectors));
+            }
+        }
+    }
+}
+
+/// The history textures of a view with [`TemporalAntiAliasing`].
+pub struct ViewTaaTextures {
+    pub history: CachedHistoryTextures,
+}
+
+pub fn prepare_taa_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    views: Query<(Entity, &ExtractedView), With<TemporalAntiAliasing>>,
+) {
+    for (entity, view) in views.iter() {
+        let history = texture_cache.get_history(
+            &render_resources,
+            entity,
+            "taa",
+            TextureDescriptor {
+                size: Extent3d {
+                    depth_or_array_layers: 1,
+                    width: view.width,
+                    height: view.height,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TAA_HISTORY_FORMAT,
+                usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+            },
+        );
+        commands.entity(entity).insert(ViewTaaTextures { history });
+    }
+}
+
+struct TaaViewBindGroups {
+    resolve_bind_group: BindGroupId,
+}
+
+pub fn queue_taa(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    taa_shaders: Res<TaaShaders>,
+    views: Query<(
+        Entity,
+        &ViewMainTexture,
+        &ViewMotionVectors,
+        &ViewTaaTextures,
+    )>,
+) {
+    for (view_entity, main_texture, motion_vectors, taa_textures) in views.iter() {
+        let resolve_bind_group = BindGroupBuilder::default()
+            .add_binding(0, main_texture.view)
+            .add_binding(1, taa_textures.history.previous.default_view)
+            .add_binding(2, motion_vectors.texture)
+            .add_binding(3, taa_shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(
+            taa_shaders
+                .resolve_pipeline_descriptor
+                .layout
+                .bind_group(0)
+                .id,
+            &resolve_bind_group,
+        );
+
+        commands.entity(view_entity).insert(TaaViewBindGroups {
+            resolve_bind_group: resolve_bind_group.id,
+        });
+    }
+}
+
+/// Blends the drawn image of a view with its history, and writes the result to the output
+/// target and the history of the next frame.
+pub struct TaaResolveNode {
+    query: QueryState<(&'static ViewTaaTextures, &'static TaaViewBindGroups)>,
+}
+
+impl TaaResolveNode {
+    pub const IN_OUTPUT_TARGET: &'static str = "output_target";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for TaaResolveNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(TaaResolveNode::IN_OUTPUT_TARGET, SlotType::TextureView),
+            SlotInfo::new(TaaResolveNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (taa_textures, taa_bind_groups) = match self.query.get_manual(world, view_entity) {
+            Ok(view) => view,
+            Err(_) => return Ok(()),
+        };
+
+        let output_target = graph.get_input_texture(Self::IN_OUTPUT_TARGET)?;
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(output_target),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: true,
+                    },
+                },
+                RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(taa_textures.history.current.default_view),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: true,
+                    },
+                },
+            ],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+
+        let taa_shaders = world.get_resource::<TaaShaders>().unwrap();
+        let layout = &taa_shaders.resolve_pipeline_descriptor.layout;
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                tracked_pass.set_pipeline(taa_shaders.resolve_pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_group(0).id,
+                    taa_bind_groups.resolve_bind_group,
+                    None,
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
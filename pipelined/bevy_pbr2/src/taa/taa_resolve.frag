@@ -0,0 +1,47 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+layout(location = 1) out vec4 o_History;
+
+layout(set = 0, binding = 0) uniform texture2D t_Color;
+layout(set = 0, binding = 1) uniform texture2D t_History;
+layout(set = 0, binding = 2) uniform texture2D t_MotionVectors;
+layout(set = 0, binding = 3) uniform sampler s_Linear;
+
+// the weight of the history, higher values give smoother but blurrier results
+const float HISTORY_WEIGHT = 0.9;
+
+void main() {
+    ivec2 size = textureSize(sampler2D(t_Color, s_Linear), 0);
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec3 color = texelFetch(sampler2D(t_Color, s_Linear), coords, 0).rgb;
+
+    // the history is clamped to the colors around the current sample, which rejects history
+    // that has been disoccluded or has changed
+    vec3 neighborhood_min = color;
+    vec3 neighborhood_max = color;
+    for (int x = -1; x <= 1; ++x) {
+        for (int y = -1; y <= 1; ++y) {
+            ivec2 neighbor_coords = clamp(coords + ivec2(x, y), ivec2(0), size - 1);
+            vec3 neighbor = texelFetch(sampler2D(t_Color, s_Linear), neighbor_coords, 0).rgb;
+            neighborhood_min = min(neighborhood_min, neighbor);
+            neighborhood_max = max(neighborhood_max, neighbor);
+        }
+    }
+
+    vec2 motion_vector = texelFetch(sampler2D(t_MotionVectors, s_Linear), coords, 0).xy;
+    vec2 history_uv = gl_FragCoord.xy / vec2(size) - motion_vector;
+    vec4 history = texture(sampler2D(t_History, s_Linear), history_uv);
+
+    vec3 result = color;
+    // new history textures are zeroed, so their alpha marks them as invalid
+    bool history_on_screen = all(greaterThanEqual(history_uv, vec2(0.0)))
+        && all(lessThanEqual(history_uv, vec2(1.0)));
+    if (history.a > 0.0 && history_on_screen) {
+        vec3 history_color = clamp(history.rgb, neighborhood_min, neighborhood_max);
+        result = mix(color, history_color, HISTORY_WEIGHT);
+    }
+
+    o_Target = vec4(result, 1.0);
+    o_History = vec4(result, 1.0);
+}
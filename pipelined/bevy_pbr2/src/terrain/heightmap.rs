@@ -0,0 +1,201 @@
+use super::Terrain;
+use bevy_math::{Vec2, Vec3};
+use bevy_render2::{
+    mesh::{Indices, Mesh},
+    pipeline::PrimitiveTopology,
+    texture::{Texture, TextureFormat},
+    view::Aabb,
+};
+
+/// The edges of a chunk, used to mark the edges that are stitched to a coarser neighbor.
+pub(crate) const EDGE_NEG_X: u8 = 1;
+pub(crate) const EDGE_POS_X: u8 = 1 << 1;
+pub(crate) const EDGE_NEG_Z: u8 = 1 << 2;
+pub(crate) const EDGE_POS_Z: u8 = 1 << 3;
+
+/// The heights of a [`Terrain`] heightmap texture, normalized to `[0, 1]`.
+pub(crate) struct Heightmap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Reads the first channel of `texture`. 16 bit grayscale images keep their precision, other
+    /// formats are converted to 8 bits. Returns `None` if the format can't be converted.
+    pub(crate) fn from_texture(texture: &Texture) -> Option<Self> {
+        let values = match texture.format {
+            TextureFormat::R8Unorm => texture
+                .data
+                .iter()
+                .map(|value| *value as f32 / u8::MAX as f32)
+                .collect(),
+            TextureFormat::R16Uint => texture
+                .data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32)
+                .collect(),
+            TextureFormat::R32Float => texture
+                .data
+                .chunks_exact(4)
+                .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+            _ => return Self::from_texture(&texture.convert(TextureFormat::R8Unorm)?),
+        };
+        Some(Heightmap {
+            width: texture.size.width,
+            height: texture.size.height,
+            values,
+        })
+    }
+
+    fn get(&self, x: u32, y: u32) -> f32 {
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        self.values[(y * self.width + x) as usize]
+    }
+
+    /// Returns the bilinearly filtered height at `uv`, which is clamped to `[0, 1]`.
+    pub(crate) fn sample(&self, uv: Vec2) -> f32 {
+        let position = uv.clamp(Vec2::ZERO, Vec2::ONE)
+            * Vec2::new((self.width - 1) as f32, (self.height - 1) as f32);
+        let (x, y) = (position.x as u32, position.y as u32);
+        let fraction = position - Vec2::new(x as f32, y as f32);
+        let top = self.get(x, y) * (1.0 - fraction.x) + self.get(x + 1, y) * fraction.x;
+        let bottom = self.get(x, y + 1) * (1.0 - fraction.x) + self.get(x + 1, y + 1) * fraction.x;
+        top * (1.0 - fraction.y) + bottom * fraction.y
+    }
+
+    /// Returns the size of a texel in uv units.
+    fn texel_size(&self) -> Vec2 {
+        Vec2::new(1.0 / self.width as f32, 1.0 / self.height as f32)
+    }
+}
+
+/// The vertices of a chunk at a level of detail, in the local space of the chunk.
+struct ChunkGrid {
+    /// The number of quads along each side.
+    resolution: u32,
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+}
+
+impl ChunkGrid {
+    fn new(terrain: &Terrain, heightmap: &Heightmap, coords: (u32, u32), lod: u32) -> Self {
+        let resolution = (terrain.chunk_resolution >> lod).max(1);
+        let chunk_size = terrain.chunk_size();
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        for z in 0..=resolution {
+            for x in 0..=resolution {
+                let local = Vec2::new(x as f32, z as f32) / resolution as f32;
+                let uv = (Vec2::new(coords.0 as f32, coords.1 as f32) + local)
+                    / Vec2::new(terrain.chunks.x as f32, terrain.chunks.y as f32);
+                let y = heightmap.sample(uv) * terrain.height;
+                positions.push(Vec3::new(local.x * chunk_size.x, y, local.y * chunk_size.y));
+                uvs.push(uv);
+            }
+        }
+        ChunkGrid {
+            resolution,
+            positions,
+            uvs,
+        }
+    }
+
+    fn index(&self, x: u32, z: u32) -> usize {
+        (z * (self.resolution + 1) + x) as usize
+    }
+}
+
+/// Returns the bounds of the chunk at `coords`, which contain all of its levels of detail.
+pub(crate) fn chunk_aabb(terrain: &Terrain, heightmap: &Heightmap, coords: (u32, u32)) -> Aabb {
+    // coarser levels only have a subset of the vertices of the first one, or heights averaged
+    // between them
+    let grid = ChunkGrid::new(terrain, heightmap, coords, 0);
+    let (min, max) = grid.positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), position| (min.min(*position), max.max(*position)),
+    );
+    Aabb::from_min_max(min, max)
+}
+
+/// Creates the mesh of the chunk at `coords`, with `chunk_resolution >> lod` quads along each
+/// side. The odd vertices of the `stitched` edges are moved onto the edges of the neighboring
+/// chunks, which have half the resolution, so there are no cracks between them.
+pub(crate) fn chunk_mesh(
+    terrain: &Terrain,
+    heightmap: &Heightmap,
+    coords: (u32, u32),
+    lod: u32,
+    stitched: u8,
+) -> Mesh {
+    let mut grid = ChunkGrid::new(terrain, heightmap, coords, lod);
+    let resolution = grid.resolution;
+    let stitch = |grid: &mut ChunkGrid, vertex: (u32, u32), previous: (u32, u32)| {
+        let next = (2 * vertex.0 - previous.0, 2 * vertex.1 - previous.1);
+        let height = (grid.positions[grid.index(previous.0, previous.1)].y
+            + grid.positions[grid.index(next.0, next.1)].y)
+            * 0.5;
+        let index = grid.index(vertex.0, vertex.1);
+        grid.positions[index].y = height;
+    };
+    for i in (1..resolution).step_by(2) {
+        if stitched & EDGE_NEG_X != 0 {
+            stitch(&mut grid, (0, i), (0, i - 1));
+        }
+        if stitched & EDGE_POS_X != 0 {
+            stitch(&mut grid, (resolution, i), (resolution, i - 1));
+        }
+        if stitched & EDGE_NEG_Z != 0 {
+            stitch(&mut grid, (i, 0), (i - 1, 0));
+        }
+        if stitched & EDGE_POS_Z != 0 {
+            stitch(&mut grid, (i, resolution), (i - 1, resolution));
+        }
+    }
+
+    // the normals come from the heightmap, so they match between levels of detail
+    let texel_size = heightmap.texel_size();
+    let world_texel_size = texel_size * terrain.size;
+    let normals = grid
+        .uvs
+        .iter()
+        .map(|uv| {
+            let height = |offset: Vec2| heightmap.sample(*uv + offset) * terrain.height;
+            let dx = (height(Vec2::new(texel_size.x, 0.0)) - height(Vec2::new(-texel_size.x, 0.0)))
+                / (2.0 * world_texel_size.x);
+            let dz = (height(Vec2::new(0.0, texel_size.y)) - height(Vec2::new(0.0, -texel_size.y)))
+                / (2.0 * world_texel_size.y);
+            Vec3::new(-dx, 1.0, -dz).normalize().into()
+        })
+        .collect::<Vec<[f32; 3]>>();
+
+    let mut indices = Vec::new();
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let a = grid.index(x, z) as u32;
+            let b = grid.index(x + 1, z) as u32;
+            let c = grid.index(x, z + 1) as u32;
+            let d = grid.index(x + 1, z + 1) as u32;
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    let positions = grid
+        .positions
+        .iter()
+        .map(|position| (*position).into())
+        .collect::<Vec<[f32; 3]>>();
+    let uvs = grid
+        .uvs
+        .iter()
+        .map(|uv| (*uv).into())
+        .collect::<Vec<[f32; 2]>>();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
@@ -0,0 +1,287 @@
+mod heightmap;
+
+use crate::{SplatMaterial, StandardMaterial};
+use bevy_app::prelude::*;
+use bevy_asset::{AssetEvent, Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_math::{UVec2, Vec2, Vec3};
+use bevy_render2::{
+    camera::{ActiveCameras, CameraPlugin},
+    mesh::Mesh,
+    texture::Texture,
+};
+use bevy_transform::prelude::*;
+use bevy_utils::{HashMap, HashSet};
+use heightmap::{
+    chunk_aabb, chunk_mesh, Heightmap, EDGE_NEG_X, EDGE_NEG_Z, EDGE_POS_X, EDGE_POS_Z,
+};
+
+/// Generates the chunk meshes of [`Terrain`] entities from their heightmap, and picks the level
+/// of detail of each chunk from its distance to the 3d camera.
+#[derive(Debug, Default)]
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Update, build_terrains.system())
+            .add_system_to_stage(CoreStage::PostUpdate, update_terrain_lods.system());
+    }
+}
+
+/// A terrain centered on its entity, with heights read from the first channel of a heightmap
+/// texture. The terrain is split into chunks, which are spawned as children of the entity with
+/// the [`Handle<StandardMaterial>`] and [`Handle<SplatMaterial>`] of the terrain, and are culled
+/// with their [`Aabb`](bevy_render2::view::Aabb).
+///
+/// Chunks have `chunk_resolution` quads along each side up to `lod_distance` from the camera,
+/// and the resolution halves every time the distance doubles. Neighboring chunks differ by at
+/// most one level, and the edges of the finer chunk are stitched to the coarser one.
+#[derive(Debug, Clone)]
+pub struct Terrain {
+    pub heightmap: Handle<Texture>,
+    /// The size of the terrain along the x and z axes.
+    pub size: Vec2,
+    /// The height of the terrain where the heightmap is at its maximum.
+    pub height: f32,
+    /// The number of chunks along the x and z axes.
+    pub chunks: UVec2,
+    /// The number of quads along each side of a chunk at the highest level of detail. Must be a
+    /// power of two.
+    pub chunk_resolution: u32,
+    pub lod_distance: f32,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Terrain {
+            heightmap: Default::default(),
+            size: Vec2::new(100.0, 100.0),
+            height: 10.0,
+            chunks: UVec2::new(8, 8),
+            chunk_resolution: 32,
+            lod_distance: 25.0,
+        }
+    }
+}
+
+impl Terrain {
+    fn chunk_size(&self) -> Vec2 {
+        self.size / Vec2::new(self.chunks.x as f32, self.chunks.y as f32)
+    }
+
+    fn lod(&self, distance: f32) -> u32 {
+        if distance < self.lod_distance {
+            0
+        } else {
+            (distance / self.lod_distance).log2() as u32 + 1
+        }
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct TerrainBundle {
+    pub terrain: Terrain,
+    pub material: Handle<StandardMaterial>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// A chunk of a [`Terrain`], and the level of detail its mesh was generated with.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainChunk {
+    pub coords: (u32, u32),
+    pub lod: u32,
+    /// The edges stitched to a coarser neighbor, as a bit mask of `-x`, `+x`, `-z` and `+z`.
+    pub stitched: u8,
+}
+
+struct TerrainChunks {
+    heightmap: Heightmap,
+    /// The chunk entities, row by row along the x axis.
+    entities: Vec<Entity>,
+    /// The center of each chunk, in the local space of the terrain.
+    centers: Vec<Vec3>,
+    meshes: HashMap<((u32, u32), u32, u8), Handle<Mesh>>,
+}
+
+fn build_terrains(
+    mut commands: Commands,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    textures: Res<Assets<Texture>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    terrains: Query<(
+        Entity,
+        &Terrain,
+        ChangeTrackers<Terrain>,
+        &Handle<StandardMaterial>,
+        Option<&Handle<SplatMaterial>>,
+        Option<&TerrainChunks>,
+    )>,
+) {
+    let mut changed_textures = HashSet::default();
+    for event in texture_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed_textures.insert(handle.clone_weak());
+            }
+            AssetEvent::Removed { .. } => {}
+        }
+    }
+
+    for (entity, terrain, terrain_tracker, material, splat_material, chunks) in terrains.iter() {
+        if chunks.is_some()
+            && !terrain_tracker.is_changed()
+            && !changed_textures.contains(&terrain.heightmap)
+        {
+            continue;
+        }
+        let heightmap = match textures
+            .get(&terrain.heightmap)
+            .and_then(Heightmap::from_texture)
+        {
+            Some(heightmap) => heightmap,
+            None => continue,
+        };
+        assert!(
+            terrain.chunk_resolution.is_power_of_two(),
+            "the chunk resolution of a terrain must be a power of two"
+        );
+
+        if let Some(chunks) = chunks {
+            for chunk in chunks.entities.iter() {
+                commands.entity(*chunk).despawn_recursive();
+            }
+        }
+        let chunk_size = terrain.chunk_size();
+        let origin = -terrain.size * 0.5;
+        let mut entities = Vec::new();
+        let mut centers = Vec::new();
+        let mut chunk_meshes = HashMap::default();
+        for z in 0..terrain.chunks.y {
+            for x in 0..terrain.chunks.x {
+                let coords = (x, z);
+                let aabb = chunk_aabb(terrain, &heightmap, coords);
+                let mesh = meshes.add(chunk_mesh(terrain, &heightmap, coords, 0, 0));
+                chunk_meshes.insert((coords, 0, 0), mesh.clone());
+                let offset = origin + Vec2::new(x as f32, z as f32) * chunk_size;
+                let translation = Vec3::new(offset.x, 0.0, offset.y);
+                let mut chunk = commands.spawn_bundle((
+                    TerrainChunk {
+                        coords,
+                        lod: 0,
+                        stitched: 0,
+                    },
+                    mesh,
+                    material.clone(),
+                    aabb,
+                    Transform::from_translation(translation),
+                    GlobalTransform::default(),
+                ));
+                if let Some(splat_material) = splat_material {
+                    chunk.insert(splat_material.clone());
+                }
+                entities.push(chunk.id());
+                centers.push(translation + aabb.center);
+            }
+        }
+        commands
+            .entity(entity)
+            .push_children(&entities)
+            .insert(TerrainChunks {
+                heightmap,
+                entities,
+                centers,
+                meshes: chunk_meshes,
+            });
+    }
+}
+
+fn update_terrain_lods(
+    active_cameras: Res<ActiveCameras>,
+    cameras: Query<&GlobalTransform>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut terrains: Query<(&Terrain, &GlobalTransform, &mut TerrainChunks)>,
+    mut chunks: Query<(&mut TerrainChunk, &mut Handle<Mesh>)>,
+) {
+    let camera_position = match active_cameras
+        .get(CameraPlugin::CAMERA_3D)
+        .and_then(|camera| camera.entity)
+        .and_then(|entity| cameras.get(entity).ok())
+    {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+
+    for (terrain, transform, mut terrain_chunks) in terrains.iter_mut() {
+        let terrain_chunks = &mut *terrain_chunks;
+        let max_lod = terrain.chunk_resolution.trailing_zeros();
+        let (width, depth) = (terrain.chunks.x as usize, terrain.chunks.y as usize);
+        let mut lods = terrain_chunks
+            .centers
+            .iter()
+            .map(|center| {
+                let distance = transform.mul_vec3(*center).distance(camera_position);
+                terrain.lod(distance).min(max_lod)
+            })
+            .collect::<Vec<_>>();
+
+        let neighbors = |index: usize| {
+            let (x, z) = (index % width, index / width);
+            [
+                (EDGE_NEG_X, x.checked_sub(1).map(|x| z * width + x)),
+                (
+                    EDGE_POS_X,
+                    Some(x + 1).filter(|x| *x < width).map(|x| z * width + x),
+                ),
+                (EDGE_NEG_Z, z.checked_sub(1).map(|z| z * width + x)),
+                (
+                    EDGE_POS_Z,
+                    Some(z + 1).filter(|z| *z < depth).map(|z| z * width + x),
+                ),
+            ]
+        };
+        // refine chunks until neighbors are at most one level apart, so edges can be stitched
+        let mut relaxed = false;
+        while !relaxed {
+            relaxed = true;
+            for index in 0..lods.len() {
+                for neighbor in neighbors(index)
+                    .iter()
+                    .filter_map(|(_, neighbor)| *neighbor)
+                {
+                    if lods[index] > lods[neighbor] + 1 {
+                        lods[index] = lods[neighbor] + 1;
+                        relaxed = false;
+                    }
+                }
+            }
+        }
+
+        for (index, entity) in terrain_chunks.entities.iter().enumerate() {
+            let lod = lods[index];
+            let stitched = neighbors(index)
+                .iter()
+                .filter_map(|(edge, neighbor)| neighbor.map(|neighbor| (edge, neighbor)))
+                .filter(|(_, neighbor)| lods[*neighbor] > lod)
+                .fold(0, |stitched, (edge, _)| stitched | edge);
+            let (mut chunk, mut mesh) = match chunks.get_mut(*entity) {
+                Ok(chunk) => chunk,
+                Err(_) => continue,
+            };
+            if chunk.lod == lod && chunk.stitched == stitched {
+                continue;
+            }
+            let heightmap = &terrain_chunks.heightmap;
+            let coords = chunk.coords;
+            *mesh = terrain_chunks
+                .meshes
+                .entry((coords, lod, stitched))
+                .or_insert_with(|| {
+                    meshes.add(chunk_mesh(terrain, heightmap, coords, lod, stitched))
+                })
+                .clone();
+            chunk.lod = lod;
+            chunk.stitched = stitched;
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use crate::{
+    render::MeshPipelines, ExtractedMeshes, MeshMeta, MeshVertexBuffer, MeshVertexLayout,
+    ViewMeshLods,
+};
+use bevy_app::prelude::*;
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    core_pipeline::{self, CorePipelineSettings, DepthMode, Transparent3dPhase},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{AddRenderPhase, Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{BindGroupBuilder, BindGroupId},
+    renderer::{RenderContext, RenderFeatures, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    view::{ExtractedView, ViewMeta, ViewUniform},
+    RenderApp, RenderStage,
+};
+use bevy_utils::tracing::warn;
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const WIREFRAME_PASS: &'static str = "wireframe_pass";
+    }
+}
+
+/// Draws the edges of [`Wireframe`] meshes (or all meshes, see [`WireframeConfig::global`]) on
+/// top of the 3d scene.
+///
+/// This uses [`PolygonMode::Line`], which requires the `NonFillPolygonMode` wgpu feature to be
+/// enabled in `WgpuOptions`. Without it, the plugin warns and draws no wireframes. Must be added
+/// after [`crate::PbrPlugin`].
+#[derive(Debug, Default)]
+pub struct WireframePlugin;
+
+impl Plugin for WireframePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Wireframe>()
+            .init_resource::<WireframeConfig>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        // the pass is added either way, so that passes added after it can be ordered around it,
+        // it draws nothing while no wireframes are queued
+        render_app
+            .add_render_phase::<WireframePhase>()
+            .add_render_init(|render_app| {
+                let features = render_app
+                    .world
+                    .get_resource::<RenderResources>()
+                    .unwrap()
+                    .features();
+                if !features.contains(RenderFeatures::NON_FILL_POLYGON_MODE) {
+                    warn!(
+                        "wireframes need the NonFillPolygonMode feature, which the render \
+                        backend doesn't support or which wasn't enabled in WgpuOptions, no \
+                        wireframes are drawn"
+                    );
+                    return;
+                }
+                render_app
+                    .add_system_to_stage(RenderStage::Queue, queue_wireframes.system())
+                    .init_resource::<WireframeShaders>();
+            });
+
+        let draw_wireframe = DrawWireframe::new(&mut render_app.world);
+        let wireframe_pass_node = WireframePassNode::new(&mut render_app.world);
+        let render_world = render_app.world.cell();
+        let draw_functions = render_world.get_resource::<DrawFunctions>().unwrap();
+        draw_functions.write().add(draw_wireframe);
+        let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::WIREFRAME_PASS, wireframe_pass_node);
+        draw_3d_graph
+            .add_node_edge(
+                core_pipeline::draw_3d_graph::node::MAIN_PASS,
+                draw_3d_graph::node::WIREFRAME_PASS,
+            )
+            .unwrap();
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::WIREFRAME_PASS,
+                WireframePassNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::RENDER_TARGET,
+                draw_3d_graph::node::WIREFRAME_PASS,
+                WireframePassNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::DEPTH,
+                draw_3d_graph::node::WIREFRAME_PASS,
+                WireframePassNode::IN_DEPTH,
+            )
+            .unwrap();
+    }
+}
+
+/// Marks a mesh entity to be drawn as a wireframe.
+#[derive(Debug, Clone, Reflect, Default)]
+#[reflect(Component)]
+pub struct Wireframe;
+
+#[derive(Debug, Clone, Default)]
+pub struct WireframeConfig {
+    /// Draws every mesh as a wireframe, whether or not it has a [`Wireframe`] component.
+    pub global: bool,
+}
+
+pub struct WireframePhase;
+
+pub struct WireframeShaders {
+    /// The pipelines for each [`MeshVertexBuffer`].
+    pipelines: MeshPipelines,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for WireframeShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("wireframe.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("wireframe.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        pipeline_layout.vertex_buffer_descriptors =
+            vec![MeshVertexBuffer::standard(MeshVertexLayout::default()).layout()];
+
+        pipeline_layout.bind_group_mut(0).bindings[0].set_dynamic(true);
+        pipeline_layout.bind_group_mut(1).bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let mut pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Line,
+                clamp_depth: false,
+                conservative: false,
+            },
+            ..RenderPipelineDescriptor::default_config(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        pipeline_descriptor.color_target_states[0].format = settings.main_texture_format();
+        // the main pass has already written the depth of these meshes
+        if let Some(depth_stencil) = pipeline_descriptor.depth_stencil.as_mut() {
+            depth_stencil.format = settings.depth_format;
+            depth_stencil.depth_write_enabled = false;
+            depth_stencil.depth_compare = depth_mode.compare(CompareFunction::LessEqual);
+        }
+
+        let pipelines = MeshPipelines::new(pipeline_descriptor.clone());
+
+        WireframeShaders {
+            pipelines,
+            pipeline_descriptor,
+        }
+    }
+}
+
+// TODO: This is temporary. Once we expose BindGroupLayouts directly, we can create view bind groups without specific shader context
+struct WireframeViewBindGroups {
+    view_bind_group: BindGroupId,
+    /// One bind group per chunk of [`MeshMeta::transform_uniforms`].
+    mesh_transform_bind_groups: Vec<BindGroupId>,
+}
+
+pub fn queue_wireframes(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut wireframe_shaders: ResMut<WireframeShaders>,
+    mesh_meta: Res<MeshMeta>,
+    view_meta: Res<ViewMeta>,
+    extracted_meshes: Res<ExtractedMeshes>,
+    views: Query<(Entity, &ViewUniform), With<RenderPhase<Transparent3dPhase>>>,
+) {
+    if !extracted_meshes
+        .meshes
+        .iter()
+        .any(|extracted_mesh| extracted_mesh.wireframe)
+    {
+        return;
+    }
+
+    wireframe_shaders.pipelines.specialize_meshes(
+        &render_resources,
+        extracted_meshes
+            .meshes
+            .iter()
+            .filter(|extracted_mesh| extracted_mesh.wireframe),
+    );
+    let layout = &wireframe_shaders.pipeline_descriptor.layout;
+    let mesh_transform_bind_groups = (0..mesh_meta.transform_uniforms.chunk_count())
+        .map(|chunk| {
+            let mesh_transform_bind_group = BindGroupBuilder::default()
+                .add_binding(0, mesh_meta.transform_uniforms.binding(chunk))
+                .finish();
+            render_resources.create_bind_group(layout.bind_group(1).id, &mesh_transform_bind_group);
+            mesh_transform_bind_group.id
+        })
+        .collect::<Vec<_>>();
+
+    let draw_wireframe = draw_functions.read().get_id::<DrawWireframe>().unwrap();
+    for (view_entity, view_uniform) in views.iter() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(layout.bind_group(0).id, &view_bind_group);
+
+        let mut wireframe_phase = RenderPhase::<WireframePhase>::default();
+        for (i, extracted_mesh) in extracted_meshes.meshes.iter().enumerate() {
+            if extracted_mesh.wireframe {
+                wireframe_phase.add(Drawable {
+                    draw_function: draw_wireframe,
+                    draw_key: i,
+                    sort_key: 0,
+                    entity: extracted_mesh.entity,
+                    clip: None,
+                });
+            }
+        }
+
+        commands.entity(view_entity).insert_bundle((
+            wireframe_phase,
+            WireframeViewBindGroups {
+                view_bind_group: view_bind_group.id,
+                mesh_transform_bind_groups: mesh_transform_bind_groups.clone(),
+            },
+        ));
+    }
+}
+
+pub struct WireframePassNode {
+    query: QueryState<&'static RenderPhase<WireframePhase>, With<ExtractedView>>,
+}
+
+impl WireframePassNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_DEPTH: &'static str = "depth";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for WireframePassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(
+                WireframePassNode::IN_COLOR_ATTACHMENT,
+                SlotType::TextureView,
+            ),
+            SlotInfo::new(WireframePassNode::IN_DEPTH, SlotType::TextureView),
+            SlotInfo::new(WireframePassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        // views only have a wireframe phase when there are wireframes to draw
+        let wireframe_phase = match self.query.get_manual(world, view_entity) {
+            Ok(wireframe_phase) => wireframe_phase,
+            Err(_) => return Ok(()),
+        };
+
+        let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(color_attachment_texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                attachment: TextureAttachment::Id(depth_texture),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: settings.stencil_ops(),
+            }),
+            sample_count: 1,
+        };
+
+        let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut draw_functions = draw_functions.write();
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                for drawable in wireframe_phase.drawn_things.iter() {
+                    let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
+                    draw_function.draw(
+                        world,
+                        &mut tracked_pass,
+                        view_entity,
+                        drawable.draw_key,
+                        drawable.sort_key,
+                    );
+                }
+            },
+        );
+        Ok(())
+    }
+}
+
+type DrawWireframeParams<'a> = (
+    Res<'a, WireframeShaders>,
+    Res<'a, ExtractedMeshes>,
+    Query<
+        'a,
+        (
+            &'a ViewUniform,
+            &'a WireframeViewBindGroups,
+            Option<&'a ViewMeshLods>,
+        ),
+    >,
+);
+pub struct DrawWireframe {
+    params: SystemState<DrawWireframeParams<'static>>,
+}
+
+impl DrawWireframe {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawWireframe {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (wireframe_shaders, extracted_meshes, views) = self.params.get(world);
+        let (view_uniforms, wireframe_view_bind_groups, view_mesh_lods) = views.get(view).unwrap();
+        let layout = &wireframe_shaders.pipeline_descriptor.layout;
+        let (extracted_mesh, mesh_level) = extracted_meshes.get(draw_key, view_mesh_lods);
+        // the pipelines are only created for triangle lists
+        if !mesh_level.key.is_triangle_list() {
+            return;
+        }
+        pass.set_pipeline(
+            wireframe_shaders
+                .pipelines
+                .get(mesh_level.key.vertex_buffer),
+        );
+        pass.set_bind_group(
+            0,
+            layout.bind_group(0).id,
+            wireframe_view_bind_groups.view_bind_group,
+            Some(&[view_uniforms.view_uniform_offset]),
+        );
+        let mesh_transform_bind_group = wireframe_view_bind_groups.mesh_transform_bind_groups
+            [extracted_mesh.transform_binding.chunk];
+        pass.set_bind_group(
+            1,
+            layout.bind_group(1).id,
+            mesh_transform_bind_group,
+            Some(&[extracted_mesh.transform_binding.offset]),
+        );
+        mesh_level.draw(pass);
+    }
+}
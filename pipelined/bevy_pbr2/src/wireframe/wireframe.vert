@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+
+layout(set = 0, binding = 0) uniform View {
+    mat4 ViewProj;
+    vec3 ViewWorldPosition;
+};
+
+layout(set = 1, binding = 0) uniform MeshTransform {
+    mat4 Model;
+};
+
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+}
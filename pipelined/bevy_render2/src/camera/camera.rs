@@ -1,12 +1,17 @@
-use crate::camera::CameraProjection;
+use crate::{
+    camera::{CameraProjection, ScalingPolicy},
+    core_pipeline::DepthMode,
+    render_phase::ClipRect,
+    view::OffscreenTargets,
+};
 use bevy_ecs::{
     component::Component,
     entity::Entity,
     event::EventReader,
     prelude::DetectChanges,
-    query::Added,
+    query::{Added, Changed},
     reflect::ReflectComponent,
-    system::{Query, QuerySet, Res},
+    system::{Query, QuerySet, RemovedComponents, Res},
 };
 use bevy_math::{Mat4, Vec2, Vec3};
 use bevy_reflect::{Reflect, ReflectDeserialize};
@@ -21,8 +26,17 @@ pub struct Camera {
     pub name: Option<String>,
     #[reflect(ignore)]
     pub window: WindowId,
-    #[reflect(ignore)]
     pub depth_calculation: DepthCalculation,
+    /// The rectangle of the target the camera draws to, set from its [`ScalingPolicy`]. The
+    /// camera draws to the whole target if this is `None`.
+    #[reflect(ignore)]
+    pub viewport: Option<ClipRect>,
+    /// The order the camera is drawn in by the
+    /// [`CameraDriverNode`](crate::core_pipeline::CameraDriverNode): cameras with a higher
+    /// priority are drawn after, and on top of, the cameras with a lower one. Only the first
+    /// camera drawing into a window clears it, so the cameras after it can draw e.g. a HUD or a
+    /// minimap over the scene.
+    pub priority: isize,
 }
 
 #[derive(Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
@@ -49,7 +63,20 @@ impl Camera {
         world_position: Vec3,
     ) -> Option<Vec2> {
         let window = windows.get(self.window)?;
-        let window_size = Vec2::new(window.width(), window.height());
+        let scale_factor = window.scale_factor() as f32;
+        // the viewport in logical pixels, from the bottom left
+        let (viewport_origin, viewport_size) = match self.viewport {
+            Some(viewport) => (
+                Vec2::new(
+                    viewport.x as f32,
+                    window
+                        .physical_height()
+                        .saturating_sub(viewport.y + viewport.height) as f32,
+                ) / scale_factor,
+                Vec2::new(viewport.width as f32, viewport.height as f32) / scale_factor,
+            ),
+            None => (Vec2::ZERO, Vec2::new(window.width(), window.height())),
+        };
         // Build a transform to convert from world to NDC using camera data
         let world_to_ndc: Mat4 =
             self.projection_matrix * camera_transform.compute_matrix().inverse();
@@ -59,7 +86,8 @@ impl Camera {
             return None;
         }
         // Once in NDC space, we can discard the z element and rescale x/y to fit the screen
-        let screen_space_coords = (ndc_space_coords.truncate() + Vec2::ONE) / 2.0 * window_size;
+        let screen_space_coords =
+            viewport_origin + (ndc_space_coords.truncate() + Vec2::ONE) / 2.0 * viewport_size;
         Some(screen_space_coords)
     }
 }
@@ -69,9 +97,13 @@ pub fn camera_system<T: CameraProjection + Component>(
     mut window_resized_events: EventReader<WindowResized>,
     mut window_created_events: EventReader<WindowCreated>,
     windows: Res<Windows>,
+    offscreen_targets: Res<OffscreenTargets>,
+    depth_mode: Option<Res<DepthMode>>,
+    removed_scaling_policies: RemovedComponents<ScalingPolicy>,
     mut queries: QuerySet<(
-        Query<(Entity, &mut Camera, &mut T)>,
+        Query<(Entity, &mut Camera, &mut T, Option<&ScalingPolicy>)>,
         Query<Entity, Added<Camera>>,
+        Query<Entity, Changed<ScalingPolicy>>,
     )>,
 ) {
     let mut changed_window_ids = Vec::new();
@@ -95,18 +127,52 @@ pub fn camera_system<T: CameraProjection + Component>(
         changed_window_ids.push(event.id);
     }
 
+    let depth_mode = depth_mode.map(|mode| *mode).unwrap_or_default();
     let mut added_cameras = vec![];
     for entity in &mut queries.q1().iter() {
         added_cameras.push(entity);
     }
-    for (entity, mut camera, mut camera_projection) in queries.q0_mut().iter_mut() {
-        if let Some(window) = windows.get(camera.window) {
-            if changed_window_ids.contains(&window.id())
-                || added_cameras.contains(&entity)
-                || camera_projection.is_changed()
-            {
-                camera_projection.update(window.width(), window.height());
-                camera.projection_matrix = camera_projection.get_projection_matrix();
+    for entity in queries.q2().iter().chain(removed_scaling_policies.iter()) {
+        added_cameras.push(entity);
+    }
+    for (entity, mut camera, mut camera_projection, scaling_policy) in queries.q0_mut().iter_mut() {
+        let size = if let Some(window) = windows.get(camera.window) {
+            let changed = changed_window_ids.contains(&window.id());
+            Some((
+                window.width(),
+                window.height(),
+                window.scale_factor() as f32,
+                (window.physical_width(), window.physical_height()),
+                changed,
+            ))
+        } else {
+            offscreen_targets.get(camera.window).map(|target| {
+                let changed = offscreen_targets.is_changed();
+                (
+                    target.width as f32,
+                    target.height as f32,
+                    1.0,
+                    (target.width, target.height),
+                    changed,
+                )
+            })
+        };
+        if let Some((width, height, scale_factor, physical_size, changed)) = size {
+            if changed || added_cameras.contains(&entity) || camera_projection.is_changed() {
+                match scaling_policy {
+                    Some(scaling_policy) => {
+                        let (viewport, size) =
+                            scaling_policy.apply(physical_size.0, physical_size.1);
+                        camera.viewport = Some(viewport);
+                        // sizes derived from the virtual resolution are in virtual pixels
+                        camera_projection.update_with_scale_factor(size.x, size.y, 1.0);
+                    }
+                    None => {
+                        camera.viewport = None;
+                        camera_projection.update_with_scale_factor(width, height, scale_factor);
+                    }
+                }
+                camera.projection_matrix = camera_projection.get_projection_matrix_for(depth_mode);
                 camera.depth_calculation = camera_projection.depth_calculation();
             }
         }
@@ -0,0 +1,249 @@
+use bevy_app::{App, CoreStage, Plugin};
+use bevy_core::Time;
+use bevy_ecs::prelude::*;
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseButton, MouseMotion, MouseScrollUnit, MouseWheel},
+    Input,
+};
+use bevy_math::{Quat, Vec2, Vec3};
+use bevy_transform::components::Transform;
+
+/// Pitch is kept just shy of straight up / down so the camera never flips over.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Adds systems that drive [`OrbitCameraController`] and [`FlyCameraController`] cameras
+/// from mouse and keyboard input. This is opt-in: it is not part of the default plugins.
+#[derive(Default)]
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Update, orbit_camera_controller_system.system())
+            .add_system_to_stage(CoreStage::Update, fly_camera_controller_system.system());
+    }
+}
+
+/// Rotates a camera around `focus` while `rotate_button` is held, and zooms with the mouse wheel.
+pub struct OrbitCameraController {
+    pub focus: Vec3,
+    pub radius: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    pub rotate_button: MouseButton,
+    /// Radians per pixel of mouse movement.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the radius zoomed per scroll line.
+    pub zoom_sensitivity: f32,
+    /// How much of the remaining motion is kept each 1/60th of a second, in `0.0..1.0`.
+    /// `0.0` disables smoothing.
+    pub smoothing: f32,
+    current_yaw: f32,
+    current_pitch: f32,
+    current_radius: f32,
+    initialized: bool,
+}
+
+impl OrbitCameraController {
+    pub fn new(focus: Vec3, radius: f32) -> Self {
+        OrbitCameraController {
+            focus,
+            radius,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for OrbitCameraController {
+    fn default() -> Self {
+        OrbitCameraController {
+            focus: Vec3::ZERO,
+            radius: 5.0,
+            yaw: 0.0,
+            pitch: -0.5,
+            min_radius: 0.1,
+            max_radius: 1000.0,
+            rotate_button: MouseButton::Left,
+            rotate_sensitivity: 0.005,
+            zoom_sensitivity: 0.1,
+            smoothing: 0.7,
+            current_yaw: 0.0,
+            current_pitch: 0.0,
+            current_radius: 0.0,
+            initialized: false,
+        }
+    }
+}
+
+/// A free-flying camera. WASD moves, Space / LShift move up and down, and the mouse looks
+/// around while `look_button` is held (or always, if it is `None`).
+pub struct FlyCameraController {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    /// Speed multiplier applied while `boost_key` is held.
+    pub boost: f32,
+    /// Radians per pixel of mouse movement.
+    pub look_sensitivity: f32,
+    pub look_button: Option<MouseButton>,
+    pub forward_key: KeyCode,
+    pub back_key: KeyCode,
+    pub left_key: KeyCode,
+    pub right_key: KeyCode,
+    pub up_key: KeyCode,
+    pub down_key: KeyCode,
+    pub boost_key: KeyCode,
+    /// How much of the previous velocity is kept each 1/60th of a second, in `0.0..1.0`.
+    /// `0.0` disables smoothing.
+    pub smoothing: f32,
+    velocity: Vec3,
+}
+
+impl Default for FlyCameraController {
+    fn default() -> Self {
+        FlyCameraController {
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 5.0,
+            boost: 3.0,
+            look_sensitivity: 0.003,
+            look_button: Some(MouseButton::Right),
+            forward_key: KeyCode::W,
+            back_key: KeyCode::S,
+            left_key: KeyCode::A,
+            right_key: KeyCode::D,
+            up_key: KeyCode::Space,
+            down_key: KeyCode::LShift,
+            boost_key: KeyCode::LControl,
+            smoothing: 0.8,
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+/// Returns how far to move towards a target this frame, so that `smoothing` behaves the same
+/// regardless of frame rate.
+fn smoothing_factor(smoothing: f32, delta_seconds: f32) -> f32 {
+    1.0 - smoothing.clamp(0.0, 0.99).powf(delta_seconds * 60.0)
+}
+
+fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch)
+}
+
+pub fn orbit_camera_controller_system(
+    time: Res<Time>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut query: Query<(&mut OrbitCameraController, &mut Transform)>,
+) {
+    let motion = mouse_motion
+        .iter()
+        .fold(Vec2::ZERO, |motion, event| motion + event.delta);
+    let scroll: f32 = mouse_wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // roughly one line per 20 pixels
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    let t = |smoothing| smoothing_factor(smoothing, time.delta_seconds());
+
+    for (mut controller, mut transform) in query.iter_mut() {
+        if mouse_buttons.pressed(controller.rotate_button) {
+            controller.yaw -= motion.x * controller.rotate_sensitivity;
+            controller.pitch = (controller.pitch - motion.y * controller.rotate_sensitivity)
+                .clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        controller.radius = (controller.radius * (1.0 - scroll * controller.zoom_sensitivity))
+            .clamp(controller.min_radius, controller.max_radius);
+
+        if controller.initialized {
+            let t = t(controller.smoothing);
+            controller.current_yaw += (controller.yaw - controller.current_yaw) * t;
+            controller.current_pitch += (controller.pitch - controller.current_pitch) * t;
+            controller.current_radius += (controller.radius - controller.current_radius) * t;
+        } else {
+            controller.current_yaw = controller.yaw;
+            controller.current_pitch = controller.pitch;
+            controller.current_radius = controller.radius;
+            controller.initialized = true;
+        }
+
+        let rotation = yaw_pitch_rotation(controller.current_yaw, controller.current_pitch);
+        transform.translation = controller.focus + rotation * Vec3::Z * controller.current_radius;
+        transform.rotation = rotation;
+    }
+}
+
+pub fn fly_camera_controller_system(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut query: Query<(&mut FlyCameraController, &mut Transform)>,
+) {
+    let motion = mouse_motion
+        .iter()
+        .fold(Vec2::ZERO, |motion, event| motion + event.delta);
+    let delta_seconds = time.delta_seconds();
+
+    for (mut controller, mut transform) in query.iter_mut() {
+        let looking = controller
+            .look_button
+            .map_or(true, |button| mouse_buttons.pressed(button));
+        if looking {
+            controller.yaw -= motion.x * controller.look_sensitivity;
+            controller.pitch = (controller.pitch - motion.y * controller.look_sensitivity)
+                .clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        transform.rotation = yaw_pitch_rotation(controller.yaw, controller.pitch);
+
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            keys.pressed(positive) as i32 as f32 - keys.pressed(negative) as i32 as f32
+        };
+        let input = Vec3::new(
+            axis(controller.right_key, controller.left_key),
+            axis(controller.up_key, controller.down_key),
+            axis(controller.back_key, controller.forward_key),
+        );
+        let mut target_velocity = Vec3::ZERO;
+        if input != Vec3::ZERO {
+            let speed = if keys.pressed(controller.boost_key) {
+                controller.speed * controller.boost
+            } else {
+                controller.speed
+            };
+            // up / down moves along the world axis rather than the camera's
+            let horizontal = Quat::from_rotation_y(controller.yaw);
+            target_velocity = (horizontal * Vec3::new(input.x, 0.0, input.z)
+                + Vec3::new(0.0, input.y, 0.0))
+            .normalize()
+                * speed;
+        }
+
+        let t = smoothing_factor(controller.smoothing, delta_seconds);
+        controller.velocity += (target_velocity - controller.velocity) * t;
+        transform.translation += controller.velocity * delta_seconds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::smoothing_factor;
+
+    #[test]
+    fn smoothing_is_frame_rate_independent() {
+        assert_eq!(smoothing_factor(0.0, 1.0 / 60.0), 1.0);
+
+        // two 120hz frames should cover the same distance as one 60hz frame
+        let half = smoothing_factor(0.5, 1.0 / 120.0);
+        let remaining = (1.0 - half) * (1.0 - half);
+        assert!((1.0 - remaining - smoothing_factor(0.5, 1.0 / 60.0)).abs() < 1e-5);
+    }
+}
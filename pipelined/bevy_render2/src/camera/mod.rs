@@ -2,7 +2,9 @@ mod active_cameras;
 mod bundle;
 #[allow(clippy::module_inception)]
 mod camera;
+mod controller;
 mod projection;
+mod scaling_policy;
 
 pub use active_cameras::*;
 use bevy_transform::components::GlobalTransform;
@@ -10,9 +12,16 @@ use bevy_utils::HashMap;
 use bevy_window::{WindowId, Windows};
 pub use bundle::*;
 pub use camera::*;
+pub use controller::*;
 pub use projection::*;
+pub use scaling_policy::*;
 
-use crate::{view::ExtractedView, RenderStage};
+use crate::{
+    dynamic_resolution::{scale_size, DynamicResolutionScale},
+    render_mode::RenderRequested,
+    view::{ExternalWindows, ExtractedView, FloatingOrigin, OffscreenTargets},
+    RenderActive, RenderApp, RenderStage, RenderSystem,
+};
 use bevy_app::{App, CoreStage, Plugin};
 use bevy_ecs::prelude::*;
 
@@ -30,6 +39,12 @@ impl Plugin for CameraPlugin {
         active_cameras.add(Self::CAMERA_2D);
         active_cameras.add(Self::CAMERA_3D);
         app.register_type::<Camera>()
+            .register_type::<DepthCalculation>()
+            .register_type::<OrthographicProjection>()
+            .register_type::<PerspectiveProjection>()
+            .register_type::<ScalingMode>()
+            .register_type::<ScalingPolicy>()
+            .register_type::<WindowOrigin>()
             .insert_resource(active_cameras)
             .add_system_to_stage(
                 CoreStage::PostUpdate,
@@ -43,13 +58,41 @@ impl Plugin for CameraPlugin {
                 CoreStage::PostUpdate,
                 crate::camera::camera_system::<PerspectiveProjection>.system(),
             );
-        let render_app = app.sub_app_mut(0);
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
             .init_resource::<ExtractedCameraNames>()
-            .add_system_to_stage(RenderStage::Extract, extract_cameras.system());
+            .add_system_to_stage(
+                RenderStage::Extract,
+                extract_cameras.system().label(RenderSystem::ExtractCameras),
+            );
     }
 }
 
+/// Returns the size in physical pixels of the target `camera` draws to: a window, an
+/// [`OffscreenTargets`] target or an [`ExternalWindows`] window. Returns `None` if the target
+/// doesn't exist or has no area, like minimized windows.
+pub fn camera_target_size(
+    camera: &Camera,
+    windows: &Windows,
+    offscreen_targets: &OffscreenTargets,
+    external_windows: &ExternalWindows,
+) -> Option<(u32, u32)> {
+    windows
+        .get(camera.window)
+        .map(|window| (window.physical_width(), window.physical_height()))
+        .or_else(|| {
+            offscreen_targets
+                .get(camera.window)
+                .map(|target| (target.width, target.height))
+        })
+        .or_else(|| {
+            external_windows
+                .get(camera.window)
+                .map(|window| (window.physical_width, window.physical_height))
+        })
+        .filter(|(width, height)| *width > 0 && *height > 0)
+}
+
 #[derive(Default)]
 pub struct ExtractedCameraNames {
     pub entities: HashMap<String, Entity>,
@@ -59,30 +102,59 @@ pub struct ExtractedCameraNames {
 pub struct ExtractedCamera {
     pub window_id: WindowId,
     pub name: Option<String>,
+    pub priority: isize,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn extract_cameras(
     mut commands: Commands,
+    render_active: Res<RenderActive>,
+    render_requested: Res<RenderRequested>,
     active_cameras: Res<ActiveCameras>,
     windows: Res<Windows>,
+    offscreen_targets: Res<OffscreenTargets>,
+    external_windows: Res<ExternalWindows>,
+    dynamic_resolution: Option<Res<DynamicResolutionScale>>,
+    floating_origin: Res<FloatingOrigin>,
     query: Query<(Entity, &Camera, &GlobalTransform)>,
 ) {
+    let dynamic_scale = dynamic_resolution.map_or(1.0, |scale| scale.0);
     let mut entities = HashMap::default();
-    for camera in active_cameras.iter() {
+    // paused frames have no views, so nothing is prepared for them
+    let active = render_active.0 && render_requested.0;
+    for camera in active_cameras.iter().filter(|_| active) {
         let name = &camera.name;
         if let Some((entity, camera, transform)) = camera.entity.and_then(|e| query.get(e).ok()) {
-            entities.insert(name.clone(), entity);
-            if let Some(window) = windows.get(camera.window) {
+            let size = camera_target_size(camera, &windows, &offscreen_targets, &external_windows);
+            if let Some((width, height)) = size {
+                // cameras are set up in physical pixels, windows may be rendered at a lower
+                // resolution, which the dynamic resolution lowers further
+                let (width, height, viewport) = match windows.get(camera.window) {
+                    Some(window) => {
+                        let width = scale_size(window.render_width(), dynamic_scale);
+                        let height = scale_size(window.render_height(), dynamic_scale);
+                        let viewport = camera.viewport.map(|viewport| {
+                            viewport
+                                .scale(window.resolution_scale() * dynamic_scale)
+                                .clamp_to_target(width, height)
+                        });
+                        (width, height, viewport)
+                    }
+                    None => (width, height, camera.viewport),
+                };
+                entities.insert(name.clone(), entity);
                 commands.get_or_spawn(entity).insert_bundle((
                     ExtractedCamera {
                         window_id: camera.window,
                         name: camera.name.clone(),
+                        priority: camera.priority,
                     },
                     ExtractedView {
                         projection: camera.projection_matrix,
-                        transform: transform.clone(),
-                        width: window.physical_width(),
-                        height: window.physical_height(),
+                        transform: floating_origin.to_render(transform),
+                        width,
+                        height,
+                        viewport,
                     },
                 ));
             }
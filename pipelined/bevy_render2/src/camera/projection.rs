@@ -1,4 +1,5 @@
 use super::DepthCalculation;
+use crate::core_pipeline::DepthMode;
 use bevy_ecs::reflect::ReflectComponent;
 use bevy_math::Mat4;
 use bevy_reflect::{Reflect, ReflectDeserialize};
@@ -8,6 +9,17 @@ pub trait CameraProjection {
     fn get_projection_matrix(&self) -> Mat4;
     fn update(&mut self, width: f32, height: f32);
     fn depth_calculation(&self) -> DepthCalculation;
+
+    /// Returns the projection matrix for a depth buffer using `depth_mode`.
+    fn get_projection_matrix_for(&self, depth_mode: DepthMode) -> Mat4 {
+        depth_mode.adjust_projection(self.get_projection_matrix())
+    }
+
+    /// Updates the projection for a window with the given logical size and scale factor.
+    /// Projections that only care about the logical size don't need to override this.
+    fn update_with_scale_factor(&mut self, width: f32, height: f32, _scale_factor: f32) {
+        self.update(width, height);
+    }
 }
 
 #[derive(Debug, Clone, Reflect)]
@@ -28,6 +40,15 @@ impl CameraProjection for PerspectiveProjection {
         self.aspect_ratio = width / height;
     }
 
+    fn get_projection_matrix_for(&self, depth_mode: DepthMode) -> Mat4 {
+        match depth_mode {
+            DepthMode::Standard => self.get_projection_matrix(),
+            DepthMode::ReverseZ => {
+                Mat4::perspective_infinite_reverse_rh(self.fov, self.aspect_ratio, self.near)
+            }
+        }
+    }
+
     fn depth_calculation(&self) -> DepthCalculation {
         DepthCalculation::Distance
     }
@@ -64,6 +85,11 @@ pub enum ScalingMode {
     FixedVertical,
     /// Keep horizontal axis constant; resize vertical with aspect ratio.
     FixedHorizontal,
+    /// Match the window size in physical pixels, with 1 world unit = the given integer number
+    /// of physical pixels. Unlike `WindowSize`, this accounts for the window's scale factor and
+    /// keeps world units aligned to the pixel grid, so pixel art stays crisp.
+    /// See [`OrthographicProjection::pixel_perfect_scale`].
+    PixelPerfect(u32),
 }
 
 #[derive(Debug, Clone, Reflect)]
@@ -94,6 +120,10 @@ impl CameraProjection for OrthographicProjection {
     }
 
     fn update(&mut self, width: f32, height: f32) {
+        self.update_with_scale_factor(width, height, 1.0);
+    }
+
+    fn update_with_scale_factor(&mut self, width: f32, height: f32, scale_factor: f32) {
         match (&self.scaling_mode, &self.window_origin) {
             (ScalingMode::WindowSize, WindowOrigin::Center) => {
                 let half_width = width / 2.0;
@@ -137,6 +167,23 @@ impl CameraProjection for OrthographicProjection {
                 self.top = aspect_ratio;
                 self.bottom = 0.0;
             }
+            (ScalingMode::PixelPerfect(pixels_per_unit), window_origin) => {
+                let pixels_per_unit = (*pixels_per_unit).max(1) as f32;
+                let physical_width = (width * scale_factor).round();
+                let physical_height = (height * scale_factor).round();
+                let (left, bottom) = match window_origin {
+                    // snap the origin to a pixel boundary when the size is odd
+                    WindowOrigin::Center => (
+                        -(physical_width / 2.0).floor(),
+                        -(physical_height / 2.0).floor(),
+                    ),
+                    WindowOrigin::BottomLeft => (0.0, 0.0),
+                };
+                self.left = left / pixels_per_unit;
+                self.right = (left + physical_width) / pixels_per_unit;
+                self.bottom = bottom / pixels_per_unit;
+                self.top = (bottom + physical_height) / pixels_per_unit;
+            }
             (ScalingMode::None, _) => {}
         }
     }
@@ -146,6 +193,22 @@ impl CameraProjection for OrthographicProjection {
     }
 }
 
+impl OrthographicProjection {
+    /// Returns the largest integer scale at which a `target_width` x `target_height` image fits
+    /// in a window of the given physical size, for use with [`ScalingMode::PixelPerfect`].
+    /// Always returns at least 1.
+    pub fn pixel_perfect_scale(
+        target_width: u32,
+        target_height: u32,
+        physical_width: u32,
+        physical_height: u32,
+    ) -> u32 {
+        let horizontal = physical_width / target_width.max(1);
+        let vertical = physical_height / target_height.max(1);
+        horizontal.min(vertical).max(1)
+    }
+}
+
 impl Default for OrthographicProjection {
     fn default() -> Self {
         OrthographicProjection {
@@ -162,3 +225,61 @@ impl Default for OrthographicProjection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CameraProjection, OrthographicProjection, PerspectiveProjection, ScalingMode};
+    use crate::core_pipeline::DepthMode;
+    use bevy_math::Vec3;
+
+    #[test]
+    fn pixel_perfect() {
+        assert_eq!(
+            OrthographicProjection::pixel_perfect_scale(320, 180, 1920, 1080),
+            6
+        );
+        assert_eq!(
+            OrthographicProjection::pixel_perfect_scale(320, 180, 1366, 768),
+            4
+        );
+        assert_eq!(
+            OrthographicProjection::pixel_perfect_scale(320, 180, 100, 100),
+            1
+        );
+
+        let mut projection = OrthographicProjection {
+            scaling_mode: ScalingMode::PixelPerfect(2),
+            ..Default::default()
+        };
+        // 641x480 logical pixels at a scale factor of 2 is 1282x960 physical pixels
+        projection.update_with_scale_factor(641.0, 480.0, 2.0);
+        assert_eq!(projection.left, -320.5);
+        assert_eq!(projection.right, 320.5);
+        assert_eq!(projection.bottom, -240.0);
+        assert_eq!(projection.top, 240.0);
+    }
+
+    #[test]
+    fn reverse_z() {
+        let depth = |projection: &dyn CameraProjection, depth_mode, distance| {
+            projection
+                .get_projection_matrix_for(depth_mode)
+                .project_point3(Vec3::new(0.0, 0.0, -distance))
+                .z
+        };
+        let perspective = PerspectiveProjection::default();
+        assert!((depth(&perspective, DepthMode::Standard, 1.0) - 0.0).abs() < 1e-6);
+        assert!((depth(&perspective, DepthMode::ReverseZ, 1.0) - 1.0).abs() < 1e-6);
+        // there is no far plane, depth only approaches zero
+        let far = depth(&perspective, DepthMode::ReverseZ, 1.0e6);
+        assert!(far > 0.0 && far < 1.0e-5);
+
+        let orthographic = OrthographicProjection {
+            near: 0.0,
+            far: 100.0,
+            ..Default::default()
+        };
+        assert!((depth(&orthographic, DepthMode::ReverseZ, 0.0) - 1.0).abs() < 1e-6);
+        assert!((depth(&orthographic, DepthMode::ReverseZ, 100.0) - 0.0).abs() < 1e-6);
+    }
+}
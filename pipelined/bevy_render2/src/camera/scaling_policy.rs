@@ -0,0 +1,113 @@
+use crate::render_phase::ClipRect;
+use bevy_math::Vec2;
+use bevy_reflect::{Reflect, ReflectDeserialize};
+use serde::{Deserialize, Serialize};
+
+/// Fits a virtual resolution to the target of a camera, so 2d games can be made for one logical
+/// resolution and shown in windows of any size. Add it to a camera entity next to its projection.
+///
+/// The projection is updated with a size derived from the virtual resolution instead of the size
+/// of the target, so with [`ScalingMode::WindowSize`](super::ScalingMode::WindowSize) one world
+/// unit is one virtual pixel. The viewport the camera draws to is stored in
+/// [`Camera::viewport`](super::Camera::viewport).
+///
+/// The 2d and 3d main passes draw to the viewport. Other passes, such as post processing, still
+/// cover the whole target.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum ScalingPolicy {
+    /// Shows exactly the virtual resolution, scaled uniformly to fit the target. The remaining
+    /// bars at the top and bottom (letterbox) or the sides (pillarbox) are cleared to the clear
+    /// color.
+    FixedVirtualResolution { width: f32, height: f32 },
+    /// Fills the target and shows at least the virtual resolution, extending the visible area
+    /// along the axis the target is longer in.
+    Expand { width: f32, height: f32 },
+    /// Fills the target and shows at most the virtual resolution, cutting off the visible area
+    /// along the axis the target is shorter in.
+    Crop { width: f32, height: f32 },
+}
+
+impl ScalingPolicy {
+    pub fn virtual_size(&self) -> Vec2 {
+        match *self {
+            ScalingPolicy::FixedVirtualResolution { width, height }
+            | ScalingPolicy::Expand { width, height }
+            | ScalingPolicy::Crop { width, height } => Vec2::new(width, height),
+        }
+    }
+
+    /// Returns the viewport of the camera in a target of the given size in physical pixels, and
+    /// the size to update its projection with.
+    pub fn apply(&self, target_width: u32, target_height: u32) -> (ClipRect, Vec2) {
+        let full = ClipRect::new(0, 0, target_width, target_height);
+        let virtual_size = self.virtual_size();
+        if target_width == 0 || target_height == 0 || virtual_size.min_element() <= 0.0 {
+            return (full, virtual_size);
+        }
+        let target_size = Vec2::new(target_width as f32, target_height as f32);
+        let target_aspect = target_size.x / target_size.y;
+        let virtual_aspect = virtual_size.x / virtual_size.y;
+        let extend_width = Vec2::new(virtual_size.y * target_aspect, virtual_size.y);
+        let extend_height = Vec2::new(virtual_size.x, virtual_size.x / target_aspect);
+        match self {
+            ScalingPolicy::FixedVirtualResolution { .. } => {
+                let scale = (target_size / virtual_size).min_element();
+                let size = (virtual_size * scale).round().min(target_size);
+                let offset = ((target_size - size) / 2.0).floor();
+                let viewport = ClipRect::new(
+                    offset.x as u32,
+                    offset.y as u32,
+                    size.x as u32,
+                    size.y as u32,
+                );
+                (viewport, virtual_size)
+            }
+            ScalingPolicy::Expand { .. } if target_aspect > virtual_aspect => (full, extend_width),
+            ScalingPolicy::Expand { .. } => (full, extend_height),
+            ScalingPolicy::Crop { .. } if target_aspect > virtual_aspect => (full, extend_height),
+            ScalingPolicy::Crop { .. } => (full, extend_width),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScalingPolicy;
+    use crate::render_phase::ClipRect;
+    use bevy_math::Vec2;
+
+    #[test]
+    fn scaling_policies() {
+        let fixed = ScalingPolicy::FixedVirtualResolution {
+            width: 320.0,
+            height: 180.0,
+        };
+        // pillarbox
+        assert_eq!(
+            fixed.apply(800, 360),
+            (ClipRect::new(80, 0, 640, 360), Vec2::new(320.0, 180.0))
+        );
+        // letterbox
+        assert_eq!(
+            fixed.apply(640, 480),
+            (ClipRect::new(0, 60, 640, 360), Vec2::new(320.0, 180.0))
+        );
+
+        let full = ClipRect::new(0, 0, 800, 360);
+        let expand = ScalingPolicy::Expand {
+            width: 320.0,
+            height: 180.0,
+        };
+        let (viewport, size) = expand.apply(800, 360);
+        assert_eq!(viewport, full);
+        assert!((size - Vec2::new(400.0, 180.0)).abs().max_element() < 1e-3);
+        let crop = ScalingPolicy::Crop {
+            width: 320.0,
+            height: 180.0,
+        };
+        let (viewport, size) = crop.apply(800, 360);
+        assert_eq!(viewport, full);
+        assert!((size - Vec2::new(320.0, 144.0)).abs().max_element() < 1e-3);
+    }
+}
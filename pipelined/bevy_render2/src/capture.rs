@@ -0,0 +1,265 @@
+use crate::{
+    core_pipeline,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_resource::{BufferId, BufferInfo, BufferMapMode, BufferUsage},
+    renderer::{RenderContext, RenderResources},
+    texture::{
+        compare_images, image_texture_conversion, Extent3d, ImageCompareError, ImageComparison,
+        Texture, TextureDimension, TextureFormat,
+    },
+    view::OffscreenTextures,
+    RenderApp, RenderStage,
+};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_utils::{tracing::warn, HashMap, HashSet};
+use bevy_window::WindowId;
+use parking_lot::Mutex;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// Copies the contents of [`OffscreenTarget`](crate::view::OffscreenTarget)s back to the CPU on
+/// request, see [`ImageCaptures`]. Must be added after the
+/// [`CorePipelinePlugin`](crate::core_pipeline::CorePipelinePlugin).
+#[derive(Default)]
+pub struct CapturePlugin;
+
+impl CapturePlugin {
+    pub const CAPTURE_NODE: &'static str = "capture";
+}
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        let captures = ImageCaptures::default();
+        app.insert_resource(captures.clone());
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(captures)
+            .add_system_to_stage(RenderStage::Cleanup, read_captures_system.system());
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(Self::CAPTURE_NODE, CaptureNode);
+        graph
+            .add_node_edge(core_pipeline::node::OVERLAY, Self::CAPTURE_NODE)
+            .unwrap();
+    }
+}
+
+struct PendingCapture {
+    target: WindowId,
+    buffer: BufferId,
+    bytes_per_row: usize,
+    size: Extent3d,
+    format: TextureFormat,
+}
+
+#[derive(Default)]
+struct CaptureState {
+    requested: HashSet<WindowId>,
+    pending: Vec<PendingCapture>,
+    captured: HashMap<WindowId, Texture>,
+}
+
+/// Requests and receives captures of offscreen targets. This resource exists in both the app
+/// world and the render world, and both share the same state.
+#[derive(Clone, Default)]
+pub struct ImageCaptures {
+    state: Arc<Mutex<CaptureState>>,
+}
+
+impl ImageCaptures {
+    /// Captures the offscreen target `target` at the end of the next rendered frame.
+    pub fn request(&self, target: WindowId) {
+        self.state.lock().requested.insert(target);
+    }
+
+    /// Returns the last captured image of `target`, if it hasn't been taken yet.
+    pub fn take(&self, target: WindowId) -> Option<Texture> {
+        self.state.lock().captured.remove(&target)
+    }
+}
+
+/// Copies the requested offscreen targets to buffers after the main passes ran.
+pub struct CaptureNode;
+
+impl Node for CaptureNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let captures = world.get_resource::<ImageCaptures>().unwrap();
+        let offscreen_textures = world.get_resource::<OffscreenTextures>().unwrap();
+        let mut state = captures.state.lock();
+        let requested = std::mem::take(&mut state.requested);
+        for target in requested {
+            let offscreen_texture = match offscreen_textures.get(target) {
+                Some(offscreen_texture) => offscreen_texture,
+                None => {
+                    warn!("cannot capture {}, it is not an offscreen target", target);
+                    continue;
+                }
+            };
+            let size = offscreen_texture.size;
+            let bytes_per_row = render_context.resources().get_aligned_texture_size(
+                size.width as usize * offscreen_texture.format.pixel_size(),
+            );
+            let buffer = render_context.resources().create_buffer(BufferInfo {
+                size: bytes_per_row * size.height as usize,
+                buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+                ..Default::default()
+            });
+            render_context.copy_texture_to_buffer(
+                offscreen_texture.texture,
+                [0, 0, 0],
+                0,
+                buffer,
+                0,
+                bytes_per_row as u32,
+                size,
+            );
+            state.pending.push(PendingCapture {
+                target,
+                buffer,
+                bytes_per_row,
+                size,
+                format: offscreen_texture.format,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads back the captures copied this frame. Runs in [`RenderStage::Cleanup`], after the frame
+/// was submitted.
+pub fn read_captures_system(captures: Res<ImageCaptures>, render_resources: Res<RenderResources>) {
+    let mut state = captures.state.lock();
+    for capture in std::mem::take(&mut state.pending) {
+        let row_size = capture.size.width as usize * capture.format.pixel_size();
+        let data = RefCell::new(Vec::with_capacity(row_size * capture.size.height as usize));
+        render_resources.map_buffer(capture.buffer, BufferMapMode::Read);
+        render_resources.read_mapped_buffer(
+            capture.buffer,
+            0..(capture.bytes_per_row * capture.size.height as usize) as u64,
+            &|bytes, _| {
+                let mut data = data.borrow_mut();
+                // drop the padding of each row
+                for row in bytes.chunks(capture.bytes_per_row) {
+                    data.extend_from_slice(&row[..row_size]);
+                }
+            },
+        );
+        render_resources.unmap_buffer(capture.buffer);
+        render_resources.remove_buffer(capture.buffer);
+
+        let texture = Texture::new(
+            capture.size,
+            TextureDimension::D2,
+            data.into_inner(),
+            capture.format,
+        );
+        state.captured.insert(capture.target, texture);
+    }
+}
+
+/// Runs `app` for `frames` frames and returns the image rendered to the offscreen target `target`
+/// in the last one. The app needs a render backend and a [`CapturePlugin`].
+///
+/// Combined with [`DrawOrder::Deterministic`](crate::render_phase::DrawOrder::Deterministic) and
+/// [`compare_to_reference`], this allows rendering regression tests. These don't need a display:
+/// disable the windowing plugin, and point the cameras at an
+/// [`OffscreenTarget`](crate::view::OffscreenTarget) instead of a window. Render backends that
+/// can fail to find a device, like `bevy_wgpu2`'s `WgpuExternalDevice::try_request`, let tests
+/// skip on machines without a GPU.
+pub fn render_to_image(app: &mut App, target: WindowId, frames: usize) -> Option<Texture> {
+    let captures = app
+        .world
+        .get_resource::<ImageCaptures>()
+        .expect("rendering to an image requires the CapturePlugin")
+        .clone();
+    for frame in 0..frames {
+        if frame + 1 == frames {
+            captures.request(target);
+        }
+        app.update();
+    }
+    captures.take(target)
+}
+
+/// Set this environment variable to save the newly rendered images as the reference images used
+/// by [`compare_to_reference`], whether they exist or not.
+pub const UPDATE_REFERENCE_IMAGES_ENV: &str = "BEVY_UPDATE_REFERENCE_IMAGES";
+
+#[derive(Error, Debug)]
+pub enum ReferenceImageError {
+    #[error(
+        "there is no reference image at {}, set {} to save the rendered image as the reference",
+        .0.display(),
+        UPDATE_REFERENCE_IMAGES_ENV
+    )]
+    MissingReference(PathBuf),
+    #[error("failed to load the reference image: {0}")]
+    Load(image::ImageError),
+    #[error("failed to save the image: {0}")]
+    Save(image::ImageError),
+    #[error("images with the format {0:?} cannot be saved")]
+    UnsupportedFormat(TextureFormat),
+    #[error(transparent)]
+    Compare(#[from] ImageCompareError),
+    #[error(
+        "{} of {} pixels differ from the reference by more than the tolerance, by up to {}",
+        .0.mismatched_pixels,
+        .0.pixel_count,
+        .0.max_difference
+    )]
+    Mismatch(ImageComparison),
+}
+
+/// Compares `image` against the reference image at `path` with [`compare_images`].
+///
+/// If [`UPDATE_REFERENCE_IMAGES_ENV`] is set, `image` is saved as the reference instead. A missing
+/// reference is an error otherwise, so a test can't pass by comparing an image to itself. When the
+/// images don't match, `image` is saved next to the reference with an `.actual.png` extension, so
+/// the two can be inspected.
+pub fn compare_to_reference(
+    image: &Texture,
+    path: impl AsRef<Path>,
+    tolerance: f32,
+) -> Result<ImageComparison, ReferenceImageError> {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_REFERENCE_IMAGES_ENV).is_some() {
+        save_image(image, path)?;
+        return Ok(ImageComparison {
+            max_difference: 0.0,
+            mismatched_pixels: 0,
+            pixel_count: image.size.volume(),
+        });
+    }
+    if !path.exists() {
+        return Err(ReferenceImageError::MissingReference(path.to_path_buf()));
+    }
+
+    let reference = image::open(path)
+        .map(image_texture_conversion::image_to_texture)
+        .map_err(ReferenceImageError::Load)?;
+    let comparison = compare_images(image, &reference, tolerance)?;
+    if comparison.is_match() {
+        Ok(comparison)
+    } else {
+        save_image(image, &path.with_extension("actual.png"))?;
+        Err(ReferenceImageError::Mismatch(comparison))
+    }
+}
+
+fn save_image(image: &Texture, path: &Path) -> Result<(), ReferenceImageError> {
+    let dynamic_image = image_texture_conversion::texture_to_image(image)
+        .ok_or(ReferenceImageError::UnsupportedFormat(image.format))?;
+    image::DynamicImage::ImageRgba8(dynamic_image.into_rgba8())
+        .save(path)
+        .map_err(ReferenceImageError::Save)
+}
@@ -0,0 +1,529 @@
+use crate::{
+    core_pipeline,
+    pipeline::{
+        BindGroupDescriptorId, BindType, ComputePipelineDescriptor, PipelineId, PipelineLayout,
+    },
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_resource::{BindGroup, BufferId, BufferInfo, BufferMapMode, BufferUsage, TextureViewId},
+    renderer::{RenderContext, RenderResourceContext, RenderResources},
+    shader::{
+        ComputeShaderStages, Shader, ShaderError, ShaderReflectError, ShaderReflectOptions,
+        ShaderStage,
+    },
+    texture::StorageTextureAccess,
+    RenderApp, RenderStage,
+};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_utils::{tracing::error, HashMap};
+use bytemuck::Pod;
+use parking_lot::Mutex;
+use std::{cell::RefCell, sync::Arc};
+use thiserror::Error;
+
+/// Runs [`ComputeJob`]s submitted to [`ComputeJobs`], without having to set up pipelines, bind
+/// groups or render graph nodes.
+///
+/// If the [`CorePipelinePlugin`](crate::core_pipeline::CorePipelinePlugin) was added before this
+/// plugin, jobs are dispatched before the main passes, so their results can be used when drawing.
+#[derive(Default)]
+pub struct ComputePlugin;
+
+impl ComputePlugin {
+    pub const COMPUTE_NODE: &'static str = "compute_jobs";
+}
+
+impl Plugin for ComputePlugin {
+    fn build(&self, app: &mut App) {
+        let jobs = ComputeJobs::default();
+        app.insert_resource(jobs.clone());
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(jobs)
+            .add_system_to_stage(RenderStage::Prepare, prepare_compute_jobs.system())
+            .add_system_to_stage(RenderStage::Cleanup, read_compute_results_system.system());
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(Self::COMPUTE_NODE, ComputeNode);
+        if graph
+            .get_node_state(core_pipeline::node::MAIN_PASS_DRIVER)
+            .is_ok()
+        {
+            graph
+                .add_node_edge(Self::COMPUTE_NODE, core_pipeline::node::MAIN_PASS_DRIVER)
+                .unwrap();
+        }
+    }
+}
+
+struct ComputeBuffer {
+    binding: u32,
+    data: Vec<u8>,
+    read_back: bool,
+}
+
+struct ComputeTexture {
+    binding: u32,
+    texture_view: TextureViewId,
+    access: StorageTextureAccess,
+}
+
+/// A compute shader dispatch, together with the data of the buffers it binds.
+///
+/// All buffers are bound in bind group 0, at the binding given when adding them. Uniform and
+/// storage buffers are supported, their type is taken from the shader. Existing textures can be
+/// bound as storage textures, for the job to write images sampled when drawing, e.g. procedural
+/// noise.
+///
+/// ```ignore
+/// let job = ComputeJob::from_glsl(SHADER, [values.len() as u32 / 64, 1, 1])
+///     .with_read_back_buffer(0, &values);
+/// let id = compute_jobs.submit(job);
+/// // on a later frame
+/// if let Some(Ok(results)) = compute_jobs.take_results(id) {
+///     let values = results.read::<f32>(0).unwrap();
+/// }
+/// ```
+pub struct ComputeJob {
+    pub shader: Shader,
+    /// The number of workgroups to dispatch in each dimension.
+    pub workgroups: [u32; 3],
+    /// Dispatch the job every frame until it is removed, instead of only once. Its buffers are
+    /// kept between frames, so each dispatch sees the data written by the previous one.
+    pub repeat: bool,
+    buffers: Vec<ComputeBuffer>,
+    textures: Vec<ComputeTexture>,
+}
+
+impl ComputeJob {
+    pub fn new(shader: Shader, workgroups: [u32; 3]) -> Self {
+        ComputeJob {
+            shader,
+            workgroups,
+            repeat: false,
+            buffers: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn from_glsl(glsl: &str, workgroups: [u32; 3]) -> Self {
+        Self::new(Shader::from_glsl(ShaderStage::Compute, glsl), workgroups)
+    }
+
+    /// Binds a buffer initialized with `data` at `binding`.
+    pub fn with_buffer<T: Pod>(self, binding: u32, data: &[T]) -> Self {
+        self.add_buffer(binding, data, false)
+    }
+
+    /// Binds a buffer initialized with `data` at `binding`, and reads its contents back after
+    /// each dispatch, see [`ComputeResults`].
+    pub fn with_read_back_buffer<T: Pod>(self, binding: u32, data: &[T]) -> Self {
+        self.add_buffer(binding, data, true)
+    }
+
+    /// Binds `texture_view` as the storage texture at `binding`, with the `access` the shader
+    /// declares. The texture must have been created with
+    /// [`TextureUsage::STORAGE`](crate::texture::TextureUsage::STORAGE), and outlive the job.
+    pub fn with_storage_texture(
+        mut self,
+        binding: u32,
+        texture_view: TextureViewId,
+        access: StorageTextureAccess,
+    ) -> Self {
+        self.textures.retain(|texture| texture.binding != binding);
+        self.textures.push(ComputeTexture {
+            binding,
+            texture_view,
+            access,
+        });
+        self
+    }
+
+    pub fn repeating(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    fn add_buffer<T: Pod>(mut self, binding: u32, data: &[T], read_back: bool) -> Self {
+        self.buffers.retain(|buffer| buffer.binding != binding);
+        self.buffers.push(ComputeBuffer {
+            binding,
+            data: bytemuck::cast_slice(data).to_vec(),
+            read_back,
+        });
+        self
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ComputeJobError {
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+    #[error("failed to reflect the shader layout: {0}")]
+    Reflect(#[from] ShaderReflectError),
+    #[error("compute jobs only support bind group 0, but the shader uses bind group {0}")]
+    UnsupportedBindGroup(u32),
+    #[error("binding {0} is used by the shader, but no buffer was added for it")]
+    MissingBuffer(u32),
+    #[error("a buffer was added for binding {0}, but the shader doesn't use it")]
+    UnusedBuffer(u32),
+    #[error("binding {0} is a storage texture, but no texture was added for it")]
+    MissingTexture(u32),
+    #[error(
+        "a texture was added for binding {0}, but the shader doesn't use it as a storage texture"
+    )]
+    UnusedTexture(u32),
+    #[error("binding {0} is not a uniform buffer, storage buffer or storage texture")]
+    UnsupportedBinding(u32),
+}
+
+/// The buffers read back after a [`ComputeJob`] was dispatched, by binding.
+#[derive(Debug, Clone, Default)]
+pub struct ComputeResults {
+    buffers: HashMap<u32, Vec<u8>>,
+}
+
+impl ComputeResults {
+    pub fn bytes(&self, binding: u32) -> Option<&[u8]> {
+        self.buffers.get(&binding).map(|data| data.as_slice())
+    }
+
+    /// Returns the contents of the buffer at `binding` as a `Vec<T>`, if it was read back.
+    pub fn read<T: Pod>(&self, binding: u32) -> Option<Vec<T>> {
+        let bytes = self.bytes(binding)?;
+        // copy instead of casting, the bytes might not be aligned for `T`
+        let mut values = vec![T::zeroed(); bytes.len() / std::mem::size_of::<T>()];
+        let len = values.len() * std::mem::size_of::<T>();
+        bytemuck::cast_slice_mut(&mut values).copy_from_slice(&bytes[..len]);
+        Some(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct ComputeJobId(usize);
+
+struct PreparedBuffer {
+    buffer: BufferId,
+    size: usize,
+    binding: u32,
+    read_back_buffer: Option<BufferId>,
+}
+
+struct PreparedJob {
+    pipeline: PipelineId,
+    bind_group: Option<(BindGroupDescriptorId, BindGroup)>,
+    buffers: Vec<PreparedBuffer>,
+    workgroups: [u32; 3],
+    repeat: bool,
+}
+
+impl PreparedJob {
+    // TODO: also remove the pipeline, once RenderResourceContext supports it
+    fn remove(self, render_resources: &dyn RenderResourceContext) {
+        for buffer in self.buffers {
+            render_resources.remove_buffer(buffer.buffer);
+            if let Some(read_back_buffer) = buffer.read_back_buffer {
+                render_resources.remove_buffer(read_back_buffer);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ComputeState {
+    next_id: usize,
+    submitted: Vec<(ComputeJobId, ComputeJob)>,
+    prepared: Vec<(ComputeJobId, PreparedJob)>,
+    dispatched: Vec<ComputeJobId>,
+    removed: Vec<ComputeJobId>,
+    results: HashMap<ComputeJobId, Result<ComputeResults, ComputeJobError>>,
+}
+
+/// Submits [`ComputeJob`]s and receives their results. This resource exists in both the app world
+/// and the render world, and both share the same state.
+#[derive(Clone, Default)]
+pub struct ComputeJobs {
+    state: Arc<Mutex<ComputeState>>,
+}
+
+impl ComputeJobs {
+    /// Dispatches `job` in the next rendered frame, and every frame after that if it repeats.
+    pub fn submit(&self, job: ComputeJob) -> ComputeJobId {
+        let mut state = self.state.lock();
+        let id = ComputeJobId(state.next_id);
+        state.next_id += 1;
+        state.submitted.push((id, job));
+        id
+    }
+
+    /// Stops dispatching the job `id` and frees its buffers.
+    pub fn remove(&self, id: ComputeJobId) {
+        let mut state = self.state.lock();
+        state.submitted.retain(|(job, _)| *job != id);
+        state.results.remove(&id);
+        state.removed.push(id);
+    }
+
+    /// Returns the results of the last dispatch of the job `id`, or the error that prevented it
+    /// from running, if they haven't been taken yet.
+    pub fn take_results(
+        &self,
+        id: ComputeJobId,
+    ) -> Option<Result<ComputeResults, ComputeJobError>> {
+        self.state.lock().results.remove(&id)
+    }
+}
+
+/// Creates the pipelines and buffers of newly submitted jobs, and frees removed ones.
+pub fn prepare_compute_jobs(jobs: Res<ComputeJobs>, render_resources: Res<RenderResources>) {
+    let mut state = jobs.state.lock();
+    let state = &mut *state;
+    for id in std::mem::take(&mut state.removed) {
+        if let Some(index) = state.prepared.iter().position(|(job, _)| *job == id) {
+            state.prepared.remove(index).1.remove(&**render_resources);
+        }
+    }
+    for (id, job) in std::mem::take(&mut state.submitted) {
+        match prepare_job(job, &**render_resources) {
+            Ok(job) => state.prepared.push((id, job)),
+            Err(err) => {
+                error!("failed to prepare compute job {:?}: {}", id, err);
+                state.results.insert(id, Err(err));
+            }
+        }
+    }
+    // bind groups that aren't used for a few frames are removed, so make sure they exist
+    for (_, job) in state.prepared.iter() {
+        if let Some((descriptor, bind_group)) = &job.bind_group {
+            render_resources.create_bind_group(*descriptor, bind_group);
+        }
+    }
+}
+
+fn prepare_job(
+    job: ComputeJob,
+    render_resources: &dyn RenderResourceContext,
+) -> Result<PreparedJob, ComputeJobError> {
+    let shader = job.shader.get_spirv_shader(None)?;
+    let shader_layout = shader.try_reflect_layout(&ShaderReflectOptions {
+        bevy_conventions: false,
+        ..Default::default()
+    })?;
+    let mut layout = PipelineLayout::from_shader_layouts(&mut [shader_layout]);
+    // the access of storage textures isn't reflected
+    if let Some(bind_group) = layout.get_bind_group_mut(0) {
+        for binding in bind_group.bindings.iter_mut() {
+            if let BindType::StorageTexture { access, .. } = &mut binding.bind_type {
+                if let Some(texture) = job.textures.iter().find(|t| t.binding == binding.index) {
+                    *access = texture.access;
+                }
+            }
+        }
+        bind_group.update_id();
+    }
+
+    // validate everything before creating any resources
+    if let Some(bind_group) = layout.bind_groups.iter().find(|group| group.index != 0) {
+        return Err(ComputeJobError::UnsupportedBindGroup(bind_group.index));
+    }
+    let bindings = layout
+        .get_bind_group(0)
+        .map_or(&[][..], |bind_group| bind_group.bindings.as_slice());
+    if let Some(buffer) = job
+        .buffers
+        .iter()
+        .find(|buffer| !bindings.iter().any(|b| b.index == buffer.binding))
+    {
+        return Err(ComputeJobError::UnusedBuffer(buffer.binding));
+    }
+    if let Some(texture) = job.textures.iter().find(|texture| {
+        !bindings.iter().any(|b| {
+            b.index == texture.binding && matches!(b.bind_type, BindType::StorageTexture { .. })
+        })
+    }) {
+        return Err(ComputeJobError::UnusedTexture(texture.binding));
+    }
+    for binding in bindings.iter() {
+        match binding.bind_type {
+            BindType::Uniform { .. } | BindType::StorageBuffer { .. } => {
+                if !job.buffers.iter().any(|b| b.binding == binding.index) {
+                    return Err(ComputeJobError::MissingBuffer(binding.index));
+                }
+            }
+            BindType::StorageTexture { .. } => {
+                if !job.textures.iter().any(|t| t.binding == binding.index) {
+                    return Err(ComputeJobError::MissingTexture(binding.index));
+                }
+            }
+            _ => return Err(ComputeJobError::UnsupportedBinding(binding.index)),
+        }
+    }
+
+    let mut buffers = Vec::with_capacity(job.buffers.len());
+    let mut bind_group = BindGroup::build();
+    for binding in bindings.iter() {
+        let usage = match binding.bind_type {
+            BindType::Uniform { .. } => BufferUsage::UNIFORM,
+            BindType::StorageBuffer { .. } => BufferUsage::STORAGE,
+            _ => {
+                let texture = job
+                    .textures
+                    .iter()
+                    .find(|texture| texture.binding == binding.index)
+                    .unwrap();
+                bind_group = bind_group.add_texture_view(binding.index, texture.texture_view);
+                continue;
+            }
+        };
+        let data = job
+            .buffers
+            .iter()
+            .find(|buffer| buffer.binding == binding.index)
+            .unwrap();
+        let buffer = render_resources.create_buffer_with_data(
+            BufferInfo {
+                buffer_usage: usage | BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+                ..Default::default()
+            },
+            &data.data,
+        );
+        let read_back_buffer = if data.read_back {
+            Some(render_resources.create_buffer(BufferInfo {
+                size: data.data.len(),
+                buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+                ..Default::default()
+            }))
+        } else {
+            None
+        };
+        bind_group = bind_group.add_buffer(binding.index, buffer, 0..data.data.len() as u64);
+        buffers.push(PreparedBuffer {
+            buffer,
+            size: data.data.len(),
+            binding: binding.index,
+            read_back_buffer,
+        });
+    }
+
+    let compute = render_resources.create_shader_module(&shader);
+    let bind_group = layout
+        .get_bind_group(0)
+        .map(|descriptor| (descriptor.id, bind_group.finish()));
+    let pipeline = render_resources.create_compute_pipeline(&ComputePipelineDescriptor::new(
+        ComputeShaderStages { compute },
+        layout,
+    ));
+    Ok(PreparedJob {
+        pipeline,
+        bind_group,
+        buffers,
+        workgroups: job.workgroups,
+        repeat: job.repeat,
+    })
+}
+
+/// Dispatches the prepared [`ComputeJob`]s, and copies the buffers to read back.
+pub struct ComputeNode;
+
+impl Node for ComputeNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let jobs = world.get_resource::<ComputeJobs>().unwrap();
+        let mut state = jobs.state.lock();
+        let state = &mut *state;
+        for (id, job) in state.prepared.iter() {
+            render_context.begin_compute_pass(&mut |compute_pass| {
+                compute_pass.set_pipeline(job.pipeline);
+                if let Some((descriptor, bind_group)) = &job.bind_group {
+                    compute_pass.set_bind_group(0, *descriptor, bind_group.id, None);
+                }
+                let [x, y, z] = job.workgroups;
+                compute_pass.dispatch(x, y, z);
+            });
+            for buffer in job.buffers.iter() {
+                if let Some(read_back_buffer) = buffer.read_back_buffer {
+                    render_context.copy_buffer_to_buffer(
+                        buffer.buffer,
+                        0,
+                        read_back_buffer,
+                        0,
+                        buffer.size as u64,
+                    );
+                }
+            }
+            state.dispatched.push(*id);
+        }
+        Ok(())
+    }
+}
+
+/// Reads back the buffers of the jobs dispatched this frame, and frees the jobs that don't
+/// repeat. Runs in [`RenderStage::Cleanup`], after the frame was submitted.
+pub fn read_compute_results_system(jobs: Res<ComputeJobs>, render_resources: Res<RenderResources>) {
+    let mut state = jobs.state.lock();
+    let state = &mut *state;
+    for id in std::mem::take(&mut state.dispatched) {
+        let index = match state.prepared.iter().position(|(job, _)| *job == id) {
+            Some(index) => index,
+            None => continue,
+        };
+        let job = &state.prepared[index].1;
+        let mut results = ComputeResults::default();
+        for buffer in job.buffers.iter() {
+            if let Some(read_back_buffer) = buffer.read_back_buffer {
+                let data = RefCell::new(Vec::with_capacity(buffer.size));
+                render_resources.map_buffer(read_back_buffer, BufferMapMode::Read);
+                render_resources.read_mapped_buffer(
+                    read_back_buffer,
+                    0..buffer.size as u64,
+                    &|bytes, _| data.borrow_mut().extend_from_slice(bytes),
+                );
+                render_resources.unmap_buffer(read_back_buffer);
+                results.buffers.insert(buffer.binding, data.into_inner());
+            }
+        }
+        state.results.insert(id, Ok(results));
+        if !job.repeat {
+            state.prepared.remove(index).1.remove(&**render_resources);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComputeJob, ComputeResults};
+    use crate::{render_resource::TextureViewId, texture::StorageTextureAccess};
+
+    #[test]
+    fn read_back_buffers() {
+        let job = ComputeJob::from_glsl("", [1, 1, 1])
+            .with_buffer(0, &[1.0f32, 2.0])
+            .with_read_back_buffer(0, &[3u32, 4, 5]);
+        assert_eq!(job.buffers.len(), 1);
+        assert!(job.buffers[0].read_back);
+
+        let mut results = ComputeResults::default();
+        results.buffers.insert(0, job.buffers[0].data.clone());
+        assert_eq!(results.read::<u32>(0), Some(vec![3, 4, 5]));
+        assert_eq!(results.read::<u32>(1), None);
+    }
+
+    #[test]
+    fn storage_textures_replace_each_other() {
+        let (first, second) = (TextureViewId::new(), TextureViewId::new());
+        let job = ComputeJob::from_glsl("", [1, 1, 1])
+            .with_storage_texture(0, first, StorageTextureAccess::WriteOnly)
+            .with_storage_texture(1, first, StorageTextureAccess::ReadOnly)
+            .with_storage_texture(0, second, StorageTextureAccess::WriteOnly);
+        let textures = job
+            .textures
+            .iter()
+            .map(|texture| (texture.binding, texture.texture_view))
+            .collect::<Vec<_>>();
+        assert_eq!(textures, vec![(1, first), (0, second)]);
+    }
+}
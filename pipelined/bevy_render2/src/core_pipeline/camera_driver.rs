@@ -0,0 +1,295 @@
+use crate::{
+    camera::{CameraPlugin, ExtractedCamera, ExtractedCameraNames},
+    core_pipeline::{
+        self, Cameras3d, Transparent2dPhase, Transparent3dPhase, ViewDepthTexture, ViewHdrTexture,
+        ViewMainTexture, ViewOutputTexture,
+    },
+    render_graph::{Node, NodeRunError, RenderGraphContext, SlotValue},
+    render_phase::RenderPhase,
+    renderer::RenderContext,
+    view::ExtractedWindows,
+};
+use bevy_ecs::{entity::Entity, world::World};
+use bevy_window::WindowId;
+
+/// The sub-graph a camera view is drawn with by the [`CameraDriverNode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraGraph {
+    /// The [`draw_2d_graph`](core_pipeline::draw_2d_graph), for the 2d camera.
+    Draw2d,
+    /// The [`draw_3d_graph`](core_pipeline::draw_3d_graph), for the [`Cameras3d`].
+    Draw3d,
+}
+
+/// Returns the camera views of the render `world` in the order the [`CameraDriverNode`] draws
+/// them: sorted by [`Camera::priority`](crate::camera::Camera::priority), then the 2d camera
+/// before the [`Cameras3d`], which keep their order. A camera that is both the 2d camera and one
+/// of the [`Cameras3d`] is drawn with both graphs.
+pub fn camera_views(world: &World) -> Vec<(Entity, CameraGraph)> {
+    let extracted_cameras = world.get_resource::<ExtractedCameraNames>().unwrap();
+    let cameras_3d = world.get_resource::<Cameras3d>().unwrap();
+    let camera_2d = extracted_cameras
+        .entities
+        .get(CameraPlugin::CAMERA_2D)
+        .filter(|entity| {
+            world
+                .get::<RenderPhase<Transparent2dPhase>>(**entity)
+                .is_some()
+        })
+        .map(|entity| (*entity, CameraGraph::Draw2d));
+    let views_3d = cameras_3d
+        .names
+        .iter()
+        .filter_map(|name| extracted_cameras.entities.get(name))
+        .filter(|entity| {
+            world
+                .get::<RenderPhase<Transparent3dPhase>>(**entity)
+                .is_some()
+        })
+        .map(|entity| (*entity, CameraGraph::Draw3d));
+    let mut views = camera_2d.into_iter().chain(views_3d).collect::<Vec<_>>();
+    // stable, so views of the same priority keep the order above
+    views.sort_by_key(|(entity, _)| {
+        world
+            .get::<ExtractedCamera>(*entity)
+            .map_or(0, |camera| camera.priority)
+    });
+    views
+}
+
+/// Returns whether `view` drawn with `camera_graph` is the first of the [`camera_views`] drawn
+/// into the window of its camera, which clears the swap chain texture of the window. The views
+/// after it load what was drawn before, e.g. a HUD or minimap camera drawn on top of the scene.
+/// 3d views drawn into a [`ViewMainTexture`], [`ViewHdrTexture`] or [`ViewOutputTexture`] still
+/// end up in their window, written by the nodes that process their final image, so they count
+/// as drawn into it.
+pub fn view_clears_window(world: &World, view: Entity, camera_graph: CameraGraph) -> bool {
+    let window = match view_window(world, view) {
+        Some(window) => window,
+        None => return true,
+    };
+    camera_views(world)
+        .into_iter()
+        .find(|(entity, _)| view_window(world, *entity) == Some(window))
+        .map_or(true, |first_view| first_view == (view, camera_graph))
+}
+
+/// Returns whether the main pass of `view` drawn with `camera_graph` clears its color target:
+/// always for 3d views drawn into a [`ViewMainTexture`], [`ViewHdrTexture`] or
+/// [`ViewOutputTexture`] of their own, otherwise only if the view is the first drawn into its
+/// window, see [`view_clears_window`].
+pub fn view_clears_target(world: &World, view: Entity, camera_graph: CameraGraph) -> bool {
+    draws_into_own_texture(world, view, camera_graph)
+        || view_clears_window(world, view, camera_graph)
+}
+
+/// Returns whether the main pass of `view` draws into a texture of its own instead of the swap
+/// chain texture of its window.
+fn draws_into_own_texture(world: &World, view: Entity, camera_graph: CameraGraph) -> bool {
+    let view = world.entity(view);
+    camera_graph == CameraGraph::Draw3d
+        && (view.contains::<ViewMainTexture>()
+            || view.contains::<ViewHdrTexture>()
+            || view.contains::<ViewOutputTexture>())
+}
+
+/// Returns the window of the camera of `view`.
+fn view_window(world: &World, view: Entity) -> Option<WindowId> {
+    world
+        .entity(view)
+        .get::<ExtractedCamera>()
+        .map(|camera| camera.window_id)
+}
+
+/// Drives the drawing of every active camera: runs the
+/// [`draw_2d_graph`](core_pipeline::draw_2d_graph) or the
+/// [`draw_3d_graph`](core_pipeline::draw_3d_graph) for each of the [`camera_views`], in order,
+/// with the swap chain texture of the window of the camera. Only the first view drawing into a
+/// window clears it, see [`view_clears_window`]. Views whose window has no swap chain texture
+/// this frame are skipped. The final image of each 3d view is written to its target with the
+/// [`tonemapping_graph`](core_pipeline::tonemapping_graph) right after it is drawn.
+pub struct CameraDriverNode;
+
+impl Node for CameraDriverNode {
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let extracted_windows = world.get_resource::<ExtractedWindows>().unwrap();
+
+        for (entity, camera_graph) in camera_views(world) {
+            let extracted_camera = world.entity(entity).get::<ExtractedCamera>().unwrap();
+            let swap_chain_texture = extracted_windows
+                .get(&extracted_camera.window_id)
+                .and_then(|window| window.swap_chain_texture);
+            match camera_graph {
+                CameraGraph::Draw2d => {
+                    let swap_chain_texture = match swap_chain_texture {
+                        Some(texture) => texture,
+                        None => continue,
+                    };
+                    graph.run_sub_graph(
+                        core_pipeline::draw_2d_graph::NAME,
+                        vec![
+                            SlotValue::Entity(entity),
+                            SlotValue::TextureView(swap_chain_texture),
+                        ],
+                    )?;
+                }
+                CameraGraph::Draw3d => {
+                    let view = world.entity(entity);
+                    let depth_texture = view.get::<ViewDepthTexture>().unwrap();
+                    let target = match view
+                        .get::<ViewOutputTexture>()
+                        .map(|output_texture| output_texture.view)
+                        .or(swap_chain_texture)
+                    {
+                        Some(texture) => texture,
+                        None => continue,
+                    };
+                    let output_target = view
+                        .get::<ViewHdrTexture>()
+                        .map_or(target, |hdr_texture| hdr_texture.view);
+                    let render_target = view
+                        .get::<ViewMainTexture>()
+                        .map_or(output_target, |main_texture| main_texture.view);
+                    graph.run_sub_graph(
+                        core_pipeline::draw_3d_graph::NAME,
+                        vec![
+                            SlotValue::Entity(entity),
+                            SlotValue::TextureView(render_target),
+                            SlotValue::TextureView(depth_texture.view),
+                            SlotValue::TextureView(output_target),
+                        ],
+                    )?;
+                    graph.run_sub_graph(
+                        core_pipeline::tonemapping_graph::NAME,
+                        vec![SlotValue::Entity(entity), SlotValue::TextureView(target)],
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{camera_views, view_clears_target, view_clears_window, CameraGraph};
+    use crate::{
+        camera::{CameraPlugin, ExtractedCamera, ExtractedCameraNames},
+        core_pipeline::{
+            Cameras3d, Transparent2dPhase, Transparent3dPhase, ViewMainTexture, ViewOutputTexture,
+        },
+        render_phase::RenderPhase,
+        render_resource::{TextureId, TextureViewId},
+    };
+    use bevy_ecs::world::World;
+    use bevy_window::WindowId;
+
+    #[test]
+    fn views_are_sorted_by_priority() {
+        let mut world = World::default();
+        let mut names = ExtractedCameraNames::default();
+        let mut spawn_camera = |name: &str, priority: isize| {
+            let entity = world
+                .spawn()
+                .insert(ExtractedCamera {
+                    window_id: WindowId::primary(),
+                    name: Some(name.to_string()),
+                    priority,
+                })
+                .id();
+            names.entities.insert(name.to_string(), entity);
+            entity
+        };
+        let camera_2d = spawn_camera(CameraPlugin::CAMERA_2D, 0);
+        let reflection = spawn_camera("reflection", 0);
+        let camera_3d = spawn_camera(CameraPlugin::CAMERA_3D, -1);
+        let inactive = spawn_camera("inactive", 0);
+        world
+            .entity_mut(camera_2d)
+            .insert(RenderPhase::<Transparent2dPhase>::default());
+        for entity in [reflection, camera_3d, inactive].iter() {
+            world
+                .entity_mut(*entity)
+                .insert(RenderPhase::<Transparent3dPhase>::default());
+        }
+        world.insert_resource(names);
+        world.insert_resource(Cameras3d {
+            names: vec![
+                "reflection".to_string(),
+                CameraPlugin::CAMERA_3D.to_string(),
+            ],
+        });
+
+        assert_eq!(
+            camera_views(&world),
+            vec![
+                (camera_3d, CameraGraph::Draw3d),
+                (camera_2d, CameraGraph::Draw2d),
+                (reflection, CameraGraph::Draw3d),
+            ]
+        );
+        // the 2d camera draws over the 3d camera, the reflection into a texture of its own,
+        // which is then written over both
+        assert!(view_clears_target(&world, camera_3d, CameraGraph::Draw3d));
+        assert!(!view_clears_target(&world, camera_2d, CameraGraph::Draw2d));
+        world.entity_mut(reflection).insert(ViewOutputTexture {
+            texture: TextureId::new(),
+            view: TextureViewId::new(),
+        });
+        assert!(view_clears_target(&world, reflection, CameraGraph::Draw3d));
+        assert!(!view_clears_window(&world, reflection, CameraGraph::Draw3d));
+    }
+
+    #[test]
+    fn views_with_a_main_texture_are_drawn_into_their_window() {
+        let mut world = World::default();
+        let mut names = ExtractedCameraNames::default();
+        let camera_3d = world
+            .spawn()
+            .insert_bundle((
+                ExtractedCamera {
+                    window_id: WindowId::primary(),
+                    name: Some(CameraPlugin::CAMERA_3D.to_string()),
+                    priority: 0,
+                },
+                RenderPhase::<Transparent3dPhase>::default(),
+                ViewMainTexture {
+                    texture: TextureId::new(),
+                    view: TextureViewId::new(),
+                },
+            ))
+            .id();
+        let hud = world
+            .spawn()
+            .insert_bundle((
+                ExtractedCamera {
+                    window_id: WindowId::primary(),
+                    name: Some(CameraPlugin::CAMERA_2D.to_string()),
+                    priority: 1,
+                },
+                RenderPhase::<Transparent2dPhase>::default(),
+            ))
+            .id();
+        names
+            .entities
+            .insert(CameraPlugin::CAMERA_3D.to_string(), camera_3d);
+        names
+            .entities
+            .insert(CameraPlugin::CAMERA_2D.to_string(), hud);
+        world.insert_resource(names);
+        world.insert_resource(Cameras3d::default());
+
+        // the 3d view clears its main texture and the window it is written to, the hud is drawn
+        // over it
+        assert!(view_clears_target(&world, camera_3d, CameraGraph::Draw3d));
+        assert!(view_clears_window(&world, camera_3d, CameraGraph::Draw3d));
+        assert!(!view_clears_target(&world, hud, CameraGraph::Draw2d));
+        assert!(!view_clears_window(&world, hud, CameraGraph::Draw2d));
+    }
+}
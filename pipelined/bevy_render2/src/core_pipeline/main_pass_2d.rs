@@ -1,10 +1,6 @@
 use crate::{
-    color::Color,
-    core_pipeline::Transparent2dPhase,
-    pass::{
-        LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
-        TextureAttachment,
-    },
+    core_pipeline::{self, Background2dPhase, CameraGraph, Transparent2dPhase},
+    pass::{Operations, PassDescriptor, RenderPass, RenderPassColorAttachment, TextureAttachment},
     render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
     render_phase::{DrawFunctions, RenderPhase, TrackedRenderPass},
     renderer::RenderContext,
@@ -13,7 +9,11 @@ use crate::{
 use bevy_ecs::prelude::*;
 
 pub struct MainPass2dNode {
-    query: QueryState<&'static RenderPhase<Transparent2dPhase>, With<ExtractedView>>,
+    query: QueryState<(
+        &'static RenderPhase<Background2dPhase>,
+        &'static RenderPhase<Transparent2dPhase>,
+        &'static ExtractedView,
+    )>,
 }
 
 impl MainPass2dNode {
@@ -46,12 +46,17 @@ impl Node for MainPass2dNode {
         world: &World,
     ) -> Result<(), NodeRunError> {
         let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
         let pass_descriptor = PassDescriptor {
             color_attachments: vec![RenderPassColorAttachment {
                 attachment: TextureAttachment::Id(color_attachment_texture),
                 resolve_target: None,
                 ops: Operations {
-                    load: LoadOp::Clear(Color::rgb(0.4, 0.4, 0.4)),
+                    load: core_pipeline::view_color_load_op(
+                        world,
+                        view_entity,
+                        CameraGraph::Draw2d,
+                    ),
                     store: true,
                 },
             }],
@@ -59,10 +64,9 @@ impl Node for MainPass2dNode {
             sample_count: 1,
         };
 
-        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
         let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
 
-        let transparent_phase = self
+        let (background_phase, transparent_phase, view) = self
             .query
             .get_manual(world, view_entity)
             .expect("view entity should exist");
@@ -71,8 +75,20 @@ impl Node for MainPass2dNode {
             &pass_descriptor,
             &mut |render_pass: &mut dyn RenderPass| {
                 let mut draw_functions = draw_functions.write();
-                let mut tracked_pass = TrackedRenderPass::new(render_pass);
-                for drawable in transparent_phase.drawn_things.iter() {
+                let mut tracked_pass =
+                    TrackedRenderPass::new(render_pass).with_target_size(view.width, view.height);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                // the background is drawn first, so the scene covers it
+                let drawables = background_phase
+                    .drawn_things
+                    .iter()
+                    .chain(transparent_phase.drawn_things.iter());
+                for drawable in drawables {
+                    if !tracked_pass.set_clip_rect(drawable.clip) {
+                        continue;
+                    }
                     let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
                     draw_function.draw(
                         world,
@@ -1,6 +1,8 @@
 use crate::{
-    color::Color,
-    core_pipeline::Transparent3dPhase,
+    core_pipeline::{
+        self, AlphaMask3dPhase, Background3dPhase, CameraGraph, CorePipelineSettings, DepthMode,
+        Opaque3dPhase, Overlay3dPhase, Transparent3dPhase,
+    },
     pass::{
         LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
         RenderPassDepthStencilAttachment, TextureAttachment,
@@ -13,7 +15,14 @@ use crate::{
 use bevy_ecs::prelude::*;
 
 pub struct MainPass3dNode {
-    query: QueryState<&'static RenderPhase<Transparent3dPhase>, With<ExtractedView>>,
+    query: QueryState<(
+        &'static RenderPhase<Background3dPhase>,
+        &'static RenderPhase<Opaque3dPhase>,
+        &'static RenderPhase<AlphaMask3dPhase>,
+        &'static RenderPhase<Transparent3dPhase>,
+        &'static RenderPhase<Overlay3dPhase>,
+        &'static ExtractedView,
+    )>,
 }
 
 impl MainPass3dNode {
@@ -49,30 +58,52 @@ impl Node for MainPass3dNode {
     ) -> Result<(), NodeRunError> {
         let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
         let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
         let pass_descriptor = PassDescriptor {
             color_attachments: vec![RenderPassColorAttachment {
                 attachment: TextureAttachment::Id(color_attachment_texture),
                 resolve_target: None,
                 ops: Operations {
-                    load: LoadOp::Clear(Color::rgb(0.4, 0.4, 0.4)),
+                    load: core_pipeline::view_color_load_op(
+                        world,
+                        view_entity,
+                        CameraGraph::Draw3d,
+                    ),
                     store: true,
                 },
             }],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 attachment: TextureAttachment::Id(depth_texture),
                 depth_ops: Some(Operations {
-                    load: LoadOp::Clear(1.0),
+                    load: LoadOp::Clear(depth_mode.clear_value()),
                     store: true,
                 }),
-                stencil_ops: None,
+                stencil_ops: settings.stencil_ops().map(|ops| Operations {
+                    load: LoadOp::Clear(0),
+                    ..ops
+                }),
             }),
             sample_count: 1,
         };
 
-        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
         let draw_functions = world.get_resource::<DrawFunctions>().unwrap();
 
-        let transparent_phase = self
+        let (
+            background_phase,
+            opaque_phase,
+            alpha_mask_phase,
+            transparent_phase,
+            overlay_phase,
+            view,
+        ) = self
             .query
             .get_manual(world, view_entity)
             .expect("view entity should exist");
@@ -81,8 +112,24 @@ impl Node for MainPass3dNode {
             &pass_descriptor,
             &mut |render_pass: &mut dyn RenderPass| {
                 let mut draw_functions = draw_functions.write();
-                let mut tracked_pass = TrackedRenderPass::new(render_pass);
-                for drawable in transparent_phase.drawn_things.iter() {
+                let mut tracked_pass =
+                    TrackedRenderPass::new(render_pass).with_target_size(view.width, view.height);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                // the background is drawn first, so the scene covers it, and blended draws after
+                // everything they may blend over
+                let drawables = background_phase
+                    .drawn_things
+                    .iter()
+                    .chain(opaque_phase.drawn_things.iter())
+                    .chain(alpha_mask_phase.drawn_things.iter())
+                    .chain(transparent_phase.drawn_things.iter())
+                    .chain(overlay_phase.drawn_things.iter());
+                for drawable in drawables {
+                    if !tracked_pass.set_clip_rect(drawable.clip) {
+                        continue;
+                    }
                     let draw_function = draw_functions.get_mut(drawable.draw_function).unwrap();
                     draw_function.draw(
                         world,
@@ -1,26 +1,36 @@
+mod camera_driver;
 mod main_pass_2d;
 mod main_pass_3d;
-mod main_pass_driver;
+mod overlay;
+mod tonemapping;
 
+pub use camera_driver::*;
 pub use main_pass_2d::*;
 pub use main_pass_3d::*;
-pub use main_pass_driver::*;
+pub use overlay::*;
+pub use tonemapping::*;
 
 use crate::{
-    camera::{ActiveCameras, CameraPlugin},
+    camera::{ActiveCameras, CameraPlugin, ExtractedCamera},
+    color::Color,
+    pass::{LoadOp, Operations},
+    pipeline::CompareFunction,
     render_command::RenderCommandPlugin,
-    render_graph::{EmptyNode, RenderGraph, SlotInfo, SlotType},
-    render_phase::{sort_phase_system, RenderPhase},
+    render_graph::{EmptyNode, RenderGraph, RenderGraphNodeTypes, SlotInfo, SlotType},
+    render_phase::{AddRenderPhase, RenderPhase},
     render_resource::{TextureId, TextureViewId},
-    renderer::RenderResources,
+    renderer::{RenderInitAppExt, RenderResources},
     texture::{
         Extent3d, TextureCache, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
     },
-    view::{ExtractedView, ViewPlugin},
-    RenderStage,
+    view::{ExtractedView, ExtractedWindows, ViewPlugin},
+    RenderApp, RenderStage,
 };
 use bevy_app::{App, Plugin};
-use bevy_ecs::prelude::*;
+use bevy_ecs::{prelude::*, reflect::ReflectComponent};
+use bevy_math::{Mat4, Vec3, Vec4};
+use bevy_reflect::Reflect;
+use bevy_utils::tracing::warn;
 
 // Plugins that contribute to the RenderGraph should use the following label conventions:
 // 1. Graph modules should have a NAME, input module, and node module (where relevant)
@@ -29,7 +39,10 @@ use bevy_ecs::prelude::*;
 
 pub mod node {
     pub const MAIN_PASS_DEPENDENCIES: &'static str = "main_pass_dependencies";
+    /// The [`CameraDriverNode`](super::CameraDriverNode), which draws the views of the cameras.
     pub const MAIN_PASS_DRIVER: &'static str = "main_pass_driver";
+    /// Draws the [`Overlays`](super::Overlays) after the main passes.
+    pub const OVERLAY: &'static str = "overlay";
     pub const VIEW: &'static str = "view";
 }
 
@@ -48,37 +61,100 @@ pub mod draw_3d_graph {
     pub const NAME: &'static str = "draw_3d";
     pub mod input {
         pub const VIEW_ENTITY: &'static str = "view_entity";
+        /// The texture the view is drawn into: its
+        /// [`ViewMainTexture`](crate::core_pipeline::ViewMainTexture) if it has one, otherwise
+        /// the [`OUTPUT_TARGET`].
         pub const RENDER_TARGET: &'static str = "render_target";
         pub const DEPTH: &'static str = "depth";
+        /// The texture the final image of the view is written to: its
+        /// [`ViewHdrTexture`](crate::core_pipeline::ViewHdrTexture) if it has one, which is
+        /// tonemapped to the target of its camera with the
+        /// [`tonemapping_graph`](crate::core_pipeline::tonemapping_graph), otherwise its
+        /// [`ViewOutputTexture`](crate::core_pipeline::ViewOutputTexture) if it has one, or the
+        /// swap chain texture of its window.
+        pub const OUTPUT_TARGET: &'static str = "output_target";
     }
     pub mod node {
         pub const MAIN_PASS: &'static str = "main_pass";
     }
 }
 
+/// Writes the final image of a 3d view to the target of its camera, run by the
+/// [`CameraDriverNode`] right after the [`draw_3d_graph`] of every 3d view. It tonemaps the
+/// [`ViewHdrTexture`] of views that have one. Nodes that process the final image of a view, such
+/// as color grading, are added after its [`TONEMAPPING`](tonemapping_graph::node::TONEMAPPING)
+/// node, so that they run in the order the views are drawn in.
+pub mod tonemapping_graph {
+    pub const NAME: &'static str = "tonemapping";
+    pub mod input {
+        pub const VIEW_ENTITY: &'static str = "view_entity";
+        /// The [`ViewOutputTexture`](crate::core_pipeline::ViewOutputTexture) of the view if it
+        /// has one, otherwise the swap chain texture of its window.
+        pub const TARGET: &'static str = "target";
+    }
+    pub mod node {
+        pub const TONEMAPPING: &'static str = "tonemapping";
+    }
+}
+
 #[derive(Default)]
 pub struct CorePipelinePlugin;
 
 impl Plugin for CorePipelinePlugin {
     fn build(&self, app: &mut App) {
-        let render_app = app.sub_app_mut(0);
+        let depth_mode = app
+            .world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = app
+            .world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default()
+            .validated();
+        app.insert_resource(depth_mode)
+            .insert_resource(settings)
+            .init_resource::<Cameras3d>()
+            .init_resource::<SortMode2d>()
+            .register_type::<ZIndex>()
+            .register_type::<YSortOffset>();
+
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
+            .insert_resource(depth_mode)
+            .insert_resource(settings)
+            .init_resource::<Overlays>()
+            .init_render_resource::<TonemappingShaders>()
             .add_system_to_stage(
                 RenderStage::Extract,
                 extract_core_pipeline_camera_phases.system(),
             )
             .add_system_to_stage(RenderStage::Prepare, prepare_core_views_system.system())
-            .add_system_to_stage(
-                RenderStage::PhaseSort,
-                sort_phase_system::<Transparent2dPhase>.system(),
-            )
-            .add_system_to_stage(
-                RenderStage::PhaseSort,
-                sort_phase_system::<Transparent3dPhase>.system(),
-            );
+            .add_system_to_stage(RenderStage::Queue, queue_tonemapping.system())
+            .add_render_phase::<Background2dPhase>()
+            .add_render_phase::<Transparent2dPhase>()
+            .add_render_phase::<Background3dPhase>()
+            .add_render_phase::<Opaque3dPhase>()
+            .add_render_phase::<AlphaMask3dPhase>()
+            .add_render_phase::<Transparent3dPhase>()
+            .add_render_phase::<Overlay3dPhase>();
+
+        let mut node_types = render_app
+            .world
+            .get_resource_mut::<RenderGraphNodeTypes>()
+            .unwrap();
+        node_types.register("MainPass2dNode", MainPass2dNode::new);
+        node_types.register("MainPass3dNode", MainPass3dNode::new);
+        node_types.register("CameraDriverNode", |_| CameraDriverNode);
+        // the name of the node before it drove every camera, used by older graph descriptors
+        node_types.register("MainPassDriverNode", |_| CameraDriverNode);
+        node_types.register("OverlayNode", |_| OverlayNode);
+        node_types.register("TonemappingNode", TonemappingNode::new);
 
         let pass_node_2d = MainPass2dNode::new(&mut render_app.world);
         let pass_node_3d = MainPass3dNode::new(&mut render_app.world);
+        let tonemapping_node = TonemappingNode::new(&mut render_app.world);
         let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
 
         let mut draw_2d_graph = RenderGraph::default();
@@ -111,6 +187,7 @@ impl Plugin for CorePipelinePlugin {
             SlotInfo::new(draw_3d_graph::input::VIEW_ENTITY, SlotType::Entity),
             SlotInfo::new(draw_3d_graph::input::RENDER_TARGET, SlotType::TextureView),
             SlotInfo::new(draw_3d_graph::input::DEPTH, SlotType::TextureView),
+            SlotInfo::new(draw_3d_graph::input::OUTPUT_TARGET, SlotType::TextureView),
         ]);
         draw_3d_graph
             .add_slot_edge(
@@ -138,8 +215,33 @@ impl Plugin for CorePipelinePlugin {
             .unwrap();
         graph.add_sub_graph(draw_3d_graph::NAME, draw_3d_graph);
 
+        let mut tonemapping_graph = RenderGraph::default();
+        tonemapping_graph.add_node(tonemapping_graph::node::TONEMAPPING, tonemapping_node);
+        let input_node_id = tonemapping_graph.set_input(vec![
+            SlotInfo::new(tonemapping_graph::input::VIEW_ENTITY, SlotType::Entity),
+            SlotInfo::new(tonemapping_graph::input::TARGET, SlotType::TextureView),
+        ]);
+        tonemapping_graph
+            .add_slot_edge(
+                input_node_id,
+                tonemapping_graph::input::VIEW_ENTITY,
+                tonemapping_graph::node::TONEMAPPING,
+                TonemappingNode::IN_VIEW,
+            )
+            .unwrap();
+        tonemapping_graph
+            .add_slot_edge(
+                input_node_id,
+                tonemapping_graph::input::TARGET,
+                tonemapping_graph::node::TONEMAPPING,
+                TonemappingNode::IN_TARGET,
+            )
+            .unwrap();
+        graph.add_sub_graph(tonemapping_graph::NAME, tonemapping_graph);
+
         graph.add_node(node::MAIN_PASS_DEPENDENCIES, EmptyNode);
-        graph.add_node(node::MAIN_PASS_DRIVER, MainPassDriverNode);
+        graph.add_node(node::MAIN_PASS_DRIVER, CameraDriverNode);
+        graph.add_node(node::OVERLAY, OverlayNode);
         graph
             .add_node_edge(ViewPlugin::VIEW_NODE, node::MAIN_PASS_DEPENDENCIES)
             .unwrap();
@@ -152,41 +254,347 @@ impl Plugin for CorePipelinePlugin {
         graph
             .add_node_edge(node::MAIN_PASS_DEPENDENCIES, node::MAIN_PASS_DRIVER)
             .unwrap();
+        graph
+            .add_node_edge(node::MAIN_PASS_DRIVER, node::OVERLAY)
+            .unwrap();
     }
 }
 
+/// Opaque draws of 3d views, drawn by the main pass before the [`AlphaMask3dPhase`].
+pub struct Opaque3dPhase;
+
+/// Draws of 3d views that discard the fragments below an alpha cutoff, drawn by the main pass
+/// after the [`Opaque3dPhase`] and before the [`Transparent3dPhase`].
+pub struct AlphaMask3dPhase;
+
+/// Blended draws of 3d views, drawn by the main pass after the opaque and alpha masked phases so
+/// they blend over them.
 pub struct Transparent3dPhase;
+
+/// Draws of 3d views that are drawn by the main pass after every other phase, like gizmos or
+/// labels placed in the world. They are still depth tested, unless their pipeline disables it.
+pub struct Overlay3dPhase;
+
+/// Draws of the 2d view, drawn by its main pass after the [`Background2dPhase`]. It is shared by
+/// sprites and other 2d renderers, like particles or tilemaps, so their drawables are interleaved
+/// by sort key, see [`ZIndex`].
 pub struct Transparent2dPhase;
 
+/// Draws of 3d views that fill the background, like gradients, video backdrops or raymarched
+/// skies. The main pass draws them after clearing the view and before the [`Opaque3dPhase`], so
+/// they are covered by the scene. Add [`Drawable`]s to the phase of a
+/// view in [`RenderStage::Queue`], with a [`Draw`] function registered in the [`DrawFunctions`].
+///
+/// The depth buffer is bound, but their pipelines shouldn't write to it, or only the
+/// [`DepthMode::clear_value`], to keep the scene in front of them.
+///
+/// [`Drawable`]: crate::render_phase::Drawable
+/// [`Draw`]: crate::render_phase::Draw
+/// [`DrawFunctions`]: crate::render_phase::DrawFunctions
+pub struct Background3dPhase;
+
+/// Draws of the 2d view that fill the background, drawn by its main pass before the
+/// [`Transparent2dPhase`], see [`Background3dPhase`].
+pub struct Background2dPhase;
+
+/// The layer a 2d entity is drawn in, in the [`Transparent2dPhase`]. Entities with a higher
+/// z-index are drawn on top of those with a lower one, whatever their translation, which only
+/// orders entities with the same z-index. Entities without a z-index are in layer 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+#[reflect(Component, PartialEq)]
+pub struct ZIndex(pub i32);
+
+impl ZIndex {
+    /// Returns the [`Drawable::sort_key`](crate::render_phase::Drawable::sort_key) of a 2d
+    /// entity in this layer, at `depth`: entities with a higher depth are drawn on top. See
+    /// [`SortMode2d::depth`].
+    ///
+    /// On targets with 32 bit pointers the key only has room for the z-index, so entities in
+    /// the same layer are drawn in the order they were queued.
+    pub fn sort_key(&self, depth: f32) -> usize {
+        // flipping the sign bit orders signed integers as unsigned ones
+        let layer = (self.0 as u32 ^ 0x8000_0000) as u64;
+        let bits = depth.to_bits();
+        // negative floats sort backwards when their bits are compared
+        let depth = if bits & 0x8000_0000 != 0 {
+            !bits
+        } else {
+            bits | 0x8000_0000
+        };
+        if cfg!(target_pointer_width = "64") {
+            ((layer << 32) | depth as u64) as usize
+        } else {
+            layer as usize
+        }
+    }
+}
+
+/// How 2d entities with the same [`ZIndex`] are ordered in the [`Transparent2dPhase`]. A resource
+/// of the app world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode2d {
+    /// Entities with a higher translation z are drawn on top.
+    Z,
+    /// Entities lower on the screen, i.e. with a lower translation y, are drawn on top, so
+    /// characters and props overlap correctly in top-down and isometric games. The y an entity
+    /// is sorted at can be moved with a [`YSortOffset`], e.g. to the feet of a character.
+    Y,
+}
+
+impl Default for SortMode2d {
+    fn default() -> Self {
+        SortMode2d::Z
+    }
+}
+
+impl SortMode2d {
+    /// Returns the depth to pass to [`ZIndex::sort_key`] for an entity at `translation`.
+    pub fn depth(&self, translation: Vec3, y_offset: Option<&YSortOffset>) -> f32 {
+        match self {
+            SortMode2d::Z => translation.z,
+            SortMode2d::Y => -(translation.y + y_offset.map_or(0.0, |offset| offset.0)),
+        }
+    }
+}
+
+/// Added to the translation y of a 2d entity when it is sorted with [`SortMode2d::Y`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+#[reflect(Component, PartialEq)]
+pub struct YSortOffset(pub f32);
+
+/// How depth is stored in the depth buffer of 3d views. Insert it before adding the
+/// [`CorePipelinePlugin`]: pipelines read it when they are created, so it can't be changed later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Depth goes from 0.0 at the near plane to 1.0 at the far plane.
+    Standard,
+    /// Depth goes from 1.0 at the near plane towards 0.0 at infinity, and perspective projections
+    /// have no far plane. This spreads the precision of floating point depth much more evenly,
+    /// which avoids z-fighting in large scenes.
+    ReverseZ,
+}
+
+impl Default for DepthMode {
+    fn default() -> Self {
+        DepthMode::Standard
+    }
+}
+
+impl DepthMode {
+    /// The value the depth buffer is cleared to, i.e. the depth of the farthest possible point.
+    pub fn clear_value(&self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::ReverseZ => 0.0,
+        }
+    }
+
+    /// The depth of the near plane.
+    pub fn near_value(&self) -> f32 {
+        match self {
+            DepthMode::Standard => 0.0,
+            DepthMode::ReverseZ => 1.0,
+        }
+    }
+
+    /// Converts a depth compare function written for [`DepthMode::Standard`] to this mode.
+    pub fn compare(&self, compare: CompareFunction) -> CompareFunction {
+        match (self, compare) {
+            (DepthMode::Standard, compare) => compare,
+            (DepthMode::ReverseZ, CompareFunction::Less) => CompareFunction::Greater,
+            (DepthMode::ReverseZ, CompareFunction::LessEqual) => CompareFunction::GreaterEqual,
+            (DepthMode::ReverseZ, CompareFunction::Greater) => CompareFunction::Less,
+            (DepthMode::ReverseZ, CompareFunction::GreaterEqual) => CompareFunction::LessEqual,
+            (DepthMode::ReverseZ, compare) => compare,
+        }
+    }
+
+    /// Converts a projection matrix with a [`DepthMode::Standard`] depth range to this mode.
+    pub fn adjust_projection(&self, projection: Mat4) -> Mat4 {
+        match self {
+            DepthMode::Standard => projection,
+            // z' = w - z
+            DepthMode::ReverseZ => {
+                Mat4::from_cols(
+                    Vec4::X,
+                    Vec4::Y,
+                    Vec4::new(0.0, 0.0, -1.0, 0.0),
+                    Vec4::new(0.0, 0.0, 1.0, 1.0),
+                ) * projection
+            }
+        }
+    }
+}
+
+/// Settings of the textures of 3d views, which the pipelines drawing into them are created with.
+/// A resource of both worlds, validated when the [`CorePipelinePlugin`] is built. Like the
+/// [`DepthMode`], it has to be inserted before adding the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorePipelineSettings {
+    /// Draws 3d views into a [`ViewHdrTexture`] of [`HDR_TEXTURE_FORMAT`], which keeps the
+    /// exposed luminance of the scene, and tonemaps it to the target of their camera after their
+    /// [`draw_3d_graph`]. Otherwise the main pass tonemaps each draw into the
+    /// [`TextureFormat::default`] of the target. Pipelines drawing into 3d views use the
+    /// [`CorePipelineSettings::main_texture_format`], and the pbr shaders leave the tonemapping
+    /// out.
+    pub hdr: bool,
+    /// The format of the [`ViewDepthTexture`], which pipelines drawing into 3d views use in their
+    /// depth stencil state. [`TextureFormat::Depth24PlusStencil8`] adds a stencil buffer, which
+    /// the main pass clears to 0 and later passes keep, and [`TextureFormat::Depth24Plus`] saves
+    /// bandwidth on mobile GPUs that store it in 24 bits. Other formats are replaced with
+    /// [`TextureFormat::Depth32Float`], the default.
+    pub depth_format: TextureFormat,
+}
+
+impl Default for CorePipelineSettings {
+    fn default() -> Self {
+        CorePipelineSettings {
+            hdr: false,
+            depth_format: TextureFormat::Depth32Float,
+        }
+    }
+}
+
+impl CorePipelineSettings {
+    /// The format of the textures the main passes of 3d views draw into, which pipelines
+    /// drawing into the [`draw_3d_graph::input::RENDER_TARGET`] or the
+    /// [`draw_3d_graph::input::OUTPUT_TARGET`] use for their color target.
+    pub fn main_texture_format(&self) -> TextureFormat {
+        if self.hdr {
+            HDR_TEXTURE_FORMAT
+        } else {
+            TextureFormat::default()
+        }
+    }
+
+    /// Returns whether the [`CorePipelineSettings::depth_format`] has a stencil aspect.
+    pub fn has_stencil(&self) -> bool {
+        self.depth_format == TextureFormat::Depth24PlusStencil8
+    }
+
+    /// The stencil operations of passes that draw into the [`ViewDepthTexture`] after the main
+    /// pass, which keep its stencil values.
+    pub fn stencil_ops(&self) -> Option<Operations<u32>> {
+        if self.has_stencil() {
+            Some(Operations {
+                load: LoadOp::Load,
+                store: true,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns these settings with a depth format the render device supports. wgpu supports all
+    /// depth formats as render attachments on every device, so only color formats are replaced.
+    pub fn validated(self) -> Self {
+        match self.depth_format {
+            TextureFormat::Depth32Float
+            | TextureFormat::Depth24Plus
+            | TextureFormat::Depth24PlusStencil8 => self,
+            format => {
+                let depth_format = CorePipelineSettings::default().depth_format;
+                warn!(
+                    "{:?} can't be used as the depth format of the core pipeline, using {:?} \
+                    instead.",
+                    format, depth_format
+                );
+                CorePipelineSettings {
+                    depth_format,
+                    ..self
+                }
+            }
+        }
+    }
+}
+
 pub struct ViewDepthTexture {
     pub texture: TextureId,
     pub view: TextureViewId,
 }
 
+/// The format of the [`ViewHdrTexture`]s, see [`CorePipelineSettings::hdr`].
+pub const HDR_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The texture a 3d view is drawn into when [`CorePipelineSettings::hdr`] is set, which is the
+/// [`draw_3d_graph::input::OUTPUT_TARGET`] of the view. It is tonemapped to the target of the
+/// camera by the [`TonemappingNode`]. Created by the [`CorePipelinePlugin`], with the size of the
+/// view and [`TextureUsage::SAMPLED`] usage.
+pub struct ViewHdrTexture {
+    pub texture: TextureId,
+    pub view: TextureViewId,
+}
+
+/// Draws a 3d view into this texture instead of its output target, for post processing nodes
+/// that read the drawn image and write the final one to
+/// [`draw_3d_graph::input::OUTPUT_TARGET`]. Its format must be the
+/// [`CorePipelineSettings::main_texture_format`], which the pipelines of the main pass draw to.
+pub struct ViewMainTexture {
+    pub texture: TextureId,
+    pub view: TextureViewId,
+}
+
+/// Writes the final image of a 3d view into this texture instead of the swap chain texture of its
+/// window, for nodes that process it after the [`draw_3d_graph`] and write the result to the
+/// window. Its format must be [`TextureFormat::default`], which the [`TonemappingNode`] writes
+/// to views with a [`ViewHdrTexture`] and the main pass to the others.
+pub struct ViewOutputTexture {
+    pub texture: TextureId,
+    pub view: TextureViewId,
+}
+
+/// The names of the [`ActiveCameras`] that are rendered with the [`draw_3d_graph`], in order
+/// among the cameras of the same [`Camera::priority`](crate::camera::Camera::priority). Defaults
+/// to [`CameraPlugin::CAMERA_3D`].
+///
+/// Add cameras to render several 3D views per frame, each to the target of its camera, such as
+/// one view per eye for XR. Each view is rendered in its own passes, as wgpu doesn't support
+/// multiview rendering yet.
+#[derive(Clone, Debug)]
+pub struct Cameras3d {
+    pub names: Vec<String>,
+}
+
+impl Default for Cameras3d {
+    fn default() -> Self {
+        Cameras3d {
+            names: vec![CameraPlugin::CAMERA_3D.to_string()],
+        }
+    }
+}
+
 pub fn extract_core_pipeline_camera_phases(
     mut commands: Commands,
     active_cameras: Res<ActiveCameras>,
+    cameras_3d: Res<Cameras3d>,
 ) {
     if let Some(camera_2d) = active_cameras.get(CameraPlugin::CAMERA_2D) {
         if let Some(entity) = camera_2d.entity {
-            commands
-                .get_or_spawn(entity)
-                .insert(RenderPhase::<Transparent2dPhase>::default());
+            commands.get_or_spawn(entity).insert_bundle((
+                RenderPhase::<Background2dPhase>::default(),
+                RenderPhase::<Transparent2dPhase>::default(),
+            ));
         }
     }
-    if let Some(camera_3d) = active_cameras.get(CameraPlugin::CAMERA_3D) {
-        if let Some(entity) = camera_3d.entity {
-            commands
-                .get_or_spawn(entity)
-                .insert(RenderPhase::<Transparent3dPhase>::default());
+    for name in cameras_3d.names.iter() {
+        if let Some(entity) = active_cameras.get(name).and_then(|camera| camera.entity) {
+            commands.get_or_spawn(entity).insert_bundle((
+                RenderPhase::<Background3dPhase>::default(),
+                RenderPhase::<Opaque3dPhase>::default(),
+                RenderPhase::<AlphaMask3dPhase>::default(),
+                RenderPhase::<Transparent3dPhase>::default(),
+                RenderPhase::<Overlay3dPhase>::default(),
+            ));
         }
     }
+    commands.insert_resource(cameras_3d.clone());
 }
 
 pub fn prepare_core_views_system(
     mut commands: Commands,
     mut texture_cache: ResMut<TextureCache>,
     render_resources: Res<RenderResources>,
+    settings: Res<CorePipelineSettings>,
     views: Query<(Entity, &ExtractedView), With<RenderPhase<Transparent3dPhase>>>,
 ) {
     for (entity, view) in views.iter() {
@@ -201,8 +609,7 @@ pub fn prepare_core_views_system(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Depth32Float, /* PERF: vulkan docs recommend using 24
-                                                      * bit depth for better performance */
+                format: settings.depth_format,
                 usage: TextureUsage::RENDER_ATTACHMENT,
             },
         );
@@ -210,5 +617,121 @@ pub fn prepare_core_views_system(
             texture: cached_texture.texture,
             view: cached_texture.default_view,
         });
+        if settings.hdr {
+            let cached_texture = texture_cache.get(
+                &render_resources,
+                TextureDescriptor {
+                    size: Extent3d::new(view.width, view.height, 1),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: HDR_TEXTURE_FORMAT,
+                    usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+                },
+            );
+            commands.entity(entity).insert(ViewHdrTexture {
+                texture: cached_texture.texture,
+                view: cached_texture.default_view,
+            });
+        }
+    }
+}
+
+/// The load operation of the color target of the main pass of `view`: a clear to the clear color
+/// of the window its camera renders to, or a load if a view was drawn into the window before it,
+/// see [`view_clears_target`]. Views with a [`ViewHdrTexture`] clear it to the color that is
+/// tonemapped to the clear color.
+pub(crate) fn view_color_load_op(
+    world: &World,
+    view: Entity,
+    camera_graph: CameraGraph,
+) -> LoadOp<Color> {
+    if !view_clears_target(world, view, camera_graph) {
+        return LoadOp::Load;
+    }
+    let clear_color = window_clear_color(world, view);
+    if camera_graph == CameraGraph::Draw3d && world.entity(view).contains::<ViewHdrTexture>() {
+        LoadOp::Clear(hdr_clear_color(clear_color))
+    } else {
+        LoadOp::Clear(clear_color)
+    }
+}
+
+/// The load operation of a pass writing the final image of `view` to the swap chain texture of
+/// its window: a clear to the clear color of the window if `view` is the first view drawn into
+/// it, otherwise a load, see [`view_clears_window`].
+pub fn view_window_load_op(
+    world: &World,
+    view: Entity,
+    camera_graph: CameraGraph,
+) -> LoadOp<Color> {
+    if view_clears_window(world, view, camera_graph) {
+        LoadOp::Clear(window_clear_color(world, view))
+    } else {
+        LoadOp::Load
+    }
+}
+
+fn window_clear_color(world: &World, view: Entity) -> Color {
+    let camera = world.entity(view).get::<ExtractedCamera>().unwrap();
+    let windows = world.get_resource::<ExtractedWindows>().unwrap();
+    windows.get(&camera.window_id).unwrap().clear_color()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CorePipelineSettings, SortMode2d, YSortOffset, ZIndex};
+    use crate::texture::TextureFormat;
+    use bevy_math::Vec3;
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn z_index_sort_key() {
+        let keys = [
+            ZIndex(-2).sort_key(100.0),
+            ZIndex(0).sort_key(-3.0),
+            ZIndex(0).sort_key(-0.5),
+            ZIndex(0).sort_key(0.0),
+            ZIndex(0).sort_key(2.0),
+            ZIndex(1).sort_key(-100.0),
+        ];
+        assert!(keys.windows(2).all(|keys| keys[0] < keys[1]));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn y_sort_key() {
+        let key = |y: f32, offset: Option<YSortOffset>| {
+            let depth = SortMode2d::Y.depth(Vec3::new(0.0, y, 5.0), offset.as_ref());
+            ZIndex(0).sort_key(depth)
+        };
+        assert!(key(10.0, None) < key(0.0, None));
+        assert!(key(-5.0, Some(YSortOffset(20.0))) < key(0.0, None));
+        assert_eq!(
+            SortMode2d::Z.depth(Vec3::new(0.0, 1.0, 5.0), Some(&YSortOffset(2.0))),
+            5.0
+        );
+    }
+
+    #[test]
+    fn depth_format_is_validated() {
+        let settings = |depth_format| CorePipelineSettings {
+            depth_format,
+            ..Default::default()
+        };
+        let stencil = settings(TextureFormat::Depth24PlusStencil8);
+        assert_eq!(stencil.validated(), stencil);
+        assert!(stencil.stencil_ops().is_some());
+        assert!(settings(TextureFormat::Depth24Plus).stencil_ops().is_none());
+        assert_eq!(
+            settings(TextureFormat::Rgba8Unorm).validated(),
+            CorePipelineSettings::default()
+        );
+        let hdr = CorePipelineSettings {
+            hdr: true,
+            depth_format: TextureFormat::Rgba8Unorm,
+        };
+        assert!(hdr.validated().hdr);
+        assert_eq!(hdr.main_texture_format(), super::HDR_TEXTURE_FORMAT);
     }
 }
@@ -0,0 +1,86 @@
+use crate::{
+    pass::{
+        LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
+        TextureAttachment,
+    },
+    render_graph::{Node, NodeRunError, RenderGraphContext},
+    renderer::RenderContext,
+    view::{ExtractedWindow, ExtractedWindows},
+};
+use bevy_ecs::world::World;
+
+/// Draws on top of a window after the main passes, e.g. the UI of a GUI library such as egui.
+///
+/// Overlays own their pipelines, buffers and textures: create and update them in systems of the
+/// render app, typically in [`RenderStage::Prepare`](crate::RenderStage::Prepare), and bind them
+/// in [`Overlay::draw`]. Pipelines must target the format of the window's swap chain, see
+/// [`ExtractedWindow::swap_chain_descriptor`], with a sample count of 1 and no depth attachment.
+pub trait Overlay: Send + Sync + 'static {
+    /// Records draw calls for `window` into `render_pass`, which loads the image drawn by the
+    /// main passes. Called once per frame for every window, including offscreen targets.
+    fn draw(&self, world: &World, window: &ExtractedWindow, render_pass: &mut dyn RenderPass);
+}
+
+/// The [`Overlay`]s drawn by the [`OverlayNode`], in the order they were added. A resource of the
+/// render app.
+#[derive(Default)]
+pub struct Overlays {
+    overlays: Vec<Box<dyn Overlay>>,
+}
+
+impl Overlays {
+    pub fn add<T: Overlay>(&mut self, overlay: T) {
+        self.overlays.push(Box::new(overlay));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overlays.is_empty()
+    }
+}
+
+/// Runs after the [`CameraDriverNode`](crate::core_pipeline::CameraDriverNode) and draws the
+/// [`Overlays`] into every window, in a single pass per window.
+pub struct OverlayNode;
+
+impl Node for OverlayNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let overlays = world.get_resource::<Overlays>().unwrap();
+        if overlays.is_empty() {
+            return Ok(());
+        }
+
+        let extracted_windows = world.get_resource::<ExtractedWindows>().unwrap();
+        for window in extracted_windows.values() {
+            let swap_chain_texture = match window.swap_chain_texture {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let pass_descriptor = PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(swap_chain_texture),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
+            render_context.begin_render_pass(
+                &pass_descriptor,
+                &mut |render_pass: &mut dyn RenderPass| {
+                    for overlay in overlays.overlays.iter() {
+                        overlay.draw(world, window, render_pass);
+                    }
+                },
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Hdr;
+layout(set = 0, binding = 1) uniform sampler s_Hdr;
+
+// luminance coefficients from Rec. 709.
+// https://en.wikipedia.org/wiki/Rec._709
+float luminance(vec3 v) {
+    return dot(v, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// the same tonemapping as the pbr shaders without CorePipelineSettings::hdr
+vec3 reinhard_luminance(vec3 color) {
+    float l_old = luminance(color);
+    float l_new = l_old / (1.0 + l_old);
+    return color * (l_new / max(l_old, 1e-8));
+}
+
+void main() {
+    vec4 color = texelFetch(sampler2D(t_Hdr, s_Hdr), ivec2(gl_FragCoord.xy), 0);
+    o_Target = vec4(reinhard_luminance(color.rgb), color.a);
+}
@@ -0,0 +1,266 @@
+use crate::{
+    camera::ExtractedCamera,
+    color::Color,
+    core_pipeline::{view_window_load_op, CameraGraph, ViewHdrTexture, ViewOutputTexture},
+    pass::{
+        LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
+        TextureAttachment,
+    },
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::TrackedRenderPass,
+    render_resource::{BindGroupBuilder, BindGroupId, SamplerId},
+    renderer::{RenderContext, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::{FilterMode, SamplerDescriptor, TextureFormat},
+    view::{ExtractedView, ExtractedWindows},
+};
+use bevy_ecs::prelude::*;
+use bevy_utils::HashMap;
+
+/// The luminance the tonemapping maps to white, which clear colors are kept below.
+const MAX_TONEMAPPED_LUMINANCE: f32 = 0.999;
+
+/// Returns the color a [`ViewHdrTexture`] is cleared to so that it is tonemapped to `color`.
+pub fn hdr_clear_color(color: Color) -> Color {
+    let [r, g, b, a] = color.as_linear_rgba_f32();
+    // the inverse of reinhard_luminance in tonemapping.frag
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    if luminance <= 0.0 {
+        return Color::rgba_linear(r, g, b, a);
+    }
+    let tonemapped = luminance.min(MAX_TONEMAPPED_LUMINANCE);
+    let scale = tonemapped / (1.0 - tonemapped) / luminance;
+    Color::rgba_linear(r * scale, g * scale, b * scale, a)
+}
+
+pub struct TonemappingShaders {
+    pipeline_descriptor: RenderPipelineDescriptor,
+    /// The tonemapping pipeline for each target format.
+    pipelines: HashMap<TextureFormat, PipelineId>,
+    sampler: SamplerId,
+}
+
+impl FromWorld for TonemappingShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+
+        let vertex_shader =
+            Shader::from_glsl(ShaderStage::Vertex, include_str!("tonemapping.vert"))
+                .get_spirv_shader(None)
+                .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("tonemapping.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+
+        TonemappingShaders {
+            pipeline_descriptor,
+            pipelines: HashMap::default(),
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl TonemappingShaders {
+    /// Returns the pipeline that tonemaps to targets of `format`, creating it if needed.
+    fn pipeline(
+        &mut self,
+        render_resources: &RenderResources,
+        format: TextureFormat,
+    ) -> PipelineId {
+        let pipeline_descriptor = &self.pipeline_descriptor;
+        *self.pipelines.entry(format).or_insert_with(|| {
+            let mut descriptor = pipeline_descriptor.clone();
+            descriptor.color_target_states[0].format = format;
+            render_resources.create_render_pipeline(&descriptor)
+        })
+    }
+}
+
+/// The pipeline and bind group the [`ViewHdrTexture`] of a view is tonemapped with.
+pub struct ViewTonemapping {
+    pipeline: PipelineId,
+    bind_group: BindGroupId,
+}
+
+/// Picks the tonemapping pipeline of each view with a [`ViewHdrTexture`] for the format of its
+/// target: [`TextureFormat::default`] for a [`ViewOutputTexture`], otherwise the swap chain format
+/// of its window.
+pub fn queue_tonemapping(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    mut shaders: ResMut<TonemappingShaders>,
+    windows: Res<ExtractedWindows>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ViewHdrTexture,
+        Option<&ViewOutputTexture>,
+    )>,
+) {
+    for (entity, camera, hdr_texture, output_texture) in views.iter() {
+        let format = match output_texture {
+            Some(_) => TextureFormat::default(),
+            None => match windows.get(&camera.window_id) {
+                Some(window) => window.swap_chain_config.format,
+                None => continue,
+            },
+        };
+        let bind_group = BindGroupBuilder::default()
+            .add_texture_view(0, hdr_texture.view)
+            .add_sampler(1, shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(
+            shaders.pipeline_descriptor.layout.bind_group(0).id,
+            &bind_group,
+        );
+        commands.entity(entity).insert(ViewTonemapping {
+            pipeline: shaders.pipeline(&render_resources, format),
+            bind_group: bind_group.id,
+        });
+    }
+}
+
+/// Tonemaps the [`ViewHdrTexture`] of a view to the target of its camera. The swap chain texture
+/// of a window is only written within the viewport of the view, over the views drawn into it
+/// before.
+pub struct TonemappingNode {
+    query: QueryState<(
+        &'static ExtractedView,
+        &'static ViewTonemapping,
+        Option<&'static ViewOutputTexture>,
+    )>,
+}
+
+impl TonemappingNode {
+    pub const IN_TARGET: &'static str = "target";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for TonemappingNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(TonemappingNode::IN_TARGET, SlotType::TextureView),
+            SlotInfo::new(TonemappingNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view, tonemapping, output_texture) = match self.query.get_manual(world, view_entity) {
+            Ok(query) => query,
+            Err(_) => return Ok(()),
+        };
+        let shaders = world.get_resource::<TonemappingShaders>().unwrap();
+        let load = match output_texture {
+            // every pixel is overwritten, clearing spares loading the previous contents
+            Some(_) => LoadOp::Clear(Color::NONE),
+            None => view_window_load_op(world, view_entity, CameraGraph::Draw3d),
+        };
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(graph.get_input_texture(Self::IN_TARGET)?),
+                resolve_target: None,
+                ops: Operations { load, store: true },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                if let (Some(viewport), None) = (view.viewport, output_texture) {
+                    tracked_pass.set_viewport(viewport);
+                }
+                tracked_pass.set_pipeline(tonemapping.pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    shaders.pipeline_descriptor.layout.bind_group(0).id,
+                    tonemapping.bind_group,
+                    None,
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hdr_clear_color;
+    use crate::color::Color;
+
+    #[test]
+    fn hdr_clear_color_is_tonemapped_back() {
+        let color = Color::rgb(0.4, 0.4, 0.4);
+        let [r, g, b, _] = color.as_linear_rgba_f32();
+        let [hdr_r, hdr_g, hdr_b, _] = hdr_clear_color(color).as_linear_rgba_f32();
+        // reinhard_luminance of a grey is reinhard of each channel
+        let tonemapped = [hdr_r, hdr_g, hdr_b]
+            .iter()
+            .map(|channel| channel / (1.0 + channel))
+            .collect::<Vec<_>>();
+        for (tonemapped, expected) in tonemapped.iter().zip([r, g, b].iter()) {
+            assert!((tonemapped - expected).abs() < 1e-5);
+        }
+        assert_eq!(
+            hdr_clear_color(Color::BLACK).as_linear_rgba_f32(),
+            [0.0, 0.0, 0.0, 1.0]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::texture::TextureCacheStats;
+use bevy_app::{App, Plugin};
+use bevy_diagnostic::{Diagnostic, DiagnosticId, Diagnostics};
+use bevy_ecs::prelude::*;
+use bevy_utils::Duration;
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// The CPU time spent in each render stage and render graph node during the last rendered frame.
+///
+/// This resource exists in both the app world and the render world. Stages are named after the
+/// `Debug` output of their label. Nodes are named after their graph node name, prefixed with
+/// `"<sub graph>/"` for nodes of sub graphs. Nodes that run more than once per frame, such as the
+/// nodes of a sub graph that runs once per view, report the sum of all runs.
+#[derive(Clone, Debug, Default)]
+pub struct RenderTimings {
+    pub stages: Vec<(Cow<'static, str>, Duration)>,
+    pub nodes: Vec<(Cow<'static, str>, Duration)>,
+    /// The GPU time of the latest frame whose timing was read back during the last rendered
+    /// frame. Backends read it back without waiting for the GPU, so it lags a few frames behind,
+    /// and is `None` for frames that didn't receive a new timing. `bevy_wgpu` measures it with
+    /// timestamp queries, when the device was created with `WgpuFeature::TimestampQuery`.
+    pub gpu_frame: Option<Duration>,
+}
+
+impl RenderTimings {
+    pub fn stage(&self, name: &str) -> Option<Duration> {
+        self.stages
+            .iter()
+            .find(|(stage, _)| stage == name)
+            .map(|(_, duration)| *duration)
+    }
+
+    pub fn node(&self, name: &str) -> Option<Duration> {
+        self.nodes
+            .iter()
+            .find(|(node, _)| node == name)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Adds `duration` to the time of the node `name`.
+    pub fn add_node_time(&mut self, name: Cow<'static, str>, duration: Duration) {
+        if let Some((_, total)) = self.nodes.iter_mut().find(|(node, _)| *node == name) {
+            *total += duration;
+        } else {
+            self.nodes.push((name, duration));
+        }
+    }
+}
+
+/// Publishes [`RenderTimings`] as diagnostics, in milliseconds. Stages are named
+/// `"render_stage/<stage>"` and nodes `"render_node/<node>"`.
+///
+/// Also publishes the [`RenderTimings::gpu_frame`] time as `"render_gpu/frame"`, and the
+/// [`TextureCacheStats`] of the last frame, as `"texture_cache/<stat>"`.
+#[derive(Default)]
+pub struct RenderDiagnosticsPlugin;
+
+impl Plugin for RenderDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderTimings>()
+            .init_resource::<TextureCacheStats>()
+            .add_startup_system(Self::setup_system.system())
+            .add_system(Self::diagnostic_system.system())
+            .add_system(Self::texture_cache_diagnostic_system.system());
+    }
+}
+
+impl RenderDiagnosticsPlugin {
+    pub const MAX_HISTORY_LENGTH: usize = 20;
+
+    pub const GPU_FRAME: DiagnosticId =
+        DiagnosticId::from_u128(170948432213566104823512839750224830061);
+    pub const TEXTURE_CACHE_ACTIVE_TEXTURES: DiagnosticId =
+        DiagnosticId::from_u128(63440954197240701312567230375919755309);
+    pub const TEXTURE_CACHE_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(16293470221073712647884117450543678955);
+    pub const TEXTURE_CACHE_HITS: DiagnosticId =
+        DiagnosticId::from_u128(104055540391908538542020140608229127478);
+    pub const TEXTURE_CACHE_MISSES: DiagnosticId =
+        DiagnosticId::from_u128(307752198476431162944791327462420885049);
+    pub const TEXTURE_CACHE_EVICTIONS: DiagnosticId =
+        DiagnosticId::from_u128(216606965945726372752939744274909491533);
+
+    /// Returns the [`DiagnosticId`] of the stage named `name`.
+    pub fn stage_diagnostic_id(name: &str) -> DiagnosticId {
+        diagnostic_id("render_stage", name)
+    }
+
+    /// Returns the [`DiagnosticId`] of the render graph node named `name`.
+    pub fn node_diagnostic_id(name: &str) -> DiagnosticId {
+        diagnostic_id("render_node", name)
+    }
+
+    pub fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        diagnostics.add(
+            Diagnostic::new(
+                Self::GPU_FRAME,
+                "render_gpu/frame",
+                Self::MAX_HISTORY_LENGTH,
+            )
+            .with_suffix("ms"),
+        );
+        diagnostics.add(Diagnostic::new(
+            Self::TEXTURE_CACHE_ACTIVE_TEXTURES,
+            "texture_cache/active_textures",
+            Self::MAX_HISTORY_LENGTH,
+        ));
+        diagnostics.add(
+            Diagnostic::new(
+                Self::TEXTURE_CACHE_BYTES,
+                "texture_cache/bytes",
+                Self::MAX_HISTORY_LENGTH,
+            )
+            .with_suffix("MiB"),
+        );
+        diagnostics.add(Diagnostic::new(
+            Self::TEXTURE_CACHE_HITS,
+            "texture_cache/hits",
+            Self::MAX_HISTORY_LENGTH,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::TEXTURE_CACHE_MISSES,
+            "texture_cache/misses",
+            Self::MAX_HISTORY_LENGTH,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::TEXTURE_CACHE_EVICTIONS,
+            "texture_cache/evictions",
+            Self::MAX_HISTORY_LENGTH,
+        ));
+    }
+
+    pub fn diagnostic_system(mut diagnostics: ResMut<Diagnostics>, timings: Res<RenderTimings>) {
+        let stages = timings
+            .stages
+            .iter()
+            .map(|(name, duration)| ("render_stage", name, duration));
+        let nodes = timings
+            .nodes
+            .iter()
+            .map(|(name, duration)| ("render_node", name, duration));
+        for (kind, name, duration) in stages.chain(nodes) {
+            let id = diagnostic_id(kind, name);
+            if diagnostics.get(id).is_none() {
+                diagnostics.add(
+                    Diagnostic::new(id, format!("{}/{}", kind, name), Self::MAX_HISTORY_LENGTH)
+                        .with_suffix("ms"),
+                );
+            }
+            diagnostics.add_measurement(id, duration.as_secs_f64() * 1000.0);
+        }
+        if let Some(gpu_frame) = timings.gpu_frame {
+            diagnostics.add_measurement(Self::GPU_FRAME, gpu_frame.as_secs_f64() * 1000.0);
+        }
+    }
+
+    pub fn texture_cache_diagnostic_system(
+        mut diagnostics: ResMut<Diagnostics>,
+        stats: Res<TextureCacheStats>,
+    ) {
+        diagnostics.add_measurement(
+            Self::TEXTURE_CACHE_ACTIVE_TEXTURES,
+            stats.active_textures as f64,
+        );
+        diagnostics.add_measurement(
+            Self::TEXTURE_CACHE_BYTES,
+            stats.bytes as f64 / (1024.0 * 1024.0),
+        );
+        diagnostics.add_measurement(Self::TEXTURE_CACHE_HITS, stats.hits as f64);
+        diagnostics.add_measurement(Self::TEXTURE_CACHE_MISSES, stats.misses as f64);
+        diagnostics.add_measurement(Self::TEXTURE_CACHE_EVICTIONS, stats.evictions as f64);
+    }
+}
+
+/// Derives a stable id from the name, so ids can be looked up without access to the plugin.
+fn diagnostic_id(kind: &str, name: &str) -> DiagnosticId {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    name.hash(&mut hasher);
+    let low = hasher.finish();
+    low.hash(&mut hasher);
+    let high = hasher.finish();
+    DiagnosticId::from_u128((high as u128) << 64 | low as u128)
+}
@@ -0,0 +1,420 @@
+use crate::{
+    color::Color,
+    core_pipeline,
+    diagnostic::RenderTimings,
+    pass::{
+        LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
+        TextureAttachment,
+    },
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_phase::TrackedRenderPass,
+    render_resource::{BindGroupBuilder, BindGroupId, SamplerId, TextureViewId},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::{
+        AddressMode, Extent3d, FilterMode, SamplerDescriptor, TextureCache, TextureDescriptor,
+        TextureDimension, TextureFormat, TextureUsage,
+    },
+    view::ExtractedWindows,
+    RenderApp, RenderStage, RenderSystem,
+};
+use bevy_app::{App, CoreStage, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_utils::{Duration, HashMap};
+use bevy_window::{WindowId, Windows};
+
+/// The steps the scale changes in, so the textures of the views aren't recreated for every small
+/// change of the frame time.
+const SCALE_STEP: f32 = 0.05;
+
+/// How far the scale moves towards the one that would meet the target each frame. The GPU time
+/// lags a few frames behind the scale, so moving all the way would overshoot.
+const SCALE_GAIN: f32 = 0.1;
+
+/// Renders the app's windows at a resolution that follows the GPU time of the frames, to keep the
+/// frame rate stable under load, see [`DynamicResolution`]. Views of the windows are drawn into
+/// textures at the [`DynamicResolutionScale`], which are upsampled to the swap chains after the
+/// overlays. Offscreen targets and external windows are always rendered at their size.
+///
+/// The GPU time is read from [`RenderTimings::gpu_frame`], so the scale stays at
+/// [`DynamicResolution::max_scale`] with backends that don't measure it, such as `bevy_wgpu`
+/// without `WgpuFeature::TimestampQuery`. Must be added after the
+/// [`CorePipelinePlugin`](crate::core_pipeline::CorePipelinePlugin).
+#[derive(Default)]
+pub struct DynamicResolutionPlugin;
+
+impl DynamicResolutionPlugin {
+    pub const UPSAMPLE_NODE: &'static str = "dynamic_resolution_upsample";
+}
+
+impl Plugin for DynamicResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DynamicResolution>()
+            .init_resource::<DynamicResolutionScale>()
+            .add_system_to_stage(CoreStage::PreUpdate, dynamic_resolution_system.system());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_render_resource::<DynamicResolutionShaders>()
+            .init_resource::<DynamicResolutionTargets>()
+            .add_system_to_stage(RenderStage::Extract, extract_dynamic_resolution.system())
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_dynamic_resolution
+                    .system()
+                    .after(RenderSystem::PrepareWindows),
+            )
+            .add_system_to_stage(RenderStage::Queue, queue_dynamic_resolution.system());
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(Self::UPSAMPLE_NODE, UpsampleNode);
+        graph
+            .add_node_edge(core_pipeline::node::OVERLAY, Self::UPSAMPLE_NODE)
+            .unwrap();
+    }
+}
+
+/// The bounds of the [`DynamicResolutionScale`], and the GPU time it keeps the frames under. A
+/// resource of the app world, which can be changed at any time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicResolution {
+    /// The GPU time per frame to stay under, e.g. 1/60 of a second for 60 frames per second.
+    pub target_frame_time: Duration,
+    /// The lowest scale of the resolution of the windows.
+    pub min_scale: f32,
+    /// The highest scale of the resolution of the windows, and the scale the app starts with.
+    pub max_scale: f32,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        DynamicResolution {
+            target_frame_time: Duration::from_secs_f64(1.0 / 60.0),
+            min_scale: 0.5,
+            max_scale: 1.0,
+        }
+    }
+}
+
+impl DynamicResolution {
+    /// Returns the scale to render at after a frame rendered at `scale` took `gpu_frame` on the
+    /// GPU, within the bounds.
+    pub fn next_scale(&self, scale: f32, gpu_frame: Duration) -> f32 {
+        // the GPU time grows with the number of pixels, i.e. with the square of the scale
+        let ratio = self.target_frame_time.as_secs_f32() / gpu_frame.as_secs_f32().max(1e-6);
+        let target = scale * ratio.sqrt();
+        let next = scale + (target - scale) * SCALE_GAIN;
+        let next = (next / SCALE_STEP).round() * SCALE_STEP;
+        next.max(self.min_scale).min(self.max_scale)
+    }
+}
+
+/// The scale of the resolution the windows are rendered at this frame, set from the GPU time by
+/// [`dynamic_resolution_system`]. A resource of the app world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicResolutionScale(pub f32);
+
+impl FromWorld for DynamicResolutionScale {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world
+            .get_resource::<DynamicResolution>()
+            .copied()
+            .unwrap_or_default();
+        DynamicResolutionScale(settings.max_scale)
+    }
+}
+
+/// Scales a size in pixels of a window to the size it is rendered at. Views and the textures they
+/// draw into are scaled the same way, so they always match.
+pub fn scale_size(size: u32, scale: f32) -> u32 {
+    ((size as f32 * scale).round() as u32).max(1)
+}
+
+/// Sets the [`DynamicResolutionScale`] from the latest [`RenderTimings::gpu_frame`].
+pub fn dynamic_resolution_system(
+    settings: Res<DynamicResolution>,
+    timings: Option<Res<RenderTimings>>,
+    mut scale: ResMut<DynamicResolutionScale>,
+) {
+    let next = match timings.and_then(|timings| timings.gpu_frame) {
+        Some(gpu_frame) => settings.next_scale(scale.0, gpu_frame),
+        // keep the scale within the settings, which may have changed
+        None => scale.0.max(settings.min_scale).min(settings.max_scale),
+    };
+    if (next - scale.0).abs() > f32::EPSILON {
+        scale.0 = next;
+    }
+}
+
+/// The [`DynamicResolutionScale`] of the frame, and the windows it applies to.
+pub struct ExtractedDynamicResolution {
+    pub scale: f32,
+    pub windows: Vec<WindowId>,
+}
+
+pub fn extract_dynamic_resolution(
+    mut commands: Commands,
+    scale: Res<DynamicResolutionScale>,
+    windows: Res<Windows>,
+) {
+    commands.insert_resource(ExtractedDynamicResolution {
+        scale: scale.0,
+        windows: windows.iter().map(|window| window.id()).collect(),
+    });
+}
+
+/// A window rendered below its resolution this frame.
+pub struct DynamicResolutionTarget {
+    /// The texture the window is drawn into, which replaced its swap chain texture in the
+    /// [`ExtractedWindows`].
+    pub source: TextureViewId,
+    /// The swap chain texture of the window, which the source is upsampled to.
+    pub output: TextureViewId,
+    pub format: TextureFormat,
+    pipeline: Option<PipelineId>,
+    bind_group: Option<BindGroupId>,
+}
+
+/// The windows rendered below their resolution this frame. A resource of the render world.
+#[derive(Default)]
+pub struct DynamicResolutionTargets {
+    targets: HashMap<WindowId, DynamicResolutionTarget>,
+}
+
+impl DynamicResolutionTargets {
+    pub fn get(&self, id: WindowId) -> Option<&DynamicResolutionTarget> {
+        self.targets.get(&id)
+    }
+}
+
+/// Replaces the swap chain textures of the windows with textures at the scaled resolution, so
+/// everything drawn to the windows is drawn into them. Their views were scaled the same way when
+/// the cameras were extracted.
+pub fn prepare_dynamic_resolution(
+    dynamic_resolution: Res<ExtractedDynamicResolution>,
+    render_resources: Res<RenderResources>,
+    mut windows: ResMut<ExtractedWindows>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut targets: ResMut<DynamicResolutionTargets>,
+) {
+    targets.targets.clear();
+    for id in dynamic_resolution.windows.iter() {
+        let window = match windows.get_mut(id) {
+            Some(window) => window,
+            None => continue,
+        };
+        let output = match window.swap_chain_texture {
+            Some(output) => output,
+            None => continue,
+        };
+        let width = scale_size(window.physical_width, dynamic_resolution.scale);
+        let height = scale_size(window.physical_height, dynamic_resolution.scale);
+        if width == window.physical_width && height == window.physical_height {
+            continue;
+        }
+        let format = window.swap_chain_config.format;
+        let texture = texture_cache.get(
+            &render_resources,
+            TextureDescriptor {
+                size: Extent3d::new(width, height, 1),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+            },
+        );
+        window.swap_chain_texture = Some(texture.default_view);
+        window.physical_width = width;
+        window.physical_height = height;
+        targets.targets.insert(
+            *id,
+            DynamicResolutionTarget {
+                source: texture.default_view,
+                output,
+                format,
+                pipeline: None,
+                bind_group: None,
+            },
+        );
+    }
+}
+
+pub struct DynamicResolutionShaders {
+    pipeline_descriptor: RenderPipelineDescriptor,
+    /// The upsampling pipeline for each swap chain format.
+    pipelines: HashMap<TextureFormat, PipelineId>,
+    sampler: SamplerId,
+}
+
+impl FromWorld for DynamicResolutionShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("upsample.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("upsample.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                blend: None,
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let mut pipelines = HashMap::default();
+        pipelines.insert(
+            TextureFormat::default(),
+            render_resources.create_render_pipeline(&pipeline_descriptor),
+        );
+
+        DynamicResolutionShaders {
+            pipeline_descriptor,
+            pipelines,
+            sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl DynamicResolutionShaders {
+    /// Returns the pipeline that upsamples to targets of `format`, creating it if needed.
+    fn pipeline(
+        &mut self,
+        render_resources: &RenderResources,
+        format: TextureFormat,
+    ) -> PipelineId {
+        let pipeline_descriptor = &self.pipeline_descriptor;
+        *self.pipelines.entry(format).or_insert_with(|| {
+            let mut descriptor = pipeline_descriptor.clone();
+            descriptor.color_target_states[0].format = format;
+            render_resources.create_render_pipeline(&descriptor)
+        })
+    }
+}
+
+pub fn queue_dynamic_resolution(
+    render_resources: Res<RenderResources>,
+    mut shaders: ResMut<DynamicResolutionShaders>,
+    mut targets: ResMut<DynamicResolutionTargets>,
+) {
+    for target in targets.targets.values_mut() {
+        let bind_group = BindGroupBuilder::default()
+            .add_binding(0, target.source)
+            .add_binding(1, shaders.sampler)
+            .finish();
+        render_resources.create_bind_group(
+            shaders.pipeline_descriptor.layout.bind_group(0).id,
+            &bind_group,
+        );
+        target.pipeline = Some(shaders.pipeline(&render_resources, target.format));
+        target.bind_group = Some(bind_group.id);
+    }
+}
+
+/// Upsamples the [`DynamicResolutionTargets`] to the swap chains of their windows.
+pub struct UpsampleNode;
+
+impl Node for UpsampleNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let targets = world.get_resource::<DynamicResolutionTargets>().unwrap();
+        let shaders = world.get_resource::<DynamicResolutionShaders>().unwrap();
+        let layout = &shaders.pipeline_descriptor.layout;
+        for target in targets.targets.values() {
+            let (pipeline, bind_group) = match (target.pipeline, target.bind_group) {
+                (Some(pipeline), Some(bind_group)) => (pipeline, bind_group),
+                _ => continue,
+            };
+            let pass_descriptor = PassDescriptor {
+                color_attachments: vec![RenderPassColorAttachment {
+                    attachment: TextureAttachment::Id(target.output),
+                    resolve_target: None,
+                    // every pixel is overwritten, clearing spares loading the previous contents
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::NONE),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+                sample_count: 1,
+            };
+            render_context.begin_render_pass(
+                &pass_descriptor,
+                &mut |render_pass: &mut dyn RenderPass| {
+                    let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                    tracked_pass.set_pipeline(pipeline);
+                    tracked_pass.set_bind_group(0, layout.bind_group(0).id, bind_group, None);
+                    tracked_pass.draw(0..3, 0..1);
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynamicResolution;
+    use bevy_utils::Duration;
+
+    #[test]
+    fn scale_follows_the_gpu_time() {
+        let settings = DynamicResolution {
+            target_frame_time: Duration::from_millis(16),
+            min_scale: 0.5,
+            max_scale: 1.0,
+        };
+        // over budget, the scale steps down
+        let scale = settings.next_scale(1.0, Duration::from_millis(32));
+        assert!(scale < 1.0 && scale >= 0.5);
+        // on budget, the scale is kept
+        assert_eq!(settings.next_scale(0.75, Duration::from_millis(16)), 0.75);
+        // under budget, the scale steps up to the maximum
+        let scale = settings.next_scale(0.75, Duration::from_millis(4));
+        assert!(scale > 0.75 && scale <= 1.0);
+        assert_eq!(settings.next_scale(1.0, Duration::from_millis(1)), 1.0);
+        assert_eq!(settings.next_scale(0.5, Duration::from_millis(100)), 0.5);
+    }
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) out vec2 v_Uv;
+
+void main() {
+    // a single triangle that covers the whole target
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+    // textures have their origin at the top left
+    v_Uv = vec2(uv.x, 1.0 - uv.y);
+}
@@ -0,0 +1,681 @@
+use crate::{
+    pass::{ComputePass, PassDescriptor, RenderPass, TextureAttachment},
+    pipeline::{BindGroupDescriptorId, IndexFormat, PipelineId},
+    render_resource::{BindGroupId, BufferId, TextureId},
+    renderer::{RenderContext, RenderResourceContext},
+    texture::Extent3d,
+    RenderApp,
+};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+use bevy_utils::tracing::{info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{borrow::Cow, fmt::Debug, ops::Range, path::PathBuf, sync::Arc};
+
+/// Records what the render graph does in a frame on request, see [`FrameTraces`]: the nodes in
+/// the order they ran, the passes they began and the commands they recorded. This helps to debug
+/// rendering where graphics debuggers like RenderDoc aren't available, e.g. on the web.
+///
+/// Frames are traced by the render backend, which only costs time in traced frames.
+#[derive(Default)]
+pub struct FrameTracePlugin;
+
+impl Plugin for FrameTracePlugin {
+    fn build(&self, app: &mut App) {
+        let traces = FrameTraces::default();
+        app.insert_resource(traces.clone())
+            .init_resource::<FrameTraceSettings>()
+            .add_system(frame_trace_key_system.system());
+        app.sub_app_mut(RenderApp).insert_resource(traces);
+    }
+}
+
+/// How frames are traced with a key by the [`FrameTracePlugin`].
+#[derive(Debug, Clone)]
+pub struct FrameTraceSettings {
+    /// The key that traces the next rendered frame, or `None` to only trace frames requested
+    /// with [`FrameTraces::request`].
+    pub key: Option<KeyCode>,
+    /// The file the traces requested with the key are written to. They are logged instead on the
+    /// web.
+    pub path: PathBuf,
+}
+
+impl Default for FrameTraceSettings {
+    fn default() -> Self {
+        FrameTraceSettings {
+            key: Some(KeyCode::F12),
+            path: PathBuf::from("frame_trace.ron"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FrameTraceState {
+    requested: bool,
+    path: Option<PathBuf>,
+    trace: Option<FrameTrace>,
+}
+
+/// Requests and receives [`FrameTrace`]s. This resource exists in both the app world and the
+/// render world, and both share the same state.
+#[derive(Clone, Default)]
+pub struct FrameTraces {
+    state: Arc<Mutex<FrameTraceState>>,
+}
+
+impl FrameTraces {
+    /// Traces the next rendered frame. If `path` is set, the trace is also written to it, or
+    /// logged on the web. It is written as RON with the `ron` feature, and with its `Debug`
+    /// output otherwise.
+    pub fn request(&self, path: Option<PathBuf>) {
+        let mut state = self.state.lock();
+        state.requested = true;
+        state.path = path;
+    }
+
+    /// Returns the last trace, if it hasn't been taken yet.
+    pub fn take(&self) -> Option<FrameTrace> {
+        self.state.lock().trace.take()
+    }
+
+    /// Whether the render backend should trace the frame it renders next.
+    pub fn is_requested(&self) -> bool {
+        self.state.lock().requested
+    }
+
+    /// Stores the `trace` of the requested frame, called by the render backend once the frame
+    /// was recorded.
+    pub fn finish(&self, trace: FrameTrace) {
+        let mut state = self.state.lock();
+        state.requested = false;
+        if let Some(path) = state.path.take() {
+            write_trace(&trace, path);
+        }
+        state.trace = Some(trace);
+    }
+}
+
+fn write_trace(trace: &FrameTrace, path: PathBuf) {
+    let text = match format_trace(trace) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("failed to serialize the frame trace: {}", err);
+            return;
+        }
+    };
+    if cfg!(target_arch = "wasm32") {
+        info!("frame trace:\n{}", text);
+    } else if let Err(err) = std::fs::write(&path, text) {
+        warn!(
+            "failed to write the frame trace to {}: {}",
+            path.display(),
+            err
+        );
+    } else {
+        info!("wrote the frame trace to {}", path.display());
+    }
+}
+
+#[cfg(feature = "ron")]
+fn format_trace(trace: &FrameTrace) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(trace, Default::default())
+}
+
+/// Without the `ron` feature, traces are written with their `Debug` output, which looks much like
+/// RON but can't be parsed back.
+#[cfg(not(feature = "ron"))]
+fn format_trace(trace: &FrameTrace) -> Result<String, std::convert::Infallible> {
+    Ok(format!("{:#?}", trace))
+}
+
+/// Requests a trace of the next frame when the [`FrameTraceSettings::key`] is pressed.
+pub fn frame_trace_key_system(
+    keys: Option<Res<Input<KeyCode>>>,
+    settings: Res<FrameTraceSettings>,
+    traces: Res<FrameTraces>,
+) {
+    let pressed = match (keys, settings.key) {
+        (Some(keys), Some(key)) => keys.just_pressed(key),
+        _ => false,
+    };
+    if pressed {
+        traces.request(Some(settings.path.clone()));
+    }
+}
+
+/// What the render graph did in a traced frame. Resource ids are written with their `Debug`
+/// output, so they can be matched between commands.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrameTrace {
+    /// The nodes in the order they ran. The nodes of a sub graph follow the node that ran it.
+    pub nodes: Vec<NodeTrace>,
+}
+
+impl FrameTrace {
+    /// The number of draw calls of the frame, indirect draws counting once per draw.
+    pub fn draw_calls(&self) -> usize {
+        self.nodes.iter().map(NodeTrace::draw_calls).sum()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTrace {
+    /// The name of the node, like in [`RenderTimings`](crate::diagnostic::RenderTimings).
+    pub name: Cow<'static, str>,
+    pub commands: Vec<TraceCommand>,
+}
+
+impl NodeTrace {
+    pub fn new(name: Cow<'static, str>) -> Self {
+        NodeTrace {
+            name,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn draw_calls(&self) -> usize {
+        self.commands
+            .iter()
+            .map(|command| match command {
+                TraceCommand::RenderPass { draw_calls, .. } => *draw_calls,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// A command recorded by a node, see [`RenderContext`].
+#[derive(Debug, Clone, Serialize)]
+pub enum TraceCommand {
+    CopyBufferToBuffer {
+        source: String,
+        destination: String,
+        size: u64,
+    },
+    CopyBufferToTexture {
+        source: String,
+        destination: String,
+        mip_level: u32,
+        size: [u32; 3],
+    },
+    CopyTextureToBuffer {
+        source: String,
+        mip_level: u32,
+        destination: String,
+        size: [u32; 3],
+    },
+    CopyTextureToTexture {
+        source: String,
+        source_mip_level: u32,
+        destination: String,
+        destination_mip_level: u32,
+        size: [u32; 3],
+    },
+    GenerateMipmaps {
+        texture: String,
+    },
+    RenderPass {
+        color_attachments: Vec<AttachmentTrace>,
+        depth_stencil_attachment: Option<AttachmentTrace>,
+        sample_count: u32,
+        draw_calls: usize,
+        commands: Vec<PassCommand>,
+    },
+    ComputePass {
+        dispatches: usize,
+        commands: Vec<PassCommand>,
+    },
+}
+
+/// An attachment of a traced render pass, with its operations written with their `Debug` output.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentTrace {
+    pub attachment: String,
+    pub resolve_target: Option<String>,
+    pub ops: String,
+}
+
+/// A command recorded in a render or compute pass, see [`RenderPass`] and [`ComputePass`].
+#[derive(Debug, Clone, Serialize)]
+pub enum PassCommand {
+    SetPipeline(String),
+    SetBindGroup {
+        index: u32,
+        bind_group: String,
+        dynamic_offsets: Vec<u32>,
+    },
+    SetVertexBuffer {
+        slot: u32,
+        buffer: String,
+        offset: u64,
+    },
+    SetIndexBuffer {
+        buffer: String,
+        offset: u64,
+        format: String,
+    },
+    SetViewport {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        min_depth: f32,
+        max_depth: f32,
+    },
+    SetScissorRect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    SetStencilReference(u32),
+    Draw {
+        vertices: Range<u32>,
+        instances: Range<u32>,
+    },
+    DrawIndexed {
+        indices: Range<u32>,
+        base_vertex: i32,
+        instances: Range<u32>,
+    },
+    MultiDrawIndirect {
+        buffer: String,
+        offset: u64,
+        count: u32,
+    },
+    Dispatch {
+        x: u32,
+        y: u32,
+        z: u32,
+    },
+}
+
+fn id(id: impl Debug) -> String {
+    format!("{:?}", id)
+}
+
+fn attachment_id(attachment: &TextureAttachment) -> String {
+    match attachment {
+        TextureAttachment::Id(view) => id(view),
+        TextureAttachment::Input(name) => format!("Input({})", name),
+    }
+}
+
+/// A [`RenderContext`] that records the commands of a node into a [`NodeTrace`] before passing
+/// them on. Render backends run the nodes of traced frames with it.
+pub struct TracingRenderContext<'a> {
+    context: &'a mut dyn RenderContext,
+    node: &'a mut NodeTrace,
+}
+
+impl<'a> TracingRenderContext<'a> {
+    pub fn new(context: &'a mut dyn RenderContext, node: &'a mut NodeTrace) -> Self {
+        TracingRenderContext { context, node }
+    }
+}
+
+impl<'a> RenderContext for TracingRenderContext<'a> {
+    fn resources(&self) -> &dyn RenderResourceContext {
+        self.context.resources()
+    }
+
+    fn resources_mut(&mut self) -> &mut dyn RenderResourceContext {
+        self.context.resources_mut()
+    }
+
+    fn copy_buffer_to_buffer(
+        &mut self,
+        source_buffer: BufferId,
+        source_offset: u64,
+        destination_buffer: BufferId,
+        destination_offset: u64,
+        size: u64,
+    ) {
+        self.node.commands.push(TraceCommand::CopyBufferToBuffer {
+            source: id(source_buffer),
+            destination: id(destination_buffer),
+            size,
+        });
+        self.context.copy_buffer_to_buffer(
+            source_buffer,
+            source_offset,
+            destination_buffer,
+            destination_offset,
+            size,
+        );
+    }
+
+    fn copy_buffer_to_texture(
+        &mut self,
+        source_buffer: BufferId,
+        source_offset: u64,
+        source_bytes_per_row: u32,
+        destination_texture: TextureId,
+        destination_origin: [u32; 3],
+        destination_mip_level: u32,
+        size: Extent3d,
+    ) {
+        self.node.commands.push(TraceCommand::CopyBufferToTexture {
+            source: id(source_buffer),
+            destination: id(destination_texture),
+            mip_level: destination_mip_level,
+            size: [size.width, size.height, size.depth_or_array_layers],
+        });
+        self.context.copy_buffer_to_texture(
+            source_buffer,
+            source_offset,
+            source_bytes_per_row,
+            destination_texture,
+            destination_origin,
+            destination_mip_level,
+            size,
+        );
+    }
+
+    fn copy_texture_to_buffer(
+        &mut self,
+        source_texture: TextureId,
+        source_origin: [u32; 3],
+        source_mip_level: u32,
+        destination_buffer: BufferId,
+        destination_offset: u64,
+        destination_bytes_per_row: u32,
+        size: Extent3d,
+    ) {
+        self.node.commands.push(TraceCommand::CopyTextureToBuffer {
+            source: id(source_texture),
+            mip_level: source_mip_level,
+            destination: id(destination_buffer),
+            size: [size.width, size.height, size.depth_or_array_layers],
+        });
+        self.context.copy_texture_to_buffer(
+            source_texture,
+            source_origin,
+            source_mip_level,
+            destination_buffer,
+            destination_offset,
+            destination_bytes_per_row,
+            size,
+        );
+    }
+
+    fn copy_texture_to_texture(
+        &mut self,
+        source_texture: TextureId,
+        source_origin: [u32; 3],
+        source_mip_level: u32,
+        destination_texture: TextureId,
+        destination_origin: [u32; 3],
+        destination_mip_level: u32,
+        size: Extent3d,
+    ) {
+        self.node.commands.push(TraceCommand::CopyTextureToTexture {
+            source: id(source_texture),
+            source_mip_level,
+            destination: id(destination_texture),
+            destination_mip_level,
+            size: [size.width, size.height, size.depth_or_array_layers],
+        });
+        self.context.copy_texture_to_texture(
+            source_texture,
+            source_origin,
+            source_mip_level,
+            destination_texture,
+            destination_origin,
+            destination_mip_level,
+            size,
+        );
+    }
+
+    fn generate_mipmaps(&mut self, texture: TextureId) {
+        self.node.commands.push(TraceCommand::GenerateMipmaps {
+            texture: id(texture),
+        });
+        self.context.generate_mipmaps(texture);
+    }
+
+    fn begin_render_pass(
+        &mut self,
+        pass_descriptor: &PassDescriptor,
+        run_pass: &mut dyn FnMut(&mut dyn RenderPass),
+    ) {
+        let mut commands = Vec::new();
+        self.context
+            .begin_render_pass(pass_descriptor, &mut |render_pass: &mut dyn RenderPass| {
+                run_pass(&mut TracingRenderPass {
+                    pass: render_pass,
+                    commands: &mut commands,
+                })
+            });
+        let draw_calls = commands
+            .iter()
+            .map(|command| match command {
+                PassCommand::Draw { .. } | PassCommand::DrawIndexed { .. } => 1,
+                PassCommand::MultiDrawIndirect { count, .. } => *count as usize,
+                _ => 0,
+            })
+            .sum();
+        self.node.commands.push(TraceCommand::RenderPass {
+            color_attachments: pass_descriptor
+                .color_attachments
+                .iter()
+                .map(|attachment| AttachmentTrace {
+                    attachment: attachment_id(&attachment.attachment),
+                    resolve_target: attachment.resolve_target.as_ref().map(attachment_id),
+                    ops: id(&attachment.ops),
+                })
+                .collect(),
+            depth_stencil_attachment: pass_descriptor.depth_stencil_attachment.as_ref().map(
+                |attachment| AttachmentTrace {
+                    attachment: attachment_id(&attachment.attachment),
+                    resolve_target: None,
+                    ops: id((&attachment.depth_ops, &attachment.stencil_ops)),
+                },
+            ),
+            sample_count: pass_descriptor.sample_count,
+            draw_calls,
+            commands,
+        });
+    }
+
+    fn begin_compute_pass(&mut self, run_pass: &mut dyn FnMut(&mut dyn ComputePass)) {
+        let mut commands = Vec::new();
+        self.context
+            .begin_compute_pass(&mut |compute_pass: &mut dyn ComputePass| {
+                run_pass(&mut TracingComputePass {
+                    pass: compute_pass,
+                    commands: &mut commands,
+                })
+            });
+        let dispatches = commands
+            .iter()
+            .filter(|command| matches!(command, PassCommand::Dispatch { .. }))
+            .count();
+        self.node.commands.push(TraceCommand::ComputePass {
+            dispatches,
+            commands,
+        });
+    }
+}
+
+struct TracingRenderPass<'a> {
+    pass: &'a mut dyn RenderPass,
+    commands: &'a mut Vec<PassCommand>,
+}
+
+impl<'a> RenderPass for TracingRenderPass<'a> {
+    fn get_render_context(&self) -> &dyn RenderContext {
+        self.pass.get_render_context()
+    }
+
+    fn set_index_buffer(&mut self, buffer: BufferId, offset: u64, index_format: IndexFormat) {
+        self.commands.push(PassCommand::SetIndexBuffer {
+            buffer: id(buffer),
+            offset,
+            format: id(index_format),
+        });
+        self.pass.set_index_buffer(buffer, offset, index_format);
+    }
+
+    fn set_vertex_buffer(&mut self, start_slot: u32, buffer: BufferId, offset: u64) {
+        self.commands.push(PassCommand::SetVertexBuffer {
+            slot: start_slot,
+            buffer: id(buffer),
+            offset,
+        });
+        self.pass.set_vertex_buffer(start_slot, buffer, offset);
+    }
+
+    fn set_pipeline(&mut self, pipeline: PipelineId) {
+        self.commands.push(PassCommand::SetPipeline(id(pipeline)));
+        self.pass.set_pipeline(pipeline);
+    }
+
+    fn set_viewport(&mut self, x: f32, y: f32, w: f32, h: f32, min_depth: f32, max_depth: f32) {
+        self.commands.push(PassCommand::SetViewport {
+            x,
+            y,
+            width: w,
+            height: h,
+            min_depth,
+            max_depth,
+        });
+        self.pass.set_viewport(x, y, w, h, min_depth, max_depth);
+    }
+
+    fn set_scissor_rect(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        self.commands.push(PassCommand::SetScissorRect {
+            x,
+            y,
+            width: w,
+            height: h,
+        });
+        self.pass.set_scissor_rect(x, y, w, h);
+    }
+
+    fn set_stencil_reference(&mut self, reference: u32) {
+        self.commands
+            .push(PassCommand::SetStencilReference(reference));
+        self.pass.set_stencil_reference(reference);
+    }
+
+    fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.commands.push(PassCommand::Draw {
+            vertices: vertices.clone(),
+            instances: instances.clone(),
+        });
+        self.pass.draw(vertices, instances);
+    }
+
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.commands.push(PassCommand::DrawIndexed {
+            indices: indices.clone(),
+            base_vertex,
+            instances: instances.clone(),
+        });
+        self.pass.draw_indexed(indices, base_vertex, instances);
+    }
+
+    fn multi_draw_indirect(&mut self, indirect_buffer: BufferId, indirect_offset: u64, count: u32) {
+        self.commands.push(PassCommand::MultiDrawIndirect {
+            buffer: id(indirect_buffer),
+            offset: indirect_offset,
+            count,
+        });
+        self.pass
+            .multi_draw_indirect(indirect_buffer, indirect_offset, count);
+    }
+
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group_descriptor_id: BindGroupDescriptorId,
+        bind_group: BindGroupId,
+        dynamic_uniform_indices: Option<&[u32]>,
+    ) {
+        self.commands.push(PassCommand::SetBindGroup {
+            index,
+            bind_group: id(bind_group),
+            dynamic_offsets: dynamic_uniform_indices.unwrap_or_default().to_vec(),
+        });
+        self.pass.set_bind_group(
+            index,
+            bind_group_descriptor_id,
+            bind_group,
+            dynamic_uniform_indices,
+        );
+    }
+}
+
+struct TracingComputePass<'a> {
+    pass: &'a mut dyn ComputePass,
+    commands: &'a mut Vec<PassCommand>,
+}
+
+impl<'a> ComputePass for TracingComputePass<'a> {
+    fn get_render_context(&self) -> &dyn RenderContext {
+        self.pass.get_render_context()
+    }
+
+    fn set_pipeline(&mut self, pipeline: PipelineId) {
+        self.commands.push(PassCommand::SetPipeline(id(pipeline)));
+        self.pass.set_pipeline(pipeline);
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        self.commands.push(PassCommand::Dispatch { x, y, z });
+        self.pass.dispatch(x, y, z);
+    }
+
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group_descriptor_id: BindGroupDescriptorId,
+        bind_group: BindGroupId,
+        dynamic_uniform_indices: Option<&[u32]>,
+    ) {
+        self.commands.push(PassCommand::SetBindGroup {
+            index,
+            bind_group: id(bind_group),
+            dynamic_offsets: dynamic_uniform_indices.unwrap_or_default().to_vec(),
+        });
+        self.pass.set_bind_group(
+            index,
+            bind_group_descriptor_id,
+            bind_group,
+            dynamic_uniform_indices,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameTrace, FrameTraces, NodeTrace, PassCommand, TraceCommand};
+
+    #[test]
+    fn traces_are_requested_once() {
+        let traces = FrameTraces::default();
+        assert!(!traces.is_requested());
+        traces.request(None);
+        assert!(traces.is_requested());
+
+        let mut node = NodeTrace::new("main_pass".into());
+        node.commands.push(TraceCommand::RenderPass {
+            color_attachments: Vec::new(),
+            depth_stencil_attachment: None,
+            sample_count: 1,
+            draw_calls: 3,
+            commands: vec![PassCommand::Draw {
+                vertices: 0..3,
+                instances: 0..1,
+            }],
+        });
+        traces.finish(FrameTrace { nodes: vec![node] });
+        assert!(!traces.is_requested());
+        assert_eq!(traces.take().unwrap().draw_calls(), 3);
+        assert!(traces.take().is_none());
+    }
+}
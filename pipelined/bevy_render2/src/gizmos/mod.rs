@@ -0,0 +1,174 @@
+mod render;
+
+pub use render::*;
+
+use crate::{
+    color::Color, core_pipeline, render_graph::RenderGraph, renderer::RenderInitAppExt, RenderApp,
+    RenderStage,
+};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_math::Vec3;
+use std::f32::consts::TAU;
+
+pub mod node {
+    pub const GIZMOS: &'static str = "gizmos";
+}
+
+pub mod draw_3d_graph {
+    pub mod node {
+        pub const GIZMO_PASS: &'static str = "gizmo_pass";
+    }
+}
+
+/// The number of line segments used to approximate circles and spheres.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Immediate-mode debug drawing. Lines added to this resource are drawn on top of the
+/// 3d scene for a single frame, then cleared.
+///
+/// ```
+/// # use bevy_ecs::prelude::*;
+/// # use bevy_math::Vec3;
+/// # use bevy_render2::{color::Color, gizmos::Gizmos};
+/// fn draw_axes(mut gizmos: ResMut<Gizmos>) {
+///     gizmos.ray(Vec3::ZERO, Vec3::X, Color::RED);
+///     gizmos.ray(Vec3::ZERO, Vec3::Y, Color::GREEN);
+///     gizmos.ray(Vec3::ZERO, Vec3::Z, Color::BLUE);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Gizmos {
+    vertices: Vec<GizmoVertex>,
+}
+
+impl Gizmos {
+    /// Draws a line from `start` to `end`.
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        let color = color.as_linear_rgba_f32();
+        self.vertices.push(GizmoVertex {
+            position: start.into(),
+            color,
+        });
+        self.vertices.push(GizmoVertex {
+            position: end.into(),
+            color,
+        });
+    }
+
+    /// Draws a line from `origin` to `origin + direction`.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Color) {
+        self.line(origin, origin + direction, color);
+    }
+
+    /// Draws a circle facing `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Color) {
+        let normal = normal.normalize();
+        let reference = if normal.x.abs() > 0.9 {
+            Vec3::Y
+        } else {
+            Vec3::X
+        };
+        let u = normal.cross(reference).normalize() * radius;
+        let v = normal.cross(u);
+        let point = |i: usize| {
+            let angle = i as f32 * TAU / CIRCLE_SEGMENTS as f32;
+            center + u * angle.cos() + v * angle.sin()
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Draws a sphere as three circles, one around each axis.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        self.circle(center, Vec3::X, radius, color);
+        self.circle(center, Vec3::Y, radius, color);
+        self.circle(center, Vec3::Z, radius, color);
+    }
+
+    /// Draws the edges of an axis-aligned bounding box.
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Color) {
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+        for &a in [false, true].iter() {
+            for &b in [false, true].iter() {
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+    /// Removes all lines that have been added this frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Adds the [`Gizmos`] resource and draws its lines for every 3d camera.
+/// Must be added after [`core_pipeline::CorePipelinePlugin`].
+#[derive(Default)]
+pub struct GizmoPlugin;
+
+impl Plugin for GizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Gizmos>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, render::extract_gizmos.system())
+            .add_system_to_stage(RenderStage::Prepare, render::prepare_gizmos.system())
+            .add_system_to_stage(RenderStage::Queue, render::queue_gizmos.system())
+            .init_render_resource::<GizmoShaders>()
+            .init_resource::<GizmoMeta>();
+
+        let gizmo_pass_node = GizmoPassNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(node::GIZMOS, GizmoNode);
+        graph
+            .add_node_edge(node::GIZMOS, core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_3d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_3d_graph::NAME)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::GIZMO_PASS, gizmo_pass_node);
+        draw_3d_graph
+            .add_node_edge(
+                core_pipeline::draw_3d_graph::node::MAIN_PASS,
+                draw_3d_graph::node::GIZMO_PASS,
+            )
+            .unwrap();
+        let input_node_id = draw_3d_graph.input_node().unwrap().id;
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::GIZMO_PASS,
+                GizmoPassNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::RENDER_TARGET,
+                draw_3d_graph::node::GIZMO_PASS,
+                GizmoPassNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_3d_graph::input::DEPTH,
+                draw_3d_graph::node::GIZMO_PASS,
+                GizmoPassNode::IN_DEPTH,
+            )
+            .unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use super::Gizmos;
+use crate::{
+    core_pipeline::{CorePipelineSettings, DepthMode},
+    pass::{
+        LoadOp, Operations, PassDescriptor, RenderPass, RenderPassColorAttachment,
+        RenderPassDepthStencilAttachment, TextureAttachment,
+    },
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
+    render_resource::{BindGroupBuilder, BindGroupId, BufferUsage, BufferVec},
+    renderer::{RenderContext, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    view::{ExtractedView, FloatingOrigin, ViewMeta, ViewUniform},
+};
+use bevy_ecs::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+pub struct GizmoShaders {
+    pipeline: PipelineId,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for GizmoShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let depth_mode = world
+            .get_resource::<DepthMode>()
+            .copied()
+            .unwrap_or_default();
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("gizmo.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("gizmo.frag"))
+            .get_spirv_shader(None)
+            .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout.clone(), fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let vertex_buffer_layout = VertexBufferLayout::build("Vertex")
+            .add_attribute(0, "Vertex_Position", VertexFormat::Float32x3)
+            .add_attribute(1, "Vertex_Color", VertexFormat::Float32x4)
+            .finish();
+        vertex_layout
+            .validate_vertex_buffers(std::slice::from_ref(&vertex_buffer_layout))
+            .unwrap();
+        pipeline_layout.vertex_buffer_descriptors = vec![vertex_buffer_layout];
+
+        pipeline_layout.bind_groups[0].bindings[0].set_dynamic(true);
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            // lines are depth tested against the scene, but don't occlude each other
+            depth_stencil: Some(DepthStencilState {
+                format: settings.depth_format,
+                depth_write_enabled: false,
+                depth_compare: depth_mode.compare(CompareFunction::LessEqual),
+                stencil: StencilState {
+                    front: StencilFaceState::IGNORE,
+                    back: StencilFaceState::IGNORE,
+                    read_mask: 0,
+                    write_mask: 0,
+                },
+                bias: DepthBiasState {
+                    constant: 0,
+                    slope_scale: 0.0,
+                    clamp: 0.0,
+                },
+            }),
+            color_target_states: vec![ColorTargetState {
+                format: settings.main_texture_format(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+
+        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+
+        GizmoShaders {
+            pipeline,
+            pipeline_descriptor,
+        }
+    }
+}
+
+pub struct ExtractedGizmos {
+    vertices: Vec<GizmoVertex>,
+}
+
+pub fn extract_gizmos(
+    mut commands: Commands,
+    mut gizmos: ResMut<Gizmos>,
+    floating_origin: Res<FloatingOrigin>,
+) {
+    let mut vertices = std::mem::take(&mut gizmos.vertices);
+    for vertex in vertices.iter_mut() {
+        vertex.position = floating_origin
+            .to_render_position(vertex.position.into())
+            .into();
+    }
+    commands.insert_resource(ExtractedGizmos { vertices });
+}
+
+pub struct GizmoMeta {
+    vertices: BufferVec<GizmoVertex>,
+    /// One bind group per chunk of [`ViewMeta::uniforms`].
+    view_bind_groups: Vec<BindGroupId>,
+}
+
+impl Default for GizmoMeta {
+    fn default() -> Self {
+        Self {
+            vertices: BufferVec::new(BufferUsage::VERTEX),
+            view_bind_groups: Vec::new(),
+        }
+    }
+}
+
+pub fn prepare_gizmos(
+    render_resources: Res<RenderResources>,
+    mut gizmo_meta: ResMut<GizmoMeta>,
+    extracted_gizmos: Res<ExtractedGizmos>,
+) {
+    gizmo_meta.vertices.clear();
+    // dont create buffers when there are no gizmos
+    if extracted_gizmos.vertices.is_empty() {
+        return;
+    }
+
+    gizmo_meta
+        .vertices
+        .reserve(extracted_gizmos.vertices.len(), &render_resources);
+    for vertex in extracted_gizmos.vertices.iter() {
+        gizmo_meta.vertices.push(*vertex);
+    }
+    gizmo_meta
+        .vertices
+        .write_to_staging_buffer(&render_resources);
+}
+
+pub fn queue_gizmos(
+    render_resources: Res<RenderResources>,
+    mut gizmo_meta: ResMut<GizmoMeta>,
+    view_meta: Res<ViewMeta>,
+    gizmo_shaders: Res<GizmoShaders>,
+) {
+    if gizmo_meta.vertices.is_empty() {
+        return;
+    }
+
+    let layout = &gizmo_shaders.pipeline_descriptor.layout;
+    gizmo_meta.view_bind_groups.clear();
+    for chunk in 0..view_meta.uniforms.chunk_count() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(0, view_meta.uniforms.binding(chunk))
+            .finish();
+        render_resources.create_bind_group(layout.bind_groups[0].id, &view_bind_group);
+        gizmo_meta.view_bind_groups.push(view_bind_group.id);
+    }
+}
+
+// TODO: this logic can be moved to prepare_gizmos once wgpu::Queue is exposed directly
+pub struct GizmoNode;
+
+impl Node for GizmoNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let gizmo_meta = world.get_resource::<GizmoMeta>().unwrap();
+        gizmo_meta.vertices.write_to_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Draws the lines in [`Gizmos`] on top of a 3d view, after its main pass.
+pub struct GizmoPassNode {
+    query: QueryState<&'static ViewUniform, With<ExtractedView>>,
+}
+
+impl GizmoPassNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_DEPTH: &'static str = "depth";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for GizmoPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(GizmoPassNode::IN_COLOR_ATTACHMENT, SlotType::TextureView),
+            SlotInfo::new(GizmoPassNode::IN_DEPTH, SlotType::TextureView),
+            SlotInfo::new(GizmoPassNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let gizmo_meta = world.get_resource::<GizmoMeta>().unwrap();
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let view_uniform = self
+            .query
+            .get_manual(world, view_entity)
+            .expect("view entity should exist");
+        let (vertex_buffer, view_bind_group) = match (
+            gizmo_meta.vertices.buffer(),
+            gizmo_meta
+                .view_bind_groups
+                .get(view_uniform.view_uniform_chunk),
+        ) {
+            (Some(vertex_buffer), Some(view_bind_group)) if !gizmo_meta.vertices.is_empty() => {
+                (vertex_buffer, *view_bind_group)
+            }
+            _ => return Ok(()),
+        };
+
+        let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let depth_texture = graph.get_input_texture(Self::IN_DEPTH)?;
+        let settings = world
+            .get_resource::<CorePipelineSettings>()
+            .copied()
+            .unwrap_or_default();
+
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(color_attachment_texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                attachment: TextureAttachment::Id(depth_texture),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: settings.stencil_ops(),
+            }),
+            sample_count: 1,
+        };
+
+        let gizmo_shaders = world.get_resource::<GizmoShaders>().unwrap();
+        let layout = &gizmo_shaders.pipeline_descriptor.layout;
+        let vertex_count = gizmo_meta.vertices.len() as u32;
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                render_pass.set_pipeline(gizmo_shaders.pipeline);
+                render_pass.set_vertex_buffer(0, vertex_buffer, 0);
+                render_pass.set_bind_group(
+                    0,
+                    layout.bind_groups[0].id,
+                    view_bind_group,
+                    Some(&[view_uniform.view_uniform_offset]),
+                );
+                render_pass.draw(0..vertex_count, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
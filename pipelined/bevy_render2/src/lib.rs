@@ -1,11 +1,20 @@
 pub mod camera;
+pub mod capture;
 pub mod color;
+pub mod compute;
 pub mod core_pipeline;
+pub mod diagnostic;
+pub mod dynamic_resolution;
+pub mod frame_trace;
+pub mod gizmos;
 pub mod mesh;
 pub mod pass;
+pub mod picking;
 pub mod pipeline;
+pub mod quality;
 pub mod render_command;
 pub mod render_graph;
+pub mod render_mode;
 pub mod render_phase;
 pub mod render_resource;
 pub mod renderer;
@@ -13,26 +22,60 @@ pub mod shader;
 pub mod texture;
 pub mod view;
 
+pub use crevice;
 pub use once_cell;
 
 use crate::{
     camera::CameraPlugin,
+    color::Color,
+    diagnostic::RenderTimings,
     mesh::MeshPlugin,
+    quality::{
+        extract_render_quality, render_quality_system, RenderQuality, RenderQualitySettings,
+    },
     render_command::RenderCommandPlugin,
-    render_graph::RenderGraph,
-    render_phase::DrawFunctions,
-    renderer::RenderResources,
-    texture::TexturePlugin,
+    render_graph::{EmptyNode, RenderGraph, RenderGraphNodeTypes},
+    render_mode::{render_mode_system, RenderMode, RenderRequested, RequestRender},
+    render_phase::{extract_draw_order, DrawFunctions, DrawOrder},
+    render_resource::{clear_registry_views_system, RenderResourceRegistry},
+    renderer::{RenderResources, RendererPending},
+    texture::{TextureCache, TexturePlugin},
     view::{ViewPlugin, WindowRenderPlugin},
 };
-use bevy_app::{App, Plugin, StartupStage};
+use bevy_app::{App, AppLabel, CoreStage, Plugin, StartupStage};
 use bevy_ecs::prelude::*;
-use bevy_utils::tracing::warn;
+use bevy_utils::{tracing::warn, Instant};
 
 #[derive(Default)]
 pub struct RenderPlugin;
 
-/// The names of the default App stages
+/// The label of the render sub-app. Use `app.sub_app_mut(RenderApp)` to add render systems
+/// and resources from a plugin.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, AppLabel)]
+pub struct RenderApp;
+
+/// Whether the app is rendered. A resource of the app world: set it to `RenderActive(false)` to
+/// pause rendering, e.g. while the game is in the background. Paused frames extract no cameras,
+/// don't acquire swap chain textures and don't run the render graph, but asset uploads are still
+/// submitted.
+///
+/// Rendering is also paused while every window is minimized, as minimized windows have no size to
+/// create a swap chain for, and on frames the [`RenderMode`](render_mode::RenderMode) skips. The
+/// render world holds the resulting state, which backends check before running the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderActive(pub bool);
+
+impl Default for RenderActive {
+    fn default() -> Self {
+        RenderActive(true)
+    }
+}
+
+/// The names of the default render app stages, in the order they run.
+///
+/// Plugins can insert custom stages relative to these by calling `add_stage_after` or
+/// `add_stage_before` on the [`RenderApp`]. [`RenderStage::Extract`] is always run first, on the
+/// app world, so custom stages must be inserted after it.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
 pub enum RenderStage {
     /// Extract data from "app world" and insert it into "render world". This step should be kept
@@ -47,7 +90,7 @@ pub enum RenderStage {
     Queue,
 
     // TODO: This could probably be moved in favor of a system ordering abstraction in Render or Queue
-    /// Sort RenderPhases here 
+    /// Sort RenderPhases here
     PhaseSort,
 
     /// Actual rendering happens here. In most cases, only the render backend should insert resources here
@@ -57,12 +100,41 @@ pub enum RenderStage {
     Cleanup,
 }
 
+/// Labels for built-in render systems, so that systems added by other plugins can be ordered
+/// relative to them with `.before()` / `.after()`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum RenderSystem {
+    /// Extracts [`view::ExtractedWindows`]. Runs in [`RenderStage::Extract`].
+    ExtractWindows,
+    /// Extracts [`camera::ExtractedCamera`]s and their [`view::ExtractedView`]s. Runs in
+    /// [`RenderStage::Extract`].
+    ExtractCameras,
+    /// Acquires the next swap chain texture of each window. Runs in [`RenderStage::Prepare`].
+    PrepareWindows,
+    /// Writes view uniforms to [`view::ViewMeta`]. Runs in [`RenderStage::Prepare`].
+    PrepareViews,
+    /// Allocates the [`render_graph::TransientTextures`] requested this frame. Runs in
+    /// [`RenderStage::Prepare`].
+    PrepareTransientTextures,
+}
+
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system_to_stage(
-            StartupStage::PreStartup,
-            check_for_render_resource_context.system(),
-        );
+        app.init_resource::<DrawOrder>()
+            .init_resource::<RenderActive>()
+            .init_resource::<RenderQuality>()
+            .init_resource::<RenderQualitySettings>()
+            .init_resource::<RenderMode>()
+            .init_resource::<RenderRequested>()
+            .add_event::<RequestRender>()
+            .add_system_to_stage(CoreStage::PreUpdate, render_quality_system.system())
+            // runs last to see the changes of the whole frame
+            .add_system_to_stage(CoreStage::Last, render_mode_system.system())
+            .register_type::<Color>()
+            .add_startup_system_to_stage(
+                StartupStage::PreStartup,
+                check_for_render_resource_context.system(),
+            );
         let mut render_app = App::empty();
         let mut extract_stage = SystemStage::parallel();
         // don't apply buffers when the stage finishes running
@@ -75,10 +147,32 @@ impl Plugin for RenderPlugin {
             .add_stage(RenderStage::PhaseSort, SystemStage::parallel())
             .add_stage(RenderStage::Render, SystemStage::parallel())
             .add_stage(RenderStage::Cleanup, SystemStage::parallel())
+            .add_system_to_stage(RenderStage::Extract, extract_draw_order.system())
+            .add_system_to_stage(RenderStage::Extract, extract_render_quality.system())
             .init_resource::<RenderGraph>()
-            .init_resource::<DrawFunctions>();
+            .init_resource::<RenderGraphNodeTypes>()
+            .init_resource::<DrawFunctions>()
+            .init_resource::<DrawOrder>()
+            .init_resource::<RenderActive>()
+            .init_resource::<RenderTimings>()
+            .init_resource::<RenderResourceRegistry>()
+            .add_system_to_stage(RenderStage::Cleanup, clear_registry_views_system.system());
+
+        render_app
+            .world
+            .get_resource_mut::<RenderGraphNodeTypes>()
+            .unwrap()
+            .register("EmptyNode", |_| EmptyNode);
+
+        app.add_sub_app(RenderApp, render_app, |app_world, render_app| {
+            // skip the frame while the render backend is still creating its device
+            if let Some(mut pending) = app_world.remove_resource::<RendererPending>() {
+                if !pending.poll(app_world, render_app) {
+                    app_world.insert_resource(pending);
+                    return;
+                }
+            }
 
-        app.add_sub_app(render_app, |app_world, render_app| {
             // reserve all existing app entities for use in render_app
             // they can only be spawned using `get_or_spawn()`
             let meta_len = app_world.entities().meta.len();
@@ -92,42 +186,36 @@ impl Plugin for RenderPlugin {
             render_app.world.entities_mut().flush_as_invalid();
 
             // extract
+            let mut stage_timings = Vec::new();
+            let start = Instant::now();
             extract(app_world, render_app);
-
-            // prepare
-            let prepare = render_app
-                .schedule
-                .get_stage_mut::<SystemStage>(&RenderStage::Prepare)
-                .unwrap();
-            prepare.run(&mut render_app.world);
-
-            // queue
-            let queue = render_app
-                .schedule
-                .get_stage_mut::<SystemStage>(&RenderStage::Queue)
-                .unwrap();
-            queue.run(&mut render_app.world);
-
-            // phase sort
-            let phase_sort = render_app
-                .schedule
-                .get_stage_mut::<SystemStage>(&RenderStage::PhaseSort)
-                .unwrap();
-            phase_sort.run(&mut render_app.world);
-
-            // render
-            let render = render_app
-                .schedule
-                .get_stage_mut::<SystemStage>(&RenderStage::Render)
-                .unwrap();
-            render.run(&mut render_app.world);
-
-            // cleanup
-            let cleanup = render_app
-                .schedule
-                .get_stage_mut::<SystemStage>(&RenderStage::Cleanup)
+            stage_timings.push((
+                format!("{:?}", RenderStage::Extract).into(),
+                start.elapsed(),
+            ));
+
+            // run the remaining stages in order, including any custom stages added by plugins
+            for (label, stage) in render_app.schedule.iter_stages_mut() {
+                if label != &RenderStage::Extract as &dyn StageLabel {
+                    let start = Instant::now();
+                    stage.run(&mut render_app.world);
+                    stage_timings.push((format!("{:?}", label).into(), start.elapsed()));
+                }
+            }
+
+            // publish the timings of this frame to the app world
+            let mut render_timings = render_app
+                .world
+                .get_resource_mut::<RenderTimings>()
                 .unwrap();
-            cleanup.run(&mut render_app.world);
+            render_timings.stages = stage_timings;
+            app_world.insert_resource(render_timings.clone());
+            let texture_cache_stats = render_app
+                .world
+                .get_resource::<TextureCache>()
+                .unwrap()
+                .stats();
+            app_world.insert_resource(texture_cache_stats);
 
             render_app.world.clear_entities();
         });
@@ -150,8 +238,11 @@ fn extract(app_world: &mut World, render_app: &mut App) {
     extract.apply_buffers(&mut render_app.world);
 }
 
-fn check_for_render_resource_context(context: Option<Res<RenderResources>>) {
-    if context.is_none() {
+fn check_for_render_resource_context(
+    context: Option<Res<RenderResources>>,
+    pending: Option<Res<RendererPending>>,
+) {
+    if context.is_none() && pending.is_none() {
         warn!(
             "bevy_render couldn't find a render backend. Perhaps try adding the bevy_wgpu feature/plugin!"
         );
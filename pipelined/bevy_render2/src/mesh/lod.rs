@@ -0,0 +1,231 @@
+use super::{Mesh, SimplifyMeshError};
+use bevy_asset::{Assets, Handle};
+use bevy_math::{Mat4, Vec3};
+
+/// Swaps an entity's [`Mesh`] for simplified versions as it gets further away from a view.
+///
+/// The entity's own `Handle<Mesh>` is used until the [`LodThreshold`] of the first [`LodLevel`]
+/// is reached, and each level is used until the threshold of the next one is reached.
+#[derive(Debug, Clone, Default)]
+pub struct Lod {
+    levels: Vec<LodLevel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LodLevel {
+    pub mesh: Handle<Mesh>,
+    /// When this level starts being used.
+    pub threshold: LodThreshold,
+}
+
+/// When a [`LodLevel`] starts being used, see [`select_lod`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LodThreshold {
+    /// The level is used from this distance to the view onwards.
+    Distance(f32),
+    /// The level is used once its geometric error, the largest distance in world units between
+    /// its surface and the surface of the entity's own mesh, projects to at most `max_pixels`
+    /// pixels on the view's target.
+    ScreenSpaceError {
+        geometric_error: f32,
+        max_pixels: f32,
+    },
+}
+
+impl LodThreshold {
+    /// Returns whether the threshold is reached by a mesh at `position` seen from `view`.
+    pub fn is_reached(&self, view: &LodView, position: Vec3) -> bool {
+        match *self {
+            LodThreshold::Distance(min_distance) => view.distance(position) >= min_distance,
+            LodThreshold::ScreenSpaceError {
+                geometric_error,
+                max_pixels,
+            } => view.projected_size(geometric_error, position) <= max_pixels,
+        }
+    }
+
+    /// Levels are sorted by this key, from the most to the least detailed. Mixing distance and
+    /// screen space error thresholds in a [`Lod`] keeps them in the order they were added.
+    fn sort_key(&self) -> f32 {
+        match *self {
+            LodThreshold::Distance(min_distance) => min_distance,
+            LodThreshold::ScreenSpaceError {
+                geometric_error, ..
+            } => geometric_error,
+        }
+    }
+}
+
+/// The position and projection of a view, to select levels of detail with.
+#[derive(Debug, Clone, Copy)]
+pub struct LodView {
+    position: Vec3,
+    /// The pixels covered by a world unit, at a distance of one unit for perspective projections.
+    pixels_per_unit: f32,
+    perspective: bool,
+}
+
+impl LodView {
+    /// Creates the view seen from `position` with `projection`, drawn to a target `height`
+    /// pixels high.
+    pub fn new(position: Vec3, projection: &Mat4, height: u32) -> Self {
+        LodView {
+            position,
+            pixels_per_unit: projection.y_axis.y * height as f32 / 2.0,
+            // orthographic projections keep w at 1
+            perspective: projection.w_axis.w == 0.0,
+        }
+    }
+
+    pub fn distance(&self, position: Vec3) -> f32 {
+        self.position.distance(position)
+    }
+
+    /// Returns the number of pixels `size` world units at `position` cover.
+    pub fn projected_size(&self, size: f32, position: Vec3) -> f32 {
+        if self.perspective {
+            size * self.pixels_per_unit / self.distance(position).max(f32::EPSILON)
+        } else {
+            size * self.pixels_per_unit
+        }
+    }
+}
+
+/// Returns the index of the last of `thresholds` that is reached by a mesh at `position` seen
+/// from `view`, or `None` if none is. The thresholds must be sorted from the most to the least
+/// detailed level.
+pub fn select_lod<'a>(
+    thresholds: impl IntoIterator<Item = &'a LodThreshold>,
+    view: &LodView,
+    position: Vec3,
+) -> Option<usize> {
+    thresholds
+        .into_iter()
+        .enumerate()
+        .filter(|(_, threshold)| threshold.is_reached(view, position))
+        .map(|(index, _)| index)
+        .last()
+}
+
+impl Lod {
+    /// Adds a level used from `min_distance` onwards. Levels are kept sorted by distance.
+    pub fn with_level(self, min_distance: f32, mesh: Handle<Mesh>) -> Self {
+        self.with_threshold(LodThreshold::Distance(min_distance), mesh)
+    }
+
+    /// Adds a level used once `threshold` is reached. Levels are kept sorted from the most to
+    /// the least detailed.
+    pub fn with_threshold(mut self, threshold: LodThreshold, mesh: Handle<Mesh>) -> Self {
+        self.add_level(threshold, mesh);
+        self
+    }
+
+    /// Adds a level used once `threshold` is reached. Levels are kept sorted from the most to
+    /// the least detailed.
+    pub fn add_level(&mut self, threshold: LodThreshold, mesh: Handle<Mesh>) {
+        let index = self
+            .levels
+            .iter()
+            .position(|level| {
+                std::mem::discriminant(&level.threshold) == std::mem::discriminant(&threshold)
+                    && level.threshold.sort_key() > threshold.sort_key()
+            })
+            .unwrap_or_else(|| self.levels.len());
+        self.levels.insert(index, LodLevel { mesh, threshold });
+    }
+
+    /// Generates a level for each `(min_distance, ratio)` in `levels` by simplifying `mesh` to
+    /// `ratio` of its triangles with [`Mesh::simplify`], and adds the simplified meshes to
+    /// `meshes`. Use it once when a mesh is loaded, as simplification is slow.
+    pub fn generate(
+        mesh: &Mesh,
+        levels: &[(f32, f32)],
+        meshes: &mut Assets<Mesh>,
+    ) -> Result<Self, SimplifyMeshError> {
+        let mut lod = Lod::default();
+        for &(min_distance, ratio) in levels {
+            let mut simplified = mesh.clone();
+            simplified.simplify(ratio)?;
+            lod.add_level(LodThreshold::Distance(min_distance), meshes.add(simplified));
+        }
+        Ok(lod)
+    }
+
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    /// Returns the level to use for a mesh at `position` seen from `view`, or `None` if the
+    /// entity's own mesh should be used.
+    pub fn select(&self, view: &LodView, position: Vec3) -> Option<&LodLevel> {
+        select_lod(
+            self.levels.iter().map(|level| &level.threshold),
+            view,
+            position,
+        )
+        .map(|index| &self.levels[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lod, LodThreshold, LodView};
+    use bevy_asset::{Handle, HandleId};
+    use bevy_math::{Mat4, Vec3};
+
+    #[test]
+    fn select() {
+        let near = Handle::weak(HandleId::random::<super::Mesh>());
+        let far = Handle::weak(HandleId::random::<super::Mesh>());
+        let lod = Lod::default()
+            .with_level(50.0, far.clone())
+            .with_level(10.0, near.clone());
+        let view = LodView::new(Vec3::ZERO, &Mat4::IDENTITY, 100);
+        let at = |distance: f32| Vec3::new(0.0, 0.0, -distance);
+
+        assert!(lod.select(&view, at(5.0)).is_none());
+        assert_eq!(lod.select(&view, at(10.0)).unwrap().mesh, near);
+        assert_eq!(lod.select(&view, at(20.0)).unwrap().mesh, near);
+        assert_eq!(lod.select(&view, at(100.0)).unwrap().mesh, far);
+    }
+
+    #[test]
+    fn select_by_screen_space_error() {
+        let coarse = Handle::weak(HandleId::random::<super::Mesh>());
+        let fine = Handle::weak(HandleId::random::<super::Mesh>());
+        let lod = Lod::default()
+            .with_threshold(
+                LodThreshold::ScreenSpaceError {
+                    geometric_error: 0.4,
+                    max_pixels: 1.0,
+                },
+                coarse.clone(),
+            )
+            .with_threshold(
+                LodThreshold::ScreenSpaceError {
+                    geometric_error: 0.1,
+                    max_pixels: 1.0,
+                },
+                fine.clone(),
+            );
+        // a vertical field of view of 90 degrees, so a unit at a distance of one unit covers
+        // half the target's height
+        let projection =
+            Mat4::perspective_infinite_reverse_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1);
+        let view = LodView::new(Vec3::ZERO, &projection, 100);
+        let at = |distance: f32| Vec3::new(0.0, 0.0, -distance);
+
+        assert!((view.projected_size(1.0, at(1.0)) - 50.0).abs() < 1e-3);
+        assert!(lod.select(&view, at(4.0)).is_none());
+        assert_eq!(lod.select(&view, at(6.0)).unwrap().mesh, fine);
+        assert_eq!(lod.select(&view, at(25.0)).unwrap().mesh, coarse);
+        // the error of orthographic views doesn't depend on the distance
+        let view = LodView::new(
+            Vec3::ZERO,
+            &Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 0.0, 1000.0),
+            10,
+        );
+        assert_eq!(lod.select(&view, at(1.0)).unwrap().mesh, fine);
+        assert_eq!(lod.select(&view, at(500.0)).unwrap().mesh, fine);
+    }
+}
@@ -1,19 +1,27 @@
 mod conversions;
 mod mesh_resource_provider;
+mod quantize;
+mod simplify;
+mod tangents;
 
 pub use mesh_resource_provider::*;
+pub use quantize::*;
+pub use simplify::*;
+pub use tangents::*;
 
 use crate::{
     pipeline::{
         IndexFormat, InputStepMode, PrimitiveTopology, VertexAttribute, VertexBufferLayout,
-        VertexFormat,
+        VertexFormat, VertexLayoutError,
     },
     render_resource::BufferId,
+    shader::ShaderLayout,
+    view::Aabb,
 };
 use bevy_core::cast_slice;
 use bevy_math::*;
 use bevy_reflect::TypeUuid;
-use bevy_utils::EnumVariantMeta;
+use bevy_utils::{EnumVariantMeta, HashMap};
 use std::{borrow::Cow, collections::BTreeMap};
 
 pub const INDEX_BUFFER_ASSET_INDEX: u64 = 0;
@@ -50,6 +58,12 @@ pub enum VertexAttributeValues {
     Snorm8x4(Vec<[i8; 4]>),
     Uint8x4(Vec<[u8; 4]>),
     Unorm8x4(Vec<[u8; 4]>),
+    // there are no `From<Vec<f64>>` conversions for these, so that untyped float literals keep
+    // being inferred as `f32`
+    Float64(Vec<f64>),
+    Float64x2(Vec<[f64; 2]>),
+    Float64x3(Vec<[f64; 3]>),
+    Float64x4(Vec<[f64; 4]>),
 }
 
 impl VertexAttributeValues {
@@ -85,6 +99,10 @@ impl VertexAttributeValues {
             VertexAttributeValues::Snorm8x4(ref values) => values.len(),
             VertexAttributeValues::Uint8x4(ref values) => values.len(),
             VertexAttributeValues::Unorm8x4(ref values) => values.len(),
+            VertexAttributeValues::Float64(ref values) => values.len(),
+            VertexAttributeValues::Float64x2(ref values) => values.len(),
+            VertexAttributeValues::Float64x3(ref values) => values.len(),
+            VertexAttributeValues::Float64x4(ref values) => values.len(),
         }
     }
 
@@ -93,6 +111,13 @@ impl VertexAttributeValues {
         self.len() == 0
     }
 
+    fn as_float2(&self) -> Option<&[[f32; 2]]> {
+        match self {
+            VertexAttributeValues::Float32x2(values) => Some(values),
+            _ => None,
+        }
+    }
+
     fn as_float3(&self) -> Option<&[[f32; 3]]> {
         match self {
             VertexAttributeValues::Float32x3(values) => Some(values),
@@ -100,6 +125,49 @@ impl VertexAttributeValues {
         }
     }
 
+    /// Rebuilds the values so that the `n`th value is the value previously found at the `n`th
+    /// index yielded by `indices`.
+    fn reorder(&mut self, indices: impl Iterator<Item = usize>) {
+        fn reorder<T: Copy>(values: &[T], indices: impl Iterator<Item = usize>) -> Vec<T> {
+            indices.map(|i| values[i]).collect()
+        }
+
+        match self {
+            VertexAttributeValues::Float32(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint32(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint32(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float32x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint32x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint32x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float32x3(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint32x3(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint32x3(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint32x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint32x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float32x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint16x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Snorm16x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint16x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Unorm16x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint16x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Snorm16x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint16x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Unorm16x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint8x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Snorm8x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint8x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Unorm8x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Sint8x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Snorm8x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Uint8x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Unorm8x4(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float64(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float64x2(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float64x3(vec) => *vec = reorder(&vec, indices),
+            VertexAttributeValues::Float64x4(vec) => *vec = reorder(&vec, indices),
+        }
+    }
+
     // TODO: add vertex format as parameter here and perform type conversions
     /// Flattens the VertexAttributeArray into a sequence of bytes. This is
     /// useful for serialization and sending to the GPU.
@@ -133,6 +201,10 @@ impl VertexAttributeValues {
             VertexAttributeValues::Snorm8x4(values) => cast_slice(&values[..]),
             VertexAttributeValues::Uint8x4(values) => cast_slice(&values[..]),
             VertexAttributeValues::Unorm8x4(values) => cast_slice(&values[..]),
+            VertexAttributeValues::Float64(values) => cast_slice(&values[..]),
+            VertexAttributeValues::Float64x2(values) => cast_slice(&values[..]),
+            VertexAttributeValues::Float64x3(values) => cast_slice(&values[..]),
+            VertexAttributeValues::Float64x4(values) => cast_slice(&values[..]),
         }
     }
 }
@@ -168,6 +240,10 @@ impl From<&VertexAttributeValues> for VertexFormat {
             VertexAttributeValues::Snorm8x4(_) => VertexFormat::Snorm8x4,
             VertexAttributeValues::Uint8x4(_) => VertexFormat::Uint8x4,
             VertexAttributeValues::Unorm8x4(_) => VertexFormat::Unorm8x4,
+            VertexAttributeValues::Float64(_) => VertexFormat::Float64,
+            VertexAttributeValues::Float64x2(_) => VertexFormat::Float64x2,
+            VertexAttributeValues::Float64x3(_) => VertexFormat::Float64x3,
+            VertexAttributeValues::Float64x4(_) => VertexFormat::Float64x4,
         }
     }
 }
@@ -220,6 +296,31 @@ impl From<&Indices> for IndexFormat {
     }
 }
 
+/// Describes a vertex attribute of a [`Mesh`]: the name it is set with, the shader location it
+/// is bound to and the format of its values. Meshes pack their vertex buffers in order of
+/// shader location, so shaders can rely on the same layout for every mesh with the same
+/// attributes.
+///
+/// The built-in attributes are listed in [`Mesh::VERTEX_ATTRIBUTES`]. Custom attributes are
+/// added with [`Mesh::insert_attribute`], at a location of [`Mesh::FIRST_CUSTOM_LOCATION`] or
+/// above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshVertexAttribute {
+    pub name: &'static str,
+    pub shader_location: u32,
+    pub format: VertexFormat,
+}
+
+impl MeshVertexAttribute {
+    pub const fn new(name: &'static str, shader_location: u32, format: VertexFormat) -> Self {
+        MeshVertexAttribute {
+            name,
+            shader_location,
+            format,
+        }
+    }
+}
+
 // TODO: this shouldn't live in the Mesh type
 #[derive(Debug, Clone)]
 pub struct MeshGpuData {
@@ -233,10 +334,11 @@ pub struct MeshGpuData {
 pub struct Mesh {
     primitive_topology: PrimitiveTopology,
     /// `std::collections::BTreeMap` with all defined vertex attributes (Positions, Normals, ...)
-    /// for this mesh. Attribute name maps to attribute values.
-    /// Uses a BTreeMap because, unlike HashMap, it has a defined iteration order,
-    /// which allows easy stable VertexBuffers (i.e. same buffer order)
+    /// for this mesh. Attribute name maps to attribute values. The vertex buffer is packed in
+    /// order of shader location, see [`Mesh::get_vertex_buffer_layout`].
     attributes: BTreeMap<Cow<'static, str>, VertexAttributeValues>,
+    /// The custom attributes added with [`Mesh::insert_attribute`].
+    custom_attributes: Vec<MeshVertexAttribute>,
     indices: Option<Indices>,
     gpu_data: Option<MeshGpuData>,
 }
@@ -259,7 +361,8 @@ pub struct Mesh {
 /// }
 /// ```
 impl Mesh {
-    /// Per vertex coloring. Use in conjunction with [`Mesh::set_attribute`]
+    /// Per vertex coloring, multiplied with the material's base color. Use in conjunction with
+    /// [`Mesh::set_attribute`]. Colors are expected to be linear `Float32x4` values.
     pub const ATTRIBUTE_COLOR: &'static str = "Vertex_Color";
     /// The direction the vertex normal is facing in.
     /// Use in conjunction with [`Mesh::set_attribute`]
@@ -271,12 +374,34 @@ impl Mesh {
     pub const ATTRIBUTE_POSITION: &'static str = "Vertex_Position";
     /// Texture coordinates for the vertex. Use in conjunction with [`Mesh::set_attribute`]
     pub const ATTRIBUTE_UV_0: &'static str = "Vertex_Uv";
+    /// A second set of texture coordinates, typically unique and non-overlapping so that baked
+    /// lighting can be mapped onto the mesh. Use in conjunction with [`Mesh::set_attribute`]
+    pub const ATTRIBUTE_UV_1: &'static str = "Vertex_Uv_1";
 
     /// Per vertex joint transform matrix weight. Use in conjunction with [`Mesh::set_attribute`]
     pub const ATTRIBUTE_JOINT_WEIGHT: &'static str = "Vertex_JointWeight";
     /// Per vertex joint transform matrix index. Use in conjunction with [`Mesh::set_attribute`]
     pub const ATTRIBUTE_JOINT_INDEX: &'static str = "Vertex_JointIndex";
 
+    /// The shader locations and formats of the built-in attributes. The values of these
+    /// attributes may be set in other formats, in which case shaders reading them must be
+    /// specialized for that format, unless it is compatible with the format here, see
+    /// [`VertexFormat::is_compatible_with`] and [`Mesh::quantize_attribute`].
+    pub const VERTEX_ATTRIBUTES: &'static [MeshVertexAttribute] = &[
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_POSITION, 0, VertexFormat::Float32x3),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_NORMAL, 1, VertexFormat::Float32x3),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_UV_0, 2, VertexFormat::Float32x2),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_COLOR, 3, VertexFormat::Float32x4),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_TANGENT, 4, VertexFormat::Float32x4),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_JOINT_WEIGHT, 5, VertexFormat::Float32x4),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_JOINT_INDEX, 6, VertexFormat::Uint16x4),
+        MeshVertexAttribute::new(Mesh::ATTRIBUTE_UV_1, 7, VertexFormat::Float32x2),
+    ];
+
+    /// The first shader location available to custom attributes. Lower locations are reserved
+    /// for the built-in attributes.
+    pub const FIRST_CUSTOM_LOCATION: u32 = 8;
+
     /// Construct a new mesh. You need to provide a PrimitiveTopology so that the
     /// renderer knows how to treat the vertex data. Most of the time this will be
     /// `PrimitiveTopology::TriangleList`.
@@ -284,6 +409,7 @@ impl Mesh {
         Mesh {
             primitive_topology,
             attributes: Default::default(),
+            custom_attributes: Vec::new(),
             indices: None,
             gpu_data: None,
         }
@@ -308,6 +434,69 @@ impl Mesh {
         self.attributes.insert(name.into(), values);
     }
 
+    /// Sets the values of a custom vertex attribute, and binds it to its shader location.
+    ///
+    /// Panics if the values don't have the format of the attribute, if its location is below
+    /// [`Mesh::FIRST_CUSTOM_LOCATION`], or if another custom attribute of this mesh uses the same
+    /// name or location.
+    pub fn insert_attribute(
+        &mut self,
+        attribute: MeshVertexAttribute,
+        values: impl Into<VertexAttributeValues>,
+    ) {
+        let values: VertexAttributeValues = values.into();
+        let format = VertexFormat::from(&values);
+        assert_eq!(
+            format, attribute.format,
+            "attribute {} is {:?}, but its values are {:?}",
+            attribute.name, attribute.format, format
+        );
+        assert!(
+            Mesh::VERTEX_ATTRIBUTES
+                .iter()
+                .all(|built_in| built_in.name != attribute.name),
+            "attribute {} has the name of a built-in attribute, use `Mesh::set_attribute` instead",
+            attribute.name
+        );
+        assert!(
+            attribute.shader_location >= Mesh::FIRST_CUSTOM_LOCATION,
+            "attribute {} uses location {}, which is reserved for the built-in attributes",
+            attribute.name,
+            attribute.shader_location
+        );
+        if let Some(existing) = self
+            .custom_attributes
+            .iter()
+            .find(|existing| existing.name == attribute.name)
+        {
+            assert_eq!(
+                *existing, attribute,
+                "attribute {} was already inserted with another location or format",
+                attribute.name
+            );
+        } else {
+            assert!(
+                self.custom_attributes
+                    .iter()
+                    .all(|existing| existing.shader_location != attribute.shader_location),
+                "attribute {} uses location {}, which is already used by another attribute",
+                attribute.name,
+                attribute.shader_location
+            );
+            self.custom_attributes.push(attribute);
+        }
+        self.attributes.insert(attribute.name.into(), values);
+    }
+
+    /// Returns the descriptor of the built-in or custom attribute named `name`.
+    pub fn attribute_descriptor(&self, name: &str) -> Option<MeshVertexAttribute> {
+        Mesh::VERTEX_ATTRIBUTES
+            .iter()
+            .chain(self.custom_attributes.iter())
+            .find(|attribute| attribute.name == name)
+            .copied()
+    }
+
     /// Retrieve the data currently set behind a vertex attribute.
     pub fn attribute(&self, name: impl Into<Cow<'static, str>>) -> Option<&VertexAttributeValues> {
         self.attributes.get(&name.into())
@@ -342,28 +531,80 @@ impl Mesh {
         })
     }
 
+    /// Returns the attributes set on this mesh with their shader locations, in the order they
+    /// are packed in the vertex buffer.
+    ///
+    /// Attributes set with a name that isn't a built-in or inserted attribute are bound to the
+    /// locations following the others, in order of name.
+    fn located_attributes(&self) -> Vec<(u32, &Cow<'static, str>, &VertexAttributeValues)> {
+        let mut attributes = Vec::with_capacity(self.attributes.len());
+        let mut unknown_attributes = Vec::new();
+        for (name, values) in self.attributes.iter() {
+            match self.attribute_descriptor(name) {
+                Some(attribute) => attributes.push((attribute.shader_location, name, values)),
+                None => unknown_attributes.push((name, values)),
+            }
+        }
+        attributes.sort_by_key(|(location, _, _)| *location);
+        let next_location = attributes
+            .last()
+            .map_or(Mesh::FIRST_CUSTOM_LOCATION, |(location, _, _)| {
+                (location + 1).max(Mesh::FIRST_CUSTOM_LOCATION)
+            });
+        attributes.extend(
+            unknown_attributes
+                .into_iter()
+                .enumerate()
+                .map(|(i, (name, values))| (next_location + i as u32, name, values)),
+        );
+        attributes
+    }
+
+    /// Returns the layout of the vertex buffer of this mesh. Attributes are packed in order of
+    /// shader location, see [`MeshVertexAttribute`].
     pub fn get_vertex_buffer_layout(&self) -> VertexBufferLayout {
         let mut attributes = Vec::new();
         let mut accumulated_offset = 0;
-        for (attribute_name, attribute_values) in self.attributes.iter() {
+        for (shader_location, attribute_name, attribute_values) in self.located_attributes() {
             let vertex_format = VertexFormat::from(attribute_values);
             attributes.push(VertexAttribute {
                 name: attribute_name.clone(),
                 offset: accumulated_offset,
                 format: vertex_format,
-                shader_location: 0,
+                shader_location,
             });
             accumulated_offset += vertex_format.get_size();
         }
 
         VertexBufferLayout {
-            name: Default::default(),
+            name: "Vertex".into(),
             stride: accumulated_offset,
             step_mode: InputStepMode::Vertex,
             attributes,
         }
     }
 
+    /// Specializes the vertex buffer layout of this mesh for a pipeline: returns the layout with
+    /// only the attributes read by `vertex_shader_layout`, or the attributes it reads that this
+    /// mesh doesn't provide in a compatible format.
+    pub fn vertex_buffer_layout_for(
+        &self,
+        vertex_shader_layout: &ShaderLayout,
+    ) -> Result<VertexBufferLayout, VertexLayoutError> {
+        let mut layout = self.get_vertex_buffer_layout();
+        let inputs = vertex_shader_layout
+            .vertex_buffer_layout
+            .iter()
+            .flat_map(|buffer| buffer.attributes.iter())
+            .map(|input| input.shader_location)
+            .collect::<Vec<_>>();
+        layout
+            .attributes
+            .retain(|attribute| inputs.contains(&attribute.shader_location));
+        vertex_shader_layout.validate_vertex_buffers(std::slice::from_ref(&layout))?;
+        Ok(layout)
+    }
+
     pub fn count_vertices(&self) -> usize {
         let mut vertex_count: Option<usize> = None;
         for (attribute_name, attribute_data) in self.attributes.iter() {
@@ -378,6 +619,8 @@ impl Mesh {
         vertex_count.unwrap_or(0)
     }
 
+    /// Returns the interleaved vertex buffer of this mesh, with the layout returned by
+    /// [`Mesh::get_vertex_buffer_layout`].
     pub fn get_vertex_buffer_data(&self) -> Vec<u8> {
         let mut vertex_size = 0;
         for attribute_values in self.attributes.values() {
@@ -389,7 +632,7 @@ impl Mesh {
         let mut attributes_interleaved_buffer = vec![0; vertex_count * vertex_size];
         // bundle into interleaved buffers
         let mut attribute_offset = 0;
-        for attribute_values in self.attributes.values() {
+        for (_, _, attribute_values) in self.located_attributes() {
             let vertex_format = VertexFormat::from(attribute_values);
             let attribute_size = vertex_format.get_size() as usize;
             let attributes_bytes = attribute_values.get_bytes();
@@ -412,10 +655,6 @@ impl Mesh {
     /// This can dramatically increase the vertex count, so make sure this is what you want.
     /// Does nothing if no [Indices] are set.
     pub fn duplicate_vertices(&mut self) {
-        fn duplicate<T: Copy>(values: &[T], indices: impl Iterator<Item = usize>) -> Vec<T> {
-            indices.map(|i| values[i]).collect()
-        }
-
         assert!(
             matches!(self.primitive_topology, PrimitiveTopology::TriangleList),
             "can only duplicate vertices for `TriangleList`s"
@@ -426,40 +665,22 @@ impl Mesh {
             None => return,
         };
         for (_, attributes) in self.attributes.iter_mut() {
-            let indices = indices.iter();
-            match attributes {
-                VertexAttributeValues::Float32(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint32(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint32(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Float32x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint32x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint32x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Float32x3(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint32x3(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint32x3(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint32x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint32x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Float32x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint16x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Snorm16x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint16x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Unorm16x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint16x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Snorm16x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint16x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Unorm16x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint8x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Snorm8x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint8x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Unorm8x2(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Sint8x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Snorm8x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Uint8x4(vec) => *vec = duplicate(&vec, indices),
-                VertexAttributeValues::Unorm8x4(vec) => *vec = duplicate(&vec, indices),
-            }
+            attributes.reorder(indices.iter());
         }
     }
 
+    /// Returns the bounds of the [`Mesh::ATTRIBUTE_POSITION`]s of the mesh, or `None` if it has
+    /// no positions.
+    pub fn compute_aabb(&self) -> Option<Aabb> {
+        let positions = self.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+        let mut positions = positions.iter().map(|position| Vec3::from(*position));
+        let first = positions.next()?;
+        let (min, max) = positions.fold((first, first), |(min, max), position| {
+            (min.min(position), max.max(position))
+        });
+        Some(Aabb::from_min_max(min, max))
+    }
+
     /// Calculates the [`Mesh::ATTRIBUTE_NORMAL`] of a mesh.
     ///
     /// Panics if [`Indices`] are set.
@@ -483,9 +704,297 @@ impl Mesh {
 
         self.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     }
+
+    /// Calculates the [`Mesh::ATTRIBUTE_NORMAL`] of a mesh by averaging the normals of all the
+    /// faces sharing a vertex, weighted by the area of each face.
+    ///
+    /// Unlike [`Mesh::compute_flat_normals`], this works on both indexed and non-indexed
+    /// geometry. Non-indexed geometry shares no vertices, so consider calling
+    /// [`Mesh::deduplicate_vertices`] first to get smooth results.
+    pub fn compute_smooth_normals(&mut self) {
+        assert!(
+            matches!(self.primitive_topology, PrimitiveTopology::TriangleList),
+            "can only compute smooth normals for `TriangleList`s"
+        );
+
+        let positions = self
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .unwrap()
+            .as_float3()
+            .expect("`Mesh::ATTRIBUTE_POSITION` vertex attributes should be of type `float3`");
+
+        let mut normals = vec![Vec3::ZERO; positions.len()];
+        let triangle_indices: Vec<usize> = match &self.indices {
+            Some(indices) => indices.iter().collect(),
+            None => (0..positions.len()).collect(),
+        };
+        for triangle in triangle_indices.chunks_exact(3) {
+            let (a, b, c) = (
+                Vec3::from(positions[triangle[0]]),
+                Vec3::from(positions[triangle[1]]),
+                Vec3::from(positions[triangle[2]]),
+            );
+            // the length of the cross product is twice the area of the face
+            let weighted_normal = (b - a).cross(c - a);
+            for &index in triangle {
+                normals[index] += weighted_normal;
+            }
+        }
+
+        let normals: Vec<[f32; 3]> = normals
+            .into_iter()
+            .map(|normal| normal.normalize_or_zero().into())
+            .collect();
+        self.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    }
+
+    /// Generates the [`Mesh::ATTRIBUTE_TANGENT`] of a mesh using the mikktspace algorithm.
+    ///
+    /// Requires [`Mesh::ATTRIBUTE_POSITION`], [`Mesh::ATTRIBUTE_NORMAL`] and
+    /// [`Mesh::ATTRIBUTE_UV_0`] to be set. The handedness of the bitangent is stored in the
+    /// `w` component of each tangent.
+    pub fn generate_tangents(&mut self) -> Result<(), GenerateTangentsError> {
+        let tangents = generate_tangents_for_mesh(self)?;
+        self.set_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+        Ok(())
+    }
+
+    /// Merges vertices with identical attribute values, and sets [`Indices`] referencing the
+    /// remaining unique vertices. This is the inverse of [`Mesh::duplicate_vertices`].
+    ///
+    /// Existing indices keep their format; non-indexed meshes get [`Indices::U32`].
+    pub fn deduplicate_vertices(&mut self) {
+        assert!(
+            matches!(self.primitive_topology, PrimitiveTopology::TriangleList),
+            "can only deduplicate vertices for `TriangleList`s"
+        );
+
+        let vertex_count = self.count_vertices();
+        let attribute_bytes: Vec<(&[u8], usize)> = self
+            .attributes
+            .values()
+            .map(|values| {
+                (
+                    values.get_bytes(),
+                    VertexFormat::from(values).get_size() as usize,
+                )
+            })
+            .collect();
+
+        // maps each original vertex to its unique vertex
+        let mut remap = Vec::with_capacity(vertex_count);
+        // the original vertex each unique vertex was taken from
+        let mut unique_vertices = Vec::new();
+        let mut vertex_lookup = HashMap::default();
+        for vertex in 0..vertex_count {
+            let key: Vec<u8> = attribute_bytes
+                .iter()
+                .flat_map(|(bytes, size)| &bytes[vertex * size..(vertex + 1) * size])
+                .copied()
+                .collect();
+            let unique_vertex = *vertex_lookup.entry(key).or_insert_with(|| {
+                unique_vertices.push(vertex);
+                unique_vertices.len() - 1
+            });
+            remap.push(unique_vertex);
+        }
+
+        for attributes in self.attributes.values_mut() {
+            attributes.reorder(unique_vertices.iter().copied());
+        }
+
+        self.indices = Some(match self.indices.take() {
+            Some(Indices::U16(indices)) => Indices::U16(
+                indices
+                    .into_iter()
+                    .map(|i| remap[i as usize] as u16)
+                    .collect(),
+            ),
+            Some(Indices::U32(indices)) => Indices::U32(
+                indices
+                    .into_iter()
+                    .map(|i| remap[i as usize] as u32)
+                    .collect(),
+            ),
+            None => Indices::U32(remap.into_iter().map(|i| i as u32).collect()),
+        });
+    }
 }
 
 fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
     let (a, b, c) = (Vec3::from(a), Vec3::from(b), Vec3::from(c));
     (b - a).cross(c - a).normalize().into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Indices, Mesh, MeshVertexAttribute, VertexAttributeValues};
+    use crate::{
+        pipeline::{InputStepMode, PrimitiveTopology, VertexBufferLayout, VertexFormat},
+        shader::ShaderLayout,
+    };
+
+    fn quad() -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+        );
+        mesh
+    }
+
+    #[test]
+    fn quantize_attributes() {
+        let mut mesh = quad();
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 6]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.5]; 6]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_TANGENT, vec![[-1.0, 0.0, 0.0, -1.0]; 6]);
+        mesh.quantize_standard_attributes();
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Snorm16x4(normals)) => {
+                assert_eq!(normals[0], [0, 0, i16::MAX, 0])
+            }
+            other => panic!("unexpected normals: {:?}", other),
+        }
+        match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+            Some(VertexAttributeValues::Snorm16x4(tangents)) => {
+                assert_eq!(tangents[0], [-i16::MAX, 0, 0, -i16::MAX])
+            }
+            other => panic!("unexpected tangents: {:?}", other),
+        }
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Unorm16x2(uvs)) => assert_eq!(uvs[0], [0, 32768]),
+            other => panic!("unexpected uvs: {:?}", other),
+        }
+        assert_eq!(mesh.get_vertex_buffer_layout().stride, 12 + 8 + 4 + 8);
+        assert!(VertexFormat::Snorm16x4.is_compatible_with(VertexFormat::Float32x3));
+
+        assert!(mesh
+            .quantize_attribute(Mesh::ATTRIBUTE_POSITION, VertexFormat::Unorm8x2)
+            .is_err());
+        assert!(mesh
+            .quantize_attribute(Mesh::ATTRIBUTE_COLOR, VertexFormat::Unorm8x4)
+            .is_err());
+    }
+
+    #[test]
+    fn deduplicate_vertices() {
+        let mut mesh = quad();
+        mesh.deduplicate_vertices();
+        assert_eq!(mesh.count_vertices(), 4);
+        match mesh.indices() {
+            Some(Indices::U32(indices)) => assert_eq!(indices, &[0, 1, 2, 0, 2, 3]),
+            _ => panic!("expected u32 indices"),
+        }
+
+        mesh.duplicate_vertices();
+        assert_eq!(mesh.count_vertices(), 6);
+        assert!(mesh.indices().is_none());
+    }
+
+    #[test]
+    fn compute_smooth_normals() {
+        let mut mesh = quad();
+        mesh.deduplicate_vertices();
+        mesh.compute_smooth_normals();
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => {
+                assert!(normals.iter().all(|normal| *normal == [0.0, 0.0, 1.0]))
+            }
+            _ => panic!("expected float3 normals"),
+        }
+    }
+
+    #[test]
+    fn simplify() {
+        let mut mesh = Mesh::from(crate::mesh::shape::Icosphere {
+            radius: 1.0,
+            subdivisions: 4,
+        });
+        let triangle_count = mesh.indices().unwrap().len() / 3;
+        let vertex_count = mesh.count_vertices();
+        mesh.simplify(0.25).unwrap();
+        let simplified_count = mesh.indices().unwrap().len() / 3;
+        assert!(simplified_count > 0 && simplified_count <= triangle_count / 4);
+        assert!(mesh.count_vertices() < vertex_count);
+        // the remaining vertices keep their positions on the sphere
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => assert!(positions
+                .iter()
+                .all(|position| (bevy_math::Vec3::from(*position).length() - 1.0).abs() < 1e-4)),
+            _ => panic!("expected float3 positions"),
+        }
+        assert!(mesh
+            .indices()
+            .unwrap()
+            .iter()
+            .all(|index| index < mesh.count_vertices()));
+
+        // a quad has no interior vertices to collapse
+        let mut mesh = quad();
+        mesh.simplify(0.5).unwrap();
+        assert_eq!(mesh.indices().unwrap().len(), 6);
+    }
+
+    const ATTRIBUTE_SPLAT_WEIGHTS: MeshVertexAttribute =
+        MeshVertexAttribute::new("Vertex_SplatWeights", 8, VertexFormat::Float32x4);
+
+    #[test]
+    fn vertex_buffer_layout() {
+        let mut mesh = quad();
+        mesh.insert_attribute(ATTRIBUTE_SPLAT_WEIGHTS, vec![[1.0, 0.0, 0.0, 0.0]; 6]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; 6]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 6]);
+
+        // packed in order of shader location rather than name
+        let layout = mesh.get_vertex_buffer_layout();
+        let attributes = layout
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.shader_location, attribute.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(attributes, vec![(0, 0), (1, 12), (2, 24), (8, 32)]);
+        assert_eq!(layout.stride, 48);
+        assert_eq!(mesh.get_vertex_buffer_data().len(), 6 * 48);
+
+        // a shader reading the positions and splat weights only gets these attributes
+        let shader_layout = ShaderLayout {
+            bind_groups: Vec::new(),
+            vertex_buffer_layout: vec![VertexBufferLayout::build("Vertex")
+                .add_attribute(0, Mesh::ATTRIBUTE_POSITION, VertexFormat::Float32x3)
+                .add_attribute(8, "Vertex_SplatWeights", VertexFormat::Float32x4)
+                .step_mode(InputStepMode::Vertex)
+                .finish()],
+            entry_point: "main".to_string(),
+        };
+        let specialized = mesh.vertex_buffer_layout_for(&shader_layout).unwrap();
+        let locations = specialized
+            .attributes
+            .iter()
+            .map(|attribute| attribute.shader_location)
+            .collect::<Vec<_>>();
+        assert_eq!(locations, vec![0, 8]);
+        assert_eq!(specialized.stride, 48);
+
+        let mut mesh = quad();
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 6]);
+        assert!(mesh.vertex_buffer_layout_for(&shader_layout).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_attribute_reserved_location() {
+        let mut mesh = quad();
+        mesh.insert_attribute(
+            MeshVertexAttribute::new("Vertex_Custom", 3, VertexFormat::Float32),
+            vec![0.0; 6],
+        );
+    }
+}
@@ -354,6 +354,50 @@ impl TryFrom<VertexAttributeValues> for Vec<f32> {
     }
 }
 
+impl TryFrom<VertexAttributeValues> for Vec<f64> {
+    type Error = FromVertexAttributeError;
+
+    fn try_from(value: VertexAttributeValues) -> Result<Self, Self::Error> {
+        match value {
+            VertexAttributeValues::Float64(value) => Ok(value),
+            _ => Err(FromVertexAttributeError::new::<Self>(value)),
+        }
+    }
+}
+
+impl TryFrom<VertexAttributeValues> for Vec<[f64; 2]> {
+    type Error = FromVertexAttributeError;
+
+    fn try_from(value: VertexAttributeValues) -> Result<Self, Self::Error> {
+        match value {
+            VertexAttributeValues::Float64x2(value) => Ok(value),
+            _ => Err(FromVertexAttributeError::new::<Self>(value)),
+        }
+    }
+}
+
+impl TryFrom<VertexAttributeValues> for Vec<[f64; 3]> {
+    type Error = FromVertexAttributeError;
+
+    fn try_from(value: VertexAttributeValues) -> Result<Self, Self::Error> {
+        match value {
+            VertexAttributeValues::Float64x3(value) => Ok(value),
+            _ => Err(FromVertexAttributeError::new::<Self>(value)),
+        }
+    }
+}
+
+impl TryFrom<VertexAttributeValues> for Vec<[f64; 4]> {
+    type Error = FromVertexAttributeError;
+
+    fn try_from(value: VertexAttributeValues) -> Result<Self, Self::Error> {
+        match value {
+            VertexAttributeValues::Float64x4(value) => Ok(value),
+            _ => Err(FromVertexAttributeError::new::<Self>(value)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::VertexAttributeValues;
@@ -502,6 +546,17 @@ mod tests {
         assert!(error.is_err());
     }
 
+    #[test]
+    fn f64_3() {
+        let buffer = vec![[0.0_f64; 3]; 10];
+        let values = VertexAttributeValues::Float64x3(buffer.clone());
+        let result_into: Vec<[f64; 3]> = values.clone().try_into().unwrap();
+        let error: Result<Vec<[f32; 3]>, _> = values.clone().try_into();
+        assert_eq!(buffer, result_into);
+        assert!(error.is_err());
+        assert_eq!(values.get_bytes().len(), 10 * 24);
+    }
+
     #[test]
     fn correct_message() {
         let buffer = vec![[0_u32; 4]; 3];
@@ -21,11 +21,23 @@ fn remove_current_mesh_resources(
 }
 
 pub fn mesh_resource_provider_system(
-    render_resource_context: Res<RenderResources>,
+    render_resource_context: Option<Res<RenderResources>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut renderer_was_pending: Local<bool>,
 ) {
+    let render_resource_context = match render_resource_context {
+        Some(render_resource_context) => render_resource_context,
+        None => {
+            *renderer_was_pending = true;
+            return;
+        }
+    };
     let mut changed_meshes = HashSet::default();
+    // the events of the meshes added while the renderer was pending may be gone
+    if std::mem::take(&mut *renderer_was_pending) {
+        changed_meshes.extend(meshes.ids().map(Handle::weak));
+    }
     let render_resource_context = &**render_resource_context;
     for event in mesh_events.iter() {
         match event {
@@ -0,0 +1,151 @@
+use super::{Mesh, VertexAttributeValues};
+use crate::pipeline::VertexFormat;
+use std::borrow::Cow;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum QuantizeAttributeError {
+    #[error("missing vertex attribute '{0}'")]
+    MissingVertexAttribute(Cow<'static, str>),
+    #[error("cannot quantize {from:?} values to {to:?}")]
+    UnsupportedConversion {
+        from: VertexFormat,
+        to: VertexFormat,
+    },
+}
+
+fn snorm8(value: f32) -> i8 {
+    (value.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8
+}
+
+fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8
+}
+
+fn snorm16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn unorm16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+impl VertexAttributeValues {
+    /// Converts `Float32x2`, `Float32x3` or `Float32x4` values to one of the normalized 8 or 16
+    /// bit `format`s, which shaders read as floats. Values are clamped to `-1.0..=1.0` for the
+    /// `Snorm` formats and to `0.0..=1.0` for the `Unorm` formats, and `Float32x3` values are
+    /// padded with a 0 to fill the 4 component formats.
+    ///
+    /// Returns `None` for other values and formats, or if `format` has fewer components.
+    pub fn quantize(&self, format: VertexFormat) -> Option<VertexAttributeValues> {
+        let (values, component_count): (Vec<[f32; 4]>, u32) = match self {
+            VertexAttributeValues::Float32x2(values) => {
+                (values.iter().map(|[x, y]| [*x, *y, 0.0, 0.0]).collect(), 2)
+            }
+            VertexAttributeValues::Float32x3(values) => (
+                values.iter().map(|[x, y, z]| [*x, *y, *z, 0.0]).collect(),
+                3,
+            ),
+            VertexAttributeValues::Float32x4(values) => (values.clone(), 4),
+            _ => return None,
+        };
+        if format.component_count() < component_count {
+            return None;
+        }
+        let values = values.iter();
+        Some(match format {
+            VertexFormat::Snorm8x2 => VertexAttributeValues::Snorm8x2(
+                values.map(|v| [snorm8(v[0]), snorm8(v[1])]).collect(),
+            ),
+            VertexFormat::Snorm8x4 => VertexAttributeValues::Snorm8x4(
+                values
+                    .map(|v| [snorm8(v[0]), snorm8(v[1]), snorm8(v[2]), snorm8(v[3])])
+                    .collect(),
+            ),
+            VertexFormat::Unorm8x2 => VertexAttributeValues::Unorm8x2(
+                values.map(|v| [unorm8(v[0]), unorm8(v[1])]).collect(),
+            ),
+            VertexFormat::Unorm8x4 => VertexAttributeValues::Unorm8x4(
+                values
+                    .map(|v| [unorm8(v[0]), unorm8(v[1]), unorm8(v[2]), unorm8(v[3])])
+                    .collect(),
+            ),
+            VertexFormat::Snorm16x2 => VertexAttributeValues::Snorm16x2(
+                values.map(|v| [snorm16(v[0]), snorm16(v[1])]).collect(),
+            ),
+            VertexFormat::Snorm16x4 => VertexAttributeValues::Snorm16x4(
+                values
+                    .map(|v| [snorm16(v[0]), snorm16(v[1]), snorm16(v[2]), snorm16(v[3])])
+                    .collect(),
+            ),
+            VertexFormat::Unorm16x2 => VertexAttributeValues::Unorm16x2(
+                values.map(|v| [unorm16(v[0]), unorm16(v[1])]).collect(),
+            ),
+            VertexFormat::Unorm16x4 => VertexAttributeValues::Unorm16x4(
+                values
+                    .map(|v| [unorm16(v[0]), unorm16(v[1]), unorm16(v[2]), unorm16(v[3])])
+                    .collect(),
+            ),
+            _ => return None,
+        })
+    }
+}
+
+impl Mesh {
+    /// Converts the values of an attribute to a normalized 8 or 16 bit `format`, see
+    /// [`VertexAttributeValues::quantize`]. Shaders read them as before, with less vertex
+    /// bandwidth and memory.
+    ///
+    /// Quantize attributes once the mesh is complete: methods such as
+    /// [`Mesh::generate_tangents`] expect `Float32` attributes.
+    pub fn quantize_attribute(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        format: VertexFormat,
+    ) -> Result<(), QuantizeAttributeError> {
+        let name = name.into();
+        let values = match self.attributes.get_mut(&name) {
+            Some(values) => values,
+            None => return Err(QuantizeAttributeError::MissingVertexAttribute(name)),
+        };
+        *values = match values.quantize(format) {
+            Some(quantized) => quantized,
+            None => {
+                return Err(QuantizeAttributeError::UnsupportedConversion {
+                    from: VertexFormat::from(&*values),
+                    to: format,
+                })
+            }
+        };
+        if let Some(attribute) = self
+            .custom_attributes
+            .iter_mut()
+            .find(|attribute| attribute.name == name)
+        {
+            attribute.format = format;
+        }
+        Ok(())
+    }
+
+    /// Quantizes [`Mesh::ATTRIBUTE_NORMAL`] and [`Mesh::ATTRIBUTE_TANGENT`] to
+    /// [`VertexFormat::Snorm16x4`], and [`Mesh::ATTRIBUTE_UV_0`] to [`VertexFormat::Unorm16x2`]
+    /// if all its values are in `0.0..=1.0`, which halves their size without visible loss.
+    /// Attributes the mesh doesn't have, or that aren't `Float32` values, are left as they are.
+    /// Pipelines drawing the mesh must be created for its new layout, see
+    /// [`Mesh::vertex_buffer_layout_for`].
+    pub fn quantize_standard_attributes(&mut self) {
+        for &name in [Mesh::ATTRIBUTE_NORMAL, Mesh::ATTRIBUTE_TANGENT].iter() {
+            let _ = self.quantize_attribute(name, VertexFormat::Snorm16x4);
+        }
+        let uvs_normalized = self
+            .attribute(Mesh::ATTRIBUTE_UV_0)
+            .and_then(|uvs| uvs.as_float2())
+            .map_or(false, |uvs| {
+                uvs.iter().flatten().all(|uv| (0.0..=1.0).contains(uv))
+            });
+        // repeating uvs can't be represented in a normalized format
+        if uvs_normalized {
+            let _ = self.quantize_attribute(Mesh::ATTRIBUTE_UV_0, VertexFormat::Unorm16x2);
+        }
+    }
+}
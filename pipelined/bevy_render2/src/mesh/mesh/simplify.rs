@@ -0,0 +1,316 @@
+use super::{Indices, Mesh};
+use crate::pipeline::PrimitiveTopology;
+use bevy_core::FloatOrd;
+use bevy_math::Vec3;
+use bevy_utils::HashMap;
+use std::{cmp::Reverse, collections::BinaryHeap, ops::Add};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SimplifyMeshError {
+    #[error("cannot simplify {0:?}, only `TriangleList`s are supported")]
+    UnsupportedTopology(PrimitiveTopology),
+    #[error("missing vertex attribute '{0}'")]
+    MissingVertexAttribute(&'static str),
+    #[error("the '{0}' vertex attribute should be of type {1}")]
+    InvalidVertexAttributeFormat(&'static str, &'static str),
+}
+
+impl Mesh {
+    /// Reduces the triangle count of the mesh to about `target_ratio` of its current count, by
+    /// collapsing the edges that change its shape the least according to a quadric error
+    /// metric. This is slow enough that it should be done offline or once when loading, e.g. to
+    /// generate the levels of a [`Lod`](crate::mesh::Lod) with
+    /// [`Lod::generate`](crate::mesh::Lod::generate).
+    ///
+    /// Vertices are merged into one of their neighbors, so the remaining vertices keep all
+    /// their attributes as they were. Vertices on the borders of the mesh, including seams
+    /// where vertices are split to have different normals or uvs, are never moved so that the
+    /// mesh doesn't open up, which can keep the mesh from reaching the target. Non-indexed
+    /// meshes are deduplicated with [`Mesh::deduplicate_vertices`] first.
+    pub fn simplify(&mut self, target_ratio: f32) -> Result<(), SimplifyMeshError> {
+        match self.primitive_topology() {
+            PrimitiveTopology::TriangleList => {}
+            other => return Err(SimplifyMeshError::UnsupportedTopology(other)),
+        }
+        self.attribute(Mesh::ATTRIBUTE_POSITION)
+            .ok_or(SimplifyMeshError::MissingVertexAttribute(
+                Mesh::ATTRIBUTE_POSITION,
+            ))?
+            .as_float3()
+            .ok_or(SimplifyMeshError::InvalidVertexAttributeFormat(
+                Mesh::ATTRIBUTE_POSITION,
+                "float3",
+            ))?;
+
+        if self.indices.is_none() {
+            self.deduplicate_vertices();
+        }
+        let positions: Vec<Vec3> = self
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|positions| positions.as_float3())
+            .unwrap()
+            .iter()
+            .map(|position| Vec3::from(*position))
+            .collect();
+        let indices: Vec<usize> = self.indices.as_ref().unwrap().iter().collect();
+        let triangles: Vec<[usize; 3]> = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        let target = (triangles.len() as f32 * target_ratio.clamp(0.0, 1.0)).round() as usize;
+        let mut triangles = Simplifier::new(&positions, triangles).simplify(target);
+
+        // drop the vertices no triangle uses anymore
+        let mut remap = vec![None; positions.len()];
+        let mut used_vertices = Vec::new();
+        for index in triangles
+            .iter_mut()
+            .flat_map(|triangle| triangle.iter_mut())
+        {
+            *index = *remap[*index].get_or_insert_with(|| {
+                used_vertices.push(*index);
+                used_vertices.len() - 1
+            });
+        }
+        for values in self.attributes.values_mut() {
+            values.reorder(used_vertices.iter().copied());
+        }
+        let indices = triangles
+            .iter()
+            .flat_map(|triangle| triangle.iter().copied());
+        self.indices = Some(match self.indices {
+            Some(Indices::U16(_)) => Indices::U16(indices.map(|i| i as u16).collect()),
+            _ => Indices::U32(indices.map(|i| i as u32).collect()),
+        });
+        Ok(())
+    }
+}
+
+/// A symmetric 4x4 matrix whose quadratic form is the sum of the squared distances of a point
+/// to a set of planes, stored as its upper triangle.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vec3, distance: f32, weight: f32) -> Self {
+        let [a, b, c, d] = [normal.x, normal.y, normal.z, distance];
+        let (a, b, c, d, w) = (a as f64, b as f64, c as f64, d as f64, weight as f64);
+        Quadric([
+            a * a * w,
+            a * b * w,
+            a * c * w,
+            a * d * w,
+            b * b * w,
+            b * c * w,
+            b * d * w,
+            c * c * w,
+            c * d * w,
+            d * d * w,
+        ])
+    }
+
+    fn error(&self, point: Vec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+impl Add for Quadric {
+    type Output = Quadric;
+
+    fn add(mut self, other: Quadric) -> Quadric {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+        self
+    }
+}
+
+/// A candidate collapse of the vertex `from` into `to`, valid as long as neither vertex has
+/// changed since it was queued.
+type Collapse = (Reverse<FloatOrd>, usize, usize, u32, u32);
+
+struct Simplifier<'a> {
+    positions: &'a [Vec3],
+    triangles: Vec<[usize; 3]>,
+    live_triangles: Vec<bool>,
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    collapsed: Vec<bool>,
+    versions: Vec<u32>,
+    collapses: BinaryHeap<Collapse>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(positions: &'a [Vec3], triangles: Vec<[usize; 3]>) -> Self {
+        let vertex_count = positions.len();
+        let mut quadrics = vec![Quadric::default(); vertex_count];
+        let mut vertex_triangles = vec![Vec::new(); vertex_count];
+        let mut live_triangles = Vec::with_capacity(triangles.len());
+        let mut edge_counts = HashMap::default();
+        for (t, triangle) in triangles.iter().enumerate() {
+            let [i, j, k] = *triangle;
+            let live = i != j && j != k && k != i;
+            live_triangles.push(live);
+            if !live {
+                continue;
+            }
+            let a = positions[i];
+            let normal = (positions[j] - a).cross(positions[k] - a);
+            let area = normal.length() / 2.0;
+            if area > 0.0 {
+                let normal = normal / (area * 2.0);
+                let plane = Quadric::from_plane(normal, -normal.dot(a), area);
+                for &vertex in triangle {
+                    quadrics[vertex] = quadrics[vertex] + plane;
+                }
+            }
+            for edge in 0..3 {
+                let (from, to) = (triangle[edge], triangle[(edge + 1) % 3]);
+                vertex_triangles[from].push(t);
+                *edge_counts.entry((from.min(to), from.max(to))).or_insert(0) += 1;
+            }
+        }
+
+        // vertices on borders, seams and non-manifold edges are locked
+        let mut locked = vec![false; vertex_count];
+        for (&(from, to), &count) in edge_counts.iter() {
+            if count != 2 {
+                locked[from] = true;
+                locked[to] = true;
+            }
+        }
+
+        let mut simplifier = Simplifier {
+            positions,
+            triangles,
+            live_triangles,
+            vertex_triangles,
+            quadrics,
+            locked,
+            collapsed: vec![false; vertex_count],
+            versions: vec![0; vertex_count],
+            collapses: BinaryHeap::new(),
+        };
+        for vertex in 0..vertex_count {
+            simplifier.queue_collapses(vertex);
+        }
+        simplifier
+    }
+
+    /// Queues the collapses of the edges around `vertex`, in both directions.
+    fn queue_collapses(&mut self, vertex: usize) {
+        for &t in self.vertex_triangles[vertex].iter() {
+            if !self.live_triangles[t] {
+                continue;
+            }
+            for &other in self.triangles[t].iter() {
+                if other == vertex {
+                    continue;
+                }
+                for &(from, to) in [(vertex, other), (other, vertex)].iter() {
+                    if self.locked[from] {
+                        continue;
+                    }
+                    let quadric = self.quadrics[from] + self.quadrics[to];
+                    let cost = quadric.error(self.positions[to]) as f32;
+                    self.collapses.push((
+                        Reverse(FloatOrd(cost)),
+                        from,
+                        to,
+                        self.versions[from],
+                        self.versions[to],
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns whether moving `from` onto `to` would flip or degenerate any of the triangles
+    /// around `from` that remain after the collapse.
+    fn flips(&self, from: usize, to: usize) -> bool {
+        self.vertex_triangles[from].iter().any(|&t| {
+            let triangle = self.triangles[t];
+            if !self.live_triangles[t] || triangle.contains(&to) {
+                return false;
+            }
+            let normal = |moved: Vec3| {
+                let position = |vertex: usize| {
+                    if vertex == from {
+                        moved
+                    } else {
+                        self.positions[vertex]
+                    }
+                };
+                let (a, b, c) = (
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                );
+                (b - a).cross(c - a)
+            };
+            normal(self.positions[from]).dot(normal(self.positions[to])) <= 0.0
+        })
+    }
+
+    /// Collapses edges until at most `target` triangles are left or no collapse is possible,
+    /// and returns the remaining triangles.
+    fn simplify(mut self, target: usize) -> Vec<[usize; 3]> {
+        let mut live_count = self.live_triangles.iter().filter(|live| **live).count();
+        while live_count > target {
+            let (_, from, to, from_version, to_version) = match self.collapses.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            if self.collapsed[from]
+                || self.collapsed[to]
+                || self.versions[from] != from_version
+                || self.versions[to] != to_version
+                || self.flips(from, to)
+            {
+                continue;
+            }
+
+            self.collapsed[from] = true;
+            self.quadrics[to] = self.quadrics[to] + self.quadrics[from];
+            self.versions[to] += 1;
+            for t in std::mem::take(&mut self.vertex_triangles[from]) {
+                if !self.live_triangles[t] {
+                    continue;
+                }
+                let triangle = &mut self.triangles[t];
+                if triangle.contains(&to) {
+                    self.live_triangles[t] = false;
+                    live_count -= 1;
+                } else {
+                    for index in triangle.iter_mut().filter(|index| **index == from) {
+                        *index = to;
+                    }
+                    self.vertex_triangles[to].push(t);
+                }
+            }
+            let live_triangles = &self.live_triangles;
+            self.vertex_triangles[to].retain(|&t| live_triangles[t]);
+            self.queue_collapses(to);
+        }
+
+        self.triangles
+            .iter()
+            .zip(self.live_triangles.iter())
+            .filter(|(_, live)| **live)
+            .map(|(triangle, _)| *triangle)
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+use super::{Indices, Mesh};
+use crate::pipeline::PrimitiveTopology;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GenerateTangentsError {
+    #[error("cannot generate tangents for {0:?}, only `TriangleList`s are supported")]
+    UnsupportedTopology(PrimitiveTopology),
+    #[error("missing vertex attribute '{0}'")]
+    MissingVertexAttribute(&'static str),
+    #[error("the '{0}' vertex attribute should be of type {1}")]
+    InvalidVertexAttributeFormat(&'static str, &'static str),
+    #[error("mikktspace failed to generate tangents")]
+    MikktspaceError,
+}
+
+pub(crate) fn generate_tangents_for_mesh(
+    mesh: &Mesh,
+) -> Result<Vec<[f32; 4]>, GenerateTangentsError> {
+    match mesh.primitive_topology() {
+        PrimitiveTopology::TriangleList => {}
+        other => return Err(GenerateTangentsError::UnsupportedTopology(other)),
+    }
+
+    let positions = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .ok_or(GenerateTangentsError::MissingVertexAttribute(
+            Mesh::ATTRIBUTE_POSITION,
+        ))?
+        .as_float3()
+        .ok_or(GenerateTangentsError::InvalidVertexAttributeFormat(
+            Mesh::ATTRIBUTE_POSITION,
+            "float3",
+        ))?;
+    let normals = mesh
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .ok_or(GenerateTangentsError::MissingVertexAttribute(
+            Mesh::ATTRIBUTE_NORMAL,
+        ))?
+        .as_float3()
+        .ok_or(GenerateTangentsError::InvalidVertexAttributeFormat(
+            Mesh::ATTRIBUTE_NORMAL,
+            "float3",
+        ))?;
+    let uvs = mesh
+        .attribute(Mesh::ATTRIBUTE_UV_0)
+        .ok_or(GenerateTangentsError::MissingVertexAttribute(
+            Mesh::ATTRIBUTE_UV_0,
+        ))?
+        .as_float2()
+        .ok_or(GenerateTangentsError::InvalidVertexAttributeFormat(
+            Mesh::ATTRIBUTE_UV_0,
+            "float2",
+        ))?;
+
+    let mut geometry = MikktspaceGeometry {
+        indices: mesh.indices(),
+        positions,
+        normals,
+        uvs,
+        tangents: vec![[0.0; 4]; positions.len()],
+    };
+    if !mikktspace::generate_tangents(&mut geometry) {
+        return Err(GenerateTangentsError::MikktspaceError);
+    }
+
+    Ok(geometry.tangents)
+}
+
+/// Exposes a triangle list [`Mesh`] to mikktspace, which addresses vertices by face and corner.
+struct MikktspaceGeometry<'a> {
+    indices: Option<&'a Indices>,
+    positions: &'a [[f32; 3]],
+    normals: &'a [[f32; 3]],
+    uvs: &'a [[f32; 2]],
+    tangents: Vec<[f32; 4]>,
+}
+
+impl MikktspaceGeometry<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        let corner = face * 3 + vert;
+        match self.indices {
+            Some(Indices::U16(indices)) => indices[corner] as usize,
+            Some(Indices::U32(indices)) => indices[corner] as usize,
+            None => corner,
+        }
+    }
+}
+
+impl mikktspace::Geometry for MikktspaceGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        match self.indices {
+            Some(indices) => indices.len() / 3,
+            None => self.positions.len() / 3,
+        }
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.positions[self.index(face, vert)]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.normals[self.index(face, vert)]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.uvs[self.index(face, vert)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.index(face, vert);
+        self.tangents[index] = tangent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_tangents_for_mesh, GenerateTangentsError};
+    use crate::{
+        mesh::{Indices, Mesh},
+        pipeline::PrimitiveTopology,
+    };
+
+    /// A quad facing +z, with the u axis of its uvs along +x and the v axis along -y.
+    fn quad() -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![
+                [-1.0, -1.0, 0.0],
+                [1.0, -1.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [-1.0, 1.0, 0.0],
+            ],
+        );
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 4]);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3])));
+        mesh
+    }
+
+    #[test]
+    fn quad_tangents_follow_the_uvs() {
+        let tangents = generate_tangents_for_mesh(&quad()).unwrap();
+        assert_eq!(tangents.len(), 4);
+        for tangent in tangents.iter() {
+            let expected = [1.0, 0.0, 0.0, -1.0];
+            let close = tangent
+                .iter()
+                .zip(expected.iter())
+                .all(|(actual, expected)| (actual - expected).abs() < 1e-5);
+            // the bitangent, w * normal x tangent, points along -y like the v axis
+            assert!(close, "expected {:?}, got {:?}", expected, tangent);
+        }
+    }
+
+    #[test]
+    fn missing_attributes_are_reported() {
+        for attribute in [
+            Mesh::ATTRIBUTE_POSITION,
+            Mesh::ATTRIBUTE_NORMAL,
+            Mesh::ATTRIBUTE_UV_0,
+        ]
+        .iter()
+        {
+            let mut mesh = quad();
+            mesh.attributes.remove(*attribute);
+            match generate_tangents_for_mesh(&mesh) {
+                Err(GenerateTangentsError::MissingVertexAttribute(missing)) => {
+                    assert_eq!(missing, *attribute)
+                }
+                other => panic!("expected {} to be missing, got {:?}", attribute, other),
+            }
+        }
+    }
+
+    #[test]
+    fn only_triangle_lists_are_supported() {
+        let mut mesh = quad();
+        mesh.primitive_topology = PrimitiveTopology::TriangleStrip;
+        assert!(matches!(
+            generate_tangents_for_mesh(&mesh),
+            Err(GenerateTangentsError::UnsupportedTopology(
+                PrimitiveTopology::TriangleStrip
+            ))
+        ));
+    }
+}
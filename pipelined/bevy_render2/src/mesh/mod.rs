@@ -1,9 +1,11 @@
+mod lod;
 #[allow(clippy::module_inception)]
 mod mesh;
 /// Generation for some primitive shape meshes.
 pub mod shape;
 
-use bevy_asset::AddAsset;
+use bevy_asset::{AddAsset, Handle};
+pub use lod::*;
 pub use mesh::*;
 
 use bevy_app::{App, CoreStage, Plugin};
@@ -13,9 +15,11 @@ pub struct MeshPlugin;
 
 impl Plugin for MeshPlugin {
     fn build(&self, app: &mut App) {
-        app.add_asset::<Mesh>().add_system_to_stage(
-            CoreStage::PostUpdate,
-            mesh_resource_provider_system.system(),
-        );
+        app.add_asset::<Mesh>()
+            .register_type::<Handle<Mesh>>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                mesh_resource_provider_system.system(),
+            );
     }
 }
@@ -0,0 +1,300 @@
+use crate::{
+    camera::Camera,
+    core_pipeline::DepthMode,
+    view::{mesh_aabb_system, Aabb, ExternalWindows, OffscreenTargets},
+};
+use bevy_app::{App, CoreStage, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_input::{mouse::MouseButton, Input, InputSystem};
+use bevy_math::{Mat4, Vec2, Vec3};
+use bevy_transform::components::GlobalTransform;
+use bevy_window::{WindowId, Windows};
+
+/// Finds the [`Pickable`] entities under the cursor of each [`PickingCamera`] on the CPU, and
+/// sends [`PickingEvent`]s when the entity under the cursor changes or is clicked. This is
+/// opt-in: it is not part of the default plugins.
+///
+/// Entities are hit tested with their [`Aabb`], which is computed for meshes that don't have one.
+/// Picking runs in [`CoreStage::PreUpdate`], with the transforms of the previous frame, which is
+/// the frame the cursor was over.
+#[derive(Default)]
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PickingEvent>()
+            .init_resource::<Hovered>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                picking_system.system().after(InputSystem),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, mesh_aabb_system::<Pickable>.system());
+    }
+}
+
+/// Marks an entity that can be picked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pickable;
+
+/// Picks entities with a [`Camera`].
+#[derive(Debug, Clone, Default)]
+pub struct PickingCamera {
+    /// Hits of cameras with a higher order take precedence, e.g. those of a 2d camera drawn on
+    /// top of a 3d camera.
+    pub order: i32,
+    /// The cursor position in physical pixels of the camera's target, from the bottom left. Set
+    /// it for cameras whose target isn't a window with a cursor, such as an offscreen target
+    /// shown in an editor viewport. Defaults to the cursor of the camera's window.
+    pub cursor: Option<Vec2>,
+    hits: Vec<PickHit>,
+}
+
+impl PickingCamera {
+    /// The entities under the cursor in the last update, from nearest to farthest.
+    pub fn hits(&self) -> &[PickHit] {
+        &self.hits
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickHit {
+    pub entity: Entity,
+    /// The distance from the near plane of the camera along the cursor ray.
+    pub distance: f32,
+    /// Where the ray enters the entity's bounds, in world space.
+    pub position: Vec3,
+}
+
+/// The nearest entity under the cursor, in the [`PickingCamera`] with the highest order that
+/// has a hit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hovered {
+    pub entity: Option<Entity>,
+    pub camera: Option<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickingEvent {
+    HoverStarted(Entity),
+    HoverEnded(Entity),
+    Clicked(Entity, MouseButton),
+}
+
+/// A half line, e.g. from the camera through the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Normalized.
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Returns the ray from the near plane of a camera through `ndc`, a position in normalized
+    /// device coordinates, i.e. `-1.0..1.0` from the bottom left of the target.
+    pub fn from_camera(
+        projection: Mat4,
+        transform: &GlobalTransform,
+        depth_mode: DepthMode,
+        ndc: Vec2,
+    ) -> Self {
+        let world_from_ndc = transform.compute_matrix() * projection.inverse();
+        // a depth between near and far is finite in both depth modes, even with an infinite far
+        // plane
+        let near = world_from_ndc.project_point3(ndc.extend(depth_mode.near_value()));
+        let middle = world_from_ndc.project_point3(ndc.extend(0.5));
+        Ray {
+            origin: near,
+            direction: (middle - near).normalize(),
+        }
+    }
+
+    /// Returns the distance along the ray to where it enters `aabb` transformed by `model`, or
+    /// `0.0` if it starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb, model: &Mat4) -> Option<f32> {
+        // intersect in the local space of the box, where the distances along the ray are the
+        // same as long as the direction isn't normalized again
+        let local_from_world = model.inverse();
+        let origin = local_from_world.transform_point3(self.origin);
+        let direction = local_from_world.transform_vector3(self.direction);
+        let (min, max) = (aabb.min(), aabb.max());
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            if direction[axis].abs() < f32::EPSILON {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (min[axis] - origin[axis]) / direction[axis];
+            let t2 = (max[axis] - origin[axis]) / direction[axis];
+            near = near.max(t1.min(t2));
+            far = far.min(t1.max(t2));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+/// Returns the size of the target of a camera in physical pixels, and the cursor over it, if any.
+fn target_cursor(
+    window_id: WindowId,
+    windows: &Windows,
+    offscreen_targets: &OffscreenTargets,
+    external_windows: &ExternalWindows,
+) -> Option<(Vec2, Option<Vec2>)> {
+    if let Some(window) = windows.get(window_id) {
+        let size = Vec2::new(
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        );
+        let cursor = window
+            .cursor_position()
+            .map(|cursor| cursor * window.scale_factor() as f32);
+        return Some((size, cursor));
+    }
+    offscreen_targets
+        .get(window_id)
+        .map(|target| (target.width, target.height))
+        .or_else(|| {
+            external_windows
+                .get(window_id)
+                .map(|window| (window.physical_width, window.physical_height))
+        })
+        .map(|(width, height)| (Vec2::new(width as f32, height as f32), None))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn picking_system(
+    mut hovered: ResMut<Hovered>,
+    mut events: EventWriter<PickingEvent>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    offscreen_targets: Res<OffscreenTargets>,
+    external_windows: Res<ExternalWindows>,
+    depth_mode: Option<Res<DepthMode>>,
+    mut cameras: Query<(Entity, &Camera, &GlobalTransform, &mut PickingCamera)>,
+    pickables: Query<(Entity, &Aabb, &GlobalTransform), With<Pickable>>,
+) {
+    let depth_mode = depth_mode.map(|mode| *mode).unwrap_or_default();
+    let mut top_hit: Option<(i32, Entity, Entity)> = None;
+    for (camera_entity, camera, camera_transform, mut picking_camera) in cameras.iter_mut() {
+        picking_camera.hits.clear();
+        let (size, window_cursor) = match target_cursor(
+            camera.window,
+            &windows,
+            &offscreen_targets,
+            &external_windows,
+        ) {
+            Some(target) => target,
+            None => continue,
+        };
+        let cursor = match picking_camera.cursor.or(window_cursor) {
+            Some(cursor) => cursor,
+            None => continue,
+        };
+        // the viewport of the camera, from the bottom left
+        let (origin, viewport_size) = match camera.viewport {
+            Some(viewport) => (
+                Vec2::new(
+                    viewport.x as f32,
+                    size.y - (viewport.y + viewport.height) as f32,
+                ),
+                Vec2::new(viewport.width as f32, viewport.height as f32),
+            ),
+            None => (Vec2::ZERO, size),
+        };
+        let ndc = (cursor - origin) / viewport_size * 2.0 - Vec2::ONE;
+        // e.g. over a letterbox bar
+        if ndc.abs().max_element() > 1.0 {
+            continue;
+        }
+        let ray = Ray::from_camera(camera.projection_matrix, camera_transform, depth_mode, ndc);
+
+        for (entity, aabb, transform) in pickables.iter() {
+            if let Some(distance) = ray.intersect_aabb(aabb, &transform.compute_matrix()) {
+                picking_camera.hits.push(PickHit {
+                    entity,
+                    distance,
+                    position: ray.origin + ray.direction * distance,
+                });
+            }
+        }
+        picking_camera
+            .hits
+            .sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+        if let Some(hit) = picking_camera.hits.first() {
+            if top_hit.map_or(true, |(order, _, _)| picking_camera.order > order) {
+                top_hit = Some((picking_camera.order, camera_entity, hit.entity));
+            }
+        }
+    }
+
+    let entity = top_hit.map(|(_, _, entity)| entity);
+    if entity != hovered.entity {
+        if let Some(previous) = hovered.entity {
+            events.send(PickingEvent::HoverEnded(previous));
+        }
+        if let Some(entity) = entity {
+            events.send(PickingEvent::HoverStarted(entity));
+        }
+    }
+    if let Some(entity) = entity {
+        for button in mouse_buttons.get_just_pressed() {
+            events.send(PickingEvent::Clicked(entity, *button));
+        }
+    }
+    *hovered = Hovered {
+        entity,
+        camera: top_hit.map(|(_, camera, _)| camera),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ray;
+    use crate::{core_pipeline::DepthMode, view::Aabb};
+    use bevy_math::{Mat4, Vec2, Vec3};
+    use bevy_transform::components::GlobalTransform;
+
+    #[test]
+    fn ray_from_camera() {
+        let transform = GlobalTransform::from_translation(Vec3::new(0.0, 0.0, 5.0));
+        for &depth_mode in [DepthMode::Standard, DepthMode::ReverseZ].iter() {
+            let projection = depth_mode.adjust_projection(Mat4::perspective_rh(
+                std::f32::consts::FRAC_PI_2,
+                1.0,
+                0.1,
+                100.0,
+            ));
+            let ray = Ray::from_camera(projection, &transform, depth_mode, Vec2::ZERO);
+            assert!((ray.origin - Vec3::new(0.0, 0.0, 4.9)).length() < 1e-3);
+            assert!((ray.direction - -Vec3::Z).length() < 1e-3);
+            // the top right corner of a 90 degree view
+            let ray = Ray::from_camera(projection, &transform, depth_mode, Vec2::ONE);
+            assert!((ray.direction - Vec3::new(1.0, 1.0, -1.0).normalize()).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn ray_intersects_aabb() {
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 10.0),
+            direction: -Vec3::Z,
+        };
+        let aabb = Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_eq!(ray.intersect_aabb(&aabb, &Mat4::IDENTITY), Some(9.0));
+        let scaled = Mat4::from_scale(Vec3::splat(2.0));
+        assert_eq!(ray.intersect_aabb(&aabb, &scaled), Some(8.0));
+        let beside = Mat4::from_translation(Vec3::new(3.0, 0.0, 0.0));
+        assert_eq!(ray.intersect_aabb(&aabb, &beside), None);
+        let behind = Mat4::from_translation(Vec3::new(0.0, 0.0, 20.0));
+        assert_eq!(ray.intersect_aabb(&aabb, &behind), None);
+        // flat bounds, e.g. of a sprite
+        let flat = Aabb::from_min_max(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(ray.intersect_aabb(&flat, &Mat4::IDENTITY), Some(10.0));
+    }
+}
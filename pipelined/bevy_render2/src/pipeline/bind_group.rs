@@ -1,6 +1,9 @@
 use super::BindingDescriptor;
 use bevy_utils::FixedState;
-use std::hash::{BuildHasher, Hash, Hasher};
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    num::NonZeroU32,
+};
 
 #[derive(Clone, Debug, Eq)]
 pub struct BindGroupDescriptor {
@@ -29,6 +32,26 @@ impl BindGroupDescriptor {
         self.hash(&mut hasher);
         self.id = BindGroupDescriptorId(hasher.finish());
     }
+
+    /// Sets the number of elements of the binding array `index`, or makes it a single binding
+    /// with `None`, and updates the id of the layout. Bindings of arrays declared without a size
+    /// in shaders, like `texture2D textures[]`, can be resized this way, e.g. to the number of
+    /// textures of a bindless atlas, which gives a distinct layout for each size.
+    ///
+    /// Binding arrays of textures need [`RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY`], and
+    /// arrays without a size in shaders [`RenderFeatures::UNSIZED_BINDING_ARRAY`].
+    ///
+    /// [`RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY`]: crate::renderer::RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY
+    /// [`RenderFeatures::UNSIZED_BINDING_ARRAY`]: crate::renderer::RenderFeatures::UNSIZED_BINDING_ARRAY
+    pub fn set_binding_count(&mut self, index: u32, count: Option<NonZeroU32>) {
+        let binding = self
+            .bindings
+            .iter_mut()
+            .find(|binding| binding.index == index)
+            .unwrap_or_else(|| panic!("binding {} isn't in bind group {}", index, self.index));
+        binding.count = count;
+        self.update_id();
+    }
 }
 
 impl Hash for BindGroupDescriptor {
@@ -19,6 +19,8 @@ pub struct BindingDescriptor {
     pub index: u32,
     pub bind_type: BindType,
     pub shader_stage: BindingShaderStage,
+    /// The number of elements of a binding array, or `None` for a single binding. See
+    /// [`BindGroupDescriptor::set_binding_count`](super::BindGroupDescriptor::set_binding_count).
     pub count: Option<NonZeroU32>,
 }
 
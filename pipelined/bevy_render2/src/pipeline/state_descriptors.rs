@@ -35,7 +35,9 @@ pub struct MultisampleState {
     pub alpha_to_coverage_enabled: bool,
 }
 
-#[derive(Clone, Debug)]
+/// Offsets the depth of rasterized triangles, e.g. to avoid shadow acne when rendering shadow
+/// maps. Only triangle topologies can use a depth bias.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct DepthBiasState {
     /// Constant depth biasing factor, in basic units of the depth format.
     pub constant: i32,
@@ -45,6 +47,12 @@ pub struct DepthBiasState {
     pub clamp: f32,
 }
 
+impl DepthBiasState {
+    pub fn is_enabled(&self) -> bool {
+        self.constant != 0 || self.slope_scale != 0.0
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum StencilOperation {
     Keep = 0,
@@ -144,7 +152,12 @@ pub struct PrimitiveState {
     pub front_face: FrontFace,
     pub cull_mode: Option<Face>,
     pub polygon_mode: PolygonMode,
+    /// Clamps the depth of fragments to the depth range instead of clipping them. Requires
+    /// [`RenderFeatures::DEPTH_CLAMPING`](crate::renderer::RenderFeatures::DEPTH_CLAMPING).
     pub clamp_depth: bool,
+    /// Rasterizes every pixel a triangle touches, even partially, e.g. for voxelization. Requires
+    /// [`RenderFeatures::CONSERVATIVE_RASTERIZATION`](crate::renderer::RenderFeatures::CONSERVATIVE_RASTERIZATION)
+    /// and [`PolygonMode::Fill`].
     pub conservative: bool,
 }
 
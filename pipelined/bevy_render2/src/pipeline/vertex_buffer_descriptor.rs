@@ -5,6 +5,7 @@ use std::{
     borrow::Cow,
     hash::{Hash, Hasher},
 };
+use thiserror::Error;
 
 #[derive(Clone, Debug, Eq, PartialEq, Default, Reflect, Serialize, Deserialize)]
 #[reflect_value(Serialize, Deserialize, PartialEq)]
@@ -27,6 +28,59 @@ impl VertexBufferLayout {
             attributes: vec![attribute],
         }
     }
+
+    /// Starts building a layout whose attributes are tightly packed in the order they are added.
+    pub fn build(name: impl Into<Cow<'static, str>>) -> VertexBufferLayoutBuilder {
+        VertexBufferLayoutBuilder {
+            layout: VertexBufferLayout {
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Builds a [`VertexBufferLayout`], computing the attribute offsets and the stride.
+///
+/// Use [`ShaderLayout::validate_vertex_buffers`](crate::shader::ShaderLayout::validate_vertex_buffers)
+/// to check the result against the reflected vertex shader.
+#[derive(Debug)]
+pub struct VertexBufferLayoutBuilder {
+    layout: VertexBufferLayout,
+}
+
+impl VertexBufferLayoutBuilder {
+    pub fn step_mode(mut self, step_mode: InputStepMode) -> Self {
+        self.layout.step_mode = step_mode;
+        self
+    }
+
+    /// Adds an attribute right after the previous one.
+    pub fn add_attribute(
+        mut self,
+        shader_location: u32,
+        name: impl Into<Cow<'static, str>>,
+        format: VertexFormat,
+    ) -> Self {
+        self.layout.attributes.push(VertexAttribute {
+            name: name.into(),
+            format,
+            offset: self.layout.stride,
+            shader_location,
+        });
+        self.layout.stride += format.get_size();
+        self
+    }
+
+    /// Skips `size` bytes, e.g. for data in the buffer that the shader doesn't use.
+    pub fn add_padding(mut self, size: u64) -> Self {
+        self.layout.stride += size;
+        self
+    }
+
+    pub fn finish(self) -> VertexBufferLayout {
+        self.layout
+    }
 }
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum InputStepMode {
@@ -48,6 +102,45 @@ pub struct VertexAttribute {
     pub shader_location: u32,
 }
 
+/// A difference between the vertex buffer layouts of a pipeline and its vertex shader inputs.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VertexAttributeMismatch {
+    #[error("location {location} ({name}) is read by the shader as {expected:?}, but no vertex buffer provides it")]
+    Missing {
+        location: u32,
+        name: String,
+        expected: VertexFormat,
+    },
+    #[error("location {location} ({name}) is {actual:?} in the vertex buffer, but the shader reads it as {expected:?}")]
+    Format {
+        location: u32,
+        name: String,
+        expected: VertexFormat,
+        actual: VertexFormat,
+    },
+    #[error("location {location} is provided by more than one vertex attribute")]
+    Duplicate { location: u32 },
+    #[error("location {location} at offset {offset} doesn't fit in the stride {stride} of its vertex buffer")]
+    OutOfBounds {
+        location: u32,
+        offset: u64,
+        stride: u64,
+    },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("the vertex buffer layouts don't match the vertex shader:{}", format_mismatches(.mismatches))]
+pub struct VertexLayoutError {
+    pub mismatches: Vec<VertexAttributeMismatch>,
+}
+
+fn format_mismatches(mismatches: &[VertexAttributeMismatch]) -> String {
+    mismatches
+        .iter()
+        .map(|mismatch| format!("\n  {}", mismatch))
+        .collect()
+}
+
 /// Internally, `bevy_render` uses hashes to identify vertex attribute names.
 pub fn get_vertex_attribute_name_id(name: &str) -> u64 {
     let mut hasher = bevy_utils::AHasher::default();
@@ -33,6 +33,12 @@ pub enum VertexFormat {
     Sint32x2 = 46,
     Sint32x3 = 47,
     Sint32x4 = 48,
+    /// The `Float64` formats need backend support for 64-bit vertex attributes and 64-bit floats
+    /// in shaders, which is only available on some native platforms.
+    Float64 = 49,
+    Float64x2 = 50,
+    Float64x3 = 51,
+    Float64x4 = 52,
 }
 
 impl VertexFormat {
@@ -68,8 +74,90 @@ impl VertexFormat {
             VertexFormat::Sint32x2 => 4 * 2,
             VertexFormat::Sint32x3 => 4 * 3,
             VertexFormat::Sint32x4 => 4 * 4,
+            VertexFormat::Float64 => 8,
+            VertexFormat::Float64x2 => 8 * 2,
+            VertexFormat::Float64x3 => 8 * 3,
+            VertexFormat::Float64x4 => 8 * 4,
         }
     }
+
+    /// The number of components of the format, e.g. 3 for `Float32x3`.
+    pub fn component_count(&self) -> u32 {
+        match *self {
+            VertexFormat::Float32
+            | VertexFormat::Uint32
+            | VertexFormat::Sint32
+            | VertexFormat::Float64 => 1,
+            VertexFormat::Uint8x2
+            | VertexFormat::Sint8x2
+            | VertexFormat::Unorm8x2
+            | VertexFormat::Snorm8x2
+            | VertexFormat::Uint16x2
+            | VertexFormat::Sint16x2
+            | VertexFormat::Unorm16x2
+            | VertexFormat::Snorm16x2
+            | VertexFormat::Float16x2
+            | VertexFormat::Float32x2
+            | VertexFormat::Uint32x2
+            | VertexFormat::Sint32x2
+            | VertexFormat::Float64x2 => 2,
+            VertexFormat::Float32x3
+            | VertexFormat::Uint32x3
+            | VertexFormat::Sint32x3
+            | VertexFormat::Float64x3 => 3,
+            _ => 4,
+        }
+    }
+
+    /// The type of the components as seen by the shader. Normalized and half float formats are
+    /// read as floats.
+    pub fn shader_scalar_type(&self) -> VertexScalarType {
+        match *self {
+            VertexFormat::Uint8x2
+            | VertexFormat::Uint8x4
+            | VertexFormat::Uint16x2
+            | VertexFormat::Uint16x4
+            | VertexFormat::Uint32
+            | VertexFormat::Uint32x2
+            | VertexFormat::Uint32x3
+            | VertexFormat::Uint32x4 => VertexScalarType::Uint,
+            VertexFormat::Sint8x2
+            | VertexFormat::Sint8x4
+            | VertexFormat::Sint16x2
+            | VertexFormat::Sint16x4
+            | VertexFormat::Sint32
+            | VertexFormat::Sint32x2
+            | VertexFormat::Sint32x3
+            | VertexFormat::Sint32x4 => VertexScalarType::Sint,
+            VertexFormat::Float64
+            | VertexFormat::Float64x2
+            | VertexFormat::Float64x3
+            | VertexFormat::Float64x4 => VertexScalarType::Double,
+            _ => VertexScalarType::Float,
+        }
+    }
+
+    /// Returns true for the `Float64` formats.
+    pub fn is_64bit(&self) -> bool {
+        self.shader_scalar_type() == VertexScalarType::Double
+    }
+
+    /// Returns true if a vertex attribute of this format can feed a shader input reflected as
+    /// `shader_format`. The attribute may have more components than the input reads, e.g.
+    /// normals packed as [`VertexFormat::Snorm16x4`] for a `vec3` input, which ignores the rest.
+    pub fn is_compatible_with(&self, shader_format: VertexFormat) -> bool {
+        self.shader_scalar_type() == shader_format.shader_scalar_type()
+            && self.component_count() >= shader_format.component_count()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum VertexScalarType {
+    Float,
+    Uint,
+    Sint,
+    /// 64-bit floats, `double` in GLSL.
+    Double,
 }
 
 pub trait AsVertexFormats {
@@ -82,6 +170,12 @@ impl AsVertexFormats for f32 {
     }
 }
 
+impl AsVertexFormats for f64 {
+    fn as_vertex_formats() -> &'static [VertexFormat] {
+        &[VertexFormat::Float64]
+    }
+}
+
 impl AsVertexFormats for Vec2 {
     fn as_vertex_formats() -> &'static [VertexFormat] {
         &[VertexFormat::Float32x2]
@@ -134,3 +228,21 @@ impl AsVertexFormats for [f32; 4] {
         &[VertexFormat::Float32x4]
     }
 }
+
+impl AsVertexFormats for [f64; 2] {
+    fn as_vertex_formats() -> &'static [VertexFormat] {
+        &[VertexFormat::Float64x2]
+    }
+}
+
+impl AsVertexFormats for [f64; 3] {
+    fn as_vertex_formats() -> &'static [VertexFormat] {
+        &[VertexFormat::Float64x3]
+    }
+}
+
+impl AsVertexFormats for [f64; 4] {
+    fn as_vertex_formats() -> &'static [VertexFormat] {
+        &[VertexFormat::Float64x4]
+    }
+}
@@ -0,0 +1,136 @@
+use crate::texture::{FilterMode, SamplerDescriptor};
+use bevy_ecs::prelude::*;
+use std::num::NonZeroU8;
+
+/// A graphics quality preset, for a simple graphics options menu. A resource of the app world,
+/// which sets the [`RenderQualitySettings`] when it is inserted or changed, so it can be switched
+/// at runtime. Insert it before adding the [`RenderPlugin`](crate::RenderPlugin) to pick the
+/// quality the app starts with.
+///
+/// The presets only cover the settings the renderer supports. There is no MSAA, SSAO, HDR target
+/// or shadow cascades yet, so antialiasing and the other effects are still enabled per camera by
+/// their plugins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Default for RenderQuality {
+    fn default() -> Self {
+        RenderQuality::Medium
+    }
+}
+
+impl RenderQuality {
+    /// Returns the settings of this preset.
+    pub fn settings(&self) -> RenderQualitySettings {
+        match self {
+            RenderQuality::Low => RenderQualitySettings {
+                shadow_map_size: 512,
+                anisotropy: None,
+            },
+            RenderQuality::Medium => RenderQualitySettings {
+                shadow_map_size: 1024,
+                anisotropy: NonZeroU8::new(4),
+            },
+            RenderQuality::High => RenderQualitySettings {
+                shadow_map_size: 2048,
+                anisotropy: NonZeroU8::new(16),
+            },
+        }
+    }
+}
+
+/// The settings the plugins of the renderer read their quality from. A resource of both worlds:
+/// it is set from the [`RenderQuality`] of the app world and extracted every frame. Individual
+/// settings can be changed after the preset, until the preset changes again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderQualitySettings {
+    /// The width and height of the shadow map of each light.
+    pub shadow_map_size: u32,
+    /// The maximum anisotropy of texture samplers that filter linearly and don't set their own
+    /// `anisotropy_clamp`, see [`RenderQualitySettings::sampler`].
+    pub anisotropy: Option<NonZeroU8>,
+}
+
+impl Default for RenderQualitySettings {
+    fn default() -> Self {
+        RenderQuality::default().settings()
+    }
+}
+
+impl FromWorld for RenderQualitySettings {
+    fn from_world(world: &mut World) -> Self {
+        world
+            .get_resource::<RenderQuality>()
+            .copied()
+            .unwrap_or_default()
+            .settings()
+    }
+}
+
+impl RenderQualitySettings {
+    /// Returns `descriptor` with the [`RenderQualitySettings::anisotropy`]. Anisotropic filtering
+    /// needs every filter to be linear, so other samplers, and samplers with their own
+    /// anisotropy, are returned as is.
+    pub fn sampler(&self, descriptor: &SamplerDescriptor) -> SamplerDescriptor {
+        let linear = descriptor.mag_filter == FilterMode::Linear
+            && descriptor.min_filter == FilterMode::Linear
+            && descriptor.mipmap_filter == FilterMode::Linear;
+        if !linear || descriptor.anisotropy_clamp.is_some() || descriptor.compare_function.is_some()
+        {
+            return *descriptor;
+        }
+        SamplerDescriptor {
+            anisotropy_clamp: self.anisotropy,
+            ..*descriptor
+        }
+    }
+}
+
+/// Sets the [`RenderQualitySettings`] when the [`RenderQuality`] changes.
+pub fn render_quality_system(
+    quality: Res<RenderQuality>,
+    mut settings: ResMut<RenderQualitySettings>,
+) {
+    // the settings are initialized from the quality, and may have been changed since
+    if quality.is_changed() && !quality.is_added() {
+        *settings = quality.settings();
+    }
+}
+
+pub fn extract_render_quality(mut commands: Commands, settings: Res<RenderQualitySettings>) {
+    commands.insert_resource(*settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderQuality;
+    use crate::texture::{FilterMode, SamplerDescriptor};
+
+    #[test]
+    fn anisotropy_only_applies_to_linear_samplers() {
+        let settings = RenderQuality::High.settings();
+        let linear = SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.sampler(&linear).anisotropy_clamp,
+            settings.anisotropy
+        );
+
+        let nearest = SamplerDescriptor::default();
+        assert_eq!(settings.sampler(&nearest), nearest);
+
+        let own_anisotropy = SamplerDescriptor {
+            anisotropy_clamp: std::num::NonZeroU8::new(2),
+            ..linear
+        };
+        assert_eq!(settings.sampler(&own_anisotropy), own_anisotropy);
+    }
+}
@@ -4,10 +4,11 @@ pub use render_command_queue::*;
 use crate::{
     render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
     renderer::RenderContext,
-    RenderStage,
+    RenderApp, RenderStage,
 };
 use bevy_app::{App, Plugin};
 use bevy_ecs::prelude::*;
+use std::ops::{Deref, DerefMut};
 
 #[derive(Default)]
 pub struct RenderCommandPlugin;
@@ -18,9 +19,12 @@ impl RenderCommandPlugin {
 
 impl Plugin for RenderCommandPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<RenderCommandQueue>();
-        let render_app = app.sub_app_mut(0);
-        render_app.add_system_to_stage(RenderStage::Extract, extract_render_commands.system());
+        app.init_resource::<RenderCommandQueue>()
+            .init_resource::<AssetUploadQueue>();
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_render_commands.system())
+            .add_system_to_stage(RenderStage::Extract, extract_asset_uploads.system());
         let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
         graph.add_node(Self::RENDER_COMMAND_QUEUE_NODE, RenderCommandQueueNode);
     }
@@ -35,6 +39,43 @@ fn extract_render_commands(
     commands.insert_resource(queue);
 }
 
+fn extract_asset_uploads(mut commands: Commands, mut asset_upload_queue: ResMut<AssetUploadQueue>) {
+    let mut queue = AssetUploadQueue::default();
+    queue.extend(&mut asset_upload_queue);
+    commands.insert_resource(queue);
+}
+
+/// Commands that upload asset data, such as the copies that fill new textures.
+///
+/// Unlike the frame's [`RenderCommandQueue`], backends that support it record these commands in
+/// their own command buffer, in [`RenderStage::Prepare`], and clear the queue. The command buffer
+/// may be submitted right away, so the GPU can start on it before the frame is recorded, or
+/// together with the frame's. Either way it must execute before the frame's commands on the same
+/// GPU queue, which orders the uploads before any pass that reads the uploaded resources, so a
+/// frame never samples a half-uploaded texture. Commands left in the queue are recorded by
+/// [`RenderCommandQueueNode`] ahead of the frame's commands instead.
+///
+/// Upload commands may not copy from the staging belt, whose chunks stay mapped until the render
+/// graph is run.
+#[derive(Debug, Default, Clone)]
+pub struct AssetUploadQueue(RenderCommandQueue);
+
+impl Deref for AssetUploadQueue {
+    type Target = RenderCommandQueue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for AssetUploadQueue {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Records the [`AssetUploadQueue`] commands a backend hasn't submitted yet, followed by the
+/// [`RenderCommandQueue`].
 pub struct RenderCommandQueueNode;
 
 impl Node for RenderCommandQueueNode {
@@ -44,6 +85,9 @@ impl Node for RenderCommandQueueNode {
         render_context: &mut dyn RenderContext,
         world: &World,
     ) -> Result<(), NodeRunError> {
+        if let Some(uploads) = world.get_resource::<AssetUploadQueue>() {
+            uploads.execute(render_context);
+        }
         let queue = world.get_resource::<RenderCommandQueue>().unwrap();
         queue.execute(render_context);
         Ok(())
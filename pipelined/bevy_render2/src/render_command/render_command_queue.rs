@@ -40,10 +40,19 @@ pub enum Command {
         destination_bytes_per_row: u32,
         size: Extent3d,
     },
+    /// See [`RenderContext::generate_mipmaps`].
+    GenerateMipmaps(TextureId),
     // TODO: Frees probably don't need to be queued?
     FreeBuffer(BufferId),
 }
 
+/// A list of [`Command`]s to record before the render graph's passes run.
+///
+/// Commands are executed in the order they were pushed (FIFO), including commands pushed from
+/// different systems over the course of a frame, so later commands can rely on the results of
+/// earlier ones. In the render app, the queue is executed by
+/// [`RenderCommandQueueNode`](super::RenderCommandQueueNode), which runs before
+/// [`MAIN_PASS_DEPENDENCIES`](crate::core_pipeline::node::MAIN_PASS_DEPENDENCIES).
 #[derive(Debug, Default, Clone)]
 pub struct RenderCommandQueue {
     // TODO: this shouldn't really need a mutex. it just needs to be shared on whatever thread it's
@@ -139,14 +148,34 @@ impl RenderCommandQueue {
         })
     }
 
+    /// Queues generation of mip levels `1..` of `texture` from level 0. See
+    /// [`RenderContext::generate_mipmaps`] for the texture requirements.
+    pub fn generate_mipmaps(&mut self, texture: TextureId) {
+        self.push(Command::GenerateMipmaps(texture));
+    }
+
     pub fn free_buffer(&mut self, buffer: BufferId) {
         self.push(Command::FreeBuffer(buffer));
     }
 
+    /// Iterates over the queued commands, in execution order.
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.queue.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
     pub fn clear(&mut self) {
         self.queue.clear();
     }
 
+    /// Moves all commands from `other` to the end of this queue, preserving their order.
     pub fn extend(&mut self, other: &mut RenderCommandQueue) {
         self.queue.extend(other.queue.drain(..));
     }
@@ -220,8 +249,34 @@ impl RenderCommandQueue {
                     destination_bytes_per_row,
                     size,
                 ),
+                Command::GenerateMipmaps(texture) => render_context.generate_mipmaps(texture),
                 Command::FreeBuffer(buffer) => render_context.resources().remove_buffer(buffer),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, RenderCommandQueue};
+    use crate::render_resource::{BufferId, TextureId};
+
+    #[test]
+    fn commands_are_fifo() {
+        let buffer = BufferId::new();
+        let texture = TextureId::new();
+        let mut queue = RenderCommandQueue::default();
+        queue.generate_mipmaps(texture);
+
+        let mut other = RenderCommandQueue::default();
+        other.free_buffer(buffer);
+        queue.extend(&mut other);
+
+        assert!(other.is_empty());
+        assert_eq!(queue.len(), 2);
+        let mut commands = queue.iter();
+        assert!(matches!(commands.next(), Some(Command::GenerateMipmaps(id)) if *id == texture));
+        assert!(matches!(commands.next(), Some(Command::FreeBuffer(id)) if *id == buffer));
+        assert!(commands.next().is_none());
+    }
+}
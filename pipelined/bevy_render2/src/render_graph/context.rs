@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 
 use crate::{
-    render_graph::{NodeState, RenderGraph, SlotInfos, SlotLabel, SlotType, SlotValue},
+    render_graph::{
+        FromSlotValue, NodeState, RenderGraph, SlotInfos, SlotLabel, SlotType, SlotValue,
+    },
     render_resource::{BufferId, SamplerId, TextureViewId},
 };
 use bevy_ecs::entity::Entity;
@@ -49,70 +51,59 @@ impl<'a> RenderGraphContext<'a> {
         &self.node.output_slots
     }
 
-    pub fn get_input(&self, label: impl Into<SlotLabel>) -> Result<SlotValue, InputSlotError> {
+    /// Returns the value of the input slot `label`, e.g. `graph.get_input::<Entity>("view")`.
+    pub fn get_input<T: FromSlotValue>(
+        &self,
+        label: impl Into<SlotLabel>,
+    ) -> Result<T, InputSlotError> {
         let label = label.into();
-        let index = self
+        let (slot, value) = self
             .input_info()
             .get_slot_index(label.clone())
-            .ok_or(InputSlotError::InvalidSlot(label))?;
-        Ok(self.inputs[index])
+            .and_then(|index| Some((self.input_info().get_slot(index)?, *self.inputs.get(index)?)))
+            .ok_or_else(|| InputSlotError::InvalidSlot {
+                node: self.node_name(),
+                label,
+            })?;
+        T::from_slot_value(value).ok_or_else(|| InputSlotError::MismatchedSlotType {
+            node: self.node_name(),
+            slot: slot.name.clone(),
+            expected: T::SLOT_TYPE.unwrap_or(slot.slot_type),
+            actual: value.slot_type(),
+        })
+    }
+
+    /// Returns the name and value of each input slot whose values are `T`s, in slot order.
+    pub fn iter_inputs<T: FromSlotValue>(&self) -> impl Iterator<Item = (&str, T)> {
+        self.input_info()
+            .iter()
+            .zip(self.inputs.iter())
+            .filter_map(|(slot, value)| Some((&*slot.name, T::from_slot_value(*value)?)))
     }
 
     pub fn get_input_texture(
         &self,
         label: impl Into<SlotLabel>,
     ) -> Result<TextureViewId, InputSlotError> {
-        let label = label.into();
-        match self.get_input(label.clone())? {
-            SlotValue::TextureView(value) => Ok(value),
-            value @ _ => Err(InputSlotError::MismatchedSlotType {
-                label,
-                actual: value.slot_type(),
-                expected: SlotType::TextureView,
-            }),
-        }
+        self.get_input(label)
     }
 
     pub fn get_input_sampler(
         &self,
         label: impl Into<SlotLabel>,
     ) -> Result<SamplerId, InputSlotError> {
-        let label = label.into();
-        match self.get_input(label.clone())? {
-            SlotValue::Sampler(value) => Ok(value),
-            value @ _ => Err(InputSlotError::MismatchedSlotType {
-                label,
-                actual: value.slot_type(),
-                expected: SlotType::Sampler,
-            }),
-        }
+        self.get_input(label)
     }
 
     pub fn get_input_buffer(
         &self,
         label: impl Into<SlotLabel>,
     ) -> Result<BufferId, InputSlotError> {
-        let label = label.into();
-        match self.get_input(label.clone())? {
-            SlotValue::Buffer(value) => Ok(value),
-            value @ _ => Err(InputSlotError::MismatchedSlotType {
-                label,
-                actual: value.slot_type(),
-                expected: SlotType::Buffer,
-            }),
-        }
+        self.get_input(label)
     }
 
     pub fn get_input_entity(&self, label: impl Into<SlotLabel>) -> Result<Entity, InputSlotError> {
-        let label = label.into();
-        match self.get_input(label.clone())? {
-            SlotValue::Entity(value) => Ok(value),
-            value @ _ => Err(InputSlotError::MismatchedSlotType {
-                label,
-                actual: value.slot_type(),
-                expected: SlotType::Entity,
-            }),
-        }
+        self.get_input(label)
     }
 
     pub fn set_output(
@@ -122,25 +113,49 @@ impl<'a> RenderGraphContext<'a> {
     ) -> Result<(), OutputSlotError> {
         let label = label.into();
         let value = value.into();
-        let slot_index = self
-            .output_info()
-            .get_slot_index(label.clone())
-            .ok_or(OutputSlotError::InvalidSlot(label.clone()))?;
+        let slot_index = match self.output_info().get_slot_index(label.clone()) {
+            Some(slot_index) if slot_index < self.outputs.len() => slot_index,
+            _ => {
+                return Err(OutputSlotError::InvalidSlot {
+                    node: self.node_name(),
+                    label,
+                })
+            }
+        };
         let slot = self
             .output_info()
             .get_slot(slot_index)
             .expect("slot is valid");
         if value.slot_type() != slot.slot_type {
             return Err(OutputSlotError::MismatchedSlotType {
-                label,
-                actual: slot.slot_type,
-                expected: value.slot_type(),
+                node: self.node_name(),
+                slot: slot.name.clone(),
+                expected: slot.slot_type,
+                actual: value.slot_type(),
             });
         }
         self.outputs[slot_index] = Some(value);
         Ok(())
     }
 
+    /// Sets the output slot `label` to `entity`, e.g. the view entity a camera driver node chose
+    /// for the nodes after it.
+    pub fn set_output_entity(
+        &mut self,
+        label: impl Into<SlotLabel>,
+        entity: Entity,
+    ) -> Result<(), OutputSlotError> {
+        self.set_output(label, entity)
+    }
+
+    /// The name of the node in errors: its name in the graph, or its type name if it has none.
+    fn node_name(&self) -> Cow<'static, str> {
+        self.node
+            .name
+            .clone()
+            .unwrap_or_else(|| self.node.type_name.into())
+    }
+
     pub fn run_sub_graph(
         &mut self,
         name: impl Into<Cow<'static, str>>,
@@ -211,11 +226,15 @@ pub enum RunSubGraphError {
 
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum OutputSlotError {
-    #[error("slot does not exist")]
-    InvalidSlot(SlotLabel),
-    #[error("attempted to assign the wrong type to slot")]
-    MismatchedSlotType {
+    #[error("node '{node}' has no output slot {label:?}")]
+    InvalidSlot {
+        node: Cow<'static, str>,
         label: SlotLabel,
+    },
+    #[error("attempted to assign a {actual:?} to the {expected:?} output slot '{slot}' of node '{node}'")]
+    MismatchedSlotType {
+        node: Cow<'static, str>,
+        slot: Cow<'static, str>,
         expected: SlotType,
         actual: SlotType,
     },
@@ -223,12 +242,102 @@ pub enum OutputSlotError {
 
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum InputSlotError {
-    #[error("slot does not exist")]
-    InvalidSlot(SlotLabel),
-    #[error("attempted to retrieve the wrong type from input slot")]
-    MismatchedSlotType {
+    #[error("node '{node}' has no input slot {label:?}")]
+    InvalidSlot {
+        node: Cow<'static, str>,
         label: SlotLabel,
+    },
+    #[error("attempted to retrieve a {expected:?} from the {actual:?} input slot '{slot}' of node '{node}'")]
+    MismatchedSlotType {
+        node: Cow<'static, str>,
+        slot: Cow<'static, str>,
         expected: SlotType,
         actual: SlotType,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{InputSlotError, OutputSlotError, RenderGraphContext};
+    use crate::{
+        render_graph::{Node, NodeRunError, RenderGraph, SlotInfo, SlotType, SlotValue},
+        render_resource::TextureViewId,
+        renderer::RenderContext,
+    };
+    use bevy_ecs::{entity::Entity, world::World};
+
+    struct ViewNode;
+
+    impl Node for ViewNode {
+        fn input(&self) -> Vec<SlotInfo> {
+            vec![
+                SlotInfo::new("view", SlotType::Entity),
+                SlotInfo::new("color", SlotType::TextureView),
+                SlotInfo::new("depth", SlotType::TextureView),
+            ]
+        }
+
+        fn output(&self) -> Vec<SlotInfo> {
+            vec![SlotInfo::new("view", SlotType::Entity)]
+        }
+
+        fn run(
+            &self,
+            _: &mut RenderGraphContext,
+            _: &mut dyn RenderContext,
+            _: &World,
+        ) -> Result<(), NodeRunError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn typed_slots() {
+        let mut graph = RenderGraph::default();
+        graph.add_node("view_node", ViewNode);
+        let node = graph.get_node_state("view_node").unwrap();
+        let (view, color, depth) = (Entity::new(1), TextureViewId::new(), TextureViewId::new());
+        let inputs = [
+            SlotValue::Entity(view),
+            SlotValue::TextureView(color),
+            SlotValue::TextureView(depth),
+        ];
+        let mut outputs = [None];
+        let mut context = RenderGraphContext::new(&graph, node, &inputs, &mut outputs);
+
+        assert_eq!(context.get_input::<Entity>("view"), Ok(view));
+        assert_eq!(context.get_input::<TextureViewId>(2), Ok(depth));
+        assert_eq!(
+            context.iter_inputs::<TextureViewId>().collect::<Vec<_>>(),
+            vec![("color", color), ("depth", depth)]
+        );
+        assert_eq!(
+            context.get_input::<Entity>("color"),
+            Err(InputSlotError::MismatchedSlotType {
+                node: "view_node".into(),
+                slot: "color".into(),
+                expected: SlotType::Entity,
+                actual: SlotType::TextureView,
+            })
+        );
+        assert_eq!(
+            context.get_input::<Entity>(3),
+            Err(InputSlotError::InvalidSlot {
+                node: "view_node".into(),
+                label: 3.into(),
+            })
+        );
+
+        assert_eq!(
+            context.set_output("view", color),
+            Err(OutputSlotError::MismatchedSlotType {
+                node: "view_node".into(),
+                slot: "view".into(),
+                expected: SlotType::Entity,
+                actual: SlotType::TextureView,
+            })
+        );
+        context.set_output_entity("view", view).unwrap();
+        assert!(matches!(outputs[0], Some(SlotValue::Entity(entity)) if entity == view));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{
+    render_graph::{Node, NodeId, NodeState, RenderGraph, RenderGraphError, SlotInfo, SlotType},
+    RenderApp, RenderStage,
+};
+use bevy_app::{App, Plugin};
+use bevy_asset::{AddAsset, AssetEvent, Assets};
+use bevy_ecs::prelude::*;
+use bevy_reflect::TypeUuid;
+use bevy_utils::{tracing::error, HashMap};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Nodes, edges and sub graphs to add to a [`RenderGraph`], for data-driven pipeline variants.
+///
+/// Nodes are created by the type name they were registered with in [`RenderGraphNodeTypes`].
+/// Edges can connect the new nodes to each other and to nodes already in the graph, and sub
+/// graphs that already exist are extended rather than replaced. With the `ron` feature, the
+/// [`RenderGraphDescriptorPlugin`] loads descriptors from `.render_graph.ron` files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "c5cf054b-9c27-42c2-8b1f-a90982cf0f55"]
+pub struct RenderGraphDescriptor {
+    #[serde(default)]
+    pub nodes: Vec<NodeDescriptor>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDescriptor>,
+    #[serde(default)]
+    pub sub_graphs: Vec<SubGraphDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDescriptor {
+    pub name: String,
+    /// The name the node type was registered with in [`RenderGraphNodeTypes`].
+    pub node_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EdgeDescriptor {
+    Slot {
+        output_node: String,
+        output_slot: String,
+        input_node: String,
+        input_slot: String,
+    },
+    Node {
+        output_node: String,
+        input_node: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubGraphDescriptor {
+    pub name: String,
+    /// The input slots of the sub graph, read by its nodes through edges from the
+    /// [`RenderGraph::INPUT_NODE_NAME`] node. Only used when the sub graph is created.
+    #[serde(default)]
+    pub inputs: Vec<(String, SlotType)>,
+    pub graph: RenderGraphDescriptor,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RenderGraphDescriptorError {
+    #[error("node type '{0}' is not registered")]
+    UnknownNodeType(String),
+    #[error("a node named '{0}' already exists")]
+    NodeAlreadyExists(String),
+    #[error("render graph error in sub graph '{sub_graph}': {error}")]
+    SubGraph {
+        sub_graph: String,
+        error: Box<RenderGraphDescriptorError>,
+    },
+    #[error(transparent)]
+    RenderGraph(#[from] RenderGraphError),
+}
+
+type NodeConstructor = Box<dyn Fn(&mut World) -> NodeState + Send + Sync>;
+
+/// The node types [`RenderGraphDescriptor`]s can create, by name. A resource of the render app.
+#[derive(Default)]
+pub struct RenderGraphNodeTypes {
+    constructors: HashMap<String, NodeConstructor>,
+}
+
+impl RenderGraphNodeTypes {
+    /// Registers a node type, created with `constructor` from the render world.
+    pub fn register<T: Node>(
+        &mut self,
+        name: impl Into<String>,
+        constructor: impl Fn(&mut World) -> T + Send + Sync + 'static,
+    ) {
+        self.constructors.insert(
+            name.into(),
+            Box::new(move |world| NodeState::new(NodeId::new(), constructor(world))),
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(name)
+    }
+}
+
+impl RenderGraphDescriptor {
+    /// Adds the nodes, edges and sub graphs to `graph`. Nodes are added before edges, so edges
+    /// can refer to nodes regardless of their order.
+    pub fn apply(
+        &self,
+        graph: &mut RenderGraph,
+        node_types: &RenderGraphNodeTypes,
+        world: &mut World,
+    ) -> Result<(), RenderGraphDescriptorError> {
+        for node in self.nodes.iter() {
+            if graph.get_node_id(node.name.clone()).is_ok() {
+                return Err(RenderGraphDescriptorError::NodeAlreadyExists(
+                    node.name.clone(),
+                ));
+            }
+            let constructor = node_types
+                .constructors
+                .get(&node.node_type)
+                .ok_or_else(|| {
+                    RenderGraphDescriptorError::UnknownNodeType(node.node_type.clone())
+                })?;
+            graph.add_node_state(node.name.clone(), constructor(world));
+        }
+
+        for edge in self.edges.iter() {
+            match edge {
+                EdgeDescriptor::Slot {
+                    output_node,
+                    output_slot,
+                    input_node,
+                    input_slot,
+                } => graph.add_slot_edge(
+                    output_node.clone(),
+                    output_slot.clone(),
+                    input_node.clone(),
+                    input_slot.clone(),
+                )?,
+                EdgeDescriptor::Node {
+                    output_node,
+                    input_node,
+                } => graph.add_node_edge(output_node.clone(), input_node.clone())?,
+            }
+        }
+
+        for sub_graph in self.sub_graphs.iter() {
+            if graph.get_sub_graph(&sub_graph.name).is_none() {
+                let mut new_graph = RenderGraph::default();
+                if !sub_graph.inputs.is_empty() {
+                    new_graph.set_input(
+                        sub_graph
+                            .inputs
+                            .iter()
+                            .map(|(name, slot_type)| SlotInfo::new(name.clone(), *slot_type))
+                            .collect(),
+                    );
+                }
+                graph.add_sub_graph(sub_graph.name.clone(), new_graph);
+            }
+            let target = graph.get_sub_graph_mut(&sub_graph.name).unwrap();
+            sub_graph
+                .graph
+                .apply(target, node_types, world)
+                .map_err(|error| RenderGraphDescriptorError::SubGraph {
+                    sub_graph: sub_graph.name.clone(),
+                    error: Box::new(error),
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies [`RenderGraphDescriptor`] assets to the [`RenderGraph`] once they are loaded.
+///
+/// Descriptors are applied once, when they are created. Changes to a loaded descriptor take
+/// effect the next time the app starts, as nodes can't be removed from the graph.
+#[derive(Default)]
+pub struct RenderGraphDescriptorPlugin;
+
+impl Plugin for RenderGraphDescriptorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<RenderGraphDescriptor>();
+        #[cfg(feature = "ron")]
+        app.init_asset_loader::<RenderGraphDescriptorLoader>();
+
+        app.sub_app_mut(RenderApp)
+            .add_system_to_stage(
+                RenderStage::Extract,
+                extract_render_graph_descriptors.system(),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                apply_render_graph_descriptors.exclusive_system(),
+            );
+    }
+}
+
+/// Descriptors created since the last frame, waiting to be applied in the render world.
+#[derive(Default)]
+struct PendingRenderGraphDescriptors(Vec<RenderGraphDescriptor>);
+
+fn extract_render_graph_descriptors(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<RenderGraphDescriptor>>,
+    descriptors: Res<Assets<RenderGraphDescriptor>>,
+) {
+    let pending = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } => descriptors.get(handle).cloned(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !pending.is_empty() {
+        commands.insert_resource(PendingRenderGraphDescriptors(pending));
+    }
+}
+
+fn apply_render_graph_descriptors(world: &mut World) {
+    let pending = match world.remove_resource::<PendingRenderGraphDescriptors>() {
+        Some(pending) => pending.0,
+        None => return,
+    };
+    let mut graph = world.remove_resource::<RenderGraph>().unwrap();
+    let node_types = world.remove_resource::<RenderGraphNodeTypes>().unwrap();
+    for descriptor in pending {
+        if let Err(err) = descriptor.apply(&mut graph, &node_types, world) {
+            error!("failed to apply render graph descriptor: {}", err);
+        }
+    }
+    world.insert_resource(node_types);
+    world.insert_resource(graph);
+}
+
+#[cfg(feature = "ron")]
+#[derive(Default)]
+pub struct RenderGraphDescriptorLoader;
+
+#[cfg(feature = "ron")]
+impl bevy_asset::AssetLoader for RenderGraphDescriptorLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy_asset::LoadContext,
+    ) -> bevy_utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let descriptor = ron::de::from_bytes::<RenderGraphDescriptor>(bytes)?;
+            load_context.set_default_asset(bevy_asset::LoadedAsset::new(descriptor));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["render_graph.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        EdgeDescriptor, NodeDescriptor, RenderGraphDescriptor, RenderGraphDescriptorError,
+        RenderGraphNodeTypes, SubGraphDescriptor,
+    };
+    use crate::render_graph::{EmptyNode, RenderGraph, SlotType};
+    use bevy_ecs::world::World;
+
+    fn node(name: &str) -> NodeDescriptor {
+        NodeDescriptor {
+            name: name.to_string(),
+            node_type: "EmptyNode".to_string(),
+        }
+    }
+
+    #[test]
+    fn apply_descriptor() {
+        let mut world = World::default();
+        let mut node_types = RenderGraphNodeTypes::default();
+        node_types.register("EmptyNode", |_| EmptyNode);
+        let mut graph = RenderGraph::default();
+        graph.add_node("existing", EmptyNode);
+
+        let descriptor = RenderGraphDescriptor {
+            nodes: vec![node("a")],
+            edges: vec![EdgeDescriptor::Node {
+                output_node: "existing".to_string(),
+                input_node: "a".to_string(),
+            }],
+            sub_graphs: vec![SubGraphDescriptor {
+                name: "sub".to_string(),
+                inputs: vec![("view".to_string(), SlotType::Entity)],
+                graph: RenderGraphDescriptor {
+                    nodes: vec![node("b")],
+                    ..Default::default()
+                },
+            }],
+        };
+        descriptor
+            .apply(&mut graph, &node_types, &mut world)
+            .unwrap();
+
+        let a = graph.get_node_id("a").unwrap();
+        assert!(graph
+            .descendants(graph.get_node_id("existing").unwrap())
+            .contains(&a));
+        let sub_graph = graph.get_sub_graph("sub").unwrap();
+        assert!(sub_graph.get_node_id("b").is_ok());
+        assert!(sub_graph.input_node().is_some());
+
+        assert_eq!(
+            descriptor.apply(&mut graph, &node_types, &mut world),
+            Err(RenderGraphDescriptorError::NodeAlreadyExists(
+                "a".to_string()
+            ))
+        );
+        let unknown = RenderGraphDescriptor {
+            nodes: vec![NodeDescriptor {
+                name: "c".to_string(),
+                node_type: "Unknown".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            unknown.apply(&mut graph, &node_types, &mut world),
+            Err(RenderGraphDescriptorError::UnknownNodeType(
+                "Unknown".to_string()
+            ))
+        );
+    }
+}
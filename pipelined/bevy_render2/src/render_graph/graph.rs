@@ -1,12 +1,12 @@
 use crate::{
     render_graph::{
         Edge, Node, NodeId, NodeLabel, NodeRunError, NodeState, RenderGraphContext,
-        RenderGraphError, SlotInfo, SlotLabel,
+        RenderGraphError, RenderGraphSnapshot, SlotInfo, SlotLabel,
     },
     renderer::RenderContext,
 };
 use bevy_ecs::prelude::World;
-use bevy_utils::HashMap;
+use bevy_utils::{HashMap, HashSet};
 use std::{borrow::Cow, fmt::Debug};
 
 #[derive(Default)]
@@ -15,6 +15,7 @@ pub struct RenderGraph {
     node_names: HashMap<Cow<'static, str>, NodeId>,
     sub_graphs: HashMap<Cow<'static, str>, RenderGraph>,
     input_node: Option<NodeId>,
+    transient_texture_users: HashMap<Cow<'static, str>, Vec<NodeId>>,
 }
 
 impl RenderGraph {
@@ -49,9 +50,18 @@ impl RenderGraph {
     where
         T: Node,
     {
-        let id = NodeId::new();
+        self.add_node_state(name, NodeState::new(NodeId::new(), node))
+    }
+
+    /// Adds a node whose type isn't known statically, e.g. one created from a
+    /// [`RenderGraphDescriptor`](crate::render_graph::RenderGraphDescriptor).
+    pub(crate) fn add_node_state(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        mut node_state: NodeState,
+    ) -> NodeId {
+        let id = node_state.id;
         let name = name.into();
-        let mut node_state = NodeState::new(id, node);
         node_state.name = Some(name.clone());
         self.nodes.insert(id, node_state);
         self.node_names.insert(name, id);
@@ -292,6 +302,47 @@ impl RenderGraph {
             .map(move |(edge, input_node_id)| (edge, self.get_node_state(input_node_id).unwrap())))
     }
 
+    /// Declares that `node` reads or writes the transient texture `texture`. Transient textures
+    /// whose users never run at the same time can share memory. See
+    /// [`TransientTextures`](super::TransientTextures).
+    pub fn add_transient_texture_user(
+        &mut self,
+        texture: impl Into<Cow<'static, str>>,
+        node: impl Into<NodeLabel>,
+    ) -> Result<(), RenderGraphError> {
+        let node = self.get_node_id(node)?;
+        let users = self
+            .transient_texture_users
+            .entry(texture.into())
+            .or_insert_with(Vec::new);
+        if !users.contains(&node) {
+            users.push(node);
+        }
+        Ok(())
+    }
+
+    pub fn get_transient_texture_users(&self, texture: &str) -> &[NodeId] {
+        self.transient_texture_users
+            .get(texture)
+            .map_or(&[], |users| users.as_slice())
+    }
+
+    /// Returns the ids of all nodes that can only run after `node` has run.
+    pub fn descendants(&self, node: NodeId) -> HashSet<NodeId> {
+        let mut descendants = HashSet::default();
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            if let Ok(outputs) = self.iter_node_outputs(id) {
+                for (_, output) in outputs {
+                    if descendants.insert(output.id) {
+                        stack.push(output.id);
+                    }
+                }
+            }
+        }
+        descendants
+    }
+
     pub fn add_sub_graph(&mut self, name: impl Into<Cow<'static, str>>, sub_graph: RenderGraph) {
         self.sub_graphs.insert(name.into(), sub_graph);
     }
@@ -303,6 +354,17 @@ impl RenderGraph {
     pub fn get_sub_graph_mut(&mut self, name: impl AsRef<str>) -> Option<&mut RenderGraph> {
         self.sub_graphs.get_mut(name.as_ref())
     }
+
+    pub fn iter_sub_graphs(&self) -> impl Iterator<Item = (&str, &RenderGraph)> {
+        self.sub_graphs
+            .iter()
+            .map(|(name, graph)| (name.as_ref(), graph))
+    }
+
+    /// Returns the structure of this graph and its sub graphs, see [`RenderGraphSnapshot`].
+    pub fn snapshot(&self) -> RenderGraphSnapshot {
+        RenderGraphSnapshot::new(self)
+    }
 }
 
 impl Debug for RenderGraph {
@@ -1,14 +1,20 @@
 mod context;
+mod descriptor;
 mod edge;
 mod graph;
 mod node;
 mod node_slot;
+mod snapshot;
+mod transient;
 
 pub use context::*;
+pub use descriptor::*;
 pub use edge::*;
 pub use graph::*;
 pub use node::*;
 pub use node_slot::*;
+pub use snapshot::*;
+pub use transient::*;
 
 use thiserror::Error;
 
@@ -50,9 +50,9 @@ impl_downcast!(Node);
 
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum NodeRunError {
-    #[error("encountered an input slot error")]
+    #[error("encountered an input slot error: {0}")]
     InputSlotError(#[from] InputSlotError),
-    #[error("encountered an output slot error")]
+    #[error("encountered an output slot error: {0}")]
     OutputSlotError(#[from] OutputSlotError),
     #[error("encountered an error when running a sub-graph")]
     RunSubGraphError(#[from] RunSubGraphError),
@@ -1,5 +1,6 @@
 use crate::render_resource::{BufferId, SamplerId, TextureViewId};
 use bevy_ecs::entity::Entity;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 #[derive(Debug, Copy, Clone)]
@@ -45,7 +46,69 @@ impl From<Entity> for SlotValue {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// A value that can be read from a [`SlotValue`], see
+/// [`RenderGraphContext::get_input`](super::RenderGraphContext::get_input).
+pub trait FromSlotValue: Into<SlotValue> + Sized {
+    /// The type of the slots values are read from, or `None` if they can be read from any slot.
+    const SLOT_TYPE: Option<SlotType>;
+
+    /// Returns the value in `value`, or `None` if it has another type.
+    fn from_slot_value(value: SlotValue) -> Option<Self>;
+}
+
+impl FromSlotValue for SlotValue {
+    const SLOT_TYPE: Option<SlotType> = None;
+
+    fn from_slot_value(value: SlotValue) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromSlotValue for BufferId {
+    const SLOT_TYPE: Option<SlotType> = Some(SlotType::Buffer);
+
+    fn from_slot_value(value: SlotValue) -> Option<Self> {
+        match value {
+            SlotValue::Buffer(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromSlotValue for TextureViewId {
+    const SLOT_TYPE: Option<SlotType> = Some(SlotType::TextureView);
+
+    fn from_slot_value(value: SlotValue) -> Option<Self> {
+        match value {
+            SlotValue::TextureView(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromSlotValue for SamplerId {
+    const SLOT_TYPE: Option<SlotType> = Some(SlotType::Sampler);
+
+    fn from_slot_value(value: SlotValue) -> Option<Self> {
+        match value {
+            SlotValue::Sampler(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromSlotValue for Entity {
+    const SLOT_TYPE: Option<SlotType> = Some(SlotType::Entity);
+
+    fn from_slot_value(value: SlotValue) -> Option<Self> {
+        match value {
+            SlotValue::Entity(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SlotType {
     Buffer,
     TextureView,
@@ -0,0 +1,346 @@
+use crate::render_graph::{Edge, NodeId, RenderGraph, SlotInfos, SlotType};
+use bevy_utils::HashMap;
+use std::fmt::{self, Display};
+
+/// The structure of a [`RenderGraph`]: its nodes, their slots, the edges between them and its sub
+/// graphs, identified by name instead of by [`NodeId`]. Nodes without a name are called after
+/// their type and their index among the unnamed nodes of that type, e.g. `my_crate::MyNode#0`.
+///
+/// Snapshots of the same structure compare equal no matter in which order nodes and edges were
+/// added, which makes them suitable for asserting how plugins wire their nodes in tests. The
+/// [`Display`] output lists one item per line and can be compared against a checked in string.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RenderGraphSnapshot {
+    /// Sorted by name.
+    pub nodes: Vec<NodeSnapshot>,
+    /// Sorted by output node, then input node.
+    pub edges: Vec<EdgeSnapshot>,
+    /// Sorted by name.
+    pub sub_graphs: Vec<(String, RenderGraphSnapshot)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeSnapshot {
+    pub name: String,
+    pub inputs: Vec<SlotSnapshot>,
+    pub outputs: Vec<SlotSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotSnapshot {
+    pub name: String,
+    pub slot_type: SlotType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeSnapshot {
+    Slot {
+        output_node: String,
+        output_slot: String,
+        input_node: String,
+        input_slot: String,
+    },
+    Node {
+        output_node: String,
+        input_node: String,
+    },
+}
+
+impl EdgeSnapshot {
+    fn sort_key(&self) -> (&str, &str, &str, &str) {
+        match self {
+            EdgeSnapshot::Slot {
+                output_node,
+                output_slot,
+                input_node,
+                input_slot,
+            } => (output_node, input_node, output_slot, input_slot),
+            EdgeSnapshot::Node {
+                output_node,
+                input_node,
+            } => (output_node, input_node, "", ""),
+        }
+    }
+}
+
+impl RenderGraphSnapshot {
+    pub fn new(graph: &RenderGraph) -> Self {
+        let slot_names = |slots: &SlotInfos| {
+            slots
+                .iter()
+                .map(|slot| slot.name.to_string())
+                .collect::<Vec<_>>()
+        };
+        // unnamed nodes are indexed in the order of their slots, which doesn't depend on the
+        // order they were added in
+        let mut unnamed = graph
+            .iter_nodes()
+            .filter(|node| node.name.is_none())
+            .collect::<Vec<_>>();
+        unnamed.sort_by_key(|node| {
+            (
+                node.type_name,
+                slot_names(&node.input_slots),
+                slot_names(&node.output_slots),
+            )
+        });
+        let mut type_counts = HashMap::<&str, usize>::default();
+        let mut names = HashMap::default();
+        for node in unnamed {
+            let index = type_counts.entry(node.type_name).or_insert(0);
+            names.insert(node.id, format!("{}#{}", node.type_name, index));
+            *index += 1;
+        }
+        for node in graph.iter_nodes() {
+            if let Some(name) = &node.name {
+                names.insert(node.id, name.to_string());
+            }
+        }
+        let node_name = |id: NodeId| {
+            names
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("{:?}", id))
+        };
+        let slot_name = |slots: &SlotInfos, index: usize| {
+            slots
+                .get_slot(index)
+                .map_or_else(|| index.to_string(), |slot| slot.name.to_string())
+        };
+        let slots = |slots: &SlotInfos| {
+            slots
+                .iter()
+                .map(|slot| SlotSnapshot {
+                    name: slot.name.to_string(),
+                    slot_type: slot.slot_type,
+                })
+                .collect()
+        };
+
+        let mut nodes = graph
+            .iter_nodes()
+            .map(|node| NodeSnapshot {
+                name: node_name(node.id),
+                inputs: slots(&node.input_slots),
+                outputs: slots(&node.output_slots),
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // every edge is stored on both of its nodes, so only the output side is collected
+        let mut edges = graph
+            .iter_nodes()
+            .flat_map(|node| node.edges.output_edges.iter())
+            .map(|edge| match *edge {
+                Edge::SlotEdge {
+                    output_node,
+                    output_index,
+                    input_node,
+                    input_index,
+                } => EdgeSnapshot::Slot {
+                    output_node: node_name(output_node),
+                    output_slot: graph.get_node_state(output_node).map_or_else(
+                        |_| output_index.to_string(),
+                        |node| slot_name(&node.output_slots, output_index),
+                    ),
+                    input_node: node_name(input_node),
+                    input_slot: graph.get_node_state(input_node).map_or_else(
+                        |_| input_index.to_string(),
+                        |node| slot_name(&node.input_slots, input_index),
+                    ),
+                },
+                Edge::NodeEdge {
+                    output_node,
+                    input_node,
+                } => EdgeSnapshot::Node {
+                    output_node: node_name(output_node),
+                    input_node: node_name(input_node),
+                },
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+
+        let mut sub_graphs = graph
+            .iter_sub_graphs()
+            .map(|(name, sub_graph)| (name.to_string(), RenderGraphSnapshot::new(sub_graph)))
+            .collect::<Vec<_>>();
+        sub_graphs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        RenderGraphSnapshot {
+            nodes,
+            edges,
+            sub_graphs,
+        }
+    }
+
+    pub fn node(&self, name: &str) -> Option<&NodeSnapshot> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    pub fn sub_graph(&self, name: &str) -> Option<&RenderGraphSnapshot> {
+        self.sub_graphs
+            .iter()
+            .find(|(sub_graph, _)| sub_graph == name)
+            .map(|(_, snapshot)| snapshot)
+    }
+
+    /// Returns true if `output_node` has an edge of any kind to `input_node`.
+    pub fn has_edge(&self, output_node: &str, input_node: &str) -> bool {
+        self.edges.iter().any(|edge| {
+            let (output, input, _, _) = edge.sort_key();
+            output == output_node && input == input_node
+        })
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent);
+        for node in self.nodes.iter() {
+            writeln!(f, "{}node {}", pad, node.name)?;
+            for slot in node.inputs.iter() {
+                writeln!(f, "{}  in {}: {:?}", pad, slot.name, slot.slot_type)?;
+            }
+            for slot in node.outputs.iter() {
+                writeln!(f, "{}  out {}: {:?}", pad, slot.name, slot.slot_type)?;
+            }
+        }
+        for edge in self.edges.iter() {
+            match edge {
+                EdgeSnapshot::Slot {
+                    output_node,
+                    output_slot,
+                    input_node,
+                    input_slot,
+                } => writeln!(
+                    f,
+                    "{}edge {}.{} -> {}.{}",
+                    pad, output_node, output_slot, input_node, input_slot
+                )?,
+                EdgeSnapshot::Node {
+                    output_node,
+                    input_node,
+                } => writeln!(f, "{}edge {} -> {}", pad, output_node, input_node)?,
+            }
+        }
+        for (name, sub_graph) in self.sub_graphs.iter() {
+            writeln!(f, "{}sub graph {}", pad, name)?;
+            sub_graph.fmt_indented(f, indent + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RenderGraphSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderGraphSnapshot;
+    use crate::{
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        renderer::RenderContext,
+    };
+    use bevy_ecs::world::World;
+
+    struct TestNode {
+        inputs: Vec<SlotInfo>,
+        outputs: Vec<SlotInfo>,
+    }
+
+    impl TestNode {
+        fn new(inputs: &[&'static str], outputs: &[&'static str]) -> Self {
+            let slots = |names: &[&'static str]| {
+                names
+                    .iter()
+                    .map(|name| SlotInfo::new(*name, SlotType::TextureView))
+                    .collect()
+            };
+            TestNode {
+                inputs: slots(inputs),
+                outputs: slots(outputs),
+            }
+        }
+    }
+
+    impl Node for TestNode {
+        fn input(&self) -> Vec<SlotInfo> {
+            self.inputs.clone()
+        }
+
+        fn output(&self) -> Vec<SlotInfo> {
+            self.outputs.clone()
+        }
+
+        fn run(
+            &self,
+            _: &mut RenderGraphContext,
+            _: &mut dyn RenderContext,
+            _: &World,
+        ) -> Result<(), NodeRunError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn snapshot_is_independent_of_insertion_order() {
+        let mut first = RenderGraph::default();
+        first.add_node("b", TestNode::new(&["view"], &[]));
+        first.add_node("a", TestNode::new(&[], &["target"]));
+        first.add_slot_edge("a", "target", "b", "view").unwrap();
+
+        let mut second = RenderGraph::default();
+        second.add_node("a", TestNode::new(&[], &["target"]));
+        second.add_node("b", TestNode::new(&["view"], &[]));
+        second.add_slot_edge("a", 0, "b", 0).unwrap();
+
+        let mut sub_graph = RenderGraph::default();
+        sub_graph.add_node("c", TestNode::new(&[], &[]));
+        second.add_sub_graph("sub", sub_graph);
+
+        let first = RenderGraphSnapshot::new(&first);
+        let second = RenderGraphSnapshot::new(&second);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.edges, second.edges);
+        assert!(second.has_edge("a", "b"));
+        assert_eq!(
+            second.to_string(),
+            "node a\n  out target: TextureView\nnode b\n  in view: TextureView\n\
+             edge a.target -> b.view\nsub graph sub\n  node c\n"
+        );
+    }
+
+    #[test]
+    fn unnamed_nodes_are_named_by_type_and_index() {
+        let mut graph = RenderGraph::default();
+        graph.add_node("named", TestNode::new(&[], &[]));
+        let first = graph.add_node("first", TestNode::new(&[], &["a"]));
+        let second = graph.add_node("second", TestNode::new(&[], &["b"]));
+        for node in graph.iter_nodes_mut() {
+            if node.id == first || node.id == second {
+                node.name = None;
+            }
+        }
+
+        let snapshot = RenderGraphSnapshot::new(&graph);
+        let type_name = std::any::type_name::<TestNode>();
+        let names = snapshot
+            .nodes
+            .iter()
+            .map(|node| node.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                format!("{}#0", type_name),
+                format!("{}#1", type_name),
+                "named".to_string(),
+            ]
+        );
+        assert_eq!(snapshot.nodes[0].outputs[0].name, "a");
+        assert_eq!(snapshot.nodes[1].outputs[0].name, "b");
+        // the names don't change between snapshots
+        assert_eq!(snapshot, RenderGraphSnapshot::new(&graph));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    render_graph::{NodeId, RenderGraph},
+    renderer::RenderResources,
+    texture::{CachedTexture, TextureCache, TextureDescriptor},
+};
+use bevy_ecs::prelude::*;
+use bevy_utils::{HashMap, HashSet};
+use std::borrow::Cow;
+
+/// Short-lived textures that only live for the duration of a few render graph nodes, such as the
+/// intermediate targets of a post-processing chain.
+///
+/// Systems request transient textures every frame during [`RenderStage::Prepare`], before
+/// [`RenderSystem::PrepareTransientTextures`], and nodes read them with
+/// [`TransientTextures::get`]. The nodes that use a texture are declared once with
+/// [`RenderGraph::add_transient_texture_user`]. Textures with the same descriptor are backed by
+/// the same [`TextureCache`] entry when all users of one texture are guaranteed to run before
+/// all users of the other.
+///
+/// Only nodes of the top-level [`RenderGraph`] are considered.
+///
+/// [`RenderStage::Prepare`]: crate::RenderStage::Prepare
+/// [`RenderSystem::PrepareTransientTextures`]: crate::RenderSystem::PrepareTransientTextures
+#[derive(Default)]
+pub struct TransientTextures {
+    requested: HashMap<Cow<'static, str>, TextureDescriptor>,
+    allocated: HashMap<Cow<'static, str>, CachedTexture>,
+}
+
+impl TransientTextures {
+    /// Requests a transient texture named `name` for this frame.
+    pub fn request(&mut self, name: impl Into<Cow<'static, str>>, descriptor: TextureDescriptor) {
+        self.requested.insert(name.into(), descriptor);
+    }
+
+    /// Returns the texture allocated for `name` this frame.
+    pub fn get(&self, name: &str) -> Option<&CachedTexture> {
+        self.allocated.get(name)
+    }
+
+    /// Returns the number of distinct textures backing the transient textures of this frame.
+    pub fn allocated_texture_count(&self) -> usize {
+        self.allocated
+            .values()
+            .map(|texture| texture.texture)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+/// Groups the requested textures into chains that can share one texture. Within a chain, every
+/// user of a texture runs before every user of the next texture.
+fn plan_aliases(
+    graph: &RenderGraph,
+    requested: &HashMap<Cow<'static, str>, TextureDescriptor>,
+) -> Vec<Vec<Cow<'static, str>>> {
+    let mut descendants: HashMap<NodeId, HashSet<NodeId>> = HashMap::default();
+    let mut runs_before = |first: &str, second: &str| {
+        let first_users = graph.get_transient_texture_users(first);
+        let second_users = graph.get_transient_texture_users(second);
+        if first_users.is_empty() || second_users.is_empty() {
+            return false;
+        }
+        first_users.iter().all(|user| {
+            let descendants = descendants
+                .entry(*user)
+                .or_insert_with(|| graph.descendants(*user));
+            second_users.iter().all(|other| descendants.contains(other))
+        })
+    };
+
+    // sort for a stable result, as the requests are stored in a HashMap
+    let mut names = requested.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+
+    let mut chains: Vec<Vec<Cow<'static, str>>> = Vec::new();
+    'textures: for name in names {
+        let descriptor = &requested[&name];
+        for chain in chains.iter_mut() {
+            if requested[&chain[0]] != *descriptor {
+                continue;
+            }
+            if runs_before(chain.last().unwrap(), &name) {
+                chain.push(name);
+                continue 'textures;
+            }
+            if runs_before(&name, &chain[0]) {
+                chain.insert(0, name);
+                continue 'textures;
+            }
+        }
+        chains.push(vec![name]);
+    }
+    chains
+}
+
+pub fn prepare_transient_textures(
+    graph: Res<RenderGraph>,
+    render_resources: Res<RenderResources>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut transient_textures: ResMut<TransientTextures>,
+) {
+    let transient_textures = &mut *transient_textures;
+    transient_textures.allocated.clear();
+    for chain in plan_aliases(&graph, &transient_textures.requested) {
+        let descriptor = transient_textures.requested[&chain[0]];
+        let texture = texture_cache.get(&render_resources, descriptor);
+        for name in chain {
+            transient_textures.allocated.insert(name, texture);
+        }
+    }
+    transient_textures.requested.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::plan_aliases;
+    use crate::{
+        render_graph::{EmptyNode, RenderGraph},
+        texture::{Extent3d, TextureDescriptor, TextureFormat},
+    };
+    use bevy_utils::HashMap;
+
+    #[test]
+    fn non_overlapping_textures_are_aliased() {
+        // a -> b -> c -> d
+        let mut graph = RenderGraph::default();
+        for name in ["a", "b", "c", "d"].iter() {
+            graph.add_node(*name, EmptyNode);
+        }
+        graph.add_node_edge("a", "b").unwrap();
+        graph.add_node_edge("b", "c").unwrap();
+        graph.add_node_edge("c", "d").unwrap();
+
+        // "first" and "third" don't overlap, "second" overlaps both
+        graph.add_transient_texture_user("first", "a").unwrap();
+        graph.add_transient_texture_user("first", "b").unwrap();
+        graph.add_transient_texture_user("second", "b").unwrap();
+        graph.add_transient_texture_user("second", "c").unwrap();
+        graph.add_transient_texture_user("third", "c").unwrap();
+        graph.add_transient_texture_user("third", "d").unwrap();
+        // "other" doesn't overlap "first", but has a different descriptor
+        graph.add_transient_texture_user("other", "d").unwrap();
+
+        let descriptor = TextureDescriptor::default();
+        let mut requested = HashMap::default();
+        requested.insert("first".into(), descriptor);
+        requested.insert("second".into(), descriptor);
+        requested.insert("third".into(), descriptor);
+        requested.insert(
+            "other".into(),
+            TextureDescriptor {
+                size: Extent3d::new(2, 2, 1),
+                format: TextureFormat::R8Unorm,
+                ..descriptor
+            },
+        );
+
+        let mut chains = plan_aliases(&graph, &requested);
+        chains.sort();
+        assert_eq!(
+            chains,
+            vec![vec!["first", "third"], vec!["other"], vec!["second"]]
+        );
+    }
+}
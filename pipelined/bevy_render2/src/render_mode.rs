@@ -0,0 +1,180 @@
+use crate::{camera::Camera, mesh::Mesh, texture::Texture, view::VisibilityEvent};
+use bevy_asset::{AssetEvent, Handle};
+use bevy_ecs::prelude::*;
+use bevy_transform::components::GlobalTransform;
+use bevy_window::{WindowCreated, WindowResized, WindowScaleFactorChanged, WindowSurfaceCreated};
+
+/// When the app is rendered. A resource of the app world, which can be changed at any time.
+///
+/// [`RenderMode::OnDemand`] suits tools and menus, which show the same image most of the time.
+/// Frames that aren't rendered don't run the render graph and don't present, so the windows keep
+/// showing the last rendered frame. The app itself still updates every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Every frame is rendered.
+    Continuous,
+    /// Frames are only rendered when something they show may have changed: a [`GlobalTransform`],
+    /// a [`Camera`] or the mesh of an entity was added, changed or removed, an entity entered or
+    /// left a view, a mesh or texture asset changed, a window was created, resized or got a new
+    /// surface, or a [`RequestRender`] event was sent. The plugins drawing other things, like
+    /// materials, lights and sprites, send [`RequestRender`] when those change.
+    OnDemand,
+}
+
+impl Default for RenderMode {
+    fn default() -> Self {
+        RenderMode::Continuous
+    }
+}
+
+/// Send this event to render the current frame while the [`RenderMode`] is
+/// [`RenderMode::OnDemand`], e.g. after changing something the renderer can't detect, like the
+/// clear color.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestRender;
+
+/// Whether the current frame is rendered, set from the [`RenderMode`] at the end of each frame
+/// by [`render_mode_system`]. A resource of the app world, which extract systems check along with
+/// the [`RenderActive`](crate::RenderActive) state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderRequested(pub bool);
+
+impl Default for RenderRequested {
+    fn default() -> Self {
+        RenderRequested(true)
+    }
+}
+
+/// Sets [`RenderRequested`] from the [`RenderMode`] and the changes of the frame.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn render_mode_system(
+    render_mode: Res<RenderMode>,
+    mut render_requested: ResMut<RenderRequested>,
+    mut request_render_events: EventReader<RequestRender>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut window_created_events: EventReader<WindowCreated>,
+    mut window_resized_events: EventReader<WindowResized>,
+    mut scale_factor_changed_events: EventReader<WindowScaleFactorChanged>,
+    mut surface_created_events: EventReader<WindowSurfaceCreated>,
+    mut visibility_events: EventReader<VisibilityEvent>,
+    changed: Query<
+        (),
+        Or<(
+            Changed<GlobalTransform>,
+            Changed<Camera>,
+            Changed<Handle<Mesh>>,
+        )>,
+    >,
+    // despawned entities remove their components too
+    removed_transforms: RemovedComponents<GlobalTransform>,
+    removed_cameras: RemovedComponents<Camera>,
+    removed_meshes: RemovedComponents<Handle<Mesh>>,
+) {
+    // every reader is drained, so events of earlier frames don't request this one
+    let events = request_render_events.iter().count()
+        + mesh_events.iter().count()
+        + texture_events.iter().count()
+        + window_created_events.iter().count()
+        + window_resized_events.iter().count()
+        + scale_factor_changed_events.iter().count()
+        + surface_created_events.iter().count()
+        + visibility_events.iter().count();
+    let requested = match *render_mode {
+        RenderMode::Continuous => true,
+        RenderMode::OnDemand => {
+            render_mode.is_changed()
+                || events > 0
+                || changed.iter().next().is_some()
+                || removed_transforms.iter().next().is_some()
+                || removed_cameras.iter().next().is_some()
+                || removed_meshes.iter().next().is_some()
+        }
+    };
+    if render_requested.0 != requested {
+        render_requested.0 = requested;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_mode_system, RenderMode, RenderRequested, RequestRender};
+    use crate::{camera::Camera, mesh::Mesh, texture::Texture, view::VisibilityEvent};
+    use bevy_asset::{AssetEvent, Handle};
+    use bevy_ecs::prelude::*;
+    use bevy_transform::components::GlobalTransform;
+    use bevy_window::{
+        WindowCreated, WindowResized, WindowScaleFactorChanged, WindowSurfaceCreated,
+    };
+
+    #[test]
+    fn on_demand_renders_only_changed_frames() {
+        let mut world = World::default();
+        world.insert_resource(RenderMode::OnDemand);
+        world.insert_resource(RenderRequested::default());
+        world.insert_resource(Events::<RequestRender>::default());
+        world.insert_resource(Events::<AssetEvent<Mesh>>::default());
+        world.insert_resource(Events::<AssetEvent<Texture>>::default());
+        world.insert_resource(Events::<WindowCreated>::default());
+        world.insert_resource(Events::<WindowResized>::default());
+        world.insert_resource(Events::<WindowScaleFactorChanged>::default());
+        world.insert_resource(Events::<WindowSurfaceCreated>::default());
+        world.insert_resource(Events::<VisibilityEvent>::default());
+        let entity = world.spawn().insert(GlobalTransform::default()).id();
+        let mut stage = SystemStage::single(render_mode_system.system());
+        let requested = |world: &World| world.get_resource::<RenderRequested>().unwrap().0;
+
+        // the first frame shows the new entity
+        stage.run(&mut world);
+        assert!(requested(&world));
+        stage.run(&mut world);
+        assert!(!requested(&world));
+
+        world
+            .get_mut::<GlobalTransform>(entity)
+            .unwrap()
+            .translation
+            .x = 1.0;
+        stage.run(&mut world);
+        assert!(requested(&world));
+
+        world
+            .get_resource_mut::<Events<RequestRender>>()
+            .unwrap()
+            .send(RequestRender);
+        stage.run(&mut world);
+        assert!(requested(&world));
+        stage.run(&mut world);
+        assert!(!requested(&world));
+
+        world.spawn().insert(Camera::default());
+        stage.run(&mut world);
+        assert!(requested(&world));
+
+        world.entity_mut(entity).insert(Handle::<Mesh>::default());
+        stage.run(&mut world);
+        assert!(requested(&world));
+        stage.run(&mut world);
+        assert!(!requested(&world));
+
+        // removals are only reported until the trackers are cleared at the end of the frame
+        world.entity_mut(entity).remove::<Handle<Mesh>>();
+        stage.run(&mut world);
+        assert!(requested(&world));
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert!(!requested(&world));
+
+        world.despawn(entity);
+        stage.run(&mut world);
+        assert!(requested(&world));
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert!(!requested(&world));
+
+        *world.get_resource_mut::<RenderMode>().unwrap() = RenderMode::Continuous;
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert!(requested(&world));
+    }
+}
@@ -17,7 +17,7 @@ pub trait Draw: Send + Sync + 'static {
     );
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct DrawFunctionId(usize);
 
 #[derive(Default)]
@@ -1,4 +1,4 @@
-use bevy_utils::tracing::debug;
+use bevy_utils::tracing::{debug, warn};
 
 use crate::{
     pass::RenderPass,
@@ -7,6 +7,55 @@ use crate::{
 };
 use std::ops::Range;
 
+/// A rectangle in physical pixels of the render target, with its origin at the top left corner.
+/// Draws outside of it are discarded by the scissor test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClipRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ClipRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        ClipRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Shrinks the rectangle to fit into a render target of the given size.
+    pub fn clamp_to_target(&self, target_width: u32, target_height: u32) -> ClipRect {
+        let x = self.x.min(target_width);
+        let y = self.y.min(target_height);
+        ClipRect {
+            x,
+            y,
+            width: self.width.min(target_width - x),
+            height: self.height.min(target_height - y),
+        }
+    }
+
+    /// Scales the rectangle, e.g. from the physical pixels of a window to the pixels it is
+    /// rendered at.
+    pub fn scale(&self, scale: f32) -> ClipRect {
+        let scale = |value: u32| (value as f32 * scale).round() as u32;
+        ClipRect {
+            x: scale(self.x),
+            y: scale(self.y),
+            width: scale(self.width),
+            height: scale(self.height),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
 /// Tracks the current pipeline state to ensure draw calls are valid.
 #[derive(Debug, Default)]
 pub struct DrawState {
@@ -14,6 +63,7 @@ pub struct DrawState {
     bind_groups: Vec<(Option<BindGroupId>, Vec<u32>)>,
     vertex_buffers: Vec<Option<(BufferId, u64)>>,
     index_buffer: Option<(BufferId, u64, IndexFormat)>,
+    scissor: Option<ClipRect>,
 }
 
 impl DrawState {
@@ -78,6 +128,14 @@ impl DrawState {
         self.index_buffer == Some((buffer, offset, index_format))
     }
 
+    pub fn set_scissor_rect(&mut self, rect: Option<ClipRect>) {
+        self.scissor = rect;
+    }
+
+    pub fn is_scissor_rect_set(&self, rect: Option<ClipRect>) -> bool {
+        self.scissor == rect
+    }
+
     pub fn is_pipeline_set(&self, pipeline: PipelineId) -> bool {
         self.pipeline == Some(pipeline)
     }
@@ -94,6 +152,7 @@ impl DrawState {
 pub struct TrackedRenderPass<'a> {
     pass: &'a mut dyn RenderPass,
     state: DrawState,
+    target_size: Option<(u32, u32)>,
 }
 
 impl<'a> TrackedRenderPass<'a> {
@@ -101,8 +160,64 @@ impl<'a> TrackedRenderPass<'a> {
         Self {
             state: DrawState::default(),
             pass,
+            target_size: None,
         }
     }
+
+    /// Sets the size of the pass attachments, which is needed to clamp and reset clip rectangles
+    /// in [`TrackedRenderPass::set_clip_rect`].
+    pub fn with_target_size(mut self, width: u32, height: u32) -> Self {
+        self.target_size = Some((width, height));
+        self
+    }
+
+    /// Maps normalized device coordinates of the following draws to `viewport` instead of the
+    /// whole target.
+    pub fn set_viewport(&mut self, viewport: ClipRect) {
+        debug!("set viewport: {:?}", viewport);
+        self.pass.set_viewport(
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            0.0,
+            1.0,
+        );
+    }
+
+    /// Restricts the following draws to `clip`, or removes the restriction if it is `None`.
+    /// Returns `false` if the clip rectangle is empty, in which case the draw can be skipped.
+    pub fn set_clip_rect(&mut self, clip: Option<ClipRect>) -> bool {
+        let rect = match (clip, self.target_size) {
+            (Some(clip), Some((width, height))) => Some(clip.clamp_to_target(width, height)),
+            (clip, _) => clip,
+        };
+        if let Some(rect) = rect {
+            if rect.is_empty() {
+                return false;
+            }
+        }
+        if self.state.is_scissor_rect_set(rect) {
+            return true;
+        }
+        match (rect, self.target_size) {
+            (Some(rect), _) => {
+                debug!("set scissor rect: {:?}", rect);
+                self.pass
+                    .set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+            }
+            (None, Some((width, height))) => {
+                debug!("reset scissor rect");
+                self.pass.set_scissor_rect(0, 0, width, height);
+            }
+            (None, None) => {
+                warn!("cannot reset the scissor rect of a pass without a known target size");
+                return true;
+            }
+        }
+        self.state.set_scissor_rect(rect);
+        true
+    }
     pub fn set_pipeline(&mut self, pipeline: PipelineId) {
         debug!("set pipeline: {:?}", pipeline);
         if self.state.is_pipeline_set(pipeline) {
@@ -169,6 +284,11 @@ impl<'a> TrackedRenderPass<'a> {
         self.state.set_index_buffer(buffer, offset, index_format);
     }
 
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        debug!("draw: {:?} {:?}", vertices, instances);
+        self.pass.draw(vertices, instances);
+    }
+
     pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
         debug!(
             "draw indexed: {:?} {} {:?}",
@@ -4,14 +4,39 @@ mod draw_state;
 pub use draw::*;
 pub use draw_state::*;
 
+use crate::RenderStage;
+use bevy_app::App;
+use bevy_ecs::prelude::*;
 use std::marker::PhantomData;
-use bevy_ecs::prelude::Query;
 
 // TODO: make this configurable per phase?
 pub struct Drawable {
     pub draw_function: DrawFunctionId,
     pub draw_key: usize,
     pub sort_key: usize,
+    /// The app world entity this drawable was queued for.
+    pub entity: Entity,
+    /// Restricts the draw to a rectangle of the render target, e.g. the viewport of a scrollable
+    /// UI container. Applied by the core 2d and 3d main passes.
+    pub clip: Option<ClipRect>,
+}
+
+/// How [`sort_phase_system`] orders drawables that have the same sort key. Insert this resource in
+/// the app world; it is extracted to the render world every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawOrder {
+    /// Drawables with the same sort key are drawn in the order they were queued, which depends on
+    /// query and hash map iteration order.
+    Queued,
+    /// Drawables are sorted by sort key, then draw function, then entity, then draw key, so
+    /// identical scenes produce identical command streams. Useful for image comparison tests.
+    Deterministic,
+}
+
+impl Default for DrawOrder {
+    fn default() -> Self {
+        DrawOrder::Queued
+    }
 }
 
 pub struct RenderPhase<T> {
@@ -37,11 +62,254 @@ impl<T> RenderPhase<T> {
     pub fn sort(&mut self) {
         self.drawn_things.sort_by_key(|d| d.sort_key);
     }
+
+    /// Sorts the drawables by sort key and breaks ties by draw function, entity and draw key, see
+    /// [`DrawOrder::Deterministic`].
+    pub fn sort_deterministic(&mut self) {
+        self.drawn_things
+            .sort_by_key(|d| (d.sort_key, d.draw_function, d.entity, d.draw_key));
+    }
+}
+
+pub fn extract_draw_order(mut commands: Commands, draw_order: Res<DrawOrder>) {
+    commands.insert_resource(*draw_order);
+}
+
+/// A sort of the drawables of the phase `T` that replaces sorting by sort key, added with
+/// [`AddRenderPhase::add_render_phase_with_sort`]. With [`DrawOrder::Deterministic`], the
+/// drawables are sorted deterministically first, so the function should be a stable sort, like
+/// `sort_by_key`, to keep breaking ties deterministically.
+pub struct PhaseSortFunction<T> {
+    sort: fn(&mut [Drawable]),
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PhaseSortFunction<T> {
+    pub fn new(sort: fn(&mut [Drawable])) -> Self {
+        PhaseSortFunction {
+            sort,
+            marker: PhantomData,
+        }
+    }
+}
+
+pub fn sort_phase_system<T: 'static>(
+    draw_order: Res<DrawOrder>,
+    sort_function: Option<Res<PhaseSortFunction<T>>>,
+    mut render_phases: Query<&mut RenderPhase<T>>,
+) {
+    for mut phase in render_phases.iter_mut() {
+        match (*draw_order, sort_function.as_ref()) {
+            (DrawOrder::Queued, None) => phase.sort(),
+            (DrawOrder::Queued, Some(sort_function)) => {
+                (sort_function.sort)(&mut phase.drawn_things)
+            }
+            (DrawOrder::Deterministic, sort_function) => {
+                phase.sort_deterministic();
+                if let Some(sort_function) = sort_function {
+                    (sort_function.sort)(&mut phase.drawn_things);
+                }
+            }
+        }
+    }
+}
+
+/// [`App`] extension methods to add render phases to the render sub-app.
+///
+/// A phase is a marker type `T`: views get a [`RenderPhase<T>`] component, usually in
+/// [`RenderStage::Extract`], drawables are added to it in [`RenderStage::Queue`], and a render
+/// graph node draws them. Adding the phase sorts it in [`RenderStage::PhaseSort`] every frame.
+///
+/// To draw in the main passes, add drawables to their phases instead, e.g.
+/// [`Transparent2dPhase`](crate::core_pipeline::Transparent2dPhase), whose sort keys interleave
+/// the drawables of every plugin, see [`ZIndex`](crate::core_pipeline::ZIndex).
+pub trait AddRenderPhase {
+    /// Adds the phase `T`, whose drawables are sorted by sort key.
+    fn add_render_phase<T: 'static>(&mut self) -> &mut Self;
+
+    /// Adds the phase `T`, whose drawables are sorted with `sort`, see [`PhaseSortFunction`].
+    fn add_render_phase_with_sort<T: 'static>(&mut self, sort: fn(&mut [Drawable])) -> &mut Self;
+}
+
+impl AddRenderPhase for App {
+    fn add_render_phase<T: 'static>(&mut self) -> &mut Self {
+        self.add_system_to_stage(RenderStage::PhaseSort, sort_phase_system::<T>.system())
+    }
+
+    fn add_render_phase_with_sort<T: 'static>(&mut self, sort: fn(&mut [Drawable])) -> &mut Self {
+        self.insert_resource(PhaseSortFunction::<T>::new(sort))
+            .add_render_phase::<T>()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{
+        sort_phase_system, AddRenderPhase, ClipRect, Draw, DrawFunctionId, DrawFunctionsInternal,
+        DrawOrder, Drawable, PhaseSortFunction, RenderPhase, TrackedRenderPass,
+    };
+    use crate::RenderStage;
+    use bevy_app::App;
+    use bevy_ecs::{entity::Entity, prelude::*, world::World};
 
-pub fn sort_phase_system<T: 'static>(mut render_phases: Query<&mut RenderPhase<T>>) {
-   for mut phase in render_phases.iter_mut() {
-       phase.sort();
-   } 
-}
\ No newline at end of file
+    struct TestDraw;
+
+    impl Draw for TestDraw {
+        fn draw(&mut self, _: &World, _: &mut TrackedRenderPass, _: Entity, _: usize, _: usize) {}
+    }
+
+    #[test]
+    fn deterministic_sort_ignores_queue_order() {
+        let draw_function = DrawFunctionsInternal::default().add(TestDraw);
+        let drawable = |sort_key, entity| Drawable {
+            draw_function,
+            draw_key: 0,
+            sort_key,
+            entity: Entity::new(entity),
+            clip: None,
+        };
+        let mut first = RenderPhase::<()>::default();
+        let mut second = RenderPhase::<()>::default();
+        for (sort_key, entity) in [(1, 2), (0, 3), (1, 1)].iter() {
+            first.add(drawable(*sort_key, *entity));
+        }
+        for (sort_key, entity) in [(1, 1), (1, 2), (0, 3)].iter() {
+            second.add(drawable(*sort_key, *entity));
+        }
+        first.sort_deterministic();
+        second.sort_deterministic();
+
+        let order = |phase: &RenderPhase<()>| {
+            phase
+                .drawn_things
+                .iter()
+                .map(|d| d.entity.id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(&first), vec![3, 1, 2]);
+        assert_eq!(order(&first), order(&second));
+    }
+
+    #[test]
+    fn phase_sort_function_replaces_sort_key_order() {
+        let draw_function = DrawFunctionsInternal::default().add(TestDraw);
+        let mut phase = RenderPhase::<()>::default();
+        for (sort_key, entity) in [(0, 1), (2, 3), (1, 2), (2, 4)].iter() {
+            phase.add(Drawable {
+                draw_function,
+                draw_key: 0,
+                sort_key: *sort_key,
+                entity: Entity::new(*entity),
+                clip: None,
+            });
+        }
+        let mut world = World::new();
+        world.insert_resource(DrawOrder::Deterministic);
+        world.insert_resource(PhaseSortFunction::<()>::new(|drawables| {
+            drawables.sort_by_key(|d| std::cmp::Reverse(d.sort_key))
+        }));
+        let view = world.spawn().insert(phase).id();
+
+        let mut system = sort_phase_system::<()>.system();
+        system.initialize(&mut world);
+        system.run((), &mut world);
+
+        let phase = world.get::<RenderPhase<()>>(view).unwrap();
+        let order = phase
+            .drawn_things
+            .iter()
+            .map(|d| d.entity.id())
+            .collect::<Vec<_>>();
+        // ties keep their deterministic order
+        assert_eq!(order, vec![3, 4, 2, 1]);
+    }
+
+    struct Test2dPhase;
+    struct Test3dPhase;
+
+    struct TestDrawFunction(DrawFunctionId);
+
+    /// The entities of the drawables of each phase, in the order they are drawn.
+    #[derive(Default)]
+    struct DrawnOrder {
+        phase_2d: Vec<u32>,
+        phase_3d: Vec<u32>,
+    }
+
+    fn queue_unsorted(
+        draw_function: Res<TestDrawFunction>,
+        mut views: Query<(&mut RenderPhase<Test2dPhase>, &mut RenderPhase<Test3dPhase>)>,
+    ) {
+        for (mut phase_2d, mut phase_3d) in views.iter_mut() {
+            for (sort_key, entity) in [(1, 2), (2, 3), (0, 1)].iter() {
+                let drawable = || Drawable {
+                    draw_function: draw_function.0,
+                    draw_key: 0,
+                    sort_key: *sort_key,
+                    entity: Entity::new(*entity),
+                    clip: None,
+                };
+                phase_2d.add(drawable());
+                phase_3d.add(drawable());
+            }
+        }
+    }
+
+    fn record_drawn_order(
+        mut drawn_order: ResMut<DrawnOrder>,
+        views: Query<(&RenderPhase<Test2dPhase>, &RenderPhase<Test3dPhase>)>,
+    ) {
+        let order = |drawables: &[Drawable]| drawables.iter().map(|d| d.entity.id()).collect();
+        for (phase_2d, phase_3d) in views.iter() {
+            drawn_order.phase_2d = order(&phase_2d.drawn_things);
+            drawn_order.phase_3d = order(&phase_3d.drawn_things);
+        }
+    }
+
+    #[test]
+    fn render_phases_are_sorted_between_queue_and_render() {
+        let mut app = App::empty();
+        app.add_stage(RenderStage::Queue, SystemStage::parallel())
+            .add_stage(RenderStage::PhaseSort, SystemStage::parallel())
+            .add_stage(RenderStage::Render, SystemStage::parallel())
+            .insert_resource(DrawOrder::Queued)
+            .insert_resource(TestDrawFunction(
+                DrawFunctionsInternal::default().add(TestDraw),
+            ))
+            .init_resource::<DrawnOrder>()
+            .add_render_phase::<Test2dPhase>()
+            .add_render_phase_with_sort::<Test3dPhase>(|drawables| {
+                drawables.sort_by_key(|d| std::cmp::Reverse(d.sort_key))
+            })
+            .add_system_to_stage(RenderStage::Queue, queue_unsorted.system())
+            .add_system_to_stage(RenderStage::Render, record_drawn_order.system());
+        app.world.spawn().insert_bundle((
+            RenderPhase::<Test2dPhase>::default(),
+            RenderPhase::<Test3dPhase>::default(),
+        ));
+
+        app.update();
+
+        // both phases are sorted after everything is queued and before they are drawn, by sort
+        // key or by the sort function of the phase
+        let drawn_order = app.world.get_resource::<DrawnOrder>().unwrap();
+        assert_eq!(drawn_order.phase_2d, vec![1, 2, 3]);
+        assert_eq!(drawn_order.phase_3d, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn clip_rect_is_clamped_to_target() {
+        let target = (800, 600);
+        assert_eq!(
+            ClipRect::new(10, 20, 100, 50).clamp_to_target(target.0, target.1),
+            ClipRect::new(10, 20, 100, 50)
+        );
+        assert_eq!(
+            ClipRect::new(700, 550, 200, 200).clamp_to_target(target.0, target.1),
+            ClipRect::new(700, 550, 100, 50)
+        );
+        assert!(ClipRect::new(900, 0, 10, 10)
+            .clamp_to_target(target.0, target.1)
+            .is_empty());
+    }
+}
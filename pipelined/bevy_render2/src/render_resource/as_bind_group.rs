@@ -0,0 +1,169 @@
+use crate::{
+    pipeline::{
+        BindGroupDescriptor, BindType, BindingDescriptor, BindingShaderStage, UniformProperty,
+    },
+    render_resource::{BindGroupId, BufferId, BufferInfo, BufferUsage},
+    renderer::RenderResources,
+    texture::{Texture, TextureSampleType, TextureViewDimension},
+};
+use bevy_asset::Assets;
+use thiserror::Error;
+
+/// Creates a bind group from the fields of a struct, typically a material. Usually derived:
+///
+/// ```ignore
+/// #[derive(AsBindGroup)]
+/// struct CustomMaterial {
+///     // uniform fields with the same binding are packed in one std140 buffer, in order
+///     #[uniform(0)]
+///     color: Vec4,
+///     #[uniform(0)]
+///     roughness: f32,
+///     // the texture and the sampler of an image
+///     #[texture(1)]
+///     #[sampler(2)]
+///     base_color_texture: Handle<Texture>,
+///     // unset optional textures are replaced by a placeholder, white by default
+///     #[texture(3, placeholder = "normal")]
+///     normal_map: Option<Handle<Texture>>,
+/// }
+/// ```
+///
+/// Uniform fields must implement `AsStd140`. Textures are 2d by default, other dimensions are
+/// set with `dimension = "2d_array"`, `"cube"` or `"3d"`. The placeholders are `"white"`,
+/// `"black"`, `"normal"` and `"transparent"`, see [`WHITE_TEXTURE_HANDLE`] and its siblings.
+///
+/// Pipelines drawing with the bind group must use [`AsBindGroup::bind_group_descriptor`] as the
+/// layout of its set, instead of the layout reflected from their shaders, since bind groups are
+/// created for a specific layout.
+///
+/// [`WHITE_TEXTURE_HANDLE`]: crate::texture::WHITE_TEXTURE_HANDLE
+pub trait AsBindGroup {
+    /// Returns the layout of the bind group when it is bound to the set `index`.
+    fn bind_group_descriptor(index: u32) -> BindGroupDescriptor;
+
+    /// Creates the bind group for the layout returned by [`AsBindGroup::bind_group_descriptor`],
+    /// along with a buffer for each uniform binding.
+    fn as_bind_group(
+        &self,
+        descriptor: &BindGroupDescriptor,
+        render_resources: &RenderResources,
+        textures: &Assets<Texture>,
+    ) -> Result<PreparedBindGroup, AsBindGroupError>;
+}
+
+/// The textures looked up by [`AsBindGroup::as_bind_group`], named for derived implementations.
+pub type AsBindGroupTextures = Assets<Texture>;
+
+/// A bind group created by [`AsBindGroup::as_bind_group`].
+#[derive(Debug, Clone)]
+pub struct PreparedBindGroup {
+    pub bind_group: BindGroupId,
+    /// The uniform buffers of the bind group, which should be removed once it isn't used anymore.
+    pub buffers: Vec<BufferId>,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsBindGroupError {
+    /// A texture of the bind group isn't loaded or uploaded yet.
+    #[error("a texture of the bind group isn't ready, retry in a later frame")]
+    RetryNextFrame,
+}
+
+/// The visibility of the bindings of derived bind groups.
+fn shader_stage() -> BindingShaderStage {
+    BindingShaderStage::VERTEX | BindingShaderStage::FRAGMENT
+}
+
+/// Rounds `size` up to the size of a std140 uniform block, a multiple of 16 bytes.
+fn uniform_block_size(size: usize) -> usize {
+    (size + 15) / 16 * 16
+}
+
+/// Returns the descriptor of a uniform block of `size` bytes, as written by a
+/// `crevice::std140::Writer`.
+pub fn uniform_binding_descriptor(name: &str, index: u32, size: usize) -> BindingDescriptor {
+    BindingDescriptor {
+        name: name.to_string(),
+        index,
+        bind_type: BindType::Uniform {
+            has_dynamic_offset: false,
+            // only the size of the property matters to the layout
+            property: UniformProperty::Array(
+                Box::new(UniformProperty::UVec4),
+                uniform_block_size(size) / 16,
+            ),
+        },
+        shader_stage: shader_stage(),
+        count: None,
+    }
+}
+
+/// Returns the descriptor of a filterable float texture.
+pub fn texture_binding_descriptor(
+    name: &str,
+    index: u32,
+    view_dimension: TextureViewDimension,
+) -> BindingDescriptor {
+    BindingDescriptor {
+        name: name.to_string(),
+        index,
+        bind_type: BindType::Texture {
+            multisampled: false,
+            view_dimension,
+            sample_type: TextureSampleType::Float { filterable: true },
+        },
+        shader_stage: shader_stage(),
+        count: None,
+    }
+}
+
+/// Returns the descriptor of a filtering sampler.
+pub fn sampler_binding_descriptor(name: &str, index: u32) -> BindingDescriptor {
+    BindingDescriptor {
+        name: name.to_string(),
+        index,
+        bind_type: BindType::Sampler {
+            filtering: true,
+            comparison: false,
+        },
+        shader_stage: shader_stage(),
+        count: None,
+    }
+}
+
+/// Creates a uniform buffer from the std140 `data` of a uniform block, padded to the size of the
+/// block. Returns the buffer and its size.
+pub fn create_uniform_buffer(
+    render_resources: &RenderResources,
+    mut data: Vec<u8>,
+) -> (BufferId, u64) {
+    data.resize(uniform_block_size(data.len()), 0);
+    let buffer = render_resources.create_buffer_with_data(
+        BufferInfo {
+            size: data.len(),
+            buffer_usage: BufferUsage::UNIFORM,
+            ..Default::default()
+        },
+        &data,
+    );
+    (buffer, data.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uniform_binding_descriptor;
+
+    #[test]
+    fn uniform_blocks_are_padded() {
+        let size = |size| {
+            uniform_binding_descriptor("Block", 0, size)
+                .bind_type
+                .get_uniform_size()
+                .unwrap()
+        };
+        assert_eq!(size(4), 16);
+        assert_eq!(size(16), 16);
+        assert_eq!(size(20), 32);
+    }
+}
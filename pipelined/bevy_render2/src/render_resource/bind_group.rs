@@ -1,5 +1,8 @@
-use crate::render_resource::{
-    BufferId, RenderResourceBinding, RenderResourceId, SamplerId, TextureViewId,
+use crate::{
+    pipeline::{BindGroupDescriptor, BindType},
+    render_resource::{
+        BufferId, RenderResourceBinding, RenderResourceId, SamplerId, TextureViewId,
+    },
 };
 use bevy_utils::AHasher;
 use std::{
@@ -7,6 +10,7 @@ use std::{
     ops::Range,
     sync::Arc,
 };
+use thiserror::Error;
 
 #[derive(Hash, Eq, PartialEq, Debug, Copy, Clone)]
 pub struct BindGroupId(pub u64);
@@ -21,21 +25,78 @@ pub struct IndexedBindGroupEntry {
 pub struct BindGroup {
     pub id: BindGroupId,
     pub indexed_bindings: Arc<[IndexedBindGroupEntry]>,
+    /// The indices of the bindings with a dynamic offset, in the order their offsets are passed
+    /// when the bind group is set. Only known for bind groups built with
+    /// [`BindGroupBuilder::new`], it is empty otherwise.
+    pub dynamic_bindings: Arc<[u32]>,
 }
 
 impl BindGroup {
-    pub fn build() -> BindGroupBuilder {
+    pub fn build() -> BindGroupBuilder<'static> {
         BindGroupBuilder::default()
     }
+
+    /// The number of dynamic offsets to pass when the bind group is set, see
+    /// [`BindGroup::dynamic_bindings`].
+    pub fn dynamic_offset_count(&self) -> usize {
+        self.dynamic_bindings.len()
+    }
 }
 
+/// Builds a [`BindGroup`]. Builders created with [`BindGroupBuilder::new`] check the bindings
+/// against the layout of the bind group when it is built:
+///
+/// ```ignore
+/// let bind_group = BindGroupBuilder::new(layout.bind_group(1))
+///     .buffer(0, uniforms.binding(chunk))
+///     .texture(1, texture_view)
+///     .sampler(2, sampler)
+///     .build()?;
+/// ```
+///
+/// Builders created with `BindGroupBuilder::default()` aren't checked, and are finished with
+/// [`BindGroupBuilder::finish`].
 #[derive(Debug, Default)]
-pub struct BindGroupBuilder {
+pub struct BindGroupBuilder<'a> {
     pub indexed_bindings: Vec<IndexedBindGroupEntry>,
     pub hasher: AHasher,
+    layout: Option<&'a BindGroupDescriptor>,
 }
 
-impl BindGroupBuilder {
+impl<'a> BindGroupBuilder<'a> {
+    /// Creates a builder for a bind group with the `layout`, see [`BindGroupBuilder::build`].
+    pub fn new(layout: &'a BindGroupDescriptor) -> Self {
+        BindGroupBuilder {
+            layout: Some(layout),
+            ..Default::default()
+        }
+    }
+
+    /// Binds a range of a buffer to a uniform or storage buffer binding.
+    pub fn buffer(self, index: u32, binding: RenderResourceBinding) -> Self {
+        debug_assert!(
+            binding.get_buffer().is_some(),
+            "{:?} isn't a buffer",
+            binding
+        );
+        self.add_binding(index, binding)
+    }
+
+    /// Binds a texture view to a texture or storage texture binding.
+    pub fn texture(self, index: u32, texture: TextureViewId) -> Self {
+        self.add_texture_view(index, texture)
+    }
+
+    /// Binds texture views to a binding of an array of textures.
+    pub fn texture_array(self, index: u32, textures: Vec<TextureViewId>) -> Self {
+        self.add_binding(index, RenderResourceBinding::TextureArrayView(textures))
+    }
+
+    /// Binds a sampler to a sampler binding.
+    pub fn sampler(self, index: u32, sampler: SamplerId) -> Self {
+        self.add_sampler(index, sampler)
+    }
+
     pub fn add_binding<T: Into<RenderResourceBinding>>(mut self, index: u32, binding: T) -> Self {
         let binding = binding.into();
         self.hash_binding(&binding);
@@ -70,9 +131,92 @@ impl BindGroupBuilder {
         BindGroup {
             id: BindGroupId(self.hasher.finish()),
             indexed_bindings: self.indexed_bindings.into(),
+            dynamic_bindings: Arc::new([]),
         }
     }
 
+    /// Finishes the bind group, after checking that each binding of the layout is bound once, to
+    /// a resource of its type. Without a layout, this is [`BindGroupBuilder::finish`].
+    pub fn build(self) -> Result<BindGroup, BindGroupError> {
+        let layout = match self.layout {
+            Some(layout) => layout,
+            None => return Ok(self.finish()),
+        };
+        for (i, entry) in self.indexed_bindings.iter().enumerate() {
+            if self.indexed_bindings[..i]
+                .iter()
+                .any(|other| other.index == entry.index)
+            {
+                return Err(BindGroupError::DuplicateBinding(entry.index));
+            }
+            let descriptor = layout
+                .bindings
+                .iter()
+                .find(|descriptor| descriptor.index == entry.index)
+                .ok_or(BindGroupError::UnknownBinding(entry.index))?;
+            let matches = match (&entry.entry, &descriptor.bind_type) {
+                (RenderResourceBinding::Buffer { range, .. }, bind_type) => {
+                    if let Some(required) = bind_type.get_uniform_size() {
+                        let size = range.end.saturating_sub(range.start);
+                        if size < required {
+                            return Err(BindGroupError::BufferTooSmall {
+                                index: entry.index,
+                                size,
+                                required,
+                            });
+                        }
+                    }
+                    matches!(
+                        bind_type,
+                        BindType::Uniform { .. } | BindType::StorageBuffer { .. }
+                    )
+                }
+                (RenderResourceBinding::TextureView(_), BindType::Texture { .. })
+                | (RenderResourceBinding::TextureView(_), BindType::StorageTexture { .. }) => {
+                    descriptor.count.is_none()
+                }
+                (RenderResourceBinding::TextureArrayView(textures), BindType::Texture { .. }) => {
+                    descriptor
+                        .count
+                        .map_or(false, |count| count.get() as usize == textures.len())
+                }
+                (RenderResourceBinding::Sampler(_), BindType::Sampler { .. }) => true,
+                _ => false,
+            };
+            if !matches {
+                return Err(BindGroupError::WrongBindingType(entry.index));
+            }
+        }
+        if let Some(missing) = layout.bindings.iter().find(|descriptor| {
+            !self
+                .indexed_bindings
+                .iter()
+                .any(|entry| entry.index == descriptor.index)
+        }) {
+            return Err(BindGroupError::MissingBinding(missing.index));
+        }
+
+        let mut dynamic_bindings = layout
+            .bindings
+            .iter()
+            .filter(|descriptor| match descriptor.bind_type {
+                BindType::Uniform {
+                    has_dynamic_offset, ..
+                }
+                | BindType::StorageBuffer {
+                    has_dynamic_offset, ..
+                } => has_dynamic_offset,
+                _ => false,
+            })
+            .map(|descriptor| descriptor.index)
+            .collect::<Vec<_>>();
+        // dynamic offsets are passed in the order of their bindings
+        dynamic_bindings.sort_unstable();
+        let mut bind_group = self.finish();
+        bind_group.dynamic_bindings = dynamic_bindings.into();
+        Ok(bind_group)
+    }
+
     fn hash_binding(&mut self, binding: &RenderResourceBinding) {
         match binding {
             RenderResourceBinding::Buffer {
@@ -96,3 +240,101 @@ impl BindGroupBuilder {
         }
     }
 }
+
+/// An error of [`BindGroupBuilder::build`], with the index of the binding it is about.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindGroupError {
+    #[error("binding {0} isn't in the layout of the bind group")]
+    UnknownBinding(u32),
+    #[error("binding {0} of the layout isn't bound")]
+    MissingBinding(u32),
+    #[error("binding {0} is bound more than once")]
+    DuplicateBinding(u32),
+    #[error("binding {0} is bound to a resource of another type than its layout")]
+    WrongBindingType(u32),
+    #[error("binding {index} is bound to {size} bytes, but its layout needs {required}")]
+    BufferTooSmall {
+        index: u32,
+        size: u64,
+        required: u64,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindGroupBuilder, BindGroupError};
+    use crate::{
+        pipeline::{BindGroupDescriptor, BindType},
+        render_resource::{
+            sampler_binding_descriptor, texture_binding_descriptor, uniform_binding_descriptor,
+            BufferId, RenderResourceBinding, SamplerId, TextureViewId,
+        },
+        texture::TextureViewDimension,
+    };
+
+    #[test]
+    fn build_checks_layout() {
+        let mut uniform = uniform_binding_descriptor("Uniform", 0, 16);
+        if let BindType::Uniform {
+            has_dynamic_offset, ..
+        } = &mut uniform.bind_type
+        {
+            *has_dynamic_offset = true;
+        }
+        let layout = BindGroupDescriptor::new(
+            1,
+            vec![
+                uniform,
+                texture_binding_descriptor("Texture", 1, TextureViewDimension::D2),
+                sampler_binding_descriptor("Sampler", 2),
+            ],
+        );
+        let buffer = |size| RenderResourceBinding::Buffer {
+            buffer: BufferId::new(),
+            range: 0..size,
+        };
+        let texture = TextureViewId::new();
+        let sampler = SamplerId::new();
+
+        let bind_group = BindGroupBuilder::new(&layout)
+            .sampler(2, sampler)
+            .buffer(0, buffer(16))
+            .texture(1, texture)
+            .build()
+            .unwrap();
+        assert_eq!(&bind_group.dynamic_bindings[..], &[0]);
+        assert_eq!(bind_group.indexed_bindings[2].index, 2);
+
+        assert_eq!(
+            BindGroupBuilder::new(&layout)
+                .buffer(0, buffer(16))
+                .texture(1, texture)
+                .build(),
+            Err(BindGroupError::MissingBinding(2))
+        );
+        assert_eq!(
+            BindGroupBuilder::new(&layout)
+                .buffer(0, buffer(16))
+                .texture(1, texture)
+                .texture(2, texture)
+                .build(),
+            Err(BindGroupError::WrongBindingType(2))
+        );
+        assert_eq!(
+            BindGroupBuilder::new(&layout)
+                .buffer(0, buffer(8))
+                .texture(1, texture)
+                .sampler(2, sampler)
+                .build(),
+            Err(BindGroupError::BufferTooSmall {
+                index: 0,
+                size: 8,
+                required: 16
+            })
+        );
+        assert_eq!(
+            BindGroupBuilder::new(&layout).sampler(3, sampler).build(),
+            Err(BindGroupError::UnknownBinding(3))
+        );
+    }
+}
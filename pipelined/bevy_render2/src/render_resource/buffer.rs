@@ -1,4 +1,5 @@
 use bevy_utils::Uuid;
+use thiserror::Error;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub struct BufferId(Uuid);
@@ -10,6 +11,15 @@ impl BufferId {
     }
 }
 
+/// A range of a staging buffer written through
+/// [`RenderResourceContext::write_staging_belt`](crate::renderer::RenderResourceContext::write_staging_belt).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StagingSlice {
+    pub buffer: BufferId,
+    pub offset: u64,
+    pub size: u64,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct BufferInfo {
     pub size: usize,
@@ -27,6 +37,49 @@ impl Default for BufferInfo {
     }
 }
 
+impl BufferInfo {
+    /// Checks the usage combinations and sizes that are invalid on every backend.
+    ///
+    /// Buffers that can be mapped may only be used for copies, unless the backend supports
+    /// `mappable_primary_buffers`, which is a native-only feature.
+    pub fn validate(&self, mappable_primary_buffers: bool) -> Result<(), BufferInfoError> {
+        let usage = self.buffer_usage;
+        if usage.is_empty() {
+            return Err(BufferInfoError::EmptyUsage);
+        }
+        if !mappable_primary_buffers {
+            if usage.contains(BufferUsage::MAP_READ)
+                && !(BufferUsage::MAP_READ | BufferUsage::COPY_DST).contains(usage)
+            {
+                return Err(BufferInfoError::InvalidMapReadUsage(usage));
+            }
+            if usage.contains(BufferUsage::MAP_WRITE)
+                && !(BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC).contains(usage)
+            {
+                return Err(BufferInfoError::InvalidMapWriteUsage(usage));
+            }
+        }
+        if self.mapped_at_creation && self.size % MAPPED_AT_CREATION_ALIGNMENT != 0 {
+            return Err(BufferInfoError::UnalignedMappedAtCreation(self.size));
+        }
+        Ok(())
+    }
+}
+
+const MAPPED_AT_CREATION_ALIGNMENT: usize = 4;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BufferInfoError {
+    #[error("buffers need at least one usage")]
+    EmptyUsage,
+    #[error("MAP_READ can only be combined with COPY_DST, but the usage is {0:?}")]
+    InvalidMapReadUsage(BufferUsage),
+    #[error("MAP_WRITE can only be combined with COPY_SRC, but the usage is {0:?}")]
+    InvalidMapWriteUsage(BufferUsage),
+    #[error("buffers mapped at creation need a size that is a multiple of 4, not {0}")]
+    UnalignedMappedAtCreation(usize),
+}
+
 bitflags::bitflags! {
     #[repr(transparent)]
     #[cfg_attr(feature = "trace", derive(Serialize))]
@@ -40,7 +93,14 @@ bitflags::bitflags! {
         const VERTEX = 32;
         const UNIFORM = 64;
         const STORAGE = 128;
+        /// Allows the buffer to hold the arguments of indirect draws and dispatches.
         const INDIRECT = 256;
+        /// Allows resolving query sets, such as timestamp queries, into the buffer.
+        const QUERY_RESOLVE = 512;
+        /// A staging buffer the GPU copies into, to read results back on the CPU.
+        const READBACK = Self::MAP_READ.bits | Self::COPY_DST.bits;
+        /// A staging buffer written on the CPU, to upload its data with a copy.
+        const UPLOAD = Self::MAP_WRITE.bits | Self::COPY_SRC.bits;
     }
 }
 
@@ -49,3 +109,41 @@ pub enum BufferMapMode {
     Read,
     Write,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BufferInfo, BufferInfoError, BufferUsage};
+
+    #[test]
+    fn validate() {
+        let info = |buffer_usage: BufferUsage| BufferInfo {
+            size: 16,
+            buffer_usage,
+            mapped_at_creation: false,
+        };
+        assert_eq!(info(BufferUsage::READBACK).validate(false), Ok(()));
+        assert_eq!(
+            info(BufferUsage::INDIRECT | BufferUsage::STORAGE).validate(false),
+            Ok(())
+        );
+        assert_eq!(
+            info(BufferUsage::empty()).validate(false),
+            Err(BufferInfoError::EmptyUsage)
+        );
+        let usage = BufferUsage::MAP_READ | BufferUsage::QUERY_RESOLVE;
+        assert_eq!(
+            info(usage).validate(false),
+            Err(BufferInfoError::InvalidMapReadUsage(usage))
+        );
+        assert_eq!(info(usage).validate(true), Ok(()));
+        assert_eq!(
+            BufferInfo {
+                size: 6,
+                mapped_at_creation: true,
+                ..info(BufferUsage::UPLOAD)
+            }
+            .validate(false),
+            Err(BufferInfoError::UnalignedMappedAtCreation(6))
+        );
+    }
+}
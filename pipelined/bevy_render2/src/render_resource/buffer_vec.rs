@@ -1,12 +1,12 @@
 use crate::{
-    render_resource::{BufferId, BufferInfo, BufferMapMode, BufferUsage},
+    render_resource::{BufferId, BufferInfo, BufferUsage, StagingSlice},
     renderer::{RenderContext, RenderResources},
 };
 use bevy_core::{cast_slice, Pod};
 
 pub struct BufferVec<T: Pod> {
     values: Vec<T>,
-    staging_buffer: Option<BufferId>,
+    staging_slice: Option<StagingSlice>,
     buffer: Option<BufferId>,
     capacity: usize,
     item_size: usize,
@@ -17,7 +17,7 @@ impl<T: Pod> Default for BufferVec<T> {
     fn default() -> Self {
         Self {
             values: Vec::new(),
-            staging_buffer: None,
+            staging_slice: None,
             buffer: None,
             capacity: 0,
             buffer_usage: BufferUsage::all(),
@@ -33,9 +33,11 @@ impl<T: Pod> BufferVec<T> {
             ..Default::default()
         }
     }
+    /// The slice of the staging belt written by the last call to
+    /// [`BufferVec::write_to_staging_buffer`].
     #[inline]
-    pub fn staging_buffer(&self) -> Option<BufferId> {
-        self.staging_buffer
+    pub fn staging_slice(&self) -> Option<StagingSlice> {
+        self.staging_slice
     }
 
     #[inline]
@@ -48,6 +50,16 @@ impl<T: Pod> BufferVec<T> {
         self.capacity
     }
 
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
     pub fn push(&mut self, value: T) -> usize {
         if self.values.len() < self.capacity {
             let index = self.values.len();
@@ -64,20 +76,11 @@ impl<T: Pod> BufferVec<T> {
     pub fn reserve(&mut self, capacity: usize, render_resources: &RenderResources) {
         if capacity > self.capacity {
             self.capacity = capacity;
-            if let Some(staging_buffer) = self.staging_buffer.take() {
-                render_resources.remove_buffer(staging_buffer);
-            }
-
             if let Some(buffer) = self.buffer.take() {
                 render_resources.remove_buffer(buffer);
             }
 
             let size = self.item_size * capacity;
-            self.staging_buffer = Some(render_resources.create_buffer(BufferInfo {
-                size,
-                buffer_usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
-                mapped_at_creation: false,
-            }));
             self.buffer = Some(render_resources.create_buffer(BufferInfo {
                 size,
                 buffer_usage: BufferUsage::COPY_DST | self.buffer_usage,
@@ -91,34 +94,35 @@ impl<T: Pod> BufferVec<T> {
         self.reserve(capacity, render_resources);
     }
 
-    pub fn write_to_staging_buffer(&self, render_resources: &RenderResources) {
-        if let Some(staging_buffer) = self.staging_buffer {
-            let size = self.values.len() * self.item_size;
-            render_resources.map_buffer(staging_buffer, BufferMapMode::Write);
-            render_resources.write_mapped_buffer(
-                staging_buffer,
-                0..size as u64,
-                &mut |data, _renderer| {
-                    let bytes: &[u8] = cast_slice(&self.values);
+    /// Writes the values to the staging belt. They are copied to the buffer by
+    /// [`BufferVec::write_to_buffer`], which has to run in the same frame.
+    pub fn write_to_staging_buffer(&mut self, render_resources: &RenderResources) {
+        self.staging_slice = if self.values.is_empty() {
+            None
+        } else {
+            let bytes: &[u8] = cast_slice(&self.values);
+            Some(
+                render_resources.write_staging_belt(bytes.len() as u64, &mut |data| {
                     data.copy_from_slice(bytes);
-                },
-            );
-            render_resources.unmap_buffer(staging_buffer);
-        }
+                }),
+            )
+        };
     }
+
     pub fn write_to_buffer(&self, render_context: &mut dyn RenderContext) {
-        if let (Some(staging_buffer), Some(uniform_buffer)) = (self.staging_buffer, self.buffer) {
+        if let (Some(staging_slice), Some(buffer)) = (self.staging_slice, self.buffer) {
             render_context.copy_buffer_to_buffer(
-                staging_buffer,
-                0,
-                uniform_buffer,
+                staging_slice.buffer,
+                staging_slice.offset,
+                buffer,
                 0,
-                (self.values.len() * self.item_size) as u64,
+                staging_slice.size,
             );
         }
     }
 
     pub fn clear(&mut self) {
         self.values.clear();
+        self.staging_slice = None;
     }
 }
@@ -1,17 +1,24 @@
+mod as_bind_group;
 mod bind_group;
 mod buffer;
 mod buffer_vec;
+mod registry;
 mod render_resource_bindings;
 mod render_resource_id;
+mod storage_buffer_vec;
 mod swap_chain;
 mod texture;
 mod uniform_vec;
 
+pub use as_bind_group::*;
+pub use bevy_derive::AsBindGroup;
 pub use bind_group::*;
 pub use buffer::*;
 pub use buffer_vec::*;
+pub use registry::*;
 pub use render_resource_bindings::*;
 pub use render_resource_id::*;
+pub use storage_buffer_vec::*;
 pub use swap_chain::*;
 pub use texture::*;
 pub use uniform_vec::*;
@@ -0,0 +1,114 @@
+use crate::render_resource::RenderResourceId;
+use bevy_ecs::{entity::Entity, system::ResMut};
+use bevy_utils::HashMap;
+use std::borrow::Cow;
+
+/// Render resources published under a name, so render features can use each other's outputs
+/// without depending on the types that own them, e.g. a post processing node sampling the shadow
+/// maps of the pbr plugin.
+///
+/// Global resources stay published until they are replaced or removed, so publishers must
+/// replace them whenever they recreate them. Resources published for a view are removed at the
+/// end of the frame, like the view entities, and are looked up before the global ones.
+#[derive(Default)]
+pub struct RenderResourceRegistry {
+    resources: HashMap<Cow<'static, str>, RenderResourceId>,
+    view_resources: HashMap<Entity, HashMap<Cow<'static, str>, RenderResourceId>>,
+}
+
+impl RenderResourceRegistry {
+    /// Publishes `resource` under `name`, returning the resource it replaces.
+    pub fn insert(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        resource: impl Into<RenderResourceId>,
+    ) -> Option<RenderResourceId> {
+        self.resources.insert(name.into(), resource.into())
+    }
+
+    /// Publishes `resource` under `name` for `view` until the end of the frame, returning the
+    /// resource it replaces.
+    pub fn insert_view(
+        &mut self,
+        view: Entity,
+        name: impl Into<Cow<'static, str>>,
+        resource: impl Into<RenderResourceId>,
+    ) -> Option<RenderResourceId> {
+        self.view_resources
+            .entry(view)
+            .or_default()
+            .insert(name.into(), resource.into())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<RenderResourceId> {
+        self.resources.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RenderResourceId> {
+        self.resources.get(name)
+    }
+
+    /// Returns the resource published under `name` for `view`, or the global one if there is
+    /// none.
+    pub fn get_view(&self, view: Entity, name: &str) -> Option<&RenderResourceId> {
+        self.view_resources
+            .get(&view)
+            .and_then(|resources| resources.get(name))
+            .or_else(|| self.get(name))
+    }
+
+    /// Removes the resources published for views.
+    pub fn clear_views(&mut self) {
+        self.view_resources.clear();
+    }
+}
+
+/// Removes the resources published for views in the [`RenderResourceRegistry`]. Runs in
+/// [`RenderStage::Cleanup`](crate::RenderStage::Cleanup).
+pub fn clear_registry_views_system(mut registry: ResMut<RenderResourceRegistry>) {
+    registry.clear_views();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderResourceRegistry;
+    use crate::render_resource::{RenderResourceId, SamplerId, TextureViewId};
+    use bevy_ecs::entity::Entity;
+
+    #[test]
+    fn view_resources_override_global_ones() {
+        let mut registry = RenderResourceRegistry::default();
+        let (global, view_texture) = (TextureViewId::new(), TextureViewId::new());
+        let sampler = SamplerId::new();
+        let view = Entity::new(0);
+
+        assert_eq!(registry.insert("texture", global), None);
+        registry.insert("sampler", sampler);
+        registry.insert_view(view, "texture", view_texture);
+        assert_eq!(
+            registry.get("texture"),
+            Some(&RenderResourceId::TextureView(global))
+        );
+        assert_eq!(
+            registry
+                .get_view(view, "texture")
+                .and_then(RenderResourceId::get_texture_view),
+            Some(view_texture)
+        );
+        assert_eq!(
+            registry.get_view(view, "sampler"),
+            Some(&RenderResourceId::Sampler(sampler))
+        );
+
+        registry.clear_views();
+        assert_eq!(
+            registry.get_view(view, "texture"),
+            Some(&RenderResourceId::TextureView(global))
+        );
+        assert_eq!(
+            registry.remove("texture"),
+            Some(RenderResourceId::TextureView(global))
+        );
+        assert_eq!(registry.get_view(view, "texture"), None);
+    }
+}
@@ -32,3 +32,21 @@ impl RenderResourceId {
         }
     }
 }
+
+impl From<BufferId> for RenderResourceId {
+    fn from(id: BufferId) -> Self {
+        RenderResourceId::Buffer(id)
+    }
+}
+
+impl From<TextureViewId> for RenderResourceId {
+    fn from(id: TextureViewId) -> Self {
+        RenderResourceId::TextureView(id)
+    }
+}
+
+impl From<SamplerId> for RenderResourceId {
+    fn from(id: SamplerId) -> Self {
+        RenderResourceId::Sampler(id)
+    }
+}
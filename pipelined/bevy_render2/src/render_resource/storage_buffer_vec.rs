@@ -0,0 +1,202 @@
+use crate::{
+    render_resource::{BufferId, BufferInfo, BufferUsage, RenderResourceBinding, StagingSlice},
+    renderer::{RenderContext, RenderResources},
+};
+use crevice::std430::{self, AsStd430, Std430};
+
+/// A growable array of values in a storage buffer, packed with the std430 layout rules that GLSL
+/// uses for shader storage blocks. Values are written at a stride of their std430 size rounded up
+/// to their alignment, so a `StorageBufferVec<T>` binds to a runtime-sized array of the matching
+/// GLSL struct without manual padding fields:
+///
+/// ```glsl
+/// layout(std430, set = 0, binding = 0) readonly buffer Instances {
+///     Instance instances[];
+/// };
+/// ```
+pub struct StorageBufferVec<T: AsStd430> {
+    values: Vec<T>,
+    staging_slice: Option<StagingSlice>,
+    buffer: Option<BufferId>,
+    capacity: usize,
+    item_size: usize,
+    buffer_usage: BufferUsage,
+}
+
+impl<T: AsStd430> Default for StorageBufferVec<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            staging_slice: None,
+            buffer: None,
+            capacity: 0,
+            item_size: (T::std430_size_static() + <T as AsStd430>::Std430Type::ALIGNMENT - 1)
+                & !(<T as AsStd430>::Std430Type::ALIGNMENT - 1),
+            buffer_usage: BufferUsage::empty(),
+        }
+    }
+}
+
+impl<T: AsStd430> StorageBufferVec<T> {
+    /// Creates a vec whose buffer has `buffer_usage` in addition to `STORAGE`, e.g. `VERTEX` for
+    /// instance data written by a compute shader.
+    pub fn new(buffer_usage: BufferUsage) -> Self {
+        Self {
+            buffer_usage,
+            ..Default::default()
+        }
+    }
+
+    /// The slice of the staging belt written by the last call to
+    /// [`StorageBufferVec::write_to_staging_buffer`].
+    #[inline]
+    pub fn staging_slice(&self) -> Option<StagingSlice> {
+        self.staging_slice
+    }
+
+    #[inline]
+    pub fn buffer(&self) -> Option<BufferId> {
+        self.buffer
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The distance in bytes between consecutive values in the buffer.
+    #[inline]
+    pub fn item_size(&self) -> usize {
+        self.item_size
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the binding of the pushed values. An empty vec binds its first item, since
+    /// bindings can't be empty.
+    pub fn binding(&self) -> RenderResourceBinding {
+        RenderResourceBinding::Buffer {
+            buffer: self.buffer.unwrap(),
+            range: 0..(self.values.len().max(1) * self.item_size) as u64,
+        }
+    }
+
+    /// Pushes a value and returns its index. Values pushed beyond the reserved capacity grow the
+    /// buffer in [`StorageBufferVec::write_to_staging_buffer`].
+    pub fn push(&mut self, value: T) -> usize {
+        let index = self.values.len();
+        self.values.push(value);
+        index
+    }
+
+    pub fn reserve(&mut self, capacity: usize, render_resources: &RenderResources) {
+        if capacity > self.capacity {
+            self.capacity = capacity;
+            if let Some(buffer) = self.buffer.take() {
+                render_resources.remove_buffer(buffer);
+            }
+
+            self.buffer = Some(render_resources.create_buffer(BufferInfo {
+                size: self.item_size * capacity,
+                buffer_usage: BufferUsage::COPY_DST | BufferUsage::STORAGE | self.buffer_usage,
+                mapped_at_creation: false,
+            }));
+        }
+    }
+
+    pub fn reserve_and_clear(&mut self, capacity: usize, render_resources: &RenderResources) {
+        self.clear();
+        self.reserve(capacity, render_resources);
+    }
+
+    /// Writes the std430 representation of the values to the staging belt, first growing the
+    /// buffer if more values were pushed than reserved. This changes [`StorageBufferVec::binding`],
+    /// so bind groups must be created after calling this. The values are copied to the buffer by
+    /// [`StorageBufferVec::write_to_buffer`], which has to run in the same frame.
+    pub fn write_to_staging_buffer(&mut self, render_resources: &RenderResources) {
+        if self.values.len() > self.capacity {
+            self.reserve(self.values.len().max(self.capacity * 2), render_resources);
+        }
+        self.staging_slice = if self.values.is_empty() {
+            None
+        } else {
+            let values = &self.values;
+            let size = values.len() * self.item_size;
+            Some(
+                render_resources.write_staging_belt(size as u64, &mut |data| {
+                    // each write pads to the alignment of the value, which puts it at the stride
+                    let mut writer = std430::Writer::new(&mut data[..]);
+                    for value in values.iter() {
+                        writer.write(value).unwrap();
+                    }
+                }),
+            )
+        };
+    }
+
+    pub fn write_to_buffer(&self, render_context: &mut dyn RenderContext) {
+        if let (Some(staging_slice), Some(buffer)) = (self.staging_slice, self.buffer) {
+            render_context.copy_buffer_to_buffer(
+                staging_slice.buffer,
+                staging_slice.offset,
+                buffer,
+                0,
+                staging_slice.size,
+            );
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.staging_slice = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StorageBufferVec;
+    use crate::renderer::{HeadlessRenderResourceContext, RenderResources};
+    use bevy_math::Vec3;
+
+    #[test]
+    fn values_are_written_at_std430_stride() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        let mut storage = StorageBufferVec::<Vec3>::default();
+        // a vec3 is 12 bytes, but aligned to 16 in std430 arrays
+        assert_eq!(storage.item_size(), 16);
+
+        storage.push(Vec3::ONE);
+        storage.push(Vec3::new(2.0, 2.0, 2.0));
+        storage.write_to_staging_buffer(&render_resources);
+        assert_eq!(storage.capacity(), 2);
+
+        let context = render_resources
+            .downcast_ref::<HeadlessRenderResourceContext>()
+            .unwrap();
+        let data = context
+            .get_buffer_data(storage.staging_slice().unwrap().buffer)
+            .unwrap();
+        assert_eq!(data.len(), 32);
+        let value = |offset: usize| {
+            f32::from_ne_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+        assert_eq!(value(0), 1.0);
+        assert_eq!(value(8), 1.0);
+        assert_eq!(value(16), 2.0);
+        assert_eq!(value(28), 0.0);
+    }
+}
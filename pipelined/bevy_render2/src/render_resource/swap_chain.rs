@@ -1,16 +1,212 @@
 use bevy_window::WindowId;
+use thiserror::Error;
 
-use crate::texture::TextureFormat;
+use crate::texture::{TextureFormat, TextureUsage};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct SwapChainDescriptor {
     pub window_id: WindowId,
-    /// The texture format of the swap chain. The only formats that are guaranteed are
-    /// `Bgra8Unorm` and `Bgra8UnormSrgb`
+    /// The texture format of the swap chain. Must be one of
+    /// [`SwapChainDescriptor::SUPPORTED_FORMATS`].
     pub format: TextureFormat,
+    /// The usages of the swap chain textures. Must include
+    /// [`TextureUsage::RENDER_ATTACHMENT`] and be contained in
+    /// [`SwapChainDescriptor::SUPPORTED_USAGE`].
+    pub usage: TextureUsage,
     /// Width of the swap chain. Must be the same size as the surface.
     pub width: u32,
     /// Height of the swap chain. Must be the same size as the surface.
     pub height: u32,
     pub vsync: bool,
 }
+
+impl SwapChainDescriptor {
+    /// The formats that surfaces support on every backend.
+    pub const SUPPORTED_FORMATS: &'static [TextureFormat] = &[
+        TextureFormat::Bgra8Unorm,
+        TextureFormat::Bgra8UnormSrgb,
+        TextureFormat::Rgba8Unorm,
+        TextureFormat::Rgba8UnormSrgb,
+    ];
+
+    /// The usages that surfaces support on every backend.
+    pub const SUPPORTED_USAGE: TextureUsage = TextureUsage::from_bits_truncate(
+        TextureUsage::RENDER_ATTACHMENT.bits() | TextureUsage::COPY_SRC.bits(),
+    );
+
+    /// Checks that the format and usage of this descriptor are supported by surfaces.
+    pub fn validate(&self) -> Result<(), SwapChainDescriptorError> {
+        if !Self::SUPPORTED_FORMATS.contains(&self.format) {
+            return Err(SwapChainDescriptorError::UnsupportedFormat(self.format));
+        }
+        if !self.usage.contains(TextureUsage::RENDER_ATTACHMENT) {
+            return Err(SwapChainDescriptorError::MissingRenderAttachmentUsage);
+        }
+        if !Self::SUPPORTED_USAGE.contains(self.usage) {
+            return Err(SwapChainDescriptorError::UnsupportedUsage(
+                self.usage - Self::SUPPORTED_USAGE,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The present mode of the swap chain, [`PresentMode::Fifo`] with vsync and
+    /// [`PresentMode::Immediate`] without.
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+}
+
+/// How a swap chain presents its frames to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Frames are presented as soon as they are rendered, which may tear.
+    Immediate,
+    /// Frames are presented at the next vertical blank, a newer frame replacing one that is
+    /// still waiting.
+    Mailbox,
+    /// Frames are queued and presented at vertical blanks. Every surface supports it.
+    Fifo,
+}
+
+/// How the surface of a window is composited with what is behind the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompositeAlphaMode {
+    /// The alpha of the frames is ignored.
+    Opaque,
+    /// The frames are blended with colors that were multiplied by their alpha.
+    PreMultiplied,
+    /// The frames are blended with colors that weren't multiplied by their alpha.
+    PostMultiplied,
+}
+
+/// What the surface of a window supports, reported by the render backend when it creates the
+/// surface, see [`SurfaceInfos`](crate::view::SurfaceInfos).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SurfaceInfo {
+    /// The formats the swap chain can have, the format the surface prefers first.
+    pub formats: Vec<TextureFormat>,
+    pub present_modes: Vec<PresentMode>,
+    pub alpha_modes: Vec<CompositeAlphaMode>,
+}
+
+impl SurfaceInfo {
+    /// The format the surface prefers, which is usually the one the compositor uses.
+    pub fn preferred_format(&self) -> Option<TextureFormat> {
+        self.formats.first().copied()
+    }
+
+    /// Checks that the format and present mode of `descriptor` are supported by the surface.
+    pub fn validate(
+        &self,
+        descriptor: &SwapChainDescriptor,
+    ) -> Result<(), SwapChainDescriptorError> {
+        if !self.formats.contains(&descriptor.format) {
+            return Err(SwapChainDescriptorError::UnsupportedFormat(
+                descriptor.format,
+            ));
+        }
+        if !self.present_modes.contains(&descriptor.present_mode()) {
+            return Err(SwapChainDescriptorError::UnsupportedPresentMode(
+                descriptor.present_mode(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SwapChainDescriptorError {
+    #[error("swap chain format {0:?} is not supported by surfaces")]
+    UnsupportedFormat(TextureFormat),
+    #[error("present mode {0:?} is not supported by the surface")]
+    UnsupportedPresentMode(PresentMode),
+    #[error("swap chain usage {0:?} is not supported by surfaces")]
+    UnsupportedUsage(TextureUsage),
+    #[error("swap chain usage must include RENDER_ATTACHMENT")]
+    MissingRenderAttachmentUsage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PresentMode, SurfaceInfo, SwapChainDescriptor, SwapChainDescriptorError};
+    use crate::texture::{TextureFormat, TextureUsage};
+    use bevy_window::WindowId;
+
+    #[test]
+    fn validate() {
+        let mut descriptor = SwapChainDescriptor {
+            window_id: WindowId::primary(),
+            format: TextureFormat::Bgra8UnormSrgb,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::COPY_SRC,
+            width: 1,
+            height: 1,
+            vsync: true,
+        };
+        assert_eq!(descriptor.validate(), Ok(()));
+
+        descriptor.usage = TextureUsage::COPY_SRC;
+        assert_eq!(
+            descriptor.validate(),
+            Err(SwapChainDescriptorError::MissingRenderAttachmentUsage)
+        );
+
+        descriptor.usage = TextureUsage::RENDER_ATTACHMENT | TextureUsage::STORAGE;
+        assert_eq!(
+            descriptor.validate(),
+            Err(SwapChainDescriptorError::UnsupportedUsage(
+                TextureUsage::STORAGE
+            ))
+        );
+
+        descriptor.format = TextureFormat::Depth32Float;
+        assert_eq!(
+            descriptor.validate(),
+            Err(SwapChainDescriptorError::UnsupportedFormat(
+                TextureFormat::Depth32Float
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_against_surface() {
+        let surface_info = SurfaceInfo {
+            formats: vec![TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm],
+            present_modes: vec![PresentMode::Fifo],
+            alpha_modes: Vec::new(),
+        };
+        assert_eq!(
+            surface_info.preferred_format(),
+            Some(TextureFormat::Bgra8UnormSrgb)
+        );
+        let mut descriptor = SwapChainDescriptor {
+            window_id: WindowId::primary(),
+            format: TextureFormat::Bgra8Unorm,
+            usage: TextureUsage::RENDER_ATTACHMENT,
+            width: 1,
+            height: 1,
+            vsync: true,
+        };
+        assert_eq!(surface_info.validate(&descriptor), Ok(()));
+
+        descriptor.vsync = false;
+        assert_eq!(
+            surface_info.validate(&descriptor),
+            Err(SwapChainDescriptorError::UnsupportedPresentMode(
+                PresentMode::Immediate
+            ))
+        );
+
+        descriptor.format = TextureFormat::Rgba8Unorm;
+        assert_eq!(
+            surface_info.validate(&descriptor),
+            Err(SwapChainDescriptorError::UnsupportedFormat(
+                TextureFormat::Rgba8Unorm
+            ))
+        );
+    }
+}
@@ -4,6 +4,14 @@ use crate::{
 };
 use crevice::std140::{self, AsStd140, DynamicUniform, Std140};
 
+/// The `max_uniform_buffer_binding_size` every device supports.
+pub const DEFAULT_MAX_UNIFORM_BUFFER_BINDING_SIZE: usize = 16384;
+
+/// [`UniformVec`]s whose buffers are at most this large are written with
+/// [`RenderResourceContext::write_buffer`](crate::renderer::RenderResourceContext::write_buffer)
+/// instead of through a staging buffer.
+pub const MAX_QUEUE_WRITE_SIZE: usize = 4096;
+
 pub struct UniformVec<T: AsStd140> {
     values: Vec<T>,
     staging_buffer: Option<BufferId>,
@@ -41,6 +49,16 @@ impl<T: AsStd140> UniformVec<T> {
         self.capacity
     }
 
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
     pub fn binding(&self) -> RenderResourceBinding {
         RenderResourceBinding::Buffer {
             buffer: self.uniform_buffer.unwrap(),
@@ -48,17 +66,12 @@ impl<T: AsStd140> UniformVec<T> {
         }
     }
 
+    /// Pushes a value and returns its index. Values pushed beyond the reserved capacity grow the
+    /// buffers in [`UniformVec::write_to_staging_buffer`].
     pub fn push(&mut self, value: T) -> usize {
         let len = self.values.len();
-        if len < self.capacity {
-            self.values.push(value);
-            len
-        } else {
-            panic!(
-                "Cannot push value because capacity of {} has been reached",
-                self.capacity
-            );
-        }
+        self.values.push(value);
+        len
     }
 
     pub fn reserve(&mut self, capacity: usize, render_resources: &RenderResources) {
@@ -73,11 +86,13 @@ impl<T: AsStd140> UniformVec<T> {
             }
 
             let size = self.item_size * capacity;
-            self.staging_buffer = Some(render_resources.create_buffer(BufferInfo {
-                size,
-                buffer_usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
-                mapped_at_creation: false,
-            }));
+            if size > MAX_QUEUE_WRITE_SIZE {
+                self.staging_buffer = Some(render_resources.create_buffer(BufferInfo {
+                    size,
+                    buffer_usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
+                    mapped_at_creation: false,
+                }));
+            }
             self.uniform_buffer = Some(render_resources.create_buffer(BufferInfo {
                 size,
                 buffer_usage: BufferUsage::COPY_DST | BufferUsage::UNIFORM,
@@ -91,9 +106,18 @@ impl<T: AsStd140> UniformVec<T> {
         self.reserve(capacity, render_resources);
     }
 
-    pub fn write_to_staging_buffer(&self, render_resources: &RenderResources) {
+    /// Writes the values to the staging buffer, first growing the buffers if more values were
+    /// pushed than reserved. This changes [`UniformVec::binding`], so bind groups must be created
+    /// after calling this.
+    ///
+    /// Buffers of at most [`MAX_QUEUE_WRITE_SIZE`] bytes have no staging buffer, and the values
+    /// are written to the uniform buffer right away.
+    pub fn write_to_staging_buffer(&mut self, render_resources: &RenderResources) {
+        if self.values.len() > self.capacity {
+            self.reserve(self.values.len().max(self.capacity * 2), render_resources);
+        }
+        let size = self.values.len() * self.item_size;
         if let Some(staging_buffer) = self.staging_buffer {
-            let size = self.values.len() * self.item_size;
             render_resources.map_buffer(staging_buffer, BufferMapMode::Write);
             render_resources.write_mapped_buffer(
                 staging_buffer,
@@ -104,8 +128,16 @@ impl<T: AsStd140> UniformVec<T> {
                 },
             );
             render_resources.unmap_buffer(staging_buffer);
+        } else if let Some(uniform_buffer) = self.uniform_buffer.filter(|_| size > 0) {
+            let mut data = vec![0; size];
+            let mut writer = std140::Writer::new(&mut data[..]);
+            writer.write(self.values.as_slice()).unwrap();
+            render_resources.write_buffer(uniform_buffer, 0, &data);
         }
     }
+
+    /// Copies the values from the staging buffer to the uniform buffer. Does nothing for buffers
+    /// written by [`UniformVec::write_to_staging_buffer`] directly.
     pub fn write_to_uniform_buffer(&self, render_context: &mut dyn RenderContext) {
         if let (Some(staging_buffer), Some(uniform_buffer)) =
             (self.staging_buffer, self.uniform_buffer)
@@ -125,67 +157,195 @@ impl<T: AsStd140> UniformVec<T> {
     }
 }
 
+/// The location of a value in a [`DynamicUniformVec`]: the chunk that holds it and its dynamic
+/// offset in that chunk's buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DynamicUniformIndex {
+    pub chunk: usize,
+    pub offset: u32,
+}
+
+/// A growable list of dynamic uniforms. The values are split into chunks whose buffers are never
+/// larger than `max_uniform_buffer_binding_size`, and each chunk needs its own bind group (see
+/// [`DynamicUniformVec::binding`]). Draw functions pick the bind group of a value using
+/// [`DynamicUniformIndex::chunk`].
 pub struct DynamicUniformVec<T: AsStd140> {
-    uniform_vec: UniformVec<DynamicUniform<T>>,
+    chunks: Vec<UniformVec<DynamicUniform<T>>>,
+    len: usize,
+    item_size: usize,
+    max_chunk_size: usize,
 }
 
 impl<T: AsStd140> Default for DynamicUniformVec<T> {
     fn default() -> Self {
         Self {
-            uniform_vec: Default::default(),
+            chunks: Vec::new(),
+            len: 0,
+            item_size: UniformVec::<DynamicUniform<T>>::default().item_size,
+            max_chunk_size: DEFAULT_MAX_UNIFORM_BUFFER_BINDING_SIZE,
         }
     }
 }
 
 impl<T: AsStd140> DynamicUniformVec<T> {
+    /// The number of values that fit in one chunk.
     #[inline]
-    pub fn staging_buffer(&self) -> Option<BufferId> {
-        self.uniform_vec.staging_buffer()
+    pub fn chunk_capacity(&self) -> usize {
+        (self.max_chunk_size / self.item_size).max(1)
     }
 
+    /// The number of chunks holding the values pushed since the last clear.
     #[inline]
-    pub fn uniform_buffer(&self) -> Option<BufferId> {
-        self.uniform_vec.uniform_buffer()
+    pub fn chunk_count(&self) -> usize {
+        (self.len + self.chunk_capacity() - 1) / self.chunk_capacity()
     }
 
     #[inline]
-    pub fn binding(&self) -> RenderResourceBinding {
-        self.uniform_vec.binding()
+    pub fn staging_buffer(&self, chunk: usize) -> Option<BufferId> {
+        self.chunks.get(chunk)?.staging_buffer()
+    }
+
+    #[inline]
+    pub fn uniform_buffer(&self, chunk: usize) -> Option<BufferId> {
+        self.chunks.get(chunk)?.uniform_buffer()
+    }
+
+    /// Returns the binding of the given chunk's buffer.
+    #[inline]
+    pub fn binding(&self, chunk: usize) -> RenderResourceBinding {
+        self.chunks[chunk].binding()
     }
 
     #[inline]
     pub fn capacity(&self) -> usize {
-        self.uniform_vec.capacity()
+        self.chunks.iter().map(|chunk| chunk.capacity()).sum()
     }
 
     #[inline]
-    pub fn push(&mut self, value: T) -> u32 {
-        (self.uniform_vec.push(DynamicUniform(value)) * self.uniform_vec.item_size) as u32
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, value: T) -> DynamicUniformIndex {
+        let chunk = self.len / self.chunk_capacity();
+        if chunk == self.chunks.len() {
+            self.chunks.push(UniformVec::default());
+        }
+        let index = self.chunks[chunk].push(DynamicUniform(value));
+        self.len += 1;
+        DynamicUniformIndex {
+            chunk,
+            offset: (index * self.item_size) as u32,
+        }
+    }
+
     pub fn reserve(&mut self, capacity: usize, render_resources: &RenderResources) {
-        self.uniform_vec.reserve(capacity, render_resources);
+        if self.is_empty() {
+            self.max_chunk_size = render_resources.get_max_uniform_buffer_binding_size();
+        }
+        let chunk_capacity = self.chunk_capacity();
+        let mut remaining = capacity;
+        let mut chunk = 0;
+        while remaining > 0 {
+            if chunk == self.chunks.len() {
+                self.chunks.push(UniformVec::default());
+            }
+            self.chunks[chunk].reserve(remaining.min(chunk_capacity), render_resources);
+            remaining = remaining.saturating_sub(chunk_capacity);
+            chunk += 1;
+        }
     }
 
     #[inline]
     pub fn reserve_and_clear(&mut self, capacity: usize, render_resources: &RenderResources) {
-        self.uniform_vec
-            .reserve_and_clear(capacity, render_resources);
+        self.clear();
+        self.reserve(capacity, render_resources);
     }
 
-    #[inline]
-    pub fn write_to_staging_buffer(&self, render_resources: &RenderResources) {
-        self.uniform_vec.write_to_staging_buffer(render_resources);
+    /// Writes the values to the staging buffers, growing the buffers of chunks that hold more
+    /// values than reserved. Bind groups must be created after calling this.
+    pub fn write_to_staging_buffer(&mut self, render_resources: &RenderResources) {
+        let chunk_capacity = self.chunk_capacity();
+        let chunk_count = self.chunk_count();
+        for chunk in self.chunks[..chunk_count].iter_mut() {
+            if chunk.len() > chunk.capacity() {
+                let capacity = chunk.len().max(chunk.capacity() * 2).min(chunk_capacity);
+                chunk.reserve(capacity, render_resources);
+            }
+            chunk.write_to_staging_buffer(render_resources);
+        }
     }
 
-    #[inline]
     pub fn write_to_uniform_buffer(&self, render_context: &mut dyn RenderContext) {
-        self.uniform_vec.write_to_uniform_buffer(render_context);
+        for chunk in self.chunks[..self.chunk_count()].iter() {
+            chunk.write_to_uniform_buffer(render_context);
+        }
     }
 
-    #[inline]
     pub fn clear(&mut self) {
-        self.uniform_vec.clear();
+        for chunk in self.chunks.iter_mut() {
+            chunk.clear();
+        }
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynamicUniformIndex, DynamicUniformVec, UniformVec, MAX_QUEUE_WRITE_SIZE};
+    use crate::renderer::{HeadlessRenderResourceContext, RenderResources};
+    use bevy_math::{Mat4, Vec4};
+
+    #[test]
+    fn push_splits_into_chunks() {
+        let mut uniforms = DynamicUniformVec::<Mat4>::default();
+        let chunk_capacity = uniforms.chunk_capacity();
+        let mut last = DynamicUniformIndex::default();
+        for _ in 0..chunk_capacity {
+            last = uniforms.push(Mat4::IDENTITY);
+        }
+        assert_eq!(last.chunk, 0);
+        assert_eq!(uniforms.chunk_count(), 1);
+
+        assert_eq!(
+            uniforms.push(Mat4::IDENTITY),
+            DynamicUniformIndex {
+                chunk: 1,
+                offset: 0
+            }
+        );
+        assert_eq!(uniforms.chunk_count(), 2);
+
+        uniforms.clear();
+        assert_eq!(uniforms.chunk_count(), 0);
+        assert_eq!(uniforms.push(Mat4::IDENTITY).chunk, 0);
+    }
+
+    #[test]
+    fn small_buffers_skip_staging() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        let mut uniforms = UniformVec::<Vec4>::default();
+        uniforms.reserve(2, &render_resources);
+        uniforms.push(Vec4::ONE);
+        uniforms.write_to_staging_buffer(&render_resources);
+        assert!(uniforms.staging_buffer().is_none());
+        let context = render_resources
+            .downcast_ref::<HeadlessRenderResourceContext>()
+            .unwrap();
+        let data = context
+            .get_buffer_data(uniforms.uniform_buffer().unwrap())
+            .unwrap();
+        assert!(data[..16]
+            .chunks(4)
+            .all(|value| value == 1.0f32.to_ne_bytes()));
+
+        uniforms.reserve(MAX_QUEUE_WRITE_SIZE / 16 + 1, &render_resources);
+        assert!(uniforms.staging_buffer().is_some());
     }
 }
@@ -3,41 +3,166 @@ use crate::{
         BindGroupDescriptorId, ComputePipelineDescriptor, PipelineId, RenderPipelineDescriptor,
     },
     render_resource::{
-        BindGroup, BufferId, BufferInfo, BufferMapMode, SamplerId, SwapChainDescriptor, TextureId,
-        TextureViewId,
+        BindGroup, BindGroupId, BufferId, BufferInfo, BufferMapMode, BufferUsage, SamplerId,
+        StagingSlice, SwapChainDescriptor, TextureId, TextureViewId,
+        DEFAULT_MAX_UNIFORM_BUFFER_BINDING_SIZE,
     },
-    renderer::RenderResourceContext,
+    renderer::{RenderFeatures, RenderResourceContext, RenderResources},
     shader::{Shader, ShaderId},
-    texture::{SamplerDescriptor, TextureDescriptor, TextureViewDescriptor},
+    texture::{Extent3d, SamplerDescriptor, TextureDescriptor, TextureViewDescriptor},
+    RenderApp, RenderStage,
 };
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
 use bevy_utils::HashMap;
 use parking_lot::RwLock;
 use std::{ops::Range, sync::Arc};
 
-#[derive(Debug, Default)]
+/// Uses a [`HeadlessRenderResourceContext`] as the render backend, so render logic can run
+/// without a GPU, e.g. in tests. Add it instead of a backend plugin such as `WgpuPlugin`.
+///
+/// The systems of the render stages run as usual, but the render graph isn't run. The staging
+/// belt is recalled in [`RenderStage::Cleanup`] instead.
+#[derive(Default)]
+pub struct HeadlessRenderPlugin;
+
+impl Plugin for HeadlessRenderPlugin {
+    fn build(&self, app: &mut App) {
+        let context = HeadlessRenderResourceContext::default();
+        app.world
+            .insert_resource(RenderResources::new(Box::new(context.clone())));
+        app.sub_app_mut(RenderApp)
+            .insert_resource(RenderResources::new(Box::new(context)))
+            .add_system_to_stage(RenderStage::Cleanup, recall_staging_belt.system());
+    }
+}
+
+fn recall_staging_belt(render_resources: Res<RenderResources>) {
+    if let Some(context) = render_resources.downcast_ref::<HeadlessRenderResourceContext>() {
+        context.recall_staging_belt();
+    }
+}
+
+/// A [`RenderResourceContext`] that keeps track of the resources it creates without a GPU.
+///
+/// Clones share the same resources, so a clone can be kept to inspect the resources created
+/// through [`RenderResources`].
+#[derive(Debug, Default, Clone)]
 pub struct HeadlessRenderResourceContext {
     buffer_info: Arc<RwLock<HashMap<BufferId, BufferInfo>>>,
+    buffer_data: Arc<RwLock<HashMap<BufferId, Vec<u8>>>>,
     texture_descriptors: Arc<RwLock<HashMap<TextureId, TextureDescriptor>>>,
     texture_view_descriptors: Arc<RwLock<HashMap<TextureViewId, TextureViewDescriptor>>>,
+    sampler_descriptors: Arc<RwLock<HashMap<SamplerId, SamplerDescriptor>>>,
+    shaders: Arc<RwLock<HashMap<ShaderId, Shader>>>,
+    render_pipelines: Arc<RwLock<HashMap<PipelineId, RenderPipelineDescriptor>>>,
+    compute_pipelines: Arc<RwLock<HashMap<PipelineId, ComputePipelineDescriptor>>>,
+    bind_groups: Arc<RwLock<HashMap<BindGroupId, (BindGroupDescriptorId, BindGroup)>>>,
+    staging_belt: Arc<RwLock<Vec<BufferId>>>,
 }
 
 impl HeadlessRenderResourceContext {
     pub fn add_buffer_info(&self, buffer: BufferId, info: BufferInfo) {
+        self.buffer_data.write().insert(buffer, vec![0; info.size]);
         self.buffer_info.write().insert(buffer, info);
     }
 
     pub fn add_texture_descriptor(&self, texture: TextureId, descriptor: TextureDescriptor) {
         self.texture_descriptors.write().insert(texture, descriptor);
     }
+
+    /// Returns the contents of `buffer`, as last written with
+    /// [`RenderResourceContext::write_mapped_buffer`], [`RenderResourceContext::write_buffer`] or
+    /// on creation.
+    pub fn get_buffer_data(&self, buffer: BufferId) -> Option<Vec<u8>> {
+        self.buffer_data.read().get(&buffer).cloned()
+    }
+
+    /// Removes the buffers written with [`RenderResourceContext::write_staging_belt`] this frame.
+    pub fn recall_staging_belt(&self) {
+        for buffer in self.staging_belt.write().drain(..) {
+            self.remove_buffer(buffer);
+        }
+    }
+
+    pub fn get_texture_descriptor(&self, texture: TextureId) -> Option<TextureDescriptor> {
+        self.texture_descriptors.read().get(&texture).cloned()
+    }
+
+    pub fn get_texture_view_descriptor(
+        &self,
+        texture_view: TextureViewId,
+    ) -> Option<TextureViewDescriptor> {
+        self.texture_view_descriptors
+            .read()
+            .get(&texture_view)
+            .cloned()
+    }
+
+    pub fn get_sampler_descriptor(&self, sampler: SamplerId) -> Option<SamplerDescriptor> {
+        self.sampler_descriptors.read().get(&sampler).cloned()
+    }
+
+    pub fn get_shader(&self, shader: ShaderId) -> Option<Shader> {
+        self.shaders.read().get(&shader).cloned()
+    }
+
+    pub fn get_render_pipeline_descriptor(
+        &self,
+        pipeline: PipelineId,
+    ) -> Option<RenderPipelineDescriptor> {
+        self.render_pipelines.read().get(&pipeline).cloned()
+    }
+
+    pub fn get_compute_pipeline_descriptor(
+        &self,
+        pipeline: PipelineId,
+    ) -> Option<ComputePipelineDescriptor> {
+        self.compute_pipelines.read().get(&pipeline).cloned()
+    }
+
+    /// Returns the bind group `bind_group` and the descriptor it was created for.
+    pub fn get_bind_group(
+        &self,
+        bind_group: BindGroupId,
+    ) -> Option<(BindGroupDescriptorId, BindGroup)> {
+        self.bind_groups.read().get(&bind_group).cloned()
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.buffer_info.read().len()
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.texture_descriptors.read().len()
+    }
+
+    pub fn texture_view_count(&self) -> usize {
+        self.texture_view_descriptors.read().len()
+    }
+
+    pub fn sampler_count(&self) -> usize {
+        self.sampler_descriptors.read().len()
+    }
+
+    pub fn bind_group_count(&self) -> usize {
+        self.bind_groups.read().len()
+    }
 }
 
 impl RenderResourceContext for HeadlessRenderResourceContext {
-    fn drop_swap_chain_texture(&self, _texture_view: TextureViewId) {}
+    fn drop_swap_chain_texture(&self, texture_view: TextureViewId) {
+        self.texture_view_descriptors.write().remove(&texture_view);
+    }
 
     fn drop_all_swap_chain_textures(&self) {}
 
-    fn create_sampler(&self, _sampler_descriptor: &SamplerDescriptor) -> SamplerId {
-        SamplerId::new()
+    fn create_sampler(&self, sampler_descriptor: &SamplerDescriptor) -> SamplerId {
+        let sampler = SamplerId::new();
+        self.sampler_descriptors
+            .write()
+            .insert(sampler, *sampler_descriptor);
+        sampler
     }
 
     fn create_texture(&self, texture_descriptor: TextureDescriptor) -> TextureId {
@@ -67,75 +192,134 @@ impl RenderResourceContext for HeadlessRenderResourceContext {
     fn write_mapped_buffer(
         &self,
         id: BufferId,
-        _range: Range<u64>,
+        range: Range<u64>,
         write: &mut dyn FnMut(&mut [u8], &dyn RenderResourceContext),
     ) {
-        let size = self.buffer_info.read().get(&id).unwrap().size;
-        let mut buffer = vec![0; size];
-        write(&mut buffer, self);
+        // take the data out, so `write` can use this context
+        let mut buffer = self.buffer_data.write().remove(&id).unwrap();
+        write(&mut buffer[range.start as usize..range.end as usize], self);
+        self.buffer_data.write().insert(id, buffer);
     }
 
     fn read_mapped_buffer(
         &self,
         id: BufferId,
-        _range: Range<u64>,
+        range: Range<u64>,
         read: &dyn Fn(&[u8], &dyn RenderResourceContext),
     ) {
-        let size = self.buffer_info.read().get(&id).unwrap().size;
-        let buffer = vec![0; size];
-        read(&buffer, self);
+        let buffer = self.get_buffer_data(id).unwrap();
+        read(&buffer[range.start as usize..range.end as usize], self);
+    }
+
+    fn write_buffer(&self, id: BufferId, offset: u64, data: &[u8]) {
+        let mut buffer_data = self.buffer_data.write();
+        let buffer = buffer_data.get_mut(&id).unwrap();
+        buffer[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+    }
+
+    fn write_texture(
+        &self,
+        _id: TextureId,
+        _origin: [u32; 3],
+        _mip_level: u32,
+        _data: &[u8],
+        _bytes_per_row: u32,
+        _size: Extent3d,
+    ) {
+    }
+
+    fn write_staging_belt(&self, size: u64, write: &mut dyn FnMut(&mut [u8])) -> StagingSlice {
+        let mut data = vec![0; size as usize];
+        write(&mut data);
+        let buffer = self.create_buffer_with_data(
+            BufferInfo {
+                buffer_usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
+                ..Default::default()
+            },
+            &data,
+        );
+        self.staging_belt.write().push(buffer);
+        StagingSlice {
+            buffer,
+            offset: 0,
+            size,
+        }
     }
 
     fn map_buffer(&self, _id: BufferId, _mode: BufferMapMode) {}
 
+    fn map_buffer_async(&self, _id: BufferId, _mode: BufferMapMode) {}
+
+    fn is_buffer_mapped(&self, _id: BufferId) -> bool {
+        true
+    }
+
     fn unmap_buffer(&self, _id: BufferId) {}
 
-    fn create_buffer_with_data(&self, buffer_info: BufferInfo, _data: &[u8]) -> BufferId {
+    fn create_buffer_with_data(&self, mut buffer_info: BufferInfo, data: &[u8]) -> BufferId {
+        buffer_info.size = data.len();
         let buffer = BufferId::new();
-        self.add_buffer_info(buffer, buffer_info);
+        self.buffer_info.write().insert(buffer, buffer_info);
+        self.buffer_data.write().insert(buffer, data.to_vec());
         buffer
     }
 
-    fn create_shader_module(&self, _shader: &Shader) -> ShaderId {
-        ShaderId::new()
+    fn create_shader_module(&self, shader: &Shader) -> ShaderId {
+        let id = ShaderId::new();
+        self.shaders.write().insert(id, shader.clone());
+        id
     }
 
     fn remove_buffer(&self, buffer: BufferId) {
         self.buffer_info.write().remove(&buffer);
+        self.buffer_data.write().remove(&buffer);
     }
 
     fn remove_texture(&self, texture: TextureId) {
         self.texture_descriptors.write().remove(&texture);
     }
 
-    fn remove_sampler(&self, _sampler: SamplerId) {}
+    fn remove_sampler(&self, sampler: SamplerId) {
+        self.sampler_descriptors.write().remove(&sampler);
+    }
 
     fn remove_texture_view(&self, texture_view: TextureViewId) {
         self.texture_view_descriptors.write().remove(&texture_view);
     }
 
-    fn create_render_pipeline(
-        &self,
-        _pipeline_descriptor: &RenderPipelineDescriptor,
-    ) -> PipelineId {
-        PipelineId::new()
+    fn create_render_pipeline(&self, pipeline_descriptor: &RenderPipelineDescriptor) -> PipelineId {
+        let pipeline = PipelineId::new();
+        self.render_pipelines
+            .write()
+            .insert(pipeline, pipeline_descriptor.clone());
+        pipeline
     }
 
     fn create_compute_pipeline(
         &self,
-        _pipeline_descriptor: &ComputePipelineDescriptor,
+        pipeline_descriptor: &ComputePipelineDescriptor,
     ) -> PipelineId {
-        PipelineId::new()
+        let pipeline = PipelineId::new();
+        self.compute_pipelines
+            .write()
+            .insert(pipeline, pipeline_descriptor.clone());
+        pipeline
     }
 
     fn create_bind_group(
         &self,
-        _bind_group_descriptor_id: BindGroupDescriptorId,
-        _bind_group: &BindGroup,
+        bind_group_descriptor_id: BindGroupDescriptorId,
+        bind_group: &BindGroup,
     ) {
+        self.bind_groups
+            .write()
+            .entry(bind_group.id)
+            .or_insert_with(|| (bind_group_descriptor_id, bind_group.clone()));
     }
 
-    fn clear_bind_groups(&self) {}
+    fn clear_bind_groups(&self) {
+        self.bind_groups.write().clear();
+    }
 
     fn get_buffer_info(&self, buffer: BufferId) -> Option<BufferInfo> {
         self.buffer_info.read().get(&buffer).cloned()
@@ -143,22 +327,80 @@ impl RenderResourceContext for HeadlessRenderResourceContext {
 
     fn bind_group_descriptor_exists(
         &self,
-        _bind_group_descriptor_id: BindGroupDescriptorId,
+        bind_group_descriptor_id: BindGroupDescriptorId,
     ) -> bool {
-        false
+        let render_pipelines = self.render_pipelines.read();
+        let compute_pipelines = self.compute_pipelines.read();
+        render_pipelines
+            .values()
+            .map(|pipeline| &pipeline.layout)
+            .chain(compute_pipelines.values().map(|pipeline| &pipeline.layout))
+            .flat_map(|layout| layout.bind_groups.iter())
+            .any(|bind_group| bind_group.id == bind_group_descriptor_id)
     }
 
     fn get_aligned_uniform_size(&self, size: usize, _dynamic: bool) -> usize {
         size
     }
 
+    fn get_max_uniform_buffer_binding_size(&self) -> usize {
+        DEFAULT_MAX_UNIFORM_BUFFER_BINDING_SIZE
+    }
+
+    fn get_max_sampled_textures_per_shader_stage(&self) -> u32 {
+        u32::MAX
+    }
+
     fn get_aligned_texture_size(&self, size: usize) -> usize {
         size
     }
 
+    fn features(&self) -> RenderFeatures {
+        RenderFeatures::all()
+    }
+
     fn remove_stale_bind_groups(&self) {}
 
     fn next_swap_chain_texture(&self, _descriptor: &SwapChainDescriptor) -> TextureViewId {
-        TextureViewId::new()
+        let texture_view = TextureViewId::new();
+        self.texture_view_descriptors
+            .write()
+            .insert(texture_view, TextureViewDescriptor::default());
+        texture_view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeadlessRenderResourceContext;
+    use crate::{
+        render_resource::{BufferInfo, BufferUsage},
+        renderer::RenderResourceContext,
+    };
+
+    #[test]
+    fn buffer_data_is_kept() {
+        let context = HeadlessRenderResourceContext::default();
+        let buffer = context.create_buffer(BufferInfo {
+            size: 4,
+            buffer_usage: BufferUsage::MAP_WRITE,
+            ..Default::default()
+        });
+        context.write_mapped_buffer(buffer, 1..3, &mut |data, _| {
+            data.copy_from_slice(&[1, 2]);
+        });
+        assert_eq!(context.get_buffer_data(buffer), Some(vec![0, 1, 2, 0]));
+        context.write_buffer(buffer, 0, &[5, 6, 7, 8]);
+        assert_eq!(context.get_buffer_data(buffer), Some(vec![5, 6, 7, 8]));
+
+        let staging = context.create_buffer_with_data(Default::default(), &[3, 4]);
+        assert_eq!(context.get_buffer_info(staging).unwrap().size, 2);
+        context.remove_buffer(staging);
+        assert_eq!(context.buffer_count(), 1);
+
+        let slice = context.write_staging_belt(4, &mut |data| data.copy_from_slice(&[9; 4]));
+        assert_eq!(context.get_buffer_data(slice.buffer), Some(vec![9; 4]));
+        context.recall_staging_belt();
+        assert_eq!(context.buffer_count(), 1);
     }
 }
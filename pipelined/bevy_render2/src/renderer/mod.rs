@@ -1,7 +1,11 @@
 mod headless_render_resource_context;
+mod pending;
 mod render_context;
+mod render_error;
 mod render_resource_context;
 
 pub use headless_render_resource_context::*;
+pub use pending::*;
 pub use render_context::*;
+pub use render_error::*;
 pub use render_resource_context::*;
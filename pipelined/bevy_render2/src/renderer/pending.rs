@@ -0,0 +1,144 @@
+use crate::renderer::RenderResources;
+use bevy_app::App;
+use bevy_ecs::world::{FromWorld, World};
+
+type PollRenderer = Box<dyn FnMut(&mut World, &mut App) -> bool + Send + Sync>;
+type RenderInit = Box<dyn FnOnce(&mut App) + Send + Sync>;
+
+/// Inserted into the app world by render backends that can't create their device while the app
+/// is built, e.g. on the web, where the device is requested asynchronously. While it is pending,
+/// the [`RenderApp`](crate::RenderApp) isn't updated, so frames are skipped, and the app world
+/// has no [`RenderResources`].
+pub struct RendererPending {
+    poll: PollRenderer,
+}
+
+impl RendererPending {
+    /// `poll` is called with the app world and the render app before each frame until it returns
+    /// `true`, which it does once it inserted the [`RenderResources`] into both.
+    pub fn new(poll: impl FnMut(&mut World, &mut App) -> bool + Send + Sync + 'static) -> Self {
+        RendererPending {
+            poll: Box::new(poll),
+        }
+    }
+
+    /// Returns whether the renderer is ready, running the inits added with
+    /// [`RenderInitAppExt::add_render_init`] once it is.
+    pub(crate) fn poll(&mut self, app_world: &mut World, render_app: &mut App) -> bool {
+        if !(self.poll)(app_world, render_app) {
+            return false;
+        }
+        run_pending_render_inits(render_app);
+        true
+    }
+}
+
+/// The inits of [`RenderInitAppExt::add_render_init`] waiting for the [`RenderResources`].
+#[derive(Default)]
+struct PendingRenderInits {
+    inits: Vec<RenderInit>,
+}
+
+/// Plugin setup of the [`RenderApp`](crate::RenderApp) that needs its [`RenderResources`], such
+/// as compiling shaders. The render backend inserts them when it is built, so setup added before
+/// it, or while the renderer is [pending](RendererPending), is deferred until they exist.
+pub trait RenderInitAppExt {
+    /// Runs `init` on the render app once it has [`RenderResources`]: right away if it has them,
+    /// otherwise when the render backend inserts them, in the order the inits were added.
+    fn add_render_init(&mut self, init: impl FnOnce(&mut App) + Send + Sync + 'static)
+        -> &mut Self;
+
+    /// Initializes the resource `R` of the render app once it has [`RenderResources`], see
+    /// [`add_render_init`](RenderInitAppExt::add_render_init).
+    fn init_render_resource<R: FromWorld + Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl RenderInitAppExt for App {
+    fn add_render_init(
+        &mut self,
+        init: impl FnOnce(&mut App) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let ready = self.world.contains_resource::<RenderResources>()
+            && !self.world.contains_resource::<PendingRenderInits>();
+        if ready {
+            init(self);
+        } else {
+            self.world
+                .get_resource_or_insert_with(PendingRenderInits::default)
+                .inits
+                .push(Box::new(init));
+        }
+        self
+    }
+
+    fn init_render_resource<R: FromWorld + Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.add_render_init(|render_app| {
+            render_app.init_resource::<R>();
+        })
+    }
+}
+
+/// Runs the inits added with [`RenderInitAppExt::add_render_init`] before the render app had
+/// [`RenderResources`]. Render backends call it once they inserted them.
+pub fn run_pending_render_inits(render_app: &mut App) {
+    if let Some(pending) = render_app.world.remove_resource::<PendingRenderInits>() {
+        for init in pending.inits {
+            init(render_app);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_pending_render_inits, RenderInitAppExt, RendererPending};
+    use crate::renderer::{HeadlessRenderResourceContext, RenderResources};
+    use bevy_app::App;
+    use bevy_ecs::world::{FromWorld, World};
+
+    struct Shaders(usize);
+
+    impl FromWorld for Shaders {
+        fn from_world(world: &mut World) -> Self {
+            world.get_resource::<RenderResources>().unwrap();
+            Shaders(1)
+        }
+    }
+
+    #[test]
+    fn render_inits_wait_for_render_resources() {
+        let mut render_app = App::empty();
+        render_app
+            .init_render_resource::<Shaders>()
+            .add_render_init(|render_app| {
+                // runs after the inits added before it
+                render_app.world.get_resource_mut::<Shaders>().unwrap().0 += 1;
+            });
+        assert!(render_app.world.get_resource::<Shaders>().is_none());
+
+        let mut app_world = World::default();
+        let mut polls = 0;
+        let mut pending = RendererPending::new(move |app_world, render_app| {
+            polls += 1;
+            if polls < 2 {
+                return false;
+            }
+            let render_resources =
+                || RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+            app_world.insert_resource(render_resources());
+            render_app.insert_resource(render_resources());
+            true
+        });
+        assert!(!pending.poll(&mut app_world, &mut render_app));
+        assert!(render_app.world.get_resource::<Shaders>().is_none());
+        assert!(pending.poll(&mut app_world, &mut render_app));
+        assert_eq!(render_app.world.get_resource::<Shaders>().unwrap().0, 2);
+
+        // once the render resources exist, inits run right away
+        render_app.add_render_init(|render_app| {
+            render_app.world.get_resource_mut::<Shaders>().unwrap().0 += 1;
+        });
+        assert_eq!(render_app.world.get_resource::<Shaders>().unwrap().0, 3);
+        run_pending_render_inits(&mut render_app);
+        assert_eq!(render_app.world.get_resource::<Shaders>().unwrap().0, 3);
+    }
+}
@@ -49,6 +49,10 @@ pub trait RenderContext {
         destination_mip_level: u32,
         size: Extent3d,
     );
+    /// Fills mip levels `1..` of `texture` by downsampling level 0. The texture must be a 2d
+    /// texture with [`TextureUsage::SAMPLED`](crate::texture::TextureUsage::SAMPLED) and
+    /// [`TextureUsage::RENDER_ATTACHMENT`](crate::texture::TextureUsage::RENDER_ATTACHMENT) usage.
+    fn generate_mipmaps(&mut self, texture: TextureId);
     fn begin_render_pass(
         &mut self,
         pass_descriptor: &PassDescriptor,
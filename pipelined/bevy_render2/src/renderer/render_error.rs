@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+/// An error reported by the render backend, sent as an event in the app world instead of aborting
+/// the process.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} error in {operation} ({}): {message}", .label.as_deref().unwrap_or("unlabeled"))]
+pub struct RenderError {
+    pub kind: RenderErrorKind,
+    /// The backend operation that failed, such as `"create_buffer"` or `"submit"`.
+    pub operation: &'static str,
+    /// The resource the operation was working on, if any.
+    pub label: Option<String>,
+    pub message: String,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderErrorKind {
+    #[error("validation")]
+    Validation,
+    #[error("out of memory")]
+    OutOfMemory,
+}
@@ -3,11 +3,11 @@ use crate::{
         BindGroupDescriptorId, ComputePipelineDescriptor, PipelineId, RenderPipelineDescriptor,
     },
     render_resource::{
-        BindGroup, BufferId, BufferInfo, BufferMapMode, SamplerId, SwapChainDescriptor, TextureId,
-        TextureViewId,
+        BindGroup, BufferId, BufferInfo, BufferMapMode, SamplerId, StagingSlice,
+        SwapChainDescriptor, TextureId, TextureViewId,
     },
     shader::{Shader, ShaderId},
-    texture::{SamplerDescriptor, TextureDescriptor, TextureViewDescriptor},
+    texture::{Extent3d, SamplerDescriptor, TextureDescriptor, TextureViewDescriptor},
 };
 use downcast_rs::{impl_downcast, Downcast};
 use std::ops::{Deref, DerefMut, Range};
@@ -34,6 +34,32 @@ impl DerefMut for RenderResources {
     }
 }
 
+bitflags::bitflags! {
+    /// Optional capabilities of a render backend, see [`RenderResourceContext::features`].
+    pub struct RenderFeatures: u32 {
+        /// Pipelines can set [`PrimitiveState::clamp_depth`](crate::pipeline::PrimitiveState),
+        /// so geometry outside of the depth range is clamped instead of clipped.
+        const DEPTH_CLAMPING = 1;
+        /// Pipelines can set [`PrimitiveState::conservative`](crate::pipeline::PrimitiveState).
+        const CONSERVATIVE_RASTERIZATION = 2;
+        /// Bind group layouts can have arrays of textures, see
+        /// [`BindingDescriptor::count`](crate::pipeline::BindingDescriptor).
+        const SAMPLED_TEXTURE_BINDING_ARRAY = 4;
+        /// Shaders can index arrays of textures with non-constant values, see
+        /// [`BindingArrayCapability::DynamicIndexing`](crate::shader::BindingArrayCapability).
+        const SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING = 8;
+        /// Shaders can index arrays of textures with values that differ between invocations, see
+        /// [`BindingArrayCapability::NonUniformIndexing`](crate::shader::BindingArrayCapability).
+        const SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING = 16;
+        /// Shaders can declare binding arrays without a size, see
+        /// [`BindingArrayCapability::RuntimeArray`](crate::shader::BindingArrayCapability).
+        const UNSIZED_BINDING_ARRAY = 32;
+        /// Pipelines can set a [`PolygonMode`](crate::pipeline::PolygonMode) other than `Fill`,
+        /// to draw the edges or vertices of triangles.
+        const NON_FILL_POLYGON_MODE = 64;
+    }
+}
+
 pub trait RenderResourceContext: Downcast + Send + Sync + 'static {
     fn next_swap_chain_texture(&self, descriptor: &SwapChainDescriptor) -> TextureViewId;
     fn drop_swap_chain_texture(&self, resource: TextureViewId);
@@ -59,7 +85,40 @@ pub trait RenderResourceContext: Downcast + Send + Sync + 'static {
         range: Range<u64>,
         read: &dyn Fn(&[u8], &dyn RenderResourceContext),
     );
+    /// Writes `data` to the buffer at `offset` without a staging buffer, before the commands
+    /// submitted next. This is cheaper than mapping a staging buffer and copying it for small
+    /// per-frame data. The buffer needs [`BufferUsage::COPY_DST`](crate::render_resource::BufferUsage),
+    /// and `offset` and the length of `data` must be multiples of 4.
+    fn write_buffer(&self, id: BufferId, offset: u64, data: &[u8]);
+    /// Hands `write` a CPU-visible slice of `size` bytes in a staging belt: a set of persistently
+    /// reused staging chunks that are recycled once the frame's commands have been executed.
+    /// This avoids creating and mapping a staging buffer per frame for large uploads.
+    ///
+    /// The returned slice is only valid for the current frame, so it must be copied with
+    /// [`RenderContext::copy_buffer_to_buffer`](crate::renderer::RenderContext::copy_buffer_to_buffer)
+    /// by the render graph of the same frame. `size` must be a multiple of 4.
+    fn write_staging_belt(&self, size: u64, write: &mut dyn FnMut(&mut [u8])) -> StagingSlice;
+    /// Writes `data` to the `size` region of the mip level at `origin`, before the commands
+    /// submitted next. Unlike copies from buffers, `bytes_per_row` doesn't have to be aligned, so
+    /// tightly packed CPU images can be written as they are. The texture needs
+    /// [`TextureUsage::COPY_DST`](crate::texture::TextureUsage).
+    fn write_texture(
+        &self,
+        id: TextureId,
+        origin: [u32; 3],
+        mip_level: u32,
+        data: &[u8],
+        bytes_per_row: u32,
+        size: Extent3d,
+    );
     fn map_buffer(&self, id: BufferId, mode: BufferMapMode);
+    /// Starts mapping the buffer without waiting for the GPU to finish the commands that use it.
+    /// The buffer can be read or written once [`RenderResourceContext::is_buffer_mapped`] returns
+    /// `true`, and must not be used by commands until it is unmapped again.
+    fn map_buffer_async(&self, id: BufferId, mode: BufferMapMode);
+    /// Returns whether the mapping started by [`RenderResourceContext::map_buffer_async`] has
+    /// completed, without blocking.
+    fn is_buffer_mapped(&self, id: BufferId) -> bool;
     fn unmap_buffer(&self, id: BufferId);
     fn create_buffer_with_data(&self, buffer_info: BufferInfo, data: &[u8]) -> BufferId;
     fn create_shader_module(&self, shader: &Shader) -> ShaderId;
@@ -69,7 +128,13 @@ pub trait RenderResourceContext: Downcast + Send + Sync + 'static {
     fn remove_texture_view(&self, texture_view: TextureViewId);
     fn get_buffer_info(&self, buffer: BufferId) -> Option<BufferInfo>;
     fn get_aligned_uniform_size(&self, size: usize, dynamic: bool) -> usize;
+    fn get_max_uniform_buffer_binding_size(&self) -> usize;
+    /// The maximum number of textures a shader stage can sample. Binding arrays of textures
+    /// count towards it with their size.
+    fn get_max_sampled_textures_per_shader_stage(&self) -> u32;
     fn get_aligned_texture_size(&self, data_size: usize) -> usize;
+    /// The optional features that are enabled and supported by the backend.
+    fn features(&self) -> RenderFeatures;
     fn create_render_pipeline(&self, pipeline_descriptor: &RenderPipelineDescriptor) -> PipelineId;
     fn create_compute_pipeline(
         &self,
@@ -1,15 +1,21 @@
 #[allow(clippy::module_inception)]
 mod shader;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod shader_cache;
 #[cfg(not(target_arch = "wasm32"))]
 mod shader_reflect;
 
 pub use shader::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use shader_cache::*;
 #[cfg(not(target_arch = "wasm32"))]
 pub use shader_reflect::*;
 
-use crate::pipeline::{BindGroupDescriptor, VertexBufferLayout};
+use crate::pipeline::{
+    BindGroupDescriptor, VertexAttributeMismatch, VertexBufferLayout, VertexLayoutError,
+};
 
 /// Defines the memory layout of a shader
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +25,152 @@ pub struct ShaderLayout {
     pub entry_point: String,
 }
 
+impl ShaderLayout {
+    /// Checks that `vertex_buffers` provide every input of this vertex shader layout exactly once,
+    /// in a compatible format, and that their attributes fit in the buffer strides.
+    ///
+    /// Attributes the shader doesn't read are allowed.
+    pub fn validate_vertex_buffers(
+        &self,
+        vertex_buffers: &[VertexBufferLayout],
+    ) -> Result<(), VertexLayoutError> {
+        let mut mismatches = Vec::new();
+        let attributes = vertex_buffers
+            .iter()
+            .flat_map(|buffer| buffer.attributes.iter().map(move |a| (buffer, a)))
+            .collect::<Vec<_>>();
+
+        for (i, (buffer, attribute)) in attributes.iter().enumerate() {
+            let location = attribute.shader_location;
+            if attributes[..i]
+                .iter()
+                .any(|(_, other)| other.shader_location == location)
+            {
+                mismatches.push(VertexAttributeMismatch::Duplicate { location });
+            }
+            if buffer.stride != 0 && attribute.offset + attribute.format.get_size() > buffer.stride
+            {
+                mismatches.push(VertexAttributeMismatch::OutOfBounds {
+                    location,
+                    offset: attribute.offset,
+                    stride: buffer.stride,
+                });
+            }
+        }
+
+        for input in self
+            .vertex_buffer_layout
+            .iter()
+            .flat_map(|buffer| buffer.attributes.iter())
+        {
+            let location = input.shader_location;
+            match attributes
+                .iter()
+                .find(|(_, attribute)| attribute.shader_location == location)
+            {
+                None => mismatches.push(VertexAttributeMismatch::Missing {
+                    location,
+                    name: input.name.to_string(),
+                    expected: input.format,
+                }),
+                Some((_, attribute)) if !attribute.format.is_compatible_with(input.format) => {
+                    mismatches.push(VertexAttributeMismatch::Format {
+                        location,
+                        name: input.name.to_string(),
+                        expected: input.format,
+                        actual: attribute.format,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(VertexLayoutError { mismatches })
+        }
+    }
+}
+
 pub const GL_VERTEX_INDEX: &str = "gl_VertexIndex";
 pub const GL_INSTANCE_INDEX: &str = "gl_InstanceIndex";
 pub const GL_FRONT_FACING: &str = "gl_FrontFacing";
+
+#[cfg(test)]
+mod tests {
+    use super::ShaderLayout;
+    use crate::pipeline::{
+        InputStepMode, VertexAttribute, VertexAttributeMismatch, VertexBufferLayout, VertexFormat,
+    };
+
+    #[test]
+    fn validate_vertex_buffers() {
+        let input = |location: u32, name: &'static str, format: VertexFormat| {
+            VertexBufferLayout::new_from_attribute(
+                VertexAttribute {
+                    name: name.into(),
+                    format,
+                    offset: 0,
+                    shader_location: location,
+                },
+                InputStepMode::Vertex,
+            )
+        };
+        let shader_layout = ShaderLayout {
+            bind_groups: Vec::new(),
+            vertex_buffer_layout: vec![
+                input(0, "Vertex_Position", VertexFormat::Float32x3),
+                input(1, "Vertex_Color", VertexFormat::Float32x4),
+                input(2, "Vertex_Uv", VertexFormat::Float32x2),
+            ],
+            entry_point: "main".to_string(),
+        };
+
+        let layout = VertexBufferLayout::build("Vertex")
+            .add_attribute(0, "Vertex_Position", VertexFormat::Float32x3)
+            .add_padding(4)
+            .add_attribute(1, "Vertex_Color", VertexFormat::Unorm8x4)
+            .add_attribute(2, "Vertex_Uv", VertexFormat::Float32x2)
+            .finish();
+        assert_eq!(layout.stride, 28);
+        assert_eq!(layout.attributes[2].offset, 20);
+        assert_eq!(shader_layout.validate_vertex_buffers(&[layout]), Ok(()));
+
+        let mut layout = VertexBufferLayout::build("Vertex")
+            .add_attribute(0, "Vertex_Position", VertexFormat::Float32x2)
+            .add_attribute(0, "Vertex_Color", VertexFormat::Float32x4)
+            .finish();
+        layout.stride = 16;
+        let error = shader_layout
+            .validate_vertex_buffers(&[layout])
+            .unwrap_err();
+        assert_eq!(
+            error.mismatches,
+            vec![
+                VertexAttributeMismatch::Duplicate { location: 0 },
+                VertexAttributeMismatch::OutOfBounds {
+                    location: 0,
+                    offset: 8,
+                    stride: 16
+                },
+                VertexAttributeMismatch::Format {
+                    location: 0,
+                    name: "Vertex_Position".to_string(),
+                    expected: VertexFormat::Float32x3,
+                    actual: VertexFormat::Float32x2,
+                },
+                VertexAttributeMismatch::Missing {
+                    location: 1,
+                    name: "Vertex_Color".to_string(),
+                    expected: VertexFormat::Float32x4,
+                },
+                VertexAttributeMismatch::Missing {
+                    location: 2,
+                    name: "Vertex_Uv".to_string(),
+                    expected: VertexFormat::Float32x2,
+                },
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use super::{ShaderLayout, ShaderReflectOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use super::ShaderCache;
+use super::{ShaderLayout, ShaderReflectError, ShaderReflectOptions};
 use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
 use bevy_reflect::{TypeUuid, Uuid};
 use bevy_utils::{tracing::error, BoxedFuture};
@@ -72,6 +74,16 @@ impl From<ShaderStage> for bevy_glsl_to_spirv::ShaderType {
     }
 }
 
+#[cfg(any(
+    all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"),
+    all(target_arch = "x86_64", target_os = "macos"),
+    all(target_arch = "aarch64", target_os = "android"),
+    all(target_arch = "armv7", target_os = "androidabi"),
+    all(target_arch = "x86_64", target_os = "windows", target_env = "msvc"),
+))]
+/// The GLSL compiler of this target and its version, part of the [`ShaderCache`] key.
+pub(crate) const GLSL_COMPILER: &str = "bevy-glsl-to-spirv 0.2";
+
 #[cfg(any(
     all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"),
     all(target_arch = "x86_64", target_os = "macos"),
@@ -106,6 +118,17 @@ impl Into<shaderc::ShaderKind> for ShaderStage {
     }
 }
 
+#[cfg(not(any(
+    target_arch = "wasm32",
+    all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"),
+    all(target_arch = "x86_64", target_os = "macos"),
+    all(target_arch = "aarch64", target_os = "android"),
+    all(target_arch = "armv7", target_os = "androidabi"),
+    all(target_arch = "x86_64", target_os = "windows", target_env = "msvc"),
+)))]
+/// The GLSL compiler of this target and its version, part of the [`ShaderCache`] key.
+pub(crate) const GLSL_COMPILER: &str = "shaderc 0.7";
+
 #[cfg(not(any(
     target_arch = "wasm32",
     all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"),
@@ -164,6 +187,42 @@ impl ShaderSource {
     }
 }
 
+/// A way a shader indexes binding arrays that needs a device feature, declared as a capability
+/// of its SPIR-V module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingArrayCapability {
+    /// Arrays of textures are indexed with values that are uniform across an invocation group,
+    /// but not constant.
+    DynamicIndexing,
+    /// Arrays of textures are indexed with values that differ between invocations, with
+    /// `nonuniformEXT`. This is what bindless texturing usually needs.
+    NonUniformIndexing,
+    /// Arrays are declared without a size, like `texture2D textures[]`.
+    RuntimeArray,
+}
+
+/// Returns the [`BindingArrayCapability`]s declared by a SPIR-V module.
+pub fn binding_array_capabilities(spirv: &[u32]) -> Vec<BindingArrayCapability> {
+    const OP_CAPABILITY: u32 = 17;
+    let mut capabilities = Vec::new();
+    // the capabilities are the first instructions after the 5 words of the header
+    let mut words = spirv.get(5..).unwrap_or(&[]);
+    while let Some(&instruction) = words.first() {
+        let word_count = (instruction >> 16) as usize;
+        if instruction & 0xffff != OP_CAPABILITY || !(2..=words.len()).contains(&word_count) {
+            break;
+        }
+        match words[1] {
+            29 => capabilities.push(BindingArrayCapability::DynamicIndexing),
+            5302 => capabilities.push(BindingArrayCapability::RuntimeArray),
+            5307 => capabilities.push(BindingArrayCapability::NonUniformIndexing),
+            _ => {}
+        }
+        words = &words[word_count..];
+    }
+    capabilities
+}
+
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub struct ShaderId(Uuid);
 
@@ -216,7 +275,12 @@ impl Shader {
     pub fn get_spirv(&self, macros: Option<&[String]>) -> Result<Vec<u32>, ShaderError> {
         match self.source {
             ShaderSource::Spirv(ref bytes) => Ok(bytes.clone()),
-            ShaderSource::Glsl(ref source) => glsl_to_spirv(&source, self.stage, macros),
+            ShaderSource::Glsl(ref source) => match ShaderCache::get() {
+                Some(cache) => cache.get_or_compile(source, self.stage, macros, || {
+                    glsl_to_spirv(&source, self.stage, macros)
+                }),
+                None => glsl_to_spirv(&source, self.stage, macros),
+            },
         }
     }
 
@@ -240,6 +304,19 @@ impl Shader {
         }
     }
 
+    /// Like [`Shader::reflect_layout`], but returns an error instead of panicking when the shader
+    /// isn't SPIR-V or can't be reflected.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn try_reflect_layout(
+        &self,
+        options: &ShaderReflectOptions,
+    ) -> Result<ShaderLayout, ShaderReflectError> {
+        match self.source {
+            ShaderSource::Spirv(ref spirv) => ShaderLayout::try_from_spirv(spirv, options),
+            _ => Err(ShaderReflectError::NotSpirv),
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn reflect_layout(&self, _enforce_bevy_conventions: bool) -> Option<ShaderLayout> {
         panic!("Cannot reflect layout on wasm32.");
@@ -290,3 +367,29 @@ impl AssetLoader for ShaderLoader {
         &["vert", "frag", "spv"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{binding_array_capabilities, BindingArrayCapability};
+
+    #[test]
+    fn binding_array_capabilities_are_read_from_header() {
+        let capability = |capability| vec![(2 << 16) | 17, capability];
+        let mut spirv = vec![0x0723_0203, 0x0001_0000, 0, 16, 0];
+        // Shader, RuntimeDescriptorArray, SampledImageArrayNonUniformIndexing
+        spirv.extend(capability(1));
+        spirv.extend(capability(5302));
+        spirv.extend(capability(5307));
+        // an OpExtension ends the capabilities
+        spirv.extend(vec![(3 << 16) | 10, 0, 0]);
+        spirv.extend(capability(29));
+        assert_eq!(
+            binding_array_capabilities(&spirv),
+            vec![
+                BindingArrayCapability::RuntimeArray,
+                BindingArrayCapability::NonUniformIndexing
+            ]
+        );
+        assert!(binding_array_capabilities(&[]).is_empty());
+    }
+}
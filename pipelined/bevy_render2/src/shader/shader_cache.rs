@@ -0,0 +1,180 @@
+use super::{ShaderError, ShaderStage, GLSL_COMPILER};
+use bevy_utils::tracing::warn;
+use once_cell::sync::OnceCell;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+static SHADER_CACHE: OnceCell<ShaderCache> = OnceCell::new();
+
+/// The first word of every SPIR-V module, used to reject truncated or foreign cache files.
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+
+/// Stores the SPIR-V compiled from GLSL shaders in a directory, so later runs skip compiling the
+/// shaders they have compiled before. Entries are keyed by a hash of the source, the stage, the
+/// shader defs, the GLSL compiler and the version of this crate. SPIR-V doesn't depend on the
+/// backend or adapter, so they aren't part of the key. Entries from other compiler or engine
+/// versions are ignored, and are never removed.
+///
+/// Once [installed](ShaderCache::install), it is used by [`Shader::get_spirv`](super::Shader::get_spirv),
+/// which render backends call when they create shader modules. `WgpuPlugin` installs one when a
+/// cache directory is set in its options. Only the GLSL to SPIR-V compilation is cached: the
+/// backend compilation of SPIR-V, by wgpu and the driver, still runs on every start, since wgpu
+/// doesn't expose its results.
+pub struct ShaderCache {
+    directory: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    logged_write_error: AtomicBool,
+}
+
+impl ShaderCache {
+    /// Creates a cache in `directory`, which is created on the first write.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        ShaderCache {
+            directory: directory.into(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            logged_write_error: AtomicBool::new(false),
+        }
+    }
+
+    /// Makes this the cache of the process. Returns `false` if a cache is already installed, which
+    /// is then kept.
+    pub fn install(self) -> bool {
+        SHADER_CACHE.set(self).is_ok()
+    }
+
+    /// Returns the installed cache, if any.
+    pub fn get() -> Option<&'static ShaderCache> {
+        SHADER_CACHE.get()
+    }
+
+    /// The number of shaders read from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of shaders compiled because they weren't in the cache.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the cached SPIR-V of the GLSL `source`, or compiles it with `compile` and caches
+    /// the result. Failing to read or write the cache only costs the compilation.
+    pub fn get_or_compile(
+        &self,
+        source: &str,
+        stage: ShaderStage,
+        shader_defs: Option<&[String]>,
+        compile: impl FnOnce() -> Result<Vec<u32>, ShaderError>,
+    ) -> Result<Vec<u32>, ShaderError> {
+        let path = self
+            .directory
+            .join(format!("{:016x}.spv", self.key(source, stage, shader_defs)));
+        if let Some(spirv) = fs::read(&path).ok().and_then(|bytes| read_spirv(&bytes)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(spirv);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let spirv = compile()?;
+        if let Err(err) = self.write(&path, &spirv) {
+            if !self.logged_write_error.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Failed to write the shader cache in {}: {}",
+                    self.directory.display(),
+                    err
+                );
+            }
+        }
+        Ok(spirv)
+    }
+
+    fn key(&self, source: &str, stage: ShaderStage, shader_defs: Option<&[String]>) -> u64 {
+        // FNV-1a, since the key has to be stable across runs and compiler versions
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+            // separates consecutive fields, so their boundaries affect the hash
+            hash ^= 0xff;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        };
+        write(env!("CARGO_PKG_VERSION").as_bytes());
+        write(GLSL_COMPILER.as_bytes());
+        write(&[stage as u8]);
+        for shader_def in shader_defs.unwrap_or_default() {
+            write(shader_def.as_bytes());
+        }
+        write(source.as_bytes());
+        hash
+    }
+
+    fn write(&self, path: &std::path::Path, spirv: &[u32]) -> std::io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        let mut bytes = Vec::with_capacity(spirv.len() * 4);
+        for word in spirv.iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        // renamed into place, so other processes never read a partially written file
+        let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temporary_path, bytes)?;
+        fs::rename(&temporary_path, path)
+    }
+}
+
+fn read_spirv(bytes: &[u8]) -> Option<Vec<u32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let words = bytes
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect::<Vec<u32>>();
+    if words.first() == Some(&SPIRV_MAGIC_NUMBER) {
+        Some(words)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderCache, SPIRV_MAGIC_NUMBER};
+    use crate::shader::ShaderStage;
+
+    #[test]
+    fn compiles_each_shader_once() {
+        let directory =
+            std::env::temp_dir().join(format!("bevy_shader_cache_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let spirv = vec![SPIRV_MAGIC_NUMBER, 1, 2, 3];
+        let compile = |cache: &ShaderCache, shader_defs: Option<&[String]>| {
+            let mut compiled = false;
+            let result = cache
+                .get_or_compile("void main() {}", ShaderStage::Vertex, shader_defs, || {
+                    compiled = true;
+                    Ok(spirv.clone())
+                })
+                .unwrap();
+            assert_eq!(result, spirv);
+            compiled
+        };
+
+        let cache = ShaderCache::new(&directory);
+        assert!(compile(&cache, None));
+        assert!(!compile(&cache, None));
+        assert!(compile(&cache, Some(&["DEF".to_string()])));
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        // a new run reads the files written by the previous one
+        assert!(!compile(&ShaderCache::new(&directory), None));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -7,16 +7,41 @@ use crate::{
         UniformProperty, VertexAttribute, VertexBufferLayout, VertexFormat,
     },
     shader::{ShaderLayout, GL_FRONT_FACING, GL_INSTANCE_INDEX, GL_VERTEX_INDEX},
-    texture::{TextureSampleType, TextureViewDimension},
+    texture::{StorageTextureAccess, TextureFormat, TextureSampleType, TextureViewDimension},
 };
 use bevy_core::cast_slice;
 use spirv_reflect::{
     types::{
         ReflectDescriptorBinding, ReflectDescriptorSet, ReflectDescriptorType, ReflectDimension,
-        ReflectShaderStageFlags, ReflectTypeDescription, ReflectTypeFlags,
+        ReflectImageFormat, ReflectShaderStageFlags, ReflectTypeDescription, ReflectTypeFlags,
     },
     ShaderModule,
 };
+use thiserror::Error;
+
+/// An error that occurred while reflecting the layout of a shader, e.g. because it uses a binding
+/// the renderer doesn't support.
+#[derive(Error, Debug)]
+pub enum ShaderReflectError {
+    #[error("only SPIR-V shaders can be reflected, compile the shader to SPIR-V first")]
+    NotSpirv,
+    #[error("invalid SPIR-V module: {0}")]
+    InvalidModule(&'static str),
+    #[error("the binding {0} has no type description")]
+    MissingTypeDescription(String),
+    #[error("the size of the binding array {0} must be set in ShaderReflectOptions::array_sizes")]
+    UnsizedArray(String),
+    #[error("unsupported image dimension {0}")]
+    UnsupportedDimension(String),
+    #[error("unsupported storage texture format {0}")]
+    UnsupportedStorageTextureFormat(String),
+    #[error("unsupported bind type {0}")]
+    UnsupportedBindType(String),
+    #[error("only one specified shader stage is supported, got {0}")]
+    UnsupportedShaderStage(String),
+    #[error("unsupported type {0}")]
+    UnsupportedType(String),
+}
 
 /// Options to control shader layout reflection.
 #[derive(Debug, Clone)]
@@ -24,6 +49,9 @@ pub struct ShaderReflectOptions {
     pub bevy_conventions: bool,
     /// Map from shader binding name to size of array.
     pub array_sizes: HashMap<String, NonZeroU32>,
+    /// Map from storage texture binding name to its access. The `readonly` and `writeonly`
+    /// qualifiers aren't reflected, so storage textures missing from it are write only.
+    pub storage_texture_access: HashMap<String, StorageTextureAccess>,
 }
 
 impl Default for ShaderReflectOptions {
@@ -31,26 +59,43 @@ impl Default for ShaderReflectOptions {
         ShaderReflectOptions {
             bevy_conventions: true,
             array_sizes: HashMap::default(),
+            storage_texture_access: HashMap::default(),
         }
     }
 }
 
 impl ShaderLayout {
     pub fn from_spirv(spirv_data: &[u32], options: &ShaderReflectOptions) -> ShaderLayout {
+        Self::try_from_spirv(spirv_data, options)
+            .unwrap_or_else(|err| panic!("Failed to reflect shader layout: {}.", err))
+    }
+
+    /// Like [`ShaderLayout::from_spirv`], but returns an error instead of panicking when the
+    /// shader can't be reflected.
+    pub fn try_from_spirv(
+        spirv_data: &[u32],
+        options: &ShaderReflectOptions,
+    ) -> Result<ShaderLayout, ShaderReflectError> {
         match ShaderModule::load_u8_data(cast_slice(spirv_data)) {
             Ok(ref mut module) => {
                 // init
                 let entry_point_name = module.get_entry_point_name();
                 let shader_stage = module.get_shader_stage();
                 let mut bind_groups = Vec::new();
-                for descriptor_set in module.enumerate_descriptor_sets(None).unwrap() {
-                    let bind_group = reflect_bind_group(&descriptor_set, shader_stage, options);
+                for descriptor_set in module
+                    .enumerate_descriptor_sets(None)
+                    .map_err(ShaderReflectError::InvalidModule)?
+                {
+                    let bind_group = reflect_bind_group(&descriptor_set, shader_stage, options)?;
                     bind_groups.push(bind_group);
                 }
 
                 // obtain attribute descriptors from reflection
                 let mut vertex_attributes = Vec::new();
-                for input_variable in module.enumerate_input_variables(None).unwrap() {
+                for input_variable in module
+                    .enumerate_input_variables(None)
+                    .map_err(ShaderReflectError::InvalidModule)?
+                {
                     if input_variable.name == GL_VERTEX_INDEX
                         || input_variable.name == GL_INSTANCE_INDEX
                         || input_variable.name == GL_FRONT_FACING
@@ -61,8 +106,12 @@ impl ShaderLayout {
                     vertex_attributes.push(VertexAttribute {
                         name: input_variable.name.clone().into(),
                         format: reflect_vertex_format(
-                            input_variable.type_description.as_ref().unwrap(),
-                        ),
+                            input_variable.type_description.as_ref().ok_or_else(|| {
+                                ShaderReflectError::MissingTypeDescription(
+                                    input_variable.name.clone(),
+                                )
+                            })?,
+                        )?,
                         offset: 0,
                         shader_location: input_variable.location,
                     });
@@ -100,13 +149,13 @@ impl ShaderLayout {
                     });
                 }
 
-                ShaderLayout {
+                Ok(ShaderLayout {
                     bind_groups,
                     vertex_buffer_layout,
                     entry_point: entry_point_name,
-                }
+                })
             }
-            Err(err) => panic!("Failed to reflect shader layout: {:?}.", err),
+            Err(err) => Err(ShaderReflectError::InvalidModule(err)),
         }
     }
 }
@@ -115,21 +164,21 @@ fn reflect_bind_group(
     descriptor_set: &ReflectDescriptorSet,
     shader_stage: ReflectShaderStageFlags,
     options: &ShaderReflectOptions,
-) -> BindGroupDescriptor {
+) -> Result<BindGroupDescriptor, ShaderReflectError> {
     let mut bindings = Vec::new();
     for descriptor_binding in descriptor_set.bindings.iter() {
-        let binding = reflect_binding(descriptor_binding, shader_stage, options);
+        let binding = reflect_binding(descriptor_binding, shader_stage, options)?;
         bindings.push(binding);
     }
 
-    BindGroupDescriptor::new(descriptor_set.set, bindings)
+    Ok(BindGroupDescriptor::new(descriptor_set.set, bindings))
 }
 
 fn reflect_dimension(
     type_description: &ReflectTypeDescription,
-) -> TextureViewDimension {
+) -> Result<TextureViewDimension, ShaderReflectError> {
     let arrayed = type_description.traits.image.arrayed > 0;
-    match type_description.traits.image.dim {
+    Ok(match type_description.traits.image.dim {
         ReflectDimension::Type1d => TextureViewDimension::D1,
         ReflectDimension::Type2d => {
             if arrayed {
@@ -146,36 +195,93 @@ fn reflect_dimension(
                 TextureViewDimension::Cube
             }
         }
-        dimension => panic!("Unsupported image dimension: {:?}.", dimension),
-    }
+        dimension => {
+            return Err(ShaderReflectError::UnsupportedDimension(format!(
+                "{:?}",
+                dimension
+            )))
+        }
+    })
+}
+
+fn reflect_storage_texture_format(
+    type_description: &ReflectTypeDescription,
+) -> Result<TextureFormat, ShaderReflectError> {
+    Ok(match type_description.traits.image.image_format {
+        ReflectImageFormat::R32_UINT => TextureFormat::R32Uint,
+        ReflectImageFormat::R32_INT => TextureFormat::R32Sint,
+        ReflectImageFormat::R32_FLOAT => TextureFormat::R32Float,
+        ReflectImageFormat::RG32_UINT => TextureFormat::Rg32Uint,
+        ReflectImageFormat::RG32_INT => TextureFormat::Rg32Sint,
+        ReflectImageFormat::RG32_FLOAT => TextureFormat::Rg32Float,
+        ReflectImageFormat::RGBA8 => TextureFormat::Rgba8Unorm,
+        ReflectImageFormat::RGBA8_SNORM => TextureFormat::Rgba8Snorm,
+        ReflectImageFormat::RGBA8_UINT => TextureFormat::Rgba8Uint,
+        ReflectImageFormat::RGBA8_INT => TextureFormat::Rgba8Sint,
+        ReflectImageFormat::RGBA16_UINT => TextureFormat::Rgba16Uint,
+        ReflectImageFormat::RGBA16_INT => TextureFormat::Rgba16Sint,
+        ReflectImageFormat::RGBA16_FLOAT => TextureFormat::Rgba16Float,
+        ReflectImageFormat::RGBA32_UINT => TextureFormat::Rgba32Uint,
+        ReflectImageFormat::RGBA32_INT => TextureFormat::Rgba32Sint,
+        ReflectImageFormat::RGBA32_FLOAT => TextureFormat::Rgba32Float,
+        format => {
+            return Err(ShaderReflectError::UnsupportedStorageTextureFormat(
+                format!("{:?}", format),
+            ))
+        }
+    })
 }
 
 fn reflect_binding(
     binding: &ReflectDescriptorBinding,
     shader_stage: ReflectShaderStageFlags,
     options: &ShaderReflectOptions,
-) -> BindingDescriptor {
-    let type_description = binding.type_description.as_ref().unwrap();
-    let array_size = options.array_sizes.get(&binding.name).copied();
+) -> Result<BindingDescriptor, ShaderReflectError> {
+    let type_description = binding
+        .type_description
+        .as_ref()
+        .ok_or_else(|| ShaderReflectError::MissingTypeDescription(binding.name.clone()))?;
+    // arrays declared without a size, like `texture2D textures[]`, are reflected with a count of
+    // 0 and must be sized with the options
+    let array_size = match options.array_sizes.get(&binding.name) {
+        Some(size) => Some(*size),
+        None if binding.array.dims.is_empty() => None,
+        None => Some(
+            NonZeroU32::new(binding.count)
+                .ok_or_else(|| ShaderReflectError::UnsizedArray(binding.name.clone()))?,
+        ),
+    };
 
     let (name, bind_type) = match binding.descriptor_type {
         ReflectDescriptorType::UniformBuffer => (
             &type_description.type_name,
             BindType::Uniform {
                 has_dynamic_offset: false,
-                property: reflect_uniform(type_description),
+                property: reflect_uniform(type_description)?,
             },
         ),
         ReflectDescriptorType::SampledImage => {
             (
                 &binding.name,
                 BindType::Texture {
-                    view_dimension: reflect_dimension(type_description),
+                    view_dimension: reflect_dimension(type_description)?,
                     sample_type: TextureSampleType::Float { filterable: true },
                     multisampled: false,
                 },
             )
         }
+        ReflectDescriptorType::StorageImage => (
+            &binding.name,
+            BindType::StorageTexture {
+                access: options
+                    .storage_texture_access
+                    .get(&binding.name)
+                    .copied()
+                    .unwrap_or(StorageTextureAccess::WriteOnly),
+                format: reflect_storage_texture_format(type_description)?,
+                view_dimension: reflect_dimension(type_description)?,
+            },
+        ),
         ReflectDescriptorType::StorageBuffer => (
             &type_description.type_name,
             BindType::StorageBuffer {
@@ -192,23 +298,33 @@ fn reflect_binding(
                 filtering: true,
             },
         ),
-        _ => panic!("Unsupported bind type {:?}.", binding.descriptor_type),
+        descriptor_type => {
+            return Err(ShaderReflectError::UnsupportedBindType(format!(
+                "{:?}",
+                descriptor_type
+            )))
+        }
     };
 
     let shader_stage = match shader_stage {
         ReflectShaderStageFlags::COMPUTE => BindingShaderStage::COMPUTE,
         ReflectShaderStageFlags::VERTEX => BindingShaderStage::VERTEX,
         ReflectShaderStageFlags::FRAGMENT => BindingShaderStage::FRAGMENT,
-        _ => panic!("Only one specified shader stage is supported."),
+        shader_stage => {
+            return Err(ShaderReflectError::UnsupportedShaderStage(format!(
+                "{:?}",
+                shader_stage
+            )))
+        }
     };
 
-    BindingDescriptor {
+    Ok(BindingDescriptor {
         index: binding.binding,
         bind_type,
         name: name.to_string(),
         shader_stage,
         count: array_size,
-    }
+    })
 }
 
 #[derive(Debug)]
@@ -218,7 +334,9 @@ enum NumberType {
     Float,
 }
 
-fn reflect_uniform(type_description: &ReflectTypeDescription) -> UniformProperty {
+fn reflect_uniform(
+    type_description: &ReflectTypeDescription,
+) -> Result<UniformProperty, ShaderReflectError> {
     if type_description
         .type_flags
         .contains(ReflectTypeFlags::STRUCT)
@@ -229,31 +347,48 @@ fn reflect_uniform(type_description: &ReflectTypeDescription) -> UniformProperty
     }
 }
 
-fn reflect_uniform_struct(type_description: &ReflectTypeDescription) -> UniformProperty {
+fn reflect_uniform_struct(
+    type_description: &ReflectTypeDescription,
+) -> Result<UniformProperty, ShaderReflectError> {
     let mut properties = Vec::new();
     for member in type_description.members.iter() {
-        properties.push(reflect_uniform(member));
+        properties.push(reflect_uniform(member)?);
     }
 
-    UniformProperty::Struct(properties)
+    Ok(UniformProperty::Struct(properties))
 }
 
-fn reflect_uniform_numeric(type_description: &ReflectTypeDescription) -> UniformProperty {
+fn reflect_number_type(
+    type_description: &ReflectTypeDescription,
+) -> Result<NumberType, ShaderReflectError> {
     let traits = &type_description.traits;
-    let number_type = if type_description.type_flags.contains(ReflectTypeFlags::INT) {
+    if type_description.type_flags.contains(ReflectTypeFlags::INT) {
         match traits.numeric.scalar.signedness {
-            0 => NumberType::UInt,
-            1 => NumberType::Int,
-            signedness => panic!("Unexpected signedness {}.", signedness),
+            0 => Ok(NumberType::UInt),
+            1 => Ok(NumberType::Int),
+            signedness => Err(ShaderReflectError::UnsupportedType(format!(
+                "with signedness {}",
+                signedness
+            ))),
         }
     } else if type_description
         .type_flags
         .contains(ReflectTypeFlags::FLOAT)
     {
-        NumberType::Float
+        Ok(NumberType::Float)
     } else {
-        panic!("Unexpected type flag {:?}.", type_description.type_flags);
-    };
+        Err(ShaderReflectError::UnsupportedType(format!(
+            "{:?}",
+            type_description.type_flags
+        )))
+    }
+}
+
+fn reflect_uniform_numeric(
+    type_description: &ReflectTypeDescription,
+) -> Result<UniformProperty, ShaderReflectError> {
+    let traits = &type_description.traits;
+    let number_type = reflect_number_type(type_description)?;
 
     // TODO: handle scalar width here
 
@@ -266,15 +401,14 @@ fn reflect_uniform_numeric(type_description: &ReflectTypeDescription) -> Uniform
             traits.numeric.matrix.column_count,
             traits.numeric.matrix.row_count,
         ) {
-            (NumberType::Float, 3, 3) => UniformProperty::Mat3,
-            (NumberType::Float, 4, 4) => UniformProperty::Mat4,
-            (number_type, column_count, row_count) => panic!(
-                "unexpected uniform property matrix format {:?} {}x{}",
-                number_type, column_count, row_count
-            ),
+            (NumberType::Float, 3, 3) => Ok(UniformProperty::Mat3),
+            (NumberType::Float, 4, 4) => Ok(UniformProperty::Mat4),
+            (number_type, column_count, row_count) => Err(ShaderReflectError::UnsupportedType(
+                format!("{:?} {}x{} matrix", number_type, column_count, row_count),
+            )),
         }
     } else {
-        match (number_type, traits.numeric.vector.component_count) {
+        Ok(match (number_type, traits.numeric.vector.component_count) {
             (NumberType::UInt, 0) => UniformProperty::UInt,
             (NumberType::Int, 0) => UniformProperty::Int,
             (NumberType::Int, 2) => UniformProperty::IVec2,
@@ -283,34 +417,25 @@ fn reflect_uniform_numeric(type_description: &ReflectTypeDescription) -> Uniform
             (NumberType::Float, 3) => UniformProperty::Vec3,
             (NumberType::Float, 4) => UniformProperty::Vec4,
             (NumberType::UInt, 4) => UniformProperty::UVec4,
-            (number_type, component_count) => panic!(
-                "unexpected uniform property format {:?} {}",
-                number_type, component_count
-            ),
-        }
+            (number_type, component_count) => {
+                return Err(ShaderReflectError::UnsupportedType(format!(
+                    "{:?} uniform with {} components",
+                    number_type, component_count
+                )))
+            }
+        })
     }
 }
 
-fn reflect_vertex_format(type_description: &ReflectTypeDescription) -> VertexFormat {
+fn reflect_vertex_format(
+    type_description: &ReflectTypeDescription,
+) -> Result<VertexFormat, ShaderReflectError> {
     let traits = &type_description.traits;
-    let number_type = if type_description.type_flags.contains(ReflectTypeFlags::INT) {
-        match traits.numeric.scalar.signedness {
-            0 => NumberType::UInt,
-            1 => NumberType::Int,
-            signedness => panic!("Unexpected signedness {}.", signedness),
-        }
-    } else if type_description
-        .type_flags
-        .contains(ReflectTypeFlags::FLOAT)
-    {
-        NumberType::Float
-    } else {
-        panic!("Unexpected type flag {:?}.", type_description.type_flags);
-    };
+    let number_type = reflect_number_type(type_description)?;
 
     let width = traits.numeric.scalar.width;
 
-    match (number_type, traits.numeric.vector.component_count, width) {
+    let format = match (number_type, traits.numeric.vector.component_count, width) {
         (NumberType::UInt, 2, 8) => VertexFormat::Uint8x2,
         (NumberType::UInt, 4, 8) => VertexFormat::Uint8x4,
         (NumberType::Int, 2, 8) => VertexFormat::Sint8x2,
@@ -333,11 +458,19 @@ fn reflect_vertex_format(type_description: &ReflectTypeDescription) -> VertexFor
         (NumberType::Int, 2, 32) => VertexFormat::Sint32x2,
         (NumberType::Int, 3, 32) => VertexFormat::Sint32x3,
         (NumberType::Int, 4, 32) => VertexFormat::Sint32x4,
-        (number_type, component_count, width) => panic!(
-            "unexpected uniform property format {:?} {} {}",
-            number_type, component_count, width
-        ),
-    }
+        (NumberType::Float, 0, 64) => VertexFormat::Float64,
+        (NumberType::Float, 2, 64) => VertexFormat::Float64x2,
+        (NumberType::Float, 3, 64) => VertexFormat::Float64x3,
+        (NumberType::Float, 4, 64) => VertexFormat::Float64x4,
+        (number_type, component_count, width) => {
+            return Err(ShaderReflectError::UnsupportedType(format!(
+                "{:?} vertex attribute with {} components of {} bits",
+                number_type, component_count, width
+            )))
+        }
+    };
+
+    Ok(format)
 }
 
 #[cfg(test)]
@@ -384,6 +517,7 @@ mod tests {
             array_sizes: vec![("TextureArr".into(), 4u32.try_into().unwrap())]
                 .into_iter()
                 .collect(),
+            ..Default::default()
         };
 
         let layout = vertex_shader.reflect_layout(&options).unwrap();
@@ -468,4 +602,90 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn reflect_storage_textures() {
+        let compute_shader = Shader::from_glsl(
+            ShaderStage::Compute,
+            r#"
+            #version 450
+            layout(local_size_x = 8, local_size_y = 8) in;
+            layout(set = 0, binding = 0, rgba8) uniform writeonly image2D Output;
+            layout(set = 0, binding = 1, r32f) uniform readonly image2DArray Input;
+
+            void main() {
+                ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+                imageStore(Output, texel, imageLoad(Input, ivec3(texel, 0)).rrrr);
+            }
+        "#,
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+
+        let options = ShaderReflectOptions {
+            bevy_conventions: false,
+            storage_texture_access: vec![("Input".into(), StorageTextureAccess::ReadOnly)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let layout = compute_shader.reflect_layout(&options).unwrap();
+        assert_eq!(
+            layout.bind_groups,
+            vec![BindGroupDescriptor::new(
+                0,
+                vec![
+                    BindingDescriptor {
+                        index: 0,
+                        name: "Output".into(),
+                        bind_type: BindType::StorageTexture {
+                            access: StorageTextureAccess::WriteOnly,
+                            format: TextureFormat::Rgba8Unorm,
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        shader_stage: BindingShaderStage::COMPUTE,
+                        count: None,
+                    },
+                    BindingDescriptor {
+                        index: 1,
+                        name: "Input".into(),
+                        bind_type: BindType::StorageTexture {
+                            access: StorageTextureAccess::ReadOnly,
+                            format: TextureFormat::R32Float,
+                            view_dimension: TextureViewDimension::D2Array,
+                        },
+                        shader_stage: BindingShaderStage::COMPUTE,
+                        count: None,
+                    },
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn unsupported_storage_texture_format_is_an_error() {
+        let compute_shader = Shader::from_glsl(
+            ShaderStage::Compute,
+            r#"
+            #version 450
+            layout(local_size_x = 8, local_size_y = 8) in;
+            layout(set = 0, binding = 0, rgba16) uniform writeonly image2D Output;
+
+            void main() {
+                imageStore(Output, ivec2(gl_GlobalInvocationID.xy), vec4(1.0));
+            }
+        "#,
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+
+        let options = ShaderReflectOptions {
+            bevy_conventions: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            compute_shader.try_reflect_layout(&options),
+            Err(ShaderReflectError::UnsupportedStorageTextureFormat(_))
+        ));
+    }
 }
@@ -0,0 +1,207 @@
+use crate::{
+    pipeline::BindGroupDescriptor,
+    render_resource::{BindGroupBuilder, SamplerId, TextureViewId},
+    renderer::{RenderFeatures, RenderResources},
+    shader::ShaderReflectOptions,
+};
+use bevy_ecs::world::World;
+use bevy_utils::{tracing::warn, HashMap};
+use std::num::NonZeroU32;
+
+/// Opts the sprite and pbr pipelines into a bindless mode: the textures of their materials are
+/// bound in binding arrays, one per sampler, and each sprite or mesh indexes the array in the
+/// shaders. Scenes with thousands of distinct textures then switch bind groups a few times per
+/// frame, instead of for almost every draw.
+///
+/// A resource of the app world, which must be inserted before the
+/// [`RenderPlugin`](crate::RenderPlugin) is added. It is ignored, with a warning, by backends
+/// without the [`BindlessTextures::required_features`]. With wgpu, they must be enabled in the
+/// `WgpuOptions`, and `max_sampled_textures_per_shader_stage` raised in its limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindlessTextures {
+    /// The number of textures of each binding array. Textures beyond it are added to other
+    /// arrays, which are bound with their own bind group.
+    pub max_textures: u32,
+}
+
+impl Default for BindlessTextures {
+    fn default() -> Self {
+        BindlessTextures { max_textures: 256 }
+    }
+}
+
+impl BindlessTextures {
+    /// The features the bindless pipelines need: arrays of textures, indexed with values that
+    /// differ between draws and between the instances of a draw. The arrays are declared without
+    /// a size in the shaders, and sized to [`BindlessTextures::max_textures`] when they are
+    /// reflected.
+    pub fn required_features() -> RenderFeatures {
+        RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY
+            | RenderFeatures::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING
+            | RenderFeatures::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING
+            | RenderFeatures::UNSIZED_BINDING_ARRAY
+    }
+
+    /// Returns the [`ShaderReflectOptions`] sizing the binding array `name` to
+    /// [`BindlessTextures::max_textures`].
+    pub fn reflect_options(&self, name: &str) -> ShaderReflectOptions {
+        let mut options = ShaderReflectOptions::default();
+        options.array_sizes.insert(
+            name.to_string(),
+            NonZeroU32::new(self.max_textures).unwrap(),
+        );
+        options
+    }
+
+    /// Returns the bindless settings of the render `world` for a pipeline whose shaders sample
+    /// `other_textures` textures besides the binding array, or `None` if bindless isn't enabled
+    /// or isn't supported by the backend. `max_textures` is clamped to what the backend can
+    /// sample in a shader stage.
+    pub fn get(world: &World, other_textures: u32) -> Option<BindlessTextures> {
+        let bindless = *world.get_resource::<BindlessTextures>()?;
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let missing_features = Self::required_features() - render_resources.features();
+        if !missing_features.is_empty() {
+            warn!(
+                "bindless textures need the {:?} features, drawing with a bind group per texture",
+                missing_features
+            );
+            return None;
+        }
+        let limit = render_resources
+            .get_max_sampled_textures_per_shader_stage()
+            .saturating_sub(other_textures);
+        if limit < 2 {
+            warn!(
+                "the backend can't sample enough textures for bindless textures, drawing with a \
+                bind group per texture"
+            );
+            return None;
+        }
+        Some(BindlessTextures {
+            max_textures: bindless.max_textures.clamp(2, limit),
+        })
+    }
+}
+
+/// A binding array of textures sampled with the same sampler, see [`BindlessBatches`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindlessBatch {
+    pub sampler: SamplerId,
+    pub textures: Vec<TextureViewId>,
+}
+
+/// Where a texture is bound by [`BindlessBatches`]: the batch, and the index of the texture in
+/// the binding array of the batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BindlessSlot {
+    pub batch: usize,
+    pub index: u32,
+}
+
+/// Assigns the textures drawn in a frame to the binding arrays of [`BindlessTextures`]. Textures
+/// are batched by sampler, and a new batch of the sampler is started once one is full.
+#[derive(Debug, Default)]
+pub struct BindlessBatches {
+    max_textures: usize,
+    batches: Vec<BindlessBatch>,
+    slots: HashMap<(TextureViewId, SamplerId), BindlessSlot>,
+    /// The batch textures sampled with each sampler are added to.
+    open_batches: HashMap<SamplerId, usize>,
+}
+
+impl BindlessBatches {
+    pub fn new(bindless: BindlessTextures) -> Self {
+        BindlessBatches {
+            max_textures: bindless.max_textures as usize,
+            ..Default::default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.batches.clear();
+        self.slots.clear();
+        self.open_batches.clear();
+    }
+
+    pub fn batches(&self) -> &[BindlessBatch] {
+        &self.batches
+    }
+
+    /// Returns the slot of `texture` sampled with `sampler`, adding it to a batch if it hasn't
+    /// been added yet.
+    pub fn add(&mut self, texture: TextureViewId, sampler: SamplerId) -> BindlessSlot {
+        if let Some(slot) = self.slots.get(&(texture, sampler)) {
+            return *slot;
+        }
+        let batch = match self.open_batches.get(&sampler) {
+            Some(&batch) if self.batches[batch].textures.len() < self.max_textures => batch,
+            _ => {
+                self.batches.push(BindlessBatch {
+                    sampler,
+                    textures: Vec::with_capacity(self.max_textures),
+                });
+                self.open_batches.insert(sampler, self.batches.len() - 1);
+                self.batches.len() - 1
+            }
+        };
+        let textures = &mut self.batches[batch].textures;
+        let slot = BindlessSlot {
+            batch,
+            index: textures.len() as u32,
+        };
+        textures.push(texture);
+        self.slots.insert((texture, sampler), slot);
+        slot
+    }
+
+    /// Returns a builder of the bind group of `batch`, with the textures of the batch bound to
+    /// `texture_binding` and its sampler to `sampler_binding`. Binding arrays must be filled, so
+    /// the textures are padded to [`BindlessTextures::max_textures`] with the first one.
+    pub fn bind_group<'a>(
+        &self,
+        batch: usize,
+        layout: &'a BindGroupDescriptor,
+        texture_binding: u32,
+        sampler_binding: u32,
+    ) -> BindGroupBuilder<'a> {
+        let batch = &self.batches[batch];
+        let mut textures = batch.textures.clone();
+        textures.resize(self.max_textures, batch.textures[0]);
+        BindGroupBuilder::new(layout)
+            .texture_array(texture_binding, textures)
+            .sampler(sampler_binding, batch.sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindlessBatches, BindlessSlot, BindlessTextures};
+    use crate::render_resource::{SamplerId, TextureViewId};
+
+    #[test]
+    fn textures_are_batched_by_sampler() {
+        let mut batches = BindlessBatches::new(BindlessTextures { max_textures: 2 });
+        let (nearest, linear) = (SamplerId::new(), SamplerId::new());
+        let textures = (0..3).map(|_| TextureViewId::new()).collect::<Vec<_>>();
+
+        let first = batches.add(textures[0], nearest);
+        assert_eq!(first, BindlessSlot { batch: 0, index: 0 });
+        assert_eq!(batches.add(textures[1], linear).batch, 1);
+        assert_eq!(
+            batches.add(textures[1], nearest),
+            BindlessSlot { batch: 0, index: 1 }
+        );
+        assert_eq!(batches.add(textures[0], nearest), first);
+        // the first batch of `nearest` is full
+        assert_eq!(
+            batches.add(textures[2], nearest),
+            BindlessSlot { batch: 2, index: 0 }
+        );
+        assert_eq!(batches.batches().len(), 3);
+
+        batches.clear();
+        assert!(batches.batches().is_empty());
+        assert_eq!(batches.add(textures[2], linear).batch, 0);
+    }
+}
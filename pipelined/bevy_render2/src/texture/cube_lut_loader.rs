@@ -0,0 +1,157 @@
+use super::{Extent3d, Texture, TextureDimension, TextureFormat};
+use anyhow::Result;
+use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy_utils::BoxedFuture;
+use thiserror::Error;
+
+/// A 3D color lookup table of an Adobe `.cube` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    /// The number of entries along each axis.
+    pub size: u32,
+    /// The output colors, with red changing fastest, then green, then blue.
+    pub values: Vec<[f32; 3]>,
+}
+
+impl CubeLut {
+    /// Parses the text of a `.cube` file. Only 3D tables with the default domain of 0.0 to 1.0
+    /// are supported.
+    pub fn parse(text: &str) -> Result<CubeLut, CubeLutError> {
+        let mut size = None;
+        let mut values = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap();
+            match keyword {
+                "TITLE" => {}
+                "LUT_3D_SIZE" => {
+                    let value = words.next().ok_or(CubeLutError::InvalidLine)?;
+                    let value = value.parse().map_err(|_| CubeLutError::InvalidLine)?;
+                    if value < 2 {
+                        return Err(CubeLutError::InvalidLine);
+                    }
+                    size = Some(value);
+                }
+                "LUT_1D_SIZE" => return Err(CubeLutError::Unsupported1d),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let default = if keyword == "DOMAIN_MIN" { 0.0 } else { 1.0 };
+                    for word in words {
+                        let bound: f32 = word.parse().map_err(|_| CubeLutError::InvalidLine)?;
+                        if (bound - default).abs() > f32::EPSILON {
+                            return Err(CubeLutError::UnsupportedDomain);
+                        }
+                    }
+                }
+                _ => {
+                    let channels = std::iter::once(keyword)
+                        .chain(words)
+                        .map(|word| word.parse())
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|_| CubeLutError::InvalidLine)?;
+                    match channels[..] {
+                        [red, green, blue] => values.push([red, green, blue]),
+                        _ => return Err(CubeLutError::InvalidLine),
+                    }
+                }
+            }
+        }
+        let size = size.ok_or(CubeLutError::MissingSize)?;
+        if values.len() != (size * size * size) as usize {
+            return Err(CubeLutError::WrongValueCount);
+        }
+        Ok(CubeLut { size, values })
+    }
+
+    /// Creates an `Rgba8UnormSrgb` strip of `size` slices of `size` x `size` texels, one slice
+    /// for each blue value from left to right. Within a slice red increases to the right and green
+    /// downwards, the layout of the usual PNG color grading strips.
+    pub fn texture(&self) -> Texture {
+        let size = self.size as usize;
+        let mut data = vec![255; size * size * size * 4];
+        for (index, value) in self.values.iter().enumerate() {
+            let (red, green, blue) = (index % size, index / size % size, index / (size * size));
+            let texel = (green * size * size + blue * size + red) * 4;
+            for (byte, channel) in data[texel..texel + 3].iter_mut().zip(value.iter()) {
+                *byte = (channel.max(0.0).min(1.0) * 255.0).round() as u8;
+            }
+        }
+        Texture::new(
+            Extent3d::new(self.size * self.size, self.size, 1),
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CubeLutError {
+    #[error("invalid line in cube file")]
+    InvalidLine,
+    #[error("cube file has no LUT_3D_SIZE")]
+    MissingSize,
+    #[error("cube file doesn't have LUT_3D_SIZE^3 values")]
+    WrongValueCount,
+    #[error("1d cube files aren't supported")]
+    Unsupported1d,
+    #[error("only cube files with a domain of 0.0 to 1.0 are supported")]
+    UnsupportedDomain,
+}
+
+/// Loads `.cube` color lookup tables as textures in the layout of [`CubeLut::texture`].
+#[derive(Clone, Default)]
+pub struct CubeLutLoader;
+
+impl AssetLoader for CubeLutLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let lut = CubeLut::parse(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(lut.texture()));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cube"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CubeLut, CubeLutError};
+    use crate::texture::{Extent3d, TextureFormat};
+
+    #[test]
+    fn parse() {
+        let text = "# identity\nTITLE \"identity\"\nLUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\n\n\
+            0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1.5 1 1\n";
+        let lut = CubeLut::parse(text).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.values[1], [1.0, 0.0, 0.0]);
+
+        let texture = lut.texture();
+        assert_eq!(texture.size, Extent3d::new(4, 2, 1));
+        assert_eq!(texture.format, TextureFormat::Rgba8UnormSrgb);
+        let texel = |x: usize, y: usize| &texture.data[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(texel(1, 0), &[255, 0, 0, 255]);
+        assert_eq!(texel(2, 1), &[0, 255, 255, 255]);
+        assert_eq!(texel(3, 1), &[255, 255, 255, 255]);
+
+        assert_eq!(
+            CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n"),
+            Err(CubeLutError::WrongValueCount)
+        );
+        assert_eq!(
+            CubeLut::parse("LUT_3D_SIZE 2\nDOMAIN_MAX 2 2 2\n"),
+            Err(CubeLutError::UnsupportedDomain)
+        );
+    }
+}
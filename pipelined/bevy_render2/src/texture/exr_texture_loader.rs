@@ -0,0 +1,84 @@
+use super::{Extent3d, ImageImportConfig, Texture, TextureDimension, TextureFormat};
+use anyhow::Result;
+use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy_ecs::world::{FromWorld, World};
+use bevy_utils::BoxedFuture;
+use exr::prelude::*;
+use std::io::Cursor;
+
+/// Loads the first layer of OpenEXR images as `Rgba32Float` Texture assets. Images without an
+/// alpha channel are opaque.
+///
+/// Loaded textures are adjusted according to the [`ImageImportConfig`] resource, which can
+/// convert them to `Rgba16Float`.
+#[derive(Clone, Default)]
+pub struct ExrTextureLoader {
+    pub config: ImageImportConfig,
+}
+
+impl FromWorld for ExrTextureLoader {
+    fn from_world(world: &mut World) -> Self {
+        ExrTextureLoader {
+            config: world
+                .get_resource::<ImageImportConfig>()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl AssetLoader for ExrTextureLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let format = TextureFormat::Rgba32Float;
+
+            let image = read()
+                .no_deep_data()
+                .largest_resolution_level()
+                .specific_channels()
+                .required("R")
+                .required("G")
+                .required("B")
+                .optional("A", 1.0f32)
+                .collect_pixels(
+                    |resolution, _channels| {
+                        (
+                            resolution.width(),
+                            vec![0u8; resolution.area() * format.pixel_size()],
+                        )
+                    },
+                    |(width, rgba_data), position, (r, g, b, a): (f32, f32, f32, f32)| {
+                        let offset = (position.y() * *width + position.x()) * format.pixel_size();
+                        let pixel = &mut rgba_data[offset..offset + format.pixel_size()];
+                        for (bytes, value) in pixel.chunks_exact_mut(4).zip([r, g, b, a].iter()) {
+                            bytes.copy_from_slice(&value.to_ne_bytes());
+                        }
+                    },
+                )
+                .first_valid_layer()
+                .all_attributes()
+                .from_buffered(Cursor::new(bytes))?;
+
+            let size = image.layer_data.size;
+            let (_, rgba_data) = image.layer_data.channel_data.pixels;
+            let mut texture = Texture::new(
+                Extent3d::new(size.width() as u32, size.height() as u32, 1),
+                TextureDimension::D2,
+                rgba_data,
+                format,
+            );
+            self.config.get(load_context.path()).apply(&mut texture);
+
+            load_context.set_default_asset(LoadedAsset::new(texture));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["exr"]
+    }
+}
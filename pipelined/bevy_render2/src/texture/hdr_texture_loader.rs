@@ -1,11 +1,28 @@
-use super::{Extent3d, Texture, TextureDimension, TextureFormat};
+use super::{Extent3d, ImageImportConfig, Texture, TextureDimension, TextureFormat};
 use anyhow::Result;
 use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy_ecs::world::{FromWorld, World};
 use bevy_utils::BoxedFuture;
 
-/// Loads HDR textures as Texture assets
+/// Loads HDR textures as `Rgba32Float` Texture assets.
+///
+/// Loaded textures are adjusted according to the [`ImageImportConfig`] resource, which can
+/// convert them to `Rgba16Float`.
 #[derive(Clone, Default)]
-pub struct HdrTextureLoader;
+pub struct HdrTextureLoader {
+    pub config: ImageImportConfig,
+}
+
+impl FromWorld for HdrTextureLoader {
+    fn from_world(world: &mut World) -> Self {
+        HdrTextureLoader {
+            config: world
+                .get_resource::<ImageImportConfig>()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
 
 impl AssetLoader for HdrTextureLoader {
     fn load<'a>(
@@ -35,12 +52,13 @@ impl AssetLoader for HdrTextureLoader {
                 rgba_data.extend_from_slice(&alpha.to_ne_bytes());
             }
 
-            let texture = Texture::new(
+            let mut texture = Texture::new(
                 Extent3d::new(info.width, info.height, 1),
                 TextureDimension::D2,
                 rgba_data,
                 format,
             );
+            self.config.get(load_context.path()).apply(&mut texture);
 
             load_context.set_default_asset(LoadedAsset::new(texture));
             Ok(())
@@ -0,0 +1,177 @@
+use super::{Extent3d, Texture, TextureFormat};
+use crate::color::SrgbColorSpace;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ImageCompareError {
+    #[error("image sizes differ: {actual:?} and {expected:?}")]
+    SizeMismatch {
+        actual: Extent3d,
+        expected: Extent3d,
+    },
+    #[error("image comparison does not support the format {0:?}")]
+    UnsupportedFormat(TextureFormat),
+}
+
+/// The result of comparing two images with [`compare_images`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageComparison {
+    /// The largest difference of any channel of any pixel, from 0.0 to 1.0.
+    pub max_difference: f32,
+    /// The number of pixels with a channel that differs by more than the tolerance.
+    pub mismatched_pixels: usize,
+    pub pixel_count: usize,
+}
+
+impl ImageComparison {
+    /// Returns true if no pixel differs by more than the tolerance.
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+/// Compares the pixels of two 2d images, channel by channel.
+///
+/// Both images are converted to sRGB encoded RGBA values first, so an image stored in an sRGB
+/// format matches the same image stored in a linear or BGRA format, and `tolerance` is applied in
+/// the perceptual sRGB space, from 0.0 to 1.0.
+pub fn compare_images(
+    actual: &Texture,
+    expected: &Texture,
+    tolerance: f32,
+) -> Result<ImageComparison, ImageCompareError> {
+    if actual.size != expected.size {
+        return Err(ImageCompareError::SizeMismatch {
+            actual: actual.size,
+            expected: expected.size,
+        });
+    }
+    let actual_pixels = srgb_pixels(actual)?;
+    let expected_pixels = srgb_pixels(expected)?;
+
+    let mut comparison = ImageComparison {
+        max_difference: 0.0,
+        mismatched_pixels: 0,
+        pixel_count: actual_pixels.len(),
+    };
+    for (actual, expected) in actual_pixels.iter().zip(expected_pixels.iter()) {
+        let difference = actual
+            .iter()
+            .zip(expected.iter())
+            .map(|(actual, expected)| (actual - expected).abs())
+            .fold(0.0, f32::max);
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance {
+            comparison.mismatched_pixels += 1;
+        }
+    }
+    Ok(comparison)
+}
+
+/// Returns the pixels of `texture` as sRGB encoded RGBA values.
+fn srgb_pixels(texture: &Texture) -> Result<Vec<[f32; 4]>, ImageCompareError> {
+    let encode = |[r, g, b, a]: [f32; 4]| {
+        [
+            r.linear_to_nonlinear_srgb(),
+            g.linear_to_nonlinear_srgb(),
+            b.linear_to_nonlinear_srgb(),
+            a,
+        ]
+    };
+    let unorm = |value: u8| value as f32 / 255.0;
+    let float = |bytes: &[u8]| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let data = &texture.data;
+    let pixels = match texture.format {
+        TextureFormat::Rgba8UnormSrgb => data
+            .chunks_exact(4)
+            .map(|p| [unorm(p[0]), unorm(p[1]), unorm(p[2]), unorm(p[3])])
+            .collect(),
+        TextureFormat::Bgra8UnormSrgb => data
+            .chunks_exact(4)
+            .map(|p| [unorm(p[2]), unorm(p[1]), unorm(p[0]), unorm(p[3])])
+            .collect(),
+        TextureFormat::Rgba8Unorm => data
+            .chunks_exact(4)
+            .map(|p| encode([unorm(p[0]), unorm(p[1]), unorm(p[2]), unorm(p[3])]))
+            .collect(),
+        TextureFormat::Bgra8Unorm => data
+            .chunks_exact(4)
+            .map(|p| encode([unorm(p[2]), unorm(p[1]), unorm(p[0]), unorm(p[3])]))
+            .collect(),
+        TextureFormat::Rgba32Float => data
+            .chunks_exact(16)
+            .map(|p| {
+                encode([
+                    float(&p[0..4]),
+                    float(&p[4..8]),
+                    float(&p[8..12]),
+                    float(&p[12..16]),
+                ])
+            })
+            .collect(),
+        format => return Err(ImageCompareError::UnsupportedFormat(format)),
+    };
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_images, ImageCompareError};
+    use crate::texture::{Extent3d, Texture, TextureDimension, TextureFormat};
+
+    fn texture(data: Vec<u8>, format: TextureFormat) -> Texture {
+        Texture::new(Extent3d::new(2, 1, 1), TextureDimension::D2, data, format)
+    }
+
+    #[test]
+    fn compares_across_formats() {
+        let rgba = texture(
+            vec![255, 0, 0, 255, 0, 0, 255, 255],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let bgra = texture(
+            vec![0, 0, 255, 255, 255, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+        );
+        assert!(compare_images(&rgba, &bgra, 0.0).unwrap().is_match());
+
+        // linear 0.5 is encoded as roughly 0.735 in sRGB
+        let srgb = texture(
+            [188, 188, 188, 255].repeat(2),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let linear = texture([128, 128, 128, 255].repeat(2), TextureFormat::Rgba8Unorm);
+        let comparison = compare_images(&srgb, &linear, 0.01).unwrap();
+        assert!(comparison.is_match());
+        assert!(comparison.max_difference > 0.0);
+
+        let other = texture(
+            [200, 200, 200, 255].repeat(2),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        assert_eq!(
+            compare_images(&srgb, &other, 0.01)
+                .unwrap()
+                .mismatched_pixels,
+            2
+        );
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let small = texture(vec![0; 8], TextureFormat::Rgba8UnormSrgb);
+        let large = Texture::new_fill(
+            Extent3d::new(2, 2, 1),
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        assert_eq!(
+            compare_images(&small, &large, 0.0),
+            Err(ImageCompareError::SizeMismatch {
+                actual: small.size,
+                expected: large.size,
+            })
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use super::{FilterMode, SamplerDescriptor, Texture, TextureFormat};
+use bevy_utils::{tracing::warn, HashMap};
+use std::path::{Path, PathBuf};
+
+/// How [`ImageTextureLoader`](super::ImageTextureLoader) turns an image file into a [`Texture`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageImportSettings {
+    /// The sampler of the loaded texture.
+    pub sampler: SamplerDescriptor,
+    /// Whether the color data is sRGB encoded. Disable this for data such as normal maps.
+    pub srgb: bool,
+    /// Whether to generate a mip chain for loaded textures that don't have one.
+    pub generate_mipmaps: bool,
+    /// Whether to store 32 bit float textures, such as HDR and EXR images, as 16 bit floats. This
+    /// halves their size and makes them filterable on every backend, e.g. for environment maps.
+    pub half_float: bool,
+}
+
+impl Default for ImageImportSettings {
+    fn default() -> Self {
+        ImageImportSettings {
+            sampler: SamplerDescriptor::default(),
+            srgb: true,
+            generate_mipmaps: false,
+            half_float: false,
+        }
+    }
+}
+
+impl ImageImportSettings {
+    /// Nearest-neighbor filtering without mipmaps, so pixel art stays crisp when scaled.
+    pub fn pixel_art() -> Self {
+        ImageImportSettings {
+            sampler: SamplerDescriptor {
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Trilinear filtering with generated mipmaps.
+    pub fn trilinear() -> Self {
+        ImageImportSettings {
+            sampler: SamplerDescriptor {
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Linear,
+                ..Default::default()
+            },
+            generate_mipmaps: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn apply(&self, texture: &mut Texture) {
+        if self.half_float && texture.format == TextureFormat::Rgba32Float {
+            if let Some(converted) = texture.convert(TextureFormat::Rgba16Float) {
+                *texture = converted;
+            }
+        }
+        texture.sampler = self.sampler;
+        texture.format = texture.format.with_srgb(self.srgb);
+        if self.generate_mipmaps && texture.mip_levels_data.is_none() {
+            if let Err(err) = texture.generate_mipmaps() {
+                warn!("{}", err);
+            }
+        }
+    }
+}
+
+/// The [`ImageImportSettings`] used by [`ImageTextureLoader`](super::ImageTextureLoader), with
+/// overrides for individual asset paths.
+///
+/// This resource is read when the loader is registered, so it must be inserted before the
+/// [`TexturePlugin`](super::TexturePlugin) is added.
+#[derive(Debug, Clone, Default)]
+pub struct ImageImportConfig {
+    /// The settings of images without an override.
+    pub default: ImageImportSettings,
+    overrides: HashMap<PathBuf, ImageImportSettings>,
+}
+
+impl ImageImportConfig {
+    /// Imports every image with [`ImageImportSettings::pixel_art`] unless overridden.
+    pub fn pixel_art() -> Self {
+        ImageImportConfig {
+            default: ImageImportSettings::pixel_art(),
+            ..Default::default()
+        }
+    }
+
+    /// Uses `settings` for the image at the asset path `path`.
+    pub fn set(&mut self, path: impl Into<PathBuf>, settings: ImageImportSettings) -> &mut Self {
+        self.overrides.insert(path.into(), settings);
+        self
+    }
+
+    /// Returns the settings of the image at the asset path `path`.
+    pub fn get(&self, path: &Path) -> &ImageImportSettings {
+        self.overrides.get(path).unwrap_or(&self.default)
+    }
+}
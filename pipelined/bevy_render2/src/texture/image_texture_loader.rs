@@ -1,12 +1,31 @@
-use super::texture::{ImageType, Texture, TextureError};
+use super::{
+    texture::{ImageType, Texture, TextureError},
+    ImageImportConfig,
+};
 use anyhow::Result;
 use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy_ecs::world::{FromWorld, World};
 use bevy_utils::BoxedFuture;
 use thiserror::Error;
 
 /// Loader for images that can be read by the `image` crate.
+///
+/// Loaded textures are adjusted according to the [`ImageImportConfig`] resource.
 #[derive(Clone, Default)]
-pub struct ImageTextureLoader;
+pub struct ImageTextureLoader {
+    pub config: ImageImportConfig,
+}
+
+impl FromWorld for ImageTextureLoader {
+    fn from_world(world: &mut World) -> Self {
+        ImageTextureLoader {
+            config: world
+                .get_resource::<ImageImportConfig>()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
 
 const FILE_EXTENSIONS: &[&str] = &["png", "dds", "tga", "jpg", "jpeg", "bmp"];
 
@@ -20,13 +39,14 @@ impl AssetLoader for ImageTextureLoader {
             // use the file extension for the image type
             let ext = load_context.path().extension().unwrap().to_str().unwrap();
 
-            let dyn_img =
+            let mut dyn_img =
                 Texture::from_buffer(bytes, ImageType::Extension(ext)).map_err(|err| {
                     FileTextureError {
                         error: err,
                         path: format!("{}", load_context.path().display()),
                     }
                 })?;
+            self.config.get(load_context.path()).apply(&mut dyn_img);
 
             load_context.set_default_asset(LoadedAsset::new(dyn_img));
             Ok(())
@@ -0,0 +1,237 @@
+use super::{
+    Extent3d, ImageImportConfig, StreamedTextureSource, Texture, TextureDimension, TextureFormat,
+    TextureStreamingSettings,
+};
+use anyhow::Result;
+use bevy_asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy_ecs::world::{FromWorld, World};
+use bevy_utils::BoxedFuture;
+use std::{convert::TryInto, ops::Range};
+use thiserror::Error;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const LEVEL_INDEX_OFFSET: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+/// The layout of an uncompressed, single layer 2D KTX2 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ktx2 {
+    pub format: TextureFormat,
+    /// The size of mip level 0.
+    pub size: Extent3d,
+    /// The byte range of each mip level in the file, starting with mip level 0.
+    pub levels: Vec<Range<usize>>,
+}
+
+impl Ktx2 {
+    /// Parses the header and level index of a KTX2 file.
+    pub fn parse(bytes: &[u8]) -> Result<Ktx2, Ktx2Error> {
+        if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..12] != KTX2_IDENTIFIER {
+            return Err(Ktx2Error::InvalidIdentifier);
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let vk_format = u32_at(12);
+        let width = u32_at(20);
+        let height = u32_at(24);
+        let depth = u32_at(28);
+        let layer_count = u32_at(32);
+        let face_count = u32_at(36);
+        let level_count = u32_at(40).max(1) as usize;
+        let supercompression_scheme = u32_at(44);
+
+        let format = match vk_format {
+            9 => TextureFormat::R8Unorm,
+            16 => TextureFormat::Rg8Unorm,
+            37 => TextureFormat::Rgba8Unorm,
+            43 => TextureFormat::Rgba8UnormSrgb,
+            44 => TextureFormat::Bgra8Unorm,
+            50 => TextureFormat::Bgra8UnormSrgb,
+            97 => TextureFormat::Rgba16Float,
+            109 => TextureFormat::Rgba32Float,
+            _ => return Err(Ktx2Error::UnsupportedFormat(vk_format)),
+        };
+        if supercompression_scheme != 0 {
+            return Err(Ktx2Error::UnsupportedSupercompression(
+                supercompression_scheme,
+            ));
+        }
+        if height == 0 || depth > 1 || layer_count > 1 || face_count != 1 {
+            return Err(Ktx2Error::UnsupportedLayout);
+        }
+
+        let level_index_end = LEVEL_INDEX_OFFSET + level_count * LEVEL_INDEX_ENTRY_SIZE;
+        if bytes.len() < level_index_end {
+            return Err(Ktx2Error::UnexpectedEnd);
+        }
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let mut ktx2 = Ktx2 {
+            format,
+            size: Extent3d::new(width, height, 1),
+            levels: Vec::with_capacity(level_count),
+        };
+        for level in 0..level_count {
+            let entry = LEVEL_INDEX_OFFSET + level * LEVEL_INDEX_ENTRY_SIZE;
+            let offset = u64_at(entry) as usize;
+            let length = u64_at(entry + 8) as usize;
+            if length != ktx2.level_size(level).volume() * format.pixel_size() {
+                return Err(Ktx2Error::UnsupportedLayout);
+            }
+            if offset + length > bytes.len() {
+                return Err(Ktx2Error::UnexpectedEnd);
+            }
+            ktx2.levels.push(offset..offset + length);
+        }
+        Ok(ktx2)
+    }
+
+    /// Returns the size of mip level `level`.
+    pub fn level_size(&self, level: usize) -> Extent3d {
+        Extent3d::new(
+            (self.size.width >> level).max(1),
+            (self.size.height >> level).max(1),
+            1,
+        )
+    }
+
+    /// Returns the size of the data of the mip levels from `base_level` on, in bytes.
+    pub fn resident_size(&self, base_level: usize) -> usize {
+        self.levels[base_level..]
+            .iter()
+            .map(|range| range.len())
+            .sum()
+    }
+
+    /// Returns the first mip level that fits in `max_size` in both dimensions, or the last level.
+    pub fn base_level_for_size(&self, max_size: u32) -> usize {
+        (0..self.levels.len())
+            .find(|level| {
+                let size = self.level_size(*level);
+                size.width <= max_size && size.height <= max_size
+            })
+            .unwrap_or(self.levels.len() - 1)
+    }
+
+    /// Creates a texture from the mip levels of `bytes` from `base_level` on.
+    pub fn texture(&self, bytes: &[u8], base_level: usize) -> Texture {
+        let mut texture = Texture::new(
+            self.level_size(base_level),
+            TextureDimension::D2,
+            bytes[self.levels[base_level].clone()].to_vec(),
+            self.format,
+        );
+        if base_level + 1 < self.levels.len() {
+            texture.mip_levels_data = Some(
+                self.levels[base_level + 1..]
+                    .iter()
+                    .map(|range| bytes[range.clone()].to_vec())
+                    .collect(),
+            );
+        }
+        texture
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Ktx2Error {
+    #[error("not a ktx2 file")]
+    InvalidIdentifier,
+    #[error("unexpected end of ktx2 file")]
+    UnexpectedEnd,
+    #[error("unsupported ktx2 vkFormat {0}")]
+    UnsupportedFormat(u32),
+    #[error("unsupported ktx2 supercompression scheme {0}")]
+    UnsupportedSupercompression(u32),
+    #[error("only uncompressed single layer 2d ktx2 textures are supported")]
+    UnsupportedLayout,
+}
+
+/// Loads uncompressed KTX2 textures, including their mip chain.
+///
+/// With a [`TextureStreamingPlugin`](super::TextureStreamingPlugin), only the small mip levels
+/// are loaded, and a [`StreamedTextureSource`] is added with the label `"streaming"`.
+#[derive(Clone, Default)]
+pub struct Ktx2TextureLoader {
+    pub config: ImageImportConfig,
+    pub streaming: Option<TextureStreamingSettings>,
+}
+
+impl FromWorld for Ktx2TextureLoader {
+    fn from_world(world: &mut World) -> Self {
+        Ktx2TextureLoader {
+            config: world
+                .get_resource::<ImageImportConfig>()
+                .cloned()
+                .unwrap_or_default(),
+            streaming: None,
+        }
+    }
+}
+
+impl AssetLoader for Ktx2TextureLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let ktx2 = Ktx2::parse(bytes)?;
+            let base_level = match &self.streaming {
+                Some(streaming) => ktx2.base_level_for_size(streaming.initial_resident_size),
+                None => 0,
+            };
+
+            let mut texture = ktx2.texture(bytes, base_level);
+            self.config.get(load_context.path()).apply(&mut texture);
+            load_context.set_default_asset(LoadedAsset::new(texture));
+
+            if self.streaming.is_some() {
+                load_context.set_labeled_asset(
+                    "streaming",
+                    LoadedAsset::new(StreamedTextureSource { ktx2, base_level }),
+                );
+            }
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ktx2"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ktx2, KTX2_IDENTIFIER};
+    use crate::texture::{Extent3d, TextureFormat};
+
+    #[test]
+    fn parse() {
+        // a 2x2 Rgba8Unorm texture with two mip levels, stored smallest first
+        let mut bytes = KTX2_IDENTIFIER.to_vec();
+        for value in [37u32, 1, 2, 2, 0, 0, 1, 2, 0].iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(80, 0);
+        for (offset, length) in [(132u64, 16u64), (128, 4)].iter() {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[1; 4]);
+        bytes.extend_from_slice(&[2; 16]);
+
+        let ktx2 = Ktx2::parse(&bytes).unwrap();
+        assert_eq!(ktx2.format, TextureFormat::Rgba8Unorm);
+        assert_eq!(ktx2.levels, vec![132..148, 128..132]);
+        assert_eq!(ktx2.base_level_for_size(1), 1);
+
+        let texture = ktx2.texture(&bytes, 1);
+        assert_eq!(texture.size, Extent3d::new(1, 1, 1));
+        assert_eq!(texture.data, vec![1; 4]);
+        assert_eq!(texture.mip_levels_data, None);
+    }
+}
@@ -1,34 +1,62 @@
+mod bindless;
+mod cube_lut_loader;
+#[cfg(feature = "exr")]
+mod exr_texture_loader;
 #[cfg(feature = "hdr")]
 mod hdr_texture_loader;
+mod image_compare;
+mod image_import_settings;
 mod image_texture_loader;
+mod ktx2_texture_loader;
+mod placeholder;
+mod sampler_cache;
 mod sampler_descriptor;
+mod streaming;
+mod streaming_texture;
 #[allow(clippy::module_inception)]
 mod texture;
 mod texture_cache;
+mod texture_cache_overlay;
 mod texture_descriptor;
 mod texture_dimension;
 
 pub(crate) mod image_texture_conversion;
 
+pub use bindless::*;
+pub use cube_lut_loader::*;
+#[cfg(feature = "exr")]
+pub use exr_texture_loader::*;
 #[cfg(feature = "hdr")]
 pub use hdr_texture_loader::*;
+pub use image_compare::*;
+pub use image_import_settings::*;
 pub use image_texture_loader::*;
+pub use ktx2_texture_loader::*;
+pub use placeholder::*;
+pub use sampler_cache::*;
 pub use sampler_descriptor::*;
+pub use streaming::*;
+pub use streaming_texture::*;
 pub use texture::*;
 pub use texture_cache::*;
+pub use texture_cache_overlay::*;
 pub use texture_descriptor::*;
 pub use texture_dimension::*;
 
 use crate::{
-    render_command::RenderCommandQueue,
+    quality::RenderQualitySettings,
+    render_command::{AssetUploadQueue, RenderCommandQueue},
+    render_graph::{prepare_transient_textures, TransientTextures},
     render_resource::{BufferInfo, BufferUsage},
     renderer::{RenderResourceContext, RenderResources},
-    RenderStage,
+    RenderApp, RenderStage, RenderSystem,
 };
 use bevy_app::{App, CoreStage, Plugin};
 use bevy_asset::{AddAsset, AssetEvent, Assets, Handle};
 use bevy_ecs::prelude::*;
-use bevy_utils::HashSet;
+use bevy_tasks::{AsyncComputeTaskPool, Task};
+use bevy_utils::{tracing::error, HashMap, HashSet};
+use futures_lite::future;
 
 pub struct TexturePlugin;
 
@@ -38,37 +66,133 @@ impl Plugin for TexturePlugin {
         {
             app.init_asset_loader::<ImageTextureLoader>();
         }
+        #[cfg(feature = "hdr")]
+        {
+            app.init_asset_loader::<HdrTextureLoader>();
+        }
+        #[cfg(feature = "exr")]
+        {
+            app.init_asset_loader::<ExrTextureLoader>();
+        }
+        app.init_asset_loader::<Ktx2TextureLoader>()
+            .init_asset_loader::<CubeLutLoader>();
 
         app.add_system_to_stage(CoreStage::PostUpdate, texture_resource_system.system())
-            .add_asset::<Texture>();
+            .add_system_to_stage(CoreStage::PostUpdate, streaming_texture_system.system())
+            .add_asset::<Texture>()
+            .add_asset::<StreamingTexture>()
+            .register_type::<Handle<Texture>>()
+            .register_type::<TextureFormat>()
+            .init_resource::<SamplerCache>()
+            .init_resource::<PendingTextureUploads>();
 
-        let render_app = app.sub_app_mut(0);
+        let mut textures = app.world.get_resource_mut::<Assets<Texture>>().unwrap();
+        for (handle, texture) in std::array::IntoIter::new(placeholder_textures()) {
+            textures.set_untracked(handle, texture);
+        }
+
+        let bindless = app.world.get_resource::<BindlessTextures>().copied();
+        let render_app = app.sub_app_mut(RenderApp);
+        if let Some(bindless) = bindless {
+            render_app.insert_resource(bindless);
+        }
         render_app
             .init_resource::<TextureCache>()
+            .init_resource::<TransientTextures>()
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_transient_textures
+                    .system()
+                    .label(RenderSystem::PrepareTransientTextures),
+            )
             .add_system_to_stage(RenderStage::Cleanup, update_texture_cache_system.system());
     }
 }
 
+/// Texture uploads whose rows are being padded to the copy alignment on the
+/// [`AsyncComputeTaskPool`].
+#[derive(Default)]
+pub struct PendingTextureUploads {
+    tasks: HashMap<Handle<Texture>, Task<Vec<Vec<u8>>>>,
+}
+
+impl PendingTextureUploads {
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
 pub fn texture_resource_system(
-    render_resource_context: Res<RenderResources>,
-    mut render_command_queue: ResMut<RenderCommandQueue>,
+    render_resource_context: Option<Res<RenderResources>>,
+    quality: Res<RenderQualitySettings>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    mut asset_upload_queue: ResMut<AssetUploadQueue>,
+    mut pending_uploads: ResMut<PendingTextureUploads>,
     mut textures: ResMut<Assets<Texture>>,
     mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut last_quality: Local<Option<RenderQualitySettings>>,
+    mut renderer_was_pending: Local<bool>,
 ) {
+    let render_resource_context = match render_resource_context {
+        Some(render_resource_context) => render_resource_context,
+        None => {
+            *renderer_was_pending = true;
+            return;
+        }
+    };
     let render_resource_context = &**render_resource_context;
+    let last_quality = last_quality
+        .replace(*quality)
+        .filter(|last_quality| last_quality != &*quality);
+    if let Some(last_quality) = last_quality {
+        // bind groups are created with the samplers of the textures every frame, so replacing
+        // them is enough for the new anisotropy to be used
+        let ids = textures.ids().collect::<Vec<_>>();
+        for id in ids {
+            // borrowing a texture mutably sends a Modified event, so only the textures whose
+            // sampler changes are
+            let sampler_changed = textures.get(id).map_or(false, |texture| {
+                texture.gpu_data.is_some()
+                    && last_quality.sampler(&texture.sampler).anisotropy_clamp
+                        != quality.sampler(&texture.sampler).anisotropy_clamp
+            });
+            if !sampler_changed {
+                continue;
+            }
+            let texture = textures.get_mut(id).unwrap();
+            let gpu_data = texture.gpu_data.as_mut().unwrap();
+            render_resource_context.remove_sampler(gpu_data.sampler);
+            gpu_data.sampler =
+                render_resource_context.create_sampler(&quality.sampler(&texture.sampler));
+        }
+    }
+
     let mut changed_textures = HashSet::default();
+    // the events of the textures added while the renderer was pending may be gone
+    if std::mem::take(&mut *renderer_was_pending) {
+        changed_textures.extend(textures.ids().map(Handle::weak));
+    }
     for event in texture_events.iter() {
         match event {
             AssetEvent::Created { handle } => {
-                changed_textures.insert(handle);
+                changed_textures.insert(handle.clone_weak());
             }
             AssetEvent::Modified { handle } => {
-                changed_textures.insert(handle);
+                changed_textures.insert(handle.clone_weak());
+                // dropping the task cancels the upload of the data from before the modification,
+                // the modified texture is queued again below
+                pending_uploads.tasks.remove(handle);
                 // TODO: uncomment this to support mutated textures
                 // remove_current_texture_resources(render_resource_context, handle, &mut textures);
             }
             AssetEvent::Removed { handle } => {
                 remove_current_texture_resources(render_resource_context, handle, &mut textures);
+                // dropping the task cancels it
+                pending_uploads.tasks.remove(handle);
                 // if texture was modified and removed in the same update, ignore the
                 // modification events are ordered so future modification
                 // events are ok
@@ -78,100 +202,194 @@ pub fn texture_resource_system(
     }
 
     for texture_handle in changed_textures.iter() {
-        if let Some(texture) = textures.get_mut(*texture_handle) {
+        if pending_uploads.tasks.contains_key(texture_handle) {
+            continue;
+        }
+        // the texture is only borrowed mutably to store its gpu data, as that sends another
+        // Modified event, which would cancel a pending upload
+        if let Some(texture) = textures.get(texture_handle) {
             // TODO: this avoids creating new textures each frame because storing gpu data in the texture flags it as
             // modified. this prevents hot reloading and therefore can't be used in an actual impl.
             if texture.gpu_data.is_some() {
                 continue;
             }
+            if let Err(err) = texture.validate_usage() {
+                error!("{}, creating {:?} without it", err, texture_handle);
+            }
             // TODO: free old buffers / textures / samplers
 
-            // TODO: using Into for TextureDescriptor is weird
-            let texture_descriptor: TextureDescriptor = (&*texture).into();
-            let texture_id = render_resource_context.create_texture(texture_descriptor);
+            let mip_levels = texture_mip_levels(render_resource_context, texture);
+            if mip_levels
+                .iter()
+                .all(|mip_level| mip_level.aligned_width == mip_level.width)
+            {
+                // rows are already aligned, so the texture data can be copied as is
+                let data = texture_mip_level_data(texture);
+                let gpu_data = upload_texture(
+                    render_resource_context,
+                    &mut asset_upload_queue,
+                    &quality,
+                    texture,
+                    &mip_levels,
+                    &data,
+                );
+                textures.get_mut(texture_handle).unwrap().gpu_data = Some(gpu_data);
+                continue;
+            }
 
-            let sampler_id = render_resource_context.create_sampler(&texture.sampler);
             let format_size = texture.format.pixel_size();
-
-            let texture_view_id = render_resource_context
-                .create_texture_view(texture_id, TextureViewDescriptor::default());
-            texture.gpu_data = Some(TextureGpuData {
-                texture: texture_id,
-                texture_view: texture_view_id,
-                sampler: sampler_id,
+            let data = texture_mip_level_data(texture)
+                .into_iter()
+                .map(|data| data.to_vec())
+                .collect::<Vec<_>>();
+            let task = task_pool.spawn(async move {
+                mip_levels
+                    .iter()
+                    .zip(data.iter())
+                    .map(|(mip_level, data)| {
+                        align_rows(
+                            data,
+                            mip_level.width * format_size,
+                            mip_level.aligned_width * format_size,
+                        )
+                    })
+                    .collect()
             });
+            pending_uploads
+                .tasks
+                .insert(texture_handle.clone_weak(), task);
+        }
+    }
 
-            let mut queue_copy_command = |mip_level, width, height, data: &[u8]| {
-                let aligned_width =
-                    render_resource_context.get_aligned_texture_size(width);
-
-                let mut aligned_data = vec![
-                    0;
-                    format_size
-                        * aligned_width
-                        * height
-                        * texture.size.depth_or_array_layers as usize
-                ];
-                data.chunks_exact(format_size * width)
-                    .enumerate()
-                    .for_each(|(index, row)| {
-                        let offset = index * aligned_width * format_size;
-                        aligned_data[offset..(offset + width * format_size)].copy_from_slice(row);
-                    });
-                let staging_buffer_id = render_resource_context.create_buffer_with_data(
-                    BufferInfo {
-                        buffer_usage: BufferUsage::COPY_SRC,
-                        ..Default::default()
-                    },
-                    &aligned_data,
-                );
-
-                render_command_queue.copy_buffer_to_texture(
-                    staging_buffer_id,
-                    0,
-                    (format_size * aligned_width) as u32,
-                    texture_id,
-                    [0, 0, 0],
-                    mip_level,
-                    Extent3d {
-                        width: width as u32,
-                        height: height as u32,
-                        depth_or_array_layers: texture_descriptor.size.depth_or_array_layers,
-                    }
-                );
-                render_command_queue.free_buffer(staging_buffer_id);
-            };
-
-            queue_copy_command(
-                0,
-                texture.size.width as usize,
-                texture.size.height as usize,
-                texture.data.as_slice(),
+    let mut finished_uploads = Vec::new();
+    for (texture_handle, task) in pending_uploads.tasks.iter_mut() {
+        if let Some(data) = future::block_on(future::poll_once(task)) {
+            finished_uploads.push((texture_handle.clone_weak(), data));
+        }
+    }
+    for (texture_handle, data) in finished_uploads {
+        pending_uploads.tasks.remove(&texture_handle);
+        if let Some(texture) = textures.get_mut(&texture_handle) {
+            let mip_levels = texture_mip_levels(render_resource_context, texture);
+            let data = data.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            let gpu_data = upload_texture(
+                render_resource_context,
+                &mut asset_upload_queue,
+                &quality,
+                texture,
+                &mip_levels,
+                &data,
             );
+            texture.gpu_data = Some(gpu_data);
+        }
+    }
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    /// The width rows are padded to when copying the mip level from a buffer.
+    aligned_width: usize,
+}
+
+fn texture_mip_levels(
+    render_resource_context: &dyn RenderResourceContext,
+    texture: &Texture,
+) -> Vec<MipLevel> {
+    let mip_level_count = 1 + texture
+        .mip_levels_data
+        .as_ref()
+        .map_or(0, |mip_levels| mip_levels.len());
+    let mut width = texture.size.width as usize;
+    let mut height = texture.size.height as usize;
+    let mut mip_levels = Vec::with_capacity(mip_level_count);
+    for _ in 0..mip_level_count {
+        mip_levels.push(MipLevel {
+            width,
+            height,
+            aligned_width: render_resource_context.get_aligned_texture_size(width),
+        });
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+    mip_levels
+}
 
-            if let Some(mip_levels_data) = &texture.mip_levels_data {
-                let mut next_mip_data_len = texture.data.len() / 4;
-                let mut next_mip_width = texture.size.width as usize / 2;
-                let mut next_mip_height = texture.size.height as usize / 2;
+fn texture_mip_level_data(texture: &Texture) -> Vec<&[u8]> {
+    std::iter::once(texture.data.as_slice())
+        .chain(texture.mip_levels_data.iter().flatten().map(Vec::as_slice))
+        .collect()
+}
 
-                for (index, data) in mip_levels_data.iter().enumerate() {
-                    let mip_level = (1 + index) as u32;
+/// Copies rows of `row_size` bytes into rows of `aligned_row_size` bytes.
+fn align_rows(data: &[u8], row_size: usize, aligned_row_size: usize) -> Vec<u8> {
+    let mut aligned_data = vec![0; data.len() / row_size * aligned_row_size];
+    for (row, aligned_row) in data
+        .chunks_exact(row_size)
+        .zip(aligned_data.chunks_exact_mut(aligned_row_size))
+    {
+        aligned_row[..row_size].copy_from_slice(row);
+    }
+    aligned_data
+}
 
-                    assert_eq!(data.len(), next_mip_data_len);
+/// Creates the gpu resources of `texture` and queues copies of the `data` of each mip level,
+/// whose rows must be padded to the aligned width.
+fn upload_texture(
+    render_resource_context: &dyn RenderResourceContext,
+    render_command_queue: &mut RenderCommandQueue,
+    quality: &RenderQualitySettings,
+    texture: &Texture,
+    mip_levels: &[MipLevel],
+    data: &[&[u8]],
+) -> TextureGpuData {
+    // TODO: using Into for TextureDescriptor is weird
+    let mut texture_descriptor: TextureDescriptor = texture.into();
+    // unsupported usages are reported by texture_resource_system
+    texture_descriptor.usage &= texture.format.supported_usage();
+    let texture_id = render_resource_context.create_texture(texture_descriptor);
+    let sampler_id = render_resource_context.create_sampler(&quality.sampler(&texture.sampler));
+    let texture_view_id =
+        render_resource_context.create_texture_view(texture_id, TextureViewDescriptor::default());
 
-                    queue_copy_command(
-                        mip_level,
-                        next_mip_width,
-                        next_mip_height,
-                        data.as_slice(),
-                    );
+    let format_size = texture.format.pixel_size();
+    let layers = texture.size.depth_or_array_layers as usize;
+    for (index, (mip_level, data)) in mip_levels.iter().zip(data.iter()).enumerate() {
+        assert_eq!(
+            data.len(),
+            mip_level.aligned_width * mip_level.height * layers * format_size
+        );
 
-                    next_mip_data_len /= 4;
-                    next_mip_width /= 2;
-                    next_mip_height /= 2;
-                }
-            }
-        }
+        let staging_buffer_id = render_resource_context.create_buffer_with_data(
+            BufferInfo {
+                buffer_usage: BufferUsage::COPY_SRC,
+                ..Default::default()
+            },
+            data,
+        );
+        render_command_queue.copy_buffer_to_texture(
+            staging_buffer_id,
+            0,
+            (format_size * mip_level.aligned_width) as u32,
+            texture_id,
+            [0, 0, 0],
+            index as u32,
+            Extent3d {
+                width: mip_level.width as u32,
+                height: mip_level.height as u32,
+                depth_or_array_layers: texture_descriptor.size.depth_or_array_layers,
+            },
+        );
+        render_command_queue.free_buffer(staging_buffer_id);
+    }
+    if texture.gpu_mipmaps {
+        render_command_queue.generate_mipmaps(texture_id);
+    }
+
+    TextureGpuData {
+        texture: texture_id,
+        texture_view: texture_view_id,
+        sampler: sampler_id,
     }
 }
 
@@ -185,3 +403,14 @@ fn remove_current_texture_resources(
         render_resource_context.remove_sampler(gpu_data.sampler);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::align_rows;
+
+    #[test]
+    fn rows_are_padded() {
+        let data = [1, 2, 3, 4, 5, 6];
+        assert_eq!(align_rows(&data, 3, 4), vec![1, 2, 3, 0, 4, 5, 6, 0]);
+    }
+}
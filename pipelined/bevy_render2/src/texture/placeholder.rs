@@ -0,0 +1,78 @@
+use super::{Extent3d, Texture, TextureDimension, TextureFormat, TextureGpuData};
+use bevy_asset::{Assets, Handle, HandleUntyped};
+use bevy_reflect::TypeUuid;
+
+/// An opaque white 1x1 texture, which leaves colors unchanged when multiplied with them.
+pub const WHITE_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 12299273641913844356);
+/// An opaque black 1x1 texture, e.g. for unset emissive slots.
+pub const BLACK_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 2040896303545495445);
+/// A 1x1 tangent space normal map pointing straight out of the surface, stored in a linear
+/// format.
+pub const NORMAL_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 12737973980839850771);
+/// A fully transparent black 1x1 texture.
+pub const TRANSPARENT_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 891664354940660491);
+
+/// Returns the placeholder textures with their handles, which the
+/// [`TexturePlugin`](super::TexturePlugin) adds to the `Assets<Texture>` at startup.
+pub fn placeholder_textures() -> [(HandleUntyped, Texture); 4] {
+    let texture = |pixel: [u8; 4], format| {
+        Texture::new_fill(Extent3d::new(1, 1, 1), TextureDimension::D2, &pixel, format)
+    };
+    [
+        (
+            WHITE_TEXTURE_HANDLE,
+            texture([255, 255, 255, 255], TextureFormat::Rgba8UnormSrgb),
+        ),
+        (
+            BLACK_TEXTURE_HANDLE,
+            texture([0, 0, 0, 255], TextureFormat::Rgba8UnormSrgb),
+        ),
+        (
+            NORMAL_TEXTURE_HANDLE,
+            texture([128, 128, 255, 255], TextureFormat::Rgba8Unorm),
+        ),
+        (
+            TRANSPARENT_TEXTURE_HANDLE,
+            texture([0, 0, 0, 0], TextureFormat::Rgba8UnormSrgb),
+        ),
+    ]
+}
+
+/// Returns the gpu data of the texture in an optional material slot, or of `placeholder` if the
+/// slot is unset or its texture isn't uploaded yet, so the bind group of the material can always
+/// be created with the same layout. Returns `None` until the placeholder itself is uploaded, in
+/// the first frame.
+pub fn texture_gpu_data_or_placeholder<'a>(
+    textures: &'a Assets<Texture>,
+    handle: Option<&Handle<Texture>>,
+    placeholder: &HandleUntyped,
+) -> Option<&'a TextureGpuData> {
+    handle
+        .and_then(|handle| textures.get(handle))
+        .and_then(|texture| texture.gpu_data.as_ref())
+        .or_else(|| textures.get(placeholder.id)?.gpu_data.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::placeholder_textures;
+    use std::collections::HashSet;
+
+    #[test]
+    fn placeholders_are_single_pixels_with_distinct_handles() {
+        let placeholders = placeholder_textures();
+        for (_, texture) in placeholders.iter() {
+            assert_eq!(texture.size.volume(), 1);
+            assert_eq!(texture.data.len(), texture.format.pixel_size());
+        }
+        let ids = placeholders
+            .iter()
+            .map(|(handle, _)| handle.id)
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), placeholders.len());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    render_resource::SamplerId,
+    renderer::RenderResources,
+    texture::{AddressMode, FilterMode, SamplerDescriptor},
+};
+use bevy_utils::HashMap;
+
+/// Replaces the sampler of the [`Texture`](crate::texture::Texture) an entity is drawn with,
+/// without changing the sampler of the shared texture asset.
+///
+/// The sampler is created once per distinct descriptor and shared through the [`SamplerCache`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOverride(pub SamplerDescriptor);
+
+impl SamplerOverride {
+    /// Nearest-neighbor filtering, e.g. for pixel art.
+    pub fn nearest() -> Self {
+        SamplerOverride(SamplerDescriptor {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
+    /// Linear filtering, e.g. for smoothly scaled textures.
+    pub fn linear() -> Self {
+        SamplerOverride(SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        })
+    }
+
+    /// Uses `address_mode` in every direction.
+    pub fn with_address_mode(mut self, address_mode: AddressMode) -> Self {
+        self.0.set_address_mode(address_mode);
+        self
+    }
+}
+
+impl From<SamplerDescriptor> for SamplerOverride {
+    fn from(descriptor: SamplerDescriptor) -> Self {
+        SamplerOverride(descriptor)
+    }
+}
+
+/// Samplers shared by every user of the same [`SamplerDescriptor`], such as entities with a
+/// [`SamplerOverride`]. Cached samplers live as long as the cache.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerDescriptor, SamplerId>,
+}
+
+impl SamplerCache {
+    /// Returns the sampler for `descriptor`, creating it if it doesn't exist yet.
+    pub fn get(
+        &mut self,
+        render_resources: &RenderResources,
+        descriptor: &SamplerDescriptor,
+    ) -> SamplerId {
+        *self
+            .samplers
+            .entry(*descriptor)
+            .or_insert_with(|| render_resources.create_sampler(descriptor))
+    }
+
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SamplerCache, SamplerOverride};
+    use crate::{
+        renderer::{HeadlessRenderResourceContext, RenderResources},
+        texture::AddressMode,
+    };
+
+    #[test]
+    fn samplers_are_shared() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        let mut cache = SamplerCache::default();
+
+        let nearest = SamplerOverride::nearest().0;
+        let repeat = SamplerOverride::nearest()
+            .with_address_mode(AddressMode::Repeat)
+            .0;
+        let sampler = cache.get(&render_resources, &nearest);
+        assert_eq!(cache.get(&render_resources, &nearest), sampler);
+        assert_ne!(cache.get(&render_resources, &repeat), sampler);
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -1,5 +1,8 @@
 use crate::pipeline::CompareFunction;
-use std::num::NonZeroU8;
+use std::{
+    hash::{Hash, Hasher},
+    num::NonZeroU8,
+};
 
 /// Describes a sampler
 #[derive(Debug, Copy, Clone)]
@@ -26,6 +29,41 @@ impl SamplerDescriptor {
     }
 }
 
+// the lod clamps are compared by their bits, so descriptors can be used as keys
+impl PartialEq for SamplerDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.address_mode_u == other.address_mode_u
+            && self.address_mode_v == other.address_mode_v
+            && self.address_mode_w == other.address_mode_w
+            && self.mag_filter == other.mag_filter
+            && self.min_filter == other.min_filter
+            && self.mipmap_filter == other.mipmap_filter
+            && self.lod_min_clamp.to_bits() == other.lod_min_clamp.to_bits()
+            && self.lod_max_clamp.to_bits() == other.lod_max_clamp.to_bits()
+            && self.compare_function == other.compare_function
+            && self.anisotropy_clamp == other.anisotropy_clamp
+            && self.border_color == other.border_color
+    }
+}
+
+impl Eq for SamplerDescriptor {}
+
+impl Hash for SamplerDescriptor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address_mode_u.hash(state);
+        self.address_mode_v.hash(state);
+        self.address_mode_w.hash(state);
+        self.mag_filter.hash(state);
+        self.min_filter.hash(state);
+        self.mipmap_filter.hash(state);
+        self.lod_min_clamp.to_bits().hash(state);
+        self.lod_max_clamp.to_bits().hash(state);
+        self.compare_function.hash(state);
+        self.anisotropy_clamp.hash(state);
+        self.border_color.hash(state);
+    }
+}
+
 impl Default for SamplerDescriptor {
     fn default() -> Self {
         SamplerDescriptor {
@@ -0,0 +1,363 @@
+use super::{Extent3d, Ktx2, Ktx2TextureLoader, Texture};
+use crate::{camera::Camera, renderer::RenderResources};
+use bevy_app::{App, CoreStage, Plugin};
+use bevy_asset::{
+    AddAsset, AssetEvent, AssetIoError, AssetPath, AssetServer, Assets, Handle, HandleId,
+};
+use bevy_ecs::prelude::*;
+use bevy_math::Vec3;
+use bevy_reflect::TypeUuid;
+use bevy_tasks::{IoTaskPool, Task};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::{tracing::warn, HashMap};
+use futures_lite::future;
+use std::path::PathBuf;
+
+/// Streams the mip levels of KTX2 textures in and out of memory, based on how close the
+/// entities using them are to a camera and on the [`TextureStreamingBudget`].
+///
+/// KTX2 textures are loaded with only their small mip levels resident. Higher resolution levels
+/// are read from the file again when they are needed. Entities with a `Handle<Texture>` use it,
+/// other users like materials are reported to the [`TextureUsers`] resource. This plugin is
+/// opt-in and must be added after the [`TexturePlugin`](super::TexturePlugin).
+#[derive(Default)]
+pub struct TextureStreamingPlugin;
+
+impl Plugin for TextureStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextureStreamingSettings>()
+            .init_resource::<TextureStreamingBudget>()
+            .init_resource::<StreamedTextures>()
+            .init_resource::<TextureUsers>()
+            .add_asset::<StreamedTextureSource>()
+            // runs before texture_resource_system uploads the replaced textures
+            .add_system_to_stage(CoreStage::Update, texture_streaming_system.system());
+
+        let settings = app
+            .world
+            .get_resource::<TextureStreamingSettings>()
+            .unwrap()
+            .clone();
+        let loader = Ktx2TextureLoader {
+            streaming: Some(settings),
+            ..Ktx2TextureLoader::from_world(&mut app.world)
+        };
+        app.add_asset_loader(loader);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureStreamingSettings {
+    /// Textures are loaded with the mip levels that fit in this size resident.
+    pub initial_resident_size: u32,
+    /// The distance from a camera up to which the full resolution is wanted. Every doubling of
+    /// the distance drops one mip level.
+    pub full_resolution_distance: f32,
+}
+
+impl Default for TextureStreamingSettings {
+    fn default() -> Self {
+        TextureStreamingSettings {
+            initial_resident_size: 64,
+            full_resolution_distance: 10.0,
+        }
+    }
+}
+
+/// The memory available to the resident mip levels of streamed textures.
+#[derive(Debug, Clone)]
+pub struct TextureStreamingBudget {
+    pub max_bytes: usize,
+    resident_bytes: usize,
+}
+
+impl TextureStreamingBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        TextureStreamingBudget {
+            max_bytes,
+            resident_bytes: 0,
+        }
+    }
+
+    /// Returns the size of the mip levels of streamed textures that are currently resident.
+    pub fn resident_bytes(&self) -> usize {
+        self.resident_bytes
+    }
+}
+
+impl Default for TextureStreamingBudget {
+    fn default() -> Self {
+        TextureStreamingBudget::new(256 * 1024 * 1024)
+    }
+}
+
+/// Textures used by entities through something other than a `Handle<Texture>` component, like
+/// a material or a lightmap. Plugins add the users of their textures every frame in
+/// [`CoreStage::PreUpdate`], and the [`TextureStreamingPlugin`] streams each texture at the
+/// resolution its closest user needs. The resource only exists with a [`TextureStreamingPlugin`],
+/// so systems reporting users should take an `Option<ResMut<TextureUsers>>`.
+#[derive(Debug, Default)]
+pub struct TextureUsers {
+    users: Vec<(HandleId, Vec3)>,
+}
+
+impl TextureUsers {
+    /// Reports that `texture` is used by an entity at `position` this frame.
+    pub fn add(&mut self, texture: &Handle<Texture>, position: Vec3) {
+        self.users.push((texture.id, position));
+    }
+}
+
+/// The layout of a streamed texture file. Added by the [`Ktx2TextureLoader`] with the label
+/// `"streaming"` next to the texture itself.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "079966dd-d27b-4a12-a11d-3238c61e6246"]
+pub struct StreamedTextureSource {
+    pub ktx2: Ktx2,
+    /// The highest resolution mip level that was loaded.
+    pub base_level: usize,
+}
+
+struct StreamedTexture {
+    source: HandleId,
+    texture: Handle<Texture>,
+    path: PathBuf,
+    ktx2: Ktx2,
+    resident_base_level: usize,
+    loading: Option<(usize, Task<Result<Vec<u8>, AssetIoError>>)>,
+}
+
+/// The streaming state of the textures loaded with a [`TextureStreamingPlugin`].
+#[derive(Default)]
+pub struct StreamedTextures {
+    textures: HashMap<HandleId, StreamedTexture>,
+}
+
+impl StreamedTextures {
+    /// Returns the highest resolution mip level of `texture` that is resident.
+    pub fn resident_base_level(&self, texture: &Handle<Texture>) -> Option<usize> {
+        self.textures
+            .get(&texture.id)
+            .map(|streamed| streamed.resident_base_level)
+    }
+}
+
+/// Returns the mip level wanted for a texture seen from `distance`.
+fn wanted_level(distance: f32, full_resolution_distance: f32, level_count: usize) -> usize {
+    if !distance.is_finite() {
+        return level_count - 1;
+    }
+    let level = (distance / full_resolution_distance)
+        .max(1.0)
+        .log2()
+        .floor() as usize;
+    level.min(level_count - 1)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn texture_streaming_system(
+    settings: Res<TextureStreamingSettings>,
+    mut budget: ResMut<TextureStreamingBudget>,
+    mut streamed_textures: ResMut<StreamedTextures>,
+    asset_server: Res<AssetServer>,
+    task_pool: Res<IoTaskPool>,
+    render_resources: Option<Res<RenderResources>>,
+    sources: Res<Assets<StreamedTextureSource>>,
+    mut source_events: EventReader<AssetEvent<StreamedTextureSource>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut texture_users: ResMut<TextureUsers>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    users: Query<(&Handle<Texture>, &GlobalTransform)>,
+) {
+    let streamed_textures = &mut streamed_textures.textures;
+    for event in source_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                let (source, path) =
+                    match (sources.get(handle), asset_server.get_handle_path(handle)) {
+                        (Some(source), Some(path)) => (source, path.path().to_path_buf()),
+                        _ => continue,
+                    };
+                let texture = Handle::weak(AssetPath::new_ref(&path, None).into());
+                streamed_textures.insert(
+                    texture.id,
+                    StreamedTexture {
+                        source: handle.id,
+                        texture,
+                        path,
+                        ktx2: source.ktx2.clone(),
+                        resident_base_level: source.base_level,
+                        loading: None,
+                    },
+                );
+            }
+            AssetEvent::Removed { handle } => {
+                streamed_textures.retain(|_, streamed| streamed.source != handle.id);
+            }
+        }
+    }
+
+    // the textures are streamed once the renderer is ready
+    let render_resources = match render_resources {
+        Some(render_resources) => render_resources,
+        None => return,
+    };
+
+    // each texture is wanted at the resolution its closest user needs, and unused textures at
+    // the lowest resolution
+    let mut wanted_levels = streamed_textures
+        .iter()
+        .map(|(id, streamed)| (*id, streamed.ktx2.levels.len() - 1))
+        .collect::<HashMap<_, _>>();
+    let users = users
+        .iter()
+        .map(|(texture, transform)| (texture.id, transform.translation))
+        .chain(texture_users.users.drain(..));
+    for (texture, position) in users {
+        if let Some(wanted) = wanted_levels.get_mut(&texture) {
+            let distance = cameras
+                .iter()
+                .map(|camera| camera.translation.distance(position))
+                .fold(f32::INFINITY, f32::min);
+            let level_count = streamed_textures[&texture].ktx2.levels.len();
+            let level = wanted_level(distance, settings.full_resolution_distance, level_count);
+            *wanted = (*wanted).min(level);
+        }
+    }
+
+    // drop the highest resolution mip level of the most detailed texture until the wanted
+    // levels fit in the budget
+    let mut total_bytes = wanted_levels
+        .iter()
+        .map(|(id, level)| streamed_textures[id].ktx2.resident_size(*level))
+        .sum::<usize>();
+    while total_bytes > budget.max_bytes {
+        let most_detailed = wanted_levels
+            .iter_mut()
+            .filter(|(id, level)| **level + 1 < streamed_textures[*id].ktx2.levels.len())
+            .min_by_key(|(_, level)| **level);
+        match most_detailed {
+            Some((id, level)) => {
+                total_bytes -= streamed_textures[id].ktx2.levels[*level].len();
+                *level += 1;
+            }
+            None => break,
+        }
+    }
+
+    for (id, wanted_level) in wanted_levels {
+        let streamed = streamed_textures.get_mut(&id).unwrap();
+        let loaded = match &mut streamed.loading {
+            Some((_, task)) => future::block_on(future::poll_once(task)),
+            None => None,
+        };
+        if let Some(result) = loaded {
+            let (level, _) = streamed.loading.take().unwrap();
+            let texture =
+                result
+                    .map_err(|err| err.to_string())
+                    .and_then(|bytes| match Ktx2::parse(&bytes) {
+                        Ok(ktx2) if ktx2 == streamed.ktx2 => Ok(ktx2.texture(&bytes, level)),
+                        Ok(_) => Err("the file changed".to_string()),
+                        Err(err) => Err(err.to_string()),
+                    });
+            match texture {
+                Ok(texture) => {
+                    replace_texture(&render_resources, &mut textures, &streamed.texture, texture);
+                    streamed.resident_base_level = level;
+                }
+                Err(err) => warn!(
+                    "failed to stream mip levels of {}: {}",
+                    streamed.path.display(),
+                    err
+                ),
+            }
+        }
+
+        if streamed.loading.is_some() || wanted_level == streamed.resident_base_level {
+            continue;
+        }
+        if wanted_level > streamed.resident_base_level {
+            // lower resolution levels are already resident
+            let texture = textures.get(&streamed.texture).and_then(|texture| {
+                drop_mip_levels(texture, wanted_level - streamed.resident_base_level)
+            });
+            if let Some(texture) = texture {
+                replace_texture(&render_resources, &mut textures, &streamed.texture, texture);
+                streamed.resident_base_level = wanted_level;
+            }
+        } else {
+            let asset_server = asset_server.clone();
+            let path = streamed.path.clone();
+            let task =
+                task_pool.spawn(async move { asset_server.asset_io().load_path(&path).await });
+            streamed.loading = Some((wanted_level, task));
+        }
+    }
+
+    budget.resident_bytes = streamed_textures
+        .values()
+        .map(|streamed| streamed.ktx2.resident_size(streamed.resident_base_level))
+        .sum();
+}
+
+/// Returns `texture` without its `count` highest resolution mip levels.
+fn drop_mip_levels(texture: &Texture, count: usize) -> Option<Texture> {
+    let mip_levels = texture.mip_levels_data.as_ref()?;
+    let data = mip_levels.get(count - 1)?.clone();
+    Some(Texture {
+        data,
+        mip_levels_data: mip_levels
+            .get(count..)
+            .filter(|mip_levels| !mip_levels.is_empty())
+            .map(|mip_levels| mip_levels.to_vec()),
+        gpu_data: None,
+        size: Extent3d::new(
+            (texture.size.width >> count).max(1),
+            (texture.size.height >> count).max(1),
+            1,
+        ),
+        format: texture.format,
+        dimension: texture.dimension,
+        sampler: texture.sampler,
+        usage: texture.usage,
+        gpu_mipmaps: false,
+    })
+}
+
+/// Replaces the texture of `handle` with `new_texture`, keeping its import settings. The new
+/// texture is uploaded by the texture_resource_system.
+fn replace_texture(
+    render_resources: &RenderResources,
+    textures: &mut Assets<Texture>,
+    handle: &Handle<Texture>,
+    mut new_texture: Texture,
+) {
+    if let Some(texture) = textures.get_mut(handle) {
+        if let Some(gpu_data) = texture.gpu_data.take() {
+            render_resources.remove_texture(gpu_data.texture);
+            render_resources.remove_sampler(gpu_data.sampler);
+        }
+        if new_texture.format.with_srgb(true) == texture.format.with_srgb(true) {
+            new_texture.format = texture.format;
+        } else if let Some(converted) = new_texture.convert(texture.format) {
+            new_texture = converted;
+        }
+        new_texture.sampler = texture.sampler;
+        *texture = new_texture;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wanted_level;
+
+    #[test]
+    fn wanted_level_halves_with_distance() {
+        assert_eq!(wanted_level(5.0, 10.0, 4), 0);
+        assert_eq!(wanted_level(20.0, 10.0, 4), 1);
+        assert_eq!(wanted_level(45.0, 10.0, 4), 2);
+        assert_eq!(wanted_level(1000.0, 10.0, 4), 3);
+        assert_eq!(wanted_level(f32::INFINITY, 10.0, 4), 3);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{Extent3d, Texture, TextureDimension, TextureFormat};
+use crate::renderer::RenderResources;
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_reflect::TypeUuid;
+
+/// A rectangle of texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TextureRegion {
+    /// Returns the smallest region containing both regions.
+    pub fn union(&self, other: &TextureRegion) -> TextureRegion {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        TextureRegion {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+/// A 2d texture whose contents are replaced often, e.g. by a video decoder, a webcam feed or a
+/// software renderer.
+///
+/// Frames are written to a back buffer, either whole with [`StreamingTexture::back_buffer_mut`]
+/// or in parts with [`StreamingTexture::write_region`], and handed to the GPU with
+/// [`StreamingTexture::present`]. Only the regions written since the last upload are uploaded,
+/// directly into the existing texture. Materials use the [`Texture`] of
+/// [`StreamingTexture::texture`] like any other texture.
+#[derive(Debug, TypeUuid)]
+#[uuid = "81e645da-83ed-4208-95c3-8c98bb8e7452"]
+pub struct StreamingTexture {
+    texture: Handle<Texture>,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    front: Vec<u8>,
+    back: Vec<u8>,
+    /// The region of the back buffer written since the last present.
+    written: Option<TextureRegion>,
+    /// The region of the front buffer that hasn't been uploaded yet.
+    pending_upload: Option<TextureRegion>,
+}
+
+impl StreamingTexture {
+    /// Creates a streaming texture cleared to zero, and the [`Texture`] it uploads to.
+    pub fn new(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        textures: &mut Assets<Texture>,
+    ) -> Self {
+        let size = (width * height) as usize * format.pixel_size();
+        let texture = Texture::new(
+            Extent3d::new(width, height, 1),
+            TextureDimension::D2,
+            vec![0; size],
+            format,
+        );
+        StreamingTexture {
+            texture: textures.add(texture),
+            width,
+            height,
+            format,
+            front: vec![0; size],
+            back: vec![0; size],
+            written: None,
+            pending_upload: None,
+        }
+    }
+
+    /// The texture the frames are uploaded to.
+    pub fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// The back buffer as tightly packed rows, to write a whole frame to.
+    pub fn back_buffer_mut(&mut self) -> &mut [u8] {
+        self.written = Some(self.full_region());
+        &mut self.back
+    }
+
+    /// Writes the tightly packed rows of `data` to `region` of the back buffer.
+    pub fn write_region(&mut self, region: TextureRegion, data: &[u8]) {
+        assert!(
+            region.x + region.width <= self.width && region.y + region.height <= self.height,
+            "the region has to be inside the texture"
+        );
+        if region.width == 0 || region.height == 0 {
+            return;
+        }
+        let pixel_size = self.format.pixel_size();
+        let row_size = region.width as usize * pixel_size;
+        assert_eq!(data.len(), row_size * region.height as usize);
+        for (row, data) in data.chunks_exact(row_size).enumerate() {
+            let start = self.offset(region.x, region.y + row as u32);
+            self.back[start..start + row_size].copy_from_slice(data);
+        }
+        self.written = Some(match self.written {
+            Some(written) => written.union(&region),
+            None => region,
+        });
+    }
+
+    /// Makes the back buffer the front buffer, which is uploaded at the end of the frame.
+    pub fn present(&mut self) {
+        let written = match self.written.take() {
+            Some(written) => written,
+            None => return,
+        };
+        std::mem::swap(&mut self.front, &mut self.back);
+        // the new back buffer holds the frame before, so it gets the written region as well to
+        // stay up to date for partial writes
+        let row_size = written.width as usize * self.format.pixel_size();
+        for y in written.y..written.y + written.height {
+            let start = self.offset(written.x, y);
+            self.back[start..start + row_size]
+                .copy_from_slice(&self.front[start..start + row_size]);
+        }
+        self.pending_upload = Some(match self.pending_upload {
+            Some(pending_upload) => pending_upload.union(&written),
+            None => written,
+        });
+    }
+
+    fn full_region(&self) -> TextureRegion {
+        TextureRegion {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize * self.format.pixel_size()
+    }
+}
+
+/// Uploads the presented regions of [`StreamingTexture`]s, once their textures have been created.
+pub fn streaming_texture_system(
+    render_resources: Option<Res<RenderResources>>,
+    textures: Res<Assets<Texture>>,
+    mut streaming_textures: ResMut<Assets<StreamingTexture>>,
+) {
+    // the presented regions stay pending until the renderer is ready
+    let render_resources = match render_resources {
+        Some(render_resources) => render_resources,
+        None => return,
+    };
+    let pending = streaming_textures
+        .iter()
+        .filter(|(_, streaming_texture)| streaming_texture.pending_upload.is_some())
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    for id in pending {
+        let streaming_texture = streaming_textures.get_mut(id).unwrap();
+        let gpu_data = match textures
+            .get(&streaming_texture.texture)
+            .and_then(|texture| texture.gpu_data.as_ref())
+        {
+            Some(gpu_data) => gpu_data,
+            None => continue,
+        };
+        let region = streaming_texture.pending_upload.take().unwrap();
+        // the rows of the region are read in place, with the stride of the whole texture
+        let bytes_per_row =
+            streaming_texture.width as usize * streaming_texture.format.pixel_size();
+        let start = streaming_texture.offset(region.x, region.y);
+        let end = start
+            + (region.height as usize - 1) * bytes_per_row
+            + region.width as usize * streaming_texture.format.pixel_size();
+        render_resources.write_texture(
+            gpu_data.texture,
+            [region.x, region.y, 0],
+            0,
+            &streaming_texture.front[start..end],
+            bytes_per_row as u32,
+            Extent3d::new(region.width, region.height, 1),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextureRegion;
+
+    #[test]
+    fn region_union() {
+        let a = TextureRegion {
+            x: 2,
+            y: 4,
+            width: 2,
+            height: 2,
+        };
+        let b = TextureRegion {
+            x: 5,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(
+            a.union(&b),
+            TextureRegion {
+                x: 2,
+                y: 0,
+                width: 4,
+                height: 6,
+            }
+        );
+    }
+}
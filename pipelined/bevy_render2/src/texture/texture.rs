@@ -1,6 +1,6 @@
 use super::{
     image_texture_conversion::image_to_texture, Extent3d, SamplerDescriptor, TextureDimension,
-    TextureFormat,
+    TextureFormat, TextureUsage,
 };
 use crate::render_resource::{SamplerId, TextureId, TextureViewId};
 use bevy_reflect::TypeUuid;
@@ -27,6 +27,14 @@ pub struct Texture {
     pub format: TextureFormat,
     pub dimension: TextureDimension,
     pub sampler: SamplerDescriptor,
+    /// How the texture can be used once it is uploaded, for example as a storage texture written
+    /// by a compute shader, or as the source of a copy. It is always created with
+    /// [`TextureUsage::COPY_DST`] too, to upload its data. See [`Texture::validate_usage`].
+    pub usage: TextureUsage,
+    /// Whether the mip levels are generated on the GPU from [`Texture::data`] when the texture is
+    /// uploaded, instead of being copied from [`Texture::mip_levels_data`]. See
+    /// [`Texture::generate_mipmaps`].
+    pub gpu_mipmaps: bool,
 }
 
 impl Default for Texture {
@@ -43,6 +51,8 @@ impl Default for Texture {
             format: TextureFormat::Rgba8UnormSrgb,
             dimension: TextureDimension::D2,
             sampler: Default::default(),
+            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            gpu_mipmaps: false,
         }
     }
 }
@@ -97,14 +107,35 @@ impl Texture {
         value
     }
 
+    /// Returns an error if the format of the texture doesn't support its [`Texture::usage`],
+    /// see [`TextureFormat::supported_usage`]. Textures with unsupported usages are uploaded
+    /// without them.
+    pub fn validate_usage(&self) -> Result<(), TextureError> {
+        let unsupported = (self.usage | TextureUsage::COPY_DST) - self.format.supported_usage();
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(TextureError::UnsupportedUsage {
+                format: self.format,
+                usage: unsupported,
+            })
+        }
+    }
+
     pub fn aspect_2d(&self) -> f32 {
         self.size.height as f32 / self.size.width as f32
     }
 
+    /// Resizes the base level of the texture. Mip levels stored in [`Texture::mip_levels_data`]
+    /// don't match the new size anymore, so they are replaced with generated ones if the format
+    /// supports it (see [`Texture::generate_mipmaps`]), and dropped otherwise.
     pub fn resize(&mut self, size: Extent3d) {
         self.size = size;
         self.data
             .resize(size.volume() * self.format.pixel_size(), 0);
+        if self.mip_levels_data.take().is_some() {
+            let _ = self.generate_mipmaps();
+        }
     }
 
     /// Changes the `size`, asserting that the total number of data elements (pixels) remains the
@@ -142,7 +173,30 @@ impl Texture {
     /// - `TextureFormat::Rg8Unorm`
     /// - `TextureFormat::Rgba8UnormSrgb`
     /// - `TextureFormat::Bgra8UnormSrgb`
+    ///
+    /// `TextureFormat::Rgba32Float` textures can also be converted to `TextureFormat::Rgba16Float`.
     pub fn convert(&self, new_format: TextureFormat) -> Option<Self> {
+        if self.format == TextureFormat::Rgba32Float && new_format == TextureFormat::Rgba16Float {
+            let to_half_float = |data: &[u8]| {
+                data.chunks_exact(4)
+                    .flat_map(|bytes| {
+                        let value = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                        half::f16::from_f32(value).to_ne_bytes()
+                    })
+                    .collect::<Vec<u8>>()
+            };
+            return Some(Texture {
+                data: to_half_float(&self.data),
+                mip_levels_data: self
+                    .mip_levels_data
+                    .as_ref()
+                    .map(|mip_levels| mip_levels.iter().map(|data| to_half_float(data)).collect()),
+                gpu_data: None,
+                format: new_format,
+                ..self.clone()
+            });
+        }
+
         super::image_texture_conversion::texture_to_image(self)
             .and_then(|img| match new_format {
                 TextureFormat::R8Unorm => Some(image::DynamicImage::ImageLuma8(img.into_luma8())),
@@ -160,6 +214,46 @@ impl Texture {
             .map(super::image_texture_conversion::image_to_texture)
     }
 
+    /// Generates a full mip chain down to 1x1 when the texture is uploaded, replacing
+    /// [`Texture::mip_levels_data`]. Each level is rendered with a linear filter from the previous
+    /// one, which decodes and re-encodes sRGB formats, so colors are averaged in linear space.
+    ///
+    /// Only 2D textures with an 8 bit unorm or a 16 bit float format are supported.
+    pub fn generate_mipmaps(&mut self) -> Result<(), TextureError> {
+        let supported_format = matches!(
+            self.format,
+            TextureFormat::R8Unorm
+                | TextureFormat::Rg8Unorm
+                | TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Bgra8Unorm
+                | TextureFormat::Bgra8UnormSrgb
+                | TextureFormat::R16Float
+                | TextureFormat::Rg16Float
+                | TextureFormat::Rgba16Float
+        );
+        if !supported_format || self.dimension != TextureDimension::D2 {
+            return Err(TextureError::UnsupportedMipmapGeneration(self.format));
+        }
+        self.mip_levels_data = None;
+        self.gpu_mipmaps = true;
+        // the mip levels are rendered from the previous level
+        self.usage |= TextureUsage::RENDER_ATTACHMENT;
+        Ok(())
+    }
+
+    /// Returns the number of mip levels of the uploaded texture, including the base level.
+    pub fn mip_level_count(&self) -> u32 {
+        if self.gpu_mipmaps {
+            32 - self.size.width.max(self.size.height).leading_zeros()
+        } else {
+            1 + self
+                .mip_levels_data
+                .as_ref()
+                .map_or(0, |mip_levels| mip_levels.len() as u32)
+        }
+    }
+
     /// Load a bytes buffer in a [`Texture`], according to type `image_type`, using the `image`
     /// crate`
     pub fn from_buffer(buffer: &[u8], image_type: ImageType) -> Result<Texture, TextureError> {
@@ -198,6 +292,13 @@ pub enum TextureError {
     InvalidImageExtension(String),
     #[error("failed to load an image: {0}")]
     ImageError(#[from] image::ImageError),
+    #[error("mipmaps can only be generated for 2d textures with an 8 bit unorm or 16 bit float format, not {0:?}")]
+    UnsupportedMipmapGeneration(TextureFormat),
+    #[error("{format:?} textures can't have the {usage:?} usage")]
+    UnsupportedUsage {
+        format: TextureFormat,
+        usage: TextureUsage,
+    },
 }
 
 /// Type of a raw image buffer
@@ -207,3 +308,91 @@ pub enum ImageType<'a> {
     /// Extension of an image file, for example `"png"`
     Extension(&'a str),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Texture, TextureError};
+    use crate::texture::{Extent3d, TextureDimension, TextureFormat, TextureUsage};
+
+    #[test]
+    fn generate_mipmaps() {
+        let mut texture = Texture::new(
+            Extent3d::new(4, 2, 1),
+            TextureDimension::D2,
+            vec![0, 255, 10, 20, 0, 255, 30, 40],
+            TextureFormat::R8Unorm,
+        );
+        texture.mip_levels_data = Some(vec![vec![128, 25]]);
+        assert_eq!(texture.mip_level_count(), 2);
+
+        texture.generate_mipmaps().unwrap();
+        assert_eq!(texture.mip_levels_data, None);
+        assert_eq!(texture.mip_level_count(), 3);
+        assert!(texture.usage.contains(TextureUsage::RENDER_ATTACHMENT));
+
+        let mut texture = Texture {
+            format: TextureFormat::R32Float,
+            ..Default::default()
+        };
+        assert!(matches!(
+            texture.generate_mipmaps(),
+            Err(TextureError::UnsupportedMipmapGeneration(
+                TextureFormat::R32Float
+            ))
+        ));
+    }
+
+    #[test]
+    fn resize_replaces_mip_levels() {
+        let mut texture = Texture::new(
+            Extent3d::new(2, 2, 1),
+            TextureDimension::D2,
+            vec![0; 4],
+            TextureFormat::R8Unorm,
+        );
+        texture.mip_levels_data = Some(vec![vec![0]]);
+        texture.resize(Extent3d::new(8, 4, 1));
+        assert_eq!(texture.data.len(), 32);
+        assert_eq!(texture.mip_levels_data, None);
+        assert_eq!(texture.mip_level_count(), 4);
+
+        let mut texture = Texture::new(
+            Extent3d::new(2, 2, 1),
+            TextureDimension::D2,
+            vec![0; 16],
+            TextureFormat::R32Float,
+        );
+        texture.mip_levels_data = Some(vec![vec![0; 4]]);
+        texture.resize(Extent3d::new(4, 4, 1));
+        assert_eq!(texture.mip_level_count(), 1);
+    }
+
+    #[test]
+    fn validate_usage() {
+        let mut texture = Texture {
+            usage: TextureUsage::SAMPLED | TextureUsage::STORAGE | TextureUsage::COPY_SRC,
+            format: TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        assert!(texture.validate_usage().is_ok());
+
+        // sRGB formats can't be storage textures
+        texture.format = TextureFormat::Rgba8UnormSrgb;
+        assert!(matches!(
+            texture.validate_usage(),
+            Err(TextureError::UnsupportedUsage { usage, .. }) if usage == TextureUsage::STORAGE
+        ));
+        texture.usage = TextureUsage::SAMPLED | TextureUsage::RENDER_ATTACHMENT;
+        assert!(texture.validate_usage().is_ok());
+        texture.format = TextureFormat::Rgba8Snorm;
+        assert!(texture.validate_usage().is_err());
+
+        // the data of depth textures can't be uploaded
+        texture.usage = TextureUsage::SAMPLED;
+        texture.format = TextureFormat::Depth32Float;
+        assert!(matches!(
+            texture.validate_usage(),
+            Err(TextureError::UnsupportedUsage { usage, .. }) if usage == TextureUsage::COPY_DST
+        ));
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
     render_resource::{TextureId, TextureViewId},
     renderer::RenderResources,
-    texture::{TextureDescriptor, TextureViewDescriptor},
+    texture::{TextureDescriptor, TextureDimension, TextureViewDescriptor},
+};
+use bevy_ecs::{
+    entity::Entity,
+    prelude::{Res, ResMut},
 };
-use bevy_ecs::prelude::{Res, ResMut};
 use bevy_utils::HashMap;
 
 struct CachedTextureMeta {
@@ -13,17 +16,65 @@ struct CachedTextureMeta {
     frames_since_last_use: usize,
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct CachedTexture {
     pub texture: TextureId,
     pub default_view: TextureViewId,
 }
 
+/// The textures of a history returned by [`TextureCache::get_history`].
+#[derive(Clone, Copy, Debug)]
+pub struct CachedHistoryTextures {
+    /// The texture that was written in the previous frame. Newly created history textures are
+    /// zeroed.
+    pub previous: CachedTexture,
+    /// The texture to write in this frame, it is returned as `previous` in the next frame.
+    pub current: CachedTexture,
+}
+
+struct CachedHistoryMeta {
+    descriptor: TextureDescriptor,
+    textures: [CachedTexture; 2],
+    current: usize,
+    frames_since_last_use: usize,
+}
+
+/// What the [`TextureCache`] did during a frame, to check that textures are reused across frames
+/// rather than created every frame, e.g. by a chain of post processing passes. Also a resource of
+/// the app world, see [`RenderDiagnosticsPlugin`](crate::diagnostic::RenderDiagnosticsPlugin).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureCacheStats {
+    /// The textures held by the cache at the end of the frame, including those that weren't used
+    /// and haven't been evicted yet.
+    pub active_textures: usize,
+    /// An estimate of the memory used by the active textures, in bytes.
+    pub bytes: u64,
+    /// The textures reused from a previous frame or from earlier in the frame.
+    pub hits: usize,
+    /// The textures created because no unused texture matched the request.
+    pub misses: usize,
+    /// The textures removed because they weren't used for a few frames, or because the descriptor
+    /// of their history changed.
+    pub evictions: usize,
+}
+
 #[derive(Default)]
 pub struct TextureCache {
     textures: HashMap<TextureDescriptor, Vec<CachedTextureMeta>>,
+    histories: HashMap<(Entity, &'static str), CachedHistoryMeta>,
+    /// The stats of the frame in progress.
+    frame_stats: TextureCacheStats,
+    /// The stats of the last frame, see [`TextureCache::stats`].
+    stats: TextureCacheStats,
 }
 
 impl TextureCache {
+    /// Returns the stats of the last frame, which are updated when [`TextureCache::update`] ends
+    /// the frame.
+    pub fn stats(&self) -> TextureCacheStats {
+        self.stats
+    }
+
     pub fn get(
         &mut self,
         render_resources: &RenderResources,
@@ -35,6 +86,7 @@ impl TextureCache {
                     if !texture.taken {
                         texture.frames_since_last_use = 0;
                         texture.taken = true;
+                        self.frame_stats.hits += 1;
                         return CachedTexture {
                             texture: texture.texture,
                             default_view: texture.default_view,
@@ -42,6 +94,7 @@ impl TextureCache {
                     }
                 }
 
+                self.frame_stats.misses += 1;
                 let texture_id = render_resources.create_texture(entry.key().clone());
                 let view_id = render_resources
                     .create_texture_view(texture_id, TextureViewDescriptor::default());
@@ -57,6 +110,7 @@ impl TextureCache {
                 }
             }
             std::collections::hash_map::Entry::Vacant(entry) => {
+                self.frame_stats.misses += 1;
                 let texture_id = render_resources.create_texture(entry.key().clone());
                 let view_id = render_resources
                     .create_texture_view(texture_id, TextureViewDescriptor::default());
@@ -74,7 +128,66 @@ impl TextureCache {
         }
     }
 
+    /// Returns a pair of textures that persist across frames, for effects that read their own
+    /// output from the previous frame. `name` tells apart multiple histories of the same
+    /// `entity`. This must be called once per frame, the textures are swapped on every call. They
+    /// are recreated when the descriptor changes, e.g. when the view is resized.
+    pub fn get_history(
+        &mut self,
+        render_resources: &RenderResources,
+        entity: Entity,
+        name: &'static str,
+        descriptor: TextureDescriptor,
+    ) -> CachedHistoryTextures {
+        let create_texture = || {
+            let texture = render_resources.create_texture(descriptor);
+            CachedTexture {
+                texture,
+                default_view: render_resources
+                    .create_texture_view(texture, TextureViewDescriptor::default()),
+            }
+        };
+        let stats = &mut self.frame_stats;
+        let history = match self.histories.entry((entity, name)) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                stats.hits += 2;
+                entry.into_mut()
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                stats.misses += 2;
+                entry.insert(CachedHistoryMeta {
+                    descriptor,
+                    textures: [create_texture(), create_texture()],
+                    current: 1,
+                    frames_since_last_use: 0,
+                })
+            }
+        };
+        if history.descriptor != descriptor {
+            for texture in history.textures.iter() {
+                remove_cached_texture(render_resources, texture);
+            }
+            history.descriptor = descriptor;
+            history.textures = [create_texture(), create_texture()];
+            history.current = 1;
+            // the textures counted as hits were replaced
+            stats.hits -= 2;
+            stats.misses += 2;
+            stats.evictions += 2;
+        }
+
+        history.current = 1 - history.current;
+        history.frames_since_last_use = 0;
+        CachedHistoryTextures {
+            previous: history.textures[1 - history.current],
+            current: history.textures[history.current],
+        }
+    }
+
+    /// Ends the frame: evicts the textures that weren't used for a few frames and updates the
+    /// [`TextureCache::stats`].
     pub fn update(&mut self, render_resources: &RenderResources) {
+        let stats = &mut self.frame_stats;
         for textures in self.textures.values_mut() {
             for texture in textures.iter_mut() {
                 texture.frames_since_last_use += 1;
@@ -86,16 +199,137 @@ impl TextureCache {
                 if !should_keep {
                     render_resources.remove_texture_view(texture.default_view);
                     render_resources.remove_texture(texture.texture);
+                    stats.evictions += 1;
                 }
                 should_keep
             });
         }
+
+        self.histories.retain(|_, history| {
+            history.frames_since_last_use += 1;
+            let should_keep = history.frames_since_last_use < 3;
+            if !should_keep {
+                for texture in history.textures.iter() {
+                    remove_cached_texture(render_resources, texture);
+                }
+                stats.evictions += 2;
+            }
+            should_keep
+        });
+
+        let textures = self
+            .textures
+            .iter()
+            .map(|(descriptor, textures)| (descriptor, textures.len()));
+        let histories = self
+            .histories
+            .values()
+            .map(|history| (&history.descriptor, history.textures.len()));
+        for (descriptor, count) in textures.chain(histories) {
+            stats.active_textures += count;
+            stats.bytes += count as u64 * texture_bytes(descriptor);
+        }
+        self.stats = std::mem::take(stats);
     }
 }
 
+/// Estimates the memory used by a texture with `descriptor`, including its mip levels.
+fn texture_bytes(descriptor: &TextureDescriptor) -> u64 {
+    let size = descriptor.size;
+    (0..descriptor.mip_level_count)
+        .map(|level| {
+            let width = (size.width >> level).max(1) as u64;
+            let height = (size.height >> level).max(1) as u64;
+            // only the depth of 3d textures is halved with each level, not their array layers
+            let depth = match descriptor.dimension {
+                TextureDimension::D3 => (size.depth_or_array_layers >> level).max(1),
+                _ => size.depth_or_array_layers,
+            } as u64;
+            width * height * depth
+        })
+        .sum::<u64>()
+        * descriptor.format.pixel_size() as u64
+        * descriptor.sample_count as u64
+}
+
+fn remove_cached_texture(render_resources: &RenderResources, texture: &CachedTexture) {
+    render_resources.remove_texture_view(texture.default_view);
+    render_resources.remove_texture(texture.texture);
+}
+
 pub fn update_texture_cache_system(
     mut texture_cache: ResMut<TextureCache>,
     render_resources: Res<RenderResources>,
 ) {
     texture_cache.update(&render_resources);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{TextureCache, TextureCacheStats};
+    use crate::{
+        renderer::{HeadlessRenderResourceContext, RenderResources},
+        texture::{Extent3d, TextureDescriptor},
+    };
+    use bevy_ecs::entity::Entity;
+
+    #[test]
+    fn history_textures_are_swapped() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        let mut cache = TextureCache::default();
+        let entity = Entity::new(0);
+
+        let first = cache.get_history(&render_resources, entity, "test", Default::default());
+        cache.update(&render_resources);
+        let second = cache.get_history(&render_resources, entity, "test", Default::default());
+        assert_eq!(second.previous.texture, first.current.texture);
+        assert_eq!(second.current.texture, first.previous.texture);
+
+        let resized = TextureDescriptor {
+            size: Extent3d {
+                width: 2,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            ..Default::default()
+        };
+        let third = cache.get_history(&render_resources, entity, "test", resized);
+        assert_ne!(third.previous.texture, second.current.texture);
+        assert_ne!(third.current.texture, second.previous.texture);
+    }
+
+    #[test]
+    fn stats() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        let mut cache = TextureCache::default();
+        // 4 bytes per pixel
+        let descriptor = TextureDescriptor::default();
+
+        cache.get(&render_resources, descriptor);
+        cache.get(&render_resources, descriptor);
+        cache.update(&render_resources);
+        assert_eq!(
+            cache.stats(),
+            TextureCacheStats {
+                active_textures: 2,
+                bytes: 8,
+                hits: 0,
+                misses: 2,
+                evictions: 0,
+            }
+        );
+
+        cache.get(&render_resources, descriptor);
+        cache.update(&render_resources);
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 0);
+
+        // the unused texture is evicted after a few frames
+        cache.get(&render_resources, descriptor);
+        cache.update(&render_resources);
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().active_textures, 1);
+    }
+}
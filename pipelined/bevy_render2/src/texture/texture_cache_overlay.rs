@@ -0,0 +1,275 @@
+use super::{TextureCache, TextureCacheStats, TextureFormat};
+use crate::{
+    core_pipeline::{self, Overlay, Overlays},
+    pass::RenderPass,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_resource::{BufferUsage, BufferVec},
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    view::{ExtractedWindow, ExtractedWindows},
+    RenderApp, RenderStage,
+};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_utils::HashMap;
+use bytemuck::{Pod, Zeroable};
+use std::collections::VecDeque;
+
+/// Draws a graph of the [`TextureCacheStats`] of the last frames in the bottom left corner of
+/// every window. The lower half shows the memory used by the cache relative to its peak, the upper
+/// half the misses (red) and evictions (yellow) of each frame, which should stay empty once the
+/// cache has warmed up.
+///
+/// Must be added after the [`CorePipelinePlugin`](crate::core_pipeline::CorePipelinePlugin).
+#[derive(Default)]
+pub struct TextureCacheOverlayPlugin;
+
+impl TextureCacheOverlayPlugin {
+    pub const TEXTURE_CACHE_OVERLAY_NODE: &'static str = "texture_cache_overlay";
+}
+
+impl Plugin for TextureCacheOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_render_resource::<TextureCacheOverlayMeta>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_texture_cache_overlay.system());
+        render_app
+            .world
+            .get_resource_mut::<Overlays>()
+            .unwrap()
+            .add(TextureCacheOverlay);
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(Self::TEXTURE_CACHE_OVERLAY_NODE, TextureCacheOverlayNode);
+        graph
+            .add_node_edge(
+                Self::TEXTURE_CACHE_OVERLAY_NODE,
+                core_pipeline::node::OVERLAY,
+            )
+            .unwrap();
+    }
+}
+
+/// The number of frames shown by the graph.
+const HISTORY_LENGTH: usize = 120;
+/// The misses and evictions that fill the upper half of the graph.
+const MAX_CHURN: usize = 8;
+
+const MIN: [f32; 2] = [-0.98, -0.98];
+const SIZE: [f32; 2] = [0.6, 0.3];
+
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const BYTES_COLOR: [f32; 4] = [0.2, 0.5, 1.0, 0.9];
+const MISSES_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 0.9];
+const EVICTIONS_COLOR: [f32; 4] = [1.0, 0.9, 0.2, 0.9];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct TextureCacheOverlayVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+pub struct TextureCacheOverlayMeta {
+    history: VecDeque<TextureCacheStats>,
+    vertices: BufferVec<TextureCacheOverlayVertex>,
+    /// The pipelines for each swap chain format, created when a window with a new format appears.
+    pipelines: HashMap<TextureFormat, PipelineId>,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for TextureCacheOverlayMeta {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let vertex_shader = Shader::from_glsl(
+            ShaderStage::Vertex,
+            include_str!("texture_cache_overlay.vert"),
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+        let fragment_shader = Shader::from_glsl(
+            ShaderStage::Fragment,
+            include_str!("texture_cache_overlay.frag"),
+        )
+        .get_spirv_shader(None)
+        .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout.clone(), fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let vertex_buffer_layout = VertexBufferLayout::build("Vertex")
+            .add_attribute(0, "Vertex_Position", VertexFormat::Float32x2)
+            .add_attribute(1, "Vertex_Color", VertexFormat::Float32x4)
+            .finish();
+        vertex_layout
+            .validate_vertex_buffers(std::slice::from_ref(&vertex_buffer_layout))
+            .unwrap();
+        pipeline_layout.vertex_buffer_descriptors = vec![vertex_buffer_layout];
+
+        let mut pipeline_descriptor = RenderPipelineDescriptor {
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        pipeline_descriptor.primitive.cull_mode = None;
+
+        TextureCacheOverlayMeta {
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            vertices: BufferVec::new(BufferUsage::VERTEX),
+            pipelines: HashMap::default(),
+            pipeline_descriptor,
+        }
+    }
+}
+
+fn prepare_texture_cache_overlay(
+    render_resources: Res<RenderResources>,
+    texture_cache: Res<TextureCache>,
+    extracted_windows: Res<ExtractedWindows>,
+    mut meta: ResMut<TextureCacheOverlayMeta>,
+) {
+    let meta = &mut *meta;
+    for window in extracted_windows.values() {
+        let format = window.swap_chain_config.format;
+        if !meta.pipelines.contains_key(&format) {
+            meta.pipeline_descriptor.color_target_states[0].format = format;
+            let pipeline = render_resources.create_render_pipeline(&meta.pipeline_descriptor);
+            meta.pipelines.insert(format, pipeline);
+        }
+    }
+
+    // the stats of the last frame, the cache updates them at the end of the frame
+    if meta.history.len() == HISTORY_LENGTH {
+        meta.history.pop_front();
+    }
+    meta.history.push_back(texture_cache.stats());
+
+    // a background quad, and up to three bars per frame
+    meta.vertices
+        .reserve_and_clear(6 * (1 + 3 * HISTORY_LENGTH), &render_resources);
+    let vertices = &mut meta.vertices;
+    let max = [MIN[0] + SIZE[0], MIN[1] + SIZE[1]];
+    push_quad(vertices, MIN, max, BACKGROUND_COLOR);
+
+    let peak_bytes = meta
+        .history
+        .iter()
+        .map(|stats| stats.bytes)
+        .max()
+        .unwrap_or(0);
+    let bar_width = SIZE[0] / HISTORY_LENGTH as f32;
+    let half_height = SIZE[1] / 2.0;
+    let churn_height = half_height / MAX_CHURN as f32;
+    for (index, stats) in meta.history.iter().enumerate() {
+        let left = MIN[0] + index as f32 * bar_width;
+        let right = left + bar_width;
+        if peak_bytes > 0 && stats.bytes > 0 {
+            let height = half_height * stats.bytes as f32 / peak_bytes as f32;
+            push_quad(
+                vertices,
+                [left, MIN[1]],
+                [right, MIN[1] + height],
+                BYTES_COLOR,
+            );
+        }
+
+        let middle = MIN[1] + half_height;
+        let misses = stats.misses.min(MAX_CHURN);
+        let evictions = stats.evictions.min(MAX_CHURN - misses);
+        let misses_top = middle + misses as f32 * churn_height;
+        if misses > 0 {
+            push_quad(vertices, [left, middle], [right, misses_top], MISSES_COLOR);
+        }
+        if evictions > 0 {
+            let evictions_top = misses_top + evictions as f32 * churn_height;
+            push_quad(
+                vertices,
+                [left, misses_top],
+                [right, evictions_top],
+                EVICTIONS_COLOR,
+            );
+        }
+    }
+    vertices.write_to_staging_buffer(&render_resources);
+}
+
+fn push_quad(
+    vertices: &mut BufferVec<TextureCacheOverlayVertex>,
+    min: [f32; 2],
+    max: [f32; 2],
+    color: [f32; 4],
+) {
+    let corners = [
+        [min[0], min[1]],
+        [max[0], min[1]],
+        [max[0], max[1]],
+        [min[0], min[1]],
+        [max[0], max[1]],
+        [min[0], max[1]],
+    ];
+    for &position in corners.iter() {
+        vertices.push(TextureCacheOverlayVertex { position, color });
+    }
+}
+
+// TODO: this logic can be moved to prepare_texture_cache_overlay once wgpu::Queue is exposed directly
+pub struct TextureCacheOverlayNode;
+
+impl Node for TextureCacheOverlayNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let meta = world.get_resource::<TextureCacheOverlayMeta>().unwrap();
+        meta.vertices.write_to_buffer(render_context);
+        Ok(())
+    }
+}
+
+struct TextureCacheOverlay;
+
+impl Overlay for TextureCacheOverlay {
+    fn draw(&self, world: &World, window: &ExtractedWindow, render_pass: &mut dyn RenderPass) {
+        let meta = world.get_resource::<TextureCacheOverlayMeta>().unwrap();
+        let (pipeline, vertex_buffer) = match (
+            meta.pipelines.get(&window.swap_chain_config.format),
+            meta.vertices.buffer(),
+        ) {
+            (Some(pipeline), Some(vertex_buffer)) => (*pipeline, vertex_buffer),
+            _ => return,
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer, 0);
+        render_pass.draw(0..meta.vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec2 Vertex_Position;
+layout(location = 1) in vec4 Vertex_Color;
+
+layout(location = 0) out vec4 v_Color;
+
+void main() {
+    v_Color = Vertex_Color;
+    gl_Position = vec4(Vertex_Position, 0.0, 1.0);
+}
@@ -19,15 +19,12 @@ impl From<&Texture> for TextureDescriptor {
     fn from(texture: &Texture) -> Self {
         TextureDescriptor {
             size: texture.size,
-            mip_level_count: 1 + texture
-                .mip_levels_data
-                .as_ref()
-                .map(|mip_levels| mip_levels.len() as u32)
-                .unwrap_or(0),
+            mip_level_count: texture.mip_level_count(),
             sample_count: 1,
             dimension: texture.dimension,
             format: texture.format,
-            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            // the data of the texture is copied into it
+            usage: texture.usage | TextureUsage::COPY_DST,
         }
     }
 }
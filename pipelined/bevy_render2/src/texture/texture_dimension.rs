@@ -1,6 +1,8 @@
 // NOTE: These are currently just copies of the wgpu types, but they might change in the future
 
 use bevy_math::Vec3;
+use bevy_reflect::{Reflect, ReflectDeserialize};
+use serde::{Deserialize, Serialize};
 
 /// Dimensions of a particular texture view.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -101,7 +103,10 @@ pub struct PixelInfo {
 /// If there is a conversion in the format (such as srgb -> linear), The conversion listed is for
 /// loading from texture in a shader. When writing to the texture, the opposite conversion takes
 /// place.
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Reflect,
+)]
+#[reflect_value(Serialize, Deserialize, PartialEq, Hash)]
 pub enum TextureFormat {
     // Normal 8 bit formats
     R8Unorm = 0,
@@ -261,6 +266,60 @@ impl TextureFormat {
         let info = self.pixel_info();
         info.type_size * info.num_components
     }
+
+    /// Returns the sRGB or linear variant of this format, if it has one.
+    pub fn with_srgb(self, srgb: bool) -> Self {
+        match (self, srgb) {
+            (TextureFormat::Rgba8Unorm, true) => TextureFormat::Rgba8UnormSrgb,
+            (TextureFormat::Bgra8Unorm, true) => TextureFormat::Bgra8UnormSrgb,
+            (TextureFormat::Rgba8UnormSrgb, false) => TextureFormat::Rgba8Unorm,
+            (TextureFormat::Bgra8UnormSrgb, false) => TextureFormat::Bgra8Unorm,
+            (format, _) => format,
+        }
+    }
+
+    /// Returns the usages textures of this format can be created with. Only some 32 and 128 bit
+    /// formats can be storage textures, snorm formats can't be rendered to, and depth formats
+    /// can't be copied into, as they are only written by render passes.
+    pub fn supported_usage(&self) -> TextureUsage {
+        let storage = matches!(
+            self,
+            TextureFormat::R32Uint
+                | TextureFormat::R32Sint
+                | TextureFormat::R32Float
+                | TextureFormat::Rg32Uint
+                | TextureFormat::Rg32Sint
+                | TextureFormat::Rg32Float
+                | TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8Snorm
+                | TextureFormat::Rgba8Uint
+                | TextureFormat::Rgba8Sint
+                | TextureFormat::Rgba16Uint
+                | TextureFormat::Rgba16Sint
+                | TextureFormat::Rgba16Float
+                | TextureFormat::Rgba32Uint
+                | TextureFormat::Rgba32Sint
+                | TextureFormat::Rgba32Float
+        );
+        let render_attachment = !matches!(
+            self,
+            TextureFormat::R8Snorm
+                | TextureFormat::Rg8Snorm
+                | TextureFormat::Rgba8Snorm
+                | TextureFormat::Rg11b10Float
+        );
+        let copy = match self {
+            TextureFormat::Depth32Float => TextureUsage::COPY_SRC,
+            TextureFormat::Depth24Plus | TextureFormat::Depth24PlusStencil8 => {
+                TextureUsage::empty()
+            }
+            _ => TextureUsage::COPY_SRC | TextureUsage::COPY_DST,
+        };
+        let mut usage = TextureUsage::SAMPLED | copy;
+        usage.set(TextureUsage::STORAGE, storage);
+        usage.set(TextureUsage::RENDER_ATTACHMENT, render_attachment);
+        usage
+    }
 }
 
 impl Default for TextureFormat {
@@ -0,0 +1,109 @@
+use crate::camera::ActiveCameras;
+use bevy_ecs::prelude::*;
+use bevy_math::{Mat4, Vec3};
+use bevy_transform::components::GlobalTransform;
+
+/// The position of the world that is rendered at the origin. A resource of both worlds: it is
+/// set in the app world and extracted every frame.
+///
+/// Extraction subtracts the origin from the transforms of cameras and objects, so the view and
+/// model matrices the GPU multiplies only hold small translations. This removes the jitter of
+/// objects far from the world origin, e.g. in space or flight simulations, as long as they are
+/// near the floating origin. The positions in the app world aren't changed, so their precision
+/// still limits how smoothly objects far from the world origin move.
+///
+/// Systems that extract world positions should use [`FloatingOrigin::to_render`] or
+/// [`FloatingOrigin::to_render_position`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FloatingOrigin {
+    pub origin: Vec3,
+    /// The name of the active camera the origin is moved to every frame, for camera-relative
+    /// rendering, e.g. [`CameraPlugin::CAMERA_3D`](crate::camera::CameraPlugin::CAMERA_3D).
+    pub follow_camera: Option<String>,
+}
+
+impl FloatingOrigin {
+    /// Returns a floating origin that follows the active camera `camera`.
+    pub fn camera_relative(camera: impl Into<String>) -> Self {
+        FloatingOrigin {
+            origin: Vec3::ZERO,
+            follow_camera: Some(camera.into()),
+        }
+    }
+
+    /// Moves the origin by `offset`, e.g. when the player flies too far from it. Has no effect
+    /// while the origin follows a camera.
+    pub fn shift(&mut self, offset: Vec3) {
+        self.origin += offset;
+    }
+
+    /// Returns `transform` relative to the origin.
+    pub fn to_render(&self, transform: &GlobalTransform) -> GlobalTransform {
+        GlobalTransform {
+            translation: transform.translation - self.origin,
+            ..*transform
+        }
+    }
+
+    /// Returns `position` relative to the origin.
+    pub fn to_render_position(&self, position: Vec3) -> Vec3 {
+        position - self.origin
+    }
+
+    /// Returns the matrix of `transform` relative to the origin.
+    pub fn to_render_matrix(&self, transform: &GlobalTransform) -> Mat4 {
+        self.to_render(transform).compute_matrix()
+    }
+}
+
+/// Moves the [`FloatingOrigin`] to the camera it follows, after the transforms were propagated.
+pub fn floating_origin_system(
+    mut floating_origin: ResMut<FloatingOrigin>,
+    active_cameras: Res<ActiveCameras>,
+    cameras: Query<&GlobalTransform>,
+) {
+    let camera = match &floating_origin.follow_camera {
+        Some(camera) => camera,
+        None => return,
+    };
+    let origin = active_cameras
+        .get(camera)
+        .and_then(|camera| camera.entity)
+        .and_then(|entity| cameras.get(entity).ok())
+        .map(|transform| transform.translation);
+    if let Some(origin) = origin {
+        if floating_origin.origin != origin {
+            floating_origin.origin = origin;
+        }
+    }
+}
+
+pub fn extract_floating_origin(mut commands: Commands, floating_origin: Res<FloatingOrigin>) {
+    commands.insert_resource(floating_origin.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FloatingOrigin;
+    use bevy_math::{Quat, Vec3};
+    use bevy_transform::components::GlobalTransform;
+
+    #[test]
+    fn transforms_are_relative_to_the_origin() {
+        let mut floating_origin = FloatingOrigin::default();
+        floating_origin.shift(Vec3::new(1_000_000.0, 0.0, -2_000_000.0));
+        let transform = GlobalTransform {
+            translation: Vec3::new(1_000_010.0, 5.0, -2_000_000.0),
+            rotation: Quat::from_rotation_y(1.0),
+            scale: Vec3::splat(2.0),
+        };
+        let render_transform = floating_origin.to_render(&transform);
+        assert_eq!(render_transform.translation, Vec3::new(10.0, 5.0, 0.0));
+        assert_eq!(render_transform.rotation, transform.rotation);
+        assert_eq!(render_transform.scale, transform.scale);
+        assert_eq!(
+            floating_origin.to_render_position(transform.translation),
+            render_transform.translation
+        );
+    }
+}
@@ -1,17 +1,24 @@
+pub mod floating_origin;
+pub mod visibility;
 pub mod window;
 
 use bevy_transform::components::GlobalTransform;
+pub use floating_origin::*;
+pub use visibility::*;
 pub use window::*;
 
 use crate::{
     render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+    render_phase::ClipRect,
     render_resource::DynamicUniformVec,
     renderer::{RenderContext, RenderResources},
-    RenderStage,
+    RenderApp, RenderStage, RenderSystem,
 };
-use bevy_app::{App, Plugin};
+use bevy_app::{App, CoreStage, Plugin};
 use bevy_ecs::prelude::*;
-use bevy_math::{Mat4, Vec3};
+use bevy_math::{Mat4, Vec2, Vec3};
+use bevy_transform::TransformSystem;
+use bevy_utils::HashMap;
 use crevice::std140::AsStd140;
 
 pub struct ViewPlugin;
@@ -22,10 +29,29 @@ impl ViewPlugin {
 
 impl Plugin for ViewPlugin {
     fn build(&self, app: &mut App) {
-        let render_app = app.sub_app_mut(0);
+        app.add_event::<VisibilityEvent>()
+            .init_resource::<FloatingOrigin>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                floating_origin_system
+                    .system()
+                    .after(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                mesh_aabb_system::<ViewVisibility>.system(),
+            )
+            .add_system_to_stage(CoreStage::Last, view_visibility_system.system());
+
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
             .init_resource::<ViewMeta>()
-            .add_system_to_stage(RenderStage::Prepare, prepare_views.system());
+            .init_resource::<PreviousViewProjections>()
+            .add_system_to_stage(RenderStage::Extract, extract_floating_origin.system())
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_views.system().label(RenderSystem::PrepareViews),
+            );
 
         let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
         graph.add_node(ViewPlugin::VIEW_NODE, ViewNode);
@@ -37,12 +63,51 @@ pub struct ExtractedView {
     pub transform: GlobalTransform,
     pub width: u32,
     pub height: u32,
+    /// The rectangle of the target the view is drawn to, see [`Camera::viewport`].
+    ///
+    /// [`Camera::viewport`]: crate::camera::Camera::viewport
+    pub viewport: Option<ClipRect>,
+}
+
+impl ExtractedView {
+    /// Returns the volume visible from the view, ignoring any [`TemporalJitter`].
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(self.projection * self.transform.compute_matrix().inverse())
+    }
+}
+
+/// Offsets the projection of a view by a fraction of a pixel, e.g. for temporal anti-aliasing.
+/// The offset is in pixels, with y pointing down.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemporalJitter {
+    pub offset: Vec2,
+}
+
+impl TemporalJitter {
+    /// Returns the matrix that applies the jitter to the projection of a `width` x `height` view.
+    pub fn matrix(&self, width: u32, height: u32) -> Mat4 {
+        let ndc_offset =
+            self.offset * Vec2::new(2.0, -2.0) / Vec2::new(width as f32, height as f32);
+        Mat4::from_translation(ndc_offset.extend(0.0))
+    }
 }
 
 #[derive(Clone, AsStd140)]
 pub struct ViewUniformData {
     view_proj: Mat4,
     world_position: Vec3,
+    /// `view_proj` without the [`TemporalJitter`] of the view.
+    unjittered_view_proj: Mat4,
+    /// `unjittered_view_proj` of the previous frame, for motion vectors.
+    previous_view_proj: Mat4,
+}
+
+/// The unjittered view projections of the previous frame, by view entity.
+#[derive(Default)]
+pub struct PreviousViewProjections {
+    view_projections: HashMap<Entity, Mat4>,
+    /// The [`FloatingOrigin`] the view projections are relative to.
+    origin: Vec3,
 }
 
 #[derive(Default)]
@@ -52,27 +117,53 @@ pub struct ViewMeta {
 
 pub struct ViewUniform {
     pub view_uniform_offset: u32,
+    /// The chunk of [`ViewMeta::uniforms`] that holds this view's uniform.
+    pub view_uniform_chunk: usize,
 }
 
 fn prepare_views(
     mut commands: Commands,
     render_resources: Res<RenderResources>,
     mut view_meta: ResMut<ViewMeta>,
-    mut extracted_views: Query<(Entity, &ExtractedView)>,
+    mut previous_view_projections: ResMut<PreviousViewProjections>,
+    floating_origin: Option<Res<FloatingOrigin>>,
+    mut extracted_views: Query<(Entity, &ExtractedView, Option<&TemporalJitter>)>,
 ) {
+    // the previous view projections are moved to the current origin, so shifting the origin
+    // doesn't show up as motion
+    let origin = floating_origin.map_or(Vec3::ZERO, |floating_origin| floating_origin.origin);
+    let origin_shift = Mat4::from_translation(origin - previous_view_projections.origin);
     view_meta
         .uniforms
         .reserve_and_clear(extracted_views.iter_mut().len(), &render_resources);
-    for (entity, camera) in extracted_views.iter() {
+    let mut view_projections = HashMap::default();
+    for (entity, camera, jitter) in extracted_views.iter() {
+        let unjittered_view_proj = camera.projection * camera.transform.compute_matrix().inverse();
+        let view_proj = match jitter {
+            Some(jitter) => jitter.matrix(camera.width, camera.height) * unjittered_view_proj,
+            None => unjittered_view_proj,
+        };
+        let previous_view_proj = previous_view_projections
+            .view_projections
+            .get(&entity)
+            .map(|previous_view_proj| *previous_view_proj * origin_shift)
+            .unwrap_or(unjittered_view_proj);
+        view_projections.insert(entity, unjittered_view_proj);
+        let index = view_meta.uniforms.push(ViewUniformData {
+            view_proj,
+            world_position: camera.transform.translation,
+            unjittered_view_proj,
+            previous_view_proj,
+        });
         let view_uniforms = ViewUniform {
-            view_uniform_offset: view_meta.uniforms.push(ViewUniformData {
-                view_proj: camera.projection * camera.transform.compute_matrix().inverse(),
-                world_position: camera.transform.translation,
-            }),
+            view_uniform_offset: index.offset,
+            view_uniform_chunk: index.chunk,
         };
 
         commands.entity(entity).insert(view_uniforms);
     }
+    previous_view_projections.view_projections = view_projections;
+    previous_view_projections.origin = origin;
 
     view_meta
         .uniforms
@@ -0,0 +1,314 @@
+use crate::{
+    camera::{ActiveCameras, Camera},
+    mesh::Mesh,
+};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{component::Component, prelude::*};
+use bevy_math::{Mat4, Vec3, Vec4, Vec4Swizzles};
+use bevy_transform::components::GlobalTransform;
+
+/// An axis-aligned bounding box, in the local space of the entity it is attached to. Meshes
+/// with an [`Aabb`] are culled when they are outside of the [`Frustum`] of a view.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Aabb {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+}
+
+impl Aabb {
+    pub fn from_min_max(min: Vec3, max: Vec3) -> Self {
+        Aabb {
+            center: (min + max) * 0.5,
+            half_extents: (max - min) * 0.5,
+        }
+    }
+
+    pub fn min(&self) -> Vec3 {
+        self.center - self.half_extents
+    }
+
+    pub fn max(&self) -> Vec3 {
+        self.center + self.half_extents
+    }
+}
+
+/// The planes bounding the volume a view can see, with normals pointing inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    /// `xyz` is the normal of each plane and `w` its distance from the origin, so a point `p`
+    /// is on the inner side of a plane when `normal.dot(p) + w >= 0`.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view-projection matrix mapping depth to `[0, 1]`, in either
+    /// [`DepthMode`](crate::core_pipeline::DepthMode). The far plane of infinite projections
+    /// can't be represented, so it is replaced by a plane that accepts every point.
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        // the columns of the transpose are the rows of the matrix
+        let rows = view_projection.transpose();
+        let mut planes = [
+            rows.w_axis + rows.x_axis,
+            rows.w_axis - rows.x_axis,
+            rows.w_axis + rows.y_axis,
+            rows.w_axis - rows.y_axis,
+            rows.z_axis,
+            rows.w_axis - rows.z_axis,
+        ];
+        for plane in planes.iter_mut() {
+            let length = plane.xyz().length();
+            *plane = if length > f32::EPSILON {
+                *plane / length
+            } else {
+                Vec4::new(0.0, 0.0, 0.0, 1.0)
+            };
+        }
+        Frustum { planes }
+    }
+
+    /// Returns whether `aabb`, transformed by `model`, is at least partially inside the frustum.
+    /// This is conservative, boxes near the corners of the frustum may be reported as inside.
+    pub fn intersects_aabb(&self, aabb: &Aabb, model: &Mat4) -> bool {
+        let center = model.transform_point3(aabb.center);
+        // the extents of the transformed box along each world axis
+        let axes = [
+            model.x_axis.xyz() * aabb.half_extents.x,
+            model.y_axis.xyz() * aabb.half_extents.y,
+            model.z_axis.xyz() * aabb.half_extents.z,
+        ];
+        self.planes.iter().all(|plane| {
+            let normal = plane.xyz();
+            let radius = axes.iter().map(|axis| axis.dot(normal).abs()).sum::<f32>();
+            normal.dot(center) + plane.w >= -radius
+        })
+    }
+}
+
+/// The active cameras an entity is visible in, updated at the end of every frame for entities
+/// that have this component. Entities are culled the same way as meshes are when drawn: those
+/// without an [`Aabb`] are visible in every view, and meshes get one computed when they are
+/// loaded.
+///
+/// A [`VisibilityEvent`] is sent for every change.
+#[derive(Debug, Clone, Default)]
+pub struct ViewVisibility {
+    views: Vec<Entity>,
+}
+
+impl ViewVisibility {
+    /// Returns whether the entity is visible in any view.
+    pub fn is_visible(&self) -> bool {
+        !self.views.is_empty()
+    }
+
+    pub fn is_visible_in(&self, view: Entity) -> bool {
+        self.views.contains(&view)
+    }
+
+    /// The cameras the entity is visible in.
+    pub fn views(&self) -> &[Entity] {
+        &self.views
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityEvent {
+    /// The entity became visible in a view.
+    EnteredView { entity: Entity, view: Entity },
+    /// The entity was culled from a view it was visible in.
+    ExitedView { entity: Entity, view: Entity },
+    /// The entity became visible in a view, after being culled from every view.
+    Shown(Entity),
+    /// The entity was culled from the last view it was visible in.
+    Hidden(Entity),
+}
+
+/// Updates [`ViewVisibility`] with the frustums of the [`ActiveCameras`]. Runs in
+/// [`CoreStage::Last`](bevy_app::CoreStage::Last), once transforms and projections are up to
+/// date.
+pub fn view_visibility_system(
+    active_cameras: Res<ActiveCameras>,
+    mut events: EventWriter<VisibilityEvent>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut query: Query<(Entity, &mut ViewVisibility, &GlobalTransform, Option<&Aabb>)>,
+) {
+    let frustums = active_cameras
+        .iter()
+        .filter_map(|active_camera| {
+            let entity = active_camera.entity?;
+            let (camera, transform) = cameras.get(entity).ok()?;
+            let view_projection = camera.projection_matrix * transform.compute_matrix().inverse();
+            Some((entity, Frustum::from_view_projection(view_projection)))
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, mut visibility, transform, aabb) in query.iter_mut() {
+        let model = transform.compute_matrix();
+        let views = frustums
+            .iter()
+            .filter(|(_, frustum)| aabb.map_or(true, |aabb| frustum.intersects_aabb(aabb, &model)))
+            .map(|(view, _)| *view)
+            .collect::<Vec<_>>();
+        if views == visibility.views {
+            continue;
+        }
+
+        for &view in visibility.views.iter() {
+            if !views.contains(&view) {
+                events.send(VisibilityEvent::ExitedView { entity, view });
+            }
+        }
+        for &view in views.iter() {
+            if !visibility.views.contains(&view) {
+                events.send(VisibilityEvent::EnteredView { entity, view });
+            }
+        }
+        match (visibility.is_visible(), !views.is_empty()) {
+            (false, true) => events.send(VisibilityEvent::Shown(entity)),
+            (true, false) => events.send(VisibilityEvent::Hidden(entity)),
+            _ => {}
+        }
+        visibility.views = views;
+    }
+}
+
+/// Adds an [`Aabb`] to meshes with a `T` component that don't have one, once their mesh is
+/// loaded.
+pub fn mesh_aabb_system<T: Component>(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    query: Query<(Entity, &Handle<Mesh>), (With<T>, Without<Aabb>)>,
+) {
+    for (entity, mesh) in query.iter() {
+        if let Some(aabb) = meshes.get(mesh).and_then(|mesh| mesh.compute_aabb()) {
+            commands.entity(entity).insert(aabb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{view_visibility_system, Aabb, Frustum, ViewVisibility, VisibilityEvent};
+    use crate::camera::{ActiveCameras, Camera};
+    use bevy_ecs::prelude::*;
+    use bevy_math::{Mat4, Vec3};
+    use bevy_transform::components::GlobalTransform;
+
+    fn frustum(reverse_z: bool) -> Frustum {
+        let projection = if reverse_z {
+            Mat4::perspective_infinite_reverse_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1)
+        } else {
+            Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0)
+        };
+        // looking down -z from the origin
+        Frustum::from_view_projection(projection)
+    }
+
+    #[test]
+    fn intersects_aabb() {
+        let aabb = Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+        for &reverse_z in [false, true].iter() {
+            let frustum = frustum(reverse_z);
+            let at = |x, y, z| Mat4::from_translation(Vec3::new(x, y, z));
+            assert!(frustum.intersects_aabb(&aabb, &at(0.0, 0.0, -10.0)));
+            // partially inside
+            assert!(frustum.intersects_aabb(&aabb, &at(10.5, 0.0, -10.0)));
+            assert!(frustum.intersects_aabb(&aabb, &Mat4::IDENTITY));
+            assert!(!frustum.intersects_aabb(&aabb, &at(0.0, 0.0, 10.0)));
+            assert!(!frustum.intersects_aabb(&aabb, &at(13.0, 0.0, -10.0)));
+            assert!(!frustum.intersects_aabb(&aabb, &at(0.0, -13.0, -10.0)));
+            assert_eq!(
+                frustum.intersects_aabb(&aabb, &at(0.0, 0.0, -200.0)),
+                reverse_z
+            );
+        }
+    }
+
+    #[test]
+    fn intersects_rotated_aabb() {
+        let frustum = frustum(false);
+        let aabb = Aabb::from_min_max(Vec3::new(-20.0, -0.5, -0.5), Vec3::new(0.0, 0.5, 0.5));
+        // the box points away from the frustum, then is rotated into it
+        let model = Mat4::from_translation(Vec3::new(-11.0, 0.0, -10.0));
+        assert!(!frustum.intersects_aabb(&aabb, &model));
+        let rotated = model * Mat4::from_rotation_y(std::f32::consts::PI);
+        assert!(frustum.intersects_aabb(&aabb, &rotated));
+    }
+
+    #[test]
+    fn view_visibility() {
+        let mut world = World::default();
+        world.insert_resource(Events::<VisibilityEvent>::default());
+        let camera = world
+            .spawn()
+            .insert_bundle((
+                Camera {
+                    projection_matrix: Mat4::perspective_rh(
+                        std::f32::consts::FRAC_PI_2,
+                        1.0,
+                        0.1,
+                        100.0,
+                    ),
+                    ..Default::default()
+                },
+                GlobalTransform::default(),
+            ))
+            .id();
+        let mut active_cameras = ActiveCameras::default();
+        active_cameras.add("camera");
+        active_cameras.get_mut("camera").unwrap().entity = Some(camera);
+        world.insert_resource(active_cameras);
+        let entity = world
+            .spawn()
+            .insert_bundle((
+                ViewVisibility::default(),
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+                GlobalTransform::from_translation(Vec3::new(0.0, 0.0, -10.0)),
+            ))
+            .id();
+        let mut stage = SystemStage::single(view_visibility_system.system());
+        let mut reader = world
+            .get_resource::<Events<VisibilityEvent>>()
+            .unwrap()
+            .get_reader();
+        let mut read_events = |world: &World| {
+            let events = world.get_resource::<Events<VisibilityEvent>>().unwrap();
+            reader.iter(events).copied().collect::<Vec<_>>()
+        };
+
+        stage.run(&mut world);
+        assert!(world
+            .get::<ViewVisibility>(entity)
+            .unwrap()
+            .is_visible_in(camera));
+        assert_eq!(
+            read_events(&world),
+            vec![
+                VisibilityEvent::EnteredView {
+                    entity,
+                    view: camera
+                },
+                VisibilityEvent::Shown(entity)
+            ]
+        );
+
+        stage.run(&mut world);
+        assert!(read_events(&world).is_empty());
+
+        // behind the camera
+        *world.get_mut::<GlobalTransform>(entity).unwrap() =
+            GlobalTransform::from_translation(Vec3::new(0.0, 0.0, 10.0));
+        stage.run(&mut world);
+        assert!(!world.get::<ViewVisibility>(entity).unwrap().is_visible());
+        assert_eq!(
+            read_events(&world),
+            vec![
+                VisibilityEvent::ExitedView {
+                    entity,
+                    view: camera
+                },
+                VisibilityEvent::Hidden(entity)
+            ]
+        );
+    }
+}
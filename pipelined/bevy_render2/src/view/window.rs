@@ -1,38 +1,320 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
 use crate::{
-    render_resource::{SwapChainDescriptor, TextureViewId},
+    color::Color,
+    render_mode::RenderRequested,
+    render_resource::{
+        SurfaceInfo, SwapChainDescriptor, SwapChainDescriptorError, TextureId, TextureViewId,
+    },
     renderer::RenderResources,
-    RenderStage,
+    texture::{
+        Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+        TextureViewDescriptor,
+    },
+    RenderActive, RenderApp, RenderStage, RenderSystem,
 };
 use bevy_app::{App, Plugin};
 use bevy_ecs::prelude::*;
-use bevy_utils::HashMap;
-use bevy_window::{RawWindowHandleWrapper, WindowId, Windows};
+use bevy_utils::{tracing::warn, HashMap, HashSet};
+use bevy_window::{RawWindowHandleWrapper, WindowId, WindowSurfaceDestroyed, Windows};
+use parking_lot::RwLock;
 
 pub struct WindowRenderPlugin;
 
 impl Plugin for WindowRenderPlugin {
     fn build(&self, app: &mut App) {
-        let render_app = app.sub_app_mut(0);
+        let surface_infos = SurfaceInfos::default();
+        app.init_resource::<SwapChainSettings>()
+            .init_resource::<OffscreenTargets>()
+            .init_resource::<ExternalWindows>()
+            .insert_resource(surface_infos.clone());
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
-            .add_system_to_stage(RenderStage::Extract, extract_windows.system())
-            .add_system_to_stage(RenderStage::Prepare, prepare_windows.system());
+            .init_resource::<OffscreenTextures>()
+            .insert_resource(surface_infos)
+            .add_system_to_stage(
+                RenderStage::Extract,
+                extract_windows.system().label(RenderSystem::ExtractWindows),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_windows.system().label(RenderSystem::PrepareWindows),
+            );
+    }
+}
+
+/// The format and usage of a window's swap chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapChainConfig {
+    /// Pipelines that draw to the window must use this format for their color target.
+    pub format: TextureFormat,
+    /// Add [`TextureUsage::COPY_SRC`] to copy the final frame, for example for screenshots.
+    pub usage: TextureUsage,
+}
+
+impl Default for SwapChainConfig {
+    fn default() -> Self {
+        SwapChainConfig {
+            format: TextureFormat::default(),
+            usage: TextureUsage::RENDER_ATTACHMENT,
+        }
+    }
+}
+
+/// Per-window overrides of the [`SwapChainConfig`]. Windows without an entry use the default.
+/// Unsupported configs are rejected with a warning, falling back to the default.
+#[derive(Default)]
+pub struct SwapChainSettings {
+    configs: HashMap<WindowId, SwapChainConfig>,
+}
+
+impl SwapChainSettings {
+    pub fn insert(&mut self, window_id: WindowId, config: SwapChainConfig) {
+        self.configs.insert(window_id, config);
+    }
+
+    pub fn remove(&mut self, window_id: WindowId) -> Option<SwapChainConfig> {
+        self.configs.remove(&window_id)
+    }
+
+    pub fn get(&self, window_id: WindowId) -> SwapChainConfig {
+        self.configs.get(&window_id).copied().unwrap_or_default()
+    }
+}
+
+/// The [`SurfaceInfo`] of each window with a surface, by window id, so settings menus can offer
+/// only the formats and present modes the window supports. This resource exists in both the app
+/// world and the render world, and both share the same state.
+///
+/// Render backends set the info of a window when they create its surface, so it is available
+/// from the frame after the window is first rendered. Until then, for windows without a surface,
+/// and for surfaces the backend can't query, [`SurfaceInfos::get`] returns `None`. Backends only
+/// report what they know is supported, so a surface may support more than its info lists.
+/// [`SwapChainSettings`] and vsync settings the surface doesn't support are rejected with a
+/// warning.
+#[derive(Clone, Default)]
+pub struct SurfaceInfos {
+    infos: Arc<RwLock<HashMap<WindowId, SurfaceInfo>>>,
+}
+
+impl SurfaceInfos {
+    pub fn get(&self, window_id: WindowId) -> Option<SurfaceInfo> {
+        self.infos.read().get(&window_id).cloned()
+    }
+
+    pub fn contains(&self, window_id: WindowId) -> bool {
+        self.infos.read().contains_key(&window_id)
+    }
+
+    pub fn set(&self, window_id: WindowId, info: SurfaceInfo) {
+        self.infos.write().insert(window_id, info);
+    }
+
+    pub fn remove(&self, window_id: WindowId) -> Option<SurfaceInfo> {
+        self.infos.write().remove(&window_id)
+    }
+}
+
+/// A render target without a window, for example to render without a display in tests. Cameras
+/// render to it when their [`Camera::window`](crate::camera::Camera::window) is set to the id
+/// the target was added with.
+///
+/// The target is backed by a texture with [`TextureUsage::COPY_SRC`] and
+/// [`TextureUsage::SAMPLED`] usage, see [`OffscreenTextures`], unless it renders to an existing
+/// [`OffscreenTarget::view`]. Materials can sample it, e.g. for planar reflections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OffscreenTarget {
+    pub width: u32,
+    pub height: u32,
+    /// Pipelines that draw to the target must use this format for their color target.
+    pub format: TextureFormat,
+    /// A texture view to render to instead of a texture created for the target. It must match
+    /// the size and format of the target. This lets XR runtimes have each eye rendered to one
+    /// array layer of their swap chain images, with views registered by the backend.
+    pub view: Option<TextureViewId>,
+}
+
+impl OffscreenTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        OffscreenTarget {
+            width,
+            height,
+            format: TextureFormat::default(),
+            view: None,
+        }
+    }
+
+    /// A target that renders to `view`, which has the given size and format.
+    pub fn from_view(view: TextureViewId, width: u32, height: u32, format: TextureFormat) -> Self {
+        OffscreenTarget {
+            width,
+            height,
+            format,
+            view: Some(view),
+        }
+    }
+}
+
+/// The [`OffscreenTarget`]s to render to. They are extracted as [`ExtractedWindow`]s without a
+/// window handle.
+#[derive(Default)]
+pub struct OffscreenTargets {
+    targets: HashMap<WindowId, OffscreenTarget>,
+}
+
+impl OffscreenTargets {
+    /// Adds `target` and returns the id cameras use to render to it.
+    pub fn add(&mut self, target: OffscreenTarget) -> WindowId {
+        let id = WindowId::new();
+        self.targets.insert(id, target);
+        id
+    }
+
+    pub fn set(&mut self, id: WindowId, target: OffscreenTarget) {
+        self.targets.insert(id, target);
+    }
+
+    pub fn remove(&mut self, id: WindowId) -> Option<OffscreenTarget> {
+        self.targets.remove(&id)
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&OffscreenTarget> {
+        self.targets.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&WindowId, &OffscreenTarget)> {
+        self.targets.iter()
+    }
+}
+
+/// A window managed outside of bevy, such as a viewport widget of an editor or a Qt application,
+/// that the app renders into through a surface. Cameras render to it when their
+/// [`Camera::window`](crate::camera::Camera::window) is set to the id the window was added with.
+/// Its swap chain is configured with [`SwapChainSettings`], like other windows.
+#[derive(Clone, Debug)]
+pub struct ExternalWindow {
+    /// The handle the backend creates the surface from. `None` when the backend was given a
+    /// surface for the window directly, for example with `WgpuRenderResourceContext::set_window_surface`.
+    pub handle: Option<RawWindowHandleWrapper>,
+    /// The size of the window in physical pixels. The host must keep it up to date, as the swap
+    /// chain has to match the size of the surface.
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub vsync: bool,
+}
+
+/// The [`ExternalWindow`]s to render to. They are extracted as [`ExtractedWindow`]s with a
+/// surface.
+#[derive(Default)]
+pub struct ExternalWindows {
+    windows: HashMap<WindowId, ExternalWindow>,
+}
+
+impl ExternalWindows {
+    /// Adds `window` and returns the id cameras use to render to it.
+    pub fn add(&mut self, window: ExternalWindow) -> WindowId {
+        let id = WindowId::new();
+        self.windows.insert(id, window);
+        id
+    }
+
+    pub fn set(&mut self, id: WindowId, window: ExternalWindow) {
+        self.windows.insert(id, window);
+    }
+
+    /// Updates the size of the window, for example after the host resized it.
+    pub fn set_size(&mut self, id: WindowId, physical_width: u32, physical_height: u32) {
+        if let Some(window) = self.windows.get_mut(&id) {
+            window.physical_width = physical_width;
+            window.physical_height = physical_height;
+        }
+    }
+
+    pub fn remove(&mut self, id: WindowId) -> Option<ExternalWindow> {
+        self.windows.remove(&id)
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&ExternalWindow> {
+        self.windows.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&WindowId, &ExternalWindow)> {
+        self.windows.iter()
+    }
+}
+
+pub struct OffscreenTexture {
+    pub texture: TextureId,
+    pub view: TextureViewId,
+    pub size: Extent3d,
+    pub format: TextureFormat,
+}
+
+/// The textures backing the [`OffscreenTarget`]s in the render world, by target id. Textures are
+/// recreated when their target changes size or format.
+#[derive(Default)]
+pub struct OffscreenTextures {
+    textures: HashMap<WindowId, OffscreenTexture>,
+}
+
+impl OffscreenTextures {
+    pub fn get(&self, id: WindowId) -> Option<&OffscreenTexture> {
+        self.textures.get(&id)
     }
 }
 
 pub struct ExtractedWindow {
     pub id: WindowId,
-    pub handle: RawWindowHandleWrapper,
+    /// The handle to create the window's surface from. `None` for [`OffscreenTarget`]s and for
+    /// [`ExternalWindow`]s that were given a surface directly.
+    pub handle: Option<RawWindowHandleWrapper>,
+    /// Whether the window is presented through a surface and swap chain. `false` for
+    /// [`OffscreenTarget`]s, which render to textures instead.
+    pub has_surface: bool,
     pub physical_width: u32,
     pub physical_height: u32,
     pub vsync: bool,
+    pub transparent: bool,
+    pub swap_chain_config: SwapChainConfig,
     pub swap_chain_texture: Option<TextureViewId>,
 }
 
+impl ExtractedWindow {
+    pub fn physical_size(&self) -> Extent3d {
+        Extent3d::new(self.physical_width, self.physical_height, 1)
+    }
+
+    pub fn swap_chain_descriptor(&self) -> SwapChainDescriptor {
+        SwapChainDescriptor {
+            window_id: self.id,
+            format: self.swap_chain_config.format,
+            usage: self.swap_chain_config.usage,
+            width: self.physical_width,
+            height: self.physical_height,
+            vsync: self.vsync,
+        }
+    }
+
+    /// The color main passes clear the window to, which is transparent for transparent windows.
+    pub fn clear_color(&self) -> Color {
+        if self.transparent {
+            Color::NONE
+        } else {
+            Color::rgb(0.4, 0.4, 0.4)
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ExtractedWindows {
     pub windows: HashMap<WindowId, ExtractedWindow>,
+    /// The windows whose native surface was destroyed since the last frame. Render backends drop
+    /// their swap chains and surfaces, and create new ones from the window handle once the
+    /// windows are extracted again.
+    pub destroyed_surfaces: Vec<WindowId>,
 }
 
 impl Deref for ExtractedWindows {
@@ -49,39 +331,196 @@ impl DerefMut for ExtractedWindows {
     }
 }
 
-fn extract_windows(mut commands: Commands, windows: Res<Windows>) {
-    let mut extracted_windows = ExtractedWindows::default();
+fn extract_windows(
+    mut commands: Commands,
+    render_active: Res<RenderActive>,
+    render_requested: Res<RenderRequested>,
+    windows: Res<Windows>,
+    swap_chain_settings: Res<SwapChainSettings>,
+    offscreen_targets: Res<OffscreenTargets>,
+    external_windows: Res<ExternalWindows>,
+    surface_infos: Res<SurfaceInfos>,
+    mut surface_destroyed_events: EventReader<WindowSurfaceDestroyed>,
+    mut swap_chain_errors: Local<HashSet<(WindowId, SwapChainDescriptorError)>>,
+) {
+    let mut extracted_windows = ExtractedWindows {
+        destroyed_surfaces: surface_destroyed_events
+            .iter()
+            .map(|event| event.id)
+            .collect(),
+        ..Default::default()
+    };
+    let mut errors = HashSet::default();
+    // minimized windows have a size of zero on some platforms, which no swap chain can have, and
+    // windows without a surface, e.g. of a suspended Android app, can't be presented to
+    let mut minimized_windows = 0;
     for window in windows.iter() {
+        if !window.has_surface() || window.physical_width() == 0 || window.physical_height() == 0 {
+            minimized_windows += 1;
+            continue;
+        }
+        let mut extracted_window = ExtractedWindow {
+            id: window.id(),
+            handle: Some(window.raw_window_handle()),
+            has_surface: true,
+            // the swap chain is created at the resolution the window is rendered at
+            physical_width: window.render_width(),
+            physical_height: window.render_height(),
+            vsync: window.vsync(),
+            transparent: window.transparent(),
+            swap_chain_config: swap_chain_settings.get(window.id()),
+            swap_chain_texture: None,
+        };
+        validate_swap_chain_config(
+            &mut extracted_window,
+            surface_infos.get(window.id()),
+            &mut errors,
+        );
+        extracted_windows.insert(window.id(), extracted_window);
+    }
+    for (id, window) in external_windows.iter() {
+        if window.physical_width == 0 || window.physical_height == 0 {
+            minimized_windows += 1;
+            continue;
+        }
+        let mut extracted_window = ExtractedWindow {
+            id: *id,
+            handle: window.handle.clone(),
+            has_surface: true,
+            physical_width: window.physical_width,
+            physical_height: window.physical_height,
+            vsync: window.vsync,
+            transparent: false,
+            swap_chain_config: swap_chain_settings.get(*id),
+            swap_chain_texture: None,
+        };
+        validate_swap_chain_config(&mut extracted_window, surface_infos.get(*id), &mut errors);
+        extracted_windows.insert(*id, extracted_window);
+    }
+    for (id, target) in offscreen_targets.iter() {
         extracted_windows.insert(
-            window.id(),
+            *id,
             ExtractedWindow {
-                id: window.id(),
-                handle: window.raw_window_handle(),
-                physical_width: window.physical_width(),
-                physical_height: window.physical_height(),
-                vsync: window.vsync(),
-                swap_chain_texture: None,
+                id: *id,
+                handle: None,
+                has_surface: false,
+                physical_width: target.width,
+                physical_height: target.height,
+                vsync: false,
+                transparent: false,
+                swap_chain_config: SwapChainConfig {
+                    format: target.format,
+                    usage: TextureUsage::RENDER_ATTACHMENT
+                        | TextureUsage::COPY_SRC
+                        | TextureUsage::SAMPLED,
+                },
+                swap_chain_texture: target.view,
             },
         );
     }
 
+    // only warn about new errors, rather than every frame
+    for (id, err) in errors.difference(&swap_chain_errors) {
+        warn!("unsupported swap chain config for window {}: {}", id, err);
+    }
+    *swap_chain_errors = errors;
+
+    // apps without any window still render, e.g. to capture frames or run compute nodes
+    let all_minimized = minimized_windows > 0 && extracted_windows.is_empty();
+    let active = render_active.0 && render_requested.0 && !all_minimized;
+    commands.insert_resource(RenderActive(active));
     commands.insert_resource(extracted_windows);
 }
 
+/// Falls back to the default config if the window's config is unsupported by surfaces or by the
+/// [`SurfaceInfo`] of the window, and to vsync if the surface can't present without it.
+fn validate_swap_chain_config(
+    window: &mut ExtractedWindow,
+    surface_info: Option<SurfaceInfo>,
+    errors: &mut HashSet<(WindowId, SwapChainDescriptorError)>,
+) {
+    let validate = |window: &ExtractedWindow| {
+        let descriptor = window.swap_chain_descriptor();
+        descriptor.validate().and_then(|_| match &surface_info {
+            Some(surface_info) => surface_info.validate(&descriptor),
+            None => Ok(()),
+        })
+    };
+    // the default config may not support the present mode, so validate again after falling back
+    while let Err(err) = validate(window) {
+        let fell_back = match err {
+            SwapChainDescriptorError::UnsupportedPresentMode(_) => {
+                !std::mem::replace(&mut window.vsync, true)
+            }
+            _ => {
+                let config = std::mem::take(&mut window.swap_chain_config);
+                config != window.swap_chain_config
+            }
+        };
+        errors.insert((window.id, err));
+        if !fell_back {
+            break;
+        }
+    }
+}
+
 pub fn prepare_windows(
+    render_active: Res<RenderActive>,
     mut windows: ResMut<ExtractedWindows>,
+    mut offscreen_textures: ResMut<OffscreenTextures>,
     render_resources: Res<RenderResources>,
 ) {
+    if !render_active.0 {
+        return;
+    }
+    offscreen_textures.textures.retain(|id, texture| {
+        let valid = windows.get(id).map_or(false, |window| {
+            !window.has_surface
+                && window.swap_chain_texture.is_none()
+                && window.physical_size() == texture.size
+                && window.swap_chain_config.format == texture.format
+        });
+        if !valid {
+            render_resources.remove_texture_view(texture.view);
+            render_resources.remove_texture(texture.texture);
+        }
+        valid
+    });
+
     for window in windows.windows.values_mut() {
-        let swap_chain_descriptor = SwapChainDescriptor {
-            window_id: window.id,
-            format: crate::texture::TextureFormat::Bgra8UnormSrgb,
-            width: window.physical_width,
-            height: window.physical_height,
-            vsync: window.vsync,
-        };
+        if window.has_surface {
+            let swap_chain_descriptor = window.swap_chain_descriptor();
+            let swap_chain_texture =
+                render_resources.next_swap_chain_texture(&swap_chain_descriptor);
+            window.swap_chain_texture = Some(swap_chain_texture);
+        } else if window.swap_chain_texture.is_none() {
+            let offscreen_texture = offscreen_textures
+                .textures
+                .entry(window.id)
+                .or_insert_with(|| create_offscreen_texture(&render_resources, window));
+            window.swap_chain_texture = Some(offscreen_texture.view);
+        }
+    }
+}
 
-        let swap_chain_texture = render_resources.next_swap_chain_texture(&swap_chain_descriptor);
-        window.swap_chain_texture = Some(swap_chain_texture);
+fn create_offscreen_texture(
+    render_resources: &RenderResources,
+    window: &ExtractedWindow,
+) -> OffscreenTexture {
+    let size = window.physical_size();
+    let format = window.swap_chain_config.format;
+    let texture = render_resources.create_texture(TextureDescriptor {
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: window.swap_chain_config.usage,
+    });
+    OffscreenTexture {
+        texture,
+        view: render_resources.create_texture_view(texture, TextureViewDescriptor::default()),
+        size,
+        format,
     }
 }
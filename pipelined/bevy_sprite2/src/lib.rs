@@ -1,17 +1,22 @@
 mod bundle;
+mod lighting;
 mod rect;
 mod render;
 mod sprite;
+mod tilemap;
 
 pub use bundle::*;
+pub use lighting::*;
 pub use rect::*;
 pub use render::*;
 pub use sprite::*;
+pub use tilemap::*;
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::IntoSystem;
 use bevy_render2::{
-    core_pipeline, render_graph::RenderGraph, render_phase::DrawFunctions, RenderStage,
+    core_pipeline, render_graph::RenderGraph, render_phase::DrawFunctions,
+    renderer::RenderInitAppExt, RenderApp, RenderStage,
 };
 
 #[derive(Default)]
@@ -19,13 +24,15 @@ pub struct SpritePlugin;
 
 impl Plugin for SpritePlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Sprite>();
-        let render_app = app.sub_app_mut(0);
+        app.register_type::<Sprite>()
+            .add_system_to_stage(CoreStage::PostUpdate, sprite_aabb_system.system())
+            .add_system_to_stage(CoreStage::PostUpdate, request_render_system.system());
+        let render_app = app.sub_app_mut(RenderApp);
         render_app
             .add_system_to_stage(RenderStage::Extract, render::extract_sprites.system())
             .add_system_to_stage(RenderStage::Prepare, render::prepare_sprites.system())
             .add_system_to_stage(RenderStage::Queue, queue_sprites.system())
-            .init_resource::<SpriteShaders>()
+            .init_render_resource::<SpriteShaders>()
             .init_resource::<SpriteMeta>();
         let draw_sprite = DrawSprite::new(&mut render_app.world);
         render_app
@@ -34,7 +41,7 @@ impl Plugin for SpritePlugin {
             .unwrap()
             .write()
             .add(draw_sprite);
-        let render_world = app.sub_app_mut(0).world.cell();
+        let render_world = app.sub_app_mut(RenderApp).world.cell();
         let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
         graph.add_node("sprite", SpriteNode);
         graph
@@ -0,0 +1,45 @@
+#version 450
+
+// NOTE: this must be kept in sync with MAX_POINT_LIGHTS_2D in lighting/mod.rs
+const int MAX_POINT_LIGHTS_2D = 16;
+
+struct PointLight2d {
+    vec4 Color;
+    // the height of the light is in z
+    vec3 Position;
+    float Radius;
+};
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D t_Normals;
+layout(set = 0, binding = 1) uniform sampler s_Normals;
+
+layout(set = 1, binding = 0) uniform Lights2d {
+    mat4 InverseViewProj;
+    vec4 AmbientColor;
+    uint LightCount;
+    PointLight2d Lights[MAX_POINT_LIGHTS_2D];
+};
+
+void main() {
+    ivec2 size = textureSize(sampler2D(t_Normals, s_Normals), 0);
+    vec3 normal = texelFetch(sampler2D(t_Normals, s_Normals), ivec2(gl_FragCoord.xy), 0).xyz;
+    normal = normalize(normal * 2.0 - 1.0);
+
+    vec2 ndc = vec2(gl_FragCoord.x / float(size.x), 1.0 - gl_FragCoord.y / float(size.y)) * 2.0
+        - vec2(1.0, 1.0);
+    vec4 world_position = InverseViewProj * vec4(ndc, 0.0, 1.0);
+    vec2 position = world_position.xy / world_position.w;
+
+    vec3 light = AmbientColor.rgb;
+    for (int i = 0; i < int(LightCount) && i < MAX_POINT_LIGHTS_2D; ++i) {
+        PointLight2d point_light = Lights[i];
+        vec3 to_light = vec3(point_light.Position.xy - position, point_light.Position.z);
+        float attenuation = clamp(1.0 - pow(length(to_light.xy) / point_light.Radius, 2.0), 0.0, 1.0);
+        float diffuse = max(dot(normal, normalize(to_light)), 0.0);
+        light += point_light.Color.rgb * diffuse * attenuation * attenuation;
+    }
+    // multiplied with the sprites by the blend state
+    o_Target = vec4(light, 1.0);
+}
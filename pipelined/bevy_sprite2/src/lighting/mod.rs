@@ -0,0 +1,744 @@
+use crate::{
+    render::{sprite_vertex_buffer_layout, SPRITE_VERTEX_SHADER},
+    DrawSprite, ExtractedSprites, SpriteMeta,
+};
+use bevy_app::prelude::*;
+use bevy_asset::Handle;
+use bevy_ecs::prelude::*;
+use bevy_math::{Mat4, Vec3, Vec4};
+use bevy_reflect::Reflect;
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{self, Transparent2dPhase},
+    pass::*,
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+    render_phase::{DrawFunctions, RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+    },
+    renderer::{RenderContext, RenderInitAppExt, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::*,
+    view::{ExtractedView, FloatingOrigin, ViewMeta, ViewUniform},
+    RenderApp, RenderStage,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use crevice::std140::AsStd140;
+
+pub mod draw_2d_graph {
+    pub mod node {
+        pub const NORMAL_PASS: &'static str = "normal_pass";
+        pub const LIGHT_PASS: &'static str = "light_pass";
+    }
+}
+
+/// The format of the texture sprite normals are drawn into before lighting them.
+pub const NORMALS_2D_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+// NOTE: this must be kept in sync with MAX_POINT_LIGHTS_2D in light_2d.frag
+pub const MAX_POINT_LIGHTS_2D: usize = 16;
+
+/// Lights the sprites seen by 2d cameras with an [`AmbientLight2d`] and [`PointLight2d`]s, using
+/// the [`SpriteNormalMap`] of sprites that have one.
+///
+/// The normals of the sprites are drawn into a texture, the light of every pixel is accumulated
+/// from them and the result is multiplied over the image of the sprite pass. Must be added after
+/// [`crate::SpritePlugin`].
+#[derive(Debug, Default)]
+pub struct Lighting2dPlugin;
+
+impl Plugin for Lighting2dPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PointLight2d>()
+            .register_type::<SpriteNormalMap>()
+            .init_resource::<AmbientLight2d>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                report_normal_map_texture_users.system(),
+            );
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_lights_2d.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_lights_2d.system())
+            .add_system_to_stage(RenderStage::Queue, queue_lights_2d.system())
+            .init_render_resource::<Lighting2dShaders>()
+            .init_resource::<Light2dMeta>();
+
+        let normal_pass_node = NormalPass2dNode::new(&mut render_app.world);
+        let light_pass_node = LightPass2dNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("lighting_2d", Lighting2dNode);
+        graph
+            .add_node_edge("lighting_2d", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+
+        let draw_2d_graph = graph
+            .get_sub_graph_mut(core_pipeline::draw_2d_graph::NAME)
+            .unwrap();
+        draw_2d_graph.add_node(draw_2d_graph::node::NORMAL_PASS, normal_pass_node);
+        draw_2d_graph.add_node(draw_2d_graph::node::LIGHT_PASS, light_pass_node);
+        draw_2d_graph
+            .add_node_edge(
+                draw_2d_graph::node::NORMAL_PASS,
+                draw_2d_graph::node::LIGHT_PASS,
+            )
+            .unwrap();
+        draw_2d_graph
+            .add_node_edge(
+                core_pipeline::draw_2d_graph::node::MAIN_PASS,
+                draw_2d_graph::node::LIGHT_PASS,
+            )
+            .unwrap();
+        let input_node_id = draw_2d_graph.input_node().unwrap().id;
+        draw_2d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_2d_graph::input::VIEW_ENTITY,
+                draw_2d_graph::node::NORMAL_PASS,
+                NormalPass2dNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_2d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_2d_graph::input::VIEW_ENTITY,
+                draw_2d_graph::node::LIGHT_PASS,
+                LightPass2dNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_2d_graph
+            .add_slot_edge(
+                input_node_id,
+                core_pipeline::draw_2d_graph::input::RENDER_TARGET,
+                draw_2d_graph::node::LIGHT_PASS,
+                LightPass2dNode::IN_COLOR_ATTACHMENT,
+            )
+            .unwrap();
+    }
+}
+
+/// A light in 2d, shining on the sprites within `radius` of it.
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PointLight2d {
+    pub color: Color,
+    pub intensity: f32,
+    pub radius: f32,
+    /// The height of the light above the sprites. Lower lights light [`SpriteNormalMap`]s at a
+    /// grazing angle, which gives them more relief.
+    pub height: f32,
+}
+
+impl Default for PointLight2d {
+    fn default() -> Self {
+        PointLight2d {
+            color: Color::WHITE,
+            intensity: 1.0,
+            radius: 200.0,
+            height: 50.0,
+        }
+    }
+}
+
+#[derive(Debug, Bundle, Default)]
+pub struct PointLight2dBundle {
+    pub point_light: PointLight2d,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// The light reaching every sprite, in addition to their [`PointLight2d`]s.
+#[derive(Debug, Clone)]
+pub struct AmbientLight2d {
+    pub color: Color,
+    pub brightness: f32,
+}
+
+impl Default for AmbientLight2d {
+    fn default() -> Self {
+        AmbientLight2d {
+            color: Color::WHITE,
+            brightness: 0.1,
+        }
+    }
+}
+
+/// The normal map of a sprite, lit by [`PointLight2d`]s. Its red, green and blue channels are
+/// the x (right), y (up) and z (towards the camera) components of the normal, mapped to `[0, 1]`,
+/// and it doesn't follow the rotation of the sprite. The texture holds data rather than colors,
+/// so it should be loaded without [`ImageImportSettings::srgb`].
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct SpriteNormalMap(pub Handle<Texture>);
+
+/// Reports [`SpriteNormalMap`]s as used by their sprites, so a [`TextureStreamingPlugin`] streams
+/// them in like the textures of the sprites.
+pub fn report_normal_map_texture_users(
+    texture_users: Option<ResMut<TextureUsers>>,
+    normal_maps: Query<(&SpriteNormalMap, &GlobalTransform)>,
+) {
+    if let Some(mut texture_users) = texture_users {
+        for (normal_map, transform) in normal_maps.iter() {
+            texture_users.add(&normal_map.0, transform.translation);
+        }
+    }
+}
+
+pub struct Lighting2dShaders {
+    normal_pipeline: PipelineId,
+    normal_map_pipeline: PipelineId,
+    /// The layout of the normal pipelines, with a set for the normal map.
+    normal_map_pipeline_descriptor: RenderPipelineDescriptor,
+    light_pipeline: PipelineId,
+    light_pipeline_descriptor: RenderPipelineDescriptor,
+    normals_sampler: SamplerId,
+}
+
+impl FromWorld for Lighting2dShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+
+        let primitive = PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        };
+
+        let normal_pipeline_descriptor = |shader_defs: &[String]| {
+            let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, SPRITE_VERTEX_SHADER)
+                .get_spirv_shader(None)
+                .unwrap();
+            let fragment_shader =
+                Shader::from_glsl(ShaderStage::Fragment, include_str!("sprite_normal.frag"))
+                    .get_spirv_shader(Some(shader_defs))
+                    .unwrap();
+
+            let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+            let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+            let mut pipeline_layout =
+                PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+            pipeline_layout.vertex_buffer_descriptors = vec![sprite_vertex_buffer_layout()];
+            pipeline_layout.bind_groups[0].bindings[0].set_dynamic(true);
+            pipeline_layout.update_bind_group_ids();
+
+            let vertex = render_resources.create_shader_module(&vertex_shader);
+            let fragment = render_resources.create_shader_module(&fragment_shader);
+
+            RenderPipelineDescriptor {
+                depth_stencil: None,
+                color_target_states: vec![ColorTargetState {
+                    format: NORMALS_2D_FORMAT,
+                    // the normals of transparent parts of a sprite are blended with the ones
+                    // behind them
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrite::ALL,
+                }],
+                primitive,
+                ..RenderPipelineDescriptor::new(
+                    ShaderStages {
+                        vertex,
+                        fragment: Some(fragment),
+                    },
+                    pipeline_layout,
+                )
+            }
+        };
+        let normal_pipeline =
+            render_resources.create_render_pipeline(&normal_pipeline_descriptor(&[]));
+        let normal_map_pipeline_descriptor =
+            normal_pipeline_descriptor(&[String::from("NORMAL_MAP")]);
+        let normal_map_pipeline =
+            render_resources.create_render_pipeline(&normal_map_pipeline_descriptor);
+
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("light_2d.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader =
+            Shader::from_glsl(ShaderStage::Fragment, include_str!("light_2d.frag"))
+                .get_spirv_shader(None)
+                .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+        pipeline_layout.bind_groups[1].bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        let light_pipeline_descriptor = RenderPipelineDescriptor {
+            depth_stencil: None,
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                // multiplies the image of the sprite pass with the accumulated light
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Dst,
+                        dst_factor: BlendFactor::Zero,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            primitive,
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+        let light_pipeline = render_resources.create_render_pipeline(&light_pipeline_descriptor);
+
+        Lighting2dShaders {
+            normal_pipeline,
+            normal_map_pipeline,
+            normal_map_pipeline_descriptor,
+            light_pipeline,
+            light_pipeline_descriptor,
+            normals_sampler: render_resources.create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, AsStd140, Default, Debug)]
+pub struct GpuPointLight2d {
+    color: Vec4,
+    /// The position of the light, with its height in z.
+    position: Vec3,
+    radius: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct GpuLights2d {
+    inverse_view_proj: Mat4,
+    ambient_color: Vec4,
+    len: u32,
+    lights: [GpuPointLight2d; MAX_POINT_LIGHTS_2D],
+}
+
+pub struct ExtractedLights2d {
+    ambient_color: Vec4,
+    lights: Vec<GpuPointLight2d>,
+}
+
+pub fn extract_lights_2d(
+    mut commands: Commands,
+    ambient_light: Res<AmbientLight2d>,
+    floating_origin: Res<FloatingOrigin>,
+    lights: Query<(&PointLight2d, &GlobalTransform)>,
+) {
+    // lights beyond the supported count are ignored
+    let lights = lights
+        .iter()
+        .take(MAX_POINT_LIGHTS_2D)
+        .map(|(light, transform)| GpuPointLight2d {
+            color: Vec4::from(light.color.as_linear_rgba_f32()) * light.intensity,
+            position: floating_origin
+                .to_render_position(transform.translation)
+                .truncate()
+                .extend(light.height),
+            radius: light.radius,
+        })
+        .collect();
+    commands.insert_resource(ExtractedLights2d {
+        ambient_color: Vec4::from(ambient_light.color.as_linear_rgba_f32())
+            * ambient_light.brightness,
+        lights,
+    });
+}
+
+#[derive(Default)]
+pub struct Light2dMeta {
+    pub uniforms: DynamicUniformVec<GpuLights2d>,
+    /// The bind groups of the extracted sprites for the normal pass, by sprite index.
+    sprite_bind_groups: Vec<SpriteNormalBindGroups>,
+}
+
+struct SpriteNormalBindGroups {
+    texture: BindGroupId,
+    normal_map: Option<BindGroupId>,
+}
+
+/// The lighting textures and uniform of a 2d view.
+pub struct ViewLights2d {
+    /// The normals of the sprites seen by the view.
+    pub normals: CachedTexture,
+    pub uniform: DynamicUniformIndex,
+}
+
+pub fn prepare_lights_2d(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_resources: Res<RenderResources>,
+    mut light_meta: ResMut<Light2dMeta>,
+    lights: Res<ExtractedLights2d>,
+    views: Query<(Entity, &ExtractedView), With<RenderPhase<Transparent2dPhase>>>,
+) {
+    light_meta
+        .uniforms
+        .reserve_and_clear(views.iter().count(), &render_resources);
+    for (entity, view) in views.iter() {
+        let normals = texture_cache.get(
+            &render_resources,
+            TextureDescriptor {
+                size: Extent3d {
+                    depth_or_array_layers: 1,
+                    width: view.width,
+                    height: view.height,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: NORMALS_2D_FORMAT,
+                usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+            },
+        );
+
+        let mut gpu_lights = GpuLights2d {
+            inverse_view_proj: (view.projection * view.transform.compute_matrix().inverse())
+                .inverse(),
+            ambient_color: lights.ambient_color,
+            len: lights.lights.len() as u32,
+            lights: [GpuPointLight2d::default(); MAX_POINT_LIGHTS_2D],
+        };
+        for (gpu_light, light) in gpu_lights.lights.iter_mut().zip(lights.lights.iter()) {
+            *gpu_light = *light;
+        }
+        let uniform = light_meta.uniforms.push(gpu_lights);
+
+        commands
+            .entity(entity)
+            .insert(ViewLights2d { normals, uniform });
+    }
+
+    light_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+struct Light2dViewBindGroups {
+    /// The view uniform, for the normal pass.
+    view_bind_group: BindGroupId,
+    normals_bind_group: BindGroupId,
+    lights_bind_group: BindGroupId,
+}
+
+pub fn queue_lights_2d(
+    mut commands: Commands,
+    render_resources: Res<RenderResources>,
+    lighting_shaders: Res<Lighting2dShaders>,
+    mut light_meta: ResMut<Light2dMeta>,
+    view_meta: Res<ViewMeta>,
+    extracted_sprites: Res<ExtractedSprites>,
+    views: Query<(Entity, &ViewUniform, &ViewLights2d)>,
+) {
+    let normal_layout = &lighting_shaders.normal_map_pipeline_descriptor.layout;
+    let light_layout = &lighting_shaders.light_pipeline_descriptor.layout;
+    for (entity, view_uniform, view_lights) in views.iter() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+        render_resources.create_bind_group(normal_layout.bind_groups[0].id, &view_bind_group);
+
+        let normals_bind_group = BindGroupBuilder::default()
+            .add_binding(0, view_lights.normals.default_view)
+            .add_binding(1, lighting_shaders.normals_sampler)
+            .finish();
+        render_resources.create_bind_group(light_layout.bind_groups[0].id, &normals_bind_group);
+
+        let lights_bind_group = BindGroupBuilder::default()
+            .add_binding(0, light_meta.uniforms.binding(view_lights.uniform.chunk))
+            .finish();
+        render_resources.create_bind_group(light_layout.bind_groups[1].id, &lights_bind_group);
+
+        commands.entity(entity).insert(Light2dViewBindGroups {
+            view_bind_group: view_bind_group.id,
+            normals_bind_group: normals_bind_group.id,
+            lights_bind_group: lights_bind_group.id,
+        });
+    }
+
+    // TODO: free old bind groups? clear_unused_bind_groups() currently does this for us? Moving to RAII would also do this for us?
+    light_meta.sprite_bind_groups.clear();
+    for sprite in extracted_sprites.sprites.iter() {
+        let texture_bind_group = BindGroupBuilder::default()
+            .add_binding(0, sprite.texture_view)
+            .add_binding(1, sprite.sampler)
+            .finish();
+        render_resources.create_bind_group(normal_layout.bind_groups[1].id, &texture_bind_group);
+        let normal_map_bind_group = sprite.normal_map.map(|(texture_view, sampler)| {
+            let bind_group = BindGroupBuilder::default()
+                .add_binding(0, texture_view)
+                .add_binding(1, sampler)
+                .finish();
+            render_resources.create_bind_group(normal_layout.bind_groups[2].id, &bind_group);
+            bind_group.id
+        });
+        light_meta.sprite_bind_groups.push(SpriteNormalBindGroups {
+            texture: texture_bind_group.id,
+            normal_map: normal_map_bind_group,
+        });
+    }
+}
+
+/// Writes the [`Light2dMeta`] uniforms before the views are drawn.
+pub struct Lighting2dNode;
+
+impl Node for Lighting2dNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let light_meta = world.get_resource::<Light2dMeta>().unwrap();
+        light_meta.uniforms.write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+/// Draws the normals of the sprites of a 2d view into its [`ViewLights2d::normals`], in the
+/// order of its [`Transparent2dPhase`].
+pub struct NormalPass2dNode {
+    query: QueryState<(
+        &'static RenderPhase<Transparent2dPhase>,
+        &'static ExtractedView,
+        &'static ViewUniform,
+        &'static ViewLights2d,
+        &'static Light2dViewBindGroups,
+    )>,
+}
+
+impl NormalPass2dNode {
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for NormalPass2dNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(NormalPass2dNode::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        const INDICES: usize = 6;
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (transparent_phase, view, view_uniform, view_lights, bind_groups) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(view) => view,
+                Err(_) => return Ok(()),
+            };
+
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(view_lights.normals.default_view),
+                resolve_target: None,
+                ops: Operations {
+                    // a flat normal, facing the camera
+                    load: LoadOp::Clear(Color::rgba_linear(0.5, 0.5, 1.0, 0.0)),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+
+        let lighting_shaders = world.get_resource::<Lighting2dShaders>().unwrap();
+        let light_meta = world.get_resource::<Light2dMeta>().unwrap();
+        let sprite_meta = world.get_resource::<SpriteMeta>().unwrap();
+        let draw_sprite = world
+            .get_resource::<DrawFunctions>()
+            .unwrap()
+            .read()
+            .get_id::<DrawSprite>();
+        let layout = &lighting_shaders.normal_map_pipeline_descriptor.layout;
+        let (vertices, indices) =
+            match (sprite_meta.vertices.buffer(), sprite_meta.indices.buffer()) {
+                (Some(vertices), Some(indices)) => (vertices, indices),
+                _ => return Ok(()),
+            };
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass =
+                    TrackedRenderPass::new(render_pass).with_target_size(view.width, view.height);
+                if let Some(viewport) = view.viewport {
+                    tracked_pass.set_viewport(viewport);
+                }
+                tracked_pass.set_vertex_buffer(0, vertices, 0);
+                tracked_pass.set_index_buffer(indices, 0, IndexFormat::Uint32);
+                for drawable in transparent_phase.drawn_things.iter() {
+                    if Some(drawable.draw_function) != draw_sprite
+                        || !tracked_pass.set_clip_rect(drawable.clip)
+                    {
+                        continue;
+                    }
+                    let sprite_bind_groups = &light_meta.sprite_bind_groups[drawable.draw_key];
+                    match sprite_bind_groups.normal_map {
+                        Some(normal_map_bind_group) => {
+                            tracked_pass.set_pipeline(lighting_shaders.normal_map_pipeline);
+                            tracked_pass.set_bind_group(
+                                2,
+                                layout.bind_groups[2].id,
+                                normal_map_bind_group,
+                                None,
+                            );
+                        }
+                        None => tracked_pass.set_pipeline(lighting_shaders.normal_pipeline),
+                    }
+                    tracked_pass.set_bind_group(
+                        0,
+                        layout.bind_groups[0].id,
+                        bind_groups.view_bind_group,
+                        Some(&[view_uniform.view_uniform_offset]),
+                    );
+                    tracked_pass.set_bind_group(
+                        1,
+                        layout.bind_groups[1].id,
+                        sprite_bind_groups.texture,
+                        None,
+                    );
+                    let first_index = (drawable.draw_key * INDICES) as u32;
+                    tracked_pass.draw_indexed(first_index..first_index + INDICES as u32, 0, 0..1);
+                }
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Accumulates the light of every pixel of a 2d view and multiplies it over the image of the
+/// sprite pass.
+pub struct LightPass2dNode {
+    query: QueryState<(&'static ViewLights2d, &'static Light2dViewBindGroups)>,
+}
+
+impl LightPass2dNode {
+    pub const IN_COLOR_ATTACHMENT: &'static str = "color_attachment";
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for LightPass2dNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![
+            SlotInfo::new(LightPass2dNode::IN_COLOR_ATTACHMENT, SlotType::TextureView),
+            SlotInfo::new(LightPass2dNode::IN_VIEW, SlotType::Entity),
+        ]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (view_lights, bind_groups) = match self.query.get_manual(world, view_entity) {
+            Ok(view) => view,
+            Err(_) => return Ok(()),
+        };
+        let color_attachment_texture = graph.get_input_texture(Self::IN_COLOR_ATTACHMENT)?;
+        let pass_descriptor = PassDescriptor {
+            color_attachments: vec![RenderPassColorAttachment {
+                attachment: TextureAttachment::Id(color_attachment_texture),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            sample_count: 1,
+        };
+
+        let lighting_shaders = world.get_resource::<Lighting2dShaders>().unwrap();
+        let layout = &lighting_shaders.light_pipeline_descriptor.layout;
+
+        render_context.begin_render_pass(
+            &pass_descriptor,
+            &mut |render_pass: &mut dyn RenderPass| {
+                let mut tracked_pass = TrackedRenderPass::new(render_pass);
+                tracked_pass.set_pipeline(lighting_shaders.light_pipeline);
+                tracked_pass.set_bind_group(
+                    0,
+                    layout.bind_groups[0].id,
+                    bind_groups.normals_bind_group,
+                    None,
+                );
+                tracked_pass.set_bind_group(
+                    1,
+                    layout.bind_groups[1].id,
+                    bind_groups.lights_bind_group,
+                    Some(&[view_lights.uniform.offset]),
+                );
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
+        Ok(())
+    }
+}
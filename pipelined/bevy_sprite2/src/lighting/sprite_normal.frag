@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_Color;
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform texture2D sprite_texture;
+layout(set = 1, binding = 1) uniform sampler sprite_sampler;
+
+#ifdef NORMAL_MAP
+layout(set = 2, binding = 0) uniform texture2D normal_map;
+layout(set = 2, binding = 1) uniform sampler normal_map_sampler;
+#endif
+
+void main() {
+    float alpha = v_Color.a * texture(sampler2D(sprite_texture, sprite_sampler), v_Uv).a;
+    // normals stay encoded in [0, 1], as in the normal map
+#ifdef NORMAL_MAP
+    vec3 normal = texture(sampler2D(normal_map, normal_map_sampler), v_Uv).rgb;
+#else
+    vec3 normal = vec3(0.5, 0.5, 1.0);
+#endif
+    o_Target = vec4(normal, alpha);
+}
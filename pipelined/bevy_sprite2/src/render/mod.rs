@@ -1,9 +1,9 @@
-use crate::Sprite;
+use crate::{Sprite, SpriteNormalMap};
 use bevy_asset::{Assets, Handle};
 use bevy_ecs::{prelude::*, system::SystemState};
 use bevy_math::{Mat4, Vec2, Vec3, Vec4Swizzles};
 use bevy_render2::{
-    core_pipeline::Transparent2dPhase,
+    core_pipeline::{SortMode2d, Transparent2dPhase, YSortOffset, ZIndex},
     mesh::{shape::Quad, Indices, Mesh, VertexAttributeValues},
     pipeline::*,
     render_graph::{Node, NodeRunError, RenderGraphContext},
@@ -13,31 +13,80 @@ use bevy_render2::{
     },
     renderer::{RenderContext, RenderResources},
     shader::{Shader, ShaderStage, ShaderStages},
-    texture::{Texture, TextureFormat},
-    view::{ViewMeta, ViewUniform},
+    texture::{
+        BindlessBatches, BindlessTextures, SamplerCache, SamplerOverride, Texture, TextureFormat,
+    },
+    view::{FloatingOrigin, ViewMeta, ViewUniform},
 };
 use bevy_transform::components::GlobalTransform;
 use bevy_utils::HashMap;
 use bytemuck::{Pod, Zeroable};
 
+pub(crate) const SPRITE_VERTEX_SHADER: &str = include_str!("sprite.vert");
+pub(crate) const SPRITE_FRAGMENT_SHADER: &str = include_str!("sprite.frag");
+
+/// The layout of the vertex buffer in [`SpriteMeta`], shared by the pipelines drawing sprites.
+pub(crate) fn sprite_vertex_buffer_layout() -> VertexBufferLayout {
+    VertexBufferLayout {
+        stride: 40,
+        name: "Vertex".into(),
+        step_mode: InputStepMode::Vertex,
+        attributes: vec![
+            VertexAttribute {
+                name: "Vertex_Position".into(),
+                format: VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 0,
+            },
+            VertexAttribute {
+                name: "Vertex_Uv".into(),
+                format: VertexFormat::Float32x2,
+                offset: 12,
+                shader_location: 1,
+            },
+            VertexAttribute {
+                name: "Vertex_Color".into(),
+                format: VertexFormat::Float32x4,
+                offset: 20,
+                shader_location: 2,
+            },
+            VertexAttribute {
+                name: "Vertex_TextureIndex".into(),
+                format: VertexFormat::Uint32,
+                offset: 36,
+                shader_location: 3,
+            },
+        ],
+    }
+}
+
 pub struct SpriteShaders {
     pipeline: PipelineId,
     pipeline_descriptor: RenderPipelineDescriptor,
+    /// Set if the sprites are drawn with [`BindlessTextures`], which binds the textures of the
+    /// sprites in binding arrays indexed with the `texture_index` of their vertices.
+    bindless: Option<BindlessTextures>,
 }
 
 // TODO: this pattern for initializing the shaders / pipeline isn't ideal. this should be handled by the asset system
 impl FromWorld for SpriteShaders {
     fn from_world(world: &mut World) -> Self {
+        let bindless = BindlessTextures::get(world, 0);
         let render_resources = world.get_resource::<RenderResources>().unwrap();
-        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("sprite.vert"))
-            .get_spirv_shader(None)
+        let shader_defs = bindless.map(|_| vec![String::from("BINDLESS")]);
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, SPRITE_VERTEX_SHADER)
+            .get_spirv_shader(shader_defs.as_deref())
             .unwrap();
-        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, include_str!("sprite.frag"))
-            .get_spirv_shader(None)
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, SPRITE_FRAGMENT_SHADER)
+            .get_spirv_shader(shader_defs.as_deref())
             .unwrap();
 
         let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
-        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader
+            .reflect_layout(&bindless.map_or_else(Default::default, |bindless| {
+                bindless.reflect_options("sprite_textures")
+            }))
+            .unwrap();
 
         let mut pipeline_layout =
             PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
@@ -45,25 +94,7 @@ impl FromWorld for SpriteShaders {
         let vertex = render_resources.create_shader_module(&vertex_shader);
         let fragment = render_resources.create_shader_module(&fragment_shader);
 
-        pipeline_layout.vertex_buffer_descriptors = vec![VertexBufferLayout {
-            stride: 20,
-            name: "Vertex".into(),
-            step_mode: InputStepMode::Vertex,
-            attributes: vec![
-                VertexAttribute {
-                    name: "Vertex_Position".into(),
-                    format: VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                VertexAttribute {
-                    name: "Vertex_Uv".into(),
-                    format: VertexFormat::Float32x2,
-                    offset: 12,
-                    shader_location: 1,
-                },
-            ],
-        }];
+        pipeline_layout.vertex_buffer_descriptors = vec![sprite_vertex_buffer_layout()];
 
         pipeline_layout.bind_groups[0].bindings[0].set_dynamic(true);
 
@@ -108,35 +139,71 @@ impl FromWorld for SpriteShaders {
         SpriteShaders {
             pipeline,
             pipeline_descriptor,
+            bindless,
         }
     }
 }
 
-struct ExtractedSprite {
+pub(crate) struct ExtractedSprite {
+    entity: Entity,
     transform: Mat4,
     size: Vec2,
-    texture_view: TextureViewId,
-    sampler: SamplerId,
+    color: [f32; 4],
+    sort_key: usize,
+    pub(crate) texture_view: TextureViewId,
+    pub(crate) sampler: SamplerId,
+    /// The texture view and sampler of the [`SpriteNormalMap`], once it is loaded.
+    pub(crate) normal_map: Option<(TextureViewId, SamplerId)>,
 }
 
 pub struct ExtractedSprites {
-    sprites: Vec<ExtractedSprite>,
+    pub(crate) sprites: Vec<ExtractedSprite>,
 }
 
 pub fn extract_sprites(
     mut commands: Commands,
+    render_resources: Res<RenderResources>,
     textures: Res<Assets<Texture>>,
-    query: Query<(&Sprite, &GlobalTransform, &Handle<Texture>)>,
+    mut sampler_cache: ResMut<SamplerCache>,
+    sort_mode: Res<SortMode2d>,
+    floating_origin: Res<FloatingOrigin>,
+    query: Query<(
+        Entity,
+        &Sprite,
+        &GlobalTransform,
+        &Handle<Texture>,
+        Option<&SamplerOverride>,
+        Option<&SpriteNormalMap>,
+        Option<&ZIndex>,
+        Option<&YSortOffset>,
+    )>,
 ) {
     let mut extracted_sprites = Vec::new();
-    for (sprite, transform, handle) in query.iter() {
+    for (entity, sprite, transform, handle, sampler_override, normal_map, z_index, y_offset) in
+        query.iter()
+    {
         if let Some(texture) = textures.get(handle) {
             if let Some(gpu_data) = &texture.gpu_data {
+                let sampler = match sampler_override {
+                    Some(SamplerOverride(descriptor)) => {
+                        sampler_cache.get(&render_resources, descriptor)
+                    }
+                    None => gpu_data.sampler,
+                };
                 extracted_sprites.push(ExtractedSprite {
-                    transform: transform.compute_matrix(),
+                    entity,
+                    transform: floating_origin.to_render_matrix(transform),
                     size: sprite.size,
+                    color: sprite.color.as_linear_rgba_f32(),
+                    sort_key: z_index
+                        .copied()
+                        .unwrap_or_default()
+                        .sort_key(sort_mode.depth(transform.translation, y_offset)),
                     texture_view: gpu_data.texture_view,
-                    sampler: gpu_data.sampler,
+                    sampler,
+                    normal_map: normal_map
+                        .and_then(|normal_map| textures.get(&normal_map.0)?.gpu_data.as_ref())
+                        .map(|gpu_data| (gpu_data.texture_view, gpu_data.sampler)),
                 })
             }
         }
@@ -149,16 +216,22 @@ pub fn extract_sprites(
 
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-struct SpriteVertex {
+pub(crate) struct SpriteVertex {
     pub position: [f32; 3],
     pub uv: [f32; 2],
+    pub color: [f32; 4],
+    /// The index of the texture of the sprite in its binding array, with [`BindlessTextures`].
+    pub texture_index: u32,
 }
 
 pub struct SpriteMeta {
-    vertices: BufferVec<SpriteVertex>,
-    indices: BufferVec<u32>,
+    pub(crate) vertices: BufferVec<SpriteVertex>,
+    pub(crate) indices: BufferVec<u32>,
     quad: Mesh,
+    /// The texture bind group of each sprite, by draw key.
     texture_bind_groups: Vec<BindGroupId>,
+    /// The binding arrays of the textures of the sprites, with [`BindlessTextures`].
+    bindless_batches: Option<BindlessBatches>,
 }
 
 impl Default for SpriteMeta {
@@ -167,6 +240,7 @@ impl Default for SpriteMeta {
             vertices: BufferVec::new(BufferUsage::VERTEX),
             indices: BufferVec::new(BufferUsage::INDEX),
             texture_bind_groups: Vec::new(),
+            bindless_batches: None,
             quad: Quad {
                 size: Vec2::new(1.0, 1.0),
                 ..Default::default()
@@ -178,9 +252,19 @@ impl Default for SpriteMeta {
 
 pub fn prepare_sprites(
     render_resources: Res<RenderResources>,
+    sprite_shaders: Res<SpriteShaders>,
     mut sprite_meta: ResMut<SpriteMeta>,
     extracted_sprites: Res<ExtractedSprites>,
 ) {
+    let sprite_meta = &mut *sprite_meta;
+    sprite_meta.vertices.clear();
+    sprite_meta.indices.clear();
+    if let Some(bindless) = sprite_shaders.bindless {
+        sprite_meta
+            .bindless_batches
+            .get_or_insert_with(|| BindlessBatches::new(bindless))
+            .clear();
+    }
     // dont create buffers when there are no sprites
     if extracted_sprites.sprites.len() == 0 {
         return;
@@ -225,6 +309,11 @@ pub fn prepare_sprites(
     );
 
     for (i, extracted_sprite) in extracted_sprites.sprites.iter().enumerate() {
+        let texture_index = sprite_meta.bindless_batches.as_mut().map_or(0, |batches| {
+            batches
+                .add(extracted_sprite.texture_view, extracted_sprite.sampler)
+                .index
+        });
         for (vertex_position, vertex_uv) in quad_vertex_positions.iter().zip(quad_vertex_uvs.iter())
         {
             let mut final_position =
@@ -233,6 +322,8 @@ pub fn prepare_sprites(
             sprite_meta.vertices.push(SpriteVertex {
                 position: final_position.into(),
                 uv: *vertex_uv,
+                color: extracted_sprite.color,
+                texture_index,
             });
         }
 
@@ -264,13 +355,16 @@ pub fn queue_sprites(
     view_meta: Res<ViewMeta>,
     sprite_shaders: Res<SpriteShaders>,
     extracted_sprites: Res<ExtractedSprites>,
-    mut views: Query<(Entity, &mut RenderPhase<Transparent2dPhase>)>,
+    mut views: Query<(Entity, &ViewUniform, &mut RenderPhase<Transparent2dPhase>)>,
 ) {
-    for (view_entity, mut transparent_phase) in views.iter_mut() {
+    for (view_entity, view_uniform, mut transparent_phase) in views.iter_mut() {
         let layout = &sprite_shaders.pipeline_descriptor.layout;
 
         let camera_bind_group = BindGroupBuilder::default()
-            .add_binding(0, view_meta.uniforms.binding())
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
             .finish();
 
         // TODO: this will only create the bind group if it isn't already created. this is a bit nasty
@@ -280,29 +374,53 @@ pub fn queue_sprites(
         });
 
         // TODO: free old bind groups? clear_unused_bind_groups() currently does this for us? Moving to RAII would also do this for us?
+        let sprite_meta = &mut *sprite_meta;
         sprite_meta.texture_bind_groups.clear();
-        let mut texture_bind_group_indices = HashMap::default();
+        let mut texture_bind_groups = HashMap::default();
+        // bindless sprites share the bind group of the batch their texture was added to
+        let bindless_bind_groups = sprite_meta.bindless_batches.as_ref().map(|batches| {
+            (0..batches.batches().len())
+                .map(|batch| {
+                    let bind_group = batches
+                        .bind_group(batch, &layout.bind_groups[1], 0, 1)
+                        .build()
+                        .unwrap();
+                    render_resources.create_bind_group(layout.bind_groups[1].id, &bind_group);
+                    bind_group.id
+                })
+                .collect::<Vec<_>>()
+        });
 
         let draw_sprite_function = draw_functions.read().get_id::<DrawSprite>().unwrap();
 
         for (i, sprite) in extracted_sprites.sprites.iter().enumerate() {
-            let bind_group_index = *texture_bind_group_indices
-                .entry(sprite.texture_view)
-                .or_insert_with(|| {
-                    let index = sprite_meta.texture_bind_groups.len();
-                    let bind_group = BindGroupBuilder::default()
-                        .add_binding(0, sprite.texture_view)
-                        // NOTE: this currently reuses the same sampler across all sprites using the same texture
-                        .add_binding(1, sprite.sampler)
-                        .finish();
-                    render_resources.create_bind_group(layout.bind_groups[1].id, &bind_group);
-                    sprite_meta.texture_bind_groups.push(bind_group.id);
-                    index
+            let bindless_bind_group = sprite_meta
+                .bindless_batches
+                .as_mut()
+                .zip(bindless_bind_groups.as_ref())
+                .map(|(batches, bind_groups)| {
+                    bind_groups[batches.add(sprite.texture_view, sprite.sampler).batch]
                 });
+            let bind_group = match bindless_bind_group {
+                Some(bind_group) => bind_group,
+                None => *texture_bind_groups
+                    .entry((sprite.texture_view, sprite.sampler))
+                    .or_insert_with(|| {
+                        let bind_group = BindGroupBuilder::default()
+                            .add_binding(0, sprite.texture_view)
+                            .add_binding(1, sprite.sampler)
+                            .finish();
+                        render_resources.create_bind_group(layout.bind_groups[1].id, &bind_group);
+                        bind_group.id
+                    }),
+            };
+            sprite_meta.texture_bind_groups.push(bind_group);
             transparent_phase.add(Drawable {
                 draw_function: draw_sprite_function,
                 draw_key: i,
-                sort_key: bind_group_index,
+                sort_key: sprite.sort_key,
+                entity: sprite.entity,
+                clip: None,
             });
         }
     }
@@ -349,7 +467,7 @@ impl Draw for DrawSprite {
         pass: &mut TrackedRenderPass,
         view: Entity,
         draw_key: usize,
-        sort_key: usize,
+        _sort_key: usize,
     ) {
         const INDICES: usize = 6;
         let (sprite_shaders, sprite_buffers, views) = self.params.get(world);
@@ -371,7 +489,7 @@ impl Draw for DrawSprite {
         pass.set_bind_group(
             1,
             layout.bind_groups[1].id,
-            sprite_buffers.texture_bind_groups[sort_key],
+            sprite_buffers.texture_bind_groups[draw_key],
             None,
         );
 
@@ -382,3 +500,40 @@ impl Draw for DrawSprite {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn bindless_shaders_compile() {
+        let bindless = BindlessTextures { max_textures: 4 };
+        let shader_defs = [String::from("BINDLESS")];
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, SPRITE_VERTEX_SHADER)
+            .get_spirv_shader(Some(&shader_defs))
+            .unwrap();
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, SPRITE_FRAGMENT_SHADER)
+            .get_spirv_shader(Some(&shader_defs))
+            .unwrap();
+
+        // the texture index is passed to the fragment shader with the vertices
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        assert!(vertex_layout
+            .vertex_buffer_layout
+            .iter()
+            .any(|layout| layout
+                .attributes
+                .iter()
+                .any(|attribute| attribute.name == "Vertex_TextureIndex")));
+        let fragment_layout = fragment_shader
+            .reflect_layout(&bindless.reflect_options("sprite_textures"))
+            .unwrap();
+        let textures = fragment_layout
+            .bind_groups
+            .iter()
+            .find(|bind_group| bind_group.index == 1)
+            .unwrap();
+        assert_eq!(textures.bindings[0].count, NonZeroU32::new(4));
+    }
+}
@@ -1,11 +1,29 @@
 #version 450
+#ifdef BINDLESS
+#extension GL_EXT_nonuniform_qualifier : require
+#endif
 
 layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_Color;
+#ifdef BINDLESS
+layout(location = 2) flat in uint v_TextureIndex;
+#endif
 layout(location = 0) out vec4 o_Target;
 
+#ifdef BINDLESS
+// sized to `BindlessTextures::max_textures` when the layout is reflected
+layout(set = 1, binding = 0) uniform texture2D sprite_textures[];
+#else
 layout(set = 1, binding = 0) uniform texture2D sprite_texture;
+#endif
 layout(set = 1, binding = 1) uniform sampler sprite_sampler;
 
 void main() {
-    o_Target = texture(sampler2D(sprite_texture, sprite_sampler), v_Uv);
+#ifdef BINDLESS
+    // sprites drawn together can have different textures
+    vec4 color = texture(sampler2D(sprite_textures[nonuniformEXT(v_TextureIndex)], sprite_sampler), v_Uv);
+#else
+    vec4 color = texture(sampler2D(sprite_texture, sprite_sampler), v_Uv);
+#endif
+    o_Target = v_Color * color;
 }
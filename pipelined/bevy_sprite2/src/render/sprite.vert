@@ -2,8 +2,16 @@
 
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in vec2 Vertex_Uv;
+layout(location = 2) in vec4 Vertex_Color;
+#ifdef BINDLESS
+layout(location = 3) in uint Vertex_TextureIndex;
+#endif
 
 layout(location = 0) out vec2 v_Uv;
+layout(location = 1) out vec4 v_Color;
+#ifdef BINDLESS
+layout(location = 2) flat out uint v_TextureIndex;
+#endif
 
 layout(set = 0, binding = 0) uniform View {
     mat4 ViewProj;
@@ -12,5 +20,9 @@ layout(set = 0, binding = 0) uniform View {
 
 void main() {
     v_Uv = Vertex_Uv;
+    v_Color = Vertex_Color;
+#ifdef BINDLESS
+    v_TextureIndex = Vertex_TextureIndex;
+#endif
     gl_Position = ViewProj * vec4(Vertex_Position, 1.0);
 }
@@ -1,5 +1,16 @@
+use crate::{AmbientLight2d, PointLight2d, SpriteNormalMap};
+use bevy_asset::Handle;
+use bevy_ecs::prelude::*;
 use bevy_math::Vec2;
 use bevy_reflect::{Reflect, ReflectDeserialize, TypeUuid};
+use bevy_render2::{
+    color::Color,
+    core_pipeline::{YSortOffset, ZIndex},
+    picking::Pickable,
+    render_mode::RequestRender,
+    texture::Texture,
+    view::Aabb,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Clone, TypeUuid, Reflect)]
@@ -7,6 +18,8 @@ use serde::{Deserialize, Serialize};
 #[repr(C)]
 pub struct Sprite {
     pub size: Vec2,
+    /// Multiplied with the sprite's texture.
+    pub color: Color,
     pub flip_x: bool,
     pub flip_y: bool,
     pub resize_mode: SpriteResizeMode,
@@ -31,9 +44,53 @@ impl Sprite {
     pub fn new(size: Vec2) -> Self {
         Self {
             size,
+            color: Color::WHITE,
             resize_mode: SpriteResizeMode::Manual,
             flip_x: false,
             flip_y: false,
         }
     }
 }
+
+/// Keeps the [`Aabb`] of [`Pickable`] sprites up to date with their size, so they can be picked.
+pub fn sprite_aabb_system(
+    mut commands: Commands,
+    query: Query<(Entity, &Sprite), (With<Pickable>, Or<(Changed<Sprite>, Added<Pickable>)>)>,
+) {
+    for (entity, sprite) in query.iter() {
+        let half_extents = (sprite.size / 2.0).extend(0.0);
+        commands
+            .entity(entity)
+            .insert(Aabb::from_min_max(-half_extents, half_extents));
+    }
+}
+
+/// Sends a [`RequestRender`] when a sprite or a 2d light is added, changed or removed, which the
+/// [`RenderMode::OnDemand`](bevy_render2::render_mode::RenderMode::OnDemand) can't detect by
+/// itself.
+#[allow(clippy::type_complexity)]
+pub fn request_render_system(
+    mut request_render: EventWriter<RequestRender>,
+    ambient_light: Option<Res<AmbientLight2d>>,
+    changed: Query<
+        (),
+        Or<(
+            Changed<Sprite>,
+            Changed<Handle<Texture>>,
+            Changed<SpriteNormalMap>,
+            Changed<ZIndex>,
+            Changed<YSortOffset>,
+            Changed<PointLight2d>,
+        )>,
+    >,
+    removed_sprites: RemovedComponents<Sprite>,
+    removed_lights: RemovedComponents<PointLight2d>,
+) {
+    if ambient_light.map_or(false, |ambient_light| ambient_light.is_changed())
+        || changed.iter().next().is_some()
+        || removed_sprites.iter().next().is_some()
+        || removed_lights.iter().next().is_some()
+    {
+        request_render.send(RequestRender);
+    }
+}
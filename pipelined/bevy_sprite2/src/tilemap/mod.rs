@@ -0,0 +1,273 @@
+mod render;
+mod tile_map;
+
+pub use render::*;
+pub use tile_map::*;
+
+use bevy_app::prelude::*;
+use bevy_asset::{AddAsset, AssetEvent, Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_render2::{
+    core_pipeline,
+    render_graph::RenderGraph,
+    render_phase::DrawFunctions,
+    render_resource::{BufferId, BufferInfo, BufferUsage},
+    renderer::{RenderInitAppExt, RenderResources},
+    texture::TextureUsers,
+    view::Aabb,
+    RenderApp, RenderStage,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use bevy_utils::{HashMap, HashSet};
+
+/// Draws the [`TileMap`]s of entities with a `Handle<TileMap>`, see [`TileMapBundle`].
+#[derive(Debug, Default)]
+pub struct TileMapPlugin;
+
+impl Plugin for TileMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TileMap>()
+            .init_resource::<TileMapGpuChunks>()
+            .add_system_to_stage(CoreStage::PostUpdate, tile_map_resource_system.system())
+            .add_system_to_stage(CoreStage::PreUpdate, report_tile_map_texture_users.system());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_tile_maps.system())
+            .add_system_to_stage(RenderStage::Prepare, prepare_tile_maps.system())
+            .add_system_to_stage(RenderStage::Queue, queue_tile_maps.system())
+            .init_render_resource::<TileMapShaders>()
+            .init_resource::<TileMapMeta>();
+        let draw_tile_map = DrawTileMap::new(&mut render_app.world);
+        render_app
+            .world
+            .get_resource::<DrawFunctions>()
+            .unwrap()
+            .write()
+            .add(draw_tile_map);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node("tile_map", TileMapNode);
+        graph
+            .add_node_edge("tile_map", core_pipeline::node::MAIN_PASS_DEPENDENCIES)
+            .unwrap();
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct TileMapBundle {
+    pub tile_map: Handle<TileMap>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// The buffers of a chunk of a [`TileMap`] layer.
+#[derive(Debug, Clone, Copy)]
+pub struct TileMapChunkGpuData {
+    pub vertex_buffer: BufferId,
+    pub index_buffer: BufferId,
+    pub index_count: u32,
+    /// The bounds of the chunk, in the local space of the map.
+    pub aabb: Aabb,
+}
+
+/// The uploaded chunks of every [`TileMap`], in the order of [`TileMap::chunk_count`].
+#[derive(Default)]
+pub struct TileMapGpuChunks {
+    chunks: HashMap<Handle<TileMap>, Vec<GpuChunk>>,
+}
+
+struct GpuChunk {
+    generation: usize,
+    /// `None` for chunks without tiles.
+    gpu_data: Option<TileMapChunkGpuData>,
+}
+
+impl TileMapGpuChunks {
+    /// Returns the buffers of the chunks of `tile_map` that have tiles.
+    pub fn get(
+        &self,
+        tile_map: &Handle<TileMap>,
+    ) -> impl Iterator<Item = &TileMapChunkGpuData> + '_ {
+        self.chunks
+            .get(tile_map)
+            .into_iter()
+            .flat_map(|chunks| chunks.iter())
+            .filter_map(|chunk| chunk.gpu_data.as_ref())
+    }
+}
+
+fn remove_chunk_buffers(render_resources: &RenderResources, chunks: Vec<GpuChunk>) {
+    for gpu_data in chunks.into_iter().filter_map(|chunk| chunk.gpu_data) {
+        render_resources.remove_buffer(gpu_data.vertex_buffer);
+        render_resources.remove_buffer(gpu_data.index_buffer);
+    }
+}
+
+/// Reports the atlases of [`TileMap`]s as used by their entities, so a
+/// [`TextureStreamingPlugin`](bevy_render2::texture::TextureStreamingPlugin) streams them in.
+pub fn report_tile_map_texture_users(
+    texture_users: Option<ResMut<TextureUsers>>,
+    tile_maps: Res<Assets<TileMap>>,
+    query: Query<(&Handle<TileMap>, &GlobalTransform)>,
+) {
+    if let Some(mut texture_users) = texture_users {
+        for (tile_map, transform) in query.iter() {
+            if let Some(tile_map) = tile_maps.get(tile_map) {
+                texture_users.add(&tile_map.atlas, transform.translation);
+            }
+        }
+    }
+}
+
+/// Uploads the chunks of created [`TileMap`]s, and the chunks of modified maps whose tiles
+/// changed since they were last uploaded.
+pub fn tile_map_resource_system(
+    render_resources: Option<Res<RenderResources>>,
+    tile_maps: Res<Assets<TileMap>>,
+    mut tile_map_events: EventReader<AssetEvent<TileMap>>,
+    mut gpu_chunks: ResMut<TileMapGpuChunks>,
+    mut renderer_was_pending: Local<bool>,
+) {
+    let render_resources = match render_resources {
+        Some(render_resources) => render_resources,
+        None => {
+            *renderer_was_pending = true;
+            return;
+        }
+    };
+    let mut changed_tile_maps = HashSet::default();
+    // the events of the maps added while the renderer was pending may be gone
+    if std::mem::take(&mut *renderer_was_pending) {
+        changed_tile_maps.extend(tile_maps.ids().map(Handle::weak));
+    }
+    for event in tile_map_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed_tile_maps.insert(handle.clone_weak());
+            }
+            AssetEvent::Removed { handle } => {
+                if let Some(chunks) = gpu_chunks.chunks.remove(handle) {
+                    remove_chunk_buffers(&render_resources, chunks);
+                }
+                // if the map was modified and removed in the same update, ignore the modification
+                changed_tile_maps.remove(handle);
+            }
+        }
+    }
+
+    for handle in changed_tile_maps {
+        let tile_map = match tile_maps.get(&handle) {
+            Some(tile_map) => tile_map,
+            None => continue,
+        };
+        let chunks = gpu_chunks.chunks.entry(handle).or_insert_with(Vec::new);
+        update_chunks(&render_resources, chunks, tile_map);
+    }
+}
+
+/// Uploads the chunks of `tile_map` whose generation doesn't match the uploaded chunk.
+fn update_chunks(
+    render_resources: &RenderResources,
+    chunks: &mut Vec<GpuChunk>,
+    tile_map: &TileMap,
+) {
+    if chunks.len() != tile_map.chunk_count() {
+        // the map was replaced by one with a different number of chunks
+        remove_chunk_buffers(render_resources, std::mem::take(chunks));
+    }
+    for index in 0..tile_map.chunk_count() {
+        let generation = tile_map.chunk_generation(index);
+        if chunks
+            .get(index)
+            .map_or(false, |chunk| chunk.generation == generation)
+        {
+            continue;
+        }
+
+        let gpu_data = tile_map.chunk_mesh(index).map(|mesh| TileMapChunkGpuData {
+            vertex_buffer: render_resources.create_buffer_with_data(
+                BufferInfo {
+                    buffer_usage: BufferUsage::VERTEX,
+                    ..Default::default()
+                },
+                bytemuck::cast_slice(&mesh.vertices),
+            ),
+            index_buffer: render_resources.create_buffer_with_data(
+                BufferInfo {
+                    buffer_usage: BufferUsage::INDEX,
+                    ..Default::default()
+                },
+                bytemuck::cast_slice(&mesh.indices),
+            ),
+            index_count: mesh.indices.len() as u32,
+            aabb: mesh.aabb,
+        });
+        let chunk = GpuChunk {
+            generation,
+            gpu_data,
+        };
+        if index < chunks.len() {
+            let old_chunk = std::mem::replace(&mut chunks[index], chunk);
+            remove_chunk_buffers(render_resources, vec![old_chunk]);
+        } else {
+            chunks.push(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{update_chunks, GpuChunk, TileMap};
+    use bevy_asset::Handle;
+    use bevy_math::{UVec2, Vec2};
+    use bevy_render2::{
+        render_resource::BufferId,
+        renderer::{HeadlessRenderResourceContext, RenderResources},
+    };
+
+    fn vertex_buffers(chunks: &[GpuChunk]) -> Vec<Option<BufferId>> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.gpu_data.map(|gpu_data| gpu_data.vertex_buffer))
+            .collect()
+    }
+
+    #[test]
+    fn edits_on_chunk_edges_rebuild_their_chunk() {
+        let render_resources =
+            RenderResources::new(Box::new(HeadlessRenderResourceContext::default()));
+        // 2 by 2 chunks, the last column and row of chunks have a single column or row of tiles
+        let mut tile_map = TileMap::new(
+            UVec2::new(33, 33),
+            1,
+            Vec2::ONE,
+            Handle::default(),
+            UVec2::ONE,
+        );
+        tile_map.fill(0, Some(0));
+        let mut chunks = Vec::new();
+        update_chunks(&render_resources, &mut chunks, &tile_map);
+        let uploaded = vertex_buffers(&chunks);
+        assert_eq!(uploaded.len(), 4);
+        assert!(uploaded.iter().all(Option::is_some));
+
+        // the last tile of the first chunk
+        tile_map.set(0, UVec2::new(31, 31), None);
+        update_chunks(&render_resources, &mut chunks, &tile_map);
+        let rebuilt = vertex_buffers(&chunks);
+        assert_ne!(rebuilt[0], uploaded[0]);
+        assert_eq!(rebuilt[1..], uploaded[1..]);
+
+        // the first tile of the last chunk
+        tile_map.set(0, UVec2::new(32, 32), None);
+        update_chunks(&render_resources, &mut chunks, &tile_map);
+        let emptied = vertex_buffers(&chunks);
+        assert_eq!(emptied[..3], rebuilt[..3]);
+        assert_eq!(emptied[3], None);
+
+        // setting a tile to its current value doesn't rebuild anything
+        tile_map.set(0, UVec2::new(32, 0), Some(0));
+        update_chunks(&render_resources, &mut chunks, &tile_map);
+        assert_eq!(vertex_buffers(&chunks), emptied);
+    }
+}
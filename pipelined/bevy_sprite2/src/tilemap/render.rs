@@ -0,0 +1,372 @@
+use super::{TileMap, TileMapChunkGpuData, TileMapGpuChunks};
+use crate::render::SPRITE_FRAGMENT_SHADER;
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{prelude::*, system::SystemState};
+use bevy_math::{Mat4, Vec4};
+use bevy_render2::{
+    core_pipeline::{SortMode2d, Transparent2dPhase, YSortOffset, ZIndex},
+    pipeline::*,
+    render_graph::{Node, NodeRunError, RenderGraphContext},
+    render_phase::{Draw, DrawFunctions, Drawable, RenderPhase, TrackedRenderPass},
+    render_resource::{
+        BindGroupBuilder, BindGroupId, BufferId, DynamicUniformIndex, DynamicUniformVec, SamplerId,
+        TextureViewId,
+    },
+    renderer::{RenderContext, RenderResources},
+    shader::{Shader, ShaderStage, ShaderStages},
+    texture::{Texture, TextureFormat},
+    view::{ExtractedView, FloatingOrigin, ViewMeta, ViewUniform},
+};
+use bevy_transform::components::GlobalTransform;
+use crevice::std140::AsStd140;
+
+pub struct TileMapShaders {
+    pipeline: PipelineId,
+    pipeline_descriptor: RenderPipelineDescriptor,
+}
+
+impl FromWorld for TileMapShaders {
+    fn from_world(world: &mut World) -> Self {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let vertex_shader = Shader::from_glsl(ShaderStage::Vertex, include_str!("tile_map.vert"))
+            .get_spirv_shader(None)
+            .unwrap();
+        let fragment_shader = Shader::from_glsl(ShaderStage::Fragment, SPRITE_FRAGMENT_SHADER)
+            .get_spirv_shader(None)
+            .unwrap();
+
+        let vertex_layout = vertex_shader.reflect_layout(&Default::default()).unwrap();
+        let fragment_layout = fragment_shader.reflect_layout(&Default::default()).unwrap();
+
+        let mut pipeline_layout =
+            PipelineLayout::from_shader_layouts(&mut [vertex_layout, fragment_layout]);
+
+        let vertex = render_resources.create_shader_module(&vertex_shader);
+        let fragment = render_resources.create_shader_module(&fragment_shader);
+
+        pipeline_layout.vertex_buffer_descriptors = vec![VertexBufferLayout {
+            stride: 20,
+            name: "Vertex".into(),
+            step_mode: InputStepMode::Vertex,
+            attributes: vec![
+                VertexAttribute {
+                    name: "Vertex_Position".into(),
+                    format: VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                VertexAttribute {
+                    name: "Vertex_Uv".into(),
+                    format: VertexFormat::Float32x2,
+                    offset: 12,
+                    shader_location: 1,
+                },
+            ],
+        }];
+
+        pipeline_layout.bind_groups[0].bindings[0].set_dynamic(true);
+        pipeline_layout.bind_groups[2].bindings[0].set_dynamic(true);
+        pipeline_layout.update_bind_group_ids();
+
+        let pipeline_descriptor = RenderPipelineDescriptor {
+            depth_stencil: None,
+            color_target_states: vec![ColorTargetState {
+                format: TextureFormat::default(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrite::ALL,
+            }],
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            ..RenderPipelineDescriptor::new(
+                ShaderStages {
+                    vertex,
+                    fragment: Some(fragment),
+                },
+                pipeline_layout,
+            )
+        };
+
+        let pipeline = render_resources.create_render_pipeline(&pipeline_descriptor);
+
+        TileMapShaders {
+            pipeline,
+            pipeline_descriptor,
+        }
+    }
+}
+
+struct ExtractedTileMap {
+    entity: Entity,
+    transform: Mat4,
+    color: Vec4,
+    texture_view: TextureViewId,
+    sampler: SamplerId,
+    chunks: Vec<TileMapChunkGpuData>,
+    sort_key: usize,
+}
+
+pub struct ExtractedTileMaps {
+    tile_maps: Vec<ExtractedTileMap>,
+}
+
+pub fn extract_tile_maps(
+    mut commands: Commands,
+    tile_maps: Res<Assets<TileMap>>,
+    textures: Res<Assets<Texture>>,
+    gpu_chunks: Res<TileMapGpuChunks>,
+    sort_mode: Res<SortMode2d>,
+    floating_origin: Res<FloatingOrigin>,
+    query: Query<(
+        Entity,
+        &Handle<TileMap>,
+        &GlobalTransform,
+        Option<&ZIndex>,
+        Option<&YSortOffset>,
+    )>,
+) {
+    let mut extracted_tile_maps = Vec::new();
+    for (entity, handle, transform, z_index, y_offset) in query.iter() {
+        let tile_map = match tile_maps.get(handle) {
+            Some(tile_map) => tile_map,
+            None => continue,
+        };
+        if let Some(gpu_data) = textures
+            .get(&tile_map.atlas)
+            .and_then(|atlas| atlas.gpu_data.as_ref())
+        {
+            extracted_tile_maps.push(ExtractedTileMap {
+                entity,
+                transform: floating_origin.to_render_matrix(transform),
+                color: tile_map.color.as_linear_rgba_f32().into(),
+                texture_view: gpu_data.texture_view,
+                sampler: gpu_data.sampler,
+                chunks: gpu_chunks.get(handle).copied().collect(),
+                sort_key: z_index
+                    .copied()
+                    .unwrap_or_default()
+                    .sort_key(sort_mode.depth(transform.translation, y_offset)),
+            });
+        }
+    }
+
+    commands.insert_resource(ExtractedTileMaps {
+        tile_maps: extracted_tile_maps,
+    });
+}
+
+#[derive(Copy, Clone, AsStd140)]
+pub struct TileMapUniform {
+    transform: Mat4,
+    color: Vec4,
+}
+
+struct TileMapChunkDraw {
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
+    index_count: u32,
+    texture_bind_group: BindGroupId,
+    uniform_bind_group: BindGroupId,
+    uniform_offset: u32,
+}
+
+#[derive(Default)]
+pub struct TileMapMeta {
+    uniforms: DynamicUniformVec<TileMapUniform>,
+    /// The uniform of each extracted tile map.
+    tile_map_uniforms: Vec<DynamicUniformIndex>,
+    /// The chunks of every extracted tile map, indexed by the draw key of their drawables.
+    chunks: Vec<TileMapChunkDraw>,
+}
+
+pub fn prepare_tile_maps(
+    render_resources: Res<RenderResources>,
+    mut tile_map_meta: ResMut<TileMapMeta>,
+    extracted_tile_maps: Res<ExtractedTileMaps>,
+) {
+    let tile_map_meta = &mut *tile_map_meta;
+    tile_map_meta
+        .uniforms
+        .reserve_and_clear(extracted_tile_maps.tile_maps.len(), &render_resources);
+    tile_map_meta.tile_map_uniforms.clear();
+    for tile_map in extracted_tile_maps.tile_maps.iter() {
+        let index = tile_map_meta.uniforms.push(TileMapUniform {
+            transform: tile_map.transform,
+            color: tile_map.color,
+        });
+        tile_map_meta.tile_map_uniforms.push(index);
+    }
+    tile_map_meta
+        .uniforms
+        .write_to_staging_buffer(&render_resources);
+}
+
+// TODO: This is temporary. Once we expose BindGroupLayouts directly, we can create view bind groups without specific shader context
+struct TileMapViewMeta {
+    bind_group: BindGroupId,
+}
+
+pub fn queue_tile_maps(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    render_resources: Res<RenderResources>,
+    mut tile_map_meta: ResMut<TileMapMeta>,
+    view_meta: Res<ViewMeta>,
+    tile_map_shaders: Res<TileMapShaders>,
+    extracted_tile_maps: Res<ExtractedTileMaps>,
+    mut views: Query<(
+        Entity,
+        &ExtractedView,
+        &ViewUniform,
+        &mut RenderPhase<Transparent2dPhase>,
+    )>,
+) {
+    let tile_map_meta = &mut *tile_map_meta;
+    let layout = &tile_map_shaders.pipeline_descriptor.layout;
+
+    // TODO: free old bind groups? clear_unused_bind_groups() currently does this for us? Moving to RAII would also do this for us?
+    tile_map_meta.chunks.clear();
+    for (tile_map, uniform_index) in extracted_tile_maps
+        .tile_maps
+        .iter()
+        .zip(tile_map_meta.tile_map_uniforms.iter())
+    {
+        let texture_bind_group = BindGroupBuilder::default()
+            .add_binding(0, tile_map.texture_view)
+            .add_binding(1, tile_map.sampler)
+            .finish();
+        render_resources.create_bind_group(layout.bind_groups[1].id, &texture_bind_group);
+        let uniform_bind_group = BindGroupBuilder::default()
+            .add_binding(0, tile_map_meta.uniforms.binding(uniform_index.chunk))
+            .finish();
+        render_resources.create_bind_group(layout.bind_groups[2].id, &uniform_bind_group);
+        for chunk in tile_map.chunks.iter() {
+            tile_map_meta.chunks.push(TileMapChunkDraw {
+                vertex_buffer: chunk.vertex_buffer,
+                index_buffer: chunk.index_buffer,
+                index_count: chunk.index_count,
+                texture_bind_group: texture_bind_group.id,
+                uniform_bind_group: uniform_bind_group.id,
+                uniform_offset: uniform_index.offset,
+            });
+        }
+    }
+
+    let draw_tile_map_function = draw_functions.read().get_id::<DrawTileMap>().unwrap();
+    for (view_entity, view, view_uniform, mut transparent_phase) in views.iter_mut() {
+        let view_bind_group = BindGroupBuilder::default()
+            .add_binding(
+                0,
+                view_meta.uniforms.binding(view_uniform.view_uniform_chunk),
+            )
+            .finish();
+
+        render_resources.create_bind_group(layout.bind_groups[0].id, &view_bind_group);
+        commands.entity(view_entity).insert(TileMapViewMeta {
+            bind_group: view_bind_group.id,
+        });
+
+        // chunks are queued layer by layer, and the stable sort of the phase keeps that order
+        let frustum = view.frustum();
+        let mut draw_key = 0;
+        for tile_map in extracted_tile_maps.tile_maps.iter() {
+            for chunk in tile_map.chunks.iter() {
+                if frustum.intersects_aabb(&chunk.aabb, &tile_map.transform) {
+                    transparent_phase.add(Drawable {
+                        draw_function: draw_tile_map_function,
+                        draw_key,
+                        sort_key: tile_map.sort_key,
+                        entity: tile_map.entity,
+                        clip: None,
+                    });
+                }
+                draw_key += 1;
+            }
+        }
+    }
+}
+
+/// Writes the [`TileMapMeta`] uniforms before the views are drawn.
+pub struct TileMapNode;
+
+impl Node for TileMapNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut dyn RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let tile_map_meta = world.get_resource::<TileMapMeta>().unwrap();
+        tile_map_meta
+            .uniforms
+            .write_to_uniform_buffer(render_context);
+        Ok(())
+    }
+}
+
+type DrawTileMapQuery<'a> = (
+    Res<'a, TileMapShaders>,
+    Res<'a, TileMapMeta>,
+    Query<'a, (&'a ViewUniform, &'a TileMapViewMeta)>,
+);
+pub struct DrawTileMap {
+    params: SystemState<DrawTileMapQuery<'static>>,
+}
+
+impl DrawTileMap {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw for DrawTileMap {
+    fn draw(
+        &mut self,
+        world: &World,
+        pass: &mut TrackedRenderPass,
+        view: Entity,
+        draw_key: usize,
+        _sort_key: usize,
+    ) {
+        let (tile_map_shaders, tile_map_meta, views) = self.params.get(world);
+        let layout = &tile_map_shaders.pipeline_descriptor.layout;
+        let (view_uniforms, tile_map_view_meta) = views.get(view).unwrap();
+        let chunk = &tile_map_meta.chunks[draw_key];
+        pass.set_pipeline(tile_map_shaders.pipeline);
+        pass.set_vertex_buffer(0, chunk.vertex_buffer, 0);
+        pass.set_index_buffer(chunk.index_buffer, 0, IndexFormat::Uint32);
+        pass.set_bind_group(
+            0,
+            layout.bind_groups[0].id,
+            tile_map_view_meta.bind_group,
+            Some(&[view_uniforms.view_uniform_offset]),
+        );
+        pass.set_bind_group(1, layout.bind_groups[1].id, chunk.texture_bind_group, None);
+        pass.set_bind_group(
+            2,
+            layout.bind_groups[2].id,
+            chunk.uniform_bind_group,
+            Some(&[chunk.uniform_offset]),
+        );
+        pass.draw_indexed(0..chunk.index_count, 0, 0..1);
+    }
+}
@@ -0,0 +1,291 @@
+use bevy_asset::Handle;
+use bevy_math::{UVec2, Vec2};
+use bevy_reflect::TypeUuid;
+use bevy_render2::{color::Color, texture::Texture, view::Aabb};
+use bytemuck::{Pod, Zeroable};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// every edit of any tile map gets a new generation, so a replaced map never matches the
+// generations of the chunks uploaded for the previous one
+static NEXT_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+fn next_generation() -> usize {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A grid of tiles, drawn with the tiles of an atlas texture laid out in a grid of
+/// `atlas_size` columns and rows. Tiles are indexed row by row from the top left of the atlas.
+///
+/// The map is drawn in chunks of [`TileMap::CHUNK_SIZE`] by [`TileMap::CHUNK_SIZE`] tiles, with
+/// its first tile at the origin of the entity and its rows going up the y axis. Each chunk is
+/// only uploaded again when one of its tiles is edited, and chunks outside of the view are not
+/// drawn. Layers are drawn in order, on top of each other.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "4c5a1f3e-9b7d-4e2a-8f61-2d3b9c7e5a14"]
+pub struct TileMap {
+    pub atlas: Handle<Texture>,
+    /// Multiplied with the tiles of the atlas.
+    pub color: Color,
+    size: UVec2,
+    tile_size: Vec2,
+    atlas_size: UVec2,
+    layers: Vec<Vec<Option<u32>>>,
+    /// The generation of each chunk, layer by layer and row by row.
+    chunk_generations: Vec<usize>,
+}
+
+impl TileMap {
+    /// The number of tiles along each side of a chunk.
+    pub const CHUNK_SIZE: u32 = 32;
+
+    /// Creates an empty tile map of `size` tiles with `layers` layers.
+    pub fn new(
+        size: UVec2,
+        layers: usize,
+        tile_size: Vec2,
+        atlas: Handle<Texture>,
+        atlas_size: UVec2,
+    ) -> Self {
+        let mut tile_map = TileMap {
+            atlas,
+            color: Color::WHITE,
+            size,
+            tile_size,
+            atlas_size,
+            layers: vec![vec![None; (size.x * size.y) as usize]; layers],
+            chunk_generations: Vec::new(),
+        };
+        tile_map.chunk_generations = (0..tile_map.chunk_count())
+            .map(|_| next_generation())
+            .collect();
+        tile_map
+    }
+
+    /// The number of tiles along the x and y axes.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    /// The number of tile columns and rows in the atlas.
+    pub fn atlas_size(&self) -> UVec2 {
+        self.atlas_size
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The number of chunks along the x and y axes.
+    pub fn chunks(&self) -> UVec2 {
+        (self.size + UVec2::splat(Self::CHUNK_SIZE - 1)) / Self::CHUNK_SIZE
+    }
+
+    /// The number of chunks of all layers.
+    pub fn chunk_count(&self) -> usize {
+        let chunks = self.chunks();
+        (chunks.x * chunks.y) as usize * self.layers.len()
+    }
+
+    /// Returns the atlas index of the tile at `position` in `layer`, if there is one.
+    pub fn get(&self, layer: usize, position: UVec2) -> Option<u32> {
+        self.layers[layer][self.tile_index(position)]
+    }
+
+    /// Sets the tile at `position` in `layer` to the atlas tile `tile`, or removes it.
+    pub fn set(&mut self, layer: usize, position: UVec2, tile: Option<u32>) {
+        let index = self.tile_index(position);
+        if self.layers[layer][index] != tile {
+            self.layers[layer][index] = tile;
+            let chunk = self.chunk_index(layer, position / Self::CHUNK_SIZE);
+            self.chunk_generations[chunk] = next_generation();
+        }
+    }
+
+    /// Sets every tile of `layer` to the atlas tile `tile`, or removes them.
+    pub fn fill(&mut self, layer: usize, tile: Option<u32>) {
+        for value in self.layers[layer].iter_mut() {
+            *value = tile;
+        }
+        let chunks = self.chunks();
+        let first_chunk = self.chunk_index(layer, UVec2::ZERO);
+        for generation in self.chunk_generations[first_chunk..]
+            .iter_mut()
+            .take((chunks.x * chunks.y) as usize)
+        {
+            *generation = next_generation();
+        }
+    }
+
+    fn tile_index(&self, position: UVec2) -> usize {
+        assert!(
+            position.x < self.size.x && position.y < self.size.y,
+            "tile position {} is outside of the tile map",
+            position
+        );
+        (position.y * self.size.x + position.x) as usize
+    }
+
+    fn chunk_index(&self, layer: usize, chunk: UVec2) -> usize {
+        let chunks = self.chunks();
+        layer * (chunks.x * chunks.y) as usize + (chunk.y * chunks.x + chunk.x) as usize
+    }
+
+    pub(crate) fn chunk_generation(&self, chunk_index: usize) -> usize {
+        self.chunk_generations[chunk_index]
+    }
+
+    /// Builds the vertices and indices of the tiles of a chunk, in the local space of the map, and
+    /// its bounding box. Returns `None` for chunks without tiles.
+    pub(crate) fn chunk_mesh(&self, chunk_index: usize) -> Option<TileMapChunkMesh> {
+        let chunks = self.chunks();
+        let chunks_per_layer = (chunks.x * chunks.y) as usize;
+        let layer = &self.layers[chunk_index / chunks_per_layer];
+        let chunk = chunk_index % chunks_per_layer;
+        let first_tile =
+            UVec2::new(chunk as u32 % chunks.x, chunk as u32 / chunks.x) * Self::CHUNK_SIZE;
+        let last_tile = (first_tile + UVec2::splat(Self::CHUNK_SIZE)).min(self.size);
+
+        let atlas_tile_size =
+            Vec2::ONE / Vec2::new(self.atlas_size.x as f32, self.atlas_size.y as f32);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for y in first_tile.y..last_tile.y {
+            for x in first_tile.x..last_tile.x {
+                let tile = match layer[(y * self.size.x + x) as usize] {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let min = Vec2::new(x as f32, y as f32) * self.tile_size;
+                let max = min + self.tile_size;
+                let uv_min = Vec2::new(
+                    (tile % self.atlas_size.x) as f32,
+                    (tile / self.atlas_size.x) as f32,
+                ) * atlas_tile_size;
+                let uv_max = uv_min + atlas_tile_size;
+
+                let first_vertex = vertices.len() as u32;
+                // the rows of the atlas go down, while the rows of the map go up
+                for (position, uv) in [
+                    (min, Vec2::new(uv_min.x, uv_max.y)),
+                    (Vec2::new(max.x, min.y), uv_max),
+                    (max, Vec2::new(uv_max.x, uv_min.y)),
+                    (Vec2::new(min.x, max.y), uv_min),
+                ]
+                .iter()
+                {
+                    vertices.push(TileVertex {
+                        position: position.extend(0.0).into(),
+                        uv: (*uv).into(),
+                    });
+                }
+                indices.extend([0, 1, 2, 0, 2, 3].iter().map(|index| first_vertex + index));
+            }
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+        let min = Vec2::new(first_tile.x as f32, first_tile.y as f32) * self.tile_size;
+        let max = Vec2::new(last_tile.x as f32, last_tile.y as f32) * self.tile_size;
+        Some(TileMapChunkMesh {
+            vertices,
+            indices,
+            aabb: Aabb::from_min_max(min.extend(0.0), max.extend(0.0)),
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct TileVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+pub(crate) struct TileMapChunkMesh {
+    pub vertices: Vec<TileVertex>,
+    pub indices: Vec<u32>,
+    pub aabb: Aabb,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TileMap;
+    use bevy_asset::Handle;
+    use bevy_math::{UVec2, Vec2, Vec3};
+
+    fn tile_map(size: UVec2, layers: usize) -> TileMap {
+        TileMap::new(
+            size,
+            layers,
+            Vec2::splat(2.0),
+            Handle::default(),
+            UVec2::ONE,
+        )
+    }
+
+    #[test]
+    fn partial_chunks_cover_the_last_tiles() {
+        let mut tile_map = tile_map(UVec2::new(33, 32), 2);
+        assert_eq!(tile_map.chunks(), UVec2::new(2, 1));
+        assert_eq!(tile_map.chunk_count(), 4);
+
+        tile_map.fill(1, Some(0));
+        // the chunks of the first layer are empty
+        assert!(tile_map.chunk_mesh(0).is_none());
+        assert!(tile_map.chunk_mesh(1).is_none());
+        let full = tile_map.chunk_mesh(2).unwrap();
+        assert_eq!(full.indices.len(), 32 * 32 * 6);
+        assert_eq!(full.aabb.min(), Vec3::ZERO);
+        assert_eq!(full.aabb.max(), Vec3::new(64.0, 64.0, 0.0));
+        let partial = tile_map.chunk_mesh(3).unwrap();
+        assert_eq!(partial.indices.len(), 32 * 6);
+        assert_eq!(partial.aabb.min(), Vec3::new(64.0, 0.0, 0.0));
+        assert_eq!(partial.aabb.max(), Vec3::new(66.0, 64.0, 0.0));
+    }
+
+    #[test]
+    fn edits_change_the_generation_of_their_chunk() {
+        let mut tile_map = tile_map(UVec2::new(64, 64), 2);
+        let generations = |tile_map: &TileMap| {
+            (0..tile_map.chunk_count())
+                .map(|chunk| tile_map.chunk_generation(chunk))
+                .collect::<Vec<_>>()
+        };
+        let changed = |before: &[usize], after: &[usize]| {
+            (0..before.len())
+                .filter(|chunk| before[*chunk] != after[*chunk])
+                .collect::<Vec<_>>()
+        };
+
+        // the tiles on both sides of the edges between the chunks of a layer
+        let edits = [
+            (0, UVec2::new(31, 0), 0),
+            (0, UVec2::new(32, 0), 1),
+            (0, UVec2::new(0, 31), 0),
+            (0, UVec2::new(0, 32), 2),
+            (0, UVec2::new(32, 32), 3),
+            (1, UVec2::new(31, 31), 4),
+            (1, UVec2::new(63, 63), 7),
+        ];
+        for (layer, position, chunk) in edits.iter() {
+            let before = generations(&tile_map);
+            tile_map.set(*layer, *position, Some(0));
+            assert_eq!(
+                changed(&before, &generations(&tile_map)),
+                vec![*chunk],
+                "setting {} in layer {}",
+                position,
+                layer
+            );
+        }
+
+        let before = generations(&tile_map);
+        tile_map.fill(1, None);
+        assert_eq!(changed(&before, &generations(&tile_map)), vec![4, 5, 6, 7]);
+    }
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec2 Vertex_Uv;
+
+layout(location = 0) out vec2 v_Uv;
+layout(location = 1) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform View {
+    mat4 ViewProj;
+    vec3 ViewWorldPosition;
+};
+
+layout(set = 2, binding = 0) uniform TileMap {
+    mat4 Model;
+    vec4 Color;
+};
+
+void main() {
+    v_Uv = Vertex_Uv;
+    v_Color = Color;
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+}
@@ -21,6 +21,14 @@ impl WgpuResourceDiagnosticsPlugin {
         DiagnosticId::from_u128(283571569334075937453357861280307923122);
     pub const BIND_GROUP_LAYOUTS: DiagnosticId =
         DiagnosticId::from_u128(96406067032931216377076410852598331304);
+    pub const BIND_GROUPS_CREATED: DiagnosticId =
+        DiagnosticId::from_u128(6301948651549273727793464041041680311);
+    pub const BIND_GROUPS_REUSED: DiagnosticId =
+        DiagnosticId::from_u128(293356206848137761490817273739732719192);
+    pub const BIND_GROUPS_EVICTED: DiagnosticId =
+        DiagnosticId::from_u128(66544200504808887052881814020653977550);
+    pub const BIND_GROUPS_REAPED: DiagnosticId =
+        DiagnosticId::from_u128(197605573469599115676677463404884857374);
     pub const BUFFERS: DiagnosticId =
         DiagnosticId::from_u128(133146619577893994787249934474491530491);
     pub const RENDER_PIPELINES: DiagnosticId =
@@ -39,6 +47,10 @@ impl WgpuResourceDiagnosticsPlugin {
         DiagnosticId::from_u128(257307432866562594739240898780307437578);
     pub const WINDOW_SURFACES: DiagnosticId =
         DiagnosticId::from_u128(108237028251680341878766034324149135605);
+    pub const SUBMISSIONS: DiagnosticId =
+        DiagnosticId::from_u128(91182419039265213981845214178166730952);
+    pub const COMMAND_BUFFERS_SUBMITTED: DiagnosticId =
+        DiagnosticId::from_u128(97193683695646727004266313008370712689);
 
     pub fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
         diagnostics.add(Diagnostic::new(
@@ -72,6 +84,30 @@ impl WgpuResourceDiagnosticsPlugin {
             10,
         ));
 
+        diagnostics.add(Diagnostic::new(
+            Self::BIND_GROUPS_CREATED,
+            "bind_groups_created",
+            10,
+        ));
+
+        diagnostics.add(Diagnostic::new(
+            Self::BIND_GROUPS_REUSED,
+            "bind_groups_reused",
+            10,
+        ));
+
+        diagnostics.add(Diagnostic::new(
+            Self::BIND_GROUPS_EVICTED,
+            "bind_groups_evicted",
+            10,
+        ));
+
+        diagnostics.add(Diagnostic::new(
+            Self::BIND_GROUPS_REAPED,
+            "bind_groups_reaped",
+            10,
+        ));
+
         diagnostics.add(Diagnostic::new(Self::SHADER_MODULES, "shader_modules", 10));
 
         diagnostics.add(Diagnostic::new(
@@ -79,15 +115,27 @@ impl WgpuResourceDiagnosticsPlugin {
             "render_pipelines",
             10,
         ));
+
+        diagnostics.add(Diagnostic::new(Self::SUBMISSIONS, "submissions", 10));
+
+        diagnostics.add(Diagnostic::new(
+            Self::COMMAND_BUFFERS_SUBMITTED,
+            "command_buffers_submitted",
+            10,
+        ));
     }
 
     pub fn diagnostic_system(
         mut diagnostics: ResMut<Diagnostics>,
-        render_resources: Res<RenderResources>,
+        render_resources: Option<Res<RenderResources>>,
     ) {
-        let render_resource_context = render_resources
-            .downcast_ref::<WgpuRenderResourceContext>()
-            .unwrap();
+        let render_resource_context = match render_resources {
+            Some(ref render_resources) => render_resources
+                .downcast_ref::<WgpuRenderResourceContext>()
+                .unwrap(),
+            // the renderer is pending
+            None => return,
+        };
 
         diagnostics.add_measurement(
             Self::WINDOW_SURFACES,
@@ -162,6 +210,24 @@ impl WgpuResourceDiagnosticsPlugin {
                 .len() as f64,
         );
 
+        let bind_group_cache_stats = render_resource_context.bind_group_cache_stats();
+        diagnostics.add_measurement(
+            Self::BIND_GROUPS_CREATED,
+            bind_group_cache_stats.created as f64,
+        );
+        diagnostics.add_measurement(
+            Self::BIND_GROUPS_REUSED,
+            bind_group_cache_stats.reused as f64,
+        );
+        diagnostics.add_measurement(
+            Self::BIND_GROUPS_EVICTED,
+            bind_group_cache_stats.evicted as f64,
+        );
+        diagnostics.add_measurement(
+            Self::BIND_GROUPS_REAPED,
+            bind_group_cache_stats.reaped as f64,
+        );
+
         diagnostics.add_measurement(
             Self::SHADER_MODULES,
             render_resource_context
@@ -179,5 +245,12 @@ impl WgpuResourceDiagnosticsPlugin {
                 .read()
                 .len() as f64,
         );
+
+        let submission_stats = render_resource_context.submission_stats();
+        diagnostics.add_measurement(Self::SUBMISSIONS, submission_stats.submissions as f64);
+        diagnostics.add_measurement(
+            Self::COMMAND_BUFFERS_SUBMITTED,
+            submission_stats.command_buffers as f64,
+        );
     }
 }
@@ -0,0 +1,177 @@
+use bevy_render2::{
+    render_resource::BufferInfo,
+    renderer::{RenderError, RenderErrorKind},
+    texture::TextureDescriptor,
+};
+use futures_lite::future;
+use parking_lot::Mutex;
+use std::{borrow::Cow, fmt::Debug, future::Future, pin::Pin, sync::Arc};
+
+type ErrorScopeFuture = Pin<Box<dyn Future<Output = Option<wgpu::Error>> + Send>>;
+
+struct PendingErrorScope {
+    operation: &'static str,
+    label: ResourceLabel,
+    future: ErrorScopeFuture,
+}
+
+/// Describes the resource an operation in an error scope works on. It is only formatted when an
+/// error is reported, so frequent operations like buffer writes don't allocate a label.
+#[derive(Clone, Debug)]
+pub enum ResourceLabel {
+    Unlabeled,
+    Named(Cow<'static, str>),
+    Buffer(BufferInfo),
+    Texture(TextureDescriptor),
+}
+
+impl ResourceLabel {
+    /// Labels a resource with `name`, or leaves it unlabeled.
+    pub fn from_name(name: Option<&str>) -> Self {
+        match name {
+            Some(name) => ResourceLabel::Named(Cow::Owned(name.to_string())),
+            None => ResourceLabel::Unlabeled,
+        }
+    }
+
+    /// Returns the label [`RenderError`]s are reported with.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            ResourceLabel::Unlabeled => None,
+            ResourceLabel::Named(name) => Some(name.to_string()),
+            ResourceLabel::Buffer(info) => Some(format!(
+                "{} byte buffer ({:?})",
+                info.size, info.buffer_usage
+            )),
+            ResourceLabel::Texture(descriptor) => Some(format!(
+                "{}x{}x{} {:?} texture ({:?})",
+                descriptor.size.width,
+                descriptor.size.height,
+                descriptor.size.depth_or_array_layers,
+                descriptor.format,
+                descriptor.usage
+            )),
+        }
+    }
+}
+
+impl From<&'static str> for ResourceLabel {
+    fn from(name: &'static str) -> Self {
+        ResourceLabel::Named(Cow::Borrowed(name))
+    }
+}
+
+impl From<String> for ResourceLabel {
+    fn from(name: String) -> Self {
+        ResourceLabel::Named(Cow::Owned(name))
+    }
+}
+
+/// Collects the errors wgpu reports for operations wrapped in [`WgpuErrors::scope`], as well as
+/// uncaptured errors, until they are drained into [`RenderError`] events.
+#[derive(Clone, Default)]
+pub struct WgpuErrors {
+    /// Held from pushing the error scopes of an operation until they are popped. wgpu keeps one
+    /// stack of error scopes per device, so an operation of another thread in between would be
+    /// reported for the wrong one.
+    scope_lock: Arc<Mutex<()>>,
+    pending: Arc<Mutex<Vec<PendingErrorScope>>>,
+    errors: Arc<Mutex<Vec<RenderError>>>,
+}
+
+impl Debug for WgpuErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WgpuErrors")
+            .field("pending", &self.pending.lock().len())
+            .field("errors", &*self.errors.lock())
+            .finish()
+    }
+}
+
+impl WgpuErrors {
+    /// Reports errors raised by `device` outside of any error scope instead of panicking.
+    pub fn capture_uncaptured_errors(&self, device: &wgpu::Device) {
+        let errors = self.errors.clone();
+        device.on_uncaptured_error(move |error| {
+            errors
+                .lock()
+                .push(render_error(error, "uncaptured", &ResourceLabel::Unlabeled));
+        });
+    }
+
+    /// Runs `f` inside validation and out-of-memory error scopes of `device`. Errors are reported
+    /// for `operation` on the resource `label`.
+    ///
+    /// Scopes of different threads are serialized, so `f` must not call `scope` itself.
+    pub fn scope<T>(
+        &self,
+        device: &wgpu::Device,
+        operation: &'static str,
+        label: ResourceLabel,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let (value, validation, out_of_memory) = {
+            let _scope = self.scope_lock.lock();
+            device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let value = f();
+            let validation = device.pop_error_scope();
+            let out_of_memory = device.pop_error_scope();
+            (value, validation, out_of_memory)
+        };
+
+        let mut pending = self.pending.lock();
+        pending.push(PendingErrorScope {
+            operation,
+            label: label.clone(),
+            future: Box::pin(validation),
+        });
+        pending.push(PendingErrorScope {
+            operation,
+            label,
+            future: Box::pin(out_of_memory),
+        });
+        value
+    }
+
+    /// Reports an error found by bevy's own validation, before calling into wgpu.
+    pub fn report(&self, error: RenderError) {
+        self.errors.lock().push(error);
+    }
+
+    /// Returns the errors reported since the last call. Error scopes that haven't resolved yet
+    /// are checked again on the next call.
+    pub fn drain(&self) -> Vec<RenderError> {
+        let mut errors = std::mem::take(&mut *self.errors.lock());
+        let mut pending = self.pending.lock();
+        let mut unresolved = Vec::new();
+        for mut scope in pending.drain(..) {
+            match future::block_on(future::poll_once(&mut scope.future)) {
+                Some(Some(error)) => {
+                    errors.push(render_error(error, scope.operation, &scope.label))
+                }
+                Some(None) => {}
+                None => unresolved.push(scope),
+            }
+        }
+        *pending = unresolved;
+        errors
+    }
+}
+
+fn render_error(error: wgpu::Error, operation: &'static str, label: &ResourceLabel) -> RenderError {
+    let (kind, message) = match error {
+        wgpu::Error::OutOfMemoryError { source } => {
+            (RenderErrorKind::OutOfMemory, source.to_string())
+        }
+        wgpu::Error::ValidationError { description, .. } => {
+            (RenderErrorKind::Validation, description)
+        }
+    };
+    RenderError {
+        kind,
+        operation,
+        label: label.describe(),
+        message,
+    }
+}
@@ -0,0 +1,69 @@
+use futures_lite::future;
+use std::{collections::VecDeque, future::Future, pin::Pin};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+const FENCE_SIZE: u64 = wgpu::COPY_BUFFER_ALIGNMENT;
+
+/// Blocks the CPU when it gets more than a set number of frames ahead of the GPU.
+///
+/// Each frame's submission writes to a small fence buffer, which is mapped right after. The
+/// mapping completes once the GPU has executed the submission, so the pending mappings are the
+/// frames in flight.
+pub(crate) struct FrameLatencyLimiter {
+    max_frame_latency: usize,
+    frame_fence: Option<wgpu::Buffer>,
+    in_flight: VecDeque<(wgpu::Buffer, MapFuture)>,
+    free_fences: Vec<wgpu::Buffer>,
+}
+
+impl FrameLatencyLimiter {
+    pub fn new(max_frame_latency: u32) -> Self {
+        FrameLatencyLimiter {
+            max_frame_latency: max_frame_latency.max(1) as usize,
+            frame_fence: None,
+            in_flight: VecDeque::new(),
+            free_fences: Vec::new(),
+        }
+    }
+
+    /// Queues a write to a fence buffer, which becomes part of the frame's submission.
+    pub fn begin_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let fence = self.free_fences.pop().unwrap_or_else(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frame_latency_fence"),
+                size: FENCE_SIZE,
+                usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        queue.write_buffer(&fence, 0, &[0; FENCE_SIZE as usize]);
+        self.frame_fence = Some(fence);
+    }
+
+    /// Tracks the frame submitted since [`FrameLatencyLimiter::begin_frame`], then waits until no
+    /// more than the maximum number of frames are in flight.
+    pub fn end_frame(&mut self, device: &wgpu::Device) {
+        let fence = match self.frame_fence.take() {
+            Some(fence) => fence,
+            None => return,
+        };
+        let future = fence.slice(..).map_async(wgpu::MapMode::Read);
+        self.in_flight.push_back((fence, Box::pin(future)));
+        while self.in_flight.len() > self.max_frame_latency {
+            let (fence, mut future) = self.in_flight.pop_front().unwrap();
+            let result = loop {
+                device.poll(wgpu::Maintain::Poll);
+                if let Some(result) = future::block_on(future::poll_once(&mut future)) {
+                    break result;
+                }
+                std::thread::yield_now();
+            };
+            // a failed mapping means the device was lost, in which case the fence is dropped
+            if result.is_ok() {
+                fence.unmap();
+                self.free_fences.push(fence);
+            }
+        }
+    }
+}
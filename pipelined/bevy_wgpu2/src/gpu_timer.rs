@@ -0,0 +1,132 @@
+use bevy_utils::Duration;
+use futures_lite::future;
+use std::{collections::VecDeque, future::Future, pin::Pin};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// The size of the start and end timestamps of a frame.
+const TIMESTAMPS_SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+/// The queries of one frame, and the buffers their results are read back through.
+struct FrameQueries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+/// Measures the GPU time of each frame with timestamps written before and after its commands.
+///
+/// The timestamps are mapped after the frame is submitted and polled for in later frames, so the
+/// CPU never waits for them and the measurements arrive a few frames late.
+pub(crate) struct GpuFrameTimer {
+    /// The number of nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    current: Option<FrameQueries>,
+    in_flight: VecDeque<(FrameQueries, MapFuture)>,
+    free_queries: Vec<FrameQueries>,
+}
+
+impl GpuFrameTimer {
+    /// Returns a timer if the device supports timestamp queries.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        Some(GpuFrameTimer {
+            timestamp_period: queue.get_timestamp_period(),
+            current: None,
+            in_flight: VecDeque::new(),
+            free_queries: Vec::new(),
+        })
+    }
+
+    /// Returns the command buffer that writes the start timestamp, which has to be submitted
+    /// ahead of the frame's commands.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) -> wgpu::CommandBuffer {
+        let queries = self.free_queries.pop().unwrap_or_else(|| FrameQueries {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_frame_timer_resolve"),
+                size: TIMESTAMPS_SIZE,
+                usage: wgpu::BufferUsage::QUERY_RESOLVE | wgpu::BufferUsage::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_frame_timer_readback"),
+                size: TIMESTAMPS_SIZE,
+                usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        });
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.write_timestamp(&queries.query_set, 0);
+        self.current = Some(queries);
+        encoder.finish()
+    }
+
+    /// Returns the command buffer that writes the end timestamp and copies both timestamps to be
+    /// read back, which has to be submitted after the frame's commands.
+    pub fn end_frame(&mut self, device: &wgpu::Device) -> Option<wgpu::CommandBuffer> {
+        let queries = self.current.as_ref()?;
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.write_timestamp(&queries.query_set, 1);
+        encoder.resolve_query_set(&queries.query_set, 0..2, &queries.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.readback_buffer,
+            0,
+            TIMESTAMPS_SIZE,
+        );
+        Some(encoder.finish())
+    }
+
+    /// Starts reading back the timestamps of the frame submitted since
+    /// [`GpuFrameTimer::end_frame`], and returns the GPU time of the latest frame whose
+    /// timestamps have been read since the last call.
+    pub fn read(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        if let Some(queries) = self.current.take() {
+            let future = queries
+                .readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read);
+            self.in_flight.push_back((queries, Box::pin(future)));
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        let mut frame_time = None;
+        while let Some((_, future)) = self.in_flight.front_mut() {
+            let result = match future::block_on(future::poll_once(future)) {
+                Some(result) => result,
+                None => break,
+            };
+            let (queries, _) = self.in_flight.pop_front().unwrap();
+            // a failed mapping means the device was lost, in which case the queries are dropped
+            if result.is_err() {
+                continue;
+            }
+            {
+                let data = queries.readback_buffer.slice(..).get_mapped_range();
+                let timestamp = |offset: usize| {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(&data[offset..offset + 8]);
+                    u64::from_le_bytes(bytes)
+                };
+                let (start, end) = (timestamp(0), timestamp(8));
+                // some drivers reset their counters, e.g. when the GPU changes its clock
+                if end >= start {
+                    let nanos = (end - start) as f64 * self.timestamp_period as f64;
+                    frame_time = Some(Duration::from_nanos(nanos as u64));
+                }
+            }
+            queries.readback_buffer.unmap();
+            self.free_queries.push(queries);
+        }
+        frame_time
+    }
+}
@@ -1,26 +1,49 @@
 pub mod diagnostic;
 
 mod compute_pass;
+mod error;
+mod frame_latency;
+mod gpu_timer;
+mod pending_maps;
 mod render_context;
 mod render_graph_runner;
 mod render_pass;
 mod render_resource_context;
 mod renderer;
 mod resources;
+mod staging_belt;
+mod submission;
 mod type_converter;
 
 pub use compute_pass::*;
+pub use error::*;
 pub use render_context::*;
 pub use render_graph_runner::*;
 pub use render_pass::*;
 pub use render_resource_context::*;
 pub use renderer::*;
+pub use resources::{BindGroupCacheLimits, BindGroupCacheStats};
+pub use submission::SubmissionStats;
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
-use bevy_render2::{renderer::RenderResources, RenderStage};
+#[cfg(target_arch = "wasm32")]
+use bevy_render2::renderer::RendererPending;
+use bevy_render2::{
+    render_command::AssetUploadQueue,
+    renderer::{run_pending_render_inits, RenderError, RenderResources},
+    RenderActive, RenderApp, RenderStage,
+};
+#[cfg(target_arch = "wasm32")]
+use bevy_tasks::{IoTaskPool, TaskPool};
+use bevy_utils::tracing::error;
+#[cfg(not(target_arch = "wasm32"))]
 use futures_lite::future;
-use std::borrow::Cow;
+#[cfg(target_arch = "wasm32")]
+use parking_lot::Mutex;
+#[cfg(target_arch = "wasm32")]
+use std::sync::Arc;
+use std::{borrow::Cow, path::PathBuf};
 
 #[derive(Clone, Copy)]
 pub enum WgpuFeature {
@@ -43,6 +66,7 @@ pub enum WgpuFeature {
     TextureAdapterSpecificFormatFeatures,
     ShaderFloat64,
     VertexAttribute64Bit,
+    ConservativeRasterization,
 }
 
 #[derive(Default, Clone)]
@@ -110,28 +134,155 @@ impl Plugin for WgpuPlugin {
             .get_resource::<WgpuOptions>()
             .cloned()
             .unwrap_or_else(WgpuOptions::default);
-        let wgpu_renderer = future::block_on(WgpuRenderer::new(options));
-        let resource_context = WgpuRenderResourceContext::new(
-            wgpu_renderer.device.clone(),
-            wgpu_renderer.queue.clone(),
-        );
-        app.world
-            .insert_resource(RenderResources::new(Box::new(resource_context.clone())));
-        let render_app = app.sub_app_mut(0);
-        render_app
-            .insert_resource(RenderResources::new(Box::new(resource_context)))
-            .insert_resource(wgpu_renderer)
+        app.add_event::<RenderError>()
+            .add_system_to_stage(CoreStage::Last, wgpu_error_system.system());
+        app.sub_app_mut(RenderApp)
+            .insert_resource(WgpuSubmitOptions {
+                submit_asset_uploads_early: options.submit_asset_uploads_early,
+            })
             .add_system_to_stage(RenderStage::Prepare, wgpu_window_system.exclusive_system())
+            .add_system_to_stage(RenderStage::Prepare, wgpu_asset_upload_system.system())
             .add_system_to_stage(RenderStage::Render, wgpu_render_system.exclusive_system());
+
+        if let Some(external_device) = app.world.remove_resource::<WgpuExternalDevice>() {
+            let wgpu_renderer = WgpuRenderer::from_device(external_device, &options);
+            insert_renderer(app, wgpu_renderer, &options);
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let wgpu_renderer = future::block_on(WgpuRenderer::new(options.clone()));
+            insert_renderer(app, wgpu_renderer, &options);
+        }
+        // blocking would never let the browser complete the requests, so the device is requested
+        // in the background and the frames are skipped until it is ready
+        #[cfg(target_arch = "wasm32")]
+        {
+            let requested_device = Arc::new(Mutex::new(None));
+            let task_pool = app
+                .world
+                .get_resource::<IoTaskPool>()
+                .map_or_else(TaskPool::new, |task_pool| task_pool.0.clone());
+            let request_options = options.clone();
+            let request_result = requested_device.clone();
+            task_pool
+                .spawn(async move {
+                    let external_device = WgpuExternalDevice::request(&request_options).await;
+                    *request_result.lock() = Some(external_device);
+                })
+                .detach();
+            app.insert_resource(RendererPending::new(move |app_world, render_app| {
+                let external_device = match requested_device.lock().take() {
+                    Some(external_device) => external_device,
+                    None => return false,
+                };
+                let wgpu_renderer = WgpuRenderer::from_device(external_device, &options);
+                let resource_context = create_resource_context(&wgpu_renderer, &options);
+                app_world.insert_resource(RenderResources::new(Box::new(resource_context.clone())));
+                render_app
+                    .insert_resource(RenderResources::new(Box::new(resource_context)))
+                    .insert_resource(wgpu_renderer);
+                true
+            }));
+        }
     }
 }
 
+/// Inserts the resources of `wgpu_renderer` while the app is built, and runs the render inits
+/// that were added before them.
+fn insert_renderer(app: &mut App, wgpu_renderer: WgpuRenderer, options: &WgpuOptions) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(directory) = options.shader_cache_directory.clone() {
+        bevy_render2::shader::ShaderCache::new(directory).install();
+    }
+    let resource_context = create_resource_context(&wgpu_renderer, options);
+    app.world
+        .insert_resource(RenderResources::new(Box::new(resource_context.clone())));
+    let render_app = app.sub_app_mut(RenderApp);
+    render_app
+        .insert_resource(RenderResources::new(Box::new(resource_context)))
+        .insert_resource(wgpu_renderer);
+    run_pending_render_inits(render_app);
+}
+
+fn create_resource_context(
+    wgpu_renderer: &WgpuRenderer,
+    options: &WgpuOptions,
+) -> WgpuRenderResourceContext {
+    let resource_context =
+        WgpuRenderResourceContext::new(wgpu_renderer.device.clone(), wgpu_renderer.queue.clone());
+    resource_context
+        .resources
+        .bind_group_counter
+        .set_limits(options.bind_group_cache);
+    resource_context
+}
+
 pub fn wgpu_render_system(world: &mut World) {
     world.resource_scope(|world, mut renderer: Mut<WgpuRenderer>| {
-        renderer.update(world);
+        let active = world
+            .get_resource::<RenderActive>()
+            .map_or(true, |render_active| render_active.0);
+        if active {
+            renderer.update(world);
+        } else {
+            renderer.update_inactive(world);
+        }
     })
 }
 
+/// How [`wgpu_asset_upload_system`] submits uploads, from [`WgpuOptions`].
+pub struct WgpuSubmitOptions {
+    pub submit_asset_uploads_early: bool,
+}
+
+/// Records the [`AssetUploadQueue`] in its own command buffer, ahead of the frame's. By default it
+/// is submitted together with the frame's command buffer, which saves a submission. Command
+/// buffers of a submission, and submissions to the queue, execute in order, so the frame only
+/// reads the uploads once they are complete.
+///
+/// wgpu only exposes a single queue, so uploads can't run on a dedicated transfer queue yet.
+pub fn wgpu_asset_upload_system(
+    render_resources: Res<RenderResources>,
+    submit_options: Res<WgpuSubmitOptions>,
+    mut asset_upload_queue: ResMut<AssetUploadQueue>,
+) {
+    if asset_upload_queue.is_empty() {
+        return;
+    }
+    let resource_context = render_resources
+        .downcast_ref::<WgpuRenderResourceContext>()
+        .unwrap();
+    let mut render_context =
+        WgpuRenderContext::new(resource_context.device.clone(), resource_context.clone());
+    asset_upload_queue.execute(&mut render_context);
+    asset_upload_queue.clear();
+    if let Some(command_buffer) = render_context.finish() {
+        resource_context.queue_command_buffer(command_buffer);
+        if submit_options.submit_asset_uploads_early {
+            resource_context.submit();
+        }
+    }
+}
+
+/// Sends the errors reported by wgpu as [`RenderError`] events.
+pub fn wgpu_error_system(
+    render_resources: Option<Res<RenderResources>>,
+    mut render_errors: EventWriter<RenderError>,
+) {
+    let render_resource_context = match render_resources {
+        Some(ref render_resources) => render_resources
+            .downcast_ref::<WgpuRenderResourceContext>()
+            .unwrap(),
+        // the renderer is pending
+        None => return,
+    };
+    for render_error in render_resource_context.errors.drain() {
+        error!("{}", render_error);
+        render_errors.send(render_error);
+    }
+}
+
 pub fn wgpu_window_system(world: &mut World) {
     world.resource_scope(|world, mut renderer: Mut<WgpuRenderer>| {
         renderer.handle_new_windows(world);
@@ -145,6 +296,19 @@ pub struct WgpuOptions {
     pub power_pref: WgpuPowerOptions,
     pub features: WgpuFeatures,
     pub limits: WgpuLimits,
+    pub bind_group_cache: BindGroupCacheLimits,
+    /// Submits asset uploads on their own as soon as they are recorded, so the GPU can start on
+    /// them while the frame is prepared, instead of together with the frame's commands.
+    pub submit_asset_uploads_early: bool,
+    /// The maximum number of frames the CPU may get ahead of the GPU. After presenting a frame,
+    /// the renderer waits until no more frames than this are still executing on the GPU. Lower
+    /// values reduce input latency at the cost of throughput, with 1 waiting for the previous
+    /// frame. `None` leaves the latency to the backend, which usually queues 2 to 3 frames.
+    /// Ignored on the web.
+    pub max_frame_latency: Option<u32>,
+    /// A directory to cache compiled shaders in, which speeds up the startup of later runs. See
+    /// [`ShaderCache`](bevy_render2::shader::ShaderCache) for what is cached. Ignored on the web.
+    pub shader_cache_directory: Option<PathBuf>,
 }
 
 #[derive(Clone)]
@@ -0,0 +1,59 @@
+use bevy_render2::render_resource::BufferId;
+use bevy_utils::HashMap;
+use futures_lite::future;
+use std::{fmt, future::Future, pin::Pin};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// The buffers mapped with
+/// [`RenderResourceContext::map_buffer_async`](bevy_render2::renderer::RenderResourceContext::map_buffer_async)
+/// whose mapping hasn't completed yet.
+#[derive(Default)]
+pub(crate) struct PendingMaps {
+    maps: HashMap<BufferId, MapFuture>,
+}
+
+impl fmt::Debug for PendingMaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingMaps")
+            .field("maps", &self.maps.len())
+            .finish()
+    }
+}
+
+impl PendingMaps {
+    pub fn insert(
+        &mut self,
+        id: BufferId,
+        future: impl Future<Output = Result<(), wgpu::BufferAsyncError>> + Send + 'static,
+    ) {
+        self.maps.insert(id, Box::pin(future));
+    }
+
+    /// Returns whether the mapping of `id` has completed, without waiting for it. Buffers that
+    /// aren't being mapped are reported as mapped.
+    ///
+    /// # Panics
+    /// Panics if the mapping failed, like
+    /// [`RenderResourceContext::map_buffer`](bevy_render2::renderer::RenderResourceContext::map_buffer).
+    pub fn poll(&mut self, id: BufferId) -> bool {
+        let future = match self.maps.get_mut(&id) {
+            Some(future) => future,
+            None => return true,
+        };
+        match future::block_on(future::poll_once(future)) {
+            Some(result) => {
+                if result.is_err() {
+                    panic!("Failed to map buffer to host.");
+                }
+                self.maps.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, id: BufferId) {
+        self.maps.remove(&id);
+    }
+}
@@ -160,6 +160,11 @@ impl RenderContext for WgpuRenderContext {
         )
     }
 
+    fn generate_mipmaps(&mut self, texture: TextureId) {
+        self.render_resource_context
+            .generate_mipmaps(self.command_encoder.get_or_create(&self.device), texture)
+    }
+
     fn resources(&self) -> &dyn RenderResourceContext {
         &self.render_resource_context
     }
@@ -1,10 +1,14 @@
 use crate::{WgpuRenderContext, WgpuRenderResourceContext};
 use bevy_ecs::world::World;
-use bevy_render2::render_graph::{
-    Edge, NodeId, NodeRunError, NodeState, RenderGraph, RenderGraphContext, SlotLabel, SlotType,
-    SlotValue,
+use bevy_render2::{
+    diagnostic::RenderTimings,
+    frame_trace::{FrameTrace, NodeTrace, TracingRenderContext},
+    render_graph::{
+        Edge, NodeId, NodeRunError, NodeState, RenderGraph, RenderGraphContext, SlotLabel,
+        SlotType, SlotValue,
+    },
 };
-use bevy_utils::{tracing::debug, HashMap};
+use bevy_utils::{tracing::debug, HashMap, Instant};
 use smallvec::{smallvec, SmallVec};
 use std::{borrow::Cow, collections::VecDeque, sync::Arc};
 use thiserror::Error;
@@ -37,17 +41,21 @@ pub enum WgpuRenderGraphRunnerError {
 }
 
 impl WgpuRenderGraphRunner {
+    /// Records all nodes, including those of sub graphs, into a single command buffer, which is
+    /// queued for the next submission. The nodes are recorded into `trace` too, if it is set.
     pub fn run(
         graph: &RenderGraph,
         device: Arc<wgpu::Device>,
-        queue: &wgpu::Queue,
         world: &World,
         resources: &WgpuRenderResourceContext,
+        timings: &mut RenderTimings,
+        trace: Option<&mut FrameTrace>,
     ) -> Result<(), WgpuRenderGraphRunnerError> {
         let mut render_context = WgpuRenderContext::new(device, resources.clone());
-        Self::run_graph(graph, None, &mut render_context, world, &[])?;
+        let mut state = RunState { timings, trace };
+        Self::run_graph(graph, None, &mut render_context, world, &[], &mut state)?;
         if let Some(command_buffer) = render_context.finish() {
-            queue.submit(vec![command_buffer]);
+            resources.queue_command_buffer(command_buffer);
         }
         Ok(())
     }
@@ -58,6 +66,7 @@ impl WgpuRenderGraphRunner {
         render_context: &mut WgpuRenderContext,
         world: &World,
         inputs: &[SlotValue],
+        state: &mut RunState,
     ) -> Result<(), WgpuRenderGraphRunnerError> {
         let mut node_outputs: HashMap<NodeId, SmallVec<[SlotValue; 4]>> = HashMap::default();
         debug!("-----------------");
@@ -149,7 +158,22 @@ impl WgpuRenderGraphRunner {
             {
                 let mut context = RenderGraphContext::new(graph, node_state, &inputs, &mut outputs);
                 debug!("  Run Node {}", node_state.type_name);
-                node_state.node.run(&mut context, render_context, world)?;
+                let name = node_timing_name(&graph_name, node_state);
+                let start = Instant::now();
+                match state.trace.as_deref_mut() {
+                    Some(trace) => {
+                        let mut node_trace = NodeTrace::new(name.clone());
+                        let result = node_state.node.run(
+                            &mut context,
+                            &mut TracingRenderContext::new(render_context, &mut node_trace),
+                            world,
+                        );
+                        trace.nodes.push(node_trace);
+                        result?;
+                    }
+                    None => node_state.node.run(&mut context, render_context, world)?,
+                }
+                state.timings.add_node_time(name, start.elapsed());
 
                 for run_sub_graph in context.finish() {
                     let sub_graph = graph
@@ -162,6 +186,7 @@ impl WgpuRenderGraphRunner {
                         render_context,
                         world,
                         &run_sub_graph.inputs,
+                        state,
                     )?;
                 }
             }
@@ -190,3 +215,25 @@ impl WgpuRenderGraphRunner {
         Ok(())
     }
 }
+
+/// What a graph run records besides the commands of its nodes.
+struct RunState<'a> {
+    timings: &'a mut RenderTimings,
+    trace: Option<&'a mut FrameTrace>,
+}
+
+/// The name of a node in [`RenderTimings`]: its graph node name, or its type name if it has none,
+/// prefixed with the name of the sub graph it belongs to.
+fn node_timing_name(
+    graph_name: &Option<Cow<'static, str>>,
+    node_state: &NodeState,
+) -> Cow<'static, str> {
+    let node_name = match &node_state.name {
+        Some(name) => name.clone(),
+        None => node_state.type_name.rsplit("::").next().unwrap().into(),
+    };
+    match graph_name {
+        Some(graph_name) => format!("{}/{}", graph_name, node_name).into(),
+        None => node_name,
+    }
+}
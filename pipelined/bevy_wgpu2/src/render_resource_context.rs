@@ -1,23 +1,29 @@
 use crate::{
-    resources::{WgpuBindGroupInfo, WgpuResources},
+    error::{ResourceLabel, WgpuErrors},
+    pending_maps::PendingMaps,
+    resources::{BindGroupCacheStats, WgpuBindGroupInfo, WgpuResources},
+    staging_belt::StagingBelt,
+    submission::{SubmissionStats, Submissions},
     type_converter::{OwnedWgpuVertexBufferLayout, WgpuInto},
 };
 use bevy_render2::{
     pipeline::{
-        BindGroupDescriptor, BindGroupDescriptorId, BindingShaderStage, ComputePipelineDescriptor,
-        PipelineId, RenderPipelineDescriptor,
+        BindGroupDescriptor, BindGroupDescriptorId, BindType, BindingShaderStage,
+        ComputePipelineDescriptor, DepthBiasState, DepthStencilState, PipelineId, PolygonMode,
+        PrimitiveState, PrimitiveTopology, RenderPipelineDescriptor, VertexBufferLayout,
     },
     render_resource::{
-        BindGroup, BufferId, BufferInfo, BufferMapMode, RenderResourceBinding, SamplerId,
-        SwapChainDescriptor, TextureId, TextureViewId,
+        BindGroup, BufferId, BufferInfo, BufferMapMode, RenderResourceBinding, RenderResourceId,
+        SamplerId, StagingSlice, SwapChainDescriptor, TextureId, TextureViewId,
     },
-    renderer::RenderResourceContext,
-    shader::{Shader, ShaderId},
+    renderer::{RenderError, RenderErrorKind, RenderFeatures, RenderResourceContext},
+    shader::{binding_array_capabilities, BindingArrayCapability, Shader, ShaderId},
     texture::{Extent3d, SamplerDescriptor, TextureDescriptor, TextureViewDescriptor},
 };
 use bevy_utils::tracing::trace;
 use bevy_window::WindowId;
 use futures_lite::future;
+use parking_lot::Mutex;
 use std::{
     borrow::Cow,
     num::{NonZeroU32, NonZeroU64},
@@ -31,6 +37,10 @@ pub struct WgpuRenderResourceContext {
     pub device: Arc<wgpu::Device>,
     pub queue: Arc<wgpu::Queue>,
     pub resources: WgpuResources,
+    pub errors: WgpuErrors,
+    staging_belt: Arc<Mutex<StagingBelt>>,
+    submissions: Arc<Mutex<Submissions>>,
+    pending_maps: Arc<Mutex<PendingMaps>>,
 }
 
 pub const COPY_BYTES_PER_ROW_ALIGNMENT: usize = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
@@ -40,18 +50,326 @@ pub const PUSH_CONSTANT_ALIGNMENT: u32 = wgpu::PUSH_CONSTANT_ALIGNMENT;
 
 impl WgpuRenderResourceContext {
     pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+        let errors = WgpuErrors::default();
+        errors.capture_uncaptured_errors(&device);
         WgpuRenderResourceContext {
             device,
             queue,
             resources: WgpuResources::default(),
+            errors,
+            staging_belt: Default::default(),
+            submissions: Default::default(),
+            pending_maps: Default::default(),
         }
     }
 
+    /// Adds `command_buffer` to the next submission, after the command buffers queued before it.
+    pub fn queue_command_buffer(&self, command_buffer: wgpu::CommandBuffer) {
+        self.submissions.lock().push(command_buffer);
+    }
+
+    /// Submits the queued command buffers in a single submission. Returns `false` if there was
+    /// nothing to submit.
+    pub fn submit(&self) -> bool {
+        let command_buffers = self.submissions.lock().take();
+        if command_buffers.is_empty() {
+            return false;
+        }
+        self.errors
+            .scope(&self.device, "submit", ResourceLabel::Unlabeled, || {
+                self.queue.submit(command_buffers);
+            });
+        true
+    }
+
+    /// Completes the submission statistics of the current frame.
+    pub fn end_submission_frame(&self) {
+        self.submissions.lock().end_frame();
+    }
+
+    /// Returns the submissions of the last completed frame.
+    pub fn submission_stats(&self) -> SubmissionStats {
+        self.submissions.lock().stats()
+    }
+
+    /// Unmaps the staging belt chunks written to this frame. Call this before submitting the
+    /// commands that copy from them.
+    pub fn finish_staging_belt(&self) {
+        self.staging_belt.lock().finish();
+    }
+
+    /// Starts recycling the staging belt chunks of the submitted frame, and makes the chunks of
+    /// earlier frames that the GPU is done with available again.
+    pub fn recall_staging_belt(&self) {
+        self.staging_belt.lock().recall(self);
+    }
+
+    /// Runs `f` in an error scope, so that errors are reported as [`RenderError`] events for
+    /// `operation` on the resource described by `label`.
+    ///
+    /// [`RenderError`]: bevy_render2::renderer::RenderError
+    pub fn error_scope<T>(
+        &self,
+        operation: &'static str,
+        label: impl Into<ResourceLabel>,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        self.errors.scope(&self.device, operation, label.into(), f)
+    }
+
+    /// Labels writes to `buffer` with the buffer's size and usage.
+    fn buffer_label(&self, buffer: BufferId) -> ResourceLabel {
+        match self.resources.buffer_infos.read().get(&buffer) {
+            Some(info) => ResourceLabel::Buffer(info.clone()),
+            None => ResourceLabel::Unlabeled,
+        }
+    }
+
+    /// Labels operations on `texture` with the texture's size, format and usage.
+    fn texture_label(&self, texture: TextureId) -> ResourceLabel {
+        match self.resources.texture_descriptors.read().get(&texture) {
+            Some(descriptor) => ResourceLabel::Texture(*descriptor),
+            None => ResourceLabel::Unlabeled,
+        }
+    }
+
+    /// Reports an error if `buffer_info` has a usage or size that wgpu would reject, with a more
+    /// descriptive message than wgpu's.
+    fn validate_buffer_info(&self, buffer_info: &BufferInfo, operation: &'static str) {
+        let mappable_primary_buffers = self
+            .device
+            .features()
+            .contains(wgpu::Features::MAPPABLE_PRIMARY_BUFFERS);
+        if let Err(err) = buffer_info.validate(mappable_primary_buffers) {
+            self.errors.report(RenderError {
+                kind: RenderErrorKind::Validation,
+                operation,
+                label: ResourceLabel::Buffer(buffer_info.clone()).describe(),
+                message: err.to_string(),
+            });
+        }
+    }
+
+    /// Reports an error for each rasterization option that the device doesn't support or that
+    /// can't be combined with the rest of the pipeline, and returns copies of the states without
+    /// them, so the pipeline can still be created.
+    fn validate_rasterization_state(
+        &self,
+        primitive: &PrimitiveState,
+        depth_stencil: Option<&DepthStencilState>,
+        pipeline: Option<&str>,
+    ) -> (PrimitiveState, Option<DepthStencilState>) {
+        let report = |message: &str| {
+            self.errors.report(RenderError {
+                kind: RenderErrorKind::Validation,
+                operation: "create_render_pipeline",
+                label: pipeline.map(str::to_string),
+                message: message.to_string(),
+            })
+        };
+        let features = self.features();
+        let mut primitive = primitive.clone();
+        let mut depth_stencil = depth_stencil.cloned();
+        if primitive.clamp_depth && !features.contains(RenderFeatures::DEPTH_CLAMPING) {
+            report(
+                "clamp_depth requires the missing WgpuFeature DepthClamping, depth is clipped \
+                 instead",
+            );
+            primitive.clamp_depth = false;
+        }
+        if primitive.polygon_mode != PolygonMode::Fill
+            && !features.contains(RenderFeatures::NON_FILL_POLYGON_MODE)
+        {
+            report(
+                "polygon modes other than Fill require the missing WgpuFeature \
+                 NonFillPolygonMode, triangles are filled instead",
+            );
+            primitive.polygon_mode = PolygonMode::Fill;
+        }
+        if primitive.conservative {
+            if !features.contains(RenderFeatures::CONSERVATIVE_RASTERIZATION) {
+                report(
+                    "conservative rasterization requires the missing WgpuFeature \
+                     ConservativeRasterization",
+                );
+                primitive.conservative = false;
+            } else if primitive.polygon_mode != PolygonMode::Fill {
+                report("conservative rasterization requires PolygonMode::Fill");
+                primitive.conservative = false;
+            }
+        }
+        if let Some(depth_stencil) = depth_stencil.as_mut() {
+            let triangles = matches!(
+                primitive.topology,
+                PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip
+            );
+            if depth_stencil.bias.is_enabled() && !triangles {
+                report("a depth bias can only be used with triangle topologies");
+                depth_stencil.bias = DepthBiasState::default();
+            }
+        }
+        (primitive, depth_stencil)
+    }
+
+    /// Reports an error if `vertex_buffers` use 64-bit formats that weren't enabled with
+    /// [`WgpuFeature::VertexAttribute64Bit`] and [`WgpuFeature::ShaderFloat64`].
+    ///
+    /// [`WgpuFeature::VertexAttribute64Bit`]: crate::WgpuFeature::VertexAttribute64Bit
+    /// [`WgpuFeature::ShaderFloat64`]: crate::WgpuFeature::ShaderFloat64
+    fn validate_vertex_formats(
+        &self,
+        vertex_buffers: &[VertexBufferLayout],
+        pipeline: Option<&str>,
+    ) {
+        let attribute = match vertex_buffers
+            .iter()
+            .flat_map(|buffer| buffer.attributes.iter())
+            .find(|attribute| attribute.format.is_64bit())
+        {
+            Some(attribute) => attribute,
+            None => return,
+        };
+        let features = self.device.features();
+        let missing = [
+            (
+                wgpu::Features::VERTEX_ATTRIBUTE_64BIT,
+                "VertexAttribute64Bit",
+            ),
+            (wgpu::Features::SHADER_FLOAT64, "ShaderFloat64"),
+        ]
+        .iter()
+        .filter(|(feature, _)| !features.contains(*feature))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            self.errors.report(RenderError {
+                kind: RenderErrorKind::Validation,
+                operation: "create_render_pipeline",
+                label: pipeline.map(str::to_string),
+                message: format!(
+                    "vertex attribute {} uses the 64-bit format {:?}, which requires the missing \
+                     WgpuFeatures {}",
+                    attribute.name,
+                    attribute.format,
+                    missing.join(", ")
+                ),
+            });
+        }
+    }
+
+    /// Reports an error for each binding array of `descriptor` that the device doesn't support.
+    /// wgpu only supports arrays of sampled textures, which need
+    /// [`WgpuFeature::SampledTextureBindingArray`].
+    ///
+    /// [`WgpuFeature::SampledTextureBindingArray`]: crate::WgpuFeature::SampledTextureBindingArray
+    fn validate_binding_arrays(&self, descriptor: &BindGroupDescriptor) {
+        let features = self.features();
+        for binding in descriptor
+            .bindings
+            .iter()
+            .filter(|binding| binding.count.is_some())
+        {
+            let message = match binding.bind_type {
+                BindType::Texture { .. } => {
+                    if features.contains(RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY) {
+                        continue;
+                    }
+                    "requires the missing WgpuFeature SampledTextureBindingArray"
+                }
+                _ => "isn't supported, only arrays of sampled textures are",
+            };
+            self.errors.report(RenderError {
+                kind: RenderErrorKind::Validation,
+                operation: "create_bind_group_layout",
+                label: bind_group_layout_label(descriptor).describe(),
+                message: format!("binding array {} {}", binding.name, message),
+            });
+        }
+    }
+
+    /// Reports an error for each [`BindingArrayCapability`] of the `spirv` of a shader that the
+    /// device doesn't support.
+    fn validate_binding_array_capabilities(&self, spirv: &[u32], shader: &Shader) {
+        let features = self.features();
+        for capability in binding_array_capabilities(spirv) {
+            let (feature, name) = match capability {
+                BindingArrayCapability::DynamicIndexing => (
+                    RenderFeatures::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING,
+                    "SampledTextureArrayDynamicIndexing",
+                ),
+                BindingArrayCapability::NonUniformIndexing => (
+                    RenderFeatures::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+                    "SampledTextureArrayNonUniformIndexing",
+                ),
+                BindingArrayCapability::RuntimeArray => {
+                    (RenderFeatures::UNSIZED_BINDING_ARRAY, "UnsizedBindingArray")
+                }
+            };
+            if !features.contains(feature) {
+                self.errors.report(RenderError {
+                    kind: RenderErrorKind::Validation,
+                    operation: "create_shader_module",
+                    label: shader_label(shader).describe(),
+                    message: format!(
+                        "the shader declares the {:?} capability of binding arrays, which requires \
+                         the missing WgpuFeature {}",
+                        capability, name
+                    ),
+                });
+            }
+        }
+    }
+
+    /// Registers a texture created outside of bevy, for example a swap chain image of an XR
+    /// runtime, so it can be used like a texture created with
+    /// [`RenderResourceContext::create_texture`]. `texture_descriptor` must describe `texture`.
+    pub fn register_texture(
+        &self,
+        texture: wgpu::Texture,
+        texture_descriptor: TextureDescriptor,
+    ) -> TextureId {
+        let id = TextureId::new();
+        self.resources
+            .texture_descriptors
+            .write()
+            .insert(id, texture_descriptor);
+        self.resources.textures.write().insert(id, texture);
+        id
+    }
+
+    /// Registers a texture view created outside of bevy, so it can be rendered to, for example
+    /// as the view of an [`OffscreenTarget`](bevy_render2::view::OffscreenTarget). Remove it with
+    /// [`RenderResourceContext::remove_texture_view`] before the texture is destroyed.
+    pub fn register_texture_view(&self, texture_view: wgpu::TextureView) -> TextureViewId {
+        let id = TextureViewId::new();
+        self.resources
+            .texture_views
+            .write()
+            .insert(id, texture_view);
+        id
+    }
+
+    /// Returns the bind group cache activity of the last completed frame.
+    pub fn bind_group_cache_stats(&self) -> BindGroupCacheStats {
+        self.resources.bind_group_counter.stats()
+    }
+
     pub fn set_window_surface(&self, window_id: WindowId, surface: wgpu::Surface) {
         let mut window_surfaces = self.resources.window_surfaces.write();
         window_surfaces.insert(window_id, surface);
     }
 
+    /// Drops the surface of a window and its swap chain, for example when the native surface was
+    /// destroyed.
+    pub fn remove_window_surface(&self, window_id: WindowId) {
+        self.resources.window_swap_chains.write().remove(&window_id);
+        self.resources
+            .window_swap_chain_descriptors
+            .write()
+            .remove(&window_id);
+        self.resources.window_surfaces.write().remove(&window_id);
+    }
+
     pub fn contains_window_surface(&self, window_id: WindowId) -> bool {
         self.resources
             .window_surfaces
@@ -197,6 +515,104 @@ impl WgpuRenderResourceContext {
         );
     }
 
+    /// Fills mip levels `1..` of every layer of a 2d texture by repeatedly downsampling the
+    /// previous level with a linear filter. The texture must have `SAMPLED` and
+    /// `RENDER_ATTACHMENT` usage.
+    pub fn generate_mipmaps(&self, command_encoder: &mut wgpu::CommandEncoder, texture: TextureId) {
+        let texture_descriptors = self.resources.texture_descriptors.read();
+        let textures = self.resources.textures.read();
+        let descriptor = texture_descriptors.get(&texture).unwrap();
+        let texture = textures.get(&texture).unwrap();
+        let format: wgpu::TextureFormat = descriptor.format.wgpu_into();
+
+        let mut mipmap_pipelines = self.resources.mipmap_pipelines.write();
+        let pipeline = mipmap_pipelines.entry(format).or_insert_with(|| {
+            let shader_module = self
+                .device
+                .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                    label: Some("mipmap"),
+                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("mipmap.wgsl"))),
+                    flags: wgpu::ShaderFlags::all(),
+                });
+            self.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("mipmap"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[format.into()],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                })
+        });
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mipmap"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let view = |mip_level: u32, layer: u32| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mipmap"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: mip_level,
+                mip_level_count: NonZeroU32::new(1),
+                base_array_layer: layer,
+                array_layer_count: NonZeroU32::new(1),
+                ..Default::default()
+            })
+        };
+        for layer in 0..descriptor.size.depth_or_array_layers {
+            for mip_level in 1..descriptor.mip_level_count {
+                let source = view(mip_level - 1, layer);
+                let destination = view(mip_level, layer);
+                let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("mipmap"),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&source),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                });
+                let mut render_pass =
+                    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("mipmap"),
+                        color_attachments: &[wgpu::RenderPassColorAttachment {
+                            view: &destination,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
+                        }],
+                        depth_stencil_attachment: None,
+                    });
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+        }
+    }
+
     pub fn create_bind_group_layout(&self, descriptor: &BindGroupDescriptor) {
         if self
             .resources
@@ -239,7 +655,12 @@ impl WgpuRenderResourceContext {
             entries: bind_group_layout_entries.as_slice(),
             label: None,
         };
-        let bind_group_layout = self.device.create_bind_group_layout(&wgpu_descriptor);
+        self.validate_binding_arrays(descriptor);
+        let bind_group_layout = self.error_scope(
+            "create_bind_group_layout",
+            bind_group_layout_label(descriptor),
+            || self.device.create_bind_group_layout(&wgpu_descriptor),
+        );
         bind_group_layouts.insert(descriptor.id, bind_group_layout);
     }
 
@@ -258,14 +679,31 @@ impl WgpuRenderResourceContext {
     }
 }
 
+/// Labels a bind group layout with its index and the names of its bindings.
+fn bind_group_layout_label(descriptor: &BindGroupDescriptor) -> ResourceLabel {
+    let names = descriptor
+        .bindings
+        .iter()
+        .map(|binding| binding.name.as_str())
+        .collect::<Vec<_>>();
+    format!("bind group {} ({})", descriptor.index, names.join(", ")).into()
+}
+
+/// Labels a shader module with its stage.
+fn shader_label(shader: &Shader) -> ResourceLabel {
+    format!("{:?} shader", shader.stage).into()
+}
+
 impl RenderResourceContext for WgpuRenderResourceContext {
     fn create_sampler(&self, sampler_descriptor: &SamplerDescriptor) -> SamplerId {
         let mut samplers = self.resources.samplers.write();
 
         let descriptor: wgpu::SamplerDescriptor = (*sampler_descriptor).wgpu_into();
-        let sampler = self.device.create_sampler(&descriptor);
-
         let id = SamplerId::new();
+        let sampler = self.error_scope("create_sampler", "sampler", || {
+            self.device.create_sampler(&descriptor)
+        });
+
         samplers.insert(id, sampler);
         id
     }
@@ -275,9 +713,13 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         let mut texture_descriptors = self.resources.texture_descriptors.write();
 
         let descriptor: wgpu::TextureDescriptor = (&texture_descriptor).wgpu_into();
-        let texture = self.device.create_texture(&descriptor);
-
         let id = TextureId::new();
+        let texture = self.error_scope(
+            "create_texture",
+            ResourceLabel::Texture(texture_descriptor),
+            || self.device.create_texture(&descriptor),
+        );
+
         texture_descriptors.insert(id, texture_descriptor);
         textures.insert(id, texture);
         id
@@ -291,9 +733,12 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         let mut texture_views = self.resources.texture_views.write();
         let textures = self.resources.textures.read();
         let texture = textures.get(&texture_id).unwrap();
+        let label = self.texture_label(texture_id);
         let descriptor: wgpu::TextureViewDescriptor = texture_view_descriptor.wgpu_into();
-        let texture_view = texture.create_view(&descriptor);
         let id = TextureViewId::new();
+        let texture_view = self.error_scope("create_texture_view", label, || {
+            texture.create_view(&descriptor)
+        });
         texture_views.insert(id, texture_view);
         id
     }
@@ -303,14 +748,18 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         let mut buffer_infos = self.resources.buffer_infos.write();
         let mut buffers = self.resources.buffers.write();
 
-        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: buffer_info.size as u64,
-            usage: buffer_info.buffer_usage.wgpu_into(),
-            mapped_at_creation: buffer_info.mapped_at_creation,
+        let id = BufferId::new();
+        self.validate_buffer_info(&buffer_info, "create_buffer");
+        let label = ResourceLabel::Buffer(buffer_info.clone());
+        let buffer = self.error_scope("create_buffer", label, || {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: buffer_info.size as u64,
+                usage: buffer_info.buffer_usage.wgpu_into(),
+                mapped_at_creation: buffer_info.mapped_at_creation,
+            })
         });
 
-        let id = BufferId::new();
         buffer_infos.insert(id, buffer_info);
         buffers.insert(id, Arc::new(buffer));
         id
@@ -322,15 +771,18 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         let mut buffers = self.resources.buffers.write();
 
         buffer_info.size = data.len();
-        let buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                contents: data,
-                label: None,
-                usage: buffer_info.buffer_usage.wgpu_into(),
-            });
-
         let id = BufferId::new();
+        self.validate_buffer_info(&buffer_info, "create_buffer_with_data");
+        let label = ResourceLabel::Buffer(buffer_info.clone());
+        let buffer = self.error_scope("create_buffer_with_data", label, || {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    contents: data,
+                    label: None,
+                    usage: buffer_info.buffer_usage.wgpu_into(),
+                })
+        });
+
         buffer_infos.insert(id, buffer_info);
         buffers.insert(id, Arc::new(buffer));
         id
@@ -342,6 +794,9 @@ impl RenderResourceContext for WgpuRenderResourceContext {
 
         buffers.remove(&buffer);
         buffer_infos.remove(&buffer);
+        self.pending_maps.lock().remove(buffer);
+        self.resources
+            .remove_resource_bind_groups(RenderResourceId::Buffer(buffer));
     }
 
     fn remove_texture(&self, texture: TextureId) {
@@ -355,29 +810,51 @@ impl RenderResourceContext for WgpuRenderResourceContext {
     fn remove_texture_view(&self, texture_view: TextureViewId) {
         let mut texture_views = self.resources.texture_views.write();
         texture_views.remove(&texture_view);
+        self.resources
+            .remove_resource_bind_groups(RenderResourceId::TextureView(texture_view));
     }
 
     fn remove_sampler(&self, sampler: SamplerId) {
         let mut samplers = self.resources.samplers.write();
         samplers.remove(&sampler);
+        self.resources
+            .remove_resource_bind_groups(RenderResourceId::Sampler(sampler));
     }
 
     fn create_shader_module(&self, shader: &Shader) -> ShaderId {
         let mut shader_modules = self.resources.shader_modules.write();
         let spirv: Cow<[u32]> = shader.get_spirv(None).unwrap().into();
-        let shader_module = self
-            .device
-            .create_shader_module(&wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::SpirV(spirv),
-                flags: Default::default(),
-            });
         let id = ShaderId::new();
+        self.validate_binding_array_capabilities(&spirv, shader);
+        let shader_module = self.error_scope("create_shader_module", shader_label(shader), || {
+            self.device
+                .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::SpirV(spirv),
+                    flags: Default::default(),
+                })
+        });
         shader_modules.insert(id, shader_module);
         id
     }
 
     fn next_swap_chain_texture(&self, descriptor: &SwapChainDescriptor) -> TextureViewId {
+        // recreate the swap chain when its format or usage is changed
+        let outdated = self
+            .resources
+            .window_swap_chain_descriptors
+            .read()
+            .get(&descriptor.window_id)
+            .map_or(false, |current| {
+                current.format != descriptor.format || current.usage != descriptor.usage
+            });
+        if outdated {
+            self.resources
+                .window_swap_chains
+                .write()
+                .remove(&descriptor.window_id);
+        }
+
         if let Some(texture_id) = self.try_next_swap_chain_texture(descriptor.window_id) {
             texture_id
         } else {
@@ -393,6 +870,10 @@ impl RenderResourceContext for WgpuRenderResourceContext {
                     .create_swap_chain(surface, &swap_chain_descriptor);
 
                 window_swap_chains.insert(descriptor.window_id, swap_chain);
+                self.resources
+                    .window_swap_chain_descriptors
+                    .write()
+                    .insert(descriptor.window_id, descriptor.clone());
             }
             self.try_next_swap_chain_texture(descriptor.window_id)
                 .expect("Failed to acquire next swap chain texture!")
@@ -431,6 +912,15 @@ impl RenderResourceContext for WgpuRenderResourceContext {
                 push_constant_ranges: &[],
             });
 
+        let id = PipelineId::new();
+        let name = pipeline_descriptor.name.as_deref();
+        self.validate_vertex_formats(&layout.vertex_buffer_descriptors, name);
+        let (primitive, depth_stencil) = self.validate_rasterization_state(
+            &pipeline_descriptor.primitive,
+            pipeline_descriptor.depth_stencil.as_ref(),
+            name,
+        );
+
         let owned_vertex_buffer_descriptors = layout
             .vertex_buffer_descriptors
             .iter()
@@ -454,7 +944,7 @@ impl RenderResourceContext for WgpuRenderResourceContext {
             .as_ref()
             .map(|fragment_handle| shader_modules.get(fragment_handle).unwrap());
         let render_pipeline_descriptor = wgpu::RenderPipelineDescriptor {
-            label: None,
+            label: name,
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &vertex_shader_module,
@@ -473,19 +963,20 @@ impl RenderResourceContext for WgpuRenderResourceContext {
                     module: fragment_shader_module.as_ref().unwrap(),
                     targets: color_states.as_slice(),
                 }),
-            primitive: pipeline_descriptor.primitive.clone().wgpu_into(),
-            depth_stencil: pipeline_descriptor
-                .depth_stencil
-                .clone()
-                .map(|depth_stencil| depth_stencil.wgpu_into()),
+            primitive: primitive.wgpu_into(),
+            depth_stencil: depth_stencil.map(|depth_stencil| depth_stencil.wgpu_into()),
             multisample: pipeline_descriptor.multisample.clone().wgpu_into(),
         };
 
-        let render_pipeline = self
-            .device
-            .create_render_pipeline(&render_pipeline_descriptor);
+        let render_pipeline = self.error_scope(
+            "create_render_pipeline",
+            ResourceLabel::from_name(name),
+            || {
+                self.device
+                    .create_render_pipeline(&render_pipeline_descriptor)
+            },
+        );
         let mut render_pipelines = self.resources.render_pipelines.write();
-        let id = PipelineId::new();
         render_pipelines.insert(id, render_pipeline);
         id
     }
@@ -520,18 +1011,24 @@ impl RenderResourceContext for WgpuRenderResourceContext {
             .get(&pipeline_descriptor.shader_stages.compute)
             .unwrap();
 
+        let name = pipeline_descriptor.name.as_deref();
         let compute_pipeline_descriptor = wgpu::ComputePipelineDescriptor {
-            label: None,
+            label: name,
             layout: Some(&pipeline_layout),
             entry_point: "main",
             module: compute_shader_module,
         };
 
-        let compute_pipeline = self
-            .device
-            .create_compute_pipeline(&compute_pipeline_descriptor);
-        let mut compute_pipelines = self.resources.compute_pipelines.write();
         let id = PipelineId::new();
+        let compute_pipeline = self.error_scope(
+            "create_compute_pipeline",
+            ResourceLabel::from_name(name),
+            || {
+                self.device
+                    .create_compute_pipeline(&compute_pipeline_descriptor)
+            },
+        );
+        let mut compute_pipelines = self.resources.compute_pipelines.write();
         compute_pipelines.insert(id, compute_pipeline);
         id
     }
@@ -627,7 +1124,9 @@ impl RenderResourceContext for WgpuRenderResourceContext {
                 layout: bind_group_layout,
                 entries: entries.as_slice(),
             };
-            let wgpu_bind_group = self.device.create_bind_group(&wgpu_bind_group_descriptor);
+            let wgpu_bind_group = self.error_scope("create_bind_group", "bind group", || {
+                self.device.create_bind_group(&wgpu_bind_group_descriptor)
+            });
 
             let bind_group_info = bind_groups
                 .entry(bind_group_descriptor_id)
@@ -635,15 +1134,23 @@ impl RenderResourceContext for WgpuRenderResourceContext {
             bind_group_info
                 .bind_groups
                 .insert(bind_group.id, wgpu_bind_group);
+            self.resources
+                .bind_group_counter
+                .bind_group_created(bind_group_descriptor_id, bind_group);
             trace!(
                 "created bind group for RenderResourceSet {:?}",
                 bind_group.id
             );
+        } else {
+            self.resources
+                .bind_group_counter
+                .bind_group_reused(bind_group.id);
         }
     }
 
     fn clear_bind_groups(&self) {
         self.resources.bind_groups.write().clear();
+        self.resources.bind_group_counter.clear();
     }
 
     fn remove_stale_bind_groups(&self) {
@@ -684,6 +1191,53 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         read(&data, self);
     }
 
+    fn write_buffer(&self, id: BufferId, offset: u64, data: &[u8]) {
+        let buffers = self.resources.buffers.read();
+        let buffer = buffers.get(&id).unwrap();
+        let label = self.buffer_label(id);
+        self.error_scope("write_buffer", label, || {
+            self.queue.write_buffer(buffer, offset, data)
+        });
+    }
+
+    fn write_staging_belt(&self, size: u64, write: &mut dyn FnMut(&mut [u8])) -> StagingSlice {
+        self.staging_belt.lock().write(self, size, write)
+    }
+
+    fn write_texture(
+        &self,
+        id: TextureId,
+        origin: [u32; 3],
+        mip_level: u32,
+        data: &[u8],
+        bytes_per_row: u32,
+        size: Extent3d,
+    ) {
+        let textures = self.resources.textures.read();
+        let texture = textures.get(&id).unwrap();
+        let label = self.texture_label(id);
+        self.error_scope("write_texture", label, || {
+            self.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level,
+                    origin: wgpu::Origin3d {
+                        x: origin[0],
+                        y: origin[1],
+                        z: origin[2],
+                    },
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row),
+                    rows_per_image: NonZeroU32::new(size.height),
+                },
+                size.wgpu_into(),
+            )
+        });
+    }
+
     fn map_buffer(&self, id: BufferId, mode: BufferMapMode) {
         let buffers = self.resources.buffers.read();
         let buffer = buffers.get(&id).unwrap();
@@ -699,6 +1253,22 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         }
     }
 
+    fn map_buffer_async(&self, id: BufferId, mode: BufferMapMode) {
+        let buffers = self.resources.buffers.read();
+        let buffer = buffers.get(&id).unwrap();
+        let wgpu_mode = match mode {
+            BufferMapMode::Read => wgpu::MapMode::Read,
+            BufferMapMode::Write => wgpu::MapMode::Write,
+        };
+        let future = buffer.slice(..).map_async(wgpu_mode);
+        self.pending_maps.lock().insert(id, future);
+    }
+
+    fn is_buffer_mapped(&self, id: BufferId) -> bool {
+        self.device.poll(wgpu::Maintain::Poll);
+        self.pending_maps.lock().poll(id)
+    }
+
     fn unmap_buffer(&self, id: BufferId) {
         let buffers = self.resources.buffers.read();
         let buffer = buffers.get(&id).unwrap();
@@ -709,6 +1279,40 @@ impl RenderResourceContext for WgpuRenderResourceContext {
         (size + COPY_BYTES_PER_ROW_ALIGNMENT - 1) & !(COPY_BYTES_PER_ROW_ALIGNMENT - 1)
     }
 
+    fn features(&self) -> RenderFeatures {
+        let device_features = self.device.features();
+        let mut features = RenderFeatures::empty();
+        features.set(
+            RenderFeatures::DEPTH_CLAMPING,
+            device_features.contains(wgpu::Features::DEPTH_CLAMPING),
+        );
+        features.set(
+            RenderFeatures::CONSERVATIVE_RASTERIZATION,
+            device_features.contains(wgpu::Features::CONSERVATIVE_RASTERIZATION),
+        );
+        features.set(
+            RenderFeatures::SAMPLED_TEXTURE_BINDING_ARRAY,
+            device_features.contains(wgpu::Features::SAMPLED_TEXTURE_BINDING_ARRAY),
+        );
+        features.set(
+            RenderFeatures::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING,
+            device_features.contains(wgpu::Features::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING),
+        );
+        features.set(
+            RenderFeatures::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+            device_features.contains(wgpu::Features::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING),
+        );
+        features.set(
+            RenderFeatures::UNSIZED_BINDING_ARRAY,
+            device_features.contains(wgpu::Features::UNSIZED_BINDING_ARRAY),
+        );
+        features.set(
+            RenderFeatures::NON_FILL_POLYGON_MODE,
+            device_features.contains(wgpu::Features::NON_FILL_POLYGON_MODE),
+        );
+        features
+    }
+
     fn get_aligned_uniform_size(&self, size: usize, dynamic: bool) -> usize {
         if dynamic {
             (size + BIND_BUFFER_ALIGNMENT - 1) & !(BIND_BUFFER_ALIGNMENT - 1)
@@ -716,4 +1320,12 @@ impl RenderResourceContext for WgpuRenderResourceContext {
             size
         }
     }
+
+    fn get_max_uniform_buffer_binding_size(&self) -> usize {
+        self.device.limits().max_uniform_buffer_binding_size as usize
+    }
+
+    fn get_max_sampled_textures_per_shader_stage(&self) -> u32 {
+        self.device.limits().max_sampled_textures_per_shader_stage
+    }
 }
@@ -1,9 +1,18 @@
 use crate::{
-    type_converter::WgpuInto, WgpuBackend, WgpuOptions, WgpuPowerOptions, WgpuRenderGraphRunner,
+    frame_latency::FrameLatencyLimiter, gpu_timer::GpuFrameTimer, type_converter::WgpuInto,
+    WgpuBackend, WgpuOptions, WgpuPowerOptions, WgpuRenderContext, WgpuRenderGraphRunner,
     WgpuRenderResourceContext,
 };
 use bevy_ecs::{prelude::Mut, world::World};
-use bevy_render2::{render_graph::RenderGraph, renderer::RenderResources, view::ExtractedWindows};
+use bevy_render2::{
+    diagnostic::RenderTimings,
+    frame_trace::{FrameTrace, FrameTraces},
+    render_command::{AssetUploadQueue, RenderCommandQueue},
+    render_graph::RenderGraph,
+    render_resource::{CompositeAlphaMode, PresentMode, SurfaceInfo, SwapChainDescriptor},
+    renderer::RenderResources,
+    view::{ExtractedWindows, SurfaceInfos},
+};
 use std::sync::Arc;
 
 pub struct WgpuRenderer {
@@ -11,10 +20,55 @@ pub struct WgpuRenderer {
     pub device: Arc<wgpu::Device>,
     pub queue: Arc<wgpu::Queue>,
     pub initialized: bool,
+    /// When `false`, windows get no surface and are rendered to offscreen textures instead, see
+    /// [`WgpuExternalDevice::create_window_surfaces`].
+    pub create_window_surfaces: bool,
+    adapter: Option<wgpu::Adapter>,
+    frame_latency: Option<FrameLatencyLimiter>,
+    gpu_timer: Option<GpuFrameTimer>,
 }
 
-impl WgpuRenderer {
-    pub async fn new(options: WgpuOptions) -> Self {
+/// An existing wgpu instance, device and queue for [`WgpuPlugin`](crate::WgpuPlugin) to render
+/// with, for example when they are shared with another library or an editor that embeds the app.
+/// Insert it as a resource before adding the plugin. The device is used as is, so the features
+/// and limits of [`WgpuOptions`] are ignored.
+pub struct WgpuExternalDevice {
+    pub instance: wgpu::Instance,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    /// Whether to create surfaces for the app's windows. Disable it when the host presents on its
+    /// own. Windows are then rendered to offscreen textures of their size, which the host can
+    /// read from [`OffscreenTextures`](bevy_render2::view::OffscreenTextures).
+    pub create_window_surfaces: bool,
+    /// The adapter of the device, if known. It reports the preferred format of window surfaces.
+    pub adapter: Option<wgpu::Adapter>,
+}
+
+impl WgpuExternalDevice {
+    /// Requests an adapter and a device with the backend, features and limits of `options`, as
+    /// [`WgpuPlugin`](crate::WgpuPlugin) does when no device is inserted. On the web, where the
+    /// requests only complete once the browser's event loop runs, the plugin spawns them and
+    /// skips the frames until the device is ready. Await this before building the app to start
+    /// rendering with the first frame instead:
+    ///
+    /// ```ignore
+    /// wasm_bindgen_futures::spawn_local(async {
+    ///     let device = WgpuExternalDevice::request(&WgpuOptions::default()).await;
+    ///     App::new()
+    ///         .insert_resource(device)
+    ///         .add_plugins(PipelinedDefaultPlugins)
+    ///         .run();
+    /// });
+    /// ```
+    pub async fn request(options: &WgpuOptions) -> Self {
+        Self::try_request(options)
+            .await
+            .expect("Unable to find a GPU! Make sure you have installed required drivers!")
+    }
+
+    /// Like [`WgpuExternalDevice::request`], but returns `None` instead of panicking when there is
+    /// no suitable adapter, e.g. to skip rendering tests on machines without a GPU.
+    pub async fn try_request(options: &WgpuOptions) -> Option<Self> {
         let backend = match options.backend {
             WgpuBackend::Auto => wgpu::BackendBit::PRIMARY,
             WgpuBackend::Vulkan => wgpu::BackendBit::VULKAN,
@@ -35,8 +89,7 @@ impl WgpuRenderer {
                 },
                 compatible_surface: None,
             })
-            .await
-            .expect("Unable to find a GPU! Make sure you have installed required drivers!");
+            .await?;
 
         #[cfg(feature = "trace")]
         let trace_path = {
@@ -52,20 +105,45 @@ impl WgpuRenderer {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: options.device_label.as_ref().map(|a| a.as_ref()),
-                    features: options.features.wgpu_into(),
-                    limits: options.limits.wgpu_into(),
+                    features: options.features.clone().wgpu_into(),
+                    limits: options.limits.clone().wgpu_into(),
                 },
                 trace_path,
             )
             .await
-            .unwrap();
-        let device = Arc::new(device);
-        let queue = Arc::new(queue);
-        WgpuRenderer {
+            .ok()?;
+        Some(WgpuExternalDevice {
             instance,
-            device,
-            queue,
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+            create_window_surfaces: true,
+            adapter: Some(adapter),
+        })
+    }
+}
+
+impl WgpuRenderer {
+    pub async fn new(options: WgpuOptions) -> Self {
+        Self::from_device(WgpuExternalDevice::request(&options).await, &options)
+    }
+
+    /// Creates a renderer that uses an existing device, see [`WgpuExternalDevice`].
+    pub fn from_device(external_device: WgpuExternalDevice, options: &WgpuOptions) -> Self {
+        // frames are timed when the device was created with timestamp queries
+        let gpu_timer = GpuFrameTimer::new(&external_device.device, &external_device.queue);
+        WgpuRenderer {
+            instance: external_device.instance,
+            device: external_device.device,
+            queue: external_device.queue,
             initialized: false,
+            create_window_surfaces: external_device.create_window_surfaces,
+            adapter: external_device.adapter,
+            // there is no way to block until the GPU catches up on the web
+            frame_latency: options
+                .max_frame_latency
+                .filter(|_| !cfg!(target_arch = "wasm32"))
+                .map(FrameLatencyLimiter::new),
+            gpu_timer,
         }
     }
 
@@ -75,15 +153,66 @@ impl WgpuRenderer {
         let render_resource_context = render_resources
             .downcast_mut::<WgpuRenderResourceContext>()
             .unwrap();
-        let extracted_windows = world.get_resource::<ExtractedWindows>().unwrap();
+        let mut extracted_windows = world.get_resource_mut::<ExtractedWindows>().unwrap();
+        let surface_infos = world.get_resource::<SurfaceInfos>().unwrap();
+        for id in extracted_windows.destroyed_surfaces.iter() {
+            render_resource_context.remove_window_surface(*id);
+            surface_infos.remove(*id);
+        }
+        if !self.create_window_surfaces {
+            // without a surface, windows are prepared like offscreen targets
+            for window in extracted_windows.values_mut() {
+                window.handle = None;
+                window.has_surface = false;
+            }
+            return;
+        }
         for (id, window) in extracted_windows.iter() {
-            if !render_resource_context.contains_window_surface(*id) {
-                let surface = unsafe { self.instance.create_surface(&window.handle.get_handle()) };
-                render_resource_context.set_window_surface(*id, surface);
+            // offscreen targets don't have a surface
+            if let Some(handle) = &window.handle {
+                if !render_resource_context.contains_window_surface(*id) {
+                    let surface = unsafe { self.instance.create_surface(&handle.get_handle()) };
+                    render_resource_context.set_window_surface(*id, surface);
+                }
+            }
+            // surfaces set directly on the context get their info here too
+            if !surface_infos.contains(*id) {
+                let window_surfaces = render_resource_context.resources.window_surfaces.read();
+                let surface_info = window_surfaces
+                    .get(id)
+                    .and_then(|surface| self.surface_info(surface));
+                if let Some(surface_info) = surface_info {
+                    surface_infos.set(*id, surface_info);
+                }
             }
         }
     }
 
+    /// Returns what `surface` supports, or `None` without the adapter of an external device, which
+    /// is needed to query it. wgpu 0.8 only reports the format a surface prefers, so that is the
+    /// only format reported, or `None` if it isn't one of the
+    /// [`SwapChainDescriptor::SUPPORTED_FORMATS`]. Of the present modes, only [`PresentMode::Fifo`] is guaranteed to be
+    /// supported: wgpu doesn't report whether the others are, so they are left out even though
+    /// surfaces may support them. wgpu surfaces are always opaque.
+    fn surface_info(&self, surface: &wgpu::Surface) -> Option<SurfaceInfo> {
+        let preferred = self
+            .adapter
+            .as_ref()?
+            .get_swap_chain_preferred_format(surface);
+        let preferred_format = SwapChainDescriptor::SUPPORTED_FORMATS
+            .iter()
+            .copied()
+            .find(|format| {
+                let format: wgpu::TextureFormat = (*format).wgpu_into();
+                format == preferred
+            })?;
+        Some(SurfaceInfo {
+            formats: vec![preferred_format],
+            present_modes: vec![PresentMode::Fifo],
+            alpha_modes: vec![CompositeAlphaMode::Opaque],
+        })
+    }
+
     pub fn run_graph(&mut self, world: &mut World) {
         world.resource_scope(|world, mut graph: Mut<RenderGraph>| {
             graph.update(world);
@@ -93,20 +222,91 @@ impl WgpuRenderer {
         let resource_context = render_resources
             .downcast_ref::<WgpuRenderResourceContext>()
             .unwrap();
+        let mut timings = RenderTimings::default();
+        resource_context.finish_staging_belt();
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            resource_context.queue_command_buffer(gpu_timer.begin_frame(&self.device));
+        }
+        let frame_traces = world
+            .get_resource::<FrameTraces>()
+            .filter(|frame_traces| frame_traces.is_requested());
+        let mut trace = frame_traces.map(|_| FrameTrace::default());
         WgpuRenderGraphRunner::run(
             graph,
             self.device.clone(),
-            &*self.queue,
             world,
             resource_context,
+            &mut timings,
+            trace.as_mut(),
         )
         .unwrap();
+        if let (Some(frame_traces), Some(trace)) = (frame_traces, trace) {
+            frame_traces.finish(trace);
+        }
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            if let Some(command_buffer) = gpu_timer.end_frame(&self.device) {
+                resource_context.queue_command_buffer(command_buffer);
+            }
+        }
+        if let Some(frame_latency) = self.frame_latency.as_mut() {
+            frame_latency.begin_frame(&self.device, &self.queue);
+            if !resource_context.submit() {
+                // flush the fence write, which is otherwise only submitted with the next frame
+                self.queue.submit(std::iter::empty());
+            }
+        } else {
+            resource_context.submit();
+        }
+        resource_context.recall_staging_belt();
+        let gpu_frame = self
+            .gpu_timer
+            .as_mut()
+            .and_then(|gpu_timer| gpu_timer.read(&self.device));
+        if let Some(mut render_timings) = world.get_resource_mut::<RenderTimings>() {
+            render_timings.nodes = timings.nodes;
+            render_timings.gpu_frame = gpu_frame;
+        }
     }
 
     pub fn update(&mut self, world: &mut World) {
         self.run_graph(world);
         let render_resources = world.get_resource::<RenderResources>().unwrap();
         render_resources.drop_all_swap_chain_textures();
+        // wait after presenting, so the frame isn't delayed
+        if let Some(frame_latency) = self.frame_latency.as_mut() {
+            frame_latency.end_frame(&self.device);
+        }
+        render_resources.remove_stale_bind_groups();
+        render_resources
+            .downcast_ref::<WgpuRenderResourceContext>()
+            .unwrap()
+            .end_submission_frame();
+    }
+
+    /// Submits the asset uploads and render commands of a frame without running the render
+    /// graph, while rendering is paused by [`RenderActive`](bevy_render2::RenderActive). Nothing
+    /// is presented, so no swap chain texture is acquired.
+    pub fn update_inactive(&mut self, world: &mut World) {
+        let render_resources = world.get_resource::<RenderResources>().unwrap();
+        let resource_context = render_resources
+            .downcast_ref::<WgpuRenderResourceContext>()
+            .unwrap();
+        resource_context.finish_staging_belt();
+        let mut render_context =
+            WgpuRenderContext::new(self.device.clone(), resource_context.clone());
+        if let Some(uploads) = world.get_resource::<AssetUploadQueue>() {
+            uploads.execute(&mut render_context);
+        }
+        world
+            .get_resource::<RenderCommandQueue>()
+            .unwrap()
+            .execute(&mut render_context);
+        if let Some(command_buffer) = render_context.finish() {
+            resource_context.queue_command_buffer(command_buffer);
+        }
+        resource_context.submit();
+        resource_context.recall_staging_belt();
         render_resources.remove_stale_bind_groups();
+        resource_context.end_submission_frame();
     }
 }
@@ -1,18 +1,31 @@
 use bevy_render2::{
     pipeline::{BindGroupDescriptorId, PipelineId},
-    render_resource::{BindGroupId, BufferId, BufferInfo, SamplerId, TextureId, TextureViewId},
+    render_resource::{
+        BindGroup, BindGroupId, BufferId, BufferInfo, RenderResourceBinding, RenderResourceId,
+        SamplerId, SwapChainDescriptor, TextureId, TextureViewId,
+    },
     shader::ShaderId,
     texture::TextureDescriptor,
 };
-use bevy_utils::HashMap;
+use bevy_utils::{HashMap, HashSet};
 use bevy_window::WindowId;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use parking_lot::{RwLock, RwLockReadGuard};
 use std::sync::Arc;
 
-#[derive(Debug, Default)]
-pub struct WgpuBindGroupInfo {
-    pub bind_groups: HashMap<BindGroupId, wgpu::BindGroup>,
+/// The cached bind groups of a layout. The [`BindGroupCounter`] only manages the ids, so it can be
+/// tested without a device.
+#[derive(Debug)]
+pub struct WgpuBindGroupInfo<B = wgpu::BindGroup> {
+    pub bind_groups: HashMap<BindGroupId, B>,
+}
+
+impl<B> Default for WgpuBindGroupInfo<B> {
+    fn default() -> Self {
+        WgpuBindGroupInfo {
+            bind_groups: Default::default(),
+        }
+    }
 }
 
 /// Grabs a read lock on all wgpu resources. When paired with WgpuResourceRefs, this allows
@@ -86,6 +99,7 @@ pub struct WgpuResources {
     pub texture_descriptors: Arc<RwLock<HashMap<TextureId, TextureDescriptor>>>,
     pub window_surfaces: Arc<RwLock<HashMap<WindowId, wgpu::Surface>>>,
     pub window_swap_chains: Arc<RwLock<HashMap<WindowId, wgpu::SwapChain>>>,
+    pub window_swap_chain_descriptors: Arc<RwLock<HashMap<WindowId, SwapChainDescriptor>>>,
     pub swap_chain_frames: Arc<RwLock<HashMap<TextureViewId, wgpu::SwapChainFrame>>>,
     pub buffers: Arc<RwLock<HashMap<BufferId, Arc<wgpu::Buffer>>>>,
     pub texture_views: Arc<RwLock<HashMap<TextureViewId, wgpu::TextureView>>>,
@@ -96,6 +110,7 @@ pub struct WgpuResources {
     pub compute_pipelines: Arc<RwLock<HashMap<PipelineId, wgpu::ComputePipeline>>>,
     pub bind_groups: Arc<RwLock<HashMap<BindGroupDescriptorId, WgpuBindGroupInfo>>>,
     pub bind_group_layouts: Arc<RwLock<HashMap<BindGroupDescriptorId, wgpu::BindGroupLayout>>>,
+    pub mipmap_pipelines: Arc<RwLock<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>>,
     pub bind_group_counter: BindGroupCounter,
 }
 
@@ -129,48 +144,225 @@ impl WgpuResources {
         self.bind_group_counter
             .remove_stale_bind_groups(&mut bind_groups);
     }
+
+    pub fn remove_resource_bind_groups(&self, resource: RenderResourceId) {
+        let mut bind_groups = self.bind_groups.write();
+        self.bind_group_counter
+            .remove_resource_bind_groups(&mut bind_groups, resource);
+    }
+}
+
+/// Controls how long bind groups are cached after their last use.
+#[derive(Clone, Copy, Debug)]
+pub struct BindGroupCacheLimits {
+    /// Bind groups that haven't been used for this many frames are removed.
+    pub max_unused_frames: u64,
+    /// When more bind groups than this are cached, the least recently used ones are removed.
+    pub max_bind_groups: usize,
+}
+
+impl Default for BindGroupCacheLimits {
+    fn default() -> Self {
+        BindGroupCacheLimits {
+            max_unused_frames: 60,
+            max_bind_groups: 4096,
+        }
+    }
+}
+
+/// Bind group cache activity during a single frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BindGroupCacheStats {
+    /// Bind groups created because no bind group with the same contents was cached.
+    pub created: usize,
+    /// Bind group creations that were skipped because a bind group with the same contents was
+    /// cached.
+    pub reused: usize,
+    /// Bind groups removed because they went unused for too long or the cache was full.
+    pub evicted: usize,
+    /// Bind groups removed because a buffer, texture view or sampler they bind was removed.
+    pub reaped: usize,
+    /// Bind groups cached at the end of the frame.
+    pub live: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct BindGroupUsage {
+    pub limits: BindGroupCacheLimits,
+    frame: u64,
+    last_used: HashMap<BindGroupId, u64>,
+    resource_bind_groups: HashMap<RenderResourceId, HashSet<(BindGroupDescriptorId, BindGroupId)>>,
+    bind_group_resources: HashMap<(BindGroupDescriptorId, BindGroupId), Vec<RenderResourceId>>,
+    frame_stats: BindGroupCacheStats,
+    last_frame_stats: BindGroupCacheStats,
+}
+
+impl BindGroupUsage {
+    fn remove<B>(
+        &mut self,
+        bind_groups: &mut HashMap<BindGroupDescriptorId, WgpuBindGroupInfo<B>>,
+        key: (BindGroupDescriptorId, BindGroupId),
+    ) -> bool {
+        let (bind_group_descriptor_id, bind_group_id) = key;
+        let removed = bind_groups
+            .get_mut(&bind_group_descriptor_id)
+            .and_then(|info| info.bind_groups.remove(&bind_group_id))
+            .is_some();
+        for resource in self.bind_group_resources.remove(&key).unwrap_or_default() {
+            if let Some(users) = self.resource_bind_groups.get_mut(&resource) {
+                users.remove(&key);
+                if users.is_empty() {
+                    self.resource_bind_groups.remove(&resource);
+                }
+            }
+        }
+        removed
+    }
 }
 
+/// Tracks when bind groups were last used, so that bind groups with the same contents are reused
+/// across frames and unused ones are eventually freed.
 #[derive(Clone, Debug)]
 pub struct BindGroupCounter {
     pub used_bind_group_sender: Sender<BindGroupId>,
     pub used_bind_group_receiver: Receiver<BindGroupId>,
-    pub bind_group_usage_counts: Arc<RwLock<HashMap<BindGroupId, u64>>>,
+    pub usage: Arc<RwLock<BindGroupUsage>>,
 }
 
 impl BindGroupCounter {
-    pub fn remove_stale_bind_groups(
+    pub fn set_limits(&self, limits: BindGroupCacheLimits) {
+        self.usage.write().limits = limits;
+    }
+
+    /// Returns the cache activity of the last completed frame.
+    pub fn stats(&self) -> BindGroupCacheStats {
+        self.usage.read().last_frame_stats
+    }
+
+    pub fn bind_group_created(
+        &self,
+        bind_group_descriptor_id: BindGroupDescriptorId,
+        bind_group: &BindGroup,
+    ) {
+        let mut usage = self.usage.write();
+        let usage = &mut *usage;
+        let key = (bind_group_descriptor_id, bind_group.id);
+        let mut resources = Vec::new();
+        for indexed_binding in bind_group.indexed_bindings.iter() {
+            match &indexed_binding.entry {
+                RenderResourceBinding::Buffer { buffer, .. } => {
+                    resources.push(RenderResourceId::Buffer(*buffer))
+                }
+                RenderResourceBinding::TextureView(texture_view) => {
+                    resources.push(RenderResourceId::TextureView(*texture_view))
+                }
+                RenderResourceBinding::TextureArrayView(texture_views) => resources.extend(
+                    texture_views
+                        .iter()
+                        .map(|texture_view| RenderResourceId::TextureView(*texture_view)),
+                ),
+                RenderResourceBinding::Sampler(sampler) => {
+                    resources.push(RenderResourceId::Sampler(*sampler))
+                }
+            }
+        }
+        for resource in resources.iter() {
+            usage
+                .resource_bind_groups
+                .entry(resource.clone())
+                .or_insert_with(HashSet::default)
+                .insert(key);
+        }
+        usage.bind_group_resources.insert(key, resources);
+        usage.last_used.insert(bind_group.id, usage.frame);
+        usage.frame_stats.created += 1;
+    }
+
+    pub fn bind_group_reused(&self, bind_group_id: BindGroupId) {
+        let mut usage = self.usage.write();
+        let frame = usage.frame;
+        usage.last_used.insert(bind_group_id, frame);
+        usage.frame_stats.reused += 1;
+    }
+
+    /// Removes every bind group that binds `resource`.
+    pub fn remove_resource_bind_groups<B>(
+        &self,
+        bind_groups: &mut HashMap<BindGroupDescriptorId, WgpuBindGroupInfo<B>>,
+        resource: RenderResourceId,
+    ) {
+        let mut usage = self.usage.write();
+        let usage = &mut *usage;
+        let keys = match usage.resource_bind_groups.get(&resource) {
+            Some(keys) => keys.iter().cloned().collect::<Vec<_>>(),
+            None => return,
+        };
+        for key in keys {
+            if usage.remove(bind_groups, key) {
+                usage.frame_stats.reaped += 1;
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut usage = self.usage.write();
+        usage.last_used.clear();
+        usage.resource_bind_groups.clear();
+        usage.bind_group_resources.clear();
+    }
+
+    pub fn remove_stale_bind_groups<B>(
         &self,
-        bind_groups: &mut HashMap<BindGroupDescriptorId, WgpuBindGroupInfo>,
+        bind_groups: &mut HashMap<BindGroupDescriptorId, WgpuBindGroupInfo<B>>,
     ) {
-        let mut bind_group_usage_counts = self.bind_group_usage_counts.write();
+        let mut usage = self.usage.write();
+        let usage = &mut *usage;
+        let frame = usage.frame;
         loop {
             let bind_group = match self.used_bind_group_receiver.try_recv() {
                 Ok(bind_group) => bind_group,
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => panic!("used bind group channel disconnected"),
             };
-
-            let count = bind_group_usage_counts.entry(bind_group).or_insert(0);
-            // free every two frames
-            *count = 2;
+            usage.last_used.insert(bind_group, frame);
         }
 
-        for info in bind_groups.values_mut() {
-            info.bind_groups.retain(|id, _| {
-                let retain = {
-                    // if a value hasn't been counted yet, give it two frames of leeway
-                    let count = bind_group_usage_counts.entry(*id).or_insert(2);
-                    *count -= 1;
-                    *count > 0
-                };
-                if !retain {
-                    bind_group_usage_counts.remove(&id);
-                }
+        let mut cached = Vec::new();
+        for (bind_group_descriptor_id, info) in bind_groups.iter() {
+            for bind_group_id in info.bind_groups.keys() {
+                // bind groups that weren't tracked yet count as used this frame
+                let last_used = *usage.last_used.entry(*bind_group_id).or_insert(frame);
+                cached.push((last_used, (*bind_group_descriptor_id, *bind_group_id)));
+            }
+        }
 
-                retain
-            })
+        // least recently used first
+        cached.sort_by_key(|(last_used, _)| *last_used);
+        let limits = usage.limits;
+        let over_capacity = cached.len().saturating_sub(limits.max_bind_groups);
+        let mut live = cached.len();
+        for (index, (last_used, key)) in cached.into_iter().enumerate() {
+            let unused_frames = frame - last_used;
+            if index >= over_capacity && unused_frames < limits.max_unused_frames {
+                break;
+            }
+            if usage.remove(bind_groups, key) {
+                usage.frame_stats.evicted += 1;
+                live -= 1;
+            }
         }
+
+        let live_bind_groups = bind_groups
+            .values()
+            .flat_map(|info| info.bind_groups.keys())
+            .collect::<HashSet<_>>();
+        usage
+            .last_used
+            .retain(|bind_group_id, _| live_bind_groups.contains(bind_group_id));
+
+        usage.frame_stats.live = live;
+        usage.last_frame_stats = std::mem::take(&mut usage.frame_stats);
+        usage.frame += 1;
     }
 }
 
@@ -180,7 +372,131 @@ impl Default for BindGroupCounter {
         BindGroupCounter {
             used_bind_group_sender: send,
             used_bind_group_receiver: recv,
-            bind_group_usage_counts: Default::default(),
+            usage: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindGroupCacheLimits, BindGroupCounter, WgpuBindGroupInfo};
+    use bevy_render2::{
+        pipeline::{BindGroupDescriptor, BindGroupDescriptorId},
+        render_resource::{BindGroup, BufferId, RenderResourceId, SamplerId, TextureViewId},
+    };
+    use bevy_utils::HashMap;
+
+    type BindGroups = HashMap<BindGroupDescriptorId, WgpuBindGroupInfo<()>>;
+
+    fn counter(max_unused_frames: u64, max_bind_groups: usize) -> BindGroupCounter {
+        let counter = BindGroupCounter::default();
+        counter.set_limits(BindGroupCacheLimits {
+            max_unused_frames,
+            max_bind_groups,
+        });
+        counter
+    }
+
+    fn create(counter: &BindGroupCounter, bind_groups: &mut BindGroups, bind_group: &BindGroup) {
+        let descriptor = BindGroupDescriptor::new(0, Vec::new()).id;
+        bind_groups
+            .entry(descriptor)
+            .or_insert_with(WgpuBindGroupInfo::default)
+            .bind_groups
+            .insert(bind_group.id, ());
+        counter.bind_group_created(descriptor, bind_group);
+    }
+
+    fn sampler_bind_group() -> BindGroup {
+        BindGroup::build().add_sampler(0, SamplerId::new()).finish()
+    }
+
+    fn is_cached(bind_groups: &BindGroups, bind_group: &BindGroup) -> bool {
+        bind_groups
+            .values()
+            .any(|info| info.bind_groups.contains_key(&bind_group.id))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let counter = counter(100, 2);
+        let mut bind_groups = BindGroups::default();
+        let (a, b, c) = (
+            sampler_bind_group(),
+            sampler_bind_group(),
+            sampler_bind_group(),
+        );
+        create(&counter, &mut bind_groups, &a);
+        counter.remove_stale_bind_groups(&mut bind_groups);
+        create(&counter, &mut bind_groups, &b);
+        counter.remove_stale_bind_groups(&mut bind_groups);
+        create(&counter, &mut bind_groups, &c);
+        counter.bind_group_reused(a.id);
+        counter.remove_stale_bind_groups(&mut bind_groups);
+
+        assert!(is_cached(&bind_groups, &a));
+        assert!(!is_cached(&bind_groups, &b));
+        assert!(is_cached(&bind_groups, &c));
+        let stats = counter.stats();
+        assert_eq!((stats.created, stats.reused), (1, 1));
+        assert_eq!((stats.evicted, stats.live), (1, 2));
+    }
+
+    #[test]
+    fn evicts_after_max_unused_frames() {
+        let counter = counter(2, 100);
+        let mut bind_groups = BindGroups::default();
+        let (unused, used) = (sampler_bind_group(), sampler_bind_group());
+        create(&counter, &mut bind_groups, &unused);
+        create(&counter, &mut bind_groups, &used);
+        for _ in 0..2 {
+            counter.remove_stale_bind_groups(&mut bind_groups);
+            assert!(is_cached(&bind_groups, &unused));
+            // passes report the bind groups they set through the channel
+            counter.used_bind_group_sender.send(used.id).unwrap();
         }
+        counter.remove_stale_bind_groups(&mut bind_groups);
+
+        assert!(!is_cached(&bind_groups, &unused));
+        assert!(is_cached(&bind_groups, &used));
+        assert_eq!(counter.stats().evicted, 1);
+    }
+
+    #[test]
+    fn reaps_bind_groups_of_removed_resources() {
+        let counter = counter(100, 100);
+        let mut bind_groups = BindGroups::default();
+        let (buffer, texture_view, sampler) =
+            (BufferId::new(), TextureViewId::new(), SamplerId::new());
+        let buffer_bind_group = BindGroup::build()
+            .add_buffer(0, buffer, 0..16)
+            .add_sampler(1, sampler)
+            .finish();
+        let texture_bind_group = BindGroup::build()
+            .add_texture_view(0, texture_view)
+            .add_sampler(1, sampler)
+            .finish();
+        create(&counter, &mut bind_groups, &buffer_bind_group);
+        create(&counter, &mut bind_groups, &texture_bind_group);
+
+        counter.remove_resource_bind_groups(&mut bind_groups, RenderResourceId::Buffer(buffer));
+        assert!(!is_cached(&bind_groups, &buffer_bind_group));
+        assert!(is_cached(&bind_groups, &texture_bind_group));
+        counter.remove_resource_bind_groups(
+            &mut bind_groups,
+            RenderResourceId::TextureView(texture_view),
+        );
+        assert!(!is_cached(&bind_groups, &texture_bind_group));
+
+        // the sampler's bind groups were already reaped
+        counter.remove_resource_bind_groups(&mut bind_groups, RenderResourceId::Sampler(sampler));
+        let sampler_bind_group = BindGroup::build().add_sampler(0, sampler).finish();
+        create(&counter, &mut bind_groups, &sampler_bind_group);
+        counter.remove_resource_bind_groups(&mut bind_groups, RenderResourceId::Sampler(sampler));
+        assert!(!is_cached(&bind_groups, &sampler_bind_group));
+
+        counter.remove_stale_bind_groups(&mut bind_groups);
+        let stats = counter.stats();
+        assert_eq!((stats.reaped, stats.evicted, stats.live), (3, 0, 0));
     }
 }
@@ -0,0 +1,232 @@
+use crate::WgpuRenderResourceContext;
+use bevy_render2::{
+    render_resource::{BufferId, BufferInfo, BufferUsage, StagingSlice},
+    renderer::RenderResourceContext,
+};
+use futures_lite::future;
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+/// Chunks are at least this large, so that most frames fit into a few of them.
+const MIN_CHUNK_SIZE: u64 = 1 << 20;
+
+/// Mapped ranges have to start at a multiple of this.
+const MAP_ALIGNMENT: u64 = 8;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+struct Chunk {
+    id: BufferId,
+    buffer: Arc<wgpu::Buffer>,
+    size: u64,
+    offset: u64,
+}
+
+/// Hands out slices of staging buffers that stay mapped while they are written to.
+///
+/// Chunks are written to during the frame, unmapped by [`StagingBelt::finish`] before the render
+/// graph is submitted and mapped again by [`StagingBelt::recall`] after it. Once the mapping
+/// completes, which happens after the GPU is done copying from them, they are reused.
+#[derive(Default)]
+pub(crate) struct StagingBelt {
+    active_chunks: Vec<Chunk>,
+    closed_chunks: Vec<Chunk>,
+    recalled_chunks: Vec<(Chunk, MapFuture)>,
+    free_chunks: Vec<Chunk>,
+}
+
+impl fmt::Debug for StagingBelt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StagingBelt")
+            .field("active_chunks", &self.active_chunks.len())
+            .field("closed_chunks", &self.closed_chunks.len())
+            .field("recalled_chunks", &self.recalled_chunks.len())
+            .field("free_chunks", &self.free_chunks.len())
+            .finish()
+    }
+}
+
+fn align(size: u64, alignment: u64) -> u64 {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+impl StagingBelt {
+    pub fn write(
+        &mut self,
+        context: &WgpuRenderResourceContext,
+        size: u64,
+        write: &mut dyn FnMut(&mut [u8]),
+    ) -> StagingSlice {
+        let index = match self
+            .active_chunks
+            .iter()
+            .position(|chunk| chunk.offset + size <= chunk.size)
+        {
+            Some(index) => index,
+            None => {
+                let chunk = match self.free_chunks.iter().position(|chunk| size <= chunk.size) {
+                    Some(index) => self.free_chunks.swap_remove(index),
+                    None => Self::create_chunk(context, size),
+                };
+                self.active_chunks.push(chunk);
+                self.active_chunks.len() - 1
+            }
+        };
+
+        let chunk = &mut self.active_chunks[index];
+        let offset = chunk.offset;
+        if size > 0 {
+            let mut data = chunk
+                .buffer
+                .slice(offset..offset + size)
+                .get_mapped_range_mut();
+            write(&mut data);
+        } else {
+            write(&mut []);
+        }
+        chunk.offset = align(offset + size, MAP_ALIGNMENT);
+        StagingSlice {
+            buffer: chunk.id,
+            offset,
+            size,
+        }
+    }
+
+    fn create_chunk(context: &WgpuRenderResourceContext, size: u64) -> Chunk {
+        let size = align(size.max(MIN_CHUNK_SIZE), wgpu::COPY_BUFFER_ALIGNMENT);
+        let buffer_info = BufferInfo {
+            size: size as usize,
+            buffer_usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            mapped_at_creation: true,
+        };
+        let id = BufferId::new();
+        let buffer = Arc::new(
+            context.error_scope("write_staging_belt", "staging_belt", || {
+                context.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("staging_belt"),
+                    size,
+                    usage: wgpu::BufferUsage::MAP_WRITE | wgpu::BufferUsage::COPY_SRC,
+                    mapped_at_creation: true,
+                })
+            }),
+        );
+        context
+            .resources
+            .buffer_infos
+            .write()
+            .insert(id, buffer_info);
+        context.resources.buffers.write().insert(id, buffer.clone());
+        Chunk {
+            id,
+            buffer,
+            size,
+            offset: 0,
+        }
+    }
+
+    /// Unmaps the chunks written to this frame, so they can be copied from by the submitted
+    /// commands.
+    pub fn finish(&mut self) {
+        for chunk in self.active_chunks.drain(..) {
+            chunk.buffer.unmap();
+            self.closed_chunks.push(chunk);
+        }
+    }
+
+    /// Maps the chunks closed by [`StagingBelt::finish`] again, and frees the chunks whose
+    /// mapping has completed.
+    pub fn recall(&mut self, context: &WgpuRenderResourceContext) {
+        for chunk in self.closed_chunks.drain(..) {
+            let future = chunk.buffer.slice(..).map_async(wgpu::MapMode::Write);
+            self.recalled_chunks.push((chunk, Box::pin(future)));
+        }
+        context.device.poll(wgpu::Maintain::Poll);
+
+        let mut index = 0;
+        while index < self.recalled_chunks.len() {
+            let result = future::block_on(future::poll_once(&mut self.recalled_chunks[index].1));
+            match result {
+                None => index += 1,
+                Some(result) => {
+                    let (mut chunk, _) = self.recalled_chunks.swap_remove(index);
+                    if result.is_ok() {
+                        chunk.offset = 0;
+                        self.free_chunks.push(chunk);
+                    } else {
+                        context.remove_buffer(chunk.id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StagingBelt, MIN_CHUNK_SIZE};
+    use crate::{WgpuExternalDevice, WgpuOptions, WgpuRenderResourceContext};
+    use futures_lite::future;
+
+    /// Returns `None` on machines without a GPU, where the tests are skipped.
+    fn context() -> Option<WgpuRenderResourceContext> {
+        let device = future::block_on(WgpuExternalDevice::try_request(&WgpuOptions::default()))?;
+        Some(WgpuRenderResourceContext::new(device.device, device.queue))
+    }
+
+    /// Submits the chunks written to and waits until they can be reused.
+    fn submit(belt: &mut StagingBelt, context: &WgpuRenderResourceContext) {
+        belt.finish();
+        belt.recall(context);
+        context.device.poll(wgpu::Maintain::Wait);
+        belt.recall(context);
+    }
+
+    #[test]
+    fn recycled_chunks_are_reused() {
+        let context = match context() {
+            Some(context) => context,
+            None => return,
+        };
+        let mut belt = StagingBelt::default();
+        let first = belt.write(&context, 16, &mut |data| data.fill(1));
+        let second = belt.write(&context, 4, &mut |data| data.fill(2));
+        assert_eq!(second.buffer, first.buffer);
+        // writes start at the map alignment
+        assert_eq!(second.offset, 16);
+
+        submit(&mut belt, &context);
+        assert_eq!(belt.free_chunks.len(), 1);
+        let recycled = belt.write(&context, 16, &mut |data| data.fill(3));
+        assert_eq!(recycled.buffer, first.buffer);
+        assert_eq!(recycled.offset, 0);
+        assert!(belt.free_chunks.is_empty());
+    }
+
+    #[test]
+    fn oversized_writes_get_their_own_chunk() {
+        let context = match context() {
+            Some(context) => context,
+            None => return,
+        };
+        let mut belt = StagingBelt::default();
+        let small = belt.write(&context, 16, &mut |_| {});
+        let size = MIN_CHUNK_SIZE * 2 + 4;
+        let large = belt.write(&context, size, &mut |data| {
+            assert_eq!(data.len(), size as usize)
+        });
+        assert_ne!(large.buffer, small.buffer);
+        assert_eq!(large.offset, 0);
+        let large_info = context.resources.buffer_infos.read()[&large.buffer].clone();
+        assert!(large_info.size as u64 >= size);
+        // the smaller chunk still takes small writes
+        assert_eq!(belt.write(&context, 16, &mut |_| {}).buffer, small.buffer);
+
+        // a recycled chunk only takes writes that fit into it
+        submit(&mut belt, &context);
+        assert_eq!(belt.free_chunks.len(), 2);
+        let reused = belt.write(&context, size, &mut |_| {});
+        assert_eq!(reused.buffer, large.buffer);
+        let new = belt.write(&context, size, &mut |_| {});
+        assert_ne!(new.buffer, large.buffer);
+        assert_ne!(new.buffer, small.buffer);
+    }
+}
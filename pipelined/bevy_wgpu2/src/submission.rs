@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// Queue submissions during a single frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubmissionStats {
+    /// Calls to `wgpu::Queue::submit`.
+    pub submissions: usize,
+    /// Command buffers submitted, over all submissions.
+    pub command_buffers: usize,
+}
+
+/// Command buffers waiting to be submitted together, and the submissions of the current and the
+/// last frame.
+#[derive(Default)]
+pub(crate) struct Submissions {
+    pending: Vec<wgpu::CommandBuffer>,
+    frame_stats: SubmissionStats,
+    last_frame_stats: SubmissionStats,
+}
+
+impl fmt::Debug for Submissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Submissions")
+            .field("pending", &self.pending.len())
+            .field("frame_stats", &self.frame_stats)
+            .field("last_frame_stats", &self.last_frame_stats)
+            .finish()
+    }
+}
+
+impl Submissions {
+    pub fn push(&mut self, command_buffer: wgpu::CommandBuffer) {
+        self.pending.push(command_buffer);
+    }
+
+    /// Takes the pending command buffers for a single submission, and counts it.
+    pub fn take(&mut self) -> Vec<wgpu::CommandBuffer> {
+        if !self.pending.is_empty() {
+            self.frame_stats.submissions += 1;
+            self.frame_stats.command_buffers += self.pending.len();
+        }
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.last_frame_stats = std::mem::take(&mut self.frame_stats);
+    }
+
+    pub fn stats(&self) -> SubmissionStats {
+        self.last_frame_stats
+    }
+}
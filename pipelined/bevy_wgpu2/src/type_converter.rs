@@ -69,6 +69,10 @@ impl WgpuFrom<VertexFormat> for wgpu::VertexFormat {
             VertexFormat::Sint32x2 => wgpu::VertexFormat::Sint32x2,
             VertexFormat::Sint32x3 => wgpu::VertexFormat::Sint32x3,
             VertexFormat::Sint32x4 => wgpu::VertexFormat::Sint32x4,
+            VertexFormat::Float64 => wgpu::VertexFormat::Float64,
+            VertexFormat::Float64x2 => wgpu::VertexFormat::Float64x2,
+            VertexFormat::Float64x3 => wgpu::VertexFormat::Float64x3,
+            VertexFormat::Float64x4 => wgpu::VertexFormat::Float64x4,
         }
     }
 }
@@ -139,7 +143,12 @@ impl WgpuFrom<Color> for wgpu::Color {
 
 impl WgpuFrom<BufferUsage> for wgpu::BufferUsage {
     fn from(val: BufferUsage) -> Self {
-        wgpu::BufferUsage::from_bits(val.bits()).unwrap()
+        let mut usage = wgpu::BufferUsage::from_bits_truncate(val.bits());
+        // wgpu has no dedicated usage for query resolve destinations, they must be COPY_DST
+        if val.contains(BufferUsage::QUERY_RESOLVE) {
+            usage |= wgpu::BufferUsage::COPY_DST;
+        }
+        usage
     }
 }
 
@@ -674,8 +683,8 @@ impl WgpuFrom<&Window> for wgpu::SwapChainDescriptor {
         wgpu::SwapChainDescriptor {
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
             format: TextureFormat::default().wgpu_into(),
-            width: window.physical_width(),
-            height: window.physical_height(),
+            width: window.render_width(),
+            height: window.render_height(),
             present_mode: if window.vsync() {
                 wgpu::PresentMode::Fifo
             } else {
@@ -688,8 +697,8 @@ impl WgpuFrom<&Window> for wgpu::SwapChainDescriptor {
 impl WgpuFrom<&SwapChainDescriptor> for wgpu::SwapChainDescriptor {
     fn from(descriptor: &SwapChainDescriptor) -> Self {
         wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
-            format: TextureFormat::default().wgpu_into(),
+            usage: descriptor.usage.wgpu_into(),
+            format: descriptor.format.wgpu_into(),
             width: descriptor.width,
             height: descriptor.height,
             present_mode: if descriptor.vsync {
@@ -731,6 +740,7 @@ impl WgpuFrom<WgpuFeature> for wgpu::Features {
             }
             WgpuFeature::ShaderFloat64 => wgpu::Features::SHADER_FLOAT64,
             WgpuFeature::VertexAttribute64Bit => wgpu::Features::VERTEX_ATTRIBUTE_64BIT,
+            WgpuFeature::ConservativeRasterization => wgpu::Features::CONSERVATIVE_RASTERIZATION,
         }
     }
 }
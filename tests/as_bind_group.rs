@@ -0,0 +1,147 @@
+use bevy::{
+    app::App,
+    asset::{AddAsset, AssetPlugin, Assets, Handle},
+    core::CorePlugin,
+    math::Vec4,
+    render2::{
+        pipeline::{BindGroupDescriptor, BindType},
+        render_resource::{
+            AsBindGroup, AsBindGroupError, RenderResourceBinding, SamplerId, TextureId,
+            TextureViewId,
+        },
+        renderer::{HeadlessRenderResourceContext, RenderResources},
+        texture::{
+            Extent3d, Texture, TextureDimension, TextureFormat, TextureGpuData,
+            TextureViewDimension, WHITE_TEXTURE_HANDLE,
+        },
+    },
+};
+
+#[derive(AsBindGroup)]
+struct TestMaterial {
+    #[uniform(0)]
+    color: Vec4,
+    #[uniform(0)]
+    roughness: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    base_color_texture: Handle<Texture>,
+    // unset, so the white placeholder is bound
+    #[texture(3, placeholder = "white")]
+    #[sampler(4)]
+    occlusion_texture: Option<Handle<Texture>>,
+}
+
+/// Returns an app with texture assets, whose white placeholder texture is uploaded.
+fn app_with_textures() -> App {
+    let mut app = App::new();
+    app.add_plugin(CorePlugin)
+        .add_plugin(AssetPlugin)
+        .add_asset::<Texture>();
+    let white = uploaded_texture();
+    app.world
+        .get_resource_mut::<Assets<Texture>>()
+        .unwrap()
+        .set_untracked(&WHITE_TEXTURE_HANDLE, white);
+    app
+}
+
+/// A texture whose `gpu_data` is set, as if it had been uploaded.
+fn uploaded_texture() -> Texture {
+    let mut texture = Texture::new_fill(
+        Extent3d::new(1, 1, 1),
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    texture.gpu_data = Some(TextureGpuData {
+        texture: TextureId::new(),
+        texture_view: TextureViewId::new(),
+        sampler: SamplerId::new(),
+    });
+    texture
+}
+
+#[test]
+fn derived_bind_group_descriptor() {
+    let descriptor: BindGroupDescriptor = TestMaterial::bind_group_descriptor(2);
+    assert_eq!(descriptor.index, 2);
+    let indices = descriptor
+        .bindings
+        .iter()
+        .map(|binding| binding.index)
+        .collect::<Vec<_>>();
+    assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+
+    // the vec4 and the f32 are packed in one std140 block
+    assert_eq!(
+        descriptor.bindings[0].bind_type.get_uniform_size(),
+        Some(32)
+    );
+    assert!(matches!(
+        descriptor.bindings[1].bind_type,
+        BindType::Texture {
+            view_dimension: TextureViewDimension::D2,
+            ..
+        }
+    ));
+    assert!(matches!(
+        descriptor.bindings[2].bind_type,
+        BindType::Sampler { .. }
+    ));
+    assert!(matches!(
+        descriptor.bindings[3].bind_type,
+        BindType::Texture { .. }
+    ));
+}
+
+#[test]
+fn derived_bind_group_waits_for_its_textures() {
+    let mut app = app_with_textures();
+    let context = HeadlessRenderResourceContext::default();
+    let render_resources = RenderResources::new(Box::new(context.clone()));
+    let descriptor = TestMaterial::bind_group_descriptor(2);
+
+    let base_color_texture = app
+        .world
+        .get_resource_mut::<Assets<Texture>>()
+        .unwrap()
+        .add(Texture::default());
+    let material = TestMaterial {
+        color: Vec4::ONE,
+        roughness: 0.5,
+        base_color_texture: base_color_texture.clone(),
+        occlusion_texture: None,
+    };
+    let textures = app.world.get_resource::<Assets<Texture>>().unwrap();
+    assert_eq!(
+        material
+            .as_bind_group(&descriptor, &render_resources, textures)
+            .unwrap_err(),
+        AsBindGroupError::RetryNextFrame
+    );
+    // no buffer is created for a bind group that isn't ready
+    assert_eq!(context.buffer_count(), 0);
+
+    app.world
+        .get_resource_mut::<Assets<Texture>>()
+        .unwrap()
+        .set_untracked(&base_color_texture, uploaded_texture());
+    let textures = app.world.get_resource::<Assets<Texture>>().unwrap();
+    let prepared = material
+        .as_bind_group(&descriptor, &render_resources, textures)
+        .unwrap();
+    assert_eq!(prepared.buffers.len(), 1);
+    let (descriptor_id, bind_group) = context.get_bind_group(prepared.bind_group).unwrap();
+    assert_eq!(descriptor_id, descriptor.id);
+    let bindings = bind_group
+        .indexed_bindings
+        .iter()
+        .map(|binding| binding.index)
+        .collect::<Vec<_>>();
+    assert_eq!(bindings, vec![0, 1, 2, 3, 4]);
+    assert!(matches!(
+        bind_group.indexed_bindings[0].entry,
+        RenderResourceBinding::Buffer { buffer, .. } if buffer == prepared.buffers[0]
+    ));
+}
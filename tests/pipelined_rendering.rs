@@ -0,0 +1,233 @@
+use bevy::{
+    app::App,
+    log::LogPlugin,
+    math::{Vec2, Vec3},
+    pbr2::{PbrBundle, PointLightBundle, StandardMaterial},
+    prelude::{Assets, Transform},
+    render2::{
+        camera::{OrthographicCameraBundle, PerspectiveCameraBundle},
+        capture::{compare_to_reference, render_to_image, CapturePlugin},
+        color::Color,
+        mesh::{shape, Mesh},
+        render_phase::DrawOrder,
+        render_resource::{BufferInfo, BufferMapMode, BufferUsage},
+        renderer::{RenderResourceContext, RenderResources},
+        texture::{
+            Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+            WHITE_TEXTURE_HANDLE,
+        },
+        view::{OffscreenTarget, OffscreenTargets},
+    },
+    sprite2::{PipelinedSpriteBundle, Sprite},
+    wgpu2::{WgpuExternalDevice, WgpuOptions, WgpuRenderResourceContext},
+    window::WindowId,
+    winit::WinitPlugin,
+    PipelinedDefaultPlugins,
+};
+use std::{cell::RefCell, path::Path};
+
+const SIZE: u32 = 64;
+const FRAMES: usize = 4;
+/// The tolerance of the comparisons, in sRGB, which allows for small differences between GPUs.
+const TOLERANCE: f32 = 0.02;
+
+/// Builds an app that renders without a display, or returns `None` if the machine has no GPU to
+/// render with. Its offscreen targets are added by the tests.
+fn headless_app_without_target() -> Option<App> {
+    let device = match futures_lite::future::block_on(WgpuExternalDevice::try_request(
+        &WgpuOptions::default(),
+    )) {
+        Some(device) => device,
+        None => {
+            eprintln!("skipping rendering test: no GPU adapter available");
+            return None;
+        }
+    };
+    let mut app = App::new();
+    app.insert_resource(device)
+        .insert_resource(DrawOrder::Deterministic)
+        .add_plugins_with(PipelinedDefaultPlugins, |group| {
+            group.disable::<WinitPlugin>().disable::<LogPlugin>()
+        })
+        .add_plugin(CapturePlugin);
+    Some(app)
+}
+
+/// Builds an app that renders without a display to an offscreen target, or returns `None` if the
+/// machine has no GPU to render with.
+fn headless_app() -> Option<(App, WindowId)> {
+    let mut app = headless_app_without_target()?;
+    let target = app
+        .world
+        .get_resource_mut::<OffscreenTargets>()
+        .unwrap()
+        .add(OffscreenTarget::new(SIZE, SIZE));
+    Some((app, target))
+}
+
+/// Compares `image` to the reference image `name` in `tests/reference_images`. Run the tests with
+/// `BEVY_UPDATE_REFERENCE_IMAGES` set to save the rendered images as the references instead.
+fn assert_matches_reference(image: &Texture, name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/reference_images")
+        .join(name);
+    if let Err(error) = compare_to_reference(image, &path, TOLERANCE) {
+        panic!("{} doesn't match: {}", path.display(), error);
+    }
+}
+
+#[test]
+fn renders_pbr_scene() {
+    let (mut app, target) = match headless_app() {
+        Some(app) => app,
+        None => return,
+    };
+    let world = &mut app.world;
+    let mesh = world
+        .get_resource_mut::<Assets<Mesh>>()
+        .unwrap()
+        .add(Mesh::from(shape::Cube { size: 1.0 }));
+    let material = world
+        .get_resource_mut::<Assets<StandardMaterial>>()
+        .unwrap()
+        .add(StandardMaterial::default());
+    world.spawn().insert_bundle(PbrBundle {
+        mesh,
+        material,
+        ..Default::default()
+    });
+    world.spawn().insert_bundle(PointLightBundle {
+        transform: Transform::from_xyz(1.0, 2.0, 4.0),
+        ..Default::default()
+    });
+    let mut camera = PerspectiveCameraBundle {
+        transform: Transform::from_xyz(0.0, 0.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    };
+    camera.camera.window = target;
+    world.spawn().insert_bundle(camera);
+
+    let image = render_to_image(&mut app, target, FRAMES).expect("nothing was captured");
+    assert_matches_reference(&image, "pbr_scene.png");
+}
+
+/// Spawns a blue sprite covering the center of `target`, and a 2D camera rendering to it.
+fn spawn_sprite_scene(app: &mut App, target: WindowId) {
+    let world = &mut app.world;
+    world.spawn().insert_bundle(PipelinedSpriteBundle {
+        sprite: Sprite {
+            color: Color::BLUE,
+            ..Sprite::new(Vec2::splat(SIZE as f32 / 2.0))
+        },
+        texture: WHITE_TEXTURE_HANDLE.typed(),
+        ..Default::default()
+    });
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.camera.window = target;
+    world.spawn().insert_bundle(camera);
+}
+
+#[test]
+fn renders_sprite_scene() {
+    let (mut app, target) = match headless_app() {
+        Some(app) => app,
+        None => return,
+    };
+    spawn_sprite_scene(&mut app, target);
+
+    let image = render_to_image(&mut app, target, FRAMES).expect("nothing was captured");
+    assert_matches_reference(&image, "sprite_scene.png");
+}
+
+/// Renders to a texture created outside of bevy, like the swap chain images of an XR runtime.
+#[test]
+fn renders_sprite_scene_to_registered_view() {
+    let mut app = match headless_app_without_target() {
+        Some(app) => app,
+        None => return,
+    };
+    let size = Extent3d::new(SIZE, SIZE, 1);
+    let texture_descriptor = TextureDescriptor {
+        size,
+        format: TextureFormat::Bgra8UnormSrgb,
+        usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::COPY_SRC,
+        ..Default::default()
+    };
+    let render_resources = app.world.get_resource::<RenderResources>().unwrap();
+    let context = render_resources
+        .downcast_ref::<WgpuRenderResourceContext>()
+        .unwrap()
+        .clone();
+    let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("external"),
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+    });
+    let view = context.register_texture_view(texture.create_view(&Default::default()));
+    let texture = context.register_texture(texture, texture_descriptor);
+    let target = app
+        .world
+        .get_resource_mut::<OffscreenTargets>()
+        .unwrap()
+        .add(OffscreenTarget::from_view(
+            view,
+            SIZE,
+            SIZE,
+            texture_descriptor.format,
+        ));
+    spawn_sprite_scene(&mut app, target);
+    for _ in 0..FRAMES {
+        app.update();
+    }
+
+    // captures only cover the textures of offscreen targets, so read the texture back directly
+    let row_size = SIZE as usize * texture_descriptor.format.pixel_size();
+    let bytes_per_row = context.get_aligned_texture_size(row_size);
+    let buffer = context.create_buffer(BufferInfo {
+        size: bytes_per_row * SIZE as usize,
+        buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+        ..Default::default()
+    });
+    let mut command_encoder = context
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    context.copy_texture_to_buffer(
+        &mut command_encoder,
+        texture,
+        [0, 0, 0],
+        0,
+        buffer,
+        0,
+        bytes_per_row as u32,
+        size,
+    );
+    context.queue.submit(Some(command_encoder.finish()));
+    let data = RefCell::new(Vec::new());
+    context.map_buffer(buffer, BufferMapMode::Read);
+    context.read_mapped_buffer(
+        buffer,
+        0..(bytes_per_row * SIZE as usize) as u64,
+        &|bytes, _| {
+            for row in bytes.chunks(bytes_per_row) {
+                data.borrow_mut().extend_from_slice(&row[..row_size]);
+            }
+        },
+    );
+    context.unmap_buffer(buffer);
+
+    let image = Texture::new(
+        size,
+        TextureDimension::D2,
+        data.into_inner(),
+        texture_descriptor.format,
+    );
+    assert_matches_reference(&image, "sprite_scene.png");
+}